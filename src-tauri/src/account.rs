@@ -0,0 +1,171 @@
+use crate::gmail_client::{GmailProfile, SendAsAlias};
+use serde::Serialize;
+
+/// A local store holding one Gmail account's data.
+///
+/// The app signs in one account at a time, but its stores outlive a sign-in.
+/// Each store records the account that owns it so removing an account purges
+/// exactly that account's data. Stores saved before ownership was tracked have
+/// no owner; they belong to whichever account claims or removes them first.
+pub trait AccountScoped {
+    /// The owning account's Gmail address
+    fn account_id(&self) -> Option<&str>;
+
+    fn set_account_id(&mut self, account_id: &str);
+
+    /// Drop everything in the store, owner included
+    fn clear(&mut self);
+
+    fn persist(&self) -> Result<(), String>;
+
+    fn belongs_to(&self, account_id: &str) -> bool {
+        self.account_id()
+            .is_none_or(|owner| owner.eq_ignore_ascii_case(account_id))
+    }
+
+    /// Clear and save the store when it belongs to `account_id`, returning whether it did
+    fn purge(&mut self, account_id: &str) -> Result<bool, String> {
+        if !self.belongs_to(account_id) {
+            return Ok(false);
+        }
+        self.clear();
+        self.persist()?;
+        Ok(true)
+    }
+
+    /// Take ownership of a store that has no owner yet
+    fn claim(&mut self, account_id: &str) -> Result<(), String> {
+        if self.account_id().is_some() {
+            return Ok(());
+        }
+        self.set_account_id(account_id);
+        self.persist()
+    }
+}
+
+/// What `remove_account` deleted
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountPurge {
+    pub account_id: String,
+    /// Stores that held the account's data and were cleared
+    pub purged: Vec<String>,
+    /// The account was signed in, so its tokens were deleted too
+    pub signed_out: bool,
+}
+
+/// The signed-in account as shown in the UI
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccountProfile {
+    pub email_address: String,
+    /// Name the account sends as, when one is set in Gmail
+    pub display_name: Option<String>,
+    pub messages_total: u32,
+    pub threads_total: u32,
+}
+
+impl AccountProfile {
+    /// The profile's address, named after its send-as entry (or the primary
+    /// one when the address isn't listed)
+    pub fn new(profile: &GmailProfile, aliases: &[SendAsAlias]) -> Self {
+        let alias = aliases
+            .iter()
+            .find(|a| a.send_as_email.eq_ignore_ascii_case(&profile.email_address))
+            .or_else(|| aliases.iter().find(|a| a.is_primary.unwrap_or(false)));
+        AccountProfile {
+            email_address: profile.email_address.clone(),
+            display_name: alias
+                .and_then(|a| a.display_name.as_deref())
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string),
+            messages_total: profile.messages_total.unwrap_or(0),
+            threads_total: profile.threads_total.unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Store {
+        account_id: Option<String>,
+        items: Vec<u32>,
+    }
+
+    impl AccountScoped for Store {
+        fn account_id(&self) -> Option<&str> {
+            self.account_id.as_deref()
+        }
+
+        fn set_account_id(&mut self, account_id: &str) {
+            self.account_id = Some(account_id.to_string());
+        }
+
+        fn clear(&mut self) {
+            *self = Store::default();
+        }
+
+        fn persist(&self) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_purge_only_touches_the_owner() {
+        let mut store = Store {
+            items: vec![1, 2],
+            ..Default::default()
+        };
+        store.claim("me@example.com").unwrap();
+        store.claim("other@example.com").unwrap();
+        assert_eq!(store.account_id(), Some("me@example.com"));
+
+        assert!(!store.purge("other@example.com").unwrap());
+        assert_eq!(store.items.len(), 2);
+
+        assert!(store.purge("ME@example.com").unwrap());
+        assert!(store.items.is_empty());
+        assert_eq!(store.account_id(), None);
+    }
+
+    #[test]
+    fn test_unowned_store_belongs_to_any_account() {
+        let store = Store::default();
+        assert!(store.belongs_to("anyone@example.com"));
+    }
+
+    #[test]
+    fn test_account_profile_names_the_matching_alias() {
+        let profile = GmailProfile {
+            email_address: "me@example.com".to_string(),
+            messages_total: Some(120),
+            threads_total: None,
+            history_id: None,
+        };
+        let alias = |email: &str, name: &str, primary: bool| SendAsAlias {
+            send_as_email: email.to_string(),
+            display_name: Some(name.to_string()),
+            is_primary: Some(primary),
+            is_default: None,
+            verification_status: None,
+            signature: None,
+        };
+
+        let account = AccountProfile::new(
+            &profile,
+            &[
+                alias("work@example.com", "Work Me", true),
+                alias("Me@example.com", " Jo Bloggs ", false),
+            ],
+        );
+        assert_eq!(account.display_name.as_deref(), Some("Jo Bloggs"));
+        assert_eq!(account.messages_total, 120);
+        assert_eq!(account.threads_total, 0);
+
+        let fallback = AccountProfile::new(&profile, &[alias("work@example.com", "Work Me", true)]);
+        assert_eq!(fallback.display_name.as_deref(), Some("Work Me"));
+        assert_eq!(AccountProfile::new(&profile, &[]).display_name, None);
+    }
+}
@@ -0,0 +1,390 @@
+use crate::bulk::BulkAction;
+use crate::gmail_client::GmailLabel;
+use crate::rules::Rule;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Where the command palette was opened
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "view", rename_all = "snake_case")]
+pub enum ActionContext {
+    Global,
+    /// A list with a selection; actions take the ids as `message_ids`
+    MessageList,
+    /// One open message
+    Message {
+        email_id: String,
+    },
+    Compose {
+        session_id: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionGroup {
+    Message,
+    Label,
+    Rule,
+    Compose,
+    App,
+}
+
+/// One palette entry. Invoking it means calling `command` with `args`; when
+/// `target_arg` is set the frontend adds the current selection under that name.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PaletteAction {
+    pub id: String,
+    pub title: String,
+    pub group: ActionGroup,
+    pub command: String,
+    pub args: Value,
+    pub target_arg: Option<String>,
+    pub shortcut: Option<String>,
+}
+
+impl PaletteAction {
+    fn new(id: &str, title: &str, group: ActionGroup, command: &str, args: Value) -> Self {
+        PaletteAction {
+            id: id.to_string(),
+            title: title.to_string(),
+            group,
+            command: command.to_string(),
+            args,
+            target_arg: None,
+            shortcut: None,
+        }
+    }
+
+    fn target(mut self, arg: &str) -> Self {
+        self.target_arg = Some(arg.to_string());
+        self
+    }
+
+    fn shortcut(mut self, keys: &str) -> Self {
+        self.shortcut = Some(keys.to_string());
+        self
+    }
+}
+
+/// Account state that decides which actions are offered
+#[derive(Debug, Default)]
+pub struct ActionInputs<'a> {
+    pub signed_in: bool,
+    /// The user's labels; system labels are skipped
+    pub labels: &'a [GmailLabel],
+    pub rules: &'a [Rule],
+    /// Labels on the open message when it is cached; None offers both directions
+    pub message_label_ids: Option<&'a [String]>,
+    pub digest_enabled: bool,
+    pub attachment_indexing: bool,
+}
+
+fn bulk(id: &str, title: &str, action: BulkAction) -> PaletteAction {
+    PaletteAction::new(
+        id,
+        title,
+        ActionGroup::Message,
+        "bulk_modify_emails",
+        json!({ "action": action }),
+    )
+    .target("message_ids")
+}
+
+/// Whether the open message carries `label`, when that is known
+fn has_label(inputs: &ActionInputs, label: &str) -> Option<bool> {
+    inputs
+        .message_label_ids
+        .map(|ids| ids.iter().any(|id| id == label))
+}
+
+fn label_actions(inputs: &ActionInputs, actions: &mut Vec<PaletteAction>) {
+    for label in inputs
+        .labels
+        .iter()
+        .filter(|l| l.label_type.as_deref() == Some("user"))
+    {
+        let on_message = has_label(inputs, &label.id);
+        if on_message != Some(true) {
+            actions.push(PaletteAction {
+                group: ActionGroup::Label,
+                ..bulk(
+                    &format!("label:add:{}", label.id),
+                    &format!("Label as {}", label.name),
+                    BulkAction::AddLabel {
+                        label_id: label.id.clone(),
+                    },
+                )
+            });
+        }
+        if on_message != Some(false) {
+            actions.push(PaletteAction {
+                group: ActionGroup::Label,
+                ..bulk(
+                    &format!("label:remove:{}", label.id),
+                    &format!("Remove label {}", label.name),
+                    BulkAction::RemoveLabel {
+                        label_id: label.id.clone(),
+                    },
+                )
+            });
+        }
+    }
+}
+
+fn message_actions(
+    email_id: Option<&str>,
+    inputs: &ActionInputs,
+    actions: &mut Vec<PaletteAction>,
+) {
+    let offer = |label: &str, present: bool| has_label(inputs, label).is_none_or(|h| h == present);
+
+    if offer("INBOX", true) {
+        actions.push(bulk("archive", "Archive", BulkAction::Archive).shortcut("e"));
+    }
+    if offer("INBOX", false) {
+        actions.push(bulk(
+            "move_to_inbox",
+            "Move to Inbox",
+            BulkAction::MoveToInbox,
+        ));
+    }
+    if offer("UNREAD", true) {
+        actions.push(bulk("mark_read", "Mark as read", BulkAction::MarkRead).shortcut("shift+i"));
+    }
+    if offer("UNREAD", false) {
+        actions.push(
+            bulk("mark_unread", "Mark as unread", BulkAction::MarkUnread).shortcut("shift+u"),
+        );
+    }
+    if offer("STARRED", false) {
+        actions.push(bulk("star", "Star", BulkAction::Star).shortcut("s"));
+    }
+    if offer("STARRED", true) {
+        actions.push(bulk("unstar", "Remove star", BulkAction::Unstar));
+    }
+
+    // Single-message commands
+    if let Some(email_id) = email_id {
+        let single = |id: &str, title: &str, command: &str| {
+            PaletteAction::new(
+                id,
+                title,
+                ActionGroup::Message,
+                command,
+                json!({ "email_id": email_id }),
+            )
+        };
+        actions.push(
+            PaletteAction::new(
+                "reply",
+                "Reply",
+                ActionGroup::Message,
+                "create_compose_session",
+                json!({ "reply_to_email_id": email_id }),
+            )
+            .shortcut("r"),
+        );
+        actions.push(single("trash", "Move to Trash", "trash_email").shortcut("#"));
+        if offer("SPAM", false) {
+            actions.push(single("report_spam", "Report spam", "report_spam").shortcut("!"));
+            actions.push(single(
+                "report_phishing",
+                "Report phishing",
+                "report_phishing",
+            ));
+        }
+        if offer("SPAM", true) {
+            actions.push(single("not_spam", "Not spam", "mark_not_spam"));
+        }
+        if offer("IMPORTANT", false) {
+            actions.push(single(
+                "mark_important",
+                "Mark as important",
+                "mark_as_important",
+            ));
+        }
+        if offer("IMPORTANT", true) {
+            actions.push(single(
+                "mark_not_important",
+                "Mark as not important",
+                "mark_not_important",
+            ));
+        }
+    }
+
+    label_actions(inputs, actions);
+}
+
+/// Actions the palette can offer in `context` given the account's state
+pub fn available_actions(context: &ActionContext, inputs: &ActionInputs) -> Vec<PaletteAction> {
+    let mut actions = Vec::new();
+
+    match context {
+        ActionContext::Message { email_id } if inputs.signed_in => {
+            message_actions(Some(email_id), inputs, &mut actions);
+        }
+        ActionContext::MessageList if inputs.signed_in => {
+            message_actions(None, inputs, &mut actions);
+        }
+        ActionContext::Compose { session_id } => {
+            let session = |id: &str, title: &str, command: &str| {
+                PaletteAction::new(
+                    id,
+                    title,
+                    ActionGroup::Compose,
+                    command,
+                    json!({ "session_id": session_id }),
+                )
+            };
+            if inputs.signed_in {
+                actions.push(session("send", "Send", "send_compose_session").shortcut("mod+enter"));
+            }
+            actions.push(
+                session("attach_file", "Attach file", "add_compose_attachment").target("path"),
+            );
+            actions.push(session(
+                "preflight",
+                "Check before sending",
+                "preflight_compose_session",
+            ));
+            actions.push(session(
+                "discard",
+                "Discard draft",
+                "discard_compose_session",
+            ));
+        }
+        _ => {}
+    }
+
+    // Available everywhere
+    actions.push(
+        PaletteAction::new(
+            "compose",
+            "Compose new message",
+            ActionGroup::App,
+            "create_compose_session",
+            json!({}),
+        )
+        .shortcut("c"),
+    );
+    if !inputs.signed_in {
+        actions.push(PaletteAction::new(
+            "sign_in",
+            "Sign in to Gmail",
+            ActionGroup::App,
+            "start_gmail_auth",
+            json!({}),
+        ));
+        return actions;
+    }
+
+    actions.push(PaletteAction::new(
+        "check_mail",
+        "Check for new mail",
+        ActionGroup::App,
+        "check_for_new_emails_since_last_check",
+        json!({}),
+    ));
+    actions.push(PaletteAction::new(
+        "empty_trash",
+        "Empty Trash",
+        ActionGroup::App,
+        "empty_trash",
+        json!({}),
+    ));
+    if inputs.digest_enabled {
+        actions.push(PaletteAction::new(
+            "send_digest",
+            "Send weekly digest now",
+            ActionGroup::App,
+            "send_weekly_digest_now",
+            json!({}),
+        ));
+    }
+    if inputs.attachment_indexing {
+        actions.push(PaletteAction::new(
+            "index_attachments",
+            "Index downloaded attachments",
+            ActionGroup::App,
+            "index_cached_attachments",
+            json!({}),
+        ));
+    }
+
+    for rule in inputs.rules.iter().filter(|r| r.enabled) {
+        actions.push(PaletteAction::new(
+            &format!("rule:{}", rule.id),
+            &format!("Run rule: {}", rule.name),
+            ActionGroup::Rule,
+            "run_rule_now",
+            json!({ "rule_id": rule.id, "scope": { "type": "inbox" } }),
+        ));
+    }
+
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(id: &str, name: &str, label_type: &str) -> GmailLabel {
+        GmailLabel {
+            id: id.to_string(),
+            name: name.to_string(),
+            label_type: Some(label_type.to_string()),
+            messages_total: None,
+            messages_unread: None,
+            threads_total: None,
+            threads_unread: None,
+        }
+    }
+
+    fn ids(actions: &[PaletteAction]) -> Vec<&str> {
+        actions.iter().map(|a| a.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_message_actions_follow_label_state() {
+        let labels = vec![
+            label("INBOX", "INBOX", "system"),
+            label("Label_1", "Receipts", "user"),
+        ];
+        let message_labels = vec!["INBOX".to_string(), "UNREAD".to_string()];
+        let inputs = ActionInputs {
+            signed_in: true,
+            labels: &labels,
+            message_label_ids: Some(&message_labels),
+            ..Default::default()
+        };
+
+        let context = ActionContext::Message {
+            email_id: "msg1".to_string(),
+        };
+        let actions = available_actions(&context, &inputs);
+        let ids = ids(&actions);
+
+        assert!(ids.contains(&"archive"));
+        assert!(!ids.contains(&"move_to_inbox"));
+        assert!(ids.contains(&"mark_read"));
+        assert!(!ids.contains(&"mark_unread"));
+        assert!(ids.contains(&"label:add:Label_1"));
+        assert!(!ids.contains(&"label:remove:Label_1"));
+        assert!(!ids.iter().any(|id| id.contains("INBOX")));
+
+        let archive = actions.iter().find(|a| a.id == "archive").unwrap();
+        assert_eq!(archive.args, json!({ "action": { "type": "archive" } }));
+        assert_eq!(archive.target_arg.as_deref(), Some("message_ids"));
+        let trash = actions.iter().find(|a| a.id == "trash").unwrap();
+        assert_eq!(trash.args, json!({ "email_id": "msg1" }));
+    }
+
+    #[test]
+    fn test_signed_out_only_offers_local_actions() {
+        let context: ActionContext =
+            serde_json::from_value(json!({ "view": "message_list" })).unwrap();
+        let actions = available_actions(&context, &ActionInputs::default());
+        assert_eq!(ids(&actions), vec!["compose", "sign_in"]);
+    }
+}
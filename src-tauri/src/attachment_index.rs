@@ -0,0 +1,191 @@
+use crate::account::AccountScoped;
+use crate::local_store::{app_data_path, load_json, save_json};
+use serde::{Deserialize, Serialize};
+
+const ATTACHMENT_INDEX_FILE: &str = "attachment_index.json";
+
+/// Characters of context shown on each side of a match
+const SNIPPET_RADIUS: usize = 60;
+
+/// Extracted text of one attachment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedAttachment {
+    pub message_id: String,
+    pub thread_id: String,
+    /// Position in `GmailMessage::get_attachments`, matching the message cache
+    pub index: usize,
+    pub filename: String,
+    pub text: String,
+}
+
+/// A search hit inside an attachment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentMatch {
+    pub message_id: String,
+    pub thread_id: String,
+    pub filename: String,
+    pub snippet: String,
+}
+
+/// Local full-text index over attachment contents, so searches can find
+/// words that only appear inside a PDF or scanned image
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AttachmentIndex {
+    entries: Vec<IndexedAttachment>,
+    #[serde(default)]
+    account_id: Option<String>,
+}
+
+impl AttachmentIndex {
+    pub fn load() -> Self {
+        load_json(&app_data_path(ATTACHMENT_INDEX_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(ATTACHMENT_INDEX_FILE), self)
+    }
+
+    pub fn contains(&self, message_id: &str, index: usize) -> bool {
+        self.entries
+            .iter()
+            .any(|e| e.message_id == message_id && e.index == index)
+    }
+
+    /// Add an attachment's text, replacing any earlier copy
+    pub fn insert(&mut self, entry: IndexedAttachment) {
+        self.entries
+            .retain(|e| !(e.message_id == entry.message_id && e.index == entry.index));
+        self.entries.push(entry);
+    }
+
+    /// Attachments whose text or filename contains every word of `query`
+    pub fn search(&self, query: &str) -> Vec<AttachmentMatch> {
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|t| t.trim_matches('"').to_lowercase())
+            .filter(|t| !t.is_empty() && !t.contains(':'))
+            .collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                let text = entry.text.to_lowercase();
+                let filename = entry.filename.to_lowercase();
+                if !terms
+                    .iter()
+                    .all(|t| text.contains(t.as_str()) || filename.contains(t.as_str()))
+                {
+                    return None;
+                }
+
+                Some(AttachmentMatch {
+                    message_id: entry.message_id.clone(),
+                    thread_id: entry.thread_id.clone(),
+                    filename: entry.filename.clone(),
+                    snippet: snippet(&entry.text, &text, &terms[0]),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Text around the first occurrence of `term`; `lower` is `text` lowercased
+fn snippet(text: &str, lower: &str, term: &str) -> String {
+    let position = match lower.find(term) {
+        // Lowercasing can shift byte offsets outside ASCII, so fall back to the start
+        Some(position) if lower.len() == text.len() => position,
+        _ => 0,
+    };
+
+    let start = text[..position]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_RADIUS)
+        .map_or(0, |(i, _)| i);
+    let end = text[position..]
+        .char_indices()
+        .nth(term.chars().count() + SNIPPET_RADIUS)
+        .map_or(text.len(), |(i, _)| position + i);
+
+    let mut snippet = text[start..end].to_string();
+    if start > 0 {
+        snippet.insert_str(0, "...");
+    }
+    if end < text.len() {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+impl AccountScoped for AttachmentIndex {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message_id: &str, filename: &str, text: &str) -> IndexedAttachment {
+        IndexedAttachment {
+            message_id: message_id.to_string(),
+            thread_id: format!("thread_{}", message_id),
+            index: 0,
+            filename: filename.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_search_requires_every_term() {
+        let mut index = AttachmentIndex::default();
+        index.insert(entry(
+            "m1",
+            "scan.pdf",
+            "Invoice number 4211, due in 30 days",
+        ));
+        index.insert(entry("m2", "invoice.pdf", "Order 9000"));
+
+        let matches = index.search("invoice 4211");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].message_id, "m1");
+        assert_eq!(matches[0].snippet, "Invoice number 4211, due in 30 days");
+
+        // Filenames count, and Gmail operators are left to Gmail
+        assert_eq!(index.search("invoice has:attachment").len(), 2);
+        assert!(index.search("from:alice").is_empty());
+    }
+
+    #[test]
+    fn test_insert_replaces_and_snippet_trims() {
+        let mut index = AttachmentIndex::default();
+        index.insert(entry("m1", "a.txt", "old"));
+        index.insert(entry(
+            "m1",
+            "a.txt",
+            &format!("{} needle {}", "x".repeat(100), "y".repeat(100)),
+        ));
+        assert_eq!(index.entries.len(), 1);
+        assert!(index.contains("m1", 0));
+
+        let snippet = &index.search("needle")[0].snippet;
+        assert!(snippet.starts_with("...") && snippet.ends_with("..."));
+        assert!(snippet.contains("needle"));
+    }
+}
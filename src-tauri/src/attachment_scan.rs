@@ -0,0 +1,283 @@
+use crate::gmail_client::OutgoingAttachment;
+use crate::settings::{AttachmentScanSettings, ScanCommand};
+use reqwest::Client;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Longest a scanner gets per attachment before the send is treated as failed
+const SCAN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Exit code a command scanner uses for a positive result, as ClamAV's
+/// clamscan and clamdscan do; 0 is clean and anything else is an error
+const POSITIVE_EXIT_CODE: i32 = 1;
+
+/// Distinguishes the scratch directories of scans running at the same time
+static SCAN_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// What a scanner said about one attachment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Clean,
+    Flagged(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct EndpointReply {
+    infected: bool,
+    #[serde(default)]
+    detail: Option<String>,
+}
+
+/// Scan every attachment with the configured scanners. Err names the first
+/// flagged attachment, or the scanner failure, and means the send must not
+/// go ahead.
+pub async fn check_attachments(
+    settings: &AttachmentScanSettings,
+    attachments: &[OutgoingAttachment],
+) -> Result<(), String> {
+    if !settings.enabled || attachments.is_empty() {
+        return Ok(());
+    }
+    if settings.command.is_none() && settings.endpoint.is_none() {
+        return Err("Attachment scanning is on but no scanner is configured".to_string());
+    }
+
+    for attachment in attachments {
+        let verdict = match scan_attachment(settings, attachment).await {
+            Ok(verdict) => verdict,
+            Err(e) if settings.allow_on_error => {
+                eprintln!("Attachment scan of {} failed: {}", attachment.filename, e);
+                continue;
+            }
+            Err(e) => {
+                return Err(format!(
+                    "Couldn't scan {}, so nothing was sent: {}",
+                    attachment.filename, e
+                ))
+            }
+        };
+        if let Verdict::Flagged(detail) = verdict {
+            return Err(format!(
+                "{} was flagged by the attachment scanner: {}",
+                attachment.filename, detail
+            ));
+        }
+    }
+    Ok(())
+}
+
+async fn scan_attachment(
+    settings: &AttachmentScanSettings,
+    attachment: &OutgoingAttachment,
+) -> Result<Verdict, String> {
+    if let Some(command) = settings.command.as_ref() {
+        if let Verdict::Flagged(detail) = scan_with_command(command, attachment).await? {
+            return Ok(Verdict::Flagged(detail));
+        }
+    }
+    if let Some(endpoint) = settings.endpoint.as_deref() {
+        return scan_with_endpoint(endpoint, attachment).await;
+    }
+    Ok(Verdict::Clean)
+}
+
+async fn scan_with_command(
+    command: &ScanCommand,
+    attachment: &OutgoingAttachment,
+) -> Result<Verdict, String> {
+    if command.program.trim().is_empty() {
+        return Err("No scanner program configured".to_string());
+    }
+
+    let dir = std::env::temp_dir().join(format!(
+        "aisle3-scan-{}-{}",
+        std::process::id(),
+        SCAN_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(scratch_name(&attachment.filename));
+    let result = run_command(command, &path, &attachment.data).await;
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let (code, output) = result?;
+    command_verdict(
+        code,
+        &output.replace(&*path.to_string_lossy(), &attachment.filename),
+    )
+}
+
+async fn run_command(
+    command: &ScanCommand,
+    path: &Path,
+    data: &[u8],
+) -> Result<(Option<i32>, String), String> {
+    std::fs::write(path, data).map_err(|e| e.to_string())?;
+    let output = tokio::time::timeout(
+        SCAN_TIMEOUT,
+        tokio::process::Command::new(command.program.trim())
+            .args(&command.args)
+            .arg(path)
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| format!("{} timed out", command.program))?
+    .map_err(|e| format!("Failed to run {}: {}", command.program, e))?;
+
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok((output.status.code(), text))
+}
+
+/// Read a command scanner's exit code; its first line of output explains a
+/// positive or failed scan
+fn command_verdict(code: Option<i32>, output: &str) -> Result<Verdict, String> {
+    let detail = output
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_string();
+    match code {
+        Some(0) => Ok(Verdict::Clean),
+        Some(POSITIVE_EXIT_CODE) if detail.is_empty() => {
+            Ok(Verdict::Flagged("reported a threat".to_string()))
+        }
+        Some(POSITIVE_EXIT_CODE) => Ok(Verdict::Flagged(detail)),
+        Some(code) => Err(format!("scanner exited with {}: {}", code, detail)),
+        None => Err("scanner was killed".to_string()),
+    }
+}
+
+async fn scan_with_endpoint(
+    endpoint: &str,
+    attachment: &OutgoingAttachment,
+) -> Result<Verdict, String> {
+    let response = Client::new()
+        .post(endpoint)
+        .header("Content-Type", &attachment.mime_type)
+        .header(
+            "X-Filename",
+            urlencoding::encode(&attachment.filename).as_ref(),
+        )
+        .body(attachment.data.clone())
+        .timeout(SCAN_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !response.status().is_success() {
+        return Err(format!("scanner endpoint returned {}", response.status()));
+    }
+    let reply: EndpointReply = response.json().await.map_err(|e| e.to_string())?;
+    Ok(if reply.infected {
+        Verdict::Flagged(
+            reply
+                .detail
+                .unwrap_or_else(|| "reported a threat".to_string()),
+        )
+    } else {
+        Verdict::Clean
+    })
+}
+
+/// The attachment's own name, minus anything that could leave the scratch
+/// directory, so the scanner's report names the file the user attached
+fn scratch_name(filename: &str) -> PathBuf {
+    let name: String = filename
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    match name.trim_matches('.') {
+        "" => PathBuf::from("attachment"),
+        _ => PathBuf::from(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(filename: &str, data: &[u8]) -> OutgoingAttachment {
+        OutgoingAttachment {
+            filename: filename.to_string(),
+            mime_type: "application/octet-stream".to_string(),
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_command_verdicts_follow_clamav_exit_codes() {
+        assert_eq!(command_verdict(Some(0), "ok.txt: OK\n"), Ok(Verdict::Clean));
+        assert_eq!(
+            command_verdict(Some(1), "\nreport.doc: Eicar-Signature FOUND\n"),
+            Ok(Verdict::Flagged(
+                "report.doc: Eicar-Signature FOUND".to_string()
+            ))
+        );
+        assert!(command_verdict(Some(2), "Can't connect to clamd").is_err());
+        assert!(command_verdict(None, "").is_err());
+        assert_eq!(
+            scratch_name("../../etc/passwd"),
+            PathBuf::from(".._.._etc_passwd")
+        );
+        assert_eq!(scratch_name(".."), PathBuf::from("attachment"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_flagged_attachment_blocks_the_send() {
+        // Flags any file containing "EICAR", naming it the way clamscan does
+        let settings = AttachmentScanSettings {
+            enabled: true,
+            command: Some(ScanCommand {
+                program: "sh".to_string(),
+                args: vec![
+                    "-c".to_string(),
+                    r#"grep -q EICAR "$1" && { echo "$1: Eicar FOUND"; exit 1; }; exit 0"#
+                        .to_string(),
+                    "scan".to_string(),
+                ],
+            }),
+            ..Default::default()
+        };
+
+        let clean = [attachment("notes.txt", b"hello")];
+        assert_eq!(check_attachments(&settings, &clean).await, Ok(()));
+
+        let infected = [
+            attachment("notes.txt", b"hello"),
+            attachment("eicar.com", b"X5O EICAR test"),
+        ];
+        let error = check_attachments(&settings, &infected).await.unwrap_err();
+        assert_eq!(
+            error,
+            "eicar.com was flagged by the attachment scanner: eicar.com: Eicar FOUND"
+        );
+
+        // A scanner that can't run blocks too, unless told otherwise
+        let broken = AttachmentScanSettings {
+            command: Some(ScanCommand {
+                program: "/nonexistent/scanner".to_string(),
+                args: Vec::new(),
+            }),
+            ..settings.clone()
+        };
+        assert!(check_attachments(&broken, &clean).await.is_err());
+        let lenient = AttachmentScanSettings {
+            allow_on_error: true,
+            ..broken
+        };
+        assert_eq!(check_attachments(&lenient, &clean).await, Ok(()));
+        assert_eq!(
+            check_attachments(&AttachmentScanSettings::default(), &infected).await,
+            Ok(())
+        );
+    }
+}
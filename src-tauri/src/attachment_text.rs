@@ -0,0 +1,87 @@
+//! Text extraction from attachments for the local attachment index.
+//!
+//! Plain text formats are always handled. PDF text extraction and image OCR
+//! need the `attachment-ocr` feature, which pulls in pdf-extract and the
+//! Tesseract bindings (Tesseract itself must be installed on the system).
+
+/// Longest text kept per attachment; enough for search, bounded for the index file
+pub const MAX_EXTRACTED_CHARS: usize = 200_000;
+
+/// Extract searchable text, or None for formats this build can't read
+pub fn extract_text(mime_type: &str, filename: &str, data: &[u8]) -> Option<String> {
+    let mime_type = mime_type.to_ascii_lowercase();
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let text =
+        if mime_type.starts_with("text/") || matches!(extension.as_str(), "txt" | "csv" | "md") {
+            Some(String::from_utf8_lossy(data).to_string())
+        } else if mime_type == "application/pdf" || extension == "pdf" {
+            extract_pdf(data)
+        } else if mime_type.starts_with("image/") {
+            extract_image(data)
+        } else {
+            None
+        }?;
+
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then(|| text.chars().take(MAX_EXTRACTED_CHARS).collect())
+}
+
+#[cfg(feature = "attachment-ocr")]
+fn extract_pdf(data: &[u8]) -> Option<String> {
+    match pdf_extract::extract_text_from_mem(data) {
+        Ok(text) => Some(text),
+        Err(e) => {
+            eprintln!("PDF text extraction failed: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "attachment-ocr"))]
+fn extract_pdf(_data: &[u8]) -> Option<String> {
+    None
+}
+
+#[cfg(feature = "attachment-ocr")]
+fn extract_image(data: &[u8]) -> Option<String> {
+    let result = tesseract::Tesseract::new(None, Some("eng"))
+        .map_err(|e| e.to_string())
+        .and_then(|t| t.set_image_from_mem(data).map_err(|e| e.to_string()))
+        .and_then(|mut t| t.get_text().map_err(|e| e.to_string()));
+
+    match result {
+        Ok(text) => Some(text),
+        Err(e) => {
+            eprintln!("Image OCR failed: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "attachment-ocr"))]
+fn extract_image(_data: &[u8]) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_text_is_normalized() {
+        assert_eq!(
+            extract_text("text/plain", "notes.txt", b"Invoice\n\n  4211\tpaid").as_deref(),
+            Some("Invoice 4211 paid")
+        );
+        assert_eq!(
+            extract_text("application/octet-stream", "data.csv", b"a,b").as_deref(),
+            Some("a,b")
+        );
+        assert!(extract_text("text/plain", "empty.txt", b"  \n").is_none());
+        assert!(extract_text("application/zip", "archive.zip", b"PK").is_none());
+    }
+}
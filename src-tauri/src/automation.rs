@@ -0,0 +1,177 @@
+//! OS automation surface so scripting tools can drive the running app.
+//!
+//! macOS listens on a Unix socket (`automation.sock` in the app config
+//! directory), which AppleScript reaches through `do shell script` with `nc -U`;
+//! a native scripting dictionary would need Cocoa Apple Event handlers that
+//! Tauri does not expose. Windows listens on the named pipe
+//! `\\.\pipe\aisle3-automation`.
+//!
+//! Each request is one line of JSON, e.g. `{"command": "unread_count"}`, and
+//! gets one line back: `{"ok": true, "result": ...}` or `{"ok": false, "error": "..."}`.
+
+use crate::gmail_client::GmailClient;
+use crate::{list_mailbox, refresh_tokens_if_needed, AppState};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+#[cfg(target_os = "macos")]
+const SOCKET_FILE: &str = "automation.sock";
+
+#[cfg(windows)]
+const PIPE_NAME: &str = r"\\.\pipe\aisle3-automation";
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum AutomationRequest {
+    /// Bring the app forward and open the composer with these fields filled in
+    Compose {
+        to: Option<String>,
+        subject: Option<String>,
+        body: Option<String>,
+    },
+    Search {
+        query: String,
+        max_results: Option<u32>,
+    },
+    UnreadCount,
+}
+
+/// Payload of the `automation-compose` event the frontend opens the composer from
+#[derive(Debug, Clone, Serialize)]
+struct ComposeRequest {
+    to: Option<String>,
+    subject: Option<String>,
+    body: Option<String>,
+}
+
+async fn handle(app: &AppHandle, request: AutomationRequest) -> Result<serde_json::Value, String> {
+    match request {
+        AutomationRequest::Compose { to, subject, body } => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+            app.emit("automation-compose", ComposeRequest { to, subject, body })
+                .map_err(|e| format!("Failed to open composer: {}", e))?;
+            Ok(serde_json::Value::Null)
+        }
+        AutomationRequest::Search { query, max_results } => {
+            let page = list_mailbox(
+                Some(query),
+                None,
+                max_results,
+                app.clone(),
+                app.state::<AppState>(),
+            )
+            .await?;
+            serde_json::to_value(page).map_err(|e| e.to_string())
+        }
+        AutomationRequest::UnreadCount => {
+            let unread = unread_count(&app.state::<AppState>()).await?;
+            Ok(serde_json::json!({ "unread": unread }))
+        }
+    }
+}
+
+/// Unread inbox count; unlike `get_inbox_stats` this never falls back to sample data
+async fn unread_count(state: &State<'_, AppState>) -> Result<u32, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_inbox_stats")?;
+    let tokens = match refresh_tokens_if_needed(state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    gmail_client
+        .list_messages(Some(1), None, Some("in:inbox is:unread"))
+        .await
+        .map(|response| response.result_size_estimate.unwrap_or(0))
+        .map_err(|e| format!("Failed to count unread mail: {}", e))
+}
+
+/// Handle one request line and build the response line
+async fn dispatch(app: &AppHandle, line: &str) -> String {
+    let result = match serde_json::from_str::<AutomationRequest>(line) {
+        Ok(request) => handle(app, request).await,
+        Err(e) => Err(format!("Invalid automation request: {}", e)),
+    };
+
+    let response = match result {
+        Ok(result) => serde_json::json!({ "ok": true, "result": result }),
+        Err(error) => serde_json::json!({ "ok": false, "error": error }),
+    };
+    response.to_string()
+}
+
+/// Answer requests on a connection until the client hangs up
+async fn serve_connection<S>(app: AppHandle, stream: S)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut response = dispatch(&app, &line).await;
+        response.push('\n');
+        if writer.write_all(response.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Listen on the automation socket until the app exits
+#[cfg(target_os = "macos")]
+pub async fn serve(app: AppHandle) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = crate::local_store::app_data_path(SOCKET_FILE);
+    // A socket left behind by a previous run would make bind fail
+    let _ = std::fs::remove_file(&path);
+
+    let listener = tokio::net::UnixListener::bind(&path)
+        .map_err(|e| format!("Failed to bind {}: {}", path.display(), e))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("Failed to restrict {}: {}", path.display(), e))?;
+
+    loop {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .map_err(|e| format!("Automation socket error: {}", e))?;
+        tauri::async_runtime::spawn(serve_connection(app.clone(), stream));
+    }
+}
+
+/// Listen on the automation pipe until the app exits
+#[cfg(windows)]
+pub async fn serve(app: AppHandle) -> Result<(), String> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(PIPE_NAME)
+        .map_err(|e| format!("Failed to create {}: {}", PIPE_NAME, e))?;
+
+    loop {
+        server
+            .connect()
+            .await
+            .map_err(|e| format!("Automation pipe error: {}", e))?;
+
+        // Open the next instance before handing this one off so clients never see the pipe missing
+        let connected = server;
+        server = ServerOptions::new()
+            .create(PIPE_NAME)
+            .map_err(|e| format!("Failed to create {}: {}", PIPE_NAME, e))?;
+        tauri::async_runtime::spawn(serve_connection(app.clone(), connected));
+    }
+}
@@ -0,0 +1,630 @@
+use crate::gmail_client::{is_transient_error, GmailClient};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Messages per batchModify call; Gmail allows 1000 but smaller chunks give smoother progress
+pub const BULK_CHUNK_SIZE: usize = 250;
+
+/// Upper bound on messages a single query-driven bulk action will touch
+pub const MAX_BULK_MESSAGES: usize = 5000;
+
+/// Message ids included in a dry-run preview
+pub const DRY_RUN_SAMPLE_SIZE: usize = 10;
+
+/// Query matching everything currently in Trash
+pub const TRASH_QUERY: &str = "in:trash";
+
+/// Number of completed operations kept around for undo
+const UNDO_HISTORY_LIMIT: usize = 10;
+
+/// Extra attempts for a chunk Gmail turned away with a rate limit or server error
+const CHUNK_RETRIES: u32 = 3;
+
+/// Wait before the first chunk retry; doubles for each one after
+const CHUNK_BACKOFF: Duration = Duration::from_secs(1);
+
+/// A label-based action that can be applied to many messages at once
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BulkAction {
+    Archive,
+    MoveToInbox,
+    MarkRead,
+    MarkUnread,
+    Star,
+    Unstar,
+    AddLabel { label_id: String },
+    RemoveLabel { label_id: String },
+    Trash,
+    Untrash,
+}
+
+impl BulkAction {
+    /// Labels to add and remove for this action
+    pub fn label_changes(&self) -> (Vec<String>, Vec<String>) {
+        let label = |l: &str| vec![l.to_string()];
+        match self {
+            BulkAction::Archive => (vec![], label("INBOX")),
+            BulkAction::MoveToInbox => (label("INBOX"), vec![]),
+            BulkAction::MarkRead => (vec![], label("UNREAD")),
+            BulkAction::MarkUnread => (label("UNREAD"), vec![]),
+            BulkAction::Star => (label("STARRED"), vec![]),
+            BulkAction::Unstar => (vec![], label("STARRED")),
+            BulkAction::AddLabel { label_id } => (vec![label_id.clone()], vec![]),
+            BulkAction::RemoveLabel { label_id } => (vec![], vec![label_id.clone()]),
+            BulkAction::Trash => (label("TRASH"), vec![]),
+            BulkAction::Untrash => (vec![], label("TRASH")),
+        }
+    }
+
+    /// Merge the label changes of several actions into one add/remove pair
+    pub fn combined_label_changes(actions: &[BulkAction]) -> (Vec<String>, Vec<String>) {
+        let mut add: Vec<String> = Vec::new();
+        let mut remove: Vec<String> = Vec::new();
+
+        for action in actions {
+            let (action_add, action_remove) = action.label_changes();
+            for label in action_add {
+                if !add.contains(&label) {
+                    add.push(label);
+                }
+            }
+            for label in action_remove {
+                if !remove.contains(&label) {
+                    remove.push(label);
+                }
+            }
+        }
+
+        (add, remove)
+    }
+
+    /// Whether applying this action to a message carrying `label_ids` would
+    /// change its labels
+    pub fn changes(&self, label_ids: &[String]) -> bool {
+        let (add, remove) = self.label_changes();
+        add.iter().any(|label| !label_ids.contains(label))
+            || remove.iter().any(|label| label_ids.contains(label))
+    }
+
+    /// The action that reverts this one on messages it changed
+    pub fn inverse(&self) -> BulkAction {
+        match self {
+            BulkAction::Archive => BulkAction::MoveToInbox,
+            BulkAction::MoveToInbox => BulkAction::Archive,
+            BulkAction::MarkRead => BulkAction::MarkUnread,
+            BulkAction::MarkUnread => BulkAction::MarkRead,
+            BulkAction::Star => BulkAction::Unstar,
+            BulkAction::Unstar => BulkAction::Star,
+            BulkAction::AddLabel { label_id } => BulkAction::RemoveLabel {
+                label_id: label_id.clone(),
+            },
+            BulkAction::RemoveLabel { label_id } => BulkAction::AddLabel {
+                label_id: label_id.clone(),
+            },
+            BulkAction::Trash => BulkAction::Untrash,
+            BulkAction::Untrash => BulkAction::Trash,
+        }
+    }
+}
+
+/// Progress payload emitted after each chunk
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkProgress {
+    pub operation_id: String,
+    pub processed: usize,
+    pub total: usize,
+}
+
+/// Outcome of a bulk run
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkSummary {
+    pub operation_id: String,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub errors: Vec<String>,
+    /// Pass to `undo_bulk_action` to revert; absent when nothing changed
+    pub undo_id: Option<String>,
+    /// True when nothing was modified and `total` is only the affected count
+    pub dry_run: bool,
+    /// A few of the affected message ids, filled in for dry runs
+    pub sample_ids: Vec<String>,
+    /// Result for each message, filled in by runs that go message by message
+    pub outcomes: Vec<MessageOutcome>,
+}
+
+impl BulkSummary {
+    /// Describe what a run would touch without modifying anything
+    pub fn preview(operation_id: &str, message_ids: &[String]) -> Self {
+        BulkSummary {
+            operation_id: operation_id.to_string(),
+            total: message_ids.len(),
+            succeeded: 0,
+            failed: 0,
+            errors: Vec::new(),
+            undo_id: None,
+            dry_run: true,
+            sample_ids: message_ids
+                .iter()
+                .take(DRY_RUN_SAMPLE_SIZE)
+                .cloned()
+                .collect(),
+            outcomes: Vec::new(),
+        }
+    }
+
+    /// Sum up a `modify_each` run, returning the ids that changed
+    pub fn from_outcomes(operation_id: &str, outcomes: Vec<MessageOutcome>) -> (Self, Vec<String>) {
+        let changed_ids: Vec<String> = outcomes
+            .iter()
+            .filter(|o| o.success)
+            .map(|o| o.message_id.clone())
+            .collect();
+        let mut errors: Vec<String> = Vec::new();
+        for error in outcomes.iter().filter_map(|o| o.error.as_ref()) {
+            if !errors.contains(error) {
+                errors.push(error.clone());
+            }
+        }
+        let summary = BulkSummary {
+            operation_id: operation_id.to_string(),
+            total: outcomes.len(),
+            succeeded: changed_ids.len(),
+            failed: outcomes.len() - changed_ids.len(),
+            errors,
+            undo_id: None,
+            dry_run: false,
+            sample_ids: Vec::new(),
+            outcomes,
+        };
+        (summary, changed_ids)
+    }
+}
+
+/// Per-message result of `modify_each`
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageOutcome {
+    pub message_id: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Everything needed to revert a completed bulk run
+#[derive(Debug, Clone)]
+pub struct UndoEntry {
+    pub undo_id: String,
+    pub message_ids: Vec<String>,
+    pub actions: Vec<BulkAction>,
+}
+
+/// Recent bulk runs that can still be reverted, newest last
+#[derive(Debug, Default)]
+pub struct UndoHistory {
+    entries: Vec<UndoEntry>,
+}
+
+impl UndoHistory {
+    pub fn push(&mut self, entry: UndoEntry) {
+        self.entries.push(entry);
+        if self.entries.len() > UNDO_HISTORY_LIMIT {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Remember the messages a run actually changed and attach an undo id to its summary
+    pub fn record(
+        &mut self,
+        summary: &mut BulkSummary,
+        changed_ids: Vec<String>,
+        actions: &[BulkAction],
+    ) {
+        if changed_ids.is_empty() {
+            return;
+        }
+
+        let undo_id = format!("undo_{}", summary.operation_id);
+        self.push(UndoEntry {
+            undo_id: undo_id.clone(),
+            message_ids: changed_ids,
+            actions: actions.iter().map(BulkAction::inverse).collect(),
+        });
+        summary.undo_id = Some(undo_id);
+    }
+
+    /// Remove and return the entry so an undo can't be applied twice
+    pub fn take(&mut self, undo_id: &str) -> Option<UndoEntry> {
+        let index = self.entries.iter().position(|e| e.undo_id == undo_id)?;
+        Some(self.entries.remove(index))
+    }
+}
+
+/// Messages per metadata batch when reading labels before a run
+const LABEL_BATCH_SIZE: usize = 100;
+
+/// Each message's labels, read before a run so undo can tell which messages
+/// the run really changed
+pub async fn labels_before(
+    gmail_client: &GmailClient,
+    message_ids: &[String],
+) -> Result<HashMap<String, Vec<String>>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut labels = HashMap::with_capacity(message_ids.len());
+    for chunk in message_ids.chunks(LABEL_BATCH_SIZE) {
+        for message in gmail_client.get_messages_metadata_batch(chunk).await? {
+            labels.insert(message.id, message.label_ids.unwrap_or_default());
+        }
+    }
+    Ok(labels)
+}
+
+/// The messages among `succeeded_ids` whose labels the actions changed, going
+/// by `labels_before`. A message whose earlier labels aren't known is kept.
+pub fn actually_changed(
+    succeeded_ids: Vec<String>,
+    actions: &[BulkAction],
+    labels_before: &HashMap<String, Vec<String>>,
+) -> Vec<String> {
+    succeeded_ids
+        .into_iter()
+        .filter(|id| {
+            labels_before
+                .get(id)
+                .is_none_or(|labels| actions.iter().any(|action| action.changes(labels)))
+        })
+        .collect()
+}
+
+/// Generate an id for a bulk operation
+pub fn new_operation_id() -> String {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    format!("bulk_{}", millis)
+}
+
+/// batchModify one chunk, backing off and trying again while Gmail answers
+/// with rate limiting or server errors
+async fn modify_chunk(
+    gmail_client: &GmailClient,
+    chunk: &[String],
+    add: &[String],
+    remove: &[String],
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut attempt = 0;
+    loop {
+        match gmail_client.batch_modify_messages(chunk, add, remove).await {
+            Err(e) if attempt < CHUNK_RETRIES && is_transient_error(e.as_ref()) => {
+                tokio::time::sleep(CHUNK_BACKOFF * 2u32.pow(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Apply actions to messages in chunks, reporting progress after every chunk.
+///
+/// A chunk hitting Gmail's rate limit is retried after a backoff. A chunk that
+/// still fails is recorded and the run continues, so one bad request doesn't
+/// strand the rest of a large cleanup.
+pub async fn run_bulk_action<F>(
+    gmail_client: &GmailClient,
+    operation_id: &str,
+    message_ids: &[String],
+    actions: &[BulkAction],
+    mut on_progress: F,
+) -> (BulkSummary, Vec<String>)
+where
+    F: FnMut(&BulkProgress),
+{
+    let (add, remove) = BulkAction::combined_label_changes(actions);
+    let mut succeeded_ids = Vec::new();
+    let mut errors = Vec::new();
+    let mut processed = 0;
+
+    for chunk in message_ids.chunks(BULK_CHUNK_SIZE) {
+        match modify_chunk(gmail_client, chunk, &add, &remove).await {
+            Ok(()) => succeeded_ids.extend_from_slice(chunk),
+            Err(e) => errors.push(e.to_string()),
+        }
+
+        processed += chunk.len();
+        on_progress(&BulkProgress {
+            operation_id: operation_id.to_string(),
+            processed,
+            total: message_ids.len(),
+        });
+    }
+
+    let summary = BulkSummary {
+        operation_id: operation_id.to_string(),
+        total: message_ids.len(),
+        succeeded: succeeded_ids.len(),
+        failed: message_ids.len() - succeeded_ids.len(),
+        errors,
+        undo_id: None,
+        dry_run: false,
+        sample_ids: Vec::new(),
+        outcomes: Vec::new(),
+    };
+
+    (summary, succeeded_ids)
+}
+
+/// Apply actions to a selection of messages and report the outcome for each one.
+///
+/// Each chunk goes through one batchModify call. batchModify is all-or-nothing
+/// and doesn't say which id broke it, so a failed chunk is retried one message
+/// at a time to pin the failure on the right messages.
+pub async fn modify_each(
+    gmail_client: &GmailClient,
+    message_ids: &[String],
+    actions: &[BulkAction],
+) -> Vec<MessageOutcome> {
+    let (add, remove) = BulkAction::combined_label_changes(actions);
+    let add_refs: Vec<&str> = add.iter().map(String::as_str).collect();
+    let remove_refs: Vec<&str> = remove.iter().map(String::as_str).collect();
+    let mut outcomes = Vec::with_capacity(message_ids.len());
+
+    for chunk in message_ids.chunks(BULK_CHUNK_SIZE) {
+        if gmail_client
+            .batch_modify_messages(chunk, &add, &remove)
+            .await
+            .is_ok()
+        {
+            outcomes.extend(chunk.iter().map(|id| MessageOutcome {
+                message_id: id.clone(),
+                success: true,
+                error: None,
+            }));
+            continue;
+        }
+
+        for id in chunk {
+            let result = gmail_client
+                .modify_message(id, &add_refs, &remove_refs)
+                .await;
+            outcomes.push(MessageOutcome {
+                message_id: id.clone(),
+                success: result.is_ok(),
+                error: result.err().map(|e| e.to_string()),
+            });
+        }
+    }
+
+    outcomes
+}
+
+/// What `empty_trash` would delete: every message in Trash, counted from the
+/// Trash label, with a sample of ids from the first page
+pub async fn preview_empty_trash(
+    gmail_client: &GmailClient,
+    operation_id: &str,
+) -> Result<BulkSummary, Box<dyn std::error::Error + Send + Sync>> {
+    let sample: Vec<String> = gmail_client
+        .list_messages(Some(DRY_RUN_SAMPLE_SIZE as u32), None, Some(TRASH_QUERY))
+        .await?
+        .messages
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    let trash = gmail_client.get_label("TRASH").await?;
+
+    let mut preview = BulkSummary::preview(operation_id, &sample);
+    preview.total = trash
+        .messages_total
+        .map_or(sample.len(), |total| total as usize);
+    Ok(preview)
+}
+
+/// Permanently delete everything in Trash, reporting progress after every page.
+///
+/// Always re-reads the first page because deleted messages drop out of the
+/// listing, so page tokens would skip mail. Stops at the first failed page
+/// rather than retrying the same messages forever.
+pub async fn empty_trash<F>(
+    gmail_client: &GmailClient,
+    operation_id: &str,
+    mut on_progress: F,
+) -> BulkSummary
+where
+    F: FnMut(&BulkProgress),
+{
+    let mut errors = Vec::new();
+    let mut processed = 0;
+    let mut succeeded = 0;
+    let mut total = 0;
+
+    loop {
+        let page = match gmail_client
+            .list_messages(Some(BULK_CHUNK_SIZE as u32), None, Some(TRASH_QUERY))
+            .await
+        {
+            Ok(page) => page,
+            Err(e) => {
+                errors.push(e.to_string());
+                break;
+            }
+        };
+
+        let ids: Vec<String> = page
+            .messages
+            .unwrap_or_default()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        if ids.is_empty() {
+            break;
+        }
+
+        // The estimate shrinks as we go, so keep the largest total seen
+        let remaining = ids
+            .len()
+            .max(page.result_size_estimate.unwrap_or(0) as usize);
+        total = total.max(processed + remaining);
+
+        let result = gmail_client.batch_delete_messages(&ids).await;
+        processed += ids.len();
+        on_progress(&BulkProgress {
+            operation_id: operation_id.to_string(),
+            processed,
+            total,
+        });
+
+        match result {
+            Ok(()) => succeeded += ids.len(),
+            Err(e) => {
+                errors.push(e.to_string());
+                break;
+            }
+        }
+    }
+
+    BulkSummary {
+        operation_id: operation_id.to_string(),
+        total: processed,
+        succeeded,
+        failed: processed - succeeded,
+        errors,
+        undo_id: None,
+        dry_run: false,
+        sample_ids: Vec::new(),
+        outcomes: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inverse_reverts_label_changes() {
+        let actions = vec![
+            BulkAction::Archive,
+            BulkAction::MarkRead,
+            BulkAction::Star,
+            BulkAction::AddLabel {
+                label_id: "Label_1".to_string(),
+            },
+            BulkAction::Trash,
+        ];
+
+        for action in actions {
+            let (add, remove) = action.label_changes();
+            let (inverse_add, inverse_remove) = action.inverse().label_changes();
+            assert_eq!(add, inverse_remove);
+            assert_eq!(remove, inverse_add);
+            assert_eq!(action.inverse().inverse(), action);
+        }
+    }
+
+    #[test]
+    fn test_only_messages_the_action_changed_are_kept() {
+        let labels = |ids: &[&str]| ids.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        let before = HashMap::from([
+            ("in_inbox".to_string(), labels(&["INBOX", "UNREAD"])),
+            ("archived".to_string(), labels(&["UNREAD"])),
+        ]);
+        let succeeded = labels(&["in_inbox", "archived", "unknown"]);
+
+        assert_eq!(
+            actually_changed(succeeded.clone(), &[BulkAction::Archive], &before),
+            vec!["in_inbox", "unknown"]
+        );
+        assert_eq!(
+            actually_changed(succeeded, &[BulkAction::MarkRead], &before),
+            vec!["in_inbox", "archived", "unknown"]
+        );
+        assert!(!BulkAction::Star.changes(&labels(&["STARRED"])));
+    }
+
+    #[test]
+    fn test_combined_label_changes_deduplicates() {
+        let actions = vec![
+            BulkAction::Archive,
+            BulkAction::MarkRead,
+            BulkAction::Archive,
+            BulkAction::AddLabel {
+                label_id: "Label_1".to_string(),
+            },
+        ];
+
+        let (add, remove) = BulkAction::combined_label_changes(&actions);
+        assert_eq!(add, vec!["Label_1".to_string()]);
+        assert_eq!(remove, vec!["INBOX".to_string(), "UNREAD".to_string()]);
+    }
+
+    #[test]
+    fn test_action_deserialization() {
+        let action: BulkAction = serde_json::from_str(r#"{"type": "archive"}"#).unwrap();
+        assert_eq!(action, BulkAction::Archive);
+
+        let action: BulkAction =
+            serde_json::from_str(r#"{"type": "add_label", "label_id": "Label_7"}"#).unwrap();
+        assert_eq!(
+            action,
+            BulkAction::AddLabel {
+                label_id: "Label_7".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_preview_reports_count_and_sample() {
+        let ids: Vec<String> = (0..25).map(|i| format!("msg{}", i)).collect();
+        let preview = BulkSummary::preview("op", &ids);
+
+        assert!(preview.dry_run);
+        assert_eq!(preview.total, 25);
+        assert_eq!(preview.succeeded, 0);
+        assert_eq!(preview.sample_ids.len(), DRY_RUN_SAMPLE_SIZE);
+        assert_eq!(preview.sample_ids[0], "msg0");
+        assert!(preview.undo_id.is_none());
+    }
+
+    #[test]
+    fn test_outcomes_are_summed_up() {
+        let outcome = |id: &str, error: Option<&str>| MessageOutcome {
+            message_id: id.to_string(),
+            success: error.is_none(),
+            error: error.map(str::to_string),
+        };
+        let (summary, changed) = BulkSummary::from_outcomes(
+            "op",
+            vec![
+                outcome("m1", None),
+                outcome("m2", Some("Gmail API error: 404")),
+                outcome("m3", Some("Gmail API error: 404")),
+                outcome("m4", None),
+            ],
+        );
+
+        assert_eq!(changed, vec!["m1", "m4"]);
+        assert_eq!(
+            (summary.total, summary.succeeded, summary.failed),
+            (4, 2, 2)
+        );
+        assert_eq!(summary.errors, vec!["Gmail API error: 404"]);
+        assert_eq!(summary.outcomes.len(), 4);
+        assert!(!summary.dry_run);
+    }
+
+    #[test]
+    fn test_undo_history_is_bounded_and_single_use() {
+        let mut history = UndoHistory::default();
+        for i in 0..(UNDO_HISTORY_LIMIT + 2) {
+            history.push(UndoEntry {
+                undo_id: format!("op{}", i),
+                message_ids: vec![],
+                actions: vec![BulkAction::Archive],
+            });
+        }
+
+        assert!(history.take("op0").is_none());
+        assert!(history.take("op5").is_some());
+        assert!(history.take("op5").is_none());
+    }
+}
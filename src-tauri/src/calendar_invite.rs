@@ -0,0 +1,582 @@
+use crate::gmail_client::{flatten_parts, GmailClient, GmailMessage, OutgoingAttachment};
+use crate::scheduler;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+const PRODUCT_ID: &str = "-//Aisle3//Aisle3//EN";
+
+/// Attached to the RSVP so the organizer's calendar picks it up as an iTIP
+/// reply
+const REPLY_MIME_TYPE: &str = "text/calendar; method=REPLY; charset=UTF-8";
+
+/// iCalendar content lines are folded at this many octets
+const FOLD_AT: usize = 75;
+
+/// Properties copied verbatim into a reply so it refers to the same event
+/// and occurrence
+const ECHOED_PROPERTIES: &[&str] = &["DTSTART", "DTEND", "RECURRENCE-ID", "ORGANIZER"];
+
+/// Someone on an invite
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Attendee {
+    pub email: String,
+    pub name: Option<String>,
+    /// PARTSTAT as sent, e.g. "NEEDS-ACTION" or "ACCEPTED"
+    pub status: Option<String>,
+}
+
+/// When an event starts or ends
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EventTime {
+    /// RFC 3339 in UTC for times whose zone is known, "YYYY-MM-DD" for all-day
+    /// events, and a local "YYYY-MM-DDTHH:MM:SS" for floating times or zones
+    /// that aren't IANA names
+    pub value: String,
+    pub all_day: bool,
+    /// TZID the time was given in
+    pub time_zone: Option<String>,
+}
+
+/// The event from a text/calendar part
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CalendarInvite {
+    /// iTIP method: REQUEST for an invitation, CANCEL, REPLY, ...
+    pub method: Option<String>,
+    pub uid: String,
+    pub sequence: u32,
+    pub summary: Option<String>,
+    pub location: Option<String>,
+    pub description: Option<String>,
+    pub organizer: Option<Attendee>,
+    pub attendees: Vec<Attendee>,
+    pub start: Option<EventTime>,
+    pub end: Option<EventTime>,
+    pub recurring: bool,
+    /// Unfolded lines of `ECHOED_PROPERTIES`
+    #[serde(skip)]
+    echoed: Vec<String>,
+}
+
+/// How the user answers an invitation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RsvpResponse {
+    Accepted,
+    Tentative,
+    Declined,
+}
+
+impl RsvpResponse {
+    fn partstat(self) -> &'static str {
+        match self {
+            RsvpResponse::Accepted => "ACCEPTED",
+            RsvpResponse::Tentative => "TENTATIVE",
+            RsvpResponse::Declined => "DECLINED",
+        }
+    }
+
+    /// Subject prefix, as calendar clients word it
+    fn label(self) -> &'static str {
+        match self {
+            RsvpResponse::Accepted => "Accepted",
+            RsvpResponse::Tentative => "Tentatively Accepted",
+            RsvpResponse::Declined => "Declined",
+        }
+    }
+}
+
+struct Property<'a> {
+    name: String,
+    params: Vec<(String, String)>,
+    value: &'a str,
+    line: &'a str,
+}
+
+impl Property<'_> {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn attendee(&self) -> Option<Attendee> {
+        let email = strip_mailto(self.value);
+        (!email.is_empty()).then(|| Attendee {
+            email: email.to_string(),
+            name: self.param("CN").map(str::to_string),
+            status: self.param("PARTSTAT").map(str::to_ascii_uppercase),
+        })
+    }
+}
+
+/// Join folded lines: a line starting with a space or tab continues the one
+/// before it
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// NAME;PARAM=value;PARAM="quoted":VALUE
+fn parse_property(line: &str) -> Option<Property<'_>> {
+    let mut quoted = false;
+    let mut colon = None;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => {
+                colon = Some(i);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let colon = colon?;
+    let mut head = split_unquoted(&line[..colon], ';').into_iter();
+    let name = head.next()?.trim().to_ascii_uppercase();
+    let params = head
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((
+                key.trim().to_string(),
+                value.trim().trim_matches('"').to_string(),
+            ))
+        })
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value: &line[colon + 1..],
+        line,
+    })
+}
+
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            pieces.push(&text[start..i]);
+            start = i + 1;
+        }
+    }
+    pieces.push(&text[start..]);
+    pieces
+}
+
+fn strip_mailto(value: &str) -> &str {
+    let value = value.trim();
+    match value.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => &value[7..],
+        _ => value,
+    }
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+fn event_time(property: &Property) -> Option<EventTime> {
+    let value = property.value.trim();
+    let time_zone = property.param("TZID").map(str::to_string);
+    let is_date = property
+        .param("VALUE")
+        .is_some_and(|v| v.eq_ignore_ascii_case("DATE"))
+        || value.len() == 8;
+    if is_date {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some(EventTime {
+            value: date.format("%Y-%m-%d").to_string(),
+            all_day: true,
+            time_zone,
+        });
+    }
+
+    let (local, utc) = match value.strip_suffix(['Z', 'z']) {
+        Some(local) => (local, true),
+        None => (value, false),
+    };
+    let local = NaiveDateTime::parse_from_str(local, "%Y%m%dT%H%M%S").ok()?;
+    let zone = time_zone
+        .as_deref()
+        .and_then(|tzid| tzid.parse::<Tz>().ok());
+    let value = match (utc, zone) {
+        (true, _) => rfc3339(local.and_utc()),
+        (false, Some(zone)) => rfc3339(scheduler::resolve_local(&zone, local).with_timezone(&Utc)),
+        (false, None) => local.format("%Y-%m-%dT%H:%M:%S").to_string(),
+    };
+    Some(EventTime {
+        value,
+        all_day: false,
+        time_zone,
+    })
+}
+
+fn rfc3339(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl CalendarInvite {
+    /// The first event in an iCalendar object, or None when it has no
+    /// event with a UID
+    pub fn parse(ics: &str) -> Option<Self> {
+        let mut invite = CalendarInvite {
+            method: None,
+            uid: String::new(),
+            sequence: 0,
+            summary: None,
+            location: None,
+            description: None,
+            organizer: None,
+            attendees: Vec::new(),
+            start: None,
+            end: None,
+            recurring: false,
+            echoed: Vec::new(),
+        };
+        // Components open inside the event, e.g. VALARM; their properties
+        // aren't the event's
+        let mut in_event = false;
+        let mut nested = 0;
+        let mut seen_event = false;
+
+        for line in unfold(ics) {
+            let Some(property) = parse_property(&line) else {
+                continue;
+            };
+            let value = property.value.trim();
+            match property.name.as_str() {
+                "BEGIN" if value.eq_ignore_ascii_case("VEVENT") && !seen_event => {
+                    in_event = true;
+                    seen_event = true;
+                }
+                "BEGIN" if in_event => nested += 1,
+                "END" if in_event && nested > 0 => nested -= 1,
+                "END" if in_event => in_event = false,
+                "METHOD" if !in_event => invite.method = Some(value.to_ascii_uppercase()),
+                _ if !in_event || nested > 0 => {}
+                "UID" => invite.uid = value.to_string(),
+                "SEQUENCE" => invite.sequence = value.parse().unwrap_or(0),
+                "SUMMARY" => invite.summary = Some(unescape_text(value)),
+                "LOCATION" => invite.location = Some(unescape_text(value)),
+                "DESCRIPTION" => invite.description = Some(unescape_text(value)),
+                "ORGANIZER" => invite.organizer = property.attendee(),
+                "ATTENDEE" => invite.attendees.extend(property.attendee()),
+                "DTSTART" => invite.start = event_time(&property),
+                "DTEND" => invite.end = event_time(&property),
+                "RRULE" => invite.recurring = true,
+                _ => {}
+            }
+            if in_event && nested == 0 && ECHOED_PROPERTIES.contains(&property.name.as_str()) {
+                invite.echoed.push(property.line.to_string());
+            }
+        }
+        (!invite.uid.is_empty()).then_some(invite)
+    }
+
+    /// Whether the organizer is asking for a reply
+    pub fn accepts_replies(&self) -> bool {
+        self.method.as_deref() == Some("REQUEST") && self.organizer.is_some()
+    }
+
+    /// The attendee entry for whichever of `addresses` was invited
+    pub fn find_attendee(&self, addresses: &[String]) -> Option<&Attendee> {
+        self.attendees.iter().find(|attendee| {
+            addresses
+                .iter()
+                .any(|address| address.eq_ignore_ascii_case(&attendee.email))
+        })
+    }
+
+    /// The iTIP REPLY answering this invitation as `attendee`
+    pub fn reply_ics(
+        &self,
+        attendee: &Attendee,
+        response: RsvpResponse,
+        comment: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> String {
+        let mut attendee_line = format!("ATTENDEE;PARTSTAT={}", response.partstat());
+        if let Some(name) = attendee.name.as_deref().filter(|n| !n.is_empty()) {
+            attendee_line.push_str(&format!(";CN=\"{}\"", name.replace(['"', '\r', '\n'], "")));
+        }
+        attendee_line.push_str(&format!(":mailto:{}", attendee.email));
+
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            format!("PRODID:{}", PRODUCT_ID),
+            "VERSION:2.0".to_string(),
+            "CALSCALE:GREGORIAN".to_string(),
+            "METHOD:REPLY".to_string(),
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", self.uid),
+            format!("SEQUENCE:{}", self.sequence),
+            format!("DTSTAMP:{}", now.format("%Y%m%dT%H%M%SZ")),
+        ];
+        lines.extend(self.echoed.iter().cloned());
+        if let Some(summary) = &self.summary {
+            lines.push(format!("SUMMARY:{}", escape_text(summary)));
+        }
+        lines.push(attendee_line);
+        if let Some(comment) = comment.filter(|c| !c.trim().is_empty()) {
+            lines.push(format!("COMMENT:{}", escape_text(comment.trim())));
+        }
+        lines.push("END:VEVENT".to_string());
+        lines.push("END:VCALENDAR".to_string());
+
+        lines.iter().map(|line| fold(line)).collect()
+    }
+
+    /// Subject, body and calendar attachment of the RSVP message; the caller
+    /// addresses it to the organizer and threads it
+    pub fn reply_parts(
+        &self,
+        attendee: &Attendee,
+        response: RsvpResponse,
+        comment: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> (String, String, OutgoingAttachment) {
+        let title = self.summary.as_deref().unwrap_or("Invitation");
+        let subject = format!("{}: {}", response.label(), title);
+        let who = attendee.name.as_deref().unwrap_or(&attendee.email);
+        let mut body = format!(
+            "{} has {} this invitation.",
+            who,
+            response.label().to_lowercase()
+        );
+        if let Some(comment) = comment.filter(|c| !c.trim().is_empty()) {
+            body.push_str("\n\n");
+            body.push_str(comment.trim());
+        }
+        let attachment = OutgoingAttachment {
+            filename: "invite.ics".to_string(),
+            mime_type: REPLY_MIME_TYPE.to_string(),
+            data: self
+                .reply_ics(attendee, response, comment, now)
+                .into_bytes(),
+        };
+        (subject, body, attachment)
+    }
+}
+
+/// A content line with CRLF, folded so no physical line passes `FOLD_AT`
+/// octets; folds never split a UTF-8 sequence
+fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + 8);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > FOLD_AT {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+fn is_calendar_type(mime_type: &str) -> bool {
+    let mime_type = mime_type.to_ascii_lowercase();
+    mime_type.starts_with("text/calendar") || mime_type.starts_with("application/ics")
+}
+
+/// The invitation carried by a message, from an inline text/calendar part
+/// or, failing that, an attached .ics file
+pub async fn load_invite(
+    gmail_client: &GmailClient,
+    message: &GmailMessage,
+) -> Result<Option<CalendarInvite>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(payload) = &message.payload else {
+        return Ok(None);
+    };
+    let parts = flatten_parts(payload.parts.as_deref().unwrap_or_default());
+
+    let inline = parts
+        .into_iter()
+        .filter(|part| part.mime_type.as_deref().is_some_and(is_calendar_type))
+        .filter_map(|part| part.body.as_ref()?.data.as_deref());
+    let root = payload
+        .headers
+        .iter()
+        .flatten()
+        .find(|h| h.name.eq_ignore_ascii_case("Content-Type"))
+        .filter(|h| is_calendar_type(&h.value))
+        .and_then(|_| payload.body.as_ref()?.data.as_deref());
+    for data in root.into_iter().chain(inline) {
+        let decoded = URL_SAFE_NO_PAD.decode(data.trim_end_matches('='))?;
+        if let Some(invite) = CalendarInvite::parse(&String::from_utf8_lossy(&decoded)) {
+            return Ok(Some(invite));
+        }
+    }
+
+    for attachment in message.get_attachments() {
+        let is_ics = is_calendar_type(&attachment.mime_type)
+            || attachment.filename.to_ascii_lowercase().ends_with(".ics");
+        if !is_ics {
+            continue;
+        }
+        let data = gmail_client
+            .get_attachment(&message.id, &attachment.attachment_id)
+            .await?;
+        if let Some(invite) = CalendarInvite::parse(&String::from_utf8_lossy(&data)) {
+            return Ok(Some(invite));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const INVITE: &str = "BEGIN:VCALENDAR\r\n\
+PRODID:-//Google Inc//Google Calendar 70.9054//EN\r\n\
+VERSION:2.0\r\n\
+METHOD:REQUEST\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART;TZID=Europe/Berlin:20250610T090000\r\n\
+DTEND;TZID=Europe/Berlin:20250610T100000\r\n\
+DTSTAMP:20250601T120000Z\r\n\
+ORGANIZER;CN=Alice Smith:mailto:alice@example.com\r\n\
+UID:abc123@google.com\r\n\
+ATTENDEE;CUTYPE=INDIVIDUAL;ROLE=REQ-PARTICIPANT;PARTSTAT=NEEDS-ACTION;CN=\"Bob\r\n  Jones\";X-NUM-GUESTS=0:mailto:bob@example.com\r\n\
+ATTENDEE;PARTSTAT=ACCEPTED;CN=alice@example.com:mailto:alice@example.com\r\n\
+SEQUENCE:2\r\n\
+SUMMARY:Planning\\, Q3\r\n\
+LOCATION:Room 4\\; 2nd floor\r\n\
+DESCRIPTION:Agenda:\\n1. Budget\r\n\
+RRULE:FREQ=WEEKLY;BYDAY=TU\r\n\
+BEGIN:VALARM\r\n\
+ACTION:DISPLAY\r\n\
+DESCRIPTION:This is an event reminder\r\n\
+END:VALARM\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    #[test]
+    fn test_parse_google_invite() {
+        let invite = CalendarInvite::parse(INVITE).unwrap();
+        assert_eq!(invite.method.as_deref(), Some("REQUEST"));
+        assert_eq!(invite.uid, "abc123@google.com");
+        assert_eq!(invite.sequence, 2);
+        assert_eq!(invite.summary.as_deref(), Some("Planning, Q3"));
+        assert_eq!(invite.location.as_deref(), Some("Room 4; 2nd floor"));
+        assert_eq!(invite.description.as_deref(), Some("Agenda:\n1. Budget"));
+        assert!(invite.recurring);
+        assert!(invite.accepts_replies());
+
+        let organizer = invite.organizer.as_ref().unwrap();
+        assert_eq!(organizer.email, "alice@example.com");
+        assert_eq!(organizer.name.as_deref(), Some("Alice Smith"));
+
+        assert_eq!(invite.attendees.len(), 2);
+        assert_eq!(invite.attendees[0].name.as_deref(), Some("Bob Jones"));
+        assert_eq!(invite.attendees[0].status.as_deref(), Some("NEEDS-ACTION"));
+
+        // 09:00 in Berlin summer time
+        let start = invite.start.as_ref().unwrap();
+        assert_eq!(start.value, "2025-06-10T07:00:00Z");
+        assert_eq!(start.time_zone.as_deref(), Some("Europe/Berlin"));
+        assert!(!start.all_day);
+
+        let me = invite
+            .find_attendee(&[
+                "other@example.com".to_string(),
+                "BOB@example.com".to_string(),
+            ])
+            .unwrap();
+        assert_eq!(me.email, "bob@example.com");
+    }
+
+    #[test]
+    fn test_event_time_forms() {
+        let time = |line: &str| event_time(&parse_property(line).unwrap()).unwrap();
+        let all_day = time("DTSTART;VALUE=DATE:20250704");
+        assert_eq!(all_day.value, "2025-07-04");
+        assert!(all_day.all_day);
+        assert_eq!(
+            time("DTSTART:20250704T150000Z").value,
+            "2025-07-04T15:00:00Z"
+        );
+        // Outlook's Windows zone names aren't IANA ones; the time stays local
+        let windows = time("DTSTART;TZID=\"Pacific Standard Time\":20250704T090000");
+        assert_eq!(windows.value, "2025-07-04T09:00:00");
+        assert_eq!(windows.time_zone.as_deref(), Some("Pacific Standard Time"));
+        assert_eq!(
+            CalendarInvite::parse("BEGIN:VCALENDAR\r\nEND:VCALENDAR"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reply_is_an_itip_reply() {
+        let invite = CalendarInvite::parse(INVITE).unwrap();
+        let me = invite.attendees[0].clone();
+        let now = Utc.with_ymd_and_hms(2025, 6, 2, 8, 30, 0).unwrap();
+        let (subject, body, attachment) =
+            invite.reply_parts(&me, RsvpResponse::Tentative, Some("Might be late"), now);
+
+        assert_eq!(subject, "Tentatively Accepted: Planning, Q3");
+        assert_eq!(
+            body,
+            "Bob Jones has tentatively accepted this invitation.\n\nMight be late"
+        );
+        assert_eq!(attachment.mime_type, REPLY_MIME_TYPE);
+
+        let ics = String::from_utf8(attachment.data).unwrap();
+        assert!(ics.lines().all(|line| line.len() <= FOLD_AT + 1));
+        let reply = CalendarInvite::parse(&ics).unwrap();
+        assert_eq!(reply.method.as_deref(), Some("REPLY"));
+        assert_eq!(reply.uid, invite.uid);
+        assert_eq!(reply.sequence, 2);
+        assert_eq!(reply.start, invite.start);
+        assert_eq!(reply.organizer, invite.organizer);
+        assert_eq!(
+            reply.attendees,
+            vec![Attendee {
+                email: "bob@example.com".to_string(),
+                name: Some("Bob Jones".to_string()),
+                status: Some("TENTATIVE".to_string()),
+            }]
+        );
+        assert!(ics.contains("DTSTAMP:20250602T083000Z\r\n"));
+        assert!(!reply.accepts_replies());
+    }
+}
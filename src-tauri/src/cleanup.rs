@@ -0,0 +1,191 @@
+use crate::bulk::BulkAction;
+use crate::gmail_client::{GmailClient, GmailMessage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Unread promotional/update mail older than this is considered stale
+const OLD_NEWSLETTERS_QUERY: &str =
+    "in:inbox is:unread older_than:30d (category:promotions OR category:updates)";
+
+/// Inbox mail carrying big attachments that nobody has touched for half a year
+const LARGE_ATTACHMENTS_QUERY: &str = "in:inbox has:attachment larger:10M older_than:6m";
+
+/// Pool of old unread inbox mail sampled to find senders the user never opens
+const NEVER_OPENED_SAMPLE_QUERY: &str = "in:inbox is:unread older_than:30d";
+const NEVER_OPENED_SAMPLE_SIZE: usize = 200;
+
+/// A sender needs at least this many unread messages in the sample to be suggested
+const NEVER_OPENED_MIN_MESSAGES: usize = 5;
+const NEVER_OPENED_MAX_SENDERS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupKind {
+    OldUnreadNewsletters,
+    LargeAttachments,
+    NeverOpenedSender,
+}
+
+/// A suggested cleanup step; run it by passing `query` and `action` to `bulk_action_by_query`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupProposal {
+    pub id: String,
+    pub kind: CleanupKind,
+    pub title: String,
+    pub query: String,
+    pub action: BulkAction,
+    /// Gmail's result size estimate for the query
+    pub estimated_count: u32,
+}
+
+/// Inspect the mailbox and propose cleanup actions, skipping any that would match nothing
+pub async fn analyze(
+    gmail_client: &GmailClient,
+) -> Result<Vec<CleanupProposal>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut proposals = Vec::new();
+
+    let fixed = [
+        (
+            "old_unread_newsletters",
+            CleanupKind::OldUnreadNewsletters,
+            "Archive unread newsletters older than 30 days".to_string(),
+            OLD_NEWSLETTERS_QUERY.to_string(),
+        ),
+        (
+            "large_attachments",
+            CleanupKind::LargeAttachments,
+            "Archive old messages with attachments over 10 MB".to_string(),
+            LARGE_ATTACHMENTS_QUERY.to_string(),
+        ),
+    ];
+
+    for (id, kind, title, query) in fixed {
+        let estimated_count = estimate_count(gmail_client, &query).await?;
+        if estimated_count > 0 {
+            proposals.push(CleanupProposal {
+                id: id.to_string(),
+                kind,
+                title,
+                query,
+                action: BulkAction::Archive,
+                estimated_count,
+            });
+        }
+    }
+
+    let sample_ids = gmail_client
+        .list_all_message_ids(NEVER_OPENED_SAMPLE_QUERY, NEVER_OPENED_SAMPLE_SIZE)
+        .await?;
+
+    let mut sample = Vec::new();
+    for chunk in sample_ids.chunks(100) {
+        sample.extend(gmail_client.get_messages_metadata_batch(chunk).await?);
+    }
+
+    for (sender, _) in
+        frequent_unread_senders(&sample, NEVER_OPENED_MIN_MESSAGES, NEVER_OPENED_MAX_SENDERS)
+    {
+        // Only suggest senders with no read mail at all
+        let read_query = format!("from:{} -is:unread", sender);
+        let read = gmail_client
+            .list_messages(Some(1), None, Some(&read_query))
+            .await?;
+        if read.messages.is_some_and(|m| !m.is_empty()) {
+            continue;
+        }
+
+        let query = format!("in:inbox from:{}", sender);
+        let estimated_count = estimate_count(gmail_client, &query).await?;
+        proposals.push(CleanupProposal {
+            id: format!("never_opened:{}", sender),
+            kind: CleanupKind::NeverOpenedSender,
+            title: format!("Archive mail from {} (never opened)", sender),
+            query,
+            action: BulkAction::Archive,
+            estimated_count,
+        });
+    }
+
+    Ok(proposals)
+}
+
+async fn estimate_count(
+    gmail_client: &GmailClient,
+    query: &str,
+) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+    let response = gmail_client
+        .list_messages(Some(1), None, Some(query))
+        .await?;
+    Ok(response.result_size_estimate.unwrap_or(0))
+}
+
+/// Senders with at least `min_messages` unread messages, most frequent first
+pub fn frequent_unread_senders(
+    messages: &[GmailMessage],
+    min_messages: usize,
+    limit: usize,
+) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for message in messages.iter().filter(|m| m.is_unread()) {
+        *counts
+            .entry(message.get_from_address().to_lowercase())
+            .or_insert(0) += 1;
+    }
+
+    let mut senders: Vec<(String, usize)> = counts
+        .into_iter()
+        .filter(|(_, count)| *count >= min_messages)
+        .collect();
+    senders.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    senders.truncate(limit);
+    senders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::{MessageHeader, MessagePayload};
+
+    fn unread_from(id: usize, from: &str) -> GmailMessage {
+        GmailMessage {
+            id: format!("msg{}", id),
+            thread_id: format!("thread{}", id),
+            label_ids: Some(vec!["INBOX".to_string(), "UNREAD".to_string()]),
+            payload: Some(MessagePayload {
+                headers: Some(vec![MessageHeader {
+                    name: "From".to_string(),
+                    value: from.to_string(),
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_frequent_unread_senders_groups_by_address() {
+        let mut messages = Vec::new();
+        for i in 0..3 {
+            messages.push(unread_from(i, "Deals <deals@shop.example>"));
+        }
+        messages.push(unread_from(3, "deals@SHOP.example"));
+        messages.push(unread_from(4, "Friend <friend@example.com>"));
+
+        let senders = frequent_unread_senders(&messages, 2, 10);
+        assert_eq!(senders, vec![("deals@shop.example".to_string(), 4)]);
+    }
+
+    #[test]
+    fn test_frequent_unread_senders_respects_limit() {
+        let mut messages = Vec::new();
+        for (i, sender) in ["a@x.com", "b@x.com", "c@x.com"].iter().enumerate() {
+            for j in 0..=i {
+                messages.push(unread_from(i * 10 + j, sender));
+            }
+        }
+
+        let senders = frequent_unread_senders(&messages, 1, 2);
+        assert_eq!(senders.len(), 2);
+        assert_eq!(senders[0].0, "c@x.com");
+    }
+}
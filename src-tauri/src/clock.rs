@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use std::fmt::Debug;
+use std::time::Instant;
+
+/// Where time-dependent code gets the time, so tests can move it forward
+/// instead of sleeping
+pub trait Clock: Debug + Send + Sync {
+    /// Monotonic time, for measuring windows and intervals
+    fn instant(&self) -> Instant;
+
+    /// Wall-clock time, for timestamps and schedules
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Wall-clock time as a Unix timestamp (seconds)
+    fn unix_now(&self) -> u64 {
+        self.now().timestamp().max(0) as u64
+    }
+}
+
+/// The real clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
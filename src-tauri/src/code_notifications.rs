@@ -0,0 +1,395 @@
+use crate::gmail_client::GmailMessage;
+use serde::{Deserialize, Serialize};
+
+/// Gmail search that finds notification mail from GitHub and GitLab.com;
+/// self-hosted GitLab senders vary, so callers can pass their own query
+pub const DEFAULT_QUERY: &str = "from:notifications@github.com OR from:gitlab.com";
+
+/// Most recent notifications `get_code_notifications` groups
+pub const MAX_MESSAGES: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeHost {
+    GitHub,
+    GitLab,
+}
+
+/// Why the host sent the notification, from X-GitHub-Reason or
+/// X-GitLab-NotificationReason
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationReason {
+    ReviewRequested,
+    /// The user or one of their teams was @mentioned
+    Mention,
+    Assign,
+    /// Activity on something the user opened
+    Author,
+    Comment,
+    /// Workflow and pipeline runs
+    CiActivity,
+    StateChange,
+    SecurityAlert,
+    /// The user's own activity, when they've asked to be copied
+    YourActivity,
+    Subscribed,
+    Other,
+}
+
+impl NotificationReason {
+    fn from_github(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "review_requested" => NotificationReason::ReviewRequested,
+            "mention" | "team_mention" => NotificationReason::Mention,
+            "assign" => NotificationReason::Assign,
+            "author" => NotificationReason::Author,
+            "comment" => NotificationReason::Comment,
+            "ci_activity" => NotificationReason::CiActivity,
+            "state_change" => NotificationReason::StateChange,
+            "security_alert" => NotificationReason::SecurityAlert,
+            "your_activity" => NotificationReason::YourActivity,
+            "subscribed" | "manual" => NotificationReason::Subscribed,
+            _ => NotificationReason::Other,
+        }
+    }
+
+    fn from_gitlab(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "review_requested" | "approver" => NotificationReason::ReviewRequested,
+            "mentioned" => NotificationReason::Mention,
+            "assigned" => NotificationReason::Assign,
+            "own_activity" => NotificationReason::YourActivity,
+            "subscribed" => NotificationReason::Subscribed,
+            _ => NotificationReason::Other,
+        }
+    }
+}
+
+/// What a notification is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeItemKind {
+    /// A GitHub pull request or GitLab merge request
+    PullRequest,
+    Issue,
+    /// A GitHub Actions run or GitLab pipeline
+    CiRun,
+    Other,
+}
+
+/// One notification email, parsed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CodeNotification {
+    pub message_id: String,
+    pub host: CodeHost,
+    /// "owner/repo" or "group/project"
+    pub repository: String,
+    pub kind: CodeItemKind,
+    /// PR, issue or run number; None when the mail doesn't say
+    pub number: Option<u64>,
+    pub reason: NotificationReason,
+}
+
+impl CodeNotification {
+    /// Notifications with the same key are about the same PR, issue or run
+    pub fn thread_key(&self) -> String {
+        let host = match self.host {
+            CodeHost::GitHub => "github",
+            CodeHost::GitLab => "gitlab",
+        };
+        match self.number {
+            Some(number) => format!("{}:{}:{:?}:{}", host, self.repository, self.kind, number),
+            None => format!("{}:{}:{}", host, self.repository, self.message_id),
+        }
+    }
+}
+
+/// Recognizes one host's notification mail
+pub trait AlertParser: Sync {
+    fn parse(&self, message: &GmailMessage) -> Option<CodeNotification>;
+}
+
+/// Every known parser, tried in order; add new hosts here
+pub static PARSERS: &[&dyn AlertParser] = &[&GitHubParser, &GitLabParser];
+
+/// The first parser that recognizes `message`
+pub fn parse(message: &GmailMessage) -> Option<CodeNotification> {
+    PARSERS.iter().find_map(|parser| parser.parse(message))
+}
+
+/// GitHub marks its mail with X-GitHub-Reason and threads it with message
+/// ids such as `<owner/repo/pull/12/c345@github.com>`
+pub struct GitHubParser;
+
+impl AlertParser for GitHubParser {
+    fn parse(&self, message: &GmailMessage) -> Option<CodeNotification> {
+        let reason = NotificationReason::from_github(&message.get_header("X-GitHub-Reason")?);
+
+        // Replies point at the thread's root; the root names itself
+        let reference = message
+            .get_in_reply_to()
+            .or_else(|| message.get_message_id())?;
+        let reference = reference
+            .trim()
+            .trim_start_matches('<')
+            .trim_end_matches('>');
+        let (path, domain) = reference.rsplit_once('@')?;
+        if !domain.eq_ignore_ascii_case("github.com") {
+            return None;
+        }
+
+        let segments: Vec<&str> = path.split('/').collect();
+        let (owner, repo) = (segments.first()?, segments.get(1)?);
+        let (kind, number) = match segments.get(2..) {
+            Some(["pull", number, ..]) => (CodeItemKind::PullRequest, number.parse().ok()),
+            Some(["issues", number, ..]) => (CodeItemKind::Issue, number.parse().ok()),
+            Some(["actions", "runs", number, ..]) => (CodeItemKind::CiRun, number.parse().ok()),
+            _ => (CodeItemKind::Other, None),
+        };
+        let kind = match (kind, reason) {
+            (CodeItemKind::Other, NotificationReason::CiActivity) => CodeItemKind::CiRun,
+            (kind, _) => kind,
+        };
+
+        Some(CodeNotification {
+            message_id: message.id.clone(),
+            host: CodeHost::GitHub,
+            repository: format!("{}/{}", owner, repo),
+            kind,
+            number,
+            reason,
+        })
+    }
+}
+
+/// GitLab describes its mail in X-GitLab-* headers
+pub struct GitLabParser;
+
+impl AlertParser for GitLabParser {
+    fn parse(&self, message: &GmailMessage) -> Option<CodeNotification> {
+        let repository = message.get_header("X-GitLab-Project-Path")?;
+        let number = |name: &str| {
+            message
+                .get_header(name)
+                .and_then(|value| value.trim().parse().ok())
+        };
+
+        let (kind, number) = if let Some(iid) = number("X-GitLab-MergeRequest-IID") {
+            (CodeItemKind::PullRequest, Some(iid))
+        } else if let Some(iid) = number("X-GitLab-Issue-IID") {
+            (CodeItemKind::Issue, Some(iid))
+        } else if let Some(id) = number("X-GitLab-Pipeline-Id") {
+            (CodeItemKind::CiRun, Some(id))
+        } else {
+            (CodeItemKind::Other, None)
+        };
+        let reason = match (message.get_header("X-GitLab-NotificationReason"), kind) {
+            (Some(value), _) if !value.trim().is_empty() => NotificationReason::from_gitlab(&value),
+            (_, CodeItemKind::CiRun) => NotificationReason::CiActivity,
+            _ => NotificationReason::Subscribed,
+        };
+
+        Some(CodeNotification {
+            message_id: message.id.clone(),
+            host: CodeHost::GitLab,
+            repository: repository.trim().to_string(),
+            kind,
+            number,
+            reason,
+        })
+    }
+}
+
+/// Notifications about one PR, issue or run, newest message first
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeThread {
+    pub key: String,
+    pub host: CodeHost,
+    pub repository: String,
+    pub kind: CodeItemKind,
+    pub number: Option<u64>,
+    /// Subject of the newest notification
+    pub subject: String,
+    /// Every reason seen in the thread, first seen first
+    pub reasons: Vec<NotificationReason>,
+    pub message_ids: Vec<String>,
+    pub unread: bool,
+    /// Gmail receive time of the newest message, epoch milliseconds
+    pub latest_at: i64,
+}
+
+/// Parse `messages` and group them by PR, issue or run, newest thread
+/// first. With `reason` set only threads that include it are kept, e.g.
+/// review requests only.
+pub fn group_threads(
+    messages: &[GmailMessage],
+    reason: Option<NotificationReason>,
+) -> Vec<CodeThread> {
+    let mut sorted: Vec<&GmailMessage> = messages.iter().collect();
+    sorted.sort_by_key(|m| std::cmp::Reverse(received_at(m)));
+
+    let mut threads: Vec<CodeThread> = Vec::new();
+    for message in sorted {
+        let Some(notification) = parse(message) else {
+            continue;
+        };
+        let key = notification.thread_key();
+        let index = match threads.iter().position(|t| t.key == key) {
+            Some(index) => index,
+            None => {
+                threads.push(CodeThread {
+                    key,
+                    host: notification.host,
+                    repository: notification.repository.clone(),
+                    kind: notification.kind,
+                    number: notification.number,
+                    subject: message.get_subject(),
+                    reasons: Vec::new(),
+                    message_ids: Vec::new(),
+                    unread: false,
+                    latest_at: received_at(message),
+                });
+                threads.len() - 1
+            }
+        };
+        let thread = &mut threads[index];
+        if !thread.reasons.contains(&notification.reason) {
+            thread.reasons.push(notification.reason);
+        }
+        thread.message_ids.push(message.id.clone());
+        thread.unread |= message.is_unread();
+    }
+
+    if let Some(reason) = reason {
+        threads.retain(|t| t.reasons.contains(&reason));
+    }
+    threads
+}
+
+fn received_at(message: &GmailMessage) -> i64 {
+    message
+        .internal_date
+        .as_deref()
+        .and_then(|d| d.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::{MessageHeader, MessagePayload};
+
+    fn message(id: &str, at: i64, headers: &[(&str, &str)]) -> GmailMessage {
+        GmailMessage {
+            id: id.to_string(),
+            thread_id: id.to_string(),
+            label_ids: Some(vec!["INBOX".to_string()]),
+            payload: Some(MessagePayload {
+                headers: Some(
+                    headers
+                        .iter()
+                        .map(|(name, value)| MessageHeader {
+                            name: name.to_string(),
+                            value: value.to_string(),
+                        })
+                        .collect(),
+                ),
+                ..Default::default()
+            }),
+            internal_date: Some(at.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_github_and_gitlab_headers_are_parsed() {
+        let review = message(
+            "m1",
+            1,
+            &[
+                ("X-GitHub-Reason", "review_requested"),
+                ("Message-ID", "<acme/widgets/pull/42@github.com>"),
+            ],
+        );
+        assert_eq!(
+            parse(&review),
+            Some(CodeNotification {
+                message_id: "m1".to_string(),
+                host: CodeHost::GitHub,
+                repository: "acme/widgets".to_string(),
+                kind: CodeItemKind::PullRequest,
+                number: Some(42),
+                reason: NotificationReason::ReviewRequested,
+            })
+        );
+
+        let pipeline = message(
+            "m2",
+            2,
+            &[
+                ("X-GitLab-Project-Path", "infra/deploy"),
+                ("X-GitLab-Pipeline-Id", "9001"),
+            ],
+        );
+        let parsed = parse(&pipeline).unwrap();
+        assert_eq!(parsed.kind, CodeItemKind::CiRun);
+        assert_eq!(parsed.reason, NotificationReason::CiActivity);
+
+        let unrelated = message("m3", 3, &[("Message-ID", "<a/b/pull/1@github.com>")]);
+        assert_eq!(parse(&unrelated), None);
+    }
+
+    #[test]
+    fn test_notifications_are_threaded_and_filtered() {
+        let messages = vec![
+            message(
+                "m1",
+                10,
+                &[
+                    ("X-GitHub-Reason", "review_requested"),
+                    ("Subject", "[acme/widgets] Add caching (PR #42)"),
+                    ("Message-ID", "<acme/widgets/pull/42@github.com>"),
+                ],
+            ),
+            message(
+                "m2",
+                30,
+                &[
+                    ("X-GitHub-Reason", "mention"),
+                    ("Subject", "Re: [acme/widgets] Add caching (PR #42)"),
+                    ("Message-ID", "<acme/widgets/pull/42/c7@github.com>"),
+                    ("In-Reply-To", "<acme/widgets/pull/42@github.com>"),
+                ],
+            ),
+            message(
+                "m3",
+                20,
+                &[
+                    ("X-GitLab-Project-Path", "infra/deploy"),
+                    ("X-GitLab-MergeRequest-IID", "7"),
+                    ("X-GitLab-NotificationReason", "mentioned"),
+                ],
+            ),
+        ];
+
+        let threads = group_threads(&messages, None);
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].message_ids, vec!["m2", "m1"]);
+        assert_eq!(
+            threads[0].subject,
+            "Re: [acme/widgets] Add caching (PR #42)"
+        );
+        assert_eq!(
+            threads[0].reasons,
+            vec![
+                NotificationReason::Mention,
+                NotificationReason::ReviewRequested
+            ]
+        );
+
+        let reviews = group_threads(&messages, Some(NotificationReason::ReviewRequested));
+        assert_eq!(reviews.len(), 1);
+        assert_eq!(reviews[0].number, Some(42));
+    }
+}
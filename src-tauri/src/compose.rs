@@ -0,0 +1,825 @@
+use crate::account::AccountScoped;
+use crate::email::Email;
+use crate::gmail_client::{
+    check_attachment_size, GmailDraftMessage, GmailMessage, OutgoingAttachment, OutgoingEmail,
+};
+use crate::local_store::{app_data_path, load_json, save_json};
+use crate::no_reply::NoReplyWarning;
+use crate::outbound_rules::OutboundRuleWarning;
+use crate::send_limits::split_recipients;
+use serde::{Deserialize, Serialize};
+
+const COMPOSE_FILE: &str = "compose_sessions.json";
+
+/// A file the user attached while composing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComposeAttachment {
+    pub path: String,
+    pub filename: String,
+    pub mime_type: String,
+    pub size: u64,
+}
+
+impl ComposeAttachment {
+    /// Describe a file on disk for attaching; its contents are read at send time
+    pub fn from_path(path: &str) -> Result<Self, String> {
+        let metadata =
+            std::fs::metadata(path).map_err(|e| format!("Cannot attach {}: {}", path, e))?;
+        if !metadata.is_file() {
+            return Err(format!("Cannot attach {}: not a file", path));
+        }
+
+        let filename = std::path::Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "attachment".to_string());
+
+        Ok(ComposeAttachment {
+            path: path.to_string(),
+            mime_type: guess_mime_type(&filename).to_string(),
+            filename,
+            size: metadata.len(),
+        })
+    }
+
+    fn read(&self) -> Result<OutgoingAttachment, String> {
+        let data = std::fs::read(&self.path)
+            .map_err(|e| format!("Cannot read attachment {}: {}", self.filename, e))?;
+        Ok(OutgoingAttachment {
+            filename: self.filename.clone(),
+            mime_type: self.mime_type.clone(),
+            data,
+        })
+    }
+}
+
+/// Content type for an attached file from its extension
+pub fn guess_mime_type(filename: &str) -> &'static str {
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "txt" | "log" => "text/plain",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "ics" => "text/calendar",
+        "zip" => "application/zip",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Read every file in `paths` for sending, checking the total against Gmail's limit
+pub fn read_attachments(paths: &[String]) -> Result<Vec<OutgoingAttachment>, String> {
+    let attachments = paths
+        .iter()
+        .map(|path| ComposeAttachment::from_path(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    check_attachment_size(attachments.iter().map(|a| a.size).sum())?;
+    attachments.iter().map(ComposeAttachment::read).collect()
+}
+
+/// Threading details for a reply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplyContext {
+    pub original_message_id: String,
+    pub thread_id: String,
+    pub in_reply_to: Option<String>,
+    pub references: Option<String>,
+    /// Thread messages the user had in front of them when the reply started
+    #[serde(default)]
+    pub seen_message_ids: Vec<String>,
+}
+
+impl ReplyContext {
+    pub fn from_message(original: &GmailMessage) -> Self {
+        let message_id = original.get_message_id();
+        let references = original.get_references();
+
+        // Build references chain for proper threading
+        let references = match (message_id.as_ref(), references) {
+            (Some(msg_id), Some(refs)) => Some(format!("{} {}", refs, msg_id)),
+            (Some(msg_id), None) => Some(msg_id.clone()),
+            (None, refs) => refs,
+        };
+
+        ReplyContext {
+            original_message_id: original.id.clone(),
+            thread_id: original.thread_id.clone(),
+            in_reply_to: message_id,
+            references,
+            seen_message_ids: vec![original.id.clone()],
+        }
+    }
+
+    /// Mark every message currently in the thread as seen
+    pub fn mark_thread_seen(&mut self, thread: &[GmailMessage]) {
+        for message in thread {
+            if !self.seen_message_ids.contains(&message.id) {
+                self.seen_message_ids.push(message.id.clone());
+            }
+        }
+    }
+
+    /// Messages that joined the thread after the reply started. Drafts are
+    /// skipped since autosave puts this reply's own draft in the thread.
+    pub fn newer_messages<'a>(&self, thread: &'a [GmailMessage]) -> Vec<&'a GmailMessage> {
+        thread
+            .iter()
+            .filter(|m| !self.seen_message_ids.contains(&m.id))
+            .filter(|m| {
+                !m.label_ids
+                    .as_ref()
+                    .is_some_and(|labels| labels.iter().any(|l| l == "DRAFT"))
+            })
+            .collect()
+    }
+}
+
+/// A saved Gmail draft, as listed for resuming
+#[derive(Debug, Clone, Serialize)]
+pub struct DraftSummary {
+    pub draft_id: String,
+    pub message_id: String,
+    pub thread_id: String,
+    pub to: Vec<String>,
+    pub subject: String,
+    pub snippet: String,
+    /// Compose session already editing this draft, if any
+    pub session_id: Option<String>,
+}
+
+impl DraftSummary {
+    pub fn new(draft_id: &str, message: &GmailMessage, session_id: Option<String>) -> Self {
+        DraftSummary {
+            draft_id: draft_id.to_string(),
+            message_id: message.id.clone(),
+            thread_id: message.thread_id.clone(),
+            to: split_header(message.get_to()),
+            subject: message.get_subject(),
+            snippet: message.snippet.clone(),
+            session_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DraftPage {
+    pub drafts: Vec<DraftSummary>,
+    pub next_page_token: Option<String>,
+}
+
+/// Entries of a comma-separated address header, display names kept
+fn split_header(value: Option<String>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .collect()
+}
+
+/// Returned instead of sending when the thread moved on while the user was writing
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleReplyWarning {
+    pub session_id: String,
+    pub new_messages: Vec<Email>,
+}
+
+/// Outcome of `send_compose_session`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SendComposeResult {
+    Sent {
+        message_id: String,
+    },
+    NewerMessages(StaleReplyWarning),
+    /// Addressed to a no-reply address; nothing was sent
+    NoReply(NoReplyWarning),
+    /// Matched a warning outbound rule; nothing was sent
+    OutboundRules(OutboundRuleWarning),
+    /// Over a sending cap; the session stays queued and goes out at `send_at`
+    Deferred {
+        send_at: u64,
+        reason: String,
+    },
+}
+
+/// Reply prefixes mail clients put in front of subjects, lowercased: English,
+/// German (AW), Nordic (SV, VS), Portuguese/Spanish (RES), Dutch (Antw),
+/// French (Ref), Italian (Rif), Polish (Odp) and Turkish (Ynt)
+const REPLY_PREFIXES: &[&str] = &[
+    "re", "aw", "sv", "vs", "res", "antw", "ref", "rif", "odp", "ynt",
+];
+
+/// Split one reply prefix such as "AW:" or "Re[2]:" off the front of a subject,
+/// returning the prefix word as written and the rest
+fn split_reply_prefix(subject: &str) -> Option<(&str, &str)> {
+    let (head, rest) = subject.trim_start().split_once(':')?;
+
+    // Some clients count replies, as in "Re[2]:"
+    let word = match head.split_once('[') {
+        Some((word, counter)) => {
+            let digits = counter.strip_suffix(']')?;
+            if !digits.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            word
+        }
+        None => head,
+    };
+
+    REPLY_PREFIXES
+        .contains(&word.to_lowercase().as_str())
+        .then(|| (word, rest.trim_start()))
+}
+
+/// The subject with every leading reply prefix removed, e.g. "AW: Re: Termin" -> "Termin"
+pub fn strip_reply_prefixes(subject: &str) -> &str {
+    let mut rest = subject.trim_start();
+    while let Some((_, stripped)) = split_reply_prefix(rest) {
+        rest = stripped;
+    }
+    rest
+}
+
+/// Subject for a reply. A subject that is already a reply keeps its first
+/// prefix as written (so "AW: Termin" stays German) with the stack collapsed;
+/// anything else gets "Re: ".
+pub fn reply_subject(original_subject: &str) -> String {
+    let base = strip_reply_prefixes(original_subject);
+    match split_reply_prefix(original_subject) {
+        Some((prefix, _)) => format!("{}: {}", prefix, base),
+        None => format!("Re: {}", base),
+    }
+}
+
+/// Subject for a forward: "Fwd: " unless it is already a forward
+pub fn forward_subject(original_subject: &str) -> String {
+    let trimmed = original_subject.trim_start();
+    let already = trimmed
+        .split_once(':')
+        .is_some_and(|(prefix, _)| ["fwd", "fw"].contains(&prefix.trim().to_lowercase().as_str()));
+    if already {
+        trimmed.to_string()
+    } else {
+        format!("Fwd: {}", trimmed)
+    }
+}
+
+/// Optional parts of a forward; anything left out takes its default
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ForwardOptions {
+    /// Written above the forwarded message
+    pub note: Option<String>,
+    /// Forward the original's attachments too; on unless set to false
+    pub include_attachments: Option<bool>,
+    /// Send even though an outbound rule raised a warning
+    pub ignore_outbound_warnings: Option<bool>,
+}
+
+/// Plain-text forward body: the sender's note, then the original's headers
+/// and text in Gmail's "Forwarded message" layout
+pub fn forward_body(note: &str, original: &GmailMessage) -> String {
+    let mut body = String::new();
+    if !note.trim().is_empty() {
+        body.push_str(note.trim_end());
+        body.push_str("\r\n\r\n");
+    }
+    body.push_str("---------- Forwarded message ---------\r\n");
+    body.push_str(&format!("From: {}\r\n", original.get_from()));
+    if let Some(date) = original.get_date() {
+        body.push_str(&format!("Date: {}\r\n", date));
+    }
+    body.push_str(&format!("Subject: {}\r\n", original.get_subject()));
+    if let Some(to) = original.get_to() {
+        body.push_str(&format!("To: {}\r\n", to));
+    }
+    if let Some(cc) = original.get_cc() {
+        body.push_str(&format!("Cc: {}\r\n", cc));
+    }
+    body.push_str("\r\n");
+    body.push_str(&original.get_body_text());
+    body
+}
+
+/// A message being written. Sessions live in the backend so every window sees
+/// the same state and nothing is lost when a window closes or the app crashes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComposeSession {
+    pub id: String,
+    /// Send-as alias picked by an alias rule; None sends from the primary address
+    #[serde(default)]
+    pub from: Option<String>,
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+    pub subject: String,
+    pub body: String,
+    /// Files read from disk whenever the draft is saved or the message sent
+    pub attachments: Vec<ComposeAttachment>,
+    pub reply_context: Option<ReplyContext>,
+    /// Gmail draft backing this session once it has been autosaved
+    pub draft_id: Option<String>,
+    /// Bumped on every edit; autosave runs while it is ahead of `saved_revision`
+    pub revision: u64,
+    pub saved_revision: u64,
+    /// Queued behind a sending cap until this time (unix seconds)
+    #[serde(default)]
+    pub deferred_until: Option<u64>,
+}
+
+/// Fields to change on a session; anything left out stays as it is
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ComposeUpdate {
+    pub to: Option<Vec<String>>,
+    pub cc: Option<Vec<String>>,
+    pub bcc: Option<Vec<String>>,
+    pub subject: Option<String>,
+    pub body: Option<String>,
+    pub attachments: Option<Vec<ComposeAttachment>>,
+}
+
+impl ComposeSession {
+    /// Start a reply to `original`, addressed to its sender
+    pub fn reply_to(id: String, original: &GmailMessage) -> Self {
+        ComposeSession {
+            id,
+            to: vec![original.get_from_address()],
+            subject: reply_subject(&original.get_subject()),
+            reply_context: Some(ReplyContext::from_message(original)),
+            ..Default::default()
+        }
+    }
+
+    /// Pick a saved Gmail draft back up. A reply draft gets its reply context
+    /// from `thread`, which should hold the thread's messages as they are now.
+    pub fn from_draft(id: String, draft: &GmailDraftMessage, thread: &[GmailMessage]) -> Self {
+        let message = &draft.message;
+        let reply_context = message.get_in_reply_to().map(|in_reply_to| {
+            let original = thread
+                .iter()
+                .find(|m| m.get_message_id().as_deref() == Some(in_reply_to.as_str()));
+            let mut context = ReplyContext {
+                original_message_id: original.map(|m| m.id.clone()).unwrap_or_default(),
+                thread_id: message.thread_id.clone(),
+                in_reply_to: Some(in_reply_to.clone()),
+                references: message.get_references(),
+                seen_message_ids: Vec::new(),
+            };
+            context.mark_thread_seen(thread);
+            context
+        });
+
+        ComposeSession {
+            id,
+            to: split_header(message.get_to()),
+            cc: split_header(message.get_cc()),
+            bcc: split_header(message.get_bcc()),
+            // Unlike get_subject, a draft without a subject stays without one
+            subject: message.get_subject_header().unwrap_or_default(),
+            body: message
+                .get_body_html()
+                .unwrap_or_else(|| message.get_body_text()),
+            reply_context,
+            draft_id: Some(draft.id.clone()),
+            ..Default::default()
+        }
+    }
+
+    pub fn apply(&mut self, update: ComposeUpdate) {
+        if let Some(to) = update.to {
+            self.to = to;
+        }
+        if let Some(cc) = update.cc {
+            self.cc = cc;
+        }
+        if let Some(bcc) = update.bcc {
+            self.bcc = bcc;
+        }
+        if let Some(subject) = update.subject {
+            self.subject = subject;
+        }
+        if let Some(body) = update.body {
+            self.body = body;
+        }
+        if let Some(attachments) = update.attachments {
+            self.attachments = attachments;
+        }
+        self.revision += 1;
+    }
+
+    pub fn needs_autosave(&self) -> bool {
+        self.revision > self.saved_revision
+    }
+
+    pub fn thread_id(&self) -> Option<&str> {
+        self.reply_context.as_ref().map(|c| c.thread_id.as_str())
+    }
+
+    /// Every address the message goes to, for send limits
+    pub fn recipients(&self) -> Vec<String> {
+        self.to
+            .iter()
+            .chain(&self.cc)
+            .chain(&self.bcc)
+            .flat_map(|r| split_recipients(r))
+            .collect()
+    }
+
+    pub fn attachment_bytes(&self) -> u64 {
+        self.attachments.iter().map(|a| a.size).sum()
+    }
+
+    /// The message as it goes to Gmail, with attachment files read from disk
+    pub fn to_outgoing(&self) -> Result<OutgoingEmail, String> {
+        let join = |addresses: &[String]| {
+            let joined = addresses.join(", ");
+            (!joined.is_empty()).then_some(joined)
+        };
+
+        Ok(OutgoingEmail {
+            from: self.from.clone(),
+            to: self.to.join(", "),
+            cc: join(&self.cc),
+            bcc: join(&self.bcc),
+            subject: self.subject.clone(),
+            body: self.body.clone(),
+            in_reply_to: self
+                .reply_context
+                .as_ref()
+                .and_then(|c| c.in_reply_to.clone()),
+            references: self
+                .reply_context
+                .as_ref()
+                .and_then(|c| c.references.clone()),
+            attachments: self
+                .attachments
+                .iter()
+                .map(ComposeAttachment::read)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// Open compose sessions, persisted on every change
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ComposeStore {
+    sessions: Vec<ComposeSession>,
+    /// Drafts and scheduled sends belong to this account
+    #[serde(default)]
+    account_id: Option<String>,
+}
+
+impl ComposeStore {
+    pub fn load() -> Self {
+        load_json(&app_data_path(COMPOSE_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(COMPOSE_FILE), self)
+    }
+
+    pub fn new_session_id(&self) -> String {
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+
+        let mut id = format!("compose_{}", millis);
+        let mut suffix = 1;
+        while self.get(&id).is_some() {
+            id = format!("compose_{}_{}", millis, suffix);
+            suffix += 1;
+        }
+        id
+    }
+
+    pub fn sessions(&self) -> &[ComposeSession] {
+        &self.sessions
+    }
+
+    pub fn insert(&mut self, session: ComposeSession) {
+        self.sessions.push(session);
+    }
+
+    pub fn get(&self, session_id: &str) -> Option<&ComposeSession> {
+        self.sessions.iter().find(|s| s.id == session_id)
+    }
+
+    /// The session backed by a Gmail draft
+    pub fn find_by_draft(&self, draft_id: &str) -> Option<&ComposeSession> {
+        self.sessions
+            .iter()
+            .find(|s| s.draft_id.as_deref() == Some(draft_id))
+    }
+
+    pub fn update(&mut self, session_id: &str, update: ComposeUpdate) -> Option<&ComposeSession> {
+        let session = self.sessions.iter_mut().find(|s| s.id == session_id)?;
+        session.apply(update);
+        Some(session)
+    }
+
+    pub fn remove(&mut self, session_id: &str) -> Option<ComposeSession> {
+        let index = self.sessions.iter().position(|s| s.id == session_id)?;
+        Some(self.sessions.remove(index))
+    }
+
+    /// Snapshots of sessions with unsaved edits
+    pub fn pending_autosave(&self) -> Vec<ComposeSession> {
+        self.sessions
+            .iter()
+            .filter(|s| s.needs_autosave())
+            .cloned()
+            .collect()
+    }
+
+    /// Queue a session behind a sending cap
+    pub fn defer(&mut self, session_id: &str, until: u64) -> bool {
+        match self.sessions.iter_mut().find(|s| s.id == session_id) {
+            Some(session) => {
+                session.deferred_until = Some(until);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Deferred sessions whose send time has come
+    pub fn due_deferred(&self, now: u64) -> Vec<ComposeSession> {
+        self.sessions
+            .iter()
+            .filter(|s| s.deferred_until.is_some_and(|until| until <= now))
+            .cloned()
+            .collect()
+    }
+
+    /// Record a completed autosave of `revision`. Returns false when the session
+    /// was discarded while the save was in flight.
+    pub fn mark_saved(&mut self, session_id: &str, revision: u64, draft_id: String) -> bool {
+        match self.sessions.iter_mut().find(|s| s.id == session_id) {
+            Some(session) => {
+                session.saved_revision = session.saved_revision.max(revision);
+                session.draft_id = Some(draft_id);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl AccountScoped for ComposeStore {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::{MessageHeader, MessagePayload};
+
+    fn original() -> GmailMessage {
+        let header = |name: &str, value: &str| MessageHeader {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        GmailMessage {
+            id: "msg1".to_string(),
+            thread_id: "thread1".to_string(),
+            payload: Some(MessagePayload {
+                headers: Some(vec![
+                    header("From", "Alice <alice@example.com>"),
+                    header("Subject", "Lunch"),
+                    header("Message-ID", "<b@example.com>"),
+                    header("References", "<a@example.com>"),
+                ]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_attachments_are_read_at_send_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Report.PDF");
+        std::fs::write(&path, b"%PDF-1.4").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let attachment = ComposeAttachment::from_path(&path).unwrap();
+        assert_eq!(attachment.filename, "Report.PDF");
+        assert_eq!(attachment.mime_type, "application/pdf");
+        assert_eq!(attachment.size, 8);
+
+        let session = ComposeSession {
+            attachments: vec![attachment],
+            ..Default::default()
+        };
+        let outgoing = session.to_outgoing().unwrap();
+        assert_eq!(outgoing.attachments[0].data, b"%PDF-1.4");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(session.to_outgoing().is_err());
+        assert!(read_attachments(&[path]).is_err());
+    }
+
+    #[test]
+    fn test_reply_session_threads_and_addresses_sender() {
+        let session = ComposeSession::reply_to("c1".to_string(), &original());
+        let outgoing = session.to_outgoing().unwrap();
+
+        assert_eq!(outgoing.to, "alice@example.com");
+        assert_eq!(outgoing.subject, "Re: Lunch");
+        assert_eq!(outgoing.in_reply_to.as_deref(), Some("<b@example.com>"));
+        assert_eq!(
+            outgoing.references.as_deref(),
+            Some("<a@example.com> <b@example.com>")
+        );
+        assert_eq!(session.thread_id(), Some("thread1"));
+        assert_eq!(reply_subject("Re: Lunch"), "Re: Lunch");
+    }
+
+    #[test]
+    fn test_resumed_reply_draft_keeps_threading() {
+        let header = |name: &str, value: &str| MessageHeader {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        let draft = GmailDraftMessage {
+            id: "r-1".to_string(),
+            message: GmailMessage {
+                id: "draftmsg".to_string(),
+                label_ids: Some(vec!["DRAFT".to_string()]),
+                payload: Some(MessagePayload {
+                    headers: Some(vec![
+                        header("To", "Alice <alice@example.com>, bob@example.com"),
+                        header("In-Reply-To", "<b@example.com>"),
+                        header("References", "<a@example.com> <b@example.com>"),
+                    ]),
+                    ..Default::default()
+                }),
+                ..original()
+            },
+        };
+
+        let session = ComposeSession::from_draft("c1".to_string(), &draft, &[original()]);
+        assert_eq!(
+            session.to,
+            vec!["Alice <alice@example.com>", "bob@example.com"]
+        );
+        assert_eq!(session.subject, "");
+        assert_eq!(session.draft_id.as_deref(), Some("r-1"));
+
+        let context = session.reply_context.unwrap();
+        assert_eq!(context.original_message_id, "msg1");
+        assert_eq!(context.thread_id, "thread1");
+        assert_eq!(context.seen_message_ids, vec!["msg1".to_string()]);
+    }
+
+    #[test]
+    fn test_newer_messages_skip_seen_and_drafts() {
+        let mut context = ReplyContext::from_message(&original());
+        let message = |id: &str, labels: &[&str]| GmailMessage {
+            id: id.to_string(),
+            label_ids: Some(labels.iter().map(|l| l.to_string()).collect()),
+            ..original()
+        };
+
+        let earlier = message("msg0", &["INBOX"]);
+        context.mark_thread_seen(&[earlier.clone(), original()]);
+
+        let thread = vec![
+            earlier,
+            original(),
+            message("draft", &["DRAFT"]),
+            message("msg2", &["INBOX", "UNREAD"]),
+        ];
+        let newer: Vec<&str> = context
+            .newer_messages(&thread)
+            .iter()
+            .map(|m| m.id.as_str())
+            .collect();
+        assert_eq!(newer, vec!["msg2"]);
+    }
+
+    #[test]
+    fn test_autosave_tracks_revisions() {
+        let mut store = ComposeStore::default();
+        let id = store.new_session_id();
+        store.insert(ComposeSession {
+            id: id.clone(),
+            ..Default::default()
+        });
+        assert!(store.pending_autosave().is_empty());
+
+        store.update(
+            &id,
+            ComposeUpdate {
+                body: Some("Hello".to_string()),
+                ..Default::default()
+            },
+        );
+        let pending = store.pending_autosave();
+        assert_eq!(pending.len(), 1);
+
+        // An edit landing while the save is in flight keeps the session dirty
+        store.update(
+            &id,
+            ComposeUpdate {
+                cc: Some(vec!["bob@example.com".to_string()]),
+                ..Default::default()
+            },
+        );
+        assert!(store.mark_saved(&id, pending[0].revision, "draft1".to_string()));
+        assert_eq!(store.pending_autosave().len(), 1);
+        assert_eq!(store.get(&id).unwrap().body, "Hello");
+        assert_eq!(
+            store.get(&id).unwrap().to_outgoing().unwrap().cc.as_deref(),
+            Some("bob@example.com")
+        );
+
+        store.remove(&id);
+        assert!(!store.mark_saved(&id, 2, "draft1".to_string()));
+    }
+
+    #[test]
+    fn test_deferred_sessions_come_due() {
+        let mut store = ComposeStore::default();
+        store.insert(ComposeSession {
+            id: "c1".to_string(),
+            to: vec!["Bob <bob@example.com>".to_string()],
+            bcc: vec!["carol@example.com".to_string()],
+            ..Default::default()
+        });
+
+        assert!(store.defer("c1", 100));
+        assert!(!store.defer("missing", 100));
+        assert!(store.due_deferred(99).is_empty());
+
+        let due = store.due_deferred(100);
+        assert_eq!(due.len(), 1);
+        assert_eq!(
+            due[0].recipients(),
+            vec![
+                "bob@example.com".to_string(),
+                "carol@example.com".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reply_subject_handles_localized_prefixes() {
+        assert_eq!(reply_subject("Termin"), "Re: Termin");
+        assert_eq!(reply_subject("AW: Termin"), "AW: Termin");
+        assert_eq!(reply_subject("AW: Re: AW: Termin"), "AW: Termin");
+        assert_eq!(reply_subject("RES: Proposta"), "RES: Proposta");
+        assert_eq!(reply_subject("sv:Möte"), "sv: Möte");
+        assert_eq!(reply_subject("Re[2]: Lunch"), "Re: Lunch");
+        assert_eq!(reply_subject("Fwd: Lunch"), "Re: Fwd: Lunch");
+        assert_eq!(reply_subject("Meeting: agenda"), "Re: Meeting: agenda");
+        assert_eq!(strip_reply_prefixes("Re: SV: Vs: Hej"), "Hej");
+    }
+
+    #[test]
+    fn test_forward_subject_and_body() {
+        assert_eq!(forward_subject("Lunch"), "Fwd: Lunch");
+        assert_eq!(forward_subject("FW: Lunch"), "FW: Lunch");
+        assert_eq!(forward_subject("Re: Lunch"), "Fwd: Re: Lunch");
+
+        let body = forward_body("See below", &original());
+        assert!(body.starts_with("See below\r\n\r\n---------- Forwarded message"));
+        assert!(body.contains("From: Alice <alice@example.com>\r\n"));
+        assert!(body.contains("Subject: Lunch\r\n"));
+        assert!(!body.contains("Date:"));
+
+        let without_note = forward_body("  ", &original());
+        assert!(without_note.starts_with("---------- Forwarded message"));
+    }
+}
@@ -0,0 +1,348 @@
+use crate::compose::strip_reply_prefixes;
+use crate::email::Email;
+use crate::gmail_client::{GmailClient, GmailMessage, MessageAttachment};
+use crate::message_cache::{self, MessageCache};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+use tokio::task::JoinSet;
+
+/// A file attached somewhere in a conversation, listed once even if re-sent in replies
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConversationAttachment {
+    /// SHA-256 of the file, present when it had to be downloaded to tell it
+    /// apart from a same-sized file elsewhere in the thread
+    pub sha256: Option<String>,
+    pub filename: String,
+    pub mime_type: String,
+    pub size: u64,
+    /// Most recent message carrying this file, used for the download action
+    pub message_id: String,
+    pub attachment_id: String,
+    /// Number of messages in the thread that carry this file
+    pub occurrences: usize,
+}
+
+/// A message that changed the thread's subject. The viewer draws a break
+/// before `emails[position]`, and these are the natural points to split a
+/// thread that drifted onto a new topic.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SubjectChange {
+    pub message_id: String,
+    pub position: usize,
+    pub previous_subject: String,
+    pub subject: String,
+}
+
+/// A full thread as shown in the conversation viewer
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Conversation {
+    pub thread_id: String,
+    pub subject: String,
+    pub sender: String,
+    /// Distinct sender addresses, in the order they joined the thread
+    pub participants: Vec<String>,
+    pub snippet: String,
+    pub message_count: usize,
+    pub has_unread: bool,
+    pub latest_date: Option<String>,
+    pub emails: Vec<Email>,
+    /// Filled in by `rollup_attachments` when a single thread is opened;
+    /// empty in listings
+    pub attachments: Vec<ConversationAttachment>,
+    #[serde(default)]
+    pub subject_changes: Vec<SubjectChange>,
+}
+
+impl Conversation {
+    /// Build a conversation from thread messages in Gmail's chronological order
+    pub fn from_messages(thread_id: &str, messages: &[GmailMessage]) -> Self {
+        let first = messages.first();
+        let latest = messages.last();
+
+        Conversation {
+            thread_id: thread_id.to_string(),
+            subject: first
+                .map(|m| m.get_subject())
+                .unwrap_or_else(|| "(No Subject)".to_string()),
+            sender: latest
+                .map(|m| m.get_from())
+                .unwrap_or_else(|| "Unknown Sender".to_string()),
+            participants: participants(messages),
+            snippet: latest.map(|m| m.snippet.clone()).unwrap_or_default(),
+            message_count: messages.len(),
+            has_unread: messages.iter().any(|m| m.is_unread()),
+            latest_date: latest.and_then(|m| m.get_date()),
+            emails: messages.iter().map(Email::from).collect(),
+            attachments: Vec::new(),
+            subject_changes: subject_changes(messages),
+        }
+    }
+}
+
+fn participants(messages: &[GmailMessage]) -> Vec<String> {
+    let mut participants: Vec<String> = Vec::new();
+    for message in messages {
+        let address = message.get_from_address();
+        if !participants
+            .iter()
+            .any(|p| p.eq_ignore_ascii_case(&address))
+        {
+            participants.push(address);
+        }
+    }
+    participants
+}
+
+/// Messages whose subject differs from the one before, ignoring reply and
+/// forward prefixes, case and spacing. Messages without a subject are skipped
+/// so they don't register as a change.
+pub fn subject_changes(messages: &[GmailMessage]) -> Vec<SubjectChange> {
+    let mut changes = Vec::new();
+    let mut previous: Option<(String, String)> = None;
+
+    for (position, message) in messages.iter().enumerate() {
+        let subject = match message
+            .payload
+            .as_ref()
+            .and_then(|p| p.headers.as_ref())
+            .and_then(|h| h.iter().find(|h| h.name.eq_ignore_ascii_case("Subject")))
+        {
+            Some(header) if !header.value.trim().is_empty() => header.value.trim().to_string(),
+            _ => continue,
+        };
+        let topic = subject_topic(&subject);
+
+        if let Some((previous_subject, previous_topic)) = &previous {
+            if *previous_topic != topic {
+                changes.push(SubjectChange {
+                    message_id: message.id.clone(),
+                    position,
+                    previous_subject: previous_subject.clone(),
+                    subject: subject.clone(),
+                });
+            }
+        }
+        previous = Some((subject, topic));
+    }
+
+    changes
+}
+
+/// Subject reduced to what identifies the topic
+fn subject_topic(subject: &str) -> String {
+    let mut rest = strip_reply_prefixes(subject);
+    loop {
+        let lower = rest.to_lowercase();
+        let prefix_len = ["fwd:", "fw:"]
+            .iter()
+            .find(|p| lower.starts_with(*p))
+            .map(|p| p.len());
+        match prefix_len {
+            Some(len) => rest = strip_reply_prefixes(&rest[len..]),
+            None => break,
+        }
+    }
+
+    rest.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Same-size attachments downloaded at once when deduplicating
+const DOWNLOAD_CONCURRENCY: usize = 4;
+
+/// Collect attachments across all messages, deduplicated by content.
+///
+/// Gmail does not expose content digests and attachment ids differ per message,
+/// so files that share a size are read through the message cache or downloaded,
+/// and compared by SHA-256. A file whose size is unique in the thread can't be a
+/// re-send of another and is listed without a download; one whose download
+/// fails is listed on its own.
+pub async fn rollup_attachments(
+    gmail_client: &GmailClient,
+    cache: &Mutex<MessageCache>,
+    messages: &[GmailMessage],
+) -> Vec<ConversationAttachment> {
+    let attachments: Vec<(&GmailMessage, usize, MessageAttachment)> = messages
+        .iter()
+        .flat_map(|m| {
+            m.get_attachments()
+                .into_iter()
+                .enumerate()
+                .map(move |(index, a)| (m, index, a))
+        })
+        .collect();
+    let digests = attachment_digests(gmail_client, cache, &attachments).await;
+    let mut rollup: Vec<ConversationAttachment> = Vec::new();
+
+    for ((_, _, attachment), sha256) in attachments.into_iter().zip(digests) {
+        let existing = sha256.as_ref().and_then(|digest| {
+            rollup
+                .iter_mut()
+                .find(|a| a.sha256.as_ref() == Some(digest))
+        });
+        if let Some(existing) = existing {
+            existing.occurrences += 1;
+            existing.message_id = attachment.message_id;
+            existing.attachment_id = attachment.attachment_id;
+        } else {
+            rollup.push(ConversationAttachment {
+                sha256,
+                filename: attachment.filename,
+                mime_type: attachment.mime_type,
+                size: attachment.size,
+                message_id: attachment.message_id,
+                attachment_id: attachment.attachment_id,
+                occurrences: 1,
+            });
+        }
+    }
+
+    rollup
+}
+
+/// SHA-256 of every attachment that shares its size with another; None for
+/// the rest and for any that couldn't be downloaded
+async fn attachment_digests(
+    gmail_client: &GmailClient,
+    cache: &Mutex<MessageCache>,
+    attachments: &[(&GmailMessage, usize, MessageAttachment)],
+) -> Vec<Option<String>> {
+    let mut digests = vec![None; attachments.len()];
+    let mut downloads = Vec::new();
+    for (position, (message, index, attachment)) in attachments.iter().enumerate() {
+        let shares_size = attachments
+            .iter()
+            .filter(|(_, _, a)| a.size == attachment.size)
+            .nth(1)
+            .is_some();
+        if !shares_size {
+            continue;
+        }
+        match cache.lock().unwrap().read_attachment(&message.id, *index) {
+            Some(data) => digests[position] = Some(sha256_hex(&data)),
+            None => downloads.push(position),
+        }
+    }
+
+    for chunk in downloads.chunks(DOWNLOAD_CONCURRENCY) {
+        let mut tasks = JoinSet::new();
+        for &position in chunk {
+            let client = gmail_client.clone();
+            let attachment = attachments[position].2.clone();
+            tasks.spawn(async move {
+                let data = client
+                    .get_attachment(&attachment.message_id, &attachment.attachment_id)
+                    .await;
+                (position, data)
+            });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            let (position, data) = match joined {
+                Ok(done) => done,
+                Err(e) => {
+                    eprintln!("Attachment download task failed: {}", e);
+                    continue;
+                }
+            };
+            let (message, index, attachment) = &attachments[position];
+            match data {
+                Ok(data) => {
+                    digests[position] = Some(sha256_hex(&data));
+                    message_cache::store_attachment(cache, message, *index, &data);
+                }
+                Err(e) => eprintln!("Failed to download {}: {}", attachment.filename, e),
+            }
+        }
+    }
+
+    digests
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::{MessageBody, MessageHeader, MessagePart, MessagePayload};
+
+    fn message_with_attachment(id: &str, filename: &str, size: u64) -> GmailMessage {
+        GmailMessage {
+            id: id.to_string(),
+            thread_id: "thread1".to_string(),
+            snippet: format!("snippet {}", id),
+            label_ids: Some(vec!["INBOX".to_string()]),
+            payload: Some(MessagePayload {
+                headers: Some(vec![MessageHeader {
+                    name: "Subject".to_string(),
+                    value: "Quarterly report".to_string(),
+                }]),
+                parts: Some(vec![MessagePart {
+                    mime_type: Some("application/pdf".to_string()),
+                    filename: Some(filename.to_string()),
+                    body: Some(MessageBody {
+                        attachment_id: Some(format!("att_{}", id)),
+                        size: Some(size),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_conversation_from_messages() {
+        let messages = vec![
+            message_with_attachment("m1", "report.pdf", 2048),
+            message_with_attachment("m2", "notes.txt", 12),
+        ];
+
+        let conversation = Conversation::from_messages("thread1", &messages);
+        assert_eq!(conversation.message_count, 2);
+        assert_eq!(conversation.subject, "Quarterly report");
+        assert_eq!(conversation.snippet, "snippet m2");
+        assert!(conversation.attachments.is_empty());
+        assert!(!conversation.has_unread);
+    }
+
+    #[test]
+    fn test_subject_changes_ignore_prefixes() {
+        let with_subject = |id: &str, subject: &str| GmailMessage {
+            payload: Some(MessagePayload {
+                headers: Some(vec![MessageHeader {
+                    name: "Subject".to_string(),
+                    value: subject.to_string(),
+                }]),
+                ..Default::default()
+            }),
+            ..message_with_attachment(id, "a.txt", 1)
+        };
+
+        let messages = vec![
+            with_subject("m1", "Quarterly report"),
+            with_subject("m2", "RE: quarterly  report"),
+            with_subject("m3", "Fwd: AW: Quarterly report"),
+            with_subject("m4", "Offsite planning"),
+            with_subject("m5", ""),
+            with_subject("m6", "Re: Offsite planning"),
+        ];
+
+        let conversation = Conversation::from_messages("thread1", &messages);
+        assert_eq!(
+            conversation.subject_changes,
+            vec![SubjectChange {
+                message_id: "m4".to_string(),
+                position: 3,
+                previous_subject: "Fwd: AW: Quarterly report".to_string(),
+                subject: "Offsite planning".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// How long list commands wait on Gmail before answering with what they have
+pub const RESPONSE_DEADLINE: Duration = Duration::from_secs(3);
+
+/// Ids hydrated per request under a deadline, so one slow response only
+/// holds back a slice of the page
+pub const DEADLINE_CHUNK_SIZE: usize = 20;
+
+/// What a deadline-bound fetch got through
+#[derive(Debug)]
+pub struct Partial<T> {
+    pub items: Vec<T>,
+    /// Ids not fetched when the deadline passed, in request order
+    pub remaining: Vec<String>,
+}
+
+/// Token naming the rest of a partial response in its continuation event
+pub fn new_continuation_token() -> String {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    format!("cont_{}", millis)
+}
+
+/// Fetch `ids` in chunks until `deadline`. A chunk still in flight at the
+/// deadline is abandoned and reported as remaining along with the rest;
+/// errors from a chunk are returned as-is.
+pub async fn fetch_until<T, F, Fut>(
+    ids: &[String],
+    chunk_size: usize,
+    deadline: Instant,
+    mut fetch: F,
+) -> Result<Partial<T>, Box<dyn std::error::Error + Send + Sync>>
+where
+    F: FnMut(Vec<String>) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, Box<dyn std::error::Error + Send + Sync>>>,
+{
+    let chunk_size = chunk_size.max(1);
+    let mut items = Vec::new();
+    for (index, chunk) in ids.chunks(chunk_size).enumerate() {
+        let remaining = || ids[index * chunk_size..].to_vec();
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Ok(Partial {
+                items,
+                remaining: remaining(),
+            });
+        }
+
+        match tokio::time::timeout(left, fetch(chunk.to_vec())).await {
+            Ok(fetched) => items.extend(fetched?),
+            Err(_) => {
+                return Ok(Partial {
+                    items,
+                    remaining: remaining(),
+                })
+            }
+        }
+    }
+
+    Ok(Partial {
+        items,
+        remaining: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("m{}", i)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_everything_fetched_before_deadline() {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let partial = fetch_until(&ids(5), 2, deadline, |chunk| async move { Ok(chunk) })
+            .await
+            .unwrap();
+        assert_eq!(partial.items, ids(5));
+        assert!(partial.remaining.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_slow_chunk_is_left_for_later() {
+        let deadline = Instant::now() + Duration::from_millis(200);
+        let partial = fetch_until(&ids(6), 2, deadline, |chunk| async move {
+            // The second chunk stalls past the deadline
+            if chunk[0] == "m2" {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+            Ok(chunk)
+        })
+        .await
+        .unwrap();
+        assert_eq!(partial.items, vec!["m0", "m1"]);
+        assert_eq!(partial.remaining, vec!["m2", "m3", "m4", "m5"]);
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_not_swallowed() {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let result: Result<Partial<String>, _> =
+            fetch_until(&ids(2), 1, deadline, |_| async { Err("boom".into()) }).await;
+        assert!(result.is_err());
+    }
+}
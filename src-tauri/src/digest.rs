@@ -0,0 +1,265 @@
+use crate::account::AccountScoped;
+use crate::email::Email;
+use crate::gmail_client::{extract_email_address, OutgoingEmail};
+use crate::local_store::{app_data_path, load_json, save_json};
+use crate::scheduler;
+use crate::settings::DigestSettings;
+use chrono::{DateTime, Duration};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+const DIGEST_STATE_FILE: &str = "digest_state.json";
+
+/// Mail the weekly digest summarizes
+pub const DIGEST_QUERY: &str = "in:inbox newer_than:7d";
+
+/// Senders listed in the digest
+const TOP_SENDER_LIMIT: usize = 5;
+
+/// Unread messages listed in the digest
+const UNREAD_LIST_LIMIT: usize = 10;
+
+/// A missed slot (app closed, signed out) is still sent if the app comes back
+/// within this long; after that the week is skipped rather than sent late
+const CATCH_UP_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SenderCount {
+    pub sender: String,
+    pub count: usize,
+}
+
+/// Summary of the past week's inbox
+#[derive(Debug, Clone, Serialize)]
+pub struct WeeklyDigest {
+    /// Local date (YYYY-MM-DD) the digest was built
+    pub period_end: String,
+    pub received_count: u32,
+    pub unread_count: usize,
+    pub top_senders: Vec<SenderCount>,
+    pub unread: Vec<Email>,
+}
+
+impl WeeklyDigest {
+    /// Build a digest from the week's inbox messages. `total` is Gmail's
+    /// estimate when the messages are only the first page of results.
+    pub fn build(emails: &[Email], total: Option<u32>, now: &DateTime<Tz>) -> Self {
+        let mut top_senders: Vec<SenderCount> = Vec::new();
+        for email in emails {
+            let address = extract_email_address(&email.sender);
+            match top_senders
+                .iter_mut()
+                .find(|s| extract_email_address(&s.sender).eq_ignore_ascii_case(&address))
+            {
+                Some(entry) => entry.count += 1,
+                None => top_senders.push(SenderCount {
+                    sender: email.sender.clone(),
+                    count: 1,
+                }),
+            }
+        }
+        // Stable sort keeps first-seen (newest) senders ahead on ties
+        top_senders.sort_by_key(|s| std::cmp::Reverse(s.count));
+        top_senders.truncate(TOP_SENDER_LIMIT);
+
+        let unread: Vec<Email> = emails.iter().filter(|e| !e.is_read).cloned().collect();
+
+        WeeklyDigest {
+            period_end: now.format("%Y-%m-%d").to_string(),
+            received_count: total
+                .unwrap_or(emails.len() as u32)
+                .max(emails.len() as u32),
+            unread_count: unread.len(),
+            top_senders,
+            unread: unread.into_iter().take(UNREAD_LIST_LIMIT).collect(),
+        }
+    }
+
+    pub fn subject(&self) -> String {
+        format!("Your weekly inbox digest ({})", self.period_end)
+    }
+
+    /// Plain-text body for the digest email
+    pub fn body(&self) -> String {
+        let mut body = format!(
+            "In the past 7 days you received {} messages; {} are still unread.\n",
+            self.received_count, self.unread_count
+        );
+
+        if !self.top_senders.is_empty() {
+            body.push_str("\nTop senders:\n");
+            for sender in &self.top_senders {
+                body.push_str(&format!("  {} ({})\n", sender.sender, sender.count));
+            }
+        }
+
+        if !self.unread.is_empty() {
+            body.push_str("\nStill unread:\n");
+            for email in &self.unread {
+                body.push_str(&format!("  {} - {}\n", email.sender, email.subject));
+            }
+            if self.unread_count > self.unread.len() {
+                body.push_str(&format!(
+                    "  ...and {} more\n",
+                    self.unread_count - self.unread.len()
+                ));
+            }
+        }
+
+        body
+    }
+
+    /// The digest as a message to `to`, normally the account's own address
+    pub fn to_outgoing(&self, to: &str) -> OutgoingEmail {
+        OutgoingEmail {
+            to: to.to_string(),
+            subject: self.subject(),
+            body: self.body(),
+            ..Default::default()
+        }
+    }
+}
+
+/// The most recent scheduled slot at or before `now`
+pub fn last_slot(settings: &DigestSettings, now: &DateTime<Tz>) -> DateTime<Tz> {
+    scheduler::last_weekly_slot(settings.weekday, settings.hour, now)
+}
+
+/// When the last digest went out, persisted so restarts don't resend it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestState {
+    /// Unix timestamp (seconds) of the last sent digest
+    pub last_sent_at: Option<i64>,
+    pub account_id: Option<String>,
+}
+
+impl DigestState {
+    pub fn load() -> Self {
+        load_json(&app_data_path(DIGEST_STATE_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(DIGEST_STATE_FILE), self)
+    }
+
+    /// Whether this week's slot has passed without a digest being sent
+    pub fn is_due(&self, settings: &DigestSettings, now: &DateTime<Tz>) -> bool {
+        if !settings.enabled {
+            return false;
+        }
+        let slot = last_slot(settings, now);
+
+        let sent_since_slot = self
+            .last_sent_at
+            .is_some_and(|sent| sent >= slot.timestamp());
+        !sent_since_slot && *now - slot < Duration::hours(CATCH_UP_HOURS)
+    }
+
+    pub fn mark_sent(&mut self, now: &DateTime<Tz>) {
+        self.last_sent_at = Some(now.timestamp());
+    }
+}
+
+impl AccountScoped for DigestState {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Weekday};
+    use chrono_tz::Europe::London;
+
+    fn email(id: &str, sender: &str, is_read: bool) -> Email {
+        Email {
+            id: id.to_string(),
+            thread_id: id.to_string(),
+            subject: format!("Subject {}", id),
+            sender: sender.to_string(),
+            snippet: String::new(),
+            is_read,
+            is_no_reply: false,
+            is_important: false,
+            category: None,
+        }
+    }
+
+    fn monday_morning() -> DigestSettings {
+        DigestSettings {
+            enabled: true,
+            weekday: Weekday::Mon,
+            hour: 8,
+        }
+    }
+
+    #[test]
+    fn test_build_counts_senders_and_unread() {
+        let emails = vec![
+            email("1", "Alice <alice@example.com>", false),
+            email("2", "bob@example.com", true),
+            email("3", "ALICE@example.com", true),
+        ];
+        let now = London.with_ymd_and_hms(2025, 6, 9, 8, 0, 0).unwrap();
+        let digest = WeeklyDigest::build(&emails, Some(40), &now);
+
+        assert_eq!(digest.received_count, 40);
+        assert_eq!(digest.unread_count, 1);
+        assert_eq!(
+            digest.top_senders[0],
+            SenderCount {
+                sender: "Alice <alice@example.com>".to_string(),
+                count: 2
+            }
+        );
+        assert_eq!(digest.subject(), "Your weekly inbox digest (2025-06-09)");
+        assert!(digest
+            .body()
+            .contains("Alice <alice@example.com> - Subject 1"));
+        assert_eq!(digest.to_outgoing("me@example.com").to, "me@example.com");
+    }
+
+    #[test]
+    fn test_due_once_per_week_after_slot() {
+        let settings = monday_morning();
+        let mut state = DigestState::default();
+
+        // 2025-06-09 is a Monday
+        let before = London.with_ymd_and_hms(2025, 6, 9, 7, 30, 0).unwrap();
+        let after = London.with_ymd_and_hms(2025, 6, 9, 8, 5, 0).unwrap();
+        let too_late = London.with_ymd_and_hms(2025, 6, 11, 9, 0, 0).unwrap();
+        let next_week = London.with_ymd_and_hms(2025, 6, 16, 8, 0, 0).unwrap();
+
+        assert_eq!(
+            last_slot(&settings, &before),
+            London.with_ymd_and_hms(2025, 6, 2, 8, 0, 0).unwrap()
+        );
+        assert!(!state.is_due(&settings, &before));
+        assert!(state.is_due(&settings, &after));
+        assert!(!state.is_due(&settings, &too_late));
+
+        state.mark_sent(&after);
+        assert!(!state.is_due(&settings, &after));
+        assert!(state.is_due(&settings, &next_week));
+
+        let disabled = DigestSettings {
+            enabled: false,
+            ..monday_morning()
+        };
+        assert!(!DigestState::default().is_due(&disabled, &after));
+    }
+}
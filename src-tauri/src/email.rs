@@ -0,0 +1,83 @@
+use crate::gmail_client::GmailMessage;
+use crate::no_reply::is_no_reply_address;
+use serde::{Deserialize, Serialize};
+
+/// Gmail's inbox tabs, from the CATEGORY_* system labels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Primary,
+    Social,
+    Promotions,
+    Updates,
+    Forums,
+}
+
+impl Category {
+    pub fn from_label_id(label_id: &str) -> Option<Self> {
+        match label_id {
+            "CATEGORY_PERSONAL" => Some(Category::Primary),
+            "CATEGORY_SOCIAL" => Some(Category::Social),
+            "CATEGORY_PROMOTIONS" => Some(Category::Promotions),
+            "CATEGORY_UPDATES" => Some(Category::Updates),
+            "CATEGORY_FORUMS" => Some(Category::Forums),
+            _ => None,
+        }
+    }
+
+    /// The tab a message sits under, if Gmail has categorized it
+    pub fn of(message: &GmailMessage) -> Option<Self> {
+        message
+            .label_ids
+            .as_deref()?
+            .iter()
+            .find_map(|label| Self::from_label_id(label))
+    }
+
+    /// Gmail query for the tab's inbox
+    pub fn inbox_query(&self) -> &'static str {
+        match self {
+            Category::Primary => "in:inbox category:primary",
+            Category::Social => "in:inbox category:social",
+            Category::Promotions => "in:inbox category:promotions",
+            Category::Updates => "in:inbox category:updates",
+            Category::Forums => "in:inbox category:forums",
+        }
+    }
+}
+
+/// Email summary returned to the frontend list views
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Email {
+    pub id: String,
+    pub thread_id: String,
+    pub subject: String,
+    pub sender: String,
+    pub snippet: String,
+    pub is_read: bool,
+    /// Sent from an address that doesn't accept replies
+    #[serde(default)]
+    pub is_no_reply: bool,
+    /// Gmail marked the message IMPORTANT, for priority inbox views
+    #[serde(default)]
+    pub is_important: bool,
+    /// Inbox tab, None when Gmail hasn't categorized the message
+    #[serde(default)]
+    pub category: Option<Category>,
+}
+
+impl From<&GmailMessage> for Email {
+    fn from(message: &GmailMessage) -> Self {
+        Email {
+            id: message.id.clone(),
+            thread_id: message.thread_id.clone(),
+            subject: message.get_subject(),
+            sender: message.get_from(),
+            snippet: message.snippet.clone(),
+            is_read: !message.is_unread(),
+            is_no_reply: is_no_reply_address(&message.get_from_address()),
+            is_important: message.is_important(),
+            category: Category::of(message),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::settings::FocusModeSettings;
+use serde::Serialize;
+
+/// What the UI shows while mail is being held
+#[derive(Debug, Clone, Serialize)]
+pub struct FocusStatus {
+    pub enabled: bool,
+    pub held_count: usize,
+    /// Unix timestamp (seconds) of the next scheduled delivery
+    pub next_delivery_at: Option<u64>,
+}
+
+/// New message ids waiting for the next batch
+#[derive(Debug, Default)]
+pub struct FocusBuffer {
+    held_ids: Vec<String>,
+    last_delivery: Option<u64>,
+}
+
+impl FocusBuffer {
+    pub fn hold(&mut self, message_ids: &[String]) {
+        for id in message_ids {
+            if !self.held_ids.contains(id) {
+                self.held_ids.push(id.clone());
+            }
+        }
+    }
+
+    pub fn held_count(&self) -> usize {
+        self.held_ids.len()
+    }
+
+    /// When the next batch goes out; the first batch starts its interval on first use
+    pub fn next_delivery_at(&mut self, now: u64, settings: &FocusModeSettings) -> u64 {
+        let last = *self.last_delivery.get_or_insert(now);
+        last + u64::from(settings.batch_interval_minutes.max(1)) * 60
+    }
+
+    pub fn is_due(&mut self, now: u64, settings: &FocusModeSettings) -> bool {
+        now >= self.next_delivery_at(now, settings)
+    }
+
+    /// Release everything held and restart the interval
+    pub fn deliver(&mut self, now: u64) -> Vec<String> {
+        self.last_delivery = Some(now);
+        std::mem::take(&mut self.held_ids)
+    }
+
+    /// Route a poll's new ids through focus mode, returning the ids to surface now
+    pub fn process(
+        &mut self,
+        new_ids: &[String],
+        now: u64,
+        settings: &FocusModeSettings,
+    ) -> Vec<String> {
+        if !settings.enabled {
+            // Anything still held from before focus mode was turned off goes out first
+            self.hold(new_ids);
+            return self.deliver(now);
+        }
+
+        self.hold(new_ids);
+        if self.is_due(now, settings) {
+            self.deliver(now)
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub fn status(&mut self, now: u64, settings: &FocusModeSettings) -> FocusStatus {
+        FocusStatus {
+            enabled: settings.enabled,
+            held_count: self.held_count(),
+            next_delivery_at: settings
+                .enabled
+                .then(|| self.next_delivery_at(now, settings)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn hourly() -> FocusModeSettings {
+        FocusModeSettings {
+            enabled: true,
+            batch_interval_minutes: 60,
+        }
+    }
+
+    #[test]
+    fn test_holds_until_interval_elapses() {
+        let mut buffer = FocusBuffer::default();
+        let settings = hourly();
+
+        assert!(buffer.process(&ids(&["a"]), 1_000, &settings).is_empty());
+        assert!(buffer
+            .process(&ids(&["b", "a"]), 1_000 + 30 * 60, &settings)
+            .is_empty());
+        assert_eq!(buffer.held_count(), 2);
+
+        let delivered = buffer.process(&ids(&["c"]), 1_000 + 60 * 60, &settings);
+        assert_eq!(delivered, ids(&["a", "b", "c"]));
+        assert_eq!(buffer.held_count(), 0);
+    }
+
+    #[test]
+    fn test_disabled_passes_through_and_flushes_held() {
+        let mut buffer = FocusBuffer::default();
+        buffer.process(&ids(&["a"]), 0, &hourly());
+
+        let delivered = buffer.process(&ids(&["b"]), 10, &FocusModeSettings::default());
+        assert_eq!(delivered, ids(&["a", "b"]));
+    }
+
+    #[test]
+    fn test_manual_delivery_restarts_interval() {
+        let mut buffer = FocusBuffer::default();
+        let settings = hourly();
+        buffer.process(&ids(&["a"]), 0, &settings);
+
+        assert_eq!(buffer.deliver(1_800), ids(&["a"]));
+        assert_eq!(
+            buffer.status(1_800, &settings).next_delivery_at,
+            Some(5_400)
+        );
+    }
+}
@@ -9,13 +9,42 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use url::Url;
 
+use crate::clock::{Clock, SystemClock};
 use crate::gmail_config::{GoogleCredentials, REDIRECT_URI, SCOPES};
 
+/// Access tokens are refreshed this many seconds before they run out, so a
+/// request started just before expiry doesn't fail in flight
+const EXPIRY_MARGIN_SECS: u64 = 60;
+
+/// How long a token of unknown age is trusted after Gmail accepted it
+const CHECKED_TOKEN_TRUST_SECS: u64 = 5 * 60;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthTokens {
     pub access_token: String,
     pub refresh_token: Option<String>,
     pub expires_in: Option<u64>,
+    /// Unix timestamp (seconds) the access token was issued; missing for
+    /// tokens saved before it was recorded
+    #[serde(default)]
+    pub obtained_at: Option<u64>,
+}
+
+impl AuthTokens {
+    /// Whether the access token has expired (or is about to) at `now`, or
+    /// None when its lifetime isn't known
+    pub fn needs_refresh(&self, now: u64) -> Option<bool> {
+        let expires_at = self.obtained_at? + self.expires_in?;
+        Some(now + EXPIRY_MARGIN_SECS >= expires_at)
+    }
+
+    /// Give a token of unknown age that Gmail just accepted at `now` a short
+    /// known lifetime, so it's refreshed on schedule instead of being checked
+    /// again on every command
+    pub fn mark_checked(&mut self, now: u64) {
+        self.obtained_at = Some(now);
+        self.expires_in = Some(CHECKED_TOKEN_TRUST_SECS + EXPIRY_MARGIN_SECS);
+    }
 }
 
 #[derive(Clone)]
@@ -74,6 +103,7 @@ impl GmailAuth {
             access_token,
             refresh_token,
             expires_in,
+            obtained_at: Some(SystemClock.unix_now()),
         })
     }
 
@@ -98,6 +128,7 @@ impl GmailAuth {
             access_token,
             refresh_token: new_refresh_token,
             expires_in,
+            obtained_at: Some(SystemClock.unix_now()),
         })
     }
 }
@@ -122,3 +153,35 @@ pub fn parse_callback_url(
 
     Ok((code, state))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_refresh_near_expiry() {
+        let tokens = AuthTokens {
+            access_token: "token".to_string(),
+            refresh_token: None,
+            expires_in: Some(3600),
+            obtained_at: Some(1_000),
+        };
+        assert_eq!(tokens.needs_refresh(1_000), Some(false));
+        assert_eq!(tokens.needs_refresh(4_539), Some(false));
+        assert_eq!(tokens.needs_refresh(4_540), Some(true));
+
+        let legacy = AuthTokens {
+            obtained_at: None,
+            ..tokens
+        };
+        assert_eq!(legacy.needs_refresh(1_000_000), None);
+
+        let mut checked = legacy;
+        checked.mark_checked(1_000_000);
+        assert_eq!(checked.needs_refresh(1_000_000), Some(false));
+        assert_eq!(
+            checked.needs_refresh(1_000_000 + CHECKED_TOKEN_TRUST_SECS),
+            Some(true)
+        );
+    }
+}
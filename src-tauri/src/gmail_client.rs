@@ -1,9 +1,16 @@
 use crate::gmail_auth::AuthTokens;
-use base64::{engine::general_purpose::URL_SAFE, Engine as _};
+use crate::html_text;
+use crate::unsubscribe::UnsubscribeOptions;
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use chrono::NaiveDate;
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct GmailMessage {
     pub id: String,
     #[serde(rename = "threadId")]
@@ -12,10 +19,21 @@ pub struct GmailMessage {
     #[serde(rename = "labelIds")]
     pub label_ids: Option<Vec<String>>,
     pub payload: Option<MessagePayload>,
+    #[serde(rename = "internalDate")]
+    pub internal_date: Option<String>,
+    /// Gmail's estimate of the whole message in bytes, attachments included
+    #[serde(rename = "sizeEstimate", default)]
+    pub size_estimate: Option<u64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Messages estimated above this open as headers plus the plain-text body;
+/// HTML and attachments wait for an explicit full load
+pub const LARGE_MESSAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct MessagePayload {
+    #[serde(rename = "mimeType")]
+    pub mime_type: Option<String>,
     pub headers: Option<Vec<MessageHeader>>,
     pub parts: Option<Vec<MessagePart>>,
     pub body: Option<MessageBody>,
@@ -27,15 +45,260 @@ pub struct MessageHeader {
     pub value: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct MessagePart {
     pub headers: Option<Vec<MessageHeader>>,
     pub body: Option<MessageBody>,
+    #[serde(rename = "mimeType")]
+    pub mime_type: Option<String>,
+    pub filename: Option<String>,
+    /// Children of a multipart part, e.g. multipart/alternative nested in
+    /// multipart/mixed
+    pub parts: Option<Vec<MessagePart>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl MessagePart {
+    /// Lowercased MIME type, from mimeType or else the Content-Type header
+    fn content_type(&self) -> Option<String> {
+        self.mime_type
+            .clone()
+            .or_else(|| {
+                self.headers
+                    .iter()
+                    .flatten()
+                    .find(|h| h.name.eq_ignore_ascii_case("Content-Type"))
+                    .map(|h| h.value.clone())
+            })
+            .map(|ct| ct.to_ascii_lowercase())
+    }
+
+    /// Decoded inline body of a part of `mime_type`, skipping attachments
+    fn inline_text(&self, mime_type: &str) -> Option<String> {
+        if self.filename.as_deref().is_some_and(|f| !f.is_empty()) {
+            return None;
+        }
+        if !self.content_type()?.contains(mime_type) {
+            return None;
+        }
+        let data = self.body.as_ref()?.data.as_ref()?;
+        decode_body_data(data, self.headers.as_deref())
+    }
+}
+
+/// Decode a body's base64url data to text, undoing quoted-printable when the
+/// part's Content-Transfer-Encoding says it's still applied and converting
+/// from the Content-Type charset
+fn decode_body_data(data: &str, headers: Option<&[MessageHeader]>) -> Option<String> {
+    let header = |name: &str| {
+        headers
+            .into_iter()
+            .flatten()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+            .map(|h| h.value.as_str())
+    };
+
+    let mut bytes = URL_SAFE.decode(data).ok()?;
+    if header("Content-Transfer-Encoding")
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("quoted-printable"))
+    {
+        bytes = decode_quoted_printable(&bytes);
+    }
+    let charset = header("Content-Type").and_then(content_type_charset);
+    Some(decode_charset(&bytes, charset.as_deref()))
+}
+
+/// The charset parameter of a Content-Type value, e.g. "iso-8859-1" from
+/// `text/plain; charset="ISO-8859-1"`
+fn content_type_charset(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"').to_ascii_lowercase())
+    })
+}
+
+/// Text from bytes in `charset`. Undeclared or unknown charsets are read as
+/// UTF-8, falling back to Windows-1252 (what mislabelled mail usually is)
+/// when the bytes aren't valid UTF-8.
+pub fn decode_charset(bytes: &[u8], charset: Option<&str>) -> String {
+    let declared = charset.and_then(|c| encoding_rs::Encoding::for_label(c.as_bytes()));
+    match declared {
+        Some(encoding) if encoding != encoding_rs::UTF_8 => {
+            encoding.decode_without_bom_handling(bytes).0.into_owned()
+        }
+        _ => match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            Err(_) => encoding_rs::WINDOWS_1252
+                .decode_without_bom_handling(bytes)
+                .0
+                .into_owned(),
+        },
+    }
+}
+
+/// Undo quoted-printable encoding (RFC 2045): `=XX` escapes become bytes and
+/// `=` at the end of a line joins it to the next. Malformed escapes are kept
+/// as written.
+pub fn decode_quoted_printable(input: &[u8]) -> Vec<u8> {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let mut output = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] != b'=' {
+            output.push(input[i]);
+            i += 1;
+            continue;
+        }
+        match &input[i + 1..] {
+            [b'\r', b'\n', ..] => i += 3,
+            [b'\n', ..] => i += 2,
+            [high, low, ..] if hex(*high).is_some() && hex(*low).is_some() => {
+                output.push(hex(*high).unwrap() << 4 | hex(*low).unwrap());
+                i += 3;
+            }
+            _ => {
+                output.push(b'=');
+                i += 1;
+            }
+        }
+    }
+    output
+}
+
+/// Decode RFC 2047 encoded words such as `=?UTF-8?B?w6k=?=` in a header
+/// value. Whitespace between adjacent encoded words is dropped, and words in
+/// the same charset are joined before decoding so a character split across
+/// them survives. Malformed words are kept as written.
+pub fn decode_encoded_words(value: &str) -> String {
+    if !value.contains("=?") {
+        return value.to_string();
+    }
+
+    let flush = |output: &mut String, pending: &mut Option<(String, Vec<u8>)>| {
+        if let Some((charset, bytes)) = pending.take() {
+            output.push_str(&decode_charset(&bytes, Some(&charset)));
+        }
+    };
+
+    let mut output = String::with_capacity(value.len());
+    let mut pending: Option<(String, Vec<u8>)> = None;
+    let mut rest = value;
+    while let Some(c) = rest.chars().next() {
+        let Some((charset, bytes, len)) = encoded_word(rest) else {
+            flush(&mut output, &mut pending);
+            output.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        match &mut pending {
+            Some((current, joined)) if current.eq_ignore_ascii_case(&charset) => {
+                joined.extend(bytes)
+            }
+            _ => {
+                flush(&mut output, &mut pending);
+                pending = Some((charset, bytes));
+            }
+        }
+        rest = &rest[len..];
+        let next = rest.trim_start_matches([' ', '\t', '\r', '\n']);
+        if encoded_word(next).is_some() {
+            rest = next;
+        }
+    }
+    flush(&mut output, &mut pending);
+    output
+}
+
+/// The charset, decoded bytes and length of the encoded word `value` starts
+/// with, if it starts with one
+fn encoded_word(value: &str) -> Option<(String, Vec<u8>, usize)> {
+    let inner = value.strip_prefix("=?")?;
+    let (charset, inner) = inner.split_once('?')?;
+    let (encoding, inner) = inner.split_once('?')?;
+    let text = &inner[..inner.find("?=")?];
+    if charset.is_empty() || charset.contains(char::is_whitespace) {
+        return None;
+    }
+    if text.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let bytes = match encoding {
+        "B" | "b" => STANDARD
+            .decode(text)
+            .or_else(|_| STANDARD_NO_PAD.decode(text.trim_end_matches('=')))
+            .ok()?,
+        "Q" | "q" => decode_quoted_printable(text.replace('_', " ").as_bytes()),
+        _ => return None,
+    };
+    let len = 2 + charset.len() + 1 + encoding.len() + 1 + text.len() + 2;
+    // RFC 2231 lets a language follow the charset, as in `UTF-8*en`
+    let charset = charset.split('*').next().unwrap_or(charset);
+    Some((charset.to_string(), bytes, len))
+}
+
+/// Every part under `parts`, depth-first in document order
+pub(crate) fn flatten_parts(parts: &[MessagePart]) -> Vec<&MessagePart> {
+    let mut flat = Vec::new();
+    for part in parts {
+        flat.push(part);
+        flat.extend(flatten_parts(part.parts.as_deref().unwrap_or_default()));
+    }
+    flat
+}
+
+/// Levels of nested parts `get_message_text` asks for
+const PART_TREE_DEPTH: usize = 5;
+
+/// messages.get fields for a message and its part tree with every body's
+/// size and attachment id but none of its data
+fn part_tree_fields() -> String {
+    let part = "mimeType,filename,headers,body(size,attachmentId)";
+    let mut parts = part.to_string();
+    for _ in 0..PART_TREE_DEPTH {
+        parts = format!("{},parts({})", part, parts);
+    }
+    format!(
+        "id,threadId,labelIds,snippet,internalDate,sizeEstimate,payload({})",
+        parts
+    )
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct MessageBody {
     pub data: Option<String>,
+    #[serde(rename = "attachmentId")]
+    pub attachment_id: Option<String>,
+    pub size: Option<u64>,
+}
+
+/// Attachment metadata for a single message part; the bytes are fetched separately
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MessageAttachment {
+    pub message_id: String,
+    pub attachment_id: String,
+    pub filename: String,
+    pub mime_type: String,
+    pub size: u64,
+}
+
+/// Label state returned by modify-style endpoints
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageLabels {
+    pub id: String,
+    #[serde(rename = "threadId")]
+    pub thread_id: String,
+    #[serde(rename = "labelIds", default)]
+    pub label_ids: Vec<String>,
+}
+
+/// Label state of every message in a thread, as returned by threads.modify
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThreadLabels {
+    pub id: String,
+    #[serde(default)]
+    pub messages: Vec<MessageLabels>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -55,6 +318,27 @@ pub struct GmailMessageRef {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+pub struct GmailThreadsResponse {
+    pub threads: Option<Vec<GmailThreadRef>>,
+    #[serde(rename = "nextPageToken")]
+    pub next_page_token: Option<String>,
+    #[serde(rename = "resultSizeEstimate")]
+    pub result_size_estimate: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GmailThreadRef {
+    pub id: String,
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GmailThread {
+    pub id: String,
+    pub messages: Option<Vec<GmailMessage>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GmailProfile {
     #[serde(rename = "emailAddress")]
     pub email_address: String,
@@ -62,432 +346,2620 @@ pub struct GmailProfile {
     pub messages_total: Option<u32>,
     #[serde(rename = "threadsTotal")]
     pub threads_total: Option<u32>,
+    #[serde(rename = "historyId")]
+    pub history_id: Option<String>,
 }
 
-pub struct GmailClient {
-    client: Client,
-    access_token: String,
+/// One page of history.list
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GmailHistoryResponse {
+    pub history: Option<Vec<HistoryRecord>>,
+    #[serde(rename = "nextPageToken")]
+    pub next_page_token: Option<String>,
+    /// The mailbox's current history id
+    #[serde(rename = "historyId")]
+    pub history_id: Option<String>,
 }
 
-impl GmailClient {
-    pub fn new(tokens: &AuthTokens) -> Self {
-        Self {
-            client: Client::new(),
-            access_token: tokens.access_token.clone(),
+/// A single mailbox change; only the fields for its kind are set
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HistoryRecord {
+    pub id: String,
+    #[serde(rename = "messagesAdded", default)]
+    pub messages_added: Vec<HistoryMessage>,
+    #[serde(rename = "messagesDeleted", default)]
+    pub messages_deleted: Vec<HistoryMessage>,
+    #[serde(rename = "labelsAdded", default)]
+    pub labels_added: Vec<HistoryLabelChange>,
+    #[serde(rename = "labelsRemoved", default)]
+    pub labels_removed: Vec<HistoryLabelChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryMessage {
+    pub message: MessageLabels,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryLabelChange {
+    pub message: MessageLabels,
+    #[serde(rename = "labelIds", default)]
+    pub label_ids: Vec<String>,
+}
+
+/// Net label change on one message over a history window
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct LabelChange {
+    pub message_id: String,
+    pub thread_id: String,
+    pub added_label_ids: Vec<String>,
+    pub removed_label_ids: Vec<String>,
+}
+
+impl LabelChange {
+    pub fn adds(&self, label_id: &str) -> bool {
+        self.added_label_ids.iter().any(|l| l == label_id)
+    }
+
+    pub fn removes(&self, label_id: &str) -> bool {
+        self.removed_label_ids.iter().any(|l| l == label_id)
+    }
+
+    /// Apply the change to a message's label list
+    pub fn apply_to(&self, label_ids: &mut Vec<String>) {
+        label_ids.retain(|l| !self.removes(l));
+        for label in &self.added_label_ids {
+            if !label_ids.contains(label) {
+                label_ids.push(label.clone());
+            }
         }
     }
+}
 
-    pub async fn get_profile(
-        &self,
-    ) -> Result<GmailProfile, Box<dyn std::error::Error + Send + Sync>> {
-        let url = "https://gmail.googleapis.com/gmail/v1/users/me/profile";
+/// Everything that changed in the mailbox since a stored history id
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct MailboxDelta {
+    /// New inbox messages, oldest first
+    pub added: Vec<String>,
+    /// New messages Gmail filed straight into Spam, oldest first
+    #[serde(default)]
+    pub spam_added: Vec<String>,
+    pub deleted: Vec<String>,
+    pub label_changes: Vec<LabelChange>,
+    /// Where the next check should start from
+    pub history_id: String,
+    /// The start id was missing or too old for history.list, so this delta
+    /// only establishes a new starting point
+    pub resynced: bool,
+    /// The start id had expired, so this delta was rebuilt by re-listing
+    /// recent inbox mail against what was last seen
+    #[serde(default)]
+    pub recovered: bool,
+}
 
-        let response = self
-            .client
-            .get(url)
-            .bearer_auth(&self.access_token)
-            .send()
-            .await?;
+/// Newest inbox messages re-listed to bridge an expired history id
+pub const GAP_RELIST_LIMIT: u32 = 100;
+
+impl MailboxDelta {
+    /// Fold history records, oldest first, into one delta. A message added
+    /// and deleted inside the window is reported as neither, and label changes
+    /// on deleted messages are dropped.
+    pub fn from_records(records: &[HistoryRecord], history_id: String) -> Self {
+        let mut delta = MailboxDelta {
+            history_id,
+            ..Default::default()
+        };
 
-        if !response.status().is_success() {
-            return Err(format!("Gmail API error: {}", response.status()).into());
+        for record in records {
+            for added in &record.messages_added {
+                let message = &added.message;
+                if message.label_ids.iter().any(|l| l == "INBOX")
+                    && !delta.added.contains(&message.id)
+                {
+                    delta.added.push(message.id.clone());
+                } else if message.label_ids.iter().any(|l| l == "SPAM")
+                    && !delta.spam_added.contains(&message.id)
+                {
+                    delta.spam_added.push(message.id.clone());
+                }
+            }
+            for (change, is_add) in record
+                .labels_added
+                .iter()
+                .map(|c| (c, true))
+                .chain(record.labels_removed.iter().map(|c| (c, false)))
+            {
+                delta.record_label_change(change, is_add);
+            }
+            for deleted in &record.messages_deleted {
+                let id = &deleted.message.id;
+                let was_added = delta.added.contains(id) || delta.spam_added.contains(id);
+                delta.added.retain(|a| a != id);
+                delta.spam_added.retain(|a| a != id);
+                delta.label_changes.retain(|c| c.message_id != *id);
+                if !was_added && !delta.deleted.contains(id) {
+                    delta.deleted.push(id.clone());
+                }
+            }
         }
 
-        let profile: GmailProfile = response.json().await?;
-        Ok(profile)
+        delta
+            .label_changes
+            .retain(|c| !c.added_label_ids.is_empty() || !c.removed_label_ids.is_empty());
+        delta
     }
 
-    pub async fn list_messages(
-        &self,
-        max_results: Option<u32>,
-        page_token: Option<&str>,
-        query: Option<&str>,
-    ) -> Result<GmailResponse, Box<dyn std::error::Error + Send + Sync>> {
-        let mut url = "https://gmail.googleapis.com/gmail/v1/users/me/messages".to_string();
-        let mut params = Vec::new();
+    /// Rebuild the delta across a history gap by comparing the inbox as last
+    /// seen (`known`) with a fresh listing of its newest `limit` messages
+    /// (`recent`), both newest first. Messages that left the inbox are
+    /// reported as losing INBOX, since archived and deleted look alike here.
+    pub fn reconcile(
+        known: &[MessageLabels],
+        recent: &[MessageLabels],
+        limit: usize,
+        history_id: String,
+    ) -> Self {
+        let mut delta = MailboxDelta {
+            history_id,
+            recovered: true,
+            ..Default::default()
+        };
+        let find_known = |id: &str| known.iter().find(|k| k.id == id);
 
-        if let Some(max) = max_results {
-            params.push(format!("maxResults={}", max));
-        }
+        // Unknown mail above the newest known message is new; unknown mail
+        // between known messages was moved back into the inbox; anything
+        // below the oldest known message is just past the cached page
+        let newest_known = recent
+            .iter()
+            .position(|m| find_known(&m.id).is_some())
+            .unwrap_or(recent.len());
+        let oldest_known = recent
+            .iter()
+            .rposition(|m| find_known(&m.id).is_some())
+            .map_or(recent.len(), |index| index + 1);
+        delta.added = recent[..newest_known]
+            .iter()
+            .rev()
+            .map(|m| m.id.clone())
+            .collect();
 
-        if let Some(token) = page_token {
-            params.push(format!("pageToken={}", token));
+        for message in &recent[newest_known..oldest_known] {
+            let unread = message.label_ids.iter().any(|l| l == "UNREAD");
+            let mut change = LabelChange {
+                message_id: message.id.clone(),
+                thread_id: message.thread_id.clone(),
+                ..Default::default()
+            };
+            match find_known(&message.id) {
+                None => change.added_label_ids.push("INBOX".to_string()),
+                Some(seen) => {
+                    let was_unread = seen.label_ids.iter().any(|l| l == "UNREAD");
+                    if unread && !was_unread {
+                        change.added_label_ids.push("UNREAD".to_string());
+                    } else if !unread && was_unread {
+                        change.removed_label_ids.push("UNREAD".to_string());
+                    }
+                }
+            }
+            if !change.added_label_ids.is_empty() || !change.removed_label_ids.is_empty() {
+                delta.label_changes.push(change);
+            }
         }
 
-        if let Some(q) = query {
-            params.push(format!("q={}", urlencoding::encode(q)));
+        // A known message older than every one still listed may only have
+        // been pushed past a full listing by newer mail
+        let is_listed = |id: &str| recent.iter().any(|m| m.id == id);
+        let oldest_listed = known.iter().rposition(|k| is_listed(&k.id));
+        for (index, seen) in known.iter().enumerate() {
+            let left = !is_listed(&seen.id)
+                && (recent.len() < limit || oldest_listed.is_some_and(|oldest| index < oldest));
+            if left {
+                delta.label_changes.push(LabelChange {
+                    message_id: seen.id.clone(),
+                    thread_id: seen.thread_id.clone(),
+                    added_label_ids: Vec::new(),
+                    removed_label_ids: vec!["INBOX".to_string()],
+                });
+            }
         }
 
-        if !params.is_empty() {
-            url.push('?');
-            url.push_str(&params.join("&"));
-        }
+        delta
+    }
 
-        let response = self
-            .client
-            .get(&url)
-            .bearer_auth(&self.access_token)
-            .send()
-            .await?;
+    fn record_label_change(&mut self, change: &HistoryLabelChange, is_add: bool) {
+        let message = &change.message;
+        let index = match self
+            .label_changes
+            .iter()
+            .position(|c| c.message_id == message.id)
+        {
+            Some(index) => index,
+            None => {
+                self.label_changes.push(LabelChange {
+                    message_id: message.id.clone(),
+                    thread_id: message.thread_id.clone(),
+                    ..Default::default()
+                });
+                self.label_changes.len() - 1
+            }
+        };
+        let entry = &mut self.label_changes[index];
+        let (gained, lost) = if is_add {
+            (&mut entry.added_label_ids, &mut entry.removed_label_ids)
+        } else {
+            (&mut entry.removed_label_ids, &mut entry.added_label_ids)
+        };
 
-        if !response.status().is_success() {
-            return Err(format!("Gmail API error: {}", response.status()).into());
+        for label in &change.label_ids {
+            // Adding then removing a label nets out to no change
+            if let Some(pos) = lost.iter().position(|l| l == label) {
+                lost.remove(pos);
+            } else if !gained.contains(label) {
+                gained.push(label.clone());
+            }
         }
-
-        let gmail_response: GmailResponse = response.json().await?;
-        Ok(gmail_response)
     }
+}
 
-    pub async fn get_message(
-        &self,
-        message_id: &str,
-    ) -> Result<GmailMessage, Box<dyn std::error::Error + Send + Sync>> {
-        let url = format!(
-            "https://gmail.googleapis.com/gmail/v1/users/me/messages/{}?format=full",
-            message_id
-        );
+/// Response of messages.attachments.get
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttachmentBody {
+    pub size: Option<u64>,
+    pub data: String,
+}
 
-        let response = self
-            .client
-            .get(&url)
-            .bearer_auth(&self.access_token)
-            .send()
-            .await?;
+/// Response of messages.get with format=raw
+#[derive(Debug, Deserialize)]
+struct RawMessage {
+    raw: String,
+}
 
-        if !response.status().is_success() {
-            return Err(format!("Gmail API error: {}", response.status()).into());
-        }
+/// A system or user label; counts are only present when fetched through labels.get
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GmailLabel {
+    pub id: String,
+    pub name: String,
+    /// "system" or "user"
+    #[serde(rename = "type")]
+    pub label_type: Option<String>,
+    #[serde(rename = "messagesTotal")]
+    pub messages_total: Option<u32>,
+    #[serde(rename = "messagesUnread")]
+    pub messages_unread: Option<u32>,
+    #[serde(rename = "threadsTotal")]
+    pub threads_total: Option<u32>,
+    #[serde(rename = "threadsUnread")]
+    pub threads_unread: Option<u32>,
+}
 
-        let message: GmailMessage = response.json().await?;
-        Ok(message)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GmailLabelsResponse {
+    pub labels: Option<Vec<GmailLabel>>,
+}
+
+/// Incremental decoder for an attachments.get response body. It skips ahead to
+/// the `"data"` string and decodes its base64url contents in whole 4-character
+/// groups, so only a few bytes are held back between chunks.
+#[derive(Debug, Default)]
+pub struct AttachmentStreamDecoder {
+    /// JSON seen before the data string starts; small, since it precedes the payload
+    prefix: Vec<u8>,
+    in_data: bool,
+    done: bool,
+    /// Base64 characters waiting for a full group
+    pending: Vec<u8>,
+    written: u64,
+}
+
+impl AttachmentStreamDecoder {
+    pub fn bytes_written(&self) -> u64 {
+        self.written
     }
 
-    pub async fn get_messages_batch(
-        &self,
-        message_ids: &[String],
-    ) -> Result<Vec<GmailMessage>, Box<dyn std::error::Error + Send + Sync>> {
-        // Use Gmail's batch API for better performance
-        if message_ids.is_empty() {
-            return Ok(Vec::new());
+    pub fn feed<W: std::io::Write>(
+        &mut self,
+        chunk: &[u8],
+        writer: &mut W,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.done {
+            return Ok(());
         }
 
-        // Gmail batch API has a limit of 100 requests per batch
-        let batch_size = std::cmp::min(message_ids.len(), 100);
-        let message_ids_batch = &message_ids[..batch_size];
+        let mut chunk = chunk;
+        if !self.in_data {
+            let already_scanned = self.prefix.len();
+            self.prefix.extend_from_slice(chunk);
+            match find_data_start(&self.prefix) {
+                Some(start) => {
+                    self.in_data = true;
+                    chunk = &chunk[start.saturating_sub(already_scanned)..];
+                    self.prefix = Vec::new();
+                }
+                None => return Ok(()),
+            }
+        }
 
-        let boundary = "batch_boundary_aisle3";
-        let mut batch_body = String::new();
+        let data = match chunk.iter().position(|&b| b == b'"') {
+            Some(end) => {
+                self.done = true;
+                &chunk[..end]
+            }
+            None => chunk,
+        };
+        self.pending.extend(
+            data.iter()
+                .filter(|b| !b.is_ascii_whitespace() && **b != b'='),
+        );
 
-        // Build multipart/mixed batch request
-        for (i, message_id) in message_ids_batch.iter().enumerate() {
-            batch_body.push_str(&format!("--{}\r\n", boundary));
-            batch_body.push_str("Content-Type: application/http\r\n");
-            batch_body.push_str(&format!("Content-ID: <item{}>\r\n\r\n", i));
-            batch_body.push_str(&format!(
-                "GET /gmail/v1/users/me/messages/{}?format=full HTTP/1.1\r\n",
-                message_id
-            ));
-            batch_body.push_str("Host: gmail.googleapis.com\r\n\r\n");
+        let complete = self.pending.len() / 4 * 4;
+        if complete > 0 {
+            let decoded = URL_SAFE_NO_PAD.decode(&self.pending[..complete])?;
+            writer.write_all(&decoded)?;
+            self.written += decoded.len() as u64;
+            self.pending.drain(..complete);
         }
-        batch_body.push_str(&format!("--{}--\r\n", boundary));
-
-        let url = "https://gmail.googleapis.com/batch/gmail/v1";
-        let response = self
-            .client
-            .post(url)
-            .bearer_auth(&self.access_token)
-            .header(
-                "Content-Type",
-                format!("multipart/mixed; boundary={}", boundary),
-            )
-            .body(batch_body)
-            .send()
-            .await?;
+        Ok(())
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            println!("Gmail Batch API error response: {}", error_text);
-            return Err(format!("Gmail Batch API error: {}", error_text).into());
+    /// Flush the last partial group, returning the total bytes written
+    pub fn finish<W: std::io::Write>(
+        mut self,
+        writer: &mut W,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        if !self.in_data {
+            return Err("Attachment response had no data".into());
+        }
+        if !self.pending.is_empty() {
+            let decoded = URL_SAFE_NO_PAD.decode(&self.pending)?;
+            writer.write_all(&decoded)?;
+            self.written += decoded.len() as u64;
         }
+        writer.flush()?;
+        Ok(self.written)
+    }
+}
 
-        let response_text = response.text().await?;
+/// Offset just past the opening quote of the `"data"` value
+fn find_data_start(json: &[u8]) -> Option<usize> {
+    let key = b"\"data\"";
+    let key_end = json.windows(key.len()).position(|w| w == key)? + key.len();
+    let rest = &json[key_end..];
+    let colon = rest.iter().position(|b| !b.is_ascii_whitespace())?;
+    if rest[colon] != b':' {
+        return None;
+    }
+    let quote = rest[colon + 1..]
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())?
+        + colon
+        + 1;
+    (rest[quote] == b'"').then_some(key_end + quote + 1)
+}
 
-        // Parse batch response - Gmail uses different boundary format in response
-        let mut messages = Vec::new();
+/// An address the account may send as, from settings.sendAs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendAsAlias {
+    #[serde(rename = "sendAsEmail")]
+    pub send_as_email: String,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    #[serde(rename = "isPrimary")]
+    pub is_primary: Option<bool>,
+    #[serde(rename = "isDefault")]
+    pub is_default: Option<bool>,
+    #[serde(rename = "verificationStatus")]
+    pub verification_status: Option<String>,
+    /// HTML signature set in Gmail for this address
+    #[serde(default)]
+    pub signature: Option<String>,
+}
 
-        // Gmail generates its own boundary in the response, extract it from the first boundary marker
-        let response_boundary = if let Some(first_boundary_pos) = response_text.find("--batch_") {
-            // Extract just the boundary name (without --)
-            let boundary_start = first_boundary_pos + 2; // Skip the --
-            if let Some(boundary_end) = response_text[boundary_start..].find('\n') {
-                &response_text[boundary_start..boundary_start + boundary_end]
-            } else {
-                boundary // Fallback to our boundary
+impl SendAsAlias {
+    /// The primary address is always usable; others only once verified
+    pub fn can_send(&self) -> bool {
+        self.is_primary.unwrap_or(false) || self.verification_status.as_deref() == Some("accepted")
+    }
+
+    /// Value for the From header
+    pub fn mailbox(&self) -> String {
+        match self.display_name.as_deref().filter(|name| !name.is_empty()) {
+            Some(name) => format!("{} <{}>", name, self.send_as_email),
+            None => self.send_as_email.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SendAsResponse {
+    #[serde(rename = "sendAs")]
+    pub send_as: Option<Vec<SendAsAlias>>,
+}
+
+/// Which incoming messages a filter matches. Unset fields match anything.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilterCriteria {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// Gmail search syntax, e.g. "list:dev.example.com"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(
+        rename = "negatedQuery",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub negated_query: Option<String>,
+    #[serde(
+        rename = "hasAttachment",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub has_attachment: Option<bool>,
+}
+
+impl FilterCriteria {
+    pub fn is_empty(&self) -> bool {
+        let blank = |field: &Option<String>| field.as_deref().is_none_or(|v| v.trim().is_empty());
+        blank(&self.from)
+            && blank(&self.to)
+            && blank(&self.subject)
+            && blank(&self.query)
+            && blank(&self.negated_query)
+            && self.has_attachment != Some(true)
+    }
+}
+
+/// What a filter does to matching messages. Skipping the inbox is removing
+/// the INBOX label; marking read is removing UNREAD.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilterAction {
+    #[serde(rename = "addLabelIds", default, skip_serializing_if = "Vec::is_empty")]
+    pub add_label_ids: Vec<String>,
+    #[serde(
+        rename = "removeLabelIds",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub remove_label_ids: Vec<String>,
+    /// Must be a verified forwarding address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forward: Option<String>,
+}
+
+impl FilterAction {
+    pub fn is_empty(&self) -> bool {
+        self.add_label_ids.is_empty() && self.remove_label_ids.is_empty() && self.forward.is_none()
+    }
+}
+
+/// Server-side Gmail filter (settings.filters); Gmail applies it to new mail
+/// even while the app is closed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GmailFilter {
+    /// Assigned by Gmail on create
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub criteria: FilterCriteria,
+    pub action: FilterAction,
+}
+
+#[derive(Debug, Deserialize)]
+struct FilterListResponse {
+    filter: Option<Vec<GmailFilter>>,
+}
+
+/// An address mail can be forwarded to, once its owner has confirmed it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForwardingAddress {
+    #[serde(rename = "forwardingEmail")]
+    pub forwarding_email: String,
+    /// "accepted" or "pending"
+    #[serde(rename = "verificationStatus")]
+    pub verification_status: Option<String>,
+}
+
+impl ForwardingAddress {
+    pub fn is_verified(&self) -> bool {
+        self.verification_status.as_deref() == Some("accepted")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ForwardingAddressesResponse {
+    #[serde(rename = "forwardingAddresses")]
+    forwarding_addresses: Option<Vec<ForwardingAddress>>,
+}
+
+/// What happens to Gmail's copy of a forwarded message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ForwardingDisposition {
+    LeaveInInbox,
+    Archive,
+    Trash,
+    MarkRead,
+}
+
+/// Response of users.watch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchResponse {
+    /// History id current when the watch started
+    #[serde(rename = "historyId")]
+    pub history_id: String,
+    /// When the watch lapses, in epoch milliseconds as a decimal string
+    pub expiration: String,
+}
+
+impl WatchResponse {
+    /// Expiry in unix seconds
+    pub fn expires_at(&self) -> Option<u64> {
+        self.expiration.parse::<u64>().ok().map(|ms| ms / 1000)
+    }
+}
+
+/// Auto-forwarding of all incoming mail (settings.getAutoForwarding)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoForwarding {
+    pub enabled: bool,
+    /// Must be a verified forwarding address when enabled
+    #[serde(
+        rename = "emailAddress",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub email_address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disposition: Option<ForwardingDisposition>,
+}
+
+/// A Gmail draft as returned by drafts.create/update/list
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GmailDraft {
+    pub id: String,
+    pub message: Option<GmailMessageRef>,
+}
+
+/// A draft with its full message, as returned by drafts.get
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GmailDraftMessage {
+    pub id: String,
+    pub message: GmailMessage,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GmailDraftsResponse {
+    pub drafts: Option<Vec<GmailDraft>>,
+    #[serde(rename = "nextPageToken")]
+    pub next_page_token: Option<String>,
+    #[serde(rename = "resultSizeEstimate")]
+    pub result_size_estimate: Option<u32>,
+}
+
+/// A structured search that renders to Gmail query syntax, so callers don't
+/// assemble query strings by hand. Unset fields are left out of the query;
+/// build one with struct update syntax over `SearchQuery::default()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchQuery {
+    /// Free text matched anywhere in the message
+    pub text: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub subject: Option<String>,
+    pub has_attachment: bool,
+    /// Messages before this day (exclusive)
+    pub before: Option<NaiveDate>,
+    /// Messages on or after this day
+    pub after: Option<NaiveDate>,
+    /// Label name as shown in Gmail
+    pub label: Option<String>,
+    /// Some(true) for unread only, Some(false) for read only
+    pub is_unread: Option<bool>,
+}
+
+impl SearchQuery {
+    /// Render as a Gmail search string, e.g. `from:alice subject:"q3 report" has:attachment`
+    pub fn to_query_string(&self) -> String {
+        let mut terms = Vec::new();
+
+        if let Some(text) = non_empty(&self.text) {
+            terms.push(text.to_string());
+        }
+        for (operator, value) in [
+            ("from", &self.from),
+            ("to", &self.to),
+            ("subject", &self.subject),
+        ] {
+            if let Some(value) = non_empty(value) {
+                terms.push(format!("{}:{}", operator, quote_search_value(value)));
             }
-        } else {
+        }
+        if self.has_attachment {
+            terms.push("has:attachment".to_string());
+        }
+        if let Some(after) = self.after {
+            terms.push(format!("after:{}", after.format("%Y/%m/%d")));
+        }
+        if let Some(before) = self.before {
+            terms.push(format!("before:{}", before.format("%Y/%m/%d")));
+        }
+        if let Some(label) = non_empty(&self.label) {
+            // Gmail writes spaces in label names as hyphens
+            let label = label.split_whitespace().collect::<Vec<_>>().join("-");
+            terms.push(format!("label:{}", quote_search_value(&label)));
+        }
+        match self.is_unread {
+            Some(true) => terms.push("is:unread".to_string()),
+            Some(false) => terms.push("is:read".to_string()),
+            None => {}
+        }
+
+        terms.join(" ")
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// Quote values containing spaces; Gmail has no escape for embedded quotes, so they are dropped
+fn quote_search_value(value: &str) -> String {
+    let value = value.replace('"', "");
+    if value.contains(char::is_whitespace) {
+        format!("\"{}\"", value)
+    } else {
+        value
+    }
+}
+
+/// Gmail rejects messages whose attachments add up to more than this
+pub const MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Check a total attachment size against Gmail's limit
+pub fn check_attachment_size(total_bytes: u64) -> Result<(), String> {
+    if total_bytes > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "Attachments add up to {:.1} MB, over Gmail's 25 MB limit. Remove some files or share them as links instead.",
+            total_bytes as f64 / (1024.0 * 1024.0)
+        ));
+    }
+    Ok(())
+}
+
+/// A file sent with an outgoing message
+#[derive(Debug, Clone, Default)]
+pub struct OutgoingAttachment {
+    pub filename: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// An outgoing message before it is encoded for the Gmail API
+#[derive(Debug, Clone, Default)]
+pub struct OutgoingEmail {
+    /// Send-as alias, e.g. "Support <support@example.com>"; Gmail uses the primary address when unset
+    pub from: Option<String>,
+    pub to: String,
+    pub cc: Option<String>,
+    pub bcc: Option<String>,
+    pub subject: String,
+    pub body: String,
+    pub in_reply_to: Option<String>,
+    pub references: Option<String>,
+    pub attachments: Vec<OutgoingAttachment>,
+}
+
+/// Whether a body is sent as HTML (with a plain-text alternative) rather than plain text
+pub fn is_html_body(body: &str) -> bool {
+    body.contains('<') && (body.contains("</") || body.contains("/>"))
+}
+
+/// How `import_message` adds a message to the mailbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// messages.import: scanned and classified like delivered mail
+    #[default]
+    Import,
+    /// messages.insert: stored as-is, skipping spam and virus checks
+    Insert,
+}
+
+/// A raw message as a .eml file: every line ending is CRLF, as RFC 5322
+/// requires, whatever mix the stored copy had
+pub fn to_eml(raw: &[u8]) -> Vec<u8> {
+    let mut eml = Vec::with_capacity(raw.len() + raw.len() / 40);
+    for (i, &byte) in raw.iter().enumerate() {
+        match byte {
+            b'\n' if i == 0 || raw[i - 1] != b'\r' => eml.extend_from_slice(b"\r\n"),
+            b'\r' if raw.get(i + 1) != Some(&b'\n') => eml.extend_from_slice(b"\r\n"),
+            _ => eml.push(byte),
+        }
+    }
+    if !eml.ends_with(b"\r\n") {
+        eml.extend_from_slice(b"\r\n");
+    }
+    eml
+}
+
+/// One header line; CR and LF are folded to spaces so a value can't start a new header
+fn header_line(name: &str, value: &str) -> String {
+    format!("{}: {}\r\n", name, value.replace(['\r', '\n'], " "))
+}
+
+/// UTF-8 bytes per encoded word: 42 encode to 56 base64 characters, so a
+/// word after "Subject: " still fits a 78-character line
+const ENCODED_WORD_BYTES: usize = 42;
+
+/// A header line for free text such as the subject. Values with non-ASCII
+/// text, or an `=?` a reader would take for an encoded word, are sent as
+/// UTF-8 encoded words folded one per line so they read back as written.
+fn text_header_line(name: &str, value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    let value = value.trim();
+    if value.is_ascii() && !value.contains("=?") {
+        return header_line(name, value);
+    }
+
+    // Split between characters, never inside one
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for c in value.chars() {
+        if chunk.len() + c.len_utf8() > ENCODED_WORD_BYTES {
+            words.push(format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+            chunk.clear();
+        }
+        chunk.push(c);
+    }
+    words.push(format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+    format!("{}: {}\r\n", name, words.join("\r\n "))
+}
+
+/// `base`, suffixed until it no longer occurs in `content`, so body text can't
+/// close a part early
+fn unique_boundary(base: &str, content: &str) -> String {
+    let mut boundary = base.to_string();
+    let mut suffix = 0;
+    while content.contains(&boundary) {
+        suffix += 1;
+        boundary = format!("{}_{}", base, suffix);
+    }
+    boundary
+}
+
+impl OutgoingEmail {
+    pub fn attachment_bytes(&self) -> u64 {
+        self.attachments.iter().map(|a| a.data.len() as u64).sum()
+    }
+
+    /// Render the message in RFC 2822 format
+    pub fn to_rfc2822(&self) -> String {
+        // Create the email message in RFC 2822 format
+        let mut email_content = String::new();
+
+        if let Some(from) = self.from.as_deref().filter(|from| !from.is_empty()) {
+            email_content.push_str(&header_line("From", from));
+        }
+        email_content.push_str(&header_line("To", &self.to));
+        if let Some(cc) = self.cc.as_deref().filter(|cc| !cc.is_empty()) {
+            email_content.push_str(&header_line("Cc", cc));
+        }
+        if let Some(bcc) = self.bcc.as_deref().filter(|bcc| !bcc.is_empty()) {
+            email_content.push_str(&header_line("Bcc", bcc));
+        }
+        email_content.push_str(&text_header_line("Subject", &self.subject));
+        email_content.push_str("MIME-Version: 1.0\r\n");
+
+        // Add reply headers if this is a reply
+        if let Some(reply_to) = &self.in_reply_to {
+            email_content.push_str(&header_line("In-Reply-To", reply_to));
+        }
+        if let Some(refs) = &self.references {
+            email_content.push_str(&header_line("References", refs));
+        }
+
+        if self.attachments.is_empty() {
+            email_content.push_str(&self.body_part());
+            return email_content;
+        }
+
+        // Body first, then one base64 part per file
+        let boundary = unique_boundary("boundary_email_mixed_67890", &self.body);
+        email_content.push_str(&format!(
+            "Content-Type: multipart/mixed; boundary=\"{}\"\r\n\r\n",
             boundary
-        };
+        ));
+        email_content.push_str(&format!("--{}\r\n", boundary));
+        email_content.push_str(&self.body_part());
+        email_content.push_str("\r\n");
 
-        let parts: Vec<&str> = response_text
-            .split(&format!("--{}", response_boundary))
-            .collect();
+        for attachment in &self.attachments {
+            let filename = attachment.filename.replace(['"', '\r', '\n'], "");
+            email_content.push_str(&format!("--{}\r\n", boundary));
+            email_content.push_str(&format!(
+                "Content-Type: {}; name=\"{}\"\r\n",
+                attachment.mime_type, filename
+            ));
+            email_content.push_str(&format!(
+                "Content-Disposition: attachment; filename=\"{}\"\r\n",
+                filename
+            ));
+            email_content.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
 
-        for part in parts.iter().skip(1) {
-            // Skip the first empty part
-            if part.contains("--") && part.len() < 10 {
-                continue; // Skip the final boundary marker
+            // MIME caps encoded lines at 76 characters
+            let encoded = STANDARD.encode(&attachment.data);
+            for line in encoded.as_bytes().chunks(76) {
+                email_content.push_str(std::str::from_utf8(line).unwrap());
+                email_content.push_str("\r\n");
             }
+        }
 
-            // Find the JSON content in each part
-            if let Some(json_start) = part.find('{') {
-                if let Some(json_end) = part.rfind('}') {
-                    let json_content = &part[json_start..=json_end];
+        email_content.push_str(&format!("--{}--\r\n", boundary));
+        email_content
+    }
 
-                    if let Ok(message) = serde_json::from_str::<GmailMessage>(json_content) {
-                        messages.push(message);
-                    }
+    /// Content headers and body of the text part
+    fn body_part(&self) -> String {
+        let is_html = is_html_body(&self.body);
+
+        let mut email_content = String::new();
+
+        if is_html {
+            // Multipart email with both plain text and HTML
+            let boundary = unique_boundary("boundary_email_content_12345", &self.body);
+            email_content.push_str(&format!(
+                "Content-Type: multipart/alternative; boundary=\"{}\"\r\n",
+                boundary
+            ));
+            email_content.push_str("\r\n"); // Empty line to separate headers from body
+
+            // Plain text part (strip HTML for plain text version)
+            email_content.push_str(&format!("--{}\r\n", boundary));
+            email_content.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+            email_content.push_str("Content-Transfer-Encoding: 7bit\r\n\r\n");
+
+            // Simple HTML to text conversion (remove tags)
+            let plain_text = self
+                .body
+                .replace("<br>", "\n")
+                .replace("<br/>", "\n")
+                .replace("<br />", "\n")
+                .replace("</p>", "\n\n")
+                .replace("</div>", "\n")
+                .replace("</li>", "\n");
+
+            // Remove all HTML tags with regex-like replacement
+            let mut plain_body = String::new();
+            let mut in_tag = false;
+            for ch in plain_text.chars() {
+                match ch {
+                    '<' => in_tag = true,
+                    '>' => in_tag = false,
+                    _ if !in_tag => plain_body.push(ch),
+                    _ => {}
                 }
             }
+
+            email_content.push_str(plain_body.trim());
+            email_content.push_str("\r\n\r\n");
+
+            // HTML part
+            email_content.push_str(&format!("--{}\r\n", boundary));
+            email_content.push_str("Content-Type: text/html; charset=utf-8\r\n");
+            email_content.push_str("Content-Transfer-Encoding: 7bit\r\n\r\n");
+            email_content.push_str(&self.body);
+            email_content.push_str("\r\n\r\n");
+
+            // End boundary
+            email_content.push_str(&format!("--{}--\r\n", boundary));
+        } else {
+            // Plain text email
+            email_content.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+            email_content.push_str("\r\n"); // Empty line to separate headers from body
+            email_content.push_str(&self.body);
+        }
+
+        email_content
+    }
+}
+
+/// An error status from Gmail, kept typed so callers can tell a flaky
+/// connection from a request Gmail will never accept
+#[derive(Debug)]
+pub struct GmailApiError {
+    pub status: u16,
+    message: String,
+}
+
+impl GmailApiError {
+    /// Rate limiting and server-side failures usually clear up on their own
+    pub fn is_transient(&self) -> bool {
+        self.status == 429 || self.status >= 500
+    }
+
+    /// Gone, e.g. deleted in another client, or never existed
+    pub fn is_not_found(&self) -> bool {
+        self.status == 404
+    }
+}
+
+impl std::fmt::Display for GmailApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for GmailApiError {}
+
+/// Whether a failed client call is worth trying again later: timeouts,
+/// dropped connections and transient Gmail statuses
+pub fn is_transient_error(error: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    if let Some(api) = error.downcast_ref::<GmailApiError>() {
+        return api.is_transient();
+    }
+    if let Some(http) = error.downcast_ref::<reqwest::Error>() {
+        return http.is_timeout() || http.is_connect() || http.is_request();
+    }
+    false
+}
+
+/// Whether a failed client call means Gmail doesn't have what was asked for
+pub fn is_not_found_error(error: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    error
+        .downcast_ref::<GmailApiError>()
+        .is_some_and(GmailApiError::is_not_found)
+}
+
+/// Production Gmail API host
+const GMAIL_BASE_URL: &str = "https://gmail.googleapis.com";
+
+#[derive(Clone)]
+pub struct GmailClient {
+    client: Client,
+    access_token: String,
+    /// Scheme and host every request goes to; `test_support::fake_gmail`
+    /// points it at a local server
+    pub(crate) base_url: String,
+}
+
+impl GmailClient {
+    pub fn new(tokens: &AuthTokens) -> Self {
+        Self {
+            client: Client::new(),
+            access_token: tokens.access_token.clone(),
+            base_url: GMAIL_BASE_URL.to_string(),
+        }
+    }
+
+    /// The token requests are made with
+    pub(crate) fn access_token(&self) -> &str {
+        &self.access_token
+    }
+
+    pub async fn get_profile(
+        &self,
+    ) -> Result<GmailProfile, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/profile", self.base_url);
+
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Gmail API error: {}", response.status()).into());
         }
 
-        // If batch API fails, fallback to individual requests
-        if messages.is_empty() && !message_ids_batch.is_empty() {
-            return self.get_messages_individual(message_ids_batch).await;
+        let profile: GmailProfile = response.json().await?;
+        Ok(profile)
+    }
+
+    /// List the addresses this account can send from
+    pub async fn list_send_as(
+        &self,
+    ) -> Result<Vec<SendAsAlias>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/settings/sendAs", self.base_url);
+
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail sendAs API error: {}", error_text).into());
+        }
+
+        let aliases: SendAsResponse = response.json().await?;
+        Ok(aliases.send_as.unwrap_or_default())
+    }
+
+    pub async fn list_filters(
+        &self,
+    ) -> Result<Vec<GmailFilter>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/settings/filters", self.base_url);
+
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail filters API error: {}", error_text).into());
+        }
+
+        let filters: FilterListResponse = response.json().await?;
+        Ok(filters.filter.unwrap_or_default())
+    }
+
+    /// Create a filter; it only applies to mail that arrives afterwards
+    pub async fn create_filter(
+        &self,
+        criteria: &FilterCriteria,
+        action: &FilterAction,
+    ) -> Result<GmailFilter, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/settings/filters", self.base_url);
+
+        let filter_request = serde_json::json!({
+            "criteria": criteria,
+            "action": action,
+        });
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.access_token)
+            .json(&filter_request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail filters API error: {}", error_text).into());
+        }
+
+        let filter: GmailFilter = response.json().await?;
+        Ok(filter)
+    }
+
+    pub async fn delete_filter(
+        &self,
+        filter_id: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/settings/filters/{}",
+            self.base_url, filter_id
+        );
+
+        let response = self
+            .client
+            .delete(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail filters API error: {}", error_text).into());
+        }
+
+        Ok(())
+    }
+
+    pub async fn list_forwarding_addresses(
+        &self,
+    ) -> Result<Vec<ForwardingAddress>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/settings/forwardingAddresses",
+            self.base_url
+        );
+
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail forwarding API error: {}", error_text).into());
+        }
+
+        let addresses: ForwardingAddressesResponse = response.json().await?;
+        Ok(addresses.forwarding_addresses.unwrap_or_default())
+    }
+
+    pub async fn get_auto_forwarding(
+        &self,
+    ) -> Result<AutoForwarding, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/settings/autoForwarding",
+            self.base_url
+        );
+
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail forwarding API error: {}", error_text).into());
+        }
+
+        let forwarding: AutoForwarding = response.json().await?;
+        Ok(forwarding)
+    }
+
+    /// Gmail only accepts this from Workspace accounts with domain-wide
+    /// delegation; personal accounts get a 403 and must use Gmail's settings page
+    pub async fn update_auto_forwarding(
+        &self,
+        forwarding: &AutoForwarding,
+    ) -> Result<AutoForwarding, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/settings/autoForwarding",
+            self.base_url
+        );
+
+        let response = self
+            .client
+            .put(&url)
+            .bearer_auth(&self.access_token)
+            .json(forwarding)
+            .send()
+            .await?;
+
+        if response.status() == reqwest::StatusCode::FORBIDDEN {
+            return Err(
+                "Gmail doesn't allow this account to change forwarding from other apps; \
+                 use the Forwarding tab in Gmail's settings"
+                    .into(),
+            );
+        }
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail forwarding API error: {}", error_text).into());
+        }
+
+        let forwarding: AutoForwarding = response.json().await?;
+        Ok(forwarding)
+    }
+
+    /// List all labels; labels.list leaves out message and unread counts
+    pub async fn list_labels(
+        &self,
+    ) -> Result<Vec<GmailLabel>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/labels", self.base_url);
+
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Gmail API error: {}", response.status()).into());
+        }
+
+        let labels: GmailLabelsResponse = response.json().await?;
+        Ok(labels.labels.unwrap_or_default())
+    }
+
+    /// List all labels with their message and unread counts, fetched through batched labels.get
+    pub async fn get_labels_with_counts(
+        &self,
+    ) -> Result<Vec<GmailLabel>, Box<dyn std::error::Error + Send + Sync>> {
+        let labels = self.list_labels().await?;
+
+        let mut detailed = Vec::with_capacity(labels.len());
+        for chunk in labels.chunks(100) {
+            let paths: Vec<String> = chunk
+                .iter()
+                .map(|label| format!("/gmail/v1/users/me/labels/{}", label.id))
+                .collect();
+
+            let fetched: Vec<GmailLabel> = self.batch_get(&paths).await.unwrap_or_default();
+            // Keep labels.list order and fall back to the count-less entry if a part is missing
+            for label in chunk {
+                match fetched.iter().find(|f| f.id == label.id) {
+                    Some(found) => detailed.push(found.clone()),
+                    None => detailed.push(label.clone()),
+                }
+            }
+        }
+
+        Ok(detailed)
+    }
+
+    /// One label with its message and thread counts
+    pub async fn get_label(
+        &self,
+        label_id: &str,
+    ) -> Result<GmailLabel, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/labels/{}", self.base_url, label_id);
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail labels API error: {}", error_text).into());
+        }
+
+        let label: GmailLabel = response.json().await?;
+        Ok(label)
+    }
+
+    /// Create a user label shown in both the label list and message list
+    pub async fn create_label(
+        &self,
+        name: &str,
+    ) -> Result<GmailLabel, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/labels", self.base_url);
+
+        let label_request = serde_json::json!({
+            "name": name,
+            "labelListVisibility": "labelShow",
+            "messageListVisibility": "show"
+        });
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.access_token)
+            .json(&label_request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail labels API error: {}", error_text).into());
+        }
+
+        let label: GmailLabel = response.json().await?;
+        Ok(label)
+    }
+
+    /// Rename a user label; nested labels are named "Parent/Child"
+    pub async fn rename_label(
+        &self,
+        label_id: &str,
+        new_name: &str,
+    ) -> Result<GmailLabel, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/labels/{}", self.base_url, label_id);
+
+        let response = self
+            .client
+            .patch(&url)
+            .bearer_auth(&self.access_token)
+            .json(&serde_json::json!({ "name": new_name }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail labels API error: {}", error_text).into());
+        }
+
+        let label: GmailLabel = response.json().await?;
+        Ok(label)
+    }
+
+    /// Delete a user label; messages keep everything except this label
+    pub async fn delete_label(
+        &self,
+        label_id: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/labels/{}", self.base_url, label_id);
+
+        let response = self
+            .client
+            .delete(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail labels API error: {}", error_text).into());
+        }
+
+        Ok(())
+    }
+
+    pub async fn list_messages(
+        &self,
+        max_results: Option<u32>,
+        page_token: Option<&str>,
+        query: Option<&str>,
+    ) -> Result<GmailResponse, Box<dyn std::error::Error + Send + Sync>> {
+        let mut url = format!("{}/gmail/v1/users/me/messages", self.base_url);
+        let mut params = Vec::new();
+
+        if let Some(max) = max_results {
+            params.push(format!("maxResults={}", max));
+        }
+
+        if let Some(token) = page_token {
+            params.push(format!("pageToken={}", token));
+        }
+
+        if let Some(q) = query {
+            params.push(format!("q={}", urlencoding::encode(q)));
+        }
+
+        if !params.is_empty() {
+            url.push('?');
+            url.push_str(&params.join("&"));
+        }
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Gmail API error: {}", response.status()).into());
+        }
+
+        let gmail_response: GmailResponse = response.json().await?;
+        Ok(gmail_response)
+    }
+
+    /// Page through a query collecting message ids, stopping once `limit` ids are found
+    pub async fn list_all_message_ids(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut ids = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let response = self
+                .list_messages(Some(500), page_token.as_deref(), Some(query))
+                .await?;
+
+            ids.extend(
+                response
+                    .messages
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|m| m.id),
+            );
+
+            if ids.len() >= limit {
+                ids.truncate(limit);
+                break;
+            }
+
+            match response.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => break,
+            }
+        }
+
+        Ok(ids)
+    }
+
+    pub async fn list_threads(
+        &self,
+        max_results: Option<u32>,
+        page_token: Option<&str>,
+        query: Option<&str>,
+    ) -> Result<GmailThreadsResponse, Box<dyn std::error::Error + Send + Sync>> {
+        let mut params = Vec::new();
+
+        if let Some(max) = max_results {
+            params.push(format!("maxResults={}", max));
+        }
+
+        if let Some(token) = page_token {
+            params.push(format!("pageToken={}", token));
+        }
+
+        if let Some(q) = query {
+            params.push(format!("q={}", urlencoding::encode(q)));
+        }
+
+        self.thread_list(params).await
+    }
+
+    /// Threads carrying a label, matched by id so the label's name never has
+    /// to survive Gmail's search syntax
+    pub async fn list_threads_with_label(
+        &self,
+        label_id: &str,
+        max_results: u32,
+    ) -> Result<GmailThreadsResponse, Box<dyn std::error::Error + Send + Sync>> {
+        self.thread_list(vec![
+            format!("labelIds={}", urlencoding::encode(label_id)),
+            format!("maxResults={}", max_results),
+        ])
+        .await
+    }
+
+    async fn thread_list(
+        &self,
+        params: Vec<String>,
+    ) -> Result<GmailThreadsResponse, Box<dyn std::error::Error + Send + Sync>> {
+        let mut url = format!("{}/gmail/v1/users/me/threads", self.base_url);
+        if !params.is_empty() {
+            url.push('?');
+            url.push_str(&params.join("&"));
+        }
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Gmail API error: {}", response.status()).into());
+        }
+
+        let threads_response: GmailThreadsResponse = response.json().await?;
+        Ok(threads_response)
+    }
+
+    pub async fn get_message(
+        &self,
+        message_id: &str,
+    ) -> Result<GmailMessage, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/messages/{}?format=full",
+            self.base_url, message_id
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Box::new(GmailApiError {
+                status: status.as_u16(),
+                message: format!("Gmail API error: {}", status),
+            }));
+        }
+
+        let message: GmailMessage = response.json().await?;
+        Ok(message)
+    }
+
+    /// Headers, labels and Gmail's size estimate, without the part tree
+    pub async fn get_message_metadata(
+        &self,
+        message_id: &str,
+    ) -> Result<GmailMessage, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/messages/{}?format=metadata",
+            self.base_url, message_id
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Box::new(GmailApiError {
+                status: status.as_u16(),
+                message: format!("Gmail API error: {}", status),
+            }));
+        }
+
+        let message: GmailMessage = response.json().await?;
+        Ok(message)
+    }
+
+    /// A large message as headers plus its text/plain body. The part tree
+    /// comes without body data, and the plain-text body is fetched on its own
+    /// when Gmail stores it as a separate attachment; an inline one is small,
+    /// so the message is fetched whole and cut down with `text_only`.
+    pub async fn get_message_text(
+        &self,
+        message_id: &str,
+    ) -> Result<GmailMessage, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/messages/{}?format=full&fields={}",
+            self.base_url,
+            message_id,
+            urlencoding::encode(&part_tree_fields())
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Box::new(GmailApiError {
+                status: status.as_u16(),
+                message: format!("Gmail API error: {}", status),
+            }));
+        }
+
+        let mut message: GmailMessage = response.json().await?;
+        let Some(body) = message.plain_text_body_mut() else {
+            return Ok(message.text_only());
+        };
+        let Some(attachment_id) = body.attachment_id.clone() else {
+            return Ok(self.get_message(message_id).await?.text_only());
+        };
+        let data = self.get_attachment(message_id, &attachment_id).await?;
+        body.data = Some(URL_SAFE.encode(data));
+        Ok(message.text_only())
+    }
+
+    /// Add a raw RFC 5322 message to the mailbox without sending it, dated by
+    /// its Date header. Returns the new message's id.
+    pub async fn import_message(
+        &self,
+        raw: &[u8],
+        label_ids: &[String],
+        mode: ImportMode,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let url = match mode {
+            ImportMode::Import => format!(
+                "{}/gmail/v1/users/me/messages/import?internalDateSource=dateHeader&neverMarkSpam=true",
+                self.base_url
+            ),
+            ImportMode::Insert => format!(
+                "{}/gmail/v1/users/me/messages?internalDateSource=dateHeader",
+                self.base_url
+            ),
+        };
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.access_token)
+            .json(&serde_json::json!({
+                "raw": URL_SAFE.encode(raw),
+                "labelIds": label_ids,
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail import API error: {}", error_text).into());
+        }
+
+        let labels: MessageLabels = response.json().await?;
+        Ok(labels.id)
+    }
+
+    /// The message exactly as received, headers included, for forwarding as
+    /// an attachment
+    pub async fn get_raw_message(
+        &self,
+        message_id: &str,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/messages/{}?format=raw",
+            self.base_url, message_id
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Gmail API error: {}", response.status()).into());
+        }
+
+        let message: RawMessage = response.json().await?;
+        let data = URL_SAFE_NO_PAD.decode(message.raw.trim_end_matches('='))?;
+        Ok(data)
+    }
+
+    /// Download an attachment's raw bytes through attachments.get
+    pub async fn get_attachment(
+        &self,
+        message_id: &str,
+        attachment_id: &str,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/messages/{}/attachments/{}",
+            self.base_url, message_id, attachment_id
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail attachments API error: {}", error_text).into());
+        }
+
+        let body: AttachmentBody = response.json().await?;
+        // Gmail sometimes omits base64 padding on attachment data
+        let data = URL_SAFE_NO_PAD.decode(body.data.trim_end_matches('='))?;
+        Ok(data)
+    }
+
+    /// Stream an attachment into `writer` without holding the whole payload in
+    /// memory. The base64 data is decoded as response chunks arrive, and
+    /// `on_progress` gets the number of decoded bytes written so far.
+    pub async fn stream_attachment<W, F>(
+        &self,
+        message_id: &str,
+        attachment_id: &str,
+        writer: &mut W,
+        mut on_progress: F,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>>
+    where
+        W: std::io::Write,
+        F: FnMut(u64),
+    {
+        let url = format!(
+            "{}/gmail/v1/users/me/messages/{}/attachments/{}",
+            self.base_url, message_id, attachment_id
+        );
+
+        let mut response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail attachments API error: {}", error_text).into());
+        }
+
+        let mut decoder = AttachmentStreamDecoder::default();
+        while let Some(chunk) = response.chunk().await? {
+            decoder.feed(&chunk, writer)?;
+            on_progress(decoder.bytes_written());
+        }
+        let written = decoder.finish(writer)?;
+        on_progress(written);
+        Ok(written)
+    }
+
+    pub async fn get_thread(
+        &self,
+        thread_id: &str,
+    ) -> Result<GmailThread, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/threads/{}?format=full",
+            self.base_url, thread_id
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Gmail API error: {}", response.status()).into());
+        }
+
+        let thread: GmailThread = response.json().await?;
+        Ok(thread)
+    }
+
+    pub async fn get_messages_batch(
+        &self,
+        message_ids: &[String],
+    ) -> Result<Vec<GmailMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        // Use Gmail's batch API for better performance
+        if message_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Gmail batch API has a limit of 100 requests per batch
+        let batch_size = std::cmp::min(message_ids.len(), 100);
+        let message_ids_batch = &message_ids[..batch_size];
+
+        let paths: Vec<String> = message_ids_batch
+            .iter()
+            .map(|id| format!("/gmail/v1/users/me/messages/{}?format=full", id))
+            .collect();
+
+        let messages: Vec<GmailMessage> = self.batch_get(&paths).await?;
+
+        // If batch API fails, fallback to individual requests
+        if messages.is_empty() && !message_ids_batch.is_empty() {
+            return self.get_messages_individual(message_ids_batch).await;
+        }
+
+        Ok(messages)
+    }
+
+    /// Fetch headers and labels only for up to 100 messages in one batch
+    pub async fn get_messages_metadata_batch(
+        &self,
+        message_ids: &[String],
+    ) -> Result<Vec<GmailMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        if message_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let batch_size = std::cmp::min(message_ids.len(), 100);
+        let paths: Vec<String> = message_ids[..batch_size]
+            .iter()
+            .map(|id| format!("/gmail/v1/users/me/messages/{}?format=metadata", id))
+            .collect();
+
+        self.batch_get(&paths).await
+    }
+
+    /// Fetch thread metadata (headers, labels, snippets) for up to 100 threads in one batch
+    pub async fn get_threads_batch(
+        &self,
+        thread_ids: &[String],
+    ) -> Result<Vec<GmailThread>, Box<dyn std::error::Error + Send + Sync>> {
+        if thread_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let batch_size = std::cmp::min(thread_ids.len(), 100);
+        let thread_ids_batch = &thread_ids[..batch_size];
+
+        let paths: Vec<String> = thread_ids_batch
+            .iter()
+            .map(|id| format!("/gmail/v1/users/me/threads/{}?format=metadata", id))
+            .collect();
+
+        let mut threads: Vec<GmailThread> = self.batch_get(&paths).await?;
+
+        // Batch responses are not guaranteed to be in request order
+        threads.sort_by_key(|t| thread_ids_batch.iter().position(|id| *id == t.id));
+
+        Ok(threads)
+    }
+
+    /// Issue a multipart/mixed batch of GET requests and parse each JSON response part
+    async fn batch_get<T: DeserializeOwned>(
+        &self,
+        paths: &[String],
+    ) -> Result<Vec<T>, Box<dyn std::error::Error + Send + Sync>> {
+        let boundary = "batch_boundary_aisle3";
+        let mut batch_body = String::new();
+
+        // Build multipart/mixed batch request
+        for (i, path) in paths.iter().enumerate() {
+            batch_body.push_str(&format!("--{}\r\n", boundary));
+            batch_body.push_str("Content-Type: application/http\r\n");
+            batch_body.push_str(&format!("Content-ID: <item{}>\r\n\r\n", i));
+            batch_body.push_str(&format!("GET {} HTTP/1.1\r\n", path));
+            batch_body.push_str("Host: gmail.googleapis.com\r\n\r\n");
+        }
+        batch_body.push_str(&format!("--{}--\r\n", boundary));
+
+        let url = format!("{}/batch/gmail/v1", self.base_url);
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.access_token)
+            .header(
+                "Content-Type",
+                format!("multipart/mixed; boundary={}", boundary),
+            )
+            .body(batch_body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            println!("Gmail Batch API error response: {}", error_text);
+            return Err(format!("Gmail Batch API error: {}", error_text).into());
+        }
+
+        let response_text = response.text().await?;
+
+        // Parse batch response - Gmail uses different boundary format in response
+        let mut items = Vec::new();
+
+        // Gmail generates its own boundary in the response, extract it from the first boundary marker
+        let response_boundary = if let Some(first_boundary_pos) = response_text.find("--batch_") {
+            // Extract just the boundary name (without --)
+            let boundary_start = first_boundary_pos + 2; // Skip the --
+            if let Some(boundary_end) = response_text[boundary_start..].find('\n') {
+                &response_text[boundary_start..boundary_start + boundary_end]
+            } else {
+                boundary // Fallback to our boundary
+            }
+        } else {
+            boundary
+        };
+
+        let parts: Vec<&str> = response_text
+            .split(&format!("--{}", response_boundary))
+            .collect();
+
+        for part in parts.iter().skip(1) {
+            // Skip the first empty part
+            if part.contains("--") && part.len() < 10 {
+                continue; // Skip the final boundary marker
+            }
+
+            // Find the JSON content in each part
+            if let Some(json_start) = part.find('{') {
+                if let Some(json_end) = part.rfind('}') {
+                    let json_content = &part[json_start..=json_end];
+
+                    if let Ok(item) = serde_json::from_str::<T>(json_content) {
+                        items.push(item);
+                    }
+                }
+            }
+        }
+
+        Ok(items)
+    }
+
+    // Fallback method for individual requests
+    async fn get_messages_individual(
+        &self,
+        message_ids: &[String],
+    ) -> Result<Vec<GmailMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut messages = Vec::new();
+
+        for message_id in message_ids.iter().take(20) {
+            // Limit to 20 for now
+            match self.get_message(message_id).await {
+                Ok(message) => messages.push(message),
+                Err(e) => eprintln!("Failed to fetch message {}: {}", message_id, e),
+            }
+        }
+
+        Ok(messages)
+    }
+
+    /// Ask Gmail to publish mailbox changes to a Cloud Pub/Sub topic
+    /// ("projects/<project>/topics/<topic>"). Watches lapse after 7 days, so
+    /// callers re-watch before `expires_at`.
+    pub async fn watch(
+        &self,
+        topic_name: &str,
+    ) -> Result<WatchResponse, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/watch", self.base_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(&self.access_token)
+            .json(&serde_json::json!({ "topicName": topic_name }))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await?;
+            return Err(Box::new(GmailApiError {
+                status: status.as_u16(),
+                message: format!("Gmail watch API error: {}", error_text),
+            }));
+        }
+
+        let watch: WatchResponse = response.json().await?;
+        Ok(watch)
+    }
+
+    /// Stop push notifications for the mailbox
+    pub async fn stop_watch(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/stop", self.base_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail stop API error: {}", error_text).into());
+        }
+
+        Ok(())
+    }
+
+    /// One page of mailbox changes after `start_history_id`
+    pub async fn list_history(
+        &self,
+        start_history_id: &str,
+        page_token: Option<&str>,
+    ) -> Result<GmailHistoryResponse, Box<dyn std::error::Error + Send + Sync>> {
+        let mut url = format!(
+            "{}/gmail/v1/users/me/history?startHistoryId={}",
+            self.base_url,
+            urlencoding::encode(start_history_id)
+        );
+        for history_type in [
+            "messageAdded",
+            "messageDeleted",
+            "labelAdded",
+            "labelRemoved",
+        ] {
+            url.push_str(&format!("&historyTypes={}", history_type));
+        }
+        if let Some(token) = page_token {
+            url.push_str(&format!("&pageToken={}", token));
+        }
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await?;
+            return Err(Box::new(GmailApiError {
+                status: status.as_u16(),
+                message: format!("Gmail history API error: {}", error_text),
+            }));
+        }
+
+        let history: GmailHistoryResponse = response.json().await?;
+        Ok(history)
+    }
+
+    /// Added, deleted and relabelled messages since `start_history_id`, in one
+    /// delta. Without a start id, or when Gmail has expired it (404), the delta
+    /// is empty and just carries the mailbox's current history id.
+    pub async fn check_for_new_emails(
+        &self,
+        start_history_id: Option<&str>,
+        known_inbox: &[MessageLabels],
+    ) -> Result<MailboxDelta, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(start) = start_history_id else {
+            return self.resync_history().await;
+        };
+
+        let mut records = Vec::new();
+        let mut page_token: Option<String> = None;
+        let history_id = loop {
+            let page = match self.list_history(start, page_token.as_deref()).await {
+                Ok(page) => page,
+                Err(e) if is_not_found_error(e.as_ref()) => {
+                    return self.recover_history_gap(known_inbox).await;
+                }
+                Err(e) => return Err(e),
+            };
+            records.extend(page.history.unwrap_or_default());
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => break page.history_id.unwrap_or_else(|| start.to_string()),
+            }
+        };
+
+        Ok(MailboxDelta::from_records(&records, history_id))
+    }
+
+    /// An empty delta starting from the mailbox's current history id
+    async fn resync_history(
+        &self,
+    ) -> Result<MailboxDelta, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(MailboxDelta {
+            history_id: self.current_history_id().await?,
+            resynced: true,
+            ..Default::default()
+        })
+    }
+
+    /// Bridge an expired start id by re-listing the newest inbox messages and
+    /// reconciling them with `known_inbox`, the inbox as last seen. With
+    /// nothing known there is nothing to compare, so this is a plain resync.
+    async fn recover_history_gap(
+        &self,
+        known_inbox: &[MessageLabels],
+    ) -> Result<MailboxDelta, Box<dyn std::error::Error + Send + Sync>> {
+        if known_inbox.is_empty() {
+            return self.resync_history().await;
+        }
+
+        // Take the new starting point first so mail landing mid-listing is
+        // picked up by the next check rather than lost
+        let history_id = self.current_history_id().await?;
+        let ids: Vec<String> = self
+            .list_messages(Some(GAP_RELIST_LIMIT), None, Some("in:inbox"))
+            .await?
+            .messages
+            .unwrap_or_default()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        let mut recent: Vec<MessageLabels> = self
+            .get_messages_metadata_batch(&ids)
+            .await?
+            .into_iter()
+            .map(|m| MessageLabels {
+                id: m.id,
+                thread_id: m.thread_id,
+                label_ids: m.label_ids.unwrap_or_default(),
+            })
+            .collect();
+
+        // Batch responses are not guaranteed to be in request order
+        recent.sort_by_key(|m| ids.iter().position(|id| *id == m.id));
+
+        Ok(MailboxDelta::reconcile(
+            known_inbox,
+            &recent,
+            GAP_RELIST_LIMIT as usize,
+            history_id,
+        ))
+    }
+
+    async fn current_history_id(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self
+            .get_profile()
+            .await?
+            .history_id
+            .ok_or("Gmail profile has no history id")?)
+    }
+
+    /// Send a fully specified message, threaded when `thread_id` is given
+    pub async fn send_message(
+        &self,
+        email: &OutgoingEmail,
+        thread_id: Option<&str>,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        check_attachment_size(email.attachment_bytes())?;
+        let email_content = email.to_rfc2822();
+
+        // Encode the email content in base64 URL-safe format
+        let encoded_email = URL_SAFE.encode(email_content.as_bytes());
+
+        // Create the request payload
+        let mut send_request = serde_json::json!({
+            "raw": encoded_email
+        });
+
+        // Add thread ID if this is a reply
+        if let Some(tid) = thread_id {
+            send_request["threadId"] = serde_json::Value::String(tid.to_string());
+        }
+
+        let url = format!("{}/gmail/v1/users/me/messages/send", self.base_url);
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.access_token)
+            .json(&send_request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail send API error: {}", error_text).into());
+        }
+
+        let response_json: serde_json::Value = response.json().await?;
+        let message_id = response_json["id"]
+            .as_str()
+            .unwrap_or("unknown")
+            .to_string();
+
+        Ok(message_id)
+    }
+
+    /// Save a new draft, threaded when `thread_id` is given
+    pub async fn create_draft(
+        &self,
+        email: &OutgoingEmail,
+        thread_id: Option<&str>,
+    ) -> Result<GmailDraft, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/drafts", self.base_url);
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.access_token)
+            .json(&draft_payload(email, thread_id))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail drafts API error: {}", error_text).into());
+        }
+
+        let draft: GmailDraft = response.json().await?;
+        Ok(draft)
+    }
+
+    /// Replace a draft's content
+    pub async fn update_draft(
+        &self,
+        draft_id: &str,
+        email: &OutgoingEmail,
+        thread_id: Option<&str>,
+    ) -> Result<GmailDraft, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/drafts/{}", self.base_url, draft_id);
+
+        let mut payload = draft_payload(email, thread_id);
+        payload["id"] = serde_json::Value::String(draft_id.to_string());
+
+        let response = self
+            .client
+            .put(&url)
+            .bearer_auth(&self.access_token)
+            .json(&payload)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail drafts API error: {}", error_text).into());
+        }
+
+        let draft: GmailDraft = response.json().await?;
+        Ok(draft)
+    }
+
+    pub async fn list_drafts(
+        &self,
+        max_results: Option<u32>,
+        page_token: Option<&str>,
+    ) -> Result<GmailDraftsResponse, Box<dyn std::error::Error + Send + Sync>> {
+        let mut url = format!("{}/gmail/v1/users/me/drafts", self.base_url);
+        let mut params = Vec::new();
+
+        if let Some(max) = max_results {
+            params.push(format!("maxResults={}", max));
+        }
+
+        if let Some(token) = page_token {
+            params.push(format!("pageToken={}", token));
+        }
+
+        if !params.is_empty() {
+            url.push('?');
+            url.push_str(&params.join("&"));
+        }
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail drafts API error: {}", error_text).into());
+        }
+
+        let drafts: GmailDraftsResponse = response.json().await?;
+        Ok(drafts)
+    }
+
+    pub async fn get_draft(
+        &self,
+        draft_id: &str,
+    ) -> Result<GmailDraftMessage, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/drafts/{}?format=full",
+            self.base_url, draft_id
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail drafts API error: {}", error_text).into());
+        }
+
+        let draft: GmailDraftMessage = response.json().await?;
+        Ok(draft)
+    }
+
+    /// Send a draft as it is saved, returning the sent message id. Gmail
+    /// deletes the draft once it is sent.
+    pub async fn send_draft(
+        &self,
+        draft_id: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/drafts/send", self.base_url);
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.access_token)
+            .json(&serde_json::json!({ "id": draft_id }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail drafts API error: {}", error_text).into());
+        }
+
+        let response_json: serde_json::Value = response.json().await?;
+        let message_id = response_json["id"]
+            .as_str()
+            .unwrap_or("unknown")
+            .to_string();
+
+        Ok(message_id)
+    }
+
+    pub async fn delete_draft(
+        &self,
+        draft_id: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/drafts/{}", self.base_url, draft_id);
+
+        let response = self
+            .client
+            .delete(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail drafts API error: {}", error_text).into());
+        }
+
+        Ok(())
+    }
+
+    /// Add and remove labels on a single message, returning its updated labels
+    pub async fn modify_message(
+        &self,
+        message_id: &str,
+        add_label_ids: &[&str],
+        remove_label_ids: &[&str],
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/messages/{}/modify",
+            self.base_url, message_id
+        );
+
+        let modify_request = serde_json::json!({
+            "addLabelIds": add_label_ids,
+            "removeLabelIds": remove_label_ids
+        });
+
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(&self.access_token)
+            .json(&modify_request)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await?;
+            return Err(Box::new(GmailApiError {
+                status: status.as_u16(),
+                message: format!("Gmail modify API error: {}", error_text),
+            }));
         }
 
-        Ok(messages)
+        let labels: MessageLabels = response.json().await?;
+        Ok(labels)
     }
 
-    // Fallback method for individual requests
-    async fn get_messages_individual(
+    /// Add and remove labels on every message in a thread at once
+    pub async fn modify_thread(
         &self,
-        message_ids: &[String],
-    ) -> Result<Vec<GmailMessage>, Box<dyn std::error::Error + Send + Sync>> {
-        let mut messages = Vec::new();
-
-        for message_id in message_ids.iter().take(20) {
-            // Limit to 20 for now
-            match self.get_message(message_id).await {
-                Ok(message) => messages.push(message),
-                Err(e) => eprintln!("Failed to fetch message {}: {}", message_id, e),
-            }
-        }
+        thread_id: &str,
+        add_label_ids: &[&str],
+        remove_label_ids: &[&str],
+    ) -> Result<ThreadLabels, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/threads/{}/modify",
+            self.base_url, thread_id
+        );
 
-        Ok(messages)
-    }
+        let modify_request = serde_json::json!({
+            "addLabelIds": add_label_ids,
+            "removeLabelIds": remove_label_ids
+        });
 
-    pub async fn check_for_new_emails(
-        &self,
-        since_time: Option<&str>,
-    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
-        // Build query to get emails newer than the specified time
-        let mut query = "in:inbox".to_string();
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(&self.access_token)
+            .json(&modify_request)
+            .send()
+            .await?;
 
-        if let Some(time) = since_time {
-            // Gmail uses Unix timestamp for 'after' queries
-            query.push_str(&format!(" after:{}", time));
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await?;
+            return Err(Box::new(GmailApiError {
+                status: status.as_u16(),
+                message: format!("Gmail thread modify API error: {}", error_text),
+            }));
         }
 
-        // Get recent emails (last 5 minutes worth if no time specified)
-        let response = self.list_messages(Some(10), None, Some(&query)).await?;
+        let labels: ThreadLabels = response.json().await?;
+        Ok(labels)
+    }
 
-        let message_ids: Vec<String> = response
-            .messages
-            .unwrap_or_default()
-            .into_iter()
-            .map(|m| m.id)
-            .collect();
+    pub async fn mark_thread_as_read(
+        &self,
+        thread_id: &str,
+    ) -> Result<ThreadLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.modify_thread(thread_id, &[], &["UNREAD"]).await
+    }
 
-        Ok(message_ids)
+    pub async fn mark_thread_as_unread(
+        &self,
+        thread_id: &str,
+    ) -> Result<ThreadLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.modify_thread(thread_id, &["UNREAD"], &[]).await
     }
 
-    pub async fn send_email(
+    pub async fn mark_as_read(
         &self,
-        to: &str,
-        subject: &str,
-        body: &str,
-        in_reply_to: Option<&str>,
-        references: Option<&str>,
-        thread_id: Option<&str>,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        // Detect if body contains HTML
-        let is_html = body.contains('<') && (body.contains("</") || body.contains("/>"));
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.modify_message(message_id, &[], &["UNREAD"]).await
+    }
 
-        // Create the email message in RFC 2822 format
-        let mut email_content = String::new();
+    pub async fn mark_as_unread(
+        &self,
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.modify_message(message_id, &["UNREAD"], &[]).await
+    }
 
-        email_content.push_str(&format!("To: {}\r\n", to));
-        email_content.push_str(&format!("Subject: {}\r\n", subject));
-        email_content.push_str("MIME-Version: 1.0\r\n");
+    /// Archive a message by removing it from the inbox
+    pub async fn archive_message(
+        &self,
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.modify_message(message_id, &[], &["INBOX"]).await
+    }
 
-        if is_html {
-            // Multipart email with both plain text and HTML
-            let boundary = "boundary_email_content_12345";
-            email_content.push_str(&format!(
-                "Content-Type: multipart/alternative; boundary=\"{}\"\r\n",
-                boundary
-            ));
+    /// Put an archived message back in the inbox
+    pub async fn move_to_inbox(
+        &self,
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.modify_message(message_id, &["INBOX"], &[]).await
+    }
 
-            // Add reply headers if this is a reply
-            if let Some(reply_to) = in_reply_to {
-                email_content.push_str(&format!("In-Reply-To: {}\r\n", reply_to));
-            }
-            if let Some(refs) = references {
-                email_content.push_str(&format!("References: {}\r\n", refs));
-            }
+    pub async fn mark_as_important(
+        &self,
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.modify_message(message_id, &["IMPORTANT"], &[]).await
+    }
 
-            email_content.push_str("\r\n"); // Empty line to separate headers from body
+    pub async fn mark_not_important(
+        &self,
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.modify_message(message_id, &[], &["IMPORTANT"]).await
+    }
 
-            // Plain text part (strip HTML for plain text version)
-            email_content.push_str(&format!("--{}\r\n", boundary));
-            email_content.push_str("Content-Type: text/plain; charset=utf-8\r\n");
-            email_content.push_str("Content-Transfer-Encoding: 7bit\r\n\r\n");
+    /// Report a message as spam, taking it out of the inbox
+    pub async fn report_spam(
+        &self,
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.modify_message(message_id, &["SPAM"], &["INBOX"]).await
+    }
 
-            // Simple HTML to text conversion (remove tags)
-            let plain_text = body
-                .replace("<br>", "\n")
-                .replace("<br/>", "\n")
-                .replace("<br />", "\n")
-                .replace("</p>", "\n\n")
-                .replace("</div>", "\n")
-                .replace("</li>", "\n");
+    /// Report a message as phishing. Gmail's API has no separate phishing
+    /// label, so it goes to Spam and loses the Important marker that might
+    /// otherwise keep it prominent.
+    pub async fn report_phishing(
+        &self,
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.modify_message(message_id, &["SPAM"], &["INBOX", "IMPORTANT"])
+            .await
+    }
 
-            // Remove all HTML tags with regex-like replacement
-            let mut plain_body = String::new();
-            let mut in_tag = false;
-            for ch in plain_text.chars() {
-                match ch {
-                    '<' => in_tag = true,
-                    '>' => in_tag = false,
-                    _ if !in_tag => plain_body.push(ch),
-                    _ => {}
-                }
-            }
+    /// Undo a spam report, putting the message back in the inbox
+    pub async fn mark_not_spam(
+        &self,
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.modify_message(message_id, &["INBOX"], &["SPAM"]).await
+    }
 
-            email_content.push_str(plain_body.trim());
-            email_content.push_str("\r\n\r\n");
+    /// Move a message to Trash; Gmail purges it permanently after 30 days
+    pub async fn trash_message(
+        &self,
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.post_message_action(message_id, "trash").await
+    }
 
-            // HTML part
-            email_content.push_str(&format!("--{}\r\n", boundary));
-            email_content.push_str("Content-Type: text/html; charset=utf-8\r\n");
-            email_content.push_str("Content-Transfer-Encoding: 7bit\r\n\r\n");
-            email_content.push_str(body);
-            email_content.push_str("\r\n\r\n");
+    /// Restore a message from Trash
+    pub async fn untrash_message(
+        &self,
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        self.post_message_action(message_id, "untrash").await
+    }
 
-            // End boundary
-            email_content.push_str(&format!("--{}--\r\n", boundary));
-        } else {
-            // Plain text email
-            email_content.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+    /// Delete a message immediately, bypassing Trash. This cannot be undone.
+    pub async fn delete_message_permanently(
+        &self,
+        message_id: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/gmail/v1/users/me/messages/{}",
+            self.base_url, message_id
+        );
 
-            // Add reply headers if this is a reply
-            if let Some(reply_to) = in_reply_to {
-                email_content.push_str(&format!("In-Reply-To: {}\r\n", reply_to));
-            }
-            if let Some(refs) = references {
-                email_content.push_str(&format!("References: {}\r\n", refs));
-            }
+        let response = self
+            .client
+            .delete(&url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
 
-            email_content.push_str("\r\n"); // Empty line to separate headers from body
-            email_content.push_str(body);
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Gmail delete API error: {}", error_text).into());
         }
 
-        // Encode the email content in base64 URL-safe format
-        let encoded_email = URL_SAFE.encode(email_content.as_bytes());
-
-        // Create the request payload
-        let mut send_request = serde_json::json!({
-            "raw": encoded_email
-        });
+        Ok(())
+    }
 
-        // Add thread ID if this is a reply
-        if let Some(tid) = thread_id {
-            send_request["threadId"] = serde_json::Value::String(tid.to_string());
-        }
+    /// Permanently delete up to 1000 messages in a single request
+    pub async fn batch_delete_messages(
+        &self,
+        message_ids: &[String],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/gmail/v1/users/me/messages/batchDelete", self.base_url);
 
-        let url = "https://gmail.googleapis.com/gmail/v1/users/me/messages/send";
+        let delete_request = serde_json::json!({ "ids": message_ids });
 
         let response = self
             .client
             .post(url)
             .bearer_auth(&self.access_token)
-            .json(&send_request)
+            .json(&delete_request)
             .send()
             .await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
-            return Err(format!("Gmail send API error: {}", error_text).into());
+            return Err(format!("Gmail batchDelete API error: {}", error_text).into());
         }
 
-        let response_json: serde_json::Value = response.json().await?;
-        let message_id = response_json["id"]
-            .as_str()
-            .unwrap_or("unknown")
-            .to_string();
-
-        Ok(message_id)
+        Ok(())
     }
 
-    pub async fn mark_as_read(
+    /// POST to a `messages/{id}/{action}` endpoint that returns the updated message labels
+    async fn post_message_action(
         &self,
         message_id: &str,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        action: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
         let url = format!(
-            "https://gmail.googleapis.com/gmail/v1/users/me/messages/{}/modify",
-            message_id
+            "{}/gmail/v1/users/me/messages/{}/{}",
+            self.base_url, message_id, action
         );
 
-        let modify_request = serde_json::json!({
-            "removeLabelIds": ["UNREAD"]
-        });
-
         let response = self
             .client
             .post(&url)
             .bearer_auth(&self.access_token)
-            .json(&modify_request)
+            .header("Content-Length", "0")
             .send()
             .await?;
 
-        if !response.status().is_success() {
+        let status = response.status();
+        if !status.is_success() {
             let error_text = response.text().await?;
-            return Err(format!("Gmail modify API error: {}", error_text).into());
+            return Err(Box::new(GmailApiError {
+                status: status.as_u16(),
+                message: format!("Gmail {} API error: {}", action, error_text),
+            }));
         }
 
-        Ok(())
+        let labels: MessageLabels = response.json().await?;
+        Ok(labels)
     }
 
-    pub async fn mark_as_unread(
+    /// Apply label changes to up to 1000 messages in a single request
+    pub async fn batch_modify_messages(
         &self,
-        message_id: &str,
+        message_ids: &[String],
+        add_label_ids: &[String],
+        remove_label_ids: &[String],
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let url = format!(
-            "https://gmail.googleapis.com/gmail/v1/users/me/messages/{}/modify",
-            message_id
-        );
+        let url = format!("{}/gmail/v1/users/me/messages/batchModify", self.base_url);
 
         let modify_request = serde_json::json!({
-            "addLabelIds": ["UNREAD"]
+            "ids": message_ids,
+            "addLabelIds": add_label_ids,
+            "removeLabelIds": remove_label_ids
         });
 
         let response = self
             .client
-            .post(&url)
+            .post(url)
             .bearer_auth(&self.access_token)
             .json(&modify_request)
             .send()
             .await?;
 
-        if !response.status().is_success() {
+        let status = response.status();
+        if !status.is_success() {
             let error_text = response.text().await?;
-            return Err(format!("Gmail modify API error: {}", error_text).into());
+            return Err(Box::new(GmailApiError {
+                status: status.as_u16(),
+                message: format!("Gmail batchModify API error: {}", error_text),
+            }));
         }
 
         Ok(())
     }
 }
 
+/// Request body shared by drafts.create and drafts.update
+fn draft_payload(email: &OutgoingEmail, thread_id: Option<&str>) -> serde_json::Value {
+    let mut message = serde_json::json!({
+        "raw": URL_SAFE.encode(email.to_rfc2822().as_bytes())
+    });
+    if let Some(tid) = thread_id {
+        message["threadId"] = serde_json::Value::String(tid.to_string());
+    }
+    serde_json::json!({ "message": message })
+}
+
 // Helper functions to extract email data
 impl GmailMessage {
     pub fn get_subject(&self) -> String {
@@ -495,11 +2967,21 @@ impl GmailMessage {
             .unwrap_or_else(|| "(No Subject)".to_string())
     }
 
+    /// The Subject header, None rather than a placeholder when there isn't one
+    pub fn get_subject_header(&self) -> Option<String> {
+        self.get_header("Subject")
+    }
+
     pub fn get_from(&self) -> String {
         self.get_header("From")
             .unwrap_or_else(|| "Unknown Sender".to_string())
     }
 
+    /// Bare sender address, without any display name
+    pub fn get_from_address(&self) -> String {
+        extract_email_address(&self.get_from())
+    }
+
     pub fn get_date(&self) -> Option<String> {
         self.get_header("Date")
     }
@@ -512,6 +2994,42 @@ impl GmailMessage {
         self.get_header("References")
     }
 
+    pub fn get_reply_to(&self) -> Option<String> {
+        self.get_header("Reply-To")
+    }
+
+    pub fn get_in_reply_to(&self) -> Option<String> {
+        self.get_header("In-Reply-To")
+    }
+
+    /// Raw To, Cc and Bcc header values; Bcc only survives on drafts and sent copies
+    pub fn get_to(&self) -> Option<String> {
+        self.get_header("To")
+    }
+
+    pub fn get_cc(&self) -> Option<String> {
+        self.get_header("Cc")
+    }
+
+    pub fn get_bcc(&self) -> Option<String> {
+        self.get_header("Bcc")
+    }
+
+    /// Addresses the message was delivered to, from To, Cc and Delivered-To
+    pub fn get_recipient_addresses(&self) -> Vec<String> {
+        ["To", "Cc", "Delivered-To"]
+            .iter()
+            .filter_map(|name| self.get_header(name))
+            .flat_map(|value| {
+                value
+                    .split(',')
+                    .map(extract_email_address)
+                    .filter(|address| !address.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
     pub fn is_unread(&self) -> bool {
         self.label_ids
             .as_ref()
@@ -519,53 +3037,57 @@ impl GmailMessage {
             .unwrap_or(false)
     }
 
-    fn get_header(&self, name: &str) -> Option<String> {
+    pub fn is_important(&self) -> bool {
+        self.label_ids
+            .as_ref()
+            .map(|labels| labels.contains(&"IMPORTANT".to_string()))
+            .unwrap_or(false)
+    }
+
+    /// The List-Id header (RFC 2919) mailing list software adds
+    pub fn get_list_id(&self) -> Option<String> {
+        self.get_header("List-Id")
+    }
+
+    /// Unsubscribe methods from the List-Unsubscribe headers, if the sender is a list
+    pub fn get_unsubscribe_options(&self) -> Option<UnsubscribeOptions> {
+        let header = self.get_header("List-Unsubscribe")?;
+        UnsubscribeOptions::parse(&header, self.get_header("List-Unsubscribe-Post").as_deref())
+    }
+
+    /// A header's value with any encoded words decoded, matching the name
+    /// case-insensitively
+    pub fn get_header(&self, name: &str) -> Option<String> {
         self.payload
             .as_ref()?
             .headers
             .as_ref()?
             .iter()
             .find(|h| h.name.eq_ignore_ascii_case(name))
-            .map(|h| h.value.clone())
+            .map(|h| decode_encoded_words(&h.value))
     }
 
     pub fn get_body_text(&self) -> String {
         if let Some(payload) = &self.payload {
             // Try to get text from the main body first
-            if let Some(body) = &payload.body {
-                if let Some(data) = &body.data {
-                    if let Ok(decoded) = URL_SAFE.decode(data) {
-                        if let Ok(text) = String::from_utf8(decoded) {
-                            return text;
-                        }
-                    }
+            if let Some(data) = payload.body.as_ref().and_then(|b| b.data.as_ref()) {
+                if let Some(text) = decode_body_data(data, payload.headers.as_deref()) {
+                    return if self.is_html_payload() {
+                        html_text::html_to_text(&text)
+                    } else {
+                        text
+                    };
                 }
             }
 
-            // If no main body, look through parts for text/plain
-            if let Some(parts) = &payload.parts {
-                for part in parts {
-                    if let Some(headers) = &part.headers {
-                        let content_type = headers
-                            .iter()
-                            .find(|h| h.name.eq_ignore_ascii_case("Content-Type"))
-                            .map(|h| &h.value);
-
-                        if let Some(ct) = content_type {
-                            if ct.contains("text/plain") {
-                                if let Some(body) = &part.body {
-                                    if let Some(data) = &body.data {
-                                        if let Ok(decoded) = URL_SAFE.decode(data) {
-                                            if let Ok(text) = String::from_utf8(decoded) {
-                                                return text;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+            // If no main body, look through the part tree for text/plain
+            if let Some(text) = self.find_inline_text("text/plain") {
+                return text;
+            }
+
+            // HTML-only mail reads as text rendered from its HTML
+            if let Some(html) = self.find_inline_text("text/html") {
+                return html_text::html_to_text(&html);
             }
         }
 
@@ -574,32 +3096,134 @@ impl GmailMessage {
     }
 
     pub fn get_body_html(&self) -> Option<String> {
-        if let Some(payload) = &self.payload {
-            if let Some(parts) = &payload.parts {
-                for part in parts {
-                    if let Some(headers) = &part.headers {
-                        let content_type = headers
-                            .iter()
-                            .find(|h| h.name.eq_ignore_ascii_case("Content-Type"))
-                            .map(|h| &h.value);
-
-                        if let Some(ct) = content_type {
-                            if ct.contains("text/html") {
-                                if let Some(body) = &part.body {
-                                    if let Some(data) = &body.data {
-                                        if let Ok(decoded) = URL_SAFE.decode(data) {
-                                            if let Ok(html) = String::from_utf8(decoded) {
-                                                return Some(html);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+        self.find_inline_text("text/html")
+    }
+
+    /// Whether the message is a single HTML part rather than a multipart tree
+    fn is_html_payload(&self) -> bool {
+        self.payload
+            .as_ref()
+            .and_then(|p| p.mime_type.clone())
+            .or_else(|| self.get_header("Content-Type"))
+            .is_some_and(|ct| ct.to_ascii_lowercase().contains("text/html"))
+    }
+
+    /// The first non-attachment part of `mime_type` anywhere in the part tree
+    fn find_inline_text(&self, mime_type: &str) -> Option<String> {
+        let parts = self.payload.as_ref()?.parts.as_deref()?;
+        flatten_parts(parts)
+            .into_iter()
+            .find_map(|part| part.inline_text(mime_type))
+    }
+
+    /// Whether the message is too big to hand to the reading pane whole
+    pub fn is_large(&self) -> bool {
+        self.size_estimate
+            .is_some_and(|size| size > LARGE_MESSAGE_BYTES)
+    }
+
+    /// Body of the part `get_body_text` reads first: a text/plain payload, or
+    /// the first inline text/plain part in the tree
+    fn plain_text_body_mut(&mut self) -> Option<&mut MessageBody> {
+        let payload = self.payload.as_mut()?;
+        if payload.parts.is_none() {
+            let plain = payload
+                .headers
+                .iter()
+                .flatten()
+                .find(|h| h.name.eq_ignore_ascii_case("Content-Type"))
+                .is_none_or(|h| h.value.to_ascii_lowercase().contains("text/plain"));
+            return payload.body.as_mut().filter(|_| plain);
+        }
+        let mut pending: Vec<&mut MessagePart> = payload.parts.iter_mut().flatten().collect();
+        pending.reverse();
+        while let Some(part) = pending.pop() {
+            let plain = part.filename.as_deref().is_none_or(str::is_empty)
+                && part
+                    .content_type()
+                    .is_some_and(|ct| ct.contains("text/plain"));
+            let MessagePart { body, parts, .. } = part;
+            if plain {
+                return body.as_mut();
+            }
+            pending.extend(parts.iter_mut().flatten().rev());
+        }
+        None
+    }
+
+    /// A copy keeping headers and the text/plain body only. HTML and
+    /// attachment data are dropped; attachment ids and sizes stay so the
+    /// attachments can still be listed and downloaded.
+    pub fn text_only(&self) -> GmailMessage {
+        let is_plain = |headers: &Option<Vec<MessageHeader>>| {
+            headers
+                .iter()
+                .flatten()
+                .find(|h| h.name.eq_ignore_ascii_case("Content-Type"))
+                .is_none_or(|h| h.value.to_ascii_lowercase().contains("text/plain"))
+        };
+
+        let mut message = self.clone();
+        if let Some(payload) = message.payload.as_mut() {
+            if !is_plain(&payload.headers) {
+                if let Some(body) = payload.body.as_mut() {
+                    body.data = None;
+                }
+            }
+            let mut pending: Vec<&mut MessagePart> = payload.parts.iter_mut().flatten().collect();
+            while let Some(part) = pending.pop() {
+                let plain = match part.mime_type.as_deref() {
+                    Some(mime_type) => mime_type.eq_ignore_ascii_case("text/plain"),
+                    None => is_plain(&part.headers),
+                };
+                if !plain {
+                    if let Some(body) = part.body.as_mut() {
+                        body.data = None;
                     }
                 }
+                pending.extend(part.parts.iter_mut().flatten());
             }
         }
-        None
+        message
+    }
+
+    pub fn get_attachments(&self) -> Vec<MessageAttachment> {
+        let mut attachments = Vec::new();
+
+        if let Some(parts) = self.payload.as_ref().and_then(|p| p.parts.as_ref()) {
+            for part in flatten_parts(parts) {
+                let filename = match part.filename.as_deref() {
+                    Some(name) if !name.is_empty() => name,
+                    _ => continue,
+                };
+
+                if let Some(body) = &part.body {
+                    if let Some(attachment_id) = &body.attachment_id {
+                        attachments.push(MessageAttachment {
+                            message_id: self.id.clone(),
+                            attachment_id: attachment_id.clone(),
+                            filename: filename.to_string(),
+                            mime_type: part
+                                .mime_type
+                                .clone()
+                                .unwrap_or_else(|| "application/octet-stream".to_string()),
+                            size: body.size.unwrap_or(0),
+                        });
+                    }
+                }
+            }
+        }
+
+        attachments
+    }
+}
+
+/// Parse the address out of a "Name <email@domain.com>" header value
+pub fn extract_email_address(value: &str) -> String {
+    if let Some(start) = value.find('<') {
+        if let Some(end) = value[start..].find('>') {
+            return value[start + 1..start + end].trim().to_string();
+        }
     }
+    value.trim().to_string()
 }
@@ -104,4 +104,12 @@ pub const SCOPES: &[&str] = &[
     "https://mail.google.com/",
     "https://www.googleapis.com/auth/userinfo.email",
     "https://www.googleapis.com/auth/userinfo.profile",
+    // Contact details in the reading pane sidebar
+    "https://www.googleapis.com/auth/contacts.readonly",
+    // Recipient lookup over addresses Gmail collected from mail
+    "https://www.googleapis.com/auth/contacts.other.readonly",
+    // Storage quota for account health warnings
+    "https://www.googleapis.com/auth/drive.file",
+    // Pulling Gmail push notifications from the user's Pub/Sub subscription
+    "https://www.googleapis.com/auth/pubsub",
 ];
@@ -0,0 +1,385 @@
+/// Render an HTML body as readable plain text. Block structure becomes line
+/// breaks, lists get bullets, blockquotes get "> " prefixes, and each link
+/// keeps its target as a numbered reference listed after the text, so
+/// nothing clickable is lost for screen readers or text-only readers.
+pub fn html_to_text(html: &str) -> String {
+    let mut renderer = Renderer::default();
+    let mut rest = html;
+
+    while let Some(open) = rest.find('<') {
+        renderer.text(&rest[..open]);
+        rest = &rest[open..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        match rest.find('>') {
+            Some(close) => {
+                renderer.tag(&rest[1..close]);
+                rest = &rest[close + 1..];
+            }
+            None => {
+                renderer.text(rest);
+                rest = "";
+            }
+        }
+    }
+    renderer.text(rest);
+    renderer.finish()
+}
+
+/// Elements whose content is never shown
+const HIDDEN_TAGS: &[&str] = &["script", "style", "head", "title", "noscript", "template"];
+
+/// Elements set off by a blank line
+const PARAGRAPH_TAGS: &[&str] = &[
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "table",
+    "pre",
+    "blockquote",
+];
+
+/// Elements that start on a new line
+const LINE_TAGS: &[&str] = &[
+    "div", "tr", "section", "article", "header", "footer", "dt", "dd", "address", "center",
+];
+
+#[derive(Default)]
+struct Renderer {
+    out: String,
+    links: Vec<String>,
+    /// Open lists, innermost last: None for bullets, Some(next) for numbers
+    lists: Vec<Option<u32>>,
+    quote_depth: usize,
+    pre_depth: usize,
+    hidden_depth: usize,
+    /// Open anchor's target and where its text starts in `out`
+    anchor: Option<(String, usize)>,
+    line_has_text: bool,
+    pending_space: bool,
+}
+
+impl Renderer {
+    fn text(&mut self, raw: &str) {
+        if self.hidden_depth > 0 || raw.is_empty() {
+            return;
+        }
+        for ch in decode_entities(raw).chars() {
+            if self.pre_depth > 0 {
+                if ch == '\n' {
+                    self.newline();
+                } else {
+                    self.push_char(ch);
+                }
+            } else if ch.is_whitespace() && ch != '\u{a0}' {
+                self.pending_space = true;
+            } else {
+                self.push_char(if ch == '\u{a0}' { ' ' } else { ch });
+            }
+        }
+    }
+
+    fn push_char(&mut self, ch: char) {
+        if !self.line_has_text {
+            self.start_line();
+        } else if self.pending_space {
+            self.out.push(' ');
+        }
+        self.pending_space = false;
+        self.out.push(ch);
+    }
+
+    /// Write the quote prefix for a fresh line
+    fn start_line(&mut self) {
+        for _ in 0..self.quote_depth {
+            self.out.push_str("> ");
+        }
+        self.line_has_text = true;
+        self.pending_space = false;
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        self.line_has_text = false;
+        self.pending_space = false;
+    }
+
+    fn break_line(&mut self) {
+        if self.line_has_text {
+            self.newline();
+        }
+    }
+
+    fn blank_line(&mut self) {
+        self.break_line();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.newline();
+        }
+    }
+
+    fn tag(&mut self, body: &str) {
+        let body = body.trim();
+        let (closing, body) = match body.strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, body),
+        };
+        let name = body
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        if HIDDEN_TAGS.contains(&name.as_str()) {
+            if closing {
+                self.hidden_depth = self.hidden_depth.saturating_sub(1);
+            } else if !body.ends_with('/') {
+                self.hidden_depth += 1;
+            }
+            return;
+        }
+        if self.hidden_depth > 0 {
+            return;
+        }
+
+        match (name.as_str(), closing) {
+            ("br", _) => {
+                if !self.line_has_text {
+                    self.start_line();
+                }
+                self.newline();
+            }
+            ("hr", _) => {
+                self.break_line();
+                self.start_line();
+                self.out.push_str("----");
+                self.newline();
+            }
+            ("li", false) => {
+                self.break_line();
+                self.start_line();
+                let depth = self.lists.len().saturating_sub(1);
+                self.out.push_str(&"  ".repeat(depth));
+                match self.lists.last_mut() {
+                    Some(Some(next)) => {
+                        self.out.push_str(&format!("{}. ", next));
+                        *next += 1;
+                    }
+                    _ => self.out.push_str("- "),
+                }
+            }
+            ("li", true) => self.break_line(),
+            ("td" | "th", true) => self.pending_space = true,
+            ("img", _) => {
+                if let Some(alt) = attribute(body, "alt").filter(|a| !a.trim().is_empty()) {
+                    self.text(&format!(" [image: {}] ", alt.trim()));
+                }
+            }
+            ("a", false) => {
+                self.anchor = attribute(body, "href")
+                    .map(|href| href.trim().to_string())
+                    .filter(|href| !href.is_empty() && !href.starts_with('#'))
+                    .map(|href| (href, self.out.len()));
+            }
+            ("a", true) => self.close_anchor(),
+            (name, _) if PARAGRAPH_TAGS.contains(&name) => {
+                self.blank_line();
+                match (name, closing) {
+                    ("ul", false) => self.lists.push(None),
+                    ("ol", false) => self.lists.push(Some(1)),
+                    ("ul" | "ol", true) => {
+                        self.lists.pop();
+                    }
+                    ("blockquote", false) => self.quote_depth += 1,
+                    ("blockquote", true) => {
+                        self.quote_depth = self.quote_depth.saturating_sub(1);
+                    }
+                    ("pre", false) => self.pre_depth += 1,
+                    ("pre", true) => self.pre_depth = self.pre_depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            (name, _) if LINE_TAGS.contains(&name) => self.break_line(),
+            _ => {}
+        }
+    }
+
+    /// Follow the link text with its reference number, unless the text
+    /// already is the target
+    fn close_anchor(&mut self) {
+        let Some((href, start)) = self.anchor.take() else {
+            return;
+        };
+        let label = self.out.get(start..).unwrap_or("").trim();
+        let bare = href.strip_prefix("mailto:").unwrap_or(&href);
+        if label == href || label == bare {
+            return;
+        }
+
+        let number = match self.links.iter().position(|l| *l == href) {
+            Some(index) => index + 1,
+            None => {
+                self.links.push(href);
+                self.links.len()
+            }
+        };
+        self.text(&format!(" [{}]", number));
+    }
+
+    fn finish(self) -> String {
+        let mut text = String::new();
+        let mut blank_run = 0;
+        for line in self.out.lines().map(str::trim_end) {
+            let blank = line.trim_start_matches(['>', ' ']).is_empty();
+            blank_run = if blank { blank_run + 1 } else { 0 };
+            if blank_run < 2 {
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+        let mut text = text.trim().to_string();
+
+        if !self.links.is_empty() {
+            text.push_str("\n\nLinks:");
+            for (index, href) in self.links.iter().enumerate() {
+                text.push_str(&format!("\n[{}] {}", index + 1, href));
+            }
+        }
+        text
+    }
+}
+
+/// Value of attribute `name` in a tag body, quoted or not
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lowered = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(found) = lowered[from..].find(name) {
+        let start = from + found;
+        from = start + name.len();
+        let preceded = start == 0 || lowered[..start].ends_with(|c: char| c.is_whitespace());
+        let rest = tag[from..].trim_start();
+        let Some(value) = rest.strip_prefix('=').filter(|_| preceded) else {
+            continue;
+        };
+
+        let value = value.trim_start();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or(""),
+            _ => value.split(char::is_whitespace).next().unwrap_or(""),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+/// Decode named and numeric character references; unknown ones stay as written
+pub fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| entity(&rest[1..=end]).map(|ch| (ch, end + 2)));
+        match decoded {
+            Some((ch, len)) => {
+                out.push(ch);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bull" => '•',
+        "middot" => '·',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "euro" => '€',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_lists_and_quotes() {
+        let html = "<html><head><style>p { color: red }</style></head><body>\
+            <h1>Release   notes</h1><p>Hello&nbsp;team,<br>two things:</p>\
+            <ol><li>Faster sync</li><li>Fewer &quot;bugs&quot;</li></ol>\
+            <ul><li>Tip</li></ul>\
+            <blockquote><p>Earlier message</p></blockquote>\
+            <!-- tracking --><div>Thanks &amp; bye</div></body></html>";
+        assert_eq!(
+            html_to_text(html),
+            "Release notes\n\nHello team,\ntwo things:\n\n1. Faster sync\n2. Fewer \"bugs\"\n\n\
+             - Tip\n\n> Earlier message\n\nThanks & bye"
+        );
+    }
+
+    #[test]
+    fn test_links_become_numbered_references() {
+        let html = "<p>See the <a href=\"https://example.com/report?a=1&amp;b=2\">report</a>, \
+            <a href='https://example.com/report?a=1&b=2'>again</a>, \
+            <a href=\"https://example.com\">https://example.com</a> and \
+            <a href=\"mailto:help@example.com\">help@example.com</a> or \
+            <a href=\"#top\">top</a>.</p><img src=\"x.png\" alt=\"Logo\">";
+        assert_eq!(
+            html_to_text(html),
+            "See the report [1], again [1], https://example.com and help@example.com or top.\n\n\
+             [image: Logo]\n\nLinks:\n[1] https://example.com/report?a=1&b=2"
+        );
+    }
+
+    #[test]
+    fn test_entities_and_preformatted_text() {
+        assert_eq!(
+            decode_entities("&#8220;hi&#x201D; &unknown; AT&T"),
+            "“hi” &unknown; AT&T"
+        );
+        assert_eq!(
+            html_to_text("<pre>fn main() {\n    run();\n}</pre>"),
+            "fn main() {\n    run();\n}"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use crate::gmail_client::GmailLabel;
+use serde::Serialize;
+
+/// One label's part in a rename
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LabelRename {
+    pub label_id: String,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+/// What `rename_label` changed
+#[derive(Debug, Clone, Serialize)]
+pub struct LabelRenameSummary {
+    pub label: GmailLabel,
+    /// The label and its nested labels, parents first
+    pub renamed: Vec<LabelRename>,
+    /// Rules whose query mentioned a renamed label
+    pub rules_updated: usize,
+}
+
+/// Renaming `label_id` to `new_name` renames its nested labels along with
+/// it ("Old/Child" becomes "New/Child"); Gmail only renames the one label.
+/// Fails for system labels and for names another label already has.
+pub fn plan_renames(
+    labels: &[GmailLabel],
+    label_id: &str,
+    new_name: &str,
+) -> Result<Vec<LabelRename>, String> {
+    let label = labels
+        .iter()
+        .find(|l| l.id == label_id)
+        .ok_or_else(|| format!("No label with id {}", label_id))?;
+    if label.label_type.as_deref() == Some("system") {
+        return Err(format!(
+            "{} is a system label and can't be renamed",
+            label.name
+        ));
+    }
+
+    let child_prefix = format!("{}/", label.name);
+    let mut renames: Vec<LabelRename> = labels
+        .iter()
+        .filter(|l| l.id == label.id || l.name.starts_with(&child_prefix))
+        .map(|l| LabelRename {
+            label_id: l.id.clone(),
+            old_name: l.name.clone(),
+            new_name: format!("{}{}", new_name, &l.name[label.name.len()..]),
+        })
+        .collect();
+    renames.sort_by_key(|r| r.old_name.matches('/').count());
+
+    for rename in &renames {
+        let taken = labels.iter().any(|l| {
+            l.name.eq_ignore_ascii_case(&rename.new_name)
+                && !renames.iter().any(|r| r.label_id == l.id)
+        });
+        if taken {
+            return Err(format!("A label named {} already exists", rename.new_name));
+        }
+    }
+    Ok(renames)
+}
+
+/// Point `label:` terms in a Gmail query at the renamed labels, or None when
+/// the query doesn't mention any of them. Gmail accepts a label's name
+/// quoted, or with spaces and slashes written as dashes, in any case.
+pub fn rewrite_query(query: &str, renames: &[LabelRename]) -> Option<String> {
+    let mut rewritten = String::with_capacity(query.len());
+    let mut changed = false;
+    let mut rest = query;
+
+    while let Some(start) = find_label_term(rest) {
+        let value_start = start + "label:".len();
+        rewritten.push_str(&rest[..value_start]);
+        rest = &rest[value_start..];
+
+        let (value, quoted, len) = match rest.strip_prefix('"') {
+            Some(inner) => match inner.find('"') {
+                Some(end) => (&inner[..end], true, end + 2),
+                None => (inner, true, rest.len()),
+            },
+            None => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || c == ')' || c == '}')
+                    .unwrap_or(rest.len());
+                (&rest[..end], false, end)
+            }
+        };
+
+        match renames
+            .iter()
+            .find(|r| query_form(&r.old_name) == query_form(value))
+        {
+            Some(rename) if quoted => {
+                rewritten.push_str(&format!("\"{}\"", rename.new_name));
+                changed = true;
+            }
+            Some(rename) => {
+                rewritten.push_str(&rename.new_name.replace([' ', '/'], "-"));
+                changed = true;
+            }
+            None => rewritten.push_str(&rest[..len]),
+        }
+        rest = &rest[len..];
+    }
+    rewritten.push_str(rest);
+
+    changed.then_some(rewritten)
+}
+
+/// Offset of the next `label:` operator that starts a search term
+fn find_label_term(query: &str) -> Option<usize> {
+    let lower = query.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(found) = lower[from..].find("label:") {
+        let at = from + found;
+        let starts_term = lower[..at]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c.is_whitespace() || matches!(c, '(' | '{' | '-'));
+        if starts_term {
+            return Some(at);
+        }
+        from = at + "label:".len();
+    }
+    None
+}
+
+fn query_form(name: &str) -> String {
+    name.to_lowercase().replace([' ', '/'], "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(id: &str, name: &str, label_type: &str) -> GmailLabel {
+        GmailLabel {
+            id: id.to_string(),
+            name: name.to_string(),
+            label_type: Some(label_type.to_string()),
+            messages_total: None,
+            messages_unread: None,
+            threads_total: None,
+            threads_unread: None,
+        }
+    }
+
+    fn labels() -> Vec<GmailLabel> {
+        vec![
+            label("INBOX", "INBOX", "system"),
+            label("L1", "Projects", "user"),
+            label("L2", "Projects/Alpha", "user"),
+            label("L3", "Projects/Alpha/Notes", "user"),
+            label("L4", "Projects Old", "user"),
+            label("L5", "Archive", "user"),
+        ]
+    }
+
+    #[test]
+    fn test_plan_renames_nested_labels() {
+        let renames = plan_renames(&labels(), "L1", "Work").unwrap();
+        let names: Vec<(&str, &str)> = renames
+            .iter()
+            .map(|r| (r.old_name.as_str(), r.new_name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Projects", "Work"),
+                ("Projects/Alpha", "Work/Alpha"),
+                ("Projects/Alpha/Notes", "Work/Alpha/Notes"),
+            ]
+        );
+
+        assert!(plan_renames(&labels(), "INBOX", "Mail").is_err());
+        assert!(plan_renames(&labels(), "L1", "archive").is_err());
+        assert!(plan_renames(&labels(), "missing", "Work").is_err());
+        // Changing only the case doesn't collide with itself
+        assert!(plan_renames(&labels(), "L5", "ARCHIVE").is_ok());
+    }
+
+    #[test]
+    fn test_rewrite_query_label_terms() {
+        let renames = plan_renames(&labels(), "L1", "Client Work").unwrap();
+
+        assert_eq!(
+            rewrite_query("label:projects is:unread", &renames).as_deref(),
+            Some("label:Client-Work is:unread")
+        );
+        assert_eq!(
+            rewrite_query(
+                "(-label:Projects-Alpha OR label:\"Projects/Alpha/Notes\")",
+                &renames
+            )
+            .as_deref(),
+            Some("(-label:Client-Work-Alpha OR label:\"Client Work/Alpha/Notes\")")
+        );
+        // Other labels and look-alike words are left alone
+        assert_eq!(rewrite_query("label:projects-old", &renames), None);
+        assert_eq!(
+            rewrite_query("nolabel:projects subject:label", &renames),
+            None
+        );
+    }
+}
@@ -1,11 +1,126 @@
+pub mod account;
+pub mod actions;
+pub mod attachment_index;
+pub mod attachment_scan;
+pub mod attachment_text;
+pub mod bulk;
+pub mod calendar_invite;
+pub mod cleanup;
+pub mod clock;
+pub mod code_notifications;
+pub mod compose;
+pub mod conversation;
+pub mod deadline;
+pub mod digest;
+pub mod email;
+pub mod focus;
 pub mod gmail_auth;
 pub mod gmail_client;
 pub mod gmail_config;
+pub mod html_text;
+pub mod label_rename;
+pub mod local_store;
+pub mod mail_export;
+pub mod mail_import;
+pub mod mailbox;
+pub mod mailing_lists;
+pub mod message_cache;
+pub mod message_print;
+pub mod newsletter_feed;
+pub mod no_reply;
+pub mod notes_export;
+pub mod notify_priority;
+pub mod offline;
+pub mod onboarding;
+pub mod outbound_rules;
+pub mod pending_actions;
+pub mod people;
+pub mod poll_schedule;
+pub mod preflight;
+pub mod profile_cache;
+pub mod push;
+pub mod quoted_text;
 pub mod rate_limiter;
+pub mod reading_style;
+pub mod recent_recipients;
+pub mod reply_aliases;
+pub mod rules;
+pub mod scheduler;
+pub mod secrets;
 pub mod secure_storage;
+pub mod send_limits;
+pub mod send_receipts;
+pub mod sender_profile;
+pub mod settings;
+pub mod signature;
+pub mod sla;
+pub mod snooze;
+pub mod spam_review;
+pub mod storage_quota;
+#[cfg(any(test, feature = "fake-gmail"))]
+pub mod test_support;
+pub mod thread_claims;
+pub mod thread_mute;
+pub mod thread_watch;
+pub mod trash_countdown;
+pub mod unsubscribe;
+pub mod widget_summary;
 
+pub use account::{AccountProfile, AccountPurge, AccountScoped};
+pub use attachment_index::{AttachmentIndex, AttachmentMatch};
+pub use bulk::{BulkAction, BulkSummary};
+pub use calendar_invite::{CalendarInvite, RsvpResponse};
+pub use cleanup::CleanupProposal;
+pub use clock::{Clock, SystemClock};
+pub use code_notifications::{CodeNotification, CodeThread, NotificationReason};
+pub use compose::{ComposeSession, ComposeStore, ComposeUpdate, ForwardOptions};
+pub use conversation::{Conversation, ConversationAttachment, SubjectChange};
+pub use digest::{DigestState, WeeklyDigest};
+pub use email::{Category, Email};
+pub use focus::{FocusBuffer, FocusStatus};
 pub use gmail_auth::AuthTokens;
 pub use gmail_client::*;
 pub use gmail_config::*;
+pub use label_rename::{LabelRename, LabelRenameSummary};
+pub use mail_export::{ExportProgress, ExportSummary};
+pub use mail_import::{ImportProgress, ImportSummary};
+pub use mailbox::{MailboxContinuation, MailboxItems, MailboxPage, SearchPage};
+pub use mailing_lists::{ListDirectory, MailingList, MailingListSummary};
+pub use message_cache::MessageCache;
+pub use message_print::{PrintFormat, PrintableMessage};
+pub use newsletter_feed::{FeedList, NewsletterFeed};
+pub use notes_export::{EmailNote, NoteAttachment, NotionClient, SavedNote};
+pub use notify_priority::NotificationStyle;
+pub use offline::OfflineBundleSummary;
+pub use outbound_rules::{AuditEntry, AuditLog, OutboundMatch};
+pub use pending_actions::{Mutation, PendingAction, PendingActions};
+pub use people::ContactInfo;
+pub use preflight::PreflightReport;
+pub use profile_cache::ProfileCache;
+pub use push::{PushMode, PushStatus};
+pub use quoted_text::{BodySegment, SegmentKind};
 pub use rate_limiter::RateLimiter;
+pub use recent_recipients::{RecipientStore, RecipientSuggestion};
+pub use reply_aliases::{AliasRule, AliasRuleSet};
+pub use rules::{Rule, RuleScope, RuleSet};
+pub use scheduler::{Preset, PresetTime, ScheduledTime};
+pub use secrets::{SecretKind, SecretVault, StoredSecret};
 pub use secure_storage::{DefaultSecureStorage, SecureStorage};
+pub use send_limits::{SendLog, SendQuotaStatus};
+pub use send_receipts::{ReceiptLog, SendReceipt};
+pub use sender_profile::SenderProfile;
+pub use settings::{
+    AttachmentScanSettings, BackendSettings, FocusModeSettings, LocalApiSettings, NotesApp,
+    NotesSettings, NotesTarget, NotificationRule, NotificationSettings, OutboundRule,
+    OutboundRuleSettings, PushSettings, SendLimitSettings, SlaQueue, SlaSettings, ViewMode,
+};
+pub use signature::Signature;
+pub use sla::{SlaReport, SlaTracker, SlaWarning};
+pub use snooze::{SnoozeList, SnoozedEmail};
+pub use spam_review::{ProbableFalsePositive, SpamReview};
+pub use thread_claims::ThreadClaim;
+pub use thread_mute::{MuteList, MutedThread};
+pub use thread_watch::{WatchList, WatchedReply, WatchedThread};
+pub use trash_countdown::{TrashCountdown, TrashLog};
+pub use unsubscribe::{UnsubscribeOptions, UnsubscribeResult};
+pub use widget_summary::WidgetSummary;
@@ -0,0 +1,197 @@
+//! Optional localhost HTTP API so scripts and launchers (Raycast, Alfred) can
+//! drive the running app through its existing Gmail session and rate limits.
+//!
+//! Every request must send `Authorization: Bearer <token>`, where the token is
+//! the contents of `local_api_token` in the app config directory.
+
+use crate::local_store::app_data_path;
+use crate::{list_mailbox, send_reply, AppState};
+use axum::extract::{Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use oauth2::CsrfToken;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::net::{Ipv4Addr, SocketAddr};
+use tauri::{AppHandle, Manager};
+
+const TOKEN_FILE: &str = "local_api_token";
+
+#[derive(Clone)]
+struct ApiState {
+    app: AppHandle,
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    query: Option<String>,
+    page_token: Option<String>,
+    max_results: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    q: String,
+    page_token: Option<String>,
+    max_results: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct SendRequest {
+    original_email_id: String,
+    reply_body: String,
+}
+
+/// Read the API token, generating one readable only by the current user on first use
+pub fn load_or_create_token() -> Result<String, String> {
+    let path = app_data_path(TOKEN_FILE);
+    if let Ok(existing) = std::fs::read_to_string(&path) {
+        let existing = existing.trim();
+        if !existing.is_empty() {
+            return Ok(existing.to_string());
+        }
+    }
+
+    let token = format!(
+        "{}{}",
+        CsrfToken::new_random().secret(),
+        CsrfToken::new_random().secret()
+    );
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(&path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    file.write_all(token.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    Ok(token)
+}
+
+/// Serve the API on 127.0.0.1 until the app exits
+pub async fn serve(app: AppHandle, port: u16) -> Result<(), String> {
+    let state = ApiState {
+        app,
+        token: load_or_create_token()?,
+    };
+
+    let router = Router::new()
+        .route("/v1/mailbox", get(mailbox))
+        .route("/v1/search", get(search))
+        .route("/v1/send", post(send))
+        .route("/v1/unread", get(unread))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state);
+
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Failed to bind local API to {}: {}", addr, e))?;
+
+    axum::serve(listener, router)
+        .await
+        .map_err(|e| format!("Local API server error: {}", e))
+}
+
+async fn require_token(State(api): State<ApiState>, request: Request, next: Next) -> Response {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| tokens_match(token, &api.token));
+
+    if !authorized {
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid API token").into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Compare without short-circuiting so response timing doesn't leak the token
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn mailbox(State(api): State<ApiState>, Query(params): Query<ListParams>) -> Response {
+    respond(
+        list_mailbox(
+            params.query,
+            params.page_token,
+            params.max_results,
+            api.app.clone(),
+            api.app.state::<AppState>(),
+        )
+        .await,
+    )
+}
+
+async fn search(State(api): State<ApiState>, Query(params): Query<SearchParams>) -> Response {
+    respond(
+        list_mailbox(
+            Some(params.q),
+            params.page_token,
+            params.max_results,
+            api.app.clone(),
+            api.app.state::<AppState>(),
+        )
+        .await,
+    )
+}
+
+async fn send(State(api): State<ApiState>, Json(request): Json<SendRequest>) -> Response {
+    respond(
+        send_reply(
+            request.original_email_id,
+            request.reply_body,
+            None,
+            None,
+            None,
+            api.app.clone(),
+            api.app.state::<AppState>(),
+        )
+        .await,
+    )
+}
+
+/// The widget summary; served from memory, so polling it costs no Gmail quota
+async fn unread(State(api): State<ApiState>) -> Response {
+    let summary = api
+        .app
+        .state::<AppState>()
+        .widget_summary
+        .lock()
+        .unwrap()
+        .clone();
+    Json(summary).into_response()
+}
+
+/// Map command results onto HTTP statuses scripts can branch on
+fn respond<T: Serialize>(result: Result<T, String>) -> Response {
+    match result {
+        Ok(value) => Json(value).into_response(),
+        Err(e) if e.starts_with("Rate limit exceeded") => {
+            (StatusCode::TOO_MANY_REQUESTS, e).into_response()
+        }
+        Err(e) if e.starts_with("Authentication required") => {
+            (StatusCode::SERVICE_UNAVAILABLE, e).into_response()
+        }
+        Err(e) => (StatusCode::BAD_GATEWAY, e).into_response(),
+    }
+}
@@ -0,0 +1,67 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Path to a file in the app's config directory, creating the directory if needed
+pub fn app_data_path(file_name: &str) -> PathBuf {
+    let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("aisle3");
+    std::fs::create_dir_all(&path).ok();
+    path.push(file_name);
+    path
+}
+
+/// Load a JSON document, falling back to the default when missing or unreadable
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Save a JSON document, writing to a temp file first so a crash never leaves a torn file
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, json)
+        .map_err(|e| format!("Failed to write {}: {}", temp_path.display(), e))?;
+    std::fs::rename(&temp_path, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_json_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.json");
+
+        let mut value = HashMap::new();
+        value.insert("key".to_string(), 42u32);
+        save_json(&path, &value).unwrap();
+
+        let loaded: HashMap<String, u32> = load_json(&path);
+        assert_eq!(loaded.get("key"), Some(&42));
+        assert!(!path.with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn test_load_missing_or_corrupt_file_uses_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.json");
+        let loaded: Vec<String> = load_json(&missing);
+        assert!(loaded.is_empty());
+
+        let corrupt = dir.path().join("corrupt.json");
+        std::fs::write(&corrupt, "{not json").unwrap();
+        let loaded: Vec<String> = load_json(&corrupt);
+        assert!(loaded.is_empty());
+    }
+}
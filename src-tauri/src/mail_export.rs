@@ -0,0 +1,309 @@
+use crate::gmail_client::{extract_email_address, GmailClient};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Errors kept in the summary; the count in `failed` keeps going past this
+const MAX_REPORTED_ERRORS: usize = 50;
+
+/// The checkpoint is rewritten this often rather than after every message,
+/// since it carries the whole id list
+const CHECKPOINT_EVERY: usize = 50;
+
+/// A run stops after this many downloads fail in a row, which means the
+/// connection or the session is gone rather than one bad message. The
+/// streak is retried when the export is resumed.
+const MAX_CONSECUTIVE_FAILURES: usize = 5;
+
+/// Sender used on the separator line when the message has no usable From
+const UNKNOWN_SENDER: &str = "MAILER-DAEMON";
+
+/// Progress payload emitted after each message
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportProgress {
+    pub operation_id: String,
+    pub exported: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+/// Outcome of an export run
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSummary {
+    pub operation_id: String,
+    pub total: usize,
+    pub exported: usize,
+    pub failed: usize,
+    pub errors: Vec<String>,
+    /// False when the run stopped early; exporting to the same path with the
+    /// same query picks up where it left off
+    pub complete: bool,
+    /// This run continued an earlier, interrupted one
+    pub resumed: bool,
+}
+
+/// Where an interrupted export got to, kept beside the archive until the
+/// export completes
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ExportCheckpoint {
+    query: String,
+    /// Matches as listed when the export started, so a resumed run covers
+    /// the same messages even if the mailbox changed in between
+    message_ids: Vec<String>,
+    next: usize,
+    /// Archive length after the last checkpointed message; anything past it
+    /// is a partial write and is cut off on resume
+    bytes_written: u64,
+    exported: usize,
+    failed: usize,
+    errors: Vec<String>,
+}
+
+impl ExportCheckpoint {
+    fn path(archive: &Path) -> PathBuf {
+        let mut name = archive.as_os_str().to_owned();
+        name.push(".export.json");
+        PathBuf::from(name)
+    }
+
+    fn load(archive: &Path, query: &str) -> Option<Self> {
+        let data = std::fs::read(Self::path(archive)).ok()?;
+        let checkpoint: Self = serde_json::from_slice(&data).ok()?;
+        (checkpoint.query == query).then_some(checkpoint)
+    }
+
+    fn save(&self, archive: &Path) -> Result<(), String> {
+        let data = serde_json::to_vec(self).map_err(|e| e.to_string())?;
+        std::fs::write(Self::path(archive), data).map_err(|e| e.to_string())
+    }
+
+    fn fail(&mut self, error: String) {
+        self.failed += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(error);
+        }
+    }
+}
+
+/// One mboxrd entry: a "From " separator line, the message with LF line
+/// endings and ">From " quoting, and the blank line that ends it
+pub fn mbox_entry(raw: &[u8]) -> Vec<u8> {
+    let sender = header_value(raw, "From")
+        .map(|from| extract_email_address(&from))
+        .filter(|address| !address.is_empty() && !address.contains(char::is_whitespace))
+        .unwrap_or_else(|| UNKNOWN_SENDER.to_string());
+    let date = header_value(raw, "Date")
+        .and_then(|date| DateTime::parse_from_rfc2822(date.trim()).ok())
+        .map(|date| date.with_timezone(&Utc))
+        .unwrap_or_default();
+
+    let mut entry =
+        format!("From {} {}\n", sender, date.format("%a %b %e %H:%M:%S %Y")).into_bytes();
+    entry.reserve(raw.len() + 2);
+    for line in raw.split_inclusive(|&b| b == b'\n') {
+        let line = line
+            .strip_suffix(b"\r\n")
+            .or_else(|| line.strip_suffix(b"\n"))
+            .unwrap_or(line);
+        let quoted = line
+            .iter()
+            .position(|&b| b != b'>')
+            .is_some_and(|start| line[start..].starts_with(b"From "));
+        if quoted {
+            entry.push(b'>');
+        }
+        entry.extend_from_slice(line);
+        entry.push(b'\n');
+    }
+    entry.push(b'\n');
+    entry
+}
+
+/// First value of a header in a raw message, with folded lines joined
+fn header_value(raw: &[u8], name: &str) -> Option<String> {
+    let mut value: Option<String> = None;
+    for line in raw.split(|&b| b == b'\n') {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some(value) = value.as_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if value.is_some() {
+            break;
+        }
+        if let Some((field, rest)) = line.split_once(':') {
+            if field.trim().eq_ignore_ascii_case(name) {
+                value = Some(rest.trim().to_string());
+            }
+        }
+    }
+    value
+}
+
+/// Export every message matching `query` (Gmail search syntax, e.g.
+/// "label:receipts"; empty for the whole mailbox outside Spam and Trash) to
+/// an mbox archive at `path`, raising progress after each message. A
+/// checkpoint beside the archive lets an interrupted export resume.
+pub async fn export_mbox<F>(
+    gmail_client: &GmailClient,
+    operation_id: &str,
+    query: &str,
+    path: &Path,
+    mut on_progress: F,
+) -> Result<ExportSummary, Box<dyn std::error::Error + Send + Sync>>
+where
+    F: FnMut(&ExportProgress),
+{
+    let (mut checkpoint, resumed) = match ExportCheckpoint::load(path, query) {
+        Some(checkpoint) => (checkpoint, true),
+        None => {
+            let message_ids = gmail_client.list_all_message_ids(query, usize::MAX).await?;
+            let checkpoint = ExportCheckpoint {
+                query: query.to_string(),
+                message_ids,
+                ..Default::default()
+            };
+            checkpoint.save(path)?;
+            (checkpoint, false)
+        }
+    };
+
+    // Cut off whatever was written after the last checkpoint
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?
+        .set_len(checkpoint.bytes_written)?;
+    let mut file = OpenOptions::new().append(true).open(path)?;
+
+    let total = checkpoint.message_ids.len();
+    let mut streak: Vec<(usize, String)> = Vec::new();
+    let mut since_checkpoint = 0;
+    let mut bytes_written = checkpoint.bytes_written;
+    let mut stopped = false;
+
+    while checkpoint.next < total {
+        let index = checkpoint.next;
+        let message_id = checkpoint.message_ids[index].clone();
+        checkpoint.next += 1;
+
+        match gmail_client.get_raw_message(&message_id).await {
+            Ok(raw) => {
+                for (_, error) in streak.drain(..) {
+                    checkpoint.fail(error);
+                }
+                let entry = mbox_entry(&raw);
+                file.write_all(&entry)?;
+                bytes_written += entry.len() as u64;
+                checkpoint.exported += 1;
+            }
+            Err(e) => {
+                streak.push((index, format!("{}: {}", message_id, e)));
+                if streak.len() >= MAX_CONSECUTIVE_FAILURES {
+                    checkpoint.next = streak[0].0;
+                    stopped = true;
+                    break;
+                }
+            }
+        }
+
+        on_progress(&ExportProgress {
+            operation_id: operation_id.to_string(),
+            exported: checkpoint.exported,
+            failed: checkpoint.failed + streak.len(),
+            total,
+        });
+
+        since_checkpoint += 1;
+        if since_checkpoint >= CHECKPOINT_EVERY && streak.is_empty() {
+            file.flush()?;
+            checkpoint.bytes_written = bytes_written;
+            checkpoint.save(path)?;
+            since_checkpoint = 0;
+        }
+    }
+
+    file.flush()?;
+    if stopped {
+        // Only messages before the failing streak are kept; the streak and
+        // anything after it are fetched again on resume
+        file.set_len(bytes_written)?;
+        checkpoint.bytes_written = bytes_written;
+        checkpoint.save(path)?;
+    } else {
+        for (_, error) in streak.drain(..) {
+            checkpoint.fail(error);
+        }
+        let _ = std::fs::remove_file(ExportCheckpoint::path(path));
+    }
+
+    let mut errors = checkpoint.errors;
+    if stopped {
+        errors.extend(streak.into_iter().map(|(_, error)| error));
+    }
+    Ok(ExportSummary {
+        operation_id: operation_id.to_string(),
+        total,
+        exported: checkpoint.exported,
+        failed: checkpoint.failed,
+        errors,
+        complete: !stopped,
+        resumed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mail_import::MboxReader;
+
+    #[test]
+    fn test_entry_has_separator_and_quoting() {
+        let raw = b"From: Alice <alice@example.com>\r\n\
+                    Date: Tue, 2 Jan 2024 09:30:00 +0100\r\n\
+                    Subject: Hi\r\n\r\nFrom the top\r\n>From quoted\r\nbye";
+        let entry = String::from_utf8(mbox_entry(raw)).unwrap();
+        assert_eq!(
+            entry,
+            "From alice@example.com Tue Jan  2 08:30:00 2024\n\
+             From: Alice <alice@example.com>\n\
+             Date: Tue, 2 Jan 2024 09:30:00 +0100\n\
+             Subject: Hi\n\n>From the top\n>>From quoted\nbye\n\n"
+        );
+    }
+
+    #[test]
+    fn test_entries_read_back_as_the_original_messages() {
+        let first = b"Subject: One\n\nFrom here\n".to_vec();
+        let second = b"From: \n\nno date\n".to_vec();
+        let mut mbox = mbox_entry(&first);
+        mbox.extend(mbox_entry(&second));
+        assert!(mbox.starts_with(b"From MAILER-DAEMON Thu Jan  1 00:00:00 1970\n"));
+
+        let messages: Vec<Vec<u8>> = MboxReader::new(mbox.as_slice())
+            .map(|m| m.unwrap())
+            .collect();
+        // The reader leaves the archive's closing blank line on the last message
+        assert_eq!(messages, vec![first, [second, b"\n".to_vec()].concat()]);
+    }
+
+    #[test]
+    fn test_folded_headers() {
+        let raw = b"Subject: Hi\r\nFrom: \"Long Name\"\r\n <long@example.com>\r\n\r\nFrom: body";
+        assert_eq!(
+            header_value(raw, "from").as_deref(),
+            Some("\"Long Name\" <long@example.com>")
+        );
+        assert_eq!(header_value(raw, "Date"), None);
+    }
+}
@@ -0,0 +1,283 @@
+use crate::gmail_client::{to_eml, GmailClient, ImportMode};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Errors kept in the summary; the count in `failed` keeps going past this
+const MAX_REPORTED_ERRORS: usize = 50;
+
+/// Progress payload emitted after each message
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportProgress {
+    pub operation_id: String,
+    pub imported: usize,
+    pub failed: usize,
+    /// Bytes read across all files, for a progress bar on large archives
+    pub bytes_read: u64,
+    pub total_bytes: u64,
+}
+
+/// Outcome of an import run
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportSummary {
+    pub operation_id: String,
+    pub imported: usize,
+    pub failed: usize,
+    pub errors: Vec<String>,
+    /// Gmail ids of the imported messages
+    pub message_ids: Vec<String>,
+}
+
+/// Splits an mbox archive into messages one at a time, so an archive of any
+/// size is never held in memory whole. Handles both mboxo and mboxrd: a
+/// message starts at a "From " line at the top of the file or after a blank
+/// line, and one level of ">From " quoting is removed.
+pub struct MboxReader<R> {
+    reader: R,
+    /// The separator line that starts the next message was already read
+    at_separator: bool,
+    bytes_read: u64,
+}
+
+impl<R: BufRead> MboxReader<R> {
+    pub fn new(reader: R) -> Self {
+        MboxReader {
+            reader,
+            at_separator: false,
+            bytes_read: 0,
+        }
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    fn read_line(&mut self, line: &mut Vec<u8>) -> std::io::Result<usize> {
+        line.clear();
+        let read = self.reader.read_until(b'\n', line)?;
+        self.bytes_read += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> Iterator for MboxReader<R> {
+    type Item = std::io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = Vec::new();
+        // Skip to the first separator
+        while !self.at_separator {
+            match self.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) if line.starts_with(b"From ") => self.at_separator = true,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        let mut message = Vec::new();
+        let mut previous_blank = true;
+        self.at_separator = false;
+        loop {
+            match self.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            if previous_blank && line.starts_with(b"From ") {
+                self.at_separator = true;
+                break;
+            }
+            previous_blank = line == b"\n" || line == b"\r\n";
+            let quoted_from = line.starts_with(b">")
+                && line
+                    .iter()
+                    .position(|&b| b != b'>')
+                    .is_some_and(|start| line[start..].starts_with(b"From "));
+            message.extend_from_slice(if quoted_from { &line[1..] } else { &line });
+        }
+
+        // The blank line before a separator belongs to the mbox, not the message
+        if self.at_separator {
+            if message.ends_with(b"\r\n") {
+                message.truncate(message.len() - 2);
+            } else if message.ends_with(b"\n") {
+                message.truncate(message.len() - 1);
+            }
+        }
+        Some(Ok(message))
+    }
+}
+
+/// Whether `path` is read as a single message rather than an mbox archive
+pub fn is_eml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("eml"))
+}
+
+/// Import .eml files and mbox archives into the mailbox, one message at a
+/// time, applying `label_ids` to each. A message that fails is counted and
+/// the run carries on; an unreadable file stops only that file.
+pub async fn import_files<F>(
+    gmail_client: &GmailClient,
+    operation_id: &str,
+    paths: &[String],
+    label_ids: &[String],
+    mode: ImportMode,
+    mut on_progress: F,
+) -> ImportSummary
+where
+    F: FnMut(&ImportProgress),
+{
+    let total_bytes = paths
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    let mut summary = ImportSummary {
+        operation_id: operation_id.to_string(),
+        imported: 0,
+        failed: 0,
+        errors: Vec::new(),
+        message_ids: Vec::new(),
+    };
+    let mut finished_bytes = 0;
+
+    for path in paths.iter().map(Path::new) {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                fail(&mut summary, format!("{}: {}", path.display(), e));
+                continue;
+            }
+        };
+        let file_bytes = file.metadata().map(|m| m.len()).unwrap_or(0);
+
+        if is_eml(path) {
+            let result = match std::fs::read(path) {
+                Ok(raw) => import_one(gmail_client, &raw, label_ids, mode).await,
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(message_id) => {
+                    summary.imported += 1;
+                    summary.message_ids.push(message_id);
+                }
+                Err(e) => fail(&mut summary, format!("{}: {}", path.display(), e)),
+            }
+            finished_bytes += file_bytes;
+            report(&mut on_progress, &summary, finished_bytes, total_bytes);
+            continue;
+        }
+
+        let mut mbox = MboxReader::new(BufReader::new(file));
+        let mut index = 0;
+        while let Some(next) = mbox.next() {
+            index += 1;
+            let result = match next {
+                Ok(raw) => import_one(gmail_client, &raw, label_ids, mode).await,
+                Err(e) => {
+                    fail(&mut summary, format!("{}: {}", path.display(), e));
+                    break;
+                }
+            };
+            match result {
+                Ok(message_id) => {
+                    summary.imported += 1;
+                    summary.message_ids.push(message_id);
+                }
+                Err(e) => fail(
+                    &mut summary,
+                    format!("{} message {}: {}", path.display(), index, e),
+                ),
+            }
+            let bytes_read = finished_bytes + mbox.bytes_read();
+            report(&mut on_progress, &summary, bytes_read, total_bytes);
+        }
+        finished_bytes += file_bytes;
+    }
+
+    summary
+}
+
+fn fail(summary: &mut ImportSummary, error: String) {
+    summary.failed += 1;
+    if summary.errors.len() < MAX_REPORTED_ERRORS {
+        summary.errors.push(error);
+    }
+}
+
+async fn import_one(
+    gmail_client: &GmailClient,
+    raw: &[u8],
+    label_ids: &[String],
+    mode: ImportMode,
+) -> Result<String, String> {
+    if raw.iter().all(u8::is_ascii_whitespace) {
+        return Err("empty message".to_string());
+    }
+    gmail_client
+        .import_message(&to_eml(raw), label_ids, mode)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+fn report<F: FnMut(&ImportProgress)>(
+    on_progress: &mut F,
+    summary: &ImportSummary,
+    bytes_read: u64,
+    total_bytes: u64,
+) {
+    on_progress(&ImportProgress {
+        operation_id: summary.operation_id.clone(),
+        imported: summary.imported,
+        failed: summary.failed,
+        bytes_read: bytes_read.min(total_bytes),
+        total_bytes,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(mbox: &str) -> Vec<String> {
+        MboxReader::new(mbox.as_bytes())
+            .map(|m| String::from_utf8(m.unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_mbox_splits_on_separator_lines() {
+        let mbox = "From alice@example.com Mon Jan  1 00:00:00 2024\n\
+                    Subject: One\n\nHello\nFrom the start of a line\n\n\
+                    From bob@example.com Tue Jan  2 00:00:00 2024\n\
+                    Subject: Two\n\n>From here, quoted\n>>From twice\n";
+        assert_eq!(
+            split(mbox),
+            vec![
+                "Subject: One\n\nHello\nFrom the start of a line\n",
+                "Subject: Two\n\nFrom here, quoted\n>From twice\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mbox_reports_bytes_read_and_ignores_preamble() {
+        let mbox = "junk before the first message\nFrom x\nSubject: Only\n";
+        let mut reader = MboxReader::new(mbox.as_bytes());
+        assert_eq!(reader.next().unwrap().unwrap(), b"Subject: Only\n");
+        assert!(reader.next().is_none());
+        assert_eq!(reader.bytes_read(), mbox.len() as u64);
+
+        assert!(split("").is_empty());
+    }
+
+    #[test]
+    fn test_eml_paths() {
+        assert!(is_eml(Path::new("/tmp/message.EML")));
+        assert!(!is_eml(Path::new("/tmp/archive.mbox")));
+        assert!(!is_eml(Path::new("/tmp/Inbox")));
+    }
+}
@@ -0,0 +1,297 @@
+use crate::attachment_index::AttachmentMatch;
+use crate::conversation::Conversation;
+use crate::deadline::{self, DEADLINE_CHUNK_SIZE};
+use crate::email::Email;
+use crate::gmail_client::GmailClient;
+use crate::settings::ViewMode;
+use serde::Serialize;
+use std::time::Instant;
+
+/// Default and maximum page sizes; the maximum matches Gmail's batch limit
+pub const DEFAULT_PAGE_SIZE: u32 = 20;
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// Gmail query for the priority inbox: inbox mail marked IMPORTANT
+pub const IMPORTANT_QUERY: &str = "in:inbox is:important";
+
+/// Page contents, tagged with the view mode that produced them
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "view_mode", content = "items", rename_all = "snake_case")]
+pub enum MailboxItems {
+    Conversations(Vec<Conversation>),
+    Messages(Vec<Email>),
+}
+
+impl MailboxItems {
+    /// The rows as messages; conversations give their messages in thread order
+    pub fn into_emails(self) -> Vec<Email> {
+        match self {
+            MailboxItems::Conversations(conversations) => conversations
+                .into_iter()
+                .flat_map(|conversation| conversation.emails)
+                .collect(),
+            MailboxItems::Messages(emails) => emails,
+        }
+    }
+
+    /// Add rows loaded later for the same listing; both sides come from one
+    /// view mode
+    pub fn append(&mut self, more: MailboxItems) {
+        match (self, more) {
+            (MailboxItems::Conversations(rows), MailboxItems::Conversations(more)) => {
+                rows.extend(more)
+            }
+            (MailboxItems::Messages(rows), MailboxItems::Messages(more)) => rows.extend(more),
+            (rows, more) => *rows = more,
+        }
+    }
+}
+
+/// One page of a mailbox listing.
+///
+/// In both modes `page_size` counts top-level rows (threads or messages) and
+/// `next_page_token` comes from the matching Gmail list endpoint, so paging
+/// behaves the same whichever mode is active.
+#[derive(Debug, Serialize, Clone)]
+pub struct MailboxPage {
+    #[serde(flatten)]
+    pub items: MailboxItems,
+    pub next_page_token: Option<String>,
+    pub result_size_estimate: Option<u32>,
+    /// Set when the response deadline passed before every row loaded; the
+    /// rest arrive in a "mailbox-page-continued" event carrying this token
+    pub continuation: Option<String>,
+    /// Thread or message ids still to load, in list order
+    #[serde(skip)]
+    pub pending_ids: Vec<String>,
+}
+
+/// Payload of the "mailbox-page-continued" event
+#[derive(Debug, Serialize, Clone)]
+pub struct MailboxContinuation {
+    pub continuation: String,
+    #[serde(flatten)]
+    pub items: MailboxItems,
+    pub error: Option<String>,
+}
+
+/// One page of search results, shaped by the view mode like `MailboxPage`
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchPage {
+    #[serde(flatten)]
+    pub items: MailboxItems,
+    /// Local attachment index hits, only filled on the first page
+    pub attachment_matches: Vec<AttachmentMatch>,
+    pub next_page_token: Option<String>,
+    pub result_size_estimate: Option<u32>,
+}
+
+/// Clamp a requested page size to what a single batch request can hydrate
+pub fn page_size(requested: Option<u32>) -> u32 {
+    requested
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Fetch one page of mail shaped according to the view mode. Rows still
+/// loading at `deadline` are left in `pending_ids` for `hydrate`.
+pub async fn fetch_page(
+    gmail_client: &GmailClient,
+    view_mode: ViewMode,
+    query: Option<&str>,
+    page_token: Option<&str>,
+    page_size: u32,
+    deadline: Instant,
+) -> Result<MailboxPage, Box<dyn std::error::Error + Send + Sync>> {
+    let (ids, next_page_token, result_size_estimate) =
+        list_ids(gmail_client, view_mode, query, page_token, page_size).await?;
+
+    let (items, pending_ids) = match view_mode {
+        ViewMode::Conversations => {
+            let partial = deadline::fetch_until(&ids, DEADLINE_CHUNK_SIZE, deadline, |chunk| {
+                hydrate_conversations(gmail_client, chunk)
+            })
+            .await?;
+            (
+                MailboxItems::Conversations(partial.items),
+                partial.remaining,
+            )
+        }
+        ViewMode::Messages => {
+            let partial = deadline::fetch_until(&ids, DEADLINE_CHUNK_SIZE, deadline, |chunk| {
+                hydrate_messages(gmail_client, chunk)
+            })
+            .await?;
+            (MailboxItems::Messages(partial.items), partial.remaining)
+        }
+    };
+
+    Ok(MailboxPage {
+        items,
+        next_page_token,
+        result_size_estimate,
+        continuation: None,
+        pending_ids,
+    })
+}
+
+/// Thread or message ids for one page of a listing, with the page token and
+/// size estimate from the matching Gmail list endpoint
+async fn list_ids(
+    gmail_client: &GmailClient,
+    view_mode: ViewMode,
+    query: Option<&str>,
+    page_token: Option<&str>,
+    page_size: u32,
+) -> Result<(Vec<String>, Option<String>, Option<u32>), Box<dyn std::error::Error + Send + Sync>> {
+    Ok(match view_mode {
+        ViewMode::Conversations => {
+            let response = gmail_client
+                .list_threads(Some(page_size), page_token, query)
+                .await?;
+            let ids = response
+                .threads
+                .unwrap_or_default()
+                .into_iter()
+                .map(|t| t.id)
+                .collect::<Vec<_>>();
+            (ids, response.next_page_token, response.result_size_estimate)
+        }
+        ViewMode::Messages => {
+            let response = gmail_client
+                .list_messages(Some(page_size), page_token, query)
+                .await?;
+            let ids = response
+                .messages
+                .unwrap_or_default()
+                .into_iter()
+                .map(|m| m.id)
+                .collect::<Vec<_>>();
+            (ids, response.next_page_token, response.result_size_estimate)
+        }
+    })
+}
+
+/// Fetch one page of search results shaped according to the view mode,
+/// waiting for every row
+pub async fn search(
+    gmail_client: &GmailClient,
+    view_mode: ViewMode,
+    query: Option<&str>,
+    page_token: Option<&str>,
+    page_size: u32,
+) -> Result<SearchPage, Box<dyn std::error::Error + Send + Sync>> {
+    let (ids, next_page_token, result_size_estimate) =
+        list_ids(gmail_client, view_mode, query, page_token, page_size).await?;
+    Ok(SearchPage {
+        items: hydrate(gmail_client, view_mode, ids).await?,
+        attachment_matches: Vec::new(),
+        next_page_token,
+        result_size_estimate,
+    })
+}
+
+/// First page of threads matching `query` as conversations, whatever the
+/// view mode
+pub async fn search_conversations(
+    gmail_client: &GmailClient,
+    query: Option<&str>,
+    page_size: u32,
+) -> Result<Vec<Conversation>, Box<dyn std::error::Error + Send + Sync>> {
+    let (ids, _, _) = list_ids(
+        gmail_client,
+        ViewMode::Conversations,
+        query,
+        None,
+        page_size,
+    )
+    .await?;
+    hydrate_conversations(gmail_client, ids).await
+}
+
+/// Load the rows a deadline-bound `fetch_page` left pending
+pub async fn hydrate(
+    gmail_client: &GmailClient,
+    view_mode: ViewMode,
+    ids: Vec<String>,
+) -> Result<MailboxItems, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(match view_mode {
+        ViewMode::Conversations => {
+            MailboxItems::Conversations(hydrate_conversations(gmail_client, ids).await?)
+        }
+        ViewMode::Messages => MailboxItems::Messages(hydrate_messages(gmail_client, ids).await?),
+    })
+}
+
+async fn hydrate_conversations(
+    gmail_client: &GmailClient,
+    thread_ids: Vec<String>,
+) -> Result<Vec<Conversation>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut threads = gmail_client.get_threads_batch(&thread_ids).await?;
+    threads.sort_by_key(|t| thread_ids.iter().position(|id| *id == t.id));
+    Ok(threads
+        .iter()
+        .map(|t| Conversation::from_messages(&t.id, t.messages.as_deref().unwrap_or(&[])))
+        .collect())
+}
+
+/// Full messages for `message_ids` as list rows, in the order given
+async fn hydrate_messages(
+    gmail_client: &GmailClient,
+    message_ids: Vec<String>,
+) -> Result<Vec<Email>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut messages = gmail_client.get_messages_batch(&message_ids).await?;
+    // Batch responses are not guaranteed to be in request order
+    messages.sort_by_key(|m| message_ids.iter().position(|id| *id == m.id));
+    Ok(messages.iter().map(Email::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_size_is_clamped() {
+        assert_eq!(page_size(None), DEFAULT_PAGE_SIZE);
+        assert_eq!(page_size(Some(0)), 1);
+        assert_eq!(page_size(Some(500)), MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_page_serialization_is_tagged_by_mode() {
+        let page = MailboxPage {
+            items: MailboxItems::Messages(vec![]),
+            next_page_token: Some("next".to_string()),
+            result_size_estimate: Some(0),
+            continuation: None,
+            pending_ids: vec!["m1".to_string()],
+        };
+
+        let json = serde_json::to_value(&page).unwrap();
+        assert_eq!(json["view_mode"], "messages");
+        assert!(json["items"].is_array());
+        assert_eq!(json["next_page_token"], "next");
+        assert!(json["continuation"].is_null());
+        assert!(json.get("pending_ids").is_none());
+    }
+
+    #[test]
+    fn test_append_keeps_list_order() {
+        let email = |id: &str| Email {
+            id: id.to_string(),
+            thread_id: id.to_string(),
+            subject: String::new(),
+            sender: String::new(),
+            snippet: String::new(),
+            is_read: false,
+            is_no_reply: false,
+            is_important: false,
+            category: None,
+        };
+        let mut items = MailboxItems::Messages(vec![email("m1")]);
+        items.append(MailboxItems::Messages(vec![email("m2")]));
+
+        let ids: Vec<String> = items.into_emails().into_iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec!["m1", "m2"]);
+    }
+}
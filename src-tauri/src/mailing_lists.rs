@@ -0,0 +1,227 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::GmailMessage;
+use crate::local_store::{app_data_path, load_json, save_json};
+use serde::{Deserialize, Serialize};
+
+const LISTS_FILE: &str = "mailing_lists.json";
+
+/// Lists whose unread counts `get_mailing_lists` looks up, most recent first
+pub const MAX_COUNTED_LISTS: usize = 50;
+
+/// A mailing list sync has seen mail from, keyed by its List-Id (RFC 2919)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MailingList {
+    /// e.g. "announce.lists.example.com"
+    pub list_id: String,
+    /// The header's description, or the id when it has none
+    pub name: String,
+    /// Unix timestamp (seconds)
+    pub last_seen: u64,
+    /// Messages from the list sync has seen
+    pub messages_seen: u64,
+}
+
+impl MailingList {
+    /// Gmail search for the list's mail
+    pub fn query(&self) -> String {
+        list_query(&self.list_id)
+    }
+}
+
+/// One list as `get_mailing_lists` reports it
+#[derive(Debug, Clone, Serialize)]
+pub struct MailingListSummary {
+    pub list_id: String,
+    pub name: String,
+    pub last_seen: u64,
+    /// Unread inbox messages from the list; None when the count failed
+    pub unread_count: Option<u32>,
+    /// Search that opens the list's mail
+    pub query: String,
+}
+
+impl MailingListSummary {
+    pub fn new(list: &MailingList, unread_count: Option<u32>) -> Self {
+        MailingListSummary {
+            list_id: list.list_id.clone(),
+            name: list.name.clone(),
+            last_seen: list.last_seen,
+            unread_count,
+            query: list.query(),
+        }
+    }
+}
+
+/// Mailing lists found in synced mail, so list mail can be grouped without
+/// the user writing Gmail filters
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListDirectory {
+    lists: Vec<MailingList>,
+    account_id: Option<String>,
+}
+
+impl ListDirectory {
+    pub fn load() -> Self {
+        load_json(&app_data_path(LISTS_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(LISTS_FILE), self)
+    }
+
+    /// Known lists, most recently seen first
+    pub fn lists(&self) -> Vec<MailingList> {
+        let mut lists = self.lists.clone();
+        lists.sort_by_key(|l| std::cmp::Reverse(l.last_seen));
+        lists
+    }
+
+    /// Note list mail among `messages`, returning whether anything changed
+    pub fn record(&mut self, messages: &[GmailMessage], now: u64) -> bool {
+        let mut changed = false;
+        for message in messages {
+            let Some((list_id, name)) = message
+                .get_list_id()
+                .and_then(|header| parse_list_id(&header))
+            else {
+                continue;
+            };
+            match self.lists.iter_mut().find(|l| l.list_id == list_id) {
+                Some(list) => {
+                    // Keep a description from earlier mail over a bare id
+                    if name != list_id {
+                        list.name = name;
+                    }
+                    list.last_seen = now;
+                    list.messages_seen += 1;
+                }
+                None => self.lists.push(MailingList {
+                    list_id,
+                    name,
+                    last_seen: now,
+                    messages_seen: 1,
+                }),
+            }
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// The id and display name from a List-Id header such as
+/// `"Rust Announce" <announce.rust-lang.org>`. Ids are compared lowercased.
+pub fn parse_list_id(header: &str) -> Option<(String, String)> {
+    let header = header.trim();
+    let (name, id) = match (header.rfind('<'), header.rfind('>')) {
+        (Some(start), Some(end)) if start < end => (&header[..start], &header[start + 1..end]),
+        _ => ("", header),
+    };
+    let id = id.trim().to_ascii_lowercase();
+    if id.is_empty() || id.contains(char::is_whitespace) {
+        return None;
+    }
+    let name = name.trim().trim_matches('"').trim();
+    let name = if name.is_empty() {
+        id.clone()
+    } else {
+        name.to_string()
+    };
+    Some((id, name))
+}
+
+/// Gmail search for a list's mail; `list:` matches the List-Id header
+pub fn list_query(list_id: &str) -> String {
+    format!("list:{}", list_id)
+}
+
+impl AccountScoped for ListDirectory {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::{MessageHeader, MessagePayload};
+
+    fn message(id: &str, list_id: Option<&str>) -> GmailMessage {
+        GmailMessage {
+            id: id.to_string(),
+            thread_id: id.to_string(),
+            payload: Some(MessagePayload {
+                headers: Some(
+                    list_id
+                        .map(|value| MessageHeader {
+                            name: "List-ID".to_string(),
+                            value: value.to_string(),
+                        })
+                        .into_iter()
+                        .collect(),
+                ),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_list_id_forms() {
+        assert_eq!(
+            parse_list_id("\"Rust Announce\" <Announce.Rust-Lang.org>"),
+            Some((
+                "announce.rust-lang.org".to_string(),
+                "Rust Announce".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_list_id("<dev.example.com>"),
+            Some(("dev.example.com".to_string(), "dev.example.com".to_string()))
+        );
+        assert_eq!(
+            parse_list_id("bare.example.com"),
+            Some((
+                "bare.example.com".to_string(),
+                "bare.example.com".to_string()
+            ))
+        );
+        assert_eq!(parse_list_id("  "), None);
+    }
+
+    #[test]
+    fn test_record_groups_mail_by_list() {
+        let mut directory = ListDirectory::default();
+        assert!(!directory.record(&[message("m0", None)], 5));
+
+        directory.record(
+            &[
+                message("m1", Some("Dev <dev.example.com>")),
+                message("m2", Some("<DEV.example.com>")),
+                message("m3", Some("News <news.example.com>")),
+            ],
+            10,
+        );
+        assert!(directory.record(&[message("m4", Some("News <news.example.com>"))], 20));
+
+        let lists = directory.lists();
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists[0].list_id, "news.example.com");
+        assert_eq!(lists[0].messages_seen, 2);
+        assert_eq!(lists[1].messages_seen, 2);
+        assert_eq!(lists[1].name, "Dev");
+        assert_eq!(lists[1].query(), "list:dev.example.com");
+    }
+}
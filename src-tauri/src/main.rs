@@ -1,39 +1,379 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod account;
+mod actions;
+mod attachment_index;
+mod attachment_scan;
+mod attachment_text;
+#[cfg(any(target_os = "macos", windows))]
+mod automation;
+mod bulk;
+mod calendar_invite;
+mod cleanup;
+mod clock;
+mod code_notifications;
+mod compose;
+mod conversation;
+mod deadline;
+mod digest;
+mod email;
+mod focus;
 mod gmail_auth;
 mod gmail_client;
 mod gmail_config;
+mod html_text;
+mod label_rename;
+#[cfg(feature = "local-api")]
+mod local_api;
+mod local_store;
+mod mail_export;
+mod mail_import;
+mod mailbox;
+mod mailing_lists;
+mod message_cache;
+mod message_print;
+mod newsletter_feed;
+mod no_reply;
+mod notes_export;
+mod notify_priority;
+mod offline;
+mod onboarding;
+mod outbound_rules;
+mod pending_actions;
+mod people;
+mod poll_schedule;
+mod preflight;
+mod profile_cache;
+mod push;
+mod quoted_text;
 mod rate_limiter;
+mod reading_style;
+mod recent_recipients;
+mod reply_aliases;
+mod rules;
+mod scheduler;
+mod secrets;
 mod secure_storage;
+mod send_limits;
+mod send_receipts;
+mod sender_profile;
+mod settings;
+mod signature;
+mod sla;
+mod snooze;
+mod spam_review;
+mod storage_quota;
+// The bin's unit tests only need the fake clock, not the fake Gmail server
+#[cfg(test)]
+mod test_support {
+    mod fake_clock;
+    pub use fake_clock::FakeClock;
+}
+mod thread_claims;
+mod thread_mute;
+mod thread_watch;
+mod trash_countdown;
+mod unsubscribe;
+mod widget_summary;
 
+use account::{AccountProfile, AccountPurge, AccountScoped};
+use actions::{ActionContext, ActionInputs, PaletteAction};
+use attachment_index::{AttachmentIndex, IndexedAttachment};
+use bulk::{BulkAction, BulkSummary, UndoHistory};
+use calendar_invite::RsvpResponse;
+use cleanup::CleanupProposal;
+use clock::{Clock, SystemClock};
+use code_notifications::{CodeThread, NotificationReason};
+use compose::{
+    ComposeAttachment, ComposeSession, ComposeStore, ComposeUpdate, DraftPage, DraftSummary,
+    ForwardOptions, ReplyContext, SendComposeResult, StaleReplyWarning,
+};
+use conversation::{rollup_attachments, Conversation};
+use digest::{DigestState, WeeklyDigest};
+use email::{Category, Email};
+use focus::{FocusBuffer, FocusStatus};
 use gmail_auth::{parse_callback_url, AuthTokens, GmailAuth};
-use gmail_client::GmailClient;
+use gmail_client::{
+    check_attachment_size, is_not_found_error, is_transient_error, AutoForwarding, FilterAction,
+    FilterCriteria, ForwardingAddress, ForwardingDisposition, GmailClient, GmailDraftMessage,
+    GmailFilter, GmailLabel, GmailMessage, GmailProfile, ImportMode, MailboxDelta, MessageLabels,
+    OutgoingAttachment, OutgoingEmail, SearchQuery, SendAsAlias, ThreadLabels,
+};
+use label_rename::LabelRenameSummary;
+use mail_export::ExportSummary;
+use mail_import::ImportSummary;
+use mailbox::{MailboxContinuation, MailboxItems, MailboxPage, SearchPage};
+use mailing_lists::{ListDirectory, MailingListSummary};
+use message_cache::{CacheRead, LoadedAttachment, MessageCache, MessageError};
+use message_print::{PrintFormat, PrintableMessage};
+use newsletter_feed::{FeedList, NewsletterFeed};
+use no_reply::NoReplyWarning;
+use notes_export::{save_to_obsidian, EmailNote, NoteAttachment, NotionClient, SavedNote};
+use notify_priority::NotificationStyle;
+use offline::OfflineBundleSummary;
+use onboarding::{
+    AccountSnapshot, CachedInbox, OnboardingProgress, OnboardingStage, OnboardingState, StageStatus,
+};
+use outbound_rules::{AuditEntry, AuditLog, AuditOutcome, OutboundRuleWarning};
+use pending_actions::{Mutation, PendingAction, PendingActions};
+use people::ContactInfo;
+use poll_schedule::PollSchedule;
+use preflight::PreflightReport;
+use profile_cache::ProfileCache;
+use push::{GmailNotification, PubSubClient, PushMode, PushStatus};
 use rate_limiter::RateLimiter;
+use recent_recipients::{RecipientStore, RecipientSuggestion};
+use reply_aliases::{AliasRule, AliasRuleSet};
+use rules::{Rule, RuleScope, RuleSet};
+use scheduler::{Preset, PresetTime};
+use secrets::{SecretKind, SecretVault, StoredSecret};
 use secure_storage::DefaultSecureStorage;
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::sync::Mutex;
-use tauri::State;
+use send_limits::{split_recipients, SendDecision, SendLog, SendQuotaStatus};
+use send_receipts::{ReceiptLog, SendReceipt};
+use sender_profile::SenderProfile;
+use settings::{BackendSettings, NotesApp, ViewMode};
+use signature::Signature;
+use sla::{SlaReport, SlaTracker};
+use snooze::{SnoozeList, SnoozedEmail};
+use spam_review::{ProbableFalsePositive, SpamReview};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use storage_quota::{StorageClient, StorageStatus};
+use tauri::{Emitter, Manager, State};
 use tauri_plugin_updater::UpdaterExt;
+use thread_claims::ThreadClaim;
+use thread_mute::{MuteList, MutedThread};
+use thread_watch::{WatchList, WatchedReply, WatchedThread};
+use trash_countdown::{TrashCountdown, TrashLog};
+use unsubscribe::UnsubscribeResult;
+use widget_summary::WidgetSummary;
 
 struct AppState {
     gmail_auth: Mutex<Option<GmailAuth>>,
     auth_tokens: Mutex<Option<AuthTokens>>,
     last_check_time: Mutex<Option<String>>, // Store last email check timestamp
+    /// Gmail history id the next new-mail check starts from
+    last_history_id: Mutex<Option<String>>,
+    push_status: Mutex<PushStatus>,
+    poll_schedule: Mutex<PollSchedule>,
+    /// Cuts the sync scheduler's wait short, e.g. when a window gains focus
+    sync_wake: tokio::sync::Notify,
     rate_limiter: RateLimiter,
+    /// Time source for rate limits, schedules, snoozes and token expiry
+    clock: Arc<dyn Clock>,
+    settings: Mutex<BackendSettings>,
+    undo_history: Mutex<UndoHistory>,
+    rules: Mutex<RuleSet>,
+    widget_summary: Mutex<WidgetSummary>,
+    focus_buffer: Mutex<FocusBuffer>,
+    message_cache: Mutex<MessageCache>,
+    profile_cache: Mutex<ProfileCache>,
+    compose: Mutex<ComposeStore>,
+    send_log: Mutex<SendLog>,
+    send_receipts: Mutex<ReceiptLog>,
+    outbound_audit: Mutex<AuditLog>,
+    recent_recipients: Mutex<RecipientStore>,
+    alias_rules: Mutex<AliasRuleSet>,
+    attachment_index: Mutex<AttachmentIndex>,
+    digest_state: Mutex<DigestState>,
+    onboarding: Mutex<OnboardingState>,
+    account_snapshot: Mutex<AccountSnapshot>,
+    watched_threads: Mutex<WatchList>,
+    muted_threads: Mutex<MuteList>,
+    mailing_lists: Mutex<ListDirectory>,
+    newsletter_feeds: Mutex<FeedList>,
+    sla: Mutex<SlaTracker>,
+    pending_actions: Mutex<PendingActions>,
+    snoozed: Mutex<SnoozeList>,
+    spam_review: Mutex<SpamReview>,
+    trash_log: Mutex<TrashLog>,
+    secrets: Mutex<SecretVault>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Email {
-    id: String,
-    thread_id: String,
-    subject: String,
-    sender: String,
-    snippet: String,
-    is_read: bool,
+/// Apply a change to the widget summary and write it out for external readers
+fn update_widget_summary(state: &AppState, update: impl FnOnce(&mut WidgetSummary)) {
+    let mut summary = state.widget_summary.lock().unwrap();
+    update(&mut summary);
+    summary.touch();
+    if let Err(e) = summary.save() {
+        eprintln!("Failed to write widget summary: {}", e);
+    }
+}
+
+/// Run `f` over every store that keeps account data, locking one at a time
+fn for_each_account_store(
+    state: &AppState,
+    mut f: impl FnMut(&'static str, &mut dyn AccountScoped) -> Result<(), String>,
+) -> Result<(), String> {
+    f("settings", &mut *state.settings.lock().unwrap())?;
+    f("rules", &mut *state.rules.lock().unwrap())?;
+    f("widget_summary", &mut *state.widget_summary.lock().unwrap())?;
+    f("message_cache", &mut *state.message_cache.lock().unwrap())?;
+    f("compose", &mut *state.compose.lock().unwrap())?;
+    f("send_log", &mut *state.send_log.lock().unwrap())?;
+    f("send_receipts", &mut *state.send_receipts.lock().unwrap())?;
+    f("outbound_audit", &mut *state.outbound_audit.lock().unwrap())?;
+    f(
+        "recent_recipients",
+        &mut *state.recent_recipients.lock().unwrap(),
+    )?;
+    f("alias_rules", &mut *state.alias_rules.lock().unwrap())?;
+    f(
+        "attachment_index",
+        &mut *state.attachment_index.lock().unwrap(),
+    )?;
+    f("digest_state", &mut *state.digest_state.lock().unwrap())?;
+    f("onboarding", &mut *state.onboarding.lock().unwrap())?;
+    f(
+        "account_snapshot",
+        &mut *state.account_snapshot.lock().unwrap(),
+    )?;
+    f(
+        "watched_threads",
+        &mut *state.watched_threads.lock().unwrap(),
+    )?;
+    f("muted_threads", &mut *state.muted_threads.lock().unwrap())?;
+    f("mailing_lists", &mut *state.mailing_lists.lock().unwrap())?;
+    f(
+        "newsletter_feeds",
+        &mut *state.newsletter_feeds.lock().unwrap(),
+    )?;
+    f("sla", &mut *state.sla.lock().unwrap())?;
+    f(
+        "pending_actions",
+        &mut *state.pending_actions.lock().unwrap(),
+    )?;
+    f("snoozed", &mut *state.snoozed.lock().unwrap())?;
+    f("spam_review", &mut *state.spam_review.lock().unwrap())?;
+    f("trash_log", &mut *state.trash_log.lock().unwrap())?;
+    Ok(())
+}
+
+/// Record `account_id` as the owner of stores that don't have one yet
+fn claim_account_data(state: &AppState, account_id: &str) {
+    let result = for_each_account_store(state, |name, store| {
+        store
+            .claim(account_id)
+            .map_err(|e| format!("{}: {}", name, e))
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to tag local data with {}: {}", account_id, e);
+    }
+}
+
+/// Check a message against the sending caps
+fn check_send_limits(state: &AppState, recipients: &[String]) -> SendDecision {
+    let limits = state.settings.lock().unwrap().send_limits.clone();
+    state
+        .send_log
+        .lock()
+        .unwrap()
+        .check(recipients, state.clock.unix_now(), &limits)
+}
+
+/// Check an outgoing message against the outbound rules, logging any match.
+/// A blocking match is an Err. Warnings come back for the user to confirm,
+/// unless `ignore_warnings` is set, in which case the send goes ahead and is
+/// logged as overridden.
+fn check_outbound_rules(
+    state: &AppState,
+    subject: &str,
+    body: &str,
+    recipients: &[String],
+    ignore_warnings: bool,
+) -> Result<Option<OutboundRuleWarning>, String> {
+    let rules = state.settings.lock().unwrap().outbound_rules.rules.clone();
+    let matches = outbound_rules::check(&rules, subject, body)?;
+    if matches.is_empty() {
+        return Ok(None);
+    }
+
+    let outcome = if outbound_rules::is_blocked(&matches) {
+        AuditOutcome::Blocked
+    } else if ignore_warnings {
+        AuditOutcome::Overridden
+    } else {
+        AuditOutcome::Warned
+    };
+    {
+        let mut audit = state.outbound_audit.lock().unwrap();
+        audit.record(AuditEntry {
+            at: state.clock.unix_now(),
+            subject: subject.to_string(),
+            recipients: recipients.to_vec(),
+            outcome,
+            matches: matches.clone(),
+        });
+        if let Err(e) = audit.save() {
+            eprintln!("Failed to save outbound audit log: {}", e);
+        }
+    }
+
+    match outcome {
+        AuditOutcome::Blocked => Err(outbound_rules::block_message(&matches)),
+        AuditOutcome::Warned => Ok(Some(OutboundRuleWarning { matches })),
+        AuditOutcome::Overridden => Ok(None),
+    }
+}
+
+/// Count a sent message and keep its receipt, warning the frontend once the
+/// daily cap is close
+fn record_send(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    recipients: &[String],
+    subject: &str,
+    message_id: &str,
+) {
+    {
+        let mut receipts = state.send_receipts.lock().unwrap();
+        receipts.record(SendReceipt {
+            sent_at: state.clock.unix_now(),
+            message_id: message_id.to_string(),
+            subject: subject.to_string(),
+            recipients: recipients.to_vec(),
+        });
+        if let Err(e) = receipts.save() {
+            eprintln!("Failed to save send receipts: {}", e);
+        }
+    }
+    {
+        let mut recent = state.recent_recipients.lock().unwrap();
+        recent.record_sent(recipients, state.clock.unix_now());
+        if let Err(e) = recent.save() {
+            eprintln!("Failed to save recent recipients: {}", e);
+        }
+    }
+
+    let limits = state.settings.lock().unwrap().send_limits.clone();
+    let status = {
+        let mut log = state.send_log.lock().unwrap();
+        log.record(recipients, state.clock.unix_now());
+        if let Err(e) = log.save() {
+            eprintln!("Failed to save send log: {}", e);
+        }
+        log.status(state.clock.unix_now(), &limits)
+    };
+
+    if status.near_cap {
+        let _ = app.emit("send-quota-warning", &status);
+    }
 }
 
+/// How often edited compose sessions are pushed to Gmail drafts
+const COMPOSE_AUTOSAVE_INTERVAL_SECS: u64 = 30;
+
+/// How often snoozed messages are checked for their return time
+const SNOOZE_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// Where `report_phishing` forwards reports: the Anti-Phishing Working Group
+/// inbox, which Google and the other large mail providers draw on
+const PHISHING_REPORT_ADDRESS: &str = "reportphishing@apwg.org";
+
+/// How often the scheduler looks for rules that are due
+const RULE_SCHEDULER_INTERVAL_SECS: u64 = 5 * 60;
+
 #[tauri::command]
 async fn install_update(app: tauri::AppHandle) -> Result<String, String> {
     println!("Install update called");
@@ -84,8 +424,10 @@ async fn install_update(app: tauri::AppHandle) -> Result<String, String> {
     }
 }
 
+/// The first inbox page as threads or single messages, per the view mode;
+/// the offline snapshot and widget summary are kept from its messages
 #[tauri::command]
-async fn get_emails(state: State<'_, AppState>) -> Result<Vec<Email>, String> {
+async fn get_emails(state: State<'_, AppState>) -> Result<MailboxItems, String> {
     // Check rate limit
     state.rate_limiter.check_rate_limit("get_emails")?;
     // This will either return valid tokens or an error
@@ -102,58 +444,186 @@ async fn get_emails(state: State<'_, AppState>) -> Result<Vec<Email>, String> {
                     sender: format!("sender{}@example.com", i),
                     snippet: "This is a preview of the email content...".to_string(),
                     is_read: i % 2 == 0,
+                    is_no_reply: false,
+                    is_important: i % 4 == 1,
+                    category: None,
                 });
             }
-            return Ok(emails);
+            return Ok(MailboxItems::Messages(emails));
         }
     };
 
     // Create Gmail client and fetch real emails using the refreshed tokens
     let gmail_client = GmailClient::new(&tokens);
+    fetch_inbox(&state, &gmail_client).await
+}
 
-    // List messages (get first 20)
-    let response = gmail_client
-        .list_messages(Some(20), None, None)
-        .await
-        .map_err(|e| e.to_string())?;
+/// Load the first inbox page live and remember it for the next launch
+async fn fetch_inbox(state: &AppState, gmail_client: &GmailClient) -> Result<MailboxItems, String> {
+    let view_mode = state.settings.lock().unwrap().view_mode;
+    let mut page = mailbox::fetch_page(
+        gmail_client,
+        view_mode,
+        None,
+        None,
+        mailbox::DEFAULT_PAGE_SIZE,
+        state.clock.instant() + deadline::RESPONSE_DEADLINE,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
 
-    let message_refs: Vec<(String, String)> = response
-        .messages
-        .unwrap_or_default()
-        .into_iter()
-        .map(|m| (m.id, m.thread_id))
-        .collect();
+    // The snapshot keeps the whole page, so rows past the deadline load here
+    if !page.pending_ids.is_empty() {
+        let rest = mailbox::hydrate(gmail_client, view_mode, page.pending_ids)
+            .await
+            .map_err(|e| e.to_string())?;
+        page.items.append(rest);
+    }
 
-    let message_ids: Vec<String> = message_refs.iter().map(|(id, _)| id.clone()).collect();
+    let emails = page.items.clone().into_emails();
+    update_widget_summary(state, |summary| summary.record_inbox(&emails));
+    update_account_snapshot(state, |snapshot| {
+        snapshot.record_inbox(&emails, state.clock.unix_now())
+    });
+    record_received(state, &emails);
 
-    // Fetch full message details
-    let gmail_messages = gmail_client
-        .get_messages_batch(&message_ids)
-        .await
-        .map_err(|e| e.to_string())?;
+    Ok(page.items)
+}
 
-    // Convert to our Email format
-    let emails: Vec<Email> = gmail_messages
-        .into_iter()
-        .map(|msg| {
-            let thread_id = message_refs
-                .iter()
-                .find(|(id, _)| *id == msg.id)
-                .map(|(_, thread_id)| thread_id.clone())
-                .unwrap_or_else(|| msg.id.clone()); // Fallback to message id if not found
-
-            Email {
-                id: msg.id.clone(),
-                thread_id,
-                subject: msg.get_subject(),
-                sender: msg.get_from(),
-                snippet: msg.snippet.clone(),
-                is_read: !msg.is_unread(),
-            }
-        })
-        .collect();
+/// Integration credentials in the keyring, names only; values never leave
+/// the backend
+#[tauri::command]
+async fn list_stored_secrets(state: State<'_, AppState>) -> Result<Vec<StoredSecret>, String> {
+    Ok(state.secrets.lock().unwrap().list())
+}
+
+/// Keep an integration credential (AI provider key, webhook token, IMAP
+/// password) in the OS keyring under `name`, replacing any earlier value
+#[tauri::command]
+async fn store_secret(
+    kind: SecretKind,
+    name: String,
+    value: String,
+    state: State<'_, AppState>,
+) -> Result<StoredSecret, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("store_secret")?;
+    let mut vault = state.secrets.lock().unwrap();
+    let stored = vault.store(
+        DefaultSecureStorage::shared(),
+        kind,
+        &name,
+        &value,
+        state.clock.unix_now(),
+    )?;
+    vault.save()?;
+    Ok(stored)
+}
+
+/// Delete a stored integration credential, returning whether it was listed
+#[tauri::command]
+async fn delete_stored_secret(
+    kind: SecretKind,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    // Check rate limit
+    state
+        .rate_limiter
+        .check_rate_limit("delete_stored_secret")?;
+    let mut vault = state.secrets.lock().unwrap();
+    let removed = vault.remove(DefaultSecureStorage::shared(), kind, &name)?;
+    vault.save()?;
+    Ok(removed)
+}
+
+/// Remember who listed mail came from, for recipient suggestions
+fn record_received(state: &AppState, emails: &[Email]) {
+    let mut recent = state.recent_recipients.lock().unwrap();
+    let mut changed = false;
+    for email in emails {
+        changed |= recent.record_received(&email.id, &email.sender, state.clock.unix_now());
+    }
+    if changed {
+        if let Err(e) = recent.save() {
+            eprintln!("Failed to save recent recipients: {}", e);
+        }
+    }
+}
+
+/// Addresses starting with `prefix` that the user has recently written to or
+/// heard from, best first, for compose autocomplete that works offline and
+/// without the contacts scope
+#[tauri::command]
+async fn suggest_recipients(
+    prefix: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<RecipientSuggestion>, String> {
+    Ok(state.recent_recipients.lock().unwrap().suggest(
+        &prefix,
+        limit.unwrap_or(10),
+        state.clock.unix_now(),
+    ))
+}
+
+/// The inbox page from the last session, served without touching the network
+#[tauri::command]
+async fn get_cached_inbox(state: State<'_, AppState>) -> Result<Option<CachedInbox>, String> {
+    Ok(state.account_snapshot.lock().unwrap().cached_inbox())
+}
+
+/// Paint from the last known inbox right away ("cached-inbox-ready"), then
+/// refresh it from Gmail and report the live page with "inbox-reconciled"
+async fn serve_cached_inbox(app: tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    if let Some(cached) = state.account_snapshot.lock().unwrap().cached_inbox() {
+        let _ = app.emit("cached-inbox-ready", &cached);
+    }
+
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(_) => return, // Not signed in; the frontend loads as usual
+    };
+    if let Err(e) = state
+        .rate_limiter
+        .check_background_rate_limit("inbox_reconcile")
+    {
+        eprintln!("Skipping inbox reconcile: {}", e);
+        return;
+    }
+
+    match fetch_inbox(&state, &GmailClient::new(&tokens)).await {
+        Ok(emails) => {
+            let _ = app.emit("inbox-reconciled", &emails);
+        }
+        Err(e) => eprintln!("Failed to reconcile cached inbox: {}", e),
+    }
+}
+
+/// Which account is signed in: its address, the name it sends as and its
+/// message and thread totals
+#[tauri::command]
+async fn get_account_profile(state: State<'_, AppState>) -> Result<AccountProfile, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_account_profile")?;
+
+    let tokens = refresh_tokens_if_needed(&state).await?;
+    let gmail_client = GmailClient::new(&tokens);
+
+    let profile = load_profile(&state, &gmail_client)
+        .await
+        .map_err(|e| format!("Failed to load profile: {}", e))?;
+    // The name is a nicety; show the address alone if aliases can't be read
+    let aliases = match gmail_client.list_send_as().await {
+        Ok(aliases) => aliases,
+        Err(e) => {
+            eprintln!("Failed to load send-as aliases: {}", e);
+            Vec::new()
+        }
+    };
 
-    Ok(emails)
+    Ok(AccountProfile::new(&profile, &aliases))
 }
 
 #[tauri::command]
@@ -167,7 +637,7 @@ async fn get_inbox_stats(state: State<'_, AppState>) -> Result<(u32, u32), Strin
     // Create Gmail client and get profile using the refreshed tokens
     let gmail_client = GmailClient::new(&tokens);
 
-    match gmail_client.get_profile().await {
+    match load_profile(&state, &gmail_client).await {
         Ok(profile) => {
             let total = profile.messages_total.unwrap_or(0);
 
@@ -178,6 +648,7 @@ async fn get_inbox_stats(state: State<'_, AppState>) -> Result<(u32, u32), Strin
             {
                 Ok(unread_response) => {
                     let unread = unread_response.result_size_estimate.unwrap_or(0);
+                    update_widget_summary(&state, |summary| summary.record_counts(total, unread));
                     Ok((total, unread))
                 }
                 Err(_) => Ok((total, 0)),
@@ -212,174 +683,181 @@ async fn start_gmail_auth(state: State<'_, AppState>) -> Result<String, String>
 }
 
 #[tauri::command]
-async fn get_email_content(
-    email_id: String,
-    state: State<'_, AppState>,
-) -> Result<serde_json::Value, String> {
+async fn get_labels(state: State<'_, AppState>) -> Result<Vec<GmailLabel>, String> {
     // Check rate limit
-    state.rate_limiter.check_rate_limit("get_email_content")?;
-    // Check if we have auth tokens
-    let tokens = {
-        let tokens_guard = state.auth_tokens.lock().unwrap();
-        tokens_guard.clone()
-    };
-
-    let tokens = match tokens {
-        Some(tokens) => tokens,
-        None => return Err("Not authenticated".to_string()),
+    state.rate_limiter.check_rate_limit("get_labels")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
     };
 
-    // Create Gmail client and fetch the specific email
     let gmail_client = GmailClient::new(&tokens);
 
-    let message = gmail_client
-        .get_message(&email_id)
+    gmail_client
+        .get_labels_with_counts()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| format!("Failed to load labels: {}", e))
+}
 
-    // Create a processed response with all the fields we need
-    let processed_email = serde_json::json!({
-        "id": message.id,
-        "subject": message.get_subject(),
-        "sender": message.get_from(),
-        "date": message.get_date(),
-        "body_text": message.get_body_text(),
-        "body_html": message.get_body_html(),
-        "snippet": message.snippet,
-        "is_unread": message.is_unread()
-    });
+#[tauri::command]
+async fn create_label(name: String, state: State<'_, AppState>) -> Result<GmailLabel, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("manage_labels")?;
+    let name = validate_label_name(&name)?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
 
-    Ok(processed_email)
+    gmail_client
+        .create_label(name)
+        .await
+        .map_err(|e| format!("Failed to create label: {}", e))
 }
 
+/// Rename a label along with its nested labels, then bring the offline
+/// label list and rule queries that mention it up to date. If Gmail rejects
+/// any of the renames the ones already made are put back.
 #[tauri::command]
-async fn complete_gmail_auth(
-    callback_url: String,
+async fn rename_label(
+    label_id: String,
+    new_name: String,
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<String, String> {
-    // Parse the callback URL
-    let (code, _state) = parse_callback_url(&callback_url).map_err(|e| e.to_string())?;
-
-    // Clone the auth instance to avoid holding the lock across await
-    let gmail_auth = {
-        let auth_guard = state.gmail_auth.lock().unwrap();
-        auth_guard.as_ref().ok_or("No auth session found")?.clone()
+) -> Result<LabelRenameSummary, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("manage_labels")?;
+    let new_name = validate_label_name(&new_name)?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
     };
 
-    // Exchange code for tokens (now we don't hold the lock)
-    let tokens = gmail_auth
-        .exchange_code(&code)
+    let gmail_client = GmailClient::new(&tokens);
+
+    let labels = gmail_client
+        .list_labels()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| format!("Failed to load labels: {}", e))?;
+    let renames = label_rename::plan_renames(&labels, &label_id, new_name)?;
 
-    // Store tokens
-    *state.auth_tokens.lock().unwrap() = Some(tokens.clone());
+    let mut renamed_label = None;
+    for (done, rename) in renames.iter().enumerate() {
+        match gmail_client
+            .rename_label(&rename.label_id, &rename.new_name)
+            .await
+        {
+            Ok(label) if rename.label_id == label_id => renamed_label = Some(label),
+            Ok(_) => {}
+            Err(e) => {
+                for undo in renames[..done].iter().rev() {
+                    if let Err(e) = gmail_client
+                        .rename_label(&undo.label_id, &undo.old_name)
+                        .await
+                    {
+                        eprintln!("Failed to restore label {}: {}", undo.old_name, e);
+                    }
+                }
+                return Err(format!("Failed to rename {}: {}", rename.old_name, e));
+            }
+        }
+    }
+    let label = renamed_label.ok_or("Gmail didn't return the renamed label")?;
 
-    // Save tokens to disk for persistence
-    save_tokens(&tokens).map_err(|e| format!("Failed to save tokens: {}", e))?;
+    update_account_snapshot(&state, |snapshot| {
+        for cached in &mut snapshot.labels {
+            if let Some(rename) = renames.iter().find(|r| r.label_id == cached.id) {
+                cached.name = rename.new_name.clone();
+            }
+        }
+    });
+    let rules_updated = {
+        let mut rules = state.rules.lock().unwrap();
+        let updated = rules.rename_label_references(&renames);
+        if updated > 0 {
+            rules.save()?;
+        }
+        updated
+    };
 
-    Ok("Authentication successful!".to_string())
+    let summary = LabelRenameSummary {
+        label,
+        renamed: renames,
+        rules_updated,
+    };
+    let _ = app.emit("labels-renamed", &summary.renamed);
+    Ok(summary)
 }
 
 #[tauri::command]
-async fn logout_gmail(state: State<'_, AppState>) -> Result<String, String> {
-    *state.auth_tokens.lock().unwrap() = None;
+async fn delete_label(label_id: String, state: State<'_, AppState>) -> Result<String, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("manage_labels")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
 
-    // Delete saved tokens from secure storage
-    DefaultSecureStorage::delete_tokens_static().map_err(|e| e.to_string())?;
+    let gmail_client = GmailClient::new(&tokens);
 
-    // Also clean up legacy file if it exists
-    let token_file = get_token_file_path();
-    if token_file.exists() {
-        std::fs::remove_file(token_file).map_err(|e| e.to_string())?;
+    match gmail_client.delete_label(&label_id).await {
+        Ok(_) => Ok("Label deleted".to_string()),
+        Err(e) => Err(format!("Failed to delete label: {}", e)),
     }
-
-    Ok("Logged out successfully".to_string())
-}
-
-#[tauri::command]
-async fn get_auth_status(state: State<'_, AppState>) -> Result<bool, String> {
-    let tokens = state.auth_tokens.lock().unwrap();
-    // Check both in-memory tokens and secure storage
-    Ok(tokens.is_some() || DefaultSecureStorage::has_tokens_static())
 }
 
 #[tauri::command]
-async fn open_url(url: String) -> Result<(), String> {
-    opener::open(&url).map_err(|e| e.to_string())?;
-    Ok(())
-}
+async fn list_filters(state: State<'_, AppState>) -> Result<Vec<GmailFilter>, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_filters")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
 
-fn get_token_file_path() -> PathBuf {
-    let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
-    path.push("aisle3");
-    std::fs::create_dir_all(&path).ok();
-    path.push("tokens.json");
-    path
-}
+    let gmail_client = GmailClient::new(&tokens);
 
-fn save_tokens(tokens: &AuthTokens) -> Result<(), Box<dyn std::error::Error>> {
-    DefaultSecureStorage::save_tokens_static(tokens).map_err(|e| e.into())
+    gmail_client
+        .list_filters()
+        .await
+        .map_err(|e| format!("Failed to list filters: {}", e))
 }
 
-fn load_tokens() -> Option<AuthTokens> {
-    // First try to load from secure storage
-    if let Ok(tokens) = DefaultSecureStorage::load_tokens_static() {
-        return Some(tokens);
+/// Create a server-side filter, e.g. "from X: skip the inbox and apply a label"
+#[tauri::command]
+async fn create_filter(
+    criteria: FilterCriteria,
+    action: FilterAction,
+    state: State<'_, AppState>,
+) -> Result<GmailFilter, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("manage_filters")?;
+    // Gmail rejects these too, but only after a round trip
+    if criteria.is_empty() {
+        return Err("A filter needs at least one condition".to_string());
     }
-
-    // If no tokens in secure storage, try to migrate from old file
-    let token_file = get_token_file_path();
-    if token_file.exists() {
-        if let Ok(true) = DefaultSecureStorage::migrate_from_file_static(&token_file) {
-            // Migration successful, try loading again
-            return DefaultSecureStorage::load_tokens_static().ok();
-        }
+    if action.is_empty() {
+        return Err("A filter needs at least one action".to_string());
     }
-
-    None
-}
-
-async fn refresh_tokens_if_needed(state: &State<'_, AppState>) -> Result<AuthTokens, String> {
-    let tokens = {
-        let tokens_guard = state.auth_tokens.lock().unwrap();
-        tokens_guard.clone()
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
     };
 
-    let tokens = tokens.ok_or("Not authenticated")?;
-
-    // Try to use the current tokens first
     let gmail_client = GmailClient::new(&tokens);
 
-    // Test if tokens work by trying to get profile
-    match gmail_client.get_profile().await {
-        Ok(_) => Ok(tokens), // Tokens work fine
-        Err(_) => {
-            // Tokens expired, try to refresh
-            if let Some(refresh_token) = &tokens.refresh_token {
-                let gmail_auth = GmailAuth::new().map_err(|e| e.to_string())?;
-                let new_tokens = gmail_auth
-                    .refresh_access_token(refresh_token)
-                    .await
-                    .map_err(|e| e.to_string())?;
-
-                // Store the new tokens
-                *state.auth_tokens.lock().unwrap() = Some(new_tokens.clone());
-                save_tokens(&new_tokens).map_err(|e| format!("Failed to save tokens: {}", e))?;
-
-                Ok(new_tokens)
-            } else {
-                Err("No refresh token available".to_string())
-            }
-        }
-    }
+    gmail_client
+        .create_filter(&criteria, &action)
+        .await
+        .map_err(|e| format!("Failed to create filter: {}", e))
 }
 
 #[tauri::command]
-async fn mark_email_as_read(
-    email_id: String,
-    state: State<'_, AppState>,
-) -> Result<String, String> {
+async fn delete_filter(filter_id: String, state: State<'_, AppState>) -> Result<String, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("manage_filters")?;
     let tokens = match refresh_tokens_if_needed(&state).await {
         Ok(tokens) => tokens,
         Err(e) => return Err(format!("Authentication required: {}", e)),
@@ -387,17 +865,18 @@ async fn mark_email_as_read(
 
     let gmail_client = GmailClient::new(&tokens);
 
-    match gmail_client.mark_as_read(&email_id).await {
-        Ok(_) => Ok("Email marked as read".to_string()),
-        Err(e) => Err(format!("Failed to mark email as read: {}", e)),
+    match gmail_client.delete_filter(&filter_id).await {
+        Ok(_) => Ok("Filter deleted".to_string()),
+        Err(e) => Err(format!("Failed to delete filter: {}", e)),
     }
 }
 
 #[tauri::command]
-async fn mark_email_as_unread(
-    email_id: String,
+async fn list_forwarding_addresses(
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<Vec<ForwardingAddress>, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("forwarding")?;
     let tokens = match refresh_tokens_if_needed(&state).await {
         Ok(tokens) => tokens,
         Err(e) => return Err(format!("Authentication required: {}", e)),
@@ -405,20 +884,4749 @@ async fn mark_email_as_unread(
 
     let gmail_client = GmailClient::new(&tokens);
 
-    match gmail_client.mark_as_unread(&email_id).await {
+    gmail_client
+        .list_forwarding_addresses()
+        .await
+        .map_err(|e| format!("Failed to list forwarding addresses: {}", e))
+}
+
+#[tauri::command]
+async fn get_auto_forwarding(state: State<'_, AppState>) -> Result<AutoForwarding, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("forwarding")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    gmail_client
+        .get_auto_forwarding()
+        .await
+        .map_err(|e| format!("Failed to load forwarding settings: {}", e))
+}
+
+/// Turn auto-forwarding on (to a verified forwarding address) or off
+#[tauri::command]
+async fn set_auto_forwarding(
+    enabled: bool,
+    email_address: Option<String>,
+    disposition: Option<ForwardingDisposition>,
+    state: State<'_, AppState>,
+) -> Result<AutoForwarding, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("forwarding")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    if enabled {
+        let address = email_address
+            .as_deref()
+            .ok_or("Choose a forwarding address")?;
+        let addresses = gmail_client
+            .list_forwarding_addresses()
+            .await
+            .map_err(|e| format!("Failed to list forwarding addresses: {}", e))?;
+        match addresses
+            .iter()
+            .find(|a| a.forwarding_email.eq_ignore_ascii_case(address))
+        {
+            Some(a) if a.is_verified() => {}
+            Some(_) => return Err(format!("{} hasn't confirmed forwarding yet", address)),
+            None => return Err(format!("{} isn't a forwarding address", address)),
+        }
+    }
+
+    let forwarding = AutoForwarding {
+        enabled,
+        email_address: email_address.filter(|_| enabled),
+        disposition: disposition.filter(|_| enabled),
+    };
+    gmail_client
+        .update_auto_forwarding(&forwarding)
+        .await
+        .map_err(|e| format!("Failed to update forwarding: {}", e))
+}
+
+/// Reject empty names before spending a request on them
+fn validate_label_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Label name cannot be empty".to_string());
+    }
+    Ok(name)
+}
+
+/// Contact sidebar data for an address, aggregated from cached mail plus the
+/// People API entry when the address is a saved contact
+#[tauri::command]
+async fn get_sender_profile(
+    email: String,
+    state: State<'_, AppState>,
+) -> Result<SenderProfile, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_sender_profile")?;
+    let email = gmail_client::extract_email_address(&email);
+    let messages = state.message_cache.lock().unwrap().scan_messages();
+    let mut profile = SenderProfile::from_messages(&email, &messages);
+
+    if let Ok(tokens) = refresh_tokens_if_needed(&state).await {
+        match people::PeopleClient::new(&tokens)
+            .find_contact(&email)
+            .await
+        {
+            Ok(contact) => profile.contact = contact,
+            Err(e) => eprintln!("Contact lookup for {} failed: {}", email, e),
+        }
+    }
+
+    Ok(profile)
+}
+
+/// Saved and other contacts matching what has been typed into a recipient
+/// field, best matches first
+#[tauri::command]
+async fn search_contacts(
+    query: String,
+    limit: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ContactInfo>, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("search_contacts")?;
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    people::PeopleClient::new(&tokens)
+        .search_contacts(query, limit.unwrap_or(people::MAX_SEARCH_RESULTS))
+        .await
+        .map_err(|e| format!("Contact search failed: {}", e))
+}
+
+/// Add a downloaded attachment's text to the local index when indexing is on
+fn index_attachment_text(state: &AppState, loaded: &LoadedAttachment) {
+    let index = match loaded.index {
+        Some(index) if state.settings.lock().unwrap().index_attachment_text => index,
+        _ => return,
+    };
+    let message_id = &loaded.attachment.message_id;
+    if state
+        .attachment_index
+        .lock()
+        .unwrap()
+        .contains(message_id, index)
+    {
+        return;
+    }
+
+    let attachment = &loaded.attachment;
+    let text = match attachment_text::extract_text(
+        &attachment.mime_type,
+        &attachment.filename,
+        &loaded.data,
+    ) {
+        Some(text) => text,
+        None => return,
+    };
+
+    let mut attachment_index = state.attachment_index.lock().unwrap();
+    attachment_index.insert(IndexedAttachment {
+        message_id: message_id.clone(),
+        thread_id: loaded.thread_id.clone(),
+        index,
+        filename: attachment.filename.clone(),
+        text,
+    });
+    if let Err(e) = attachment_index.save() {
+        eprintln!("Failed to save attachment index: {}", e);
+    }
+}
+
+/// Index the text of every attachment already in the message cache, e.g.
+/// after turning indexing on or preparing an offline bundle. Returns how many
+/// attachments were added.
+#[tauri::command]
+async fn index_cached_attachments(state: State<'_, AppState>) -> Result<usize, String> {
+    if !state.settings.lock().unwrap().index_attachment_text {
+        return Err("Attachment indexing is turned off".to_string());
+    }
+
+    let messages = state.message_cache.lock().unwrap().scan_messages();
+    let mut added = 0;
+    for message in messages {
+        for (index, attachment) in message.get_attachments().into_iter().enumerate() {
+            if state
+                .attachment_index
+                .lock()
+                .unwrap()
+                .contains(&message.id, index)
+            {
+                continue;
+            }
+            let data = match state
+                .message_cache
+                .lock()
+                .unwrap()
+                .read_attachment(&message.id, index)
+            {
+                Some(data) => data,
+                None => continue,
+            };
+
+            let loaded = LoadedAttachment {
+                attachment,
+                thread_id: message.thread_id.clone(),
+                index: Some(index),
+                data,
+            };
+            index_attachment_text(&state, &loaded);
+            if state
+                .attachment_index
+                .lock()
+                .unwrap()
+                .contains(&message.id, index)
+            {
+                added += 1;
+            }
+        }
+    }
+
+    Ok(added)
+}
+
+/// Raw bytes of an attachment, served from the message cache when possible
+#[tauri::command]
+async fn get_attachment(
+    message_id: String,
+    attachment_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<u8>, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_attachment")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let loaded = message_cache::load_attachment(
+        &gmail_client,
+        &state.message_cache,
+        &message_id,
+        &attachment_id,
+    )
+    .await
+    .map_err(|e| format!("Failed to download attachment: {}", e))?;
+    index_attachment_text(&state, &loaded);
+    Ok(loaded.data)
+}
+
+/// Write an attachment to a temp file so it can be opened with the system
+/// viewer, returning the file's path
+#[tauri::command]
+async fn download_attachment(
+    message_id: String,
+    attachment_id: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_attachment")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let loaded = message_cache::load_attachment(
+        &gmail_client,
+        &state.message_cache,
+        &message_id,
+        &attachment_id,
+    )
+    .await
+    .map_err(|e| format!("Failed to download attachment: {}", e))?;
+    index_attachment_text(&state, &loaded);
+    let LoadedAttachment {
+        attachment, data, ..
+    } = loaded;
+
+    // One directory per message keeps same-named files from different mail apart
+    let dir = std::env::temp_dir()
+        .join("aisle3-attachments")
+        .join(&message_id);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create attachment folder: {}", e))?;
+
+    let path = dir.join(safe_filename(&attachment.filename));
+    std::fs::write(&path, &data).map_err(|e| format!("Failed to save attachment: {}", e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Progress payload for "attachment-save-progress" events
+#[derive(Clone, serde::Serialize)]
+struct AttachmentSaveProgress {
+    message_id: String,
+    attachment_id: String,
+    path: String,
+    bytes_written: u64,
+    /// Attachment size from the message metadata, when known
+    total_bytes: Option<u64>,
+}
+
+/// Emit save progress at most once per this many bytes
+const ATTACHMENT_PROGRESS_STEP: u64 = 256 * 1024;
+
+/// Stream an attachment to a path the user picked, decoding it as it downloads
+/// and emitting "attachment-save-progress" events. The file is written under a
+/// temporary name and only renamed into place once complete.
+#[tauri::command]
+async fn save_attachment(
+    message_id: String,
+    attachment_id: String,
+    path: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<u64, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_attachment")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let total_bytes = message_cache::load_message(&gmail_client, &state.message_cache, &message_id)
+        .await
+        .ok()
+        .and_then(|message| {
+            message
+                .get_attachments()
+                .into_iter()
+                .find(|a| a.attachment_id == attachment_id)
+                .map(|a| a.size)
+        });
+
+    let target = PathBuf::from(&path);
+    let partial = target.with_extension(match target.extension() {
+        Some(ext) => format!("{}.part", ext.to_string_lossy()),
+        None => "part".to_string(),
+    });
+    let file = std::fs::File::create(&partial)
+        .map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+    let mut writer = std::io::BufWriter::new(file);
+
+    let mut last_reported = 0;
+    let result = gmail_client
+        .stream_attachment(&message_id, &attachment_id, &mut writer, |bytes_written| {
+            if bytes_written < last_reported + ATTACHMENT_PROGRESS_STEP {
+                return;
+            }
+            last_reported = bytes_written;
+            let _ = app.emit(
+                "attachment-save-progress",
+                AttachmentSaveProgress {
+                    message_id: message_id.clone(),
+                    attachment_id: attachment_id.clone(),
+                    path: path.clone(),
+                    bytes_written,
+                    total_bytes,
+                },
+            );
+        })
+        .await;
+    drop(writer);
+
+    let bytes_written = match result {
+        Ok(bytes_written) => bytes_written,
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(format!("Failed to save attachment: {}", e));
+        }
+    };
+
+    std::fs::rename(&partial, &target)
+        .map_err(|e| format!("Failed to move attachment into place: {}", e))?;
+
+    let _ = app.emit(
+        "attachment-save-progress",
+        AttachmentSaveProgress {
+            message_id,
+            attachment_id,
+            path,
+            bytes_written,
+            total_bytes,
+        },
+    );
+    Ok(bytes_written)
+}
+
+/// Import .eml files and mbox archives from disk into the mailbox, raising
+/// "import-progress" after each message. Imported mail is archived unless
+/// `label_ids` says otherwise; `mode` "insert" skips Gmail's spam scanning.
+#[tauri::command]
+async fn import_messages(
+    paths: Vec<String>,
+    label_ids: Option<Vec<String>>,
+    mode: Option<ImportMode>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ImportSummary, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("import_messages")?;
+    if paths.is_empty() {
+        return Err("Choose at least one file to import".to_string());
+    }
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    Ok(mail_import::import_files(
+        &gmail_client,
+        &bulk::new_operation_id(),
+        &paths,
+        &label_ids.unwrap_or_default(),
+        mode.unwrap_or_default(),
+        |progress| {
+            let _ = app.emit("import-progress", progress);
+        },
+    )
+    .await)
+}
+
+/// Save a message exactly as received, headers and all, as a .eml file at a
+/// path the user picked so it can be archived or opened in another client.
+/// Returns the bytes written.
+#[tauri::command]
+async fn export_message_eml(
+    email_id: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<u64, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("export_message_eml")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let raw = gmail_client
+        .get_raw_message(&email_id)
+        .await
+        .map_err(|e| format!("Failed to download message: {}", e))?;
+    let eml = gmail_client::to_eml(&raw);
+
+    let target = PathBuf::from(&path);
+    let partial = target.with_extension("eml.part");
+    std::fs::write(&partial, &eml)
+        .map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+    std::fs::rename(&partial, &target).map_err(|e| {
+        let _ = std::fs::remove_file(&partial);
+        format!("Failed to move message into place: {}", e)
+    })?;
+    Ok(eml.len() as u64)
+}
+
+/// Save a message as a PDF or a print-ready HTML page at a path the user
+/// picked, for keeping a copy outside the mailbox. The format follows the
+/// path's extension unless given. Returns the bytes written.
+#[tauri::command]
+async fn export_message_print(
+    email_id: String,
+    path: String,
+    format: Option<PrintFormat>,
+    state: State<'_, AppState>,
+) -> Result<u64, String> {
+    // Check rate limit
+    state
+        .rate_limiter
+        .check_rate_limit("export_message_print")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let message = message_cache::load_message(&gmail_client, &state.message_cache, &email_id)
+        .await
+        .map_err(|e| format!("Failed to load message: {}", e))?;
+    let format = format.unwrap_or_else(|| PrintFormat::for_path(&path));
+    let document = PrintableMessage::from_message(&message).render(format);
+
+    let target = PathBuf::from(&path);
+    let partial = target.with_extension(format!("{}.part", format.extension()));
+    std::fs::write(&partial, &document)
+        .map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+    std::fs::rename(&partial, &target).map_err(|e| {
+        let _ = std::fs::remove_file(&partial);
+        format!("Failed to move file into place: {}", e)
+    })?;
+    Ok(document.len() as u64)
+}
+
+/// Send a cleaned-up copy of a message, with its attachments when the target
+/// wants them, to the notes target named `target` in settings: a Markdown
+/// note in an Obsidian vault or a Notion page
+#[tauri::command]
+async fn save_email_to_notes(
+    email_id: String,
+    target: String,
+    state: State<'_, AppState>,
+) -> Result<SavedNote, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("save_email_to_notes")?;
+    let notes_target = state
+        .settings
+        .lock()
+        .unwrap()
+        .notes
+        .targets
+        .iter()
+        .find(|t| t.name == target)
+        .cloned()
+        .ok_or_else(|| format!("No notes target named {}", target))?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let message = message_cache::load_message(&gmail_client, &state.message_cache, &email_id)
+        .await
+        .map_err(|e| format!("Failed to load message: {}", e))?;
+    let note = EmailNote::from_message(&message);
+
+    let mut attachments = Vec::new();
+    if notes_target.include_attachments {
+        for attachment in message.get_attachments() {
+            let loaded = message_cache::load_attachment(
+                &gmail_client,
+                &state.message_cache,
+                &email_id,
+                &attachment.attachment_id,
+            )
+            .await
+            .map_err(|e| format!("Failed to download {}: {}", attachment.filename, e))?;
+            attachments.push(NoteAttachment {
+                filename: attachment.filename,
+                mime_type: attachment.mime_type,
+                data: loaded.data,
+            });
+        }
+    }
+
+    match notes_target.app {
+        NotesApp::Obsidian => save_to_obsidian(&notes_target, &note, &attachments),
+        NotesApp::Notion => {
+            let parent_page_id = notes_target
+                .parent_page_id
+                .as_deref()
+                .ok_or_else(|| format!("Notes target {} has no parent page", target))?;
+            let token = state
+                .secrets
+                .lock()
+                .unwrap()
+                .get(
+                    DefaultSecureStorage::shared(),
+                    SecretKind::NotesToken,
+                    &target,
+                )?
+                .ok_or_else(|| format!("No Notion token stored for {}", target))?;
+            NotionClient::new(&token)
+                .save(&target, parent_page_id, &note, &attachments)
+                .await
+                .map_err(|e| format!("Failed to save to Notion: {}", e))
+        }
+    }
+}
+
+/// Export every message matching `query` to an mbox archive at `path`, for
+/// backups or moving to another client, raising "export-progress" after
+/// each message. An export that stops early resumes when called again with
+/// the same path and query.
+#[tauri::command]
+async fn export_mbox(
+    query: Option<String>,
+    path: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ExportSummary, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("export_mbox")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    mail_export::export_mbox(
+        &gmail_client,
+        &bulk::new_operation_id(),
+        query.as_deref().unwrap_or_default(),
+        &PathBuf::from(&path),
+        |progress| {
+            let _ = app.emit("export-progress", progress);
+        },
+    )
+    .await
+    .map_err(|e| format!("Failed to export mailbox: {}", e))
+}
+
+/// A filename from an email, reduced to something safe to create on disk
+fn safe_filename(filename: &str) -> String {
+    let name: String = filename
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim().trim_start_matches('.');
+    if name.is_empty() {
+        "attachment".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[tauri::command]
+async fn get_email_content(
+    email_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, MessageError> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_email_content")?;
+    // Check if we have auth tokens
+    let tokens = {
+        let tokens_guard = state.auth_tokens.lock().unwrap();
+        tokens_guard.clone()
+    };
+
+    let tokens = match tokens {
+        Some(tokens) => tokens,
+        None => return Err("Not authenticated".into()),
+    };
+
+    // Create Gmail client and fetch the specific email, served from the
+    // verified cache when possible
+    let gmail_client = GmailClient::new(&tokens);
+
+    let message = open_message(&app, &state, &gmail_client, &email_id, false).await?;
+
+    // Huge messages open as plain text; `load_full_message` brings the rest
+    let settings = state.settings.lock().unwrap().clone();
+    let mut content = if message.is_large() {
+        email_content_json(&message.text_only(), true, &settings)
+    } else {
+        email_content_json(&message, false, &settings)
+    };
+
+    let invite = calendar_invite::load_invite(&gmail_client, &message)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to read calendar invite in {}: {}", email_id, e);
+            None
+        });
+    content["calendar_invite"] = serde_json::json!(invite);
+    Ok(content)
+}
+
+/// The whole message, HTML included, for a reading pane showing the
+/// plain-text view of a large message
+#[tauri::command]
+async fn load_full_message(
+    email_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, MessageError> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_email_content")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e).into()),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+    let message = open_message(&app, &state, &gmail_client, &email_id, true).await?;
+
+    let settings = state.settings.lock().unwrap().clone();
+    Ok(email_content_json(&message, false, &settings))
+}
+
+/// Load a message for the reading pane; unless `full`, a large message not
+/// yet cached comes as headers and plain text only. One Gmail no longer has,
+/// deleted or trashed in another client, is dropped from the offline snapshot
+/// as well as the cache and announced with "message-removed".
+async fn open_message(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    gmail_client: &GmailClient,
+    message_id: &str,
+    full: bool,
+) -> Result<GmailMessage, MessageError> {
+    let loaded = if full {
+        message_cache::load_message(gmail_client, &state.message_cache, message_id).await
+    } else {
+        message_cache::load_message_for_reading(gmail_client, &state.message_cache, message_id)
+            .await
+    };
+    let error = match loaded {
+        Ok(message) => return Ok(message),
+        Err(e) => MessageError::from(e),
+    };
+    if let MessageError::NotFound { message_id } = &error {
+        update_account_snapshot(state, |snapshot| {
+            snapshot.inbox.retain(|email| email.id != *message_id)
+        });
+        let _ = app.emit("message-removed", message_id);
+    }
+    Err(error)
+}
+
+/// Reading pane payload; `truncated` marks a text-only view of a large
+/// message. In plain-text mode HTML bodies are rendered as text and no HTML
+/// is sent; otherwise `reading_css` carries the font and zoom preferences
+/// for the viewer to append to its message document. `body_segments` and
+/// `html_segments` split the bodies into content, quoted history and
+/// signature so the viewer can collapse the quotes.
+fn email_content_json(
+    message: &GmailMessage,
+    truncated: bool,
+    settings: &BackendSettings,
+) -> serde_json::Value {
+    let plain_text = settings.plain_text_mode;
+    let body_html = message.get_body_html();
+    let (body_text, body_html) = match body_html {
+        Some(html) if plain_text => (html_text::html_to_text(&html), None),
+        body_html => (message.get_body_text(), body_html),
+    };
+    serde_json::json!({
+        "id": message.id,
+        "subject": message.get_subject(),
+        "sender": message.get_from(),
+        "date": message.get_date(),
+        "body_text": body_text,
+        "body_segments": quoted_text::segment_text(&body_text),
+        "html_segments": body_html.as_deref().map(quoted_text::segment_html),
+        "body_html": body_html,
+        "snippet": message.snippet,
+        "is_unread": message.is_unread(),
+        "size_estimate": message.size_estimate,
+        "truncated": truncated,
+        "plain_text": plain_text,
+        "reading_css": reading_style::reading_css(&settings.reading)
+    })
+}
+
+#[tauri::command]
+async fn get_conversation(
+    thread_id: String,
+    state: State<'_, AppState>,
+) -> Result<Conversation, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_conversation")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let thread = gmail_client
+        .get_thread(&thread_id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let messages = thread.messages.unwrap_or_default();
+    let mut conversation = Conversation::from_messages(&thread_id, &messages);
+    conversation.attachments =
+        rollup_attachments(&gmail_client, &state.message_cache, &messages).await;
+    Ok(conversation)
+}
+
+/// Threads matching `query` as conversations. Always grouped by thread; the
+/// view mode applies to `list_mailbox` and the search commands.
+#[tauri::command]
+async fn get_conversations(
+    max_results: Option<u32>,
+    query: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Conversation>, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_conversations")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    mailbox::search_conversations(
+        &gmail_client,
+        query.as_deref(),
+        mailbox::page_size(max_results),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_mailbox(
+    query: Option<String>,
+    page_token: Option<String>,
+    max_results: Option<u32>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MailboxPage, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("list_mailbox")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    if query.as_deref().is_some_and(spam_review::is_spam_query) {
+        mark_spam_viewed(&state);
+    }
+    let view_mode = state.settings.lock().unwrap().view_mode;
+    let gmail_client = GmailClient::new(&tokens);
+
+    let mut page = mailbox::fetch_page(
+        &gmail_client,
+        view_mode,
+        query.as_deref(),
+        page_token.as_deref(),
+        mailbox::page_size(max_results),
+        state.clock.instant() + deadline::RESPONSE_DEADLINE,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Answer with what loaded in time and stream the rest
+    if !page.pending_ids.is_empty() {
+        let continuation = deadline::new_continuation_token();
+        page.continuation = Some(continuation.clone());
+        let pending_ids = std::mem::take(&mut page.pending_ids);
+        tauri::async_runtime::spawn(async move {
+            let gmail_client = GmailClient::new(&tokens);
+            let event = match mailbox::hydrate(&gmail_client, view_mode, pending_ids).await {
+                Ok(items) => MailboxContinuation {
+                    continuation,
+                    items,
+                    error: None,
+                },
+                Err(e) => MailboxContinuation {
+                    continuation,
+                    items: match view_mode {
+                        ViewMode::Conversations => MailboxItems::Conversations(Vec::new()),
+                        ViewMode::Messages => MailboxItems::Messages(Vec::new()),
+                    },
+                    error: Some(e.to_string()),
+                },
+            };
+            let _ = app.emit("mailbox-page-continued", &event);
+        });
+    }
+    Ok(page)
+}
+
+/// Search with Gmail query syntax, e.g. `from:alice has:attachment`
+#[tauri::command]
+async fn search_emails(
+    query: String,
+    max_results: Option<u32>,
+    page_token: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SearchPage, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("search_emails")?;
+    let query = query.trim();
+    if query.is_empty() {
+        return Err("Search query cannot be empty".to_string());
+    }
+    if spam_review::is_spam_query(query) {
+        mark_spam_viewed(&state);
+    }
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let view_mode = state.settings.lock().unwrap().view_mode;
+    let gmail_client = GmailClient::new(&tokens);
+
+    let mut page = mailbox::search(
+        &gmail_client,
+        view_mode,
+        Some(query),
+        page_token.as_deref(),
+        mailbox::page_size(max_results),
+    )
+    .await
+    .map_err(|e| format!("Failed to search emails: {}", e))?;
+
+    if page_token.is_none() {
+        page.attachment_matches = state.attachment_index.lock().unwrap().search(query);
+    }
+    Ok(page)
+}
+
+/// Search with a structured query instead of a hand-written query string
+#[tauri::command]
+async fn search_emails_structured(
+    search: SearchQuery,
+    max_results: Option<u32>,
+    page_token: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SearchPage, String> {
+    search_emails(search.to_query_string(), max_results, page_token, state).await
+}
+
+#[tauri::command]
+async fn get_backend_settings(state: State<'_, AppState>) -> Result<BackendSettings, String> {
+    Ok(state.settings.lock().unwrap().clone())
+}
+
+#[tauri::command]
+async fn update_backend_settings(
+    settings: BackendSettings,
+    state: State<'_, AppState>,
+) -> Result<BackendSettings, String> {
+    settings.save()?;
+    *state.settings.lock().unwrap() = settings.clone();
+    Ok(settings)
+}
+
+#[tauri::command]
+async fn complete_gmail_auth(
+    callback_url: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    // Parse the callback URL
+    let (code, _state) = parse_callback_url(&callback_url).map_err(|e| e.to_string())?;
+
+    // Clone the auth instance to avoid holding the lock across await
+    let gmail_auth = {
+        let auth_guard = state.gmail_auth.lock().unwrap();
+        auth_guard.as_ref().ok_or("No auth session found")?.clone()
+    };
+
+    // Exchange code for tokens (now we don't hold the lock)
+    let tokens = gmail_auth
+        .exchange_code(&code)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Store tokens
+    *state.auth_tokens.lock().unwrap() = Some(tokens.clone());
+
+    // Save tokens to disk for persistence
+    save_tokens(&tokens).map_err(|e| format!("Failed to save tokens: {}", e))?;
+
+    // First sign-in: fill the inbox right away, older mail follows in the background
+    tauri::async_runtime::spawn(run_onboarding(app));
+
+    Ok("Authentication successful!".to_string())
+}
+
+#[tauri::command]
+async fn logout_gmail(state: State<'_, AppState>) -> Result<String, String> {
+    stop_push_watch(&state).await;
+    *state.auth_tokens.lock().unwrap() = None;
+    state.profile_cache.lock().unwrap().clear();
+    *state.push_status.lock().unwrap() = PushStatus::default();
+
+    // The next account to sign in gets its own onboarding
+    {
+        let mut onboarding = state.onboarding.lock().unwrap();
+        *onboarding = OnboardingState {
+            running: onboarding.running,
+            ..Default::default()
+        };
+        onboarding.save()?;
+    }
+    {
+        let mut snapshot = state.account_snapshot.lock().unwrap();
+        *snapshot = AccountSnapshot::default();
+        snapshot.save()?;
+    }
+
+    // Delete saved tokens from secure storage
+    DefaultSecureStorage::delete_tokens_static().map_err(|e| e.to_string())?;
+
+    // Also clean up legacy file if it exists
+    let token_file = get_token_file_path();
+    if token_file.exists() {
+        std::fs::remove_file(token_file).map_err(|e| e.to_string())?;
+    }
+
+    Ok("Logged out successfully".to_string())
+}
+
+/// Delete everything stored for `account_id`. Stores owned by another account
+/// are left alone; when the account is the signed-in one its tokens go too.
+#[tauri::command]
+async fn remove_account(
+    account_id: String,
+    state: State<'_, AppState>,
+) -> Result<AccountPurge, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("remove_account")?;
+
+    // Onboarding is claimed by whoever signed in, so it names the token owner
+    let has_tokens =
+        state.auth_tokens.lock().unwrap().is_some() || DefaultSecureStorage::has_tokens_static();
+    let signed_in = has_tokens && state.onboarding.lock().unwrap().belongs_to(&account_id);
+    if signed_in {
+        stop_push_watch(&state).await;
+    }
+
+    let mut purged = Vec::new();
+    for_each_account_store(&state, |name, store| {
+        if store.purge(&account_id)? {
+            purged.push(name.to_string());
+        }
+        Ok(())
+    })?;
+
+    if signed_in {
+        *state.auth_tokens.lock().unwrap() = None;
+        state.profile_cache.lock().unwrap().clear();
+        *state.last_check_time.lock().unwrap() = None;
+        *state.last_history_id.lock().unwrap() = None;
+        *state.push_status.lock().unwrap() = PushStatus::default();
+        // Undo entries and held-back mail refer to the account's messages
+        *state.undo_history.lock().unwrap() = UndoHistory::default();
+        *state.focus_buffer.lock().unwrap() = FocusBuffer::default();
+
+        DefaultSecureStorage::delete_tokens_static().map_err(|e| e.to_string())?;
+        let token_file = get_token_file_path();
+        if token_file.exists() {
+            std::fs::remove_file(token_file).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(AccountPurge {
+        account_id,
+        purged,
+        signed_out: signed_in,
+    })
+}
+
+#[tauri::command]
+async fn get_auth_status(state: State<'_, AppState>) -> Result<bool, String> {
+    let tokens = state.auth_tokens.lock().unwrap();
+    // Check both in-memory tokens and secure storage
+    Ok(tokens.is_some() || DefaultSecureStorage::has_tokens_static())
+}
+
+#[tauri::command]
+async fn open_url(url: String) -> Result<(), String> {
+    opener::open(&url).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn get_token_file_path() -> PathBuf {
+    local_store::app_data_path("tokens.json")
+}
+
+fn save_tokens(tokens: &AuthTokens) -> Result<(), Box<dyn std::error::Error>> {
+    DefaultSecureStorage::save_tokens_static(tokens).map_err(|e| e.into())
+}
+
+fn load_tokens() -> Option<AuthTokens> {
+    // First try to load from secure storage
+    if let Ok(tokens) = DefaultSecureStorage::load_tokens_static() {
+        return Some(tokens);
+    }
+
+    // Older versions kept every keyring secret in one shared entry
+    if let Ok(true) = DefaultSecureStorage::migrate_legacy_entry_static() {
+        return DefaultSecureStorage::load_tokens_static().ok();
+    }
+
+    // If no tokens in secure storage, try to migrate from old file
+    let token_file = get_token_file_path();
+    if token_file.exists() {
+        if let Ok(true) = DefaultSecureStorage::migrate_from_file_static(&token_file) {
+            // Migration successful, try loading again
+            return DefaultSecureStorage::load_tokens_static().ok();
+        }
+    }
+
+    None
+}
+
+/// The signed-in account's profile, reused for a short while so account-info
+/// commands don't each fetch it
+async fn load_profile(
+    state: &AppState,
+    gmail_client: &GmailClient,
+) -> Result<GmailProfile, Box<dyn std::error::Error + Send + Sync>> {
+    profile_cache::load_profile(gmail_client, &state.profile_cache, state.clock.instant()).await
+}
+
+async fn refresh_tokens_if_needed(state: &State<'_, AppState>) -> Result<AuthTokens, String> {
+    let tokens = {
+        let tokens_guard = state.auth_tokens.lock().unwrap();
+        tokens_guard.clone()
+    };
+
+    let mut tokens = tokens.ok_or("Not authenticated")?;
+
+    let now = state.clock.unix_now();
+    let expired = match tokens.needs_refresh(now) {
+        Some(expired) => expired,
+        // Lifetime unknown: test once if tokens work by trying to get profile.
+        // A cached profile says nothing about whether the token still works.
+        None => match GmailClient::new(&tokens).get_profile().await {
+            Ok(_) => {
+                tokens.mark_checked(now);
+                *state.auth_tokens.lock().unwrap() = Some(tokens.clone());
+                false
+            }
+            Err(_) => true,
+        },
+    };
+    if !expired {
+        return Ok(tokens);
+    }
+
+    // Tokens expired, try to refresh
+    if let Some(refresh_token) = &tokens.refresh_token {
+        let gmail_auth = GmailAuth::new().map_err(|e| e.to_string())?;
+        let new_tokens = match gmail_auth.refresh_access_token(refresh_token).await {
+            Ok(new_tokens) => new_tokens,
+            Err(e) => {
+                // The keyring may hold newer tokens than the cache,
+                // e.g. after signing in again elsewhere
+                DefaultSecureStorage::invalidate_cache_static();
+                return Err(e.to_string());
+            }
+        };
+
+        // Store the new tokens
+        *state.auth_tokens.lock().unwrap() = Some(new_tokens.clone());
+        save_tokens(&new_tokens).map_err(|e| format!("Failed to save tokens: {}", e))?;
+
+        Ok(new_tokens)
+    } else {
+        Err("No refresh token available".to_string())
+    }
+}
+
+/// Apply a single-message mutation with immediate retries. If it still fails
+/// transiently it's parked in the pending actions queue instead of being lost.
+async fn run_mutation(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    gmail_client: &GmailClient,
+    mutation: Mutation,
+    message_id: &str,
+) -> Result<MessageLabels, String> {
+    match mutation.apply_with_retries(gmail_client, message_id).await {
+        Ok(labels) => {
+            match mutation {
+                Mutation::Trash => track_trash(state, message_id, true),
+                Mutation::Untrash => track_trash(state, message_id, false),
+                _ => {}
+            }
+            Ok(labels)
+        }
+        Err(e) if is_transient_error(e.as_ref()) => {
+            {
+                let mut pending = state.pending_actions.lock().unwrap();
+                pending.park(mutation, message_id, &e.to_string(), state.clock.unix_now());
+                if let Err(e) = pending.save() {
+                    eprintln!("Failed to save pending actions: {}", e);
+                }
+            }
+            let _ = app.emit("pending-actions-changed", ());
+            Err(format!("{} (saved to pending actions)", e))
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// `track_trash` for every message a bulk run moved into or out of Trash,
+/// saving the log once
+fn track_bulk_trash(state: &AppState, actions: &[BulkAction], message_ids: &[String]) {
+    let trashed = if actions.contains(&BulkAction::Trash) {
+        true
+    } else if actions.contains(&BulkAction::Untrash) {
+        false
+    } else {
+        return;
+    };
+    let mut log = state.trash_log.lock().unwrap();
+    for message_id in message_ids {
+        if trashed {
+            log.record(message_id, state.clock.unix_now());
+        } else {
+            log.forget(message_id);
+        }
+    }
+    if let Err(e) = log.save() {
+        eprintln!("Failed to save trash log: {}", e);
+    }
+}
+
+/// Note a message entering or leaving Trash so its purge date can be shown
+fn track_trash(state: &AppState, message_id: &str, trashed: bool) {
+    let mut log = state.trash_log.lock().unwrap();
+    if trashed {
+        log.record(message_id, state.clock.unix_now());
+    } else if !log.forget(message_id) {
+        return;
+    }
+    if let Err(e) = log.save() {
+        eprintln!("Failed to save trash log: {}", e);
+    }
+}
+
+#[tauri::command]
+async fn get_pending_actions(state: State<'_, AppState>) -> Result<Vec<PendingAction>, String> {
+    Ok(state.pending_actions.lock().unwrap().actions().to_vec())
+}
+
+/// Run a parked mutation again, dropping it from the queue once it succeeds
+#[tauri::command]
+async fn retry_action(
+    action_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MessageLabels, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("retry_action")?;
+    let action = state
+        .pending_actions
+        .lock()
+        .unwrap()
+        .get(&action_id)
+        .cloned()
+        .ok_or("Pending action not found")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let result = action
+        .mutation
+        .apply_with_retries(&gmail_client, &action.message_id)
+        .await;
+    {
+        let mut pending = state.pending_actions.lock().unwrap();
+        match &result {
+            Ok(_) => {
+                pending.remove(&action_id);
+            }
+            Err(e) => pending.record_failure(&action_id, &e.to_string(), state.clock.unix_now()),
+        }
+        pending.save()?;
+    }
+    let _ = app.emit("pending-actions-changed", ());
+
+    let labels = result.map_err(|e| format!("Retry failed: {}", e))?;
+    if action.mutation == Mutation::MarkRead {
+        update_widget_summary(&state, |summary| summary.record_read(&action.message_id));
+    }
+    Ok(labels)
+}
+
+#[tauri::command]
+async fn discard_action(
+    action_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let removed = {
+        let mut pending = state.pending_actions.lock().unwrap();
+        let removed = pending.remove(&action_id).is_some();
+        pending.save()?;
+        removed
+    };
+    if removed {
+        let _ = app.emit("pending-actions-changed", ());
+    }
+    Ok(removed)
+}
+
+#[tauri::command]
+async fn mark_email_as_read(
+    email_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    match run_mutation(&app, &state, &gmail_client, Mutation::MarkRead, &email_id).await {
+        Ok(_) => {
+            update_widget_summary(&state, |summary| summary.record_read(&email_id));
+            Ok("Email marked as read".to_string())
+        }
+        Err(e) => Err(format!("Failed to mark email as read: {}", e)),
+    }
+}
+
+#[tauri::command]
+async fn mark_email_as_unread(
+    email_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    match run_mutation(&app, &state, &gmail_client, Mutation::MarkUnread, &email_id).await {
         Ok(_) => Ok("Email marked as unread".to_string()),
         Err(e) => Err(format!("Failed to mark email as unread: {}", e)),
     }
 }
 
+/// Mark every message in a conversation read in one request
+#[tauri::command]
+async fn mark_thread_as_read(
+    thread_id: String,
+    state: State<'_, AppState>,
+) -> Result<ThreadLabels, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("mark_thread")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let thread = gmail_client
+        .mark_thread_as_read(&thread_id)
+        .await
+        .map_err(|e| format!("Failed to mark conversation as read: {}", e))?;
+    update_widget_summary(&state, |summary| {
+        for message in &thread.messages {
+            summary.record_read(&message.id);
+        }
+    });
+    Ok(thread)
+}
+
+/// Mark every message in a conversation unread in one request
+#[tauri::command]
+async fn mark_thread_as_unread(
+    thread_id: String,
+    state: State<'_, AppState>,
+) -> Result<ThreadLabels, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("mark_thread")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    gmail_client
+        .mark_thread_as_unread(&thread_id)
+        .await
+        .map_err(|e| format!("Failed to mark conversation as unread: {}", e))
+}
+
+#[tauri::command]
+async fn archive_email(
+    email_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("archive_email")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    match run_mutation(&app, &state, &gmail_client, Mutation::Archive, &email_id).await {
+        Ok(_) => Ok("Email archived".to_string()),
+        Err(e) => Err(format!("Failed to archive email: {}", e)),
+    }
+}
+
+#[tauri::command]
+async fn report_spam(
+    email_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MessageLabels, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("report_spam")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    run_mutation(&app, &state, &gmail_client, Mutation::ReportSpam, &email_id)
+        .await
+        .map_err(|e| format!("Failed to report spam: {}", e))
+}
+
+/// Report a targeted phishing message. Unlike `report_spam` it also drops
+/// the Important marker, and with `forward_report` it sends the original, headers
+/// and all, to the phishing report address as an attachment.
+#[tauri::command]
+async fn report_phishing(
+    email_id: String,
+    forward_report: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MessageLabels, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("report_phishing")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let labels = run_mutation(
+        &app,
+        &state,
+        &gmail_client,
+        Mutation::ReportPhishing,
+        &email_id,
+    )
+    .await
+    .map_err(|e| format!("Failed to report phishing: {}", e))?;
+
+    if forward_report.unwrap_or(false) {
+        forward_phishing_report(&app, &state, &gmail_client, &email_id)
+            .await
+            .map_err(|e| format!("Moved to Spam, but the report wasn't sent: {}", e))?;
+    }
+    Ok(labels)
+}
+
+/// Send the raw message to `PHISHING_REPORT_ADDRESS`
+async fn forward_phishing_report(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    gmail_client: &GmailClient,
+    email_id: &str,
+) -> Result<(), String> {
+    let recipients = vec![PHISHING_REPORT_ADDRESS.to_string()];
+    if let SendDecision::Deferred { until, reason } = check_send_limits(state, &recipients) {
+        return Err(format!(
+            "{}; try again in {} minutes",
+            reason,
+            until.saturating_sub(state.clock.unix_now()).div_ceil(60)
+        ));
+    }
+
+    let raw = gmail_client
+        .get_raw_message(email_id)
+        .await
+        .map_err(|e| format!("Failed to download the original: {}", e))?;
+    check_attachment_size(raw.len() as u64)?;
+
+    let report = OutgoingEmail {
+        to: PHISHING_REPORT_ADDRESS.to_string(),
+        subject: "Phishing report".to_string(),
+        body: "The attached message was received as a phishing attempt.".to_string(),
+        attachments: vec![OutgoingAttachment {
+            filename: "phishing.eml".to_string(),
+            mime_type: "message/rfc822".to_string(),
+            data: raw,
+        }],
+        ..Default::default()
+    };
+    let message_id = gmail_client
+        .send_message(&report, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    record_send(app, state, &recipients, &report.subject, &message_id);
+    Ok(())
+}
+
+#[tauri::command]
+async fn mark_not_spam(
+    email_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MessageLabels, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("mark_not_spam")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let labels = run_mutation(
+        &app,
+        &state,
+        &gmail_client,
+        Mutation::MarkNotSpam,
+        &email_id,
+    )
+    .await
+    .map_err(|e| format!("Failed to mark email as not spam: {}", e))?;
+
+    let mut review = state.spam_review.lock().unwrap();
+    if review.remove(&email_id) {
+        if let Err(e) = review.save() {
+            eprintln!("Failed to save spam review: {}", e);
+        }
+    }
+    Ok(labels)
+}
+
+#[tauri::command]
+async fn mark_as_important(
+    email_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MessageLabels, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("mark_important")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    run_mutation(
+        &app,
+        &state,
+        &gmail_client,
+        Mutation::MarkImportant,
+        &email_id,
+    )
+    .await
+    .map_err(|e| format!("Failed to mark email as important: {}", e))
+}
+
+#[tauri::command]
+async fn mark_not_important(
+    email_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MessageLabels, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("mark_important")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    run_mutation(
+        &app,
+        &state,
+        &gmail_client,
+        Mutation::MarkNotImportant,
+        &email_id,
+    )
+    .await
+    .map_err(|e| format!("Failed to mark email as not important: {}", e))
+}
+
+/// Unsubscribe from the list that sent `email_id`: the one-click POST when
+/// the list supports it, otherwise the unsubscribe message its mailto link
+/// asks for. A bare web link is handed back for the frontend to open.
+#[tauri::command]
+async fn unsubscribe_from_sender(
+    email_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<UnsubscribeResult, String> {
+    // Check rate limit
+    state
+        .rate_limiter
+        .check_rate_limit("unsubscribe_from_sender")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let message = message_cache::load_message(&gmail_client, &state.message_cache, &email_id)
+        .await
+        .map_err(|e| format!("Failed to get email: {}", e))?;
+    let options = message
+        .get_unsubscribe_options()
+        .ok_or("This sender doesn't offer a way to unsubscribe")?;
+
+    if let Some(url) = options.url.clone().filter(|_| options.one_click) {
+        unsubscribe::one_click(&url)
+            .await
+            .map_err(|e| format!("Failed to unsubscribe: {}", e))?;
+        return Ok(UnsubscribeResult::OneClick { url });
+    }
+
+    let Some(mailto) = options.mailto else {
+        let url = options.url.unwrap_or_default();
+        return Ok(UnsubscribeResult::OpenLink { url });
+    };
+    let recipients = split_recipients(&mailto.to);
+    if let SendDecision::Deferred { until, reason } = check_send_limits(&state, &recipients) {
+        return Err(format!(
+            "{}; try again in {} minutes",
+            reason,
+            until.saturating_sub(state.clock.unix_now()).div_ceil(60)
+        ));
+    }
+
+    let request = OutgoingEmail {
+        to: mailto.to.clone(),
+        subject: mailto.subject,
+        body: mailto.body,
+        ..Default::default()
+    };
+    match gmail_client.send_message(&request, None).await {
+        Ok(message_id) => {
+            record_send(&app, &state, &recipients, &request.subject, &message_id);
+            Ok(UnsubscribeResult::Mailto {
+                to: mailto.to,
+                message_id,
+            })
+        }
+        Err(e) => Err(format!("Failed to send unsubscribe request: {}", e)),
+    }
+}
+
+/// One inbox tab (Primary, Social, Promotions, ...), newest first
+#[tauri::command]
+async fn get_emails_by_category(
+    category: Category,
+    max_results: Option<u32>,
+    page_token: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SearchPage, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("list_mailbox")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let view_mode = state.settings.lock().unwrap().view_mode;
+    let gmail_client = GmailClient::new(&tokens);
+
+    mailbox::search(
+        &gmail_client,
+        view_mode,
+        Some(category.inbox_query()),
+        page_token.as_deref(),
+        mailbox::page_size(max_results),
+    )
+    .await
+    .map_err(|e| format!("Failed to load category: {}", e))
+}
+
+/// Inbox mail Gmail ranks as important, newest first, for a priority inbox
+#[tauri::command]
+async fn get_important(
+    max_results: Option<u32>,
+    page_token: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SearchPage, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("list_mailbox")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let view_mode = state.settings.lock().unwrap().view_mode;
+    let gmail_client = GmailClient::new(&tokens);
+
+    mailbox::search(
+        &gmail_client,
+        view_mode,
+        Some(mailbox::IMPORTANT_QUERY),
+        page_token.as_deref(),
+        mailbox::page_size(max_results),
+    )
+    .await
+    .map_err(|e| format!("Failed to load important mail: {}", e))
+}
+
+#[tauri::command]
+async fn trash_email(
+    email_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MessageLabels, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("trash_email")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    run_mutation(&app, &state, &gmail_client, Mutation::Trash, &email_id)
+        .await
+        .map_err(|e| format!("Failed to move email to trash: {}", e))
+}
+
+#[tauri::command]
+async fn untrash_email(
+    email_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MessageLabels, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("untrash_email")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    run_mutation(&app, &state, &gmail_client, Mutation::Untrash, &email_id)
+        .await
+        .map_err(|e| format!("Failed to restore email from trash: {}", e))
+}
+
+#[tauri::command]
+async fn delete_email_permanently(
+    email_id: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    // Check rate limit
+    state
+        .rate_limiter
+        .check_rate_limit("delete_email_permanently")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    match gmail_client.delete_message_permanently(&email_id).await {
+        Ok(_) => {
+            track_trash(&state, &email_id, false);
+            Ok("Email permanently deleted".to_string())
+        }
+        Err(e) => Err(format!("Failed to delete email: {}", e)),
+    }
+}
+
+/// Days until Gmail purges each trashed message, for the Trash view to warn
+/// about. Messages trashed before the app saw it happen are estimated from
+/// their own date, which errs towards warning early.
+#[tauri::command]
+async fn get_trash_countdowns(
+    email_ids: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<TrashCountdown>, String> {
+    let now = state.clock.unix_now();
+    let mut unseen = Vec::new();
+    let mut countdowns = Vec::new();
+    {
+        let log = state.trash_log.lock().unwrap();
+        for email_id in &email_ids {
+            match log.countdown(email_id, now) {
+                Some(countdown) => countdowns.push(countdown),
+                None => unseen.push(email_id.clone()),
+            }
+        }
+    }
+    if unseen.is_empty() {
+        return Ok(countdowns);
+    }
+
+    // Check rate limit
+    state
+        .rate_limiter
+        .check_rate_limit("get_trash_countdowns")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let messages = gmail_client
+        .get_messages_metadata_batch(&unseen)
+        .await
+        .map_err(|e| format!("Failed to load trashed messages: {}", e))?;
+    countdowns.extend(
+        messages
+            .iter()
+            .map(|message| TrashCountdown::estimate(message, now)),
+    );
+
+    // Answer in the order asked
+    countdowns.sort_by_key(|c| email_ids.iter().position(|id| *id == c.message_id));
+    Ok(countdowns)
+}
+
+#[tauri::command]
+async fn empty_trash(
+    dry_run: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<BulkSummary, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("empty_trash")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+    let operation_id = bulk::new_operation_id();
+
+    if dry_run.unwrap_or(false) {
+        return bulk::preview_empty_trash(&gmail_client, &operation_id)
+            .await
+            .map_err(|e| format!("Failed to count trash: {}", e));
+    }
+
+    Ok(bulk::empty_trash(&gmail_client, &operation_id, |progress| {
+        let _ = app.emit("bulk-progress", progress);
+    })
+    .await)
+}
+
+#[tauri::command]
+async fn send_reply(
+    original_email_id: String,
+    reply_body: String,
+    attachments: Option<Vec<String>>,
+    ignore_no_reply: Option<bool>,
+    ignore_outbound_warnings: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("send_reply")?;
+    let attachments = compose::read_attachments(&attachments.unwrap_or_default())?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    // Get the original email to extract reply information
+    let original_email = gmail_client
+        .get_message(&original_email_id)
+        .await
+        .map_err(|e| format!("Failed to get original email: {}", e))?;
+
+    if !ignore_no_reply.unwrap_or(false) {
+        if let Some(warning) = no_reply::check_reply(&original_email) {
+            let mut message = format!("{} does not accept replies", warning.address);
+            if !warning.suggested_contacts.is_empty() {
+                message.push_str(&format!(
+                    "; try {} instead",
+                    warning.suggested_contacts.join(" or ")
+                ));
+            }
+            return Err(message);
+        }
+    }
+
+    // Extract sender email from "From" header
+    let to_email = original_email.get_from_address();
+
+    let recipients = vec![to_email.clone()];
+    if let SendDecision::Deferred { until, reason } = check_send_limits(&state, &recipients) {
+        return Err(format!(
+            "{}; try again in {} minutes",
+            reason,
+            until.saturating_sub(state.clock.unix_now()).div_ceil(60)
+        ));
+    }
+
+    // Create reply subject and threading headers
+    let reply_subject = compose::reply_subject(&original_email.get_subject());
+    let reply_context = ReplyContext::from_message(&original_email);
+
+    let mut reply = OutgoingEmail {
+        from: reply_alias_from(&state, &gmail_client, &original_email).await,
+        to: to_email,
+        subject: reply_subject,
+        body: reply_body,
+        in_reply_to: reply_context.in_reply_to,
+        references: reply_context.references,
+        attachments,
+        ..Default::default()
+    };
+    if let Some(warning) = check_outbound_rules(
+        &state,
+        &reply.subject,
+        &reply.body,
+        &recipients,
+        ignore_outbound_warnings.unwrap_or(false),
+    )? {
+        return Err(warning.message());
+    }
+    apply_signature(&state, &gmail_client, &mut reply).await;
+    scan_outgoing(&state, &reply).await?;
+
+    // Send the reply
+    match gmail_client
+        .send_message(&reply, Some(&reply_context.thread_id))
+        .await
+    {
+        Ok(message_id) => {
+            record_send(&app, &state, &recipients, &reply.subject, &message_id);
+            Ok(format!(
+                "Reply sent successfully! Message ID: {}",
+                message_id
+            ))
+        }
+        Err(e) => Err(format!("Failed to send reply: {}", e)),
+    }
+}
+
+/// Forward a message to new recipients as a new thread, with the note from
+/// `options` above the original and, unless `include_attachments` is false,
+/// its attachments
+#[tauri::command]
+async fn forward_email(
+    original_email_id: String,
+    to: String,
+    cc: Option<String>,
+    options: Option<ForwardOptions>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let options = options.unwrap_or_default();
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("send_reply")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let original =
+        message_cache::load_message(&gmail_client, &state.message_cache, &original_email_id)
+            .await
+            .map_err(|e| format!("Failed to get original email: {}", e))?;
+
+    let mut recipients = split_recipients(&to);
+    recipients.extend(cc.as_deref().map(split_recipients).unwrap_or_default());
+    if recipients.is_empty() {
+        return Err("Add at least one recipient to forward to".to_string());
+    }
+    if let SendDecision::Deferred { until, reason } = check_send_limits(&state, &recipients) {
+        return Err(format!(
+            "{}; try again in {} minutes",
+            reason,
+            until.saturating_sub(state.clock.unix_now()).div_ceil(60)
+        ));
+    }
+
+    // Check the size up front so nothing is downloaded for a forward that can't go out
+    let mut attachments = Vec::new();
+    if options.include_attachments.unwrap_or(true) {
+        let originals = original.get_attachments();
+        check_attachment_size(originals.iter().map(|a| a.size).sum())?;
+        for attachment in originals {
+            let loaded = message_cache::load_attachment(
+                &gmail_client,
+                &state.message_cache,
+                &original.id,
+                &attachment.attachment_id,
+            )
+            .await
+            .map_err(|e| format!("Failed to load {}: {}", attachment.filename, e))?;
+            attachments.push(OutgoingAttachment {
+                filename: attachment.filename,
+                mime_type: attachment.mime_type,
+                data: loaded.data,
+            });
+        }
+    }
+
+    let mut forward = OutgoingEmail {
+        from: reply_alias_from(&state, &gmail_client, &original).await,
+        to,
+        cc: cc.filter(|cc| !cc.trim().is_empty()),
+        subject: compose::forward_subject(&original.get_subject()),
+        body: compose::forward_body(options.note.as_deref().unwrap_or(""), &original),
+        attachments,
+        ..Default::default()
+    };
+    if let Some(warning) = check_outbound_rules(
+        &state,
+        &forward.subject,
+        &forward.body,
+        &recipients,
+        options.ignore_outbound_warnings.unwrap_or(false),
+    )? {
+        return Err(warning.message());
+    }
+    apply_signature(&state, &gmail_client, &mut forward).await;
+    scan_outgoing(&state, &forward).await?;
+
+    match gmail_client.send_message(&forward, None).await {
+        Ok(message_id) => {
+            record_send(&app, &state, &recipients, &forward.subject, &message_id);
+            Ok(message_id)
+        }
+        Err(e) => Err(format!("Failed to forward email: {}", e)),
+    }
+}
+
+/// Run the configured attachment scanner over `email`; Err blocks the send
+async fn scan_outgoing(state: &AppState, email: &OutgoingEmail) -> Result<(), String> {
+    let settings = state.settings.lock().unwrap().attachment_scan.clone();
+    attachment_scan::check_attachments(&settings, &email.attachments).await
+}
+
+/// Append the sender's Gmail signature when `append_signature` is on. Uses the
+/// live sendAs settings, falling back to the last synced copy if they can't be read.
+async fn apply_signature(state: &AppState, gmail_client: &GmailClient, email: &mut OutgoingEmail) {
+    if !state.settings.lock().unwrap().append_signature {
+        return;
+    }
+
+    let aliases = match gmail_client.list_send_as().await {
+        Ok(aliases) => {
+            update_account_snapshot(state, |s| s.send_as = aliases.clone());
+            aliases
+        }
+        Err(e) => {
+            eprintln!("Failed to load signatures, using the last sync: {}", e);
+            state.account_snapshot.lock().unwrap().send_as.clone()
+        }
+    };
+
+    if let Some(signature) = Signature::for_sender(&aliases, email.from.as_deref()) {
+        email.body = signature.append_to(&email.body);
+    }
+}
+
+/// Answer the calendar invitation in a message with an iTIP reply to the
+/// organizer, threaded with the invitation
+#[tauri::command]
+async fn rsvp_invite(
+    email_id: String,
+    response: RsvpResponse,
+    comment: Option<String>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("rsvp_invite")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let original = message_cache::load_message(&gmail_client, &state.message_cache, &email_id)
+        .await
+        .map_err(|e| format!("Failed to get invitation: {}", e))?;
+    let invite = calendar_invite::load_invite(&gmail_client, &original)
+        .await
+        .map_err(|e| format!("Failed to read invitation: {}", e))?
+        .ok_or("This message has no calendar invitation")?;
+    let organizer = match &invite.organizer {
+        Some(organizer) if invite.accepts_replies() => organizer.email.clone(),
+        _ => return Err("This invitation doesn't take replies".to_string()),
+    };
+
+    // Answer as whichever of the account's addresses was invited
+    let aliases = gmail_client
+        .list_send_as()
+        .await
+        .map_err(|e| format!("Failed to load send-as addresses: {}", e))?;
+    let addresses: Vec<String> = aliases.iter().map(|a| a.send_as_email.clone()).collect();
+    let (attendee, from) = match invite.find_attendee(&addresses) {
+        Some(attendee) => {
+            let alias = aliases
+                .iter()
+                .find(|a| a.send_as_email.eq_ignore_ascii_case(&attendee.email));
+            let from = alias
+                .filter(|a| !a.is_primary.unwrap_or(false))
+                .map(|a| a.send_as_email.clone());
+            (attendee.clone(), from)
+        }
+        None => {
+            let primary = aliases
+                .iter()
+                .find(|a| a.is_primary.unwrap_or(false))
+                .ok_or("No primary address to reply from")?;
+            let attendee = calendar_invite::Attendee {
+                email: primary.send_as_email.clone(),
+                name: primary.display_name.clone().filter(|n| !n.is_empty()),
+                status: None,
+            };
+            (attendee, None)
+        }
+    };
+
+    let recipients = vec![organizer.clone()];
+    if let SendDecision::Deferred { until, reason } = check_send_limits(&state, &recipients) {
+        return Err(format!(
+            "{}; try again in {} minutes",
+            reason,
+            until.saturating_sub(state.clock.unix_now()).div_ceil(60)
+        ));
+    }
+
+    let (subject, body, attachment) =
+        invite.reply_parts(&attendee, response, comment.as_deref(), state.clock.now());
+    let reply_context = ReplyContext::from_message(&original);
+    let reply = OutgoingEmail {
+        from,
+        to: organizer,
+        subject,
+        body,
+        in_reply_to: reply_context.in_reply_to,
+        references: reply_context.references,
+        attachments: vec![attachment],
+        ..Default::default()
+    };
+
+    let message_id = gmail_client
+        .send_message(&reply, Some(&reply_context.thread_id))
+        .await
+        .map_err(|e| format!("Failed to send reply: {}", e))?;
+    record_send(&app, &state, &recipients, &reply.subject, &message_id);
+    Ok(message_id)
+}
+
+/// The From header an alias rule picks for replying to `original`. Falls back
+/// to the primary address when no rule matches or the alias can't be used.
+async fn reply_alias_from(
+    state: &AppState,
+    gmail_client: &GmailClient,
+    original: &GmailMessage,
+) -> Option<String> {
+    let rule = state.alias_rules.lock().unwrap().pick(original).cloned()?;
+
+    let aliases = match gmail_client.list_send_as().await {
+        Ok(aliases) => aliases,
+        Err(e) => {
+            eprintln!("Failed to load send-as aliases: {}", e);
+            return None;
+        }
+    };
+
+    let from = reply_aliases::resolve_from(&rule, &aliases);
+    if from.is_none() {
+        eprintln!(
+            "Alias rule '{}' names {}, which is not a verified send-as address",
+            rule.name, rule.send_as
+        );
+    }
+    from
+}
+
+/// Persist compose sessions locally after a change
+fn save_compose_store(store: &ComposeStore) {
+    if let Err(e) = store.save() {
+        eprintln!("Failed to save compose sessions: {}", e);
+    }
+}
+
+#[tauri::command]
+async fn create_compose_session(
+    reply_to_email_id: Option<String>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ComposeSession, String> {
+    let original = match reply_to_email_id {
+        Some(email_id) => {
+            let tokens = match refresh_tokens_if_needed(&state).await {
+                Ok(tokens) => tokens,
+                Err(e) => return Err(format!("Authentication required: {}", e)),
+            };
+            let gmail_client = GmailClient::new(&tokens);
+            let original =
+                message_cache::load_message(&gmail_client, &state.message_cache, &email_id)
+                    .await
+                    .map_err(|e| format!("Failed to get original email: {}", e))?;
+            let thread = fetch_thread_messages(&gmail_client, &original.thread_id).await?;
+            let from = reply_alias_from(&state, &gmail_client, &original).await;
+            Some((original, thread, from))
+        }
+        None => None,
+    };
+
+    let session = {
+        let mut store = state.compose.lock().unwrap();
+        let id = store.new_session_id();
+        let session = match &original {
+            Some((original, thread, from)) => {
+                let mut session = ComposeSession::reply_to(id, original);
+                session.from = from.clone();
+                // Everything already in the thread counts as read before replying
+                if let Some(context) = session.reply_context.as_mut() {
+                    context.mark_thread_seen(thread);
+                }
+                session
+            }
+            None => ComposeSession {
+                id,
+                ..Default::default()
+            },
+        };
+        store.insert(session.clone());
+        save_compose_store(&store);
+        session
+    };
+
+    let _ = app.emit("compose-session-updated", &session);
+    Ok(session)
+}
+
+#[tauri::command]
+async fn get_compose_session(
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<ComposeSession, String> {
+    state
+        .compose
+        .lock()
+        .unwrap()
+        .get(&session_id)
+        .cloned()
+        .ok_or_else(|| format!("Compose session not found: {}", session_id))
+}
+
+/// Sessions still open, e.g. to restore compose windows after a crash
+#[tauri::command]
+async fn list_compose_sessions(state: State<'_, AppState>) -> Result<Vec<ComposeSession>, String> {
+    Ok(state.compose.lock().unwrap().sessions().to_vec())
+}
+
+#[tauri::command]
+async fn update_compose_session(
+    session_id: String,
+    update: ComposeUpdate,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ComposeSession, String> {
+    if let Some(attachments) = &update.attachments {
+        check_attachment_size(attachments.iter().map(|a| a.size).sum())?;
+    }
+
+    let session = {
+        let mut store = state.compose.lock().unwrap();
+        let session = store
+            .update(&session_id, update)
+            .cloned()
+            .ok_or_else(|| format!("Compose session not found: {}", session_id))?;
+        save_compose_store(&store);
+        session
+    };
+
+    // Other windows showing this session pick up the change from the event
+    let _ = app.emit("compose-session-updated", &session);
+    Ok(session)
+}
+
+/// Attach a file from disk to a compose session
+#[tauri::command]
+async fn add_compose_attachment(
+    session_id: String,
+    path: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ComposeSession, String> {
+    let attachment = ComposeAttachment::from_path(&path)?;
+    let mut attachments = get_compose_session(session_id.clone(), state.clone())
+        .await?
+        .attachments;
+    attachments.push(attachment);
+
+    let update = ComposeUpdate {
+        attachments: Some(attachments),
+        ..Default::default()
+    };
+    update_compose_session(session_id, update, app, state).await
+}
+
+/// Thread messages with metadata only, enough to compare against a reply context
+async fn fetch_thread_messages(
+    gmail_client: &GmailClient,
+    thread_id: &str,
+) -> Result<Vec<GmailMessage>, String> {
+    let threads = gmail_client
+        .get_threads_batch(&[thread_id.to_string()])
+        .await
+        .map_err(|e| format!("Failed to load thread: {}", e))?;
+    Ok(threads
+        .into_iter()
+        .next()
+        .and_then(|t| t.messages)
+        .unwrap_or_default())
+}
+
+/// Messages that arrived in the reply's thread since the session started
+async fn find_newer_replies(
+    gmail_client: &GmailClient,
+    session: &ComposeSession,
+) -> Result<Option<StaleReplyWarning>, String> {
+    let context = match &session.reply_context {
+        Some(context) => context,
+        None => return Ok(None),
+    };
+
+    let thread = fetch_thread_messages(gmail_client, &context.thread_id).await?;
+    let new_messages: Vec<Email> = context
+        .newer_messages(&thread)
+        .into_iter()
+        .map(Email::from)
+        .collect();
+
+    Ok((!new_messages.is_empty()).then(|| StaleReplyWarning {
+        session_id: session.id.clone(),
+        new_messages,
+    }))
+}
+
+#[tauri::command]
+async fn check_reply_freshness(
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<StaleReplyWarning>, String> {
+    let session = get_compose_session(session_id, state.clone()).await?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    find_newer_replies(&GmailClient::new(&tokens), &session).await
+}
+
+/// A warning when the session is addressed to a no-reply address. Suggestions
+/// come from the message being replied to, if there is one.
+async fn find_no_reply_recipient(
+    gmail_client: &GmailClient,
+    state: &AppState,
+    session: &ComposeSession,
+) -> Option<NoReplyWarning> {
+    let address = session
+        .recipients()
+        .into_iter()
+        .find(|r| no_reply::is_no_reply_address(r))?;
+
+    let suggested_contacts = match &session.reply_context {
+        Some(context) => message_cache::load_message(
+            gmail_client,
+            &state.message_cache,
+            &context.original_message_id,
+        )
+        .await
+        .map(|original| no_reply::suggested_contacts(&original))
+        .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    Some(NoReplyWarning {
+        address,
+        suggested_contacts,
+    })
+}
+
+/// Send a compose session. Replies first check their thread: if someone else
+/// replied meanwhile, nothing is sent and the new messages come back for review
+/// unless `ignore_newer_messages` is set. Mail to a no-reply address comes back
+/// as a warning the same way unless `ignore_no_reply` is set, and so do
+/// outbound rule warnings unless `ignore_outbound_warnings` is set.
+#[tauri::command]
+async fn send_compose_session(
+    session_id: String,
+    ignore_newer_messages: Option<bool>,
+    ignore_no_reply: Option<bool>,
+    ignore_outbound_warnings: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<SendComposeResult, String> {
+    // Check rate limit
+    state
+        .rate_limiter
+        .check_rate_limit("send_compose_session")?;
+    let session = get_compose_session(session_id, state.clone()).await?;
+    if session.to.is_empty() {
+        return Err("Add at least one recipient before sending".to_string());
+    }
+    check_attachment_size(session.attachment_bytes())?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    if let Some(warning) = check_outbound_rules(
+        &state,
+        &session.subject,
+        &session.body,
+        &session.recipients(),
+        ignore_outbound_warnings.unwrap_or(false),
+    )? {
+        return Ok(SendComposeResult::OutboundRules(warning));
+    }
+
+    if !ignore_no_reply.unwrap_or(false) {
+        if let Some(warning) = find_no_reply_recipient(&gmail_client, &state, &session).await {
+            return Ok(SendComposeResult::NoReply(warning));
+        }
+    }
+
+    if !ignore_newer_messages.unwrap_or(false) {
+        if let Some(warning) = find_newer_replies(&gmail_client, &session).await? {
+            return Ok(SendComposeResult::NewerMessages(warning));
+        }
+    }
+
+    if let SendDecision::Deferred { until, reason } =
+        check_send_limits(&state, &session.recipients())
+    {
+        let mut store = state.compose.lock().unwrap();
+        store.defer(&session.id, until);
+        save_compose_store(&store);
+        return Ok(SendComposeResult::Deferred {
+            send_at: until,
+            reason,
+        });
+    }
+
+    let message_id = deliver_compose_session(&app, &state, &gmail_client, &session).await?;
+    Ok(SendComposeResult::Sent { message_id })
+}
+
+/// Send a session, count it against the caps and close it
+async fn deliver_compose_session(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    gmail_client: &GmailClient,
+    session: &ComposeSession,
+) -> Result<String, String> {
+    let mut email = session.to_outgoing()?;
+    apply_signature(state, gmail_client, &mut email).await;
+    scan_outgoing(state, &email).await?;
+    let message_id = gmail_client
+        .send_message(&email, session.thread_id())
+        .await
+        .map_err(|e| format!("Failed to send email: {}", e))?;
+    record_send(
+        app,
+        state,
+        &session.recipients(),
+        &email.subject,
+        &message_id,
+    );
+
+    {
+        let mut store = state.compose.lock().unwrap();
+        store.remove(&session.id);
+        save_compose_store(&store);
+    }
+    if let Some(draft_id) = &session.draft_id {
+        if let Err(e) = gmail_client.delete_draft(draft_id).await {
+            eprintln!("Failed to delete sent draft {}: {}", draft_id, e);
+        }
+    }
+
+    let _ = app.emit("compose-session-discarded", &session.id);
+    Ok(message_id)
+}
+
+/// Send queued sessions whose deferral has run out. A session that still
+/// doesn't fit under the caps is pushed back again.
+async fn send_deferred_sessions(app: &tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    let due = state
+        .compose
+        .lock()
+        .unwrap()
+        .due_deferred(state.clock.unix_now());
+    if due.is_empty() {
+        return;
+    }
+
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(_) => return, // Not signed in; try again on the next tick
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    for session in due {
+        if let SendDecision::Deferred { until, .. } =
+            check_send_limits(&state, &session.recipients())
+        {
+            let mut store = state.compose.lock().unwrap();
+            store.defer(&session.id, until);
+            save_compose_store(&store);
+            continue;
+        }
+
+        match deliver_compose_session(app, &state, &gmail_client, &session).await {
+            Ok(message_id) => {
+                let _ = app.emit(
+                    "deferred-send-completed",
+                    serde_json::json!({ "session_id": session.id, "message_id": message_id }),
+                );
+            }
+            Err(e) => eprintln!("Failed to send deferred session {}: {}", session.id, e),
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_send_quota(state: State<'_, AppState>) -> Result<SendQuotaStatus, String> {
+    let limits = state.settings.lock().unwrap().send_limits.clone();
+    Ok(state
+        .send_log
+        .lock()
+        .unwrap()
+        .status(state.clock.unix_now(), &limits))
+}
+
+/// Outgoing messages that matched an outbound rule, newest last
+#[tauri::command]
+async fn get_outbound_audit_log(state: State<'_, AppState>) -> Result<Vec<AuditEntry>, String> {
+    Ok(state.outbound_audit.lock().unwrap().entries().to_vec())
+}
+
+/// Write every receipt in the send log to `path` as CSV, returning the row count
+#[tauri::command]
+async fn export_send_log(path: String, state: State<'_, AppState>) -> Result<usize, String> {
+    state
+        .send_receipts
+        .lock()
+        .unwrap()
+        .export_csv(&PathBuf::from(path))
+}
+
+/// Actions for the command palette in `context`, built from the account's
+/// labels, rules and settings so the palette never offers something the
+/// backend can't do
+#[tauri::command]
+async fn get_available_actions(
+    context: ActionContext,
+    state: State<'_, AppState>,
+) -> Result<Vec<PaletteAction>, String> {
+    // Check rate limit
+    state
+        .rate_limiter
+        .check_rate_limit("get_available_actions")?;
+
+    // Signed out still gets local actions such as composing
+    let labels = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => Some(
+            GmailClient::new(&tokens)
+                .list_labels()
+                .await
+                .map_err(|e| format!("Failed to load labels: {}", e))?,
+        ),
+        Err(_) => None,
+    };
+
+    let message_label_ids = match &context {
+        ActionContext::Message { email_id } => {
+            match state.message_cache.lock().unwrap().read_message(email_id) {
+                CacheRead::Hit(message) => message.label_ids,
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    let rules = state.rules.lock().unwrap().rules.clone();
+    let settings = state.settings.lock().unwrap().clone();
+
+    let inputs = ActionInputs {
+        signed_in: labels.is_some(),
+        labels: labels.as_deref().unwrap_or_default(),
+        rules: &rules,
+        message_label_ids: message_label_ids.as_deref(),
+        digest_enabled: settings.digest.enabled,
+        attachment_indexing: settings.index_attachment_text,
+    };
+    Ok(actions::available_actions(&context, &inputs))
+}
+
+fn save_onboarding_state(state: &AppState) {
+    if let Err(e) = state.onboarding.lock().unwrap().save() {
+        eprintln!("Failed to save onboarding state: {}", e);
+    }
+}
+
+fn update_account_snapshot(state: &AppState, update: impl FnOnce(&mut AccountSnapshot)) {
+    let mut snapshot = state.account_snapshot.lock().unwrap();
+    update(&mut snapshot);
+    if let Err(e) = snapshot.save() {
+        eprintln!("Failed to save account snapshot: {}", e);
+    }
+}
+
+/// Page through older mail into the cache until the history limit, the last
+/// page, or the user defers it. Returns the messages synced so far.
+async fn sync_history(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    gmail_client: &GmailClient,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    loop {
+        let (page_token, synced) = {
+            let onboarding = state.onboarding.lock().unwrap();
+            if onboarding.history_deferred || onboarding.is_complete(OnboardingStage::History) {
+                return Ok(onboarding.history_synced);
+            }
+            (
+                onboarding.history_page_token.clone(),
+                onboarding.history_synced,
+            )
+        };
+
+        // Yield to interactive work when the background budget runs out
+        if let Err(e) = state
+            .rate_limiter
+            .check_background_rate_limit("history_sync")
+        {
+            eprintln!("Pausing history sync: {}", e);
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            continue;
+        }
+
+        let (count, next_page_token) = onboarding::sync_history_page(
+            gmail_client,
+            &state.message_cache,
+            page_token.as_deref(),
+        )
+        .await?;
+        state
+            .onboarding
+            .lock()
+            .unwrap()
+            .record_history_page(count, next_page_token);
+        save_onboarding_state(state);
+
+        let _ = app.emit(
+            "onboarding-progress",
+            OnboardingProgress::new(
+                OnboardingStage::History,
+                StageStatus::InProgress,
+                synced + count,
+            ),
+        );
+    }
+}
+
+/// Run the onboarding stages that haven't finished, emitting
+/// "onboarding-progress" as each starts and ends. A failed stage doesn't stop
+/// the ones after it and is retried on the next run.
+async fn run_onboarding(app: tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    {
+        let mut onboarding = state.onboarding.lock().unwrap();
+        if onboarding.running {
+            return;
+        }
+        onboarding.running = true;
+    }
+
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            eprintln!("Skipping onboarding sync: {}", e);
+            state.onboarding.lock().unwrap().running = false;
+            return;
+        }
+    };
+    let gmail_client = GmailClient::new(&tokens);
+
+    // Tag what's stored so far, and what the sync adds, as this account's
+    match load_profile(&state, &gmail_client).await {
+        Ok(profile) => claim_account_data(&state, &profile.email_address),
+        Err(e) => eprintln!("Failed to read the signed-in address: {}", e),
+    }
+
+    let pending = state.onboarding.lock().unwrap().pending_stages();
+    for stage in pending {
+        let _ = app.emit(
+            "onboarding-progress",
+            OnboardingProgress::new(stage, StageStatus::Started, 0),
+        );
+
+        let result = match stage {
+            OnboardingStage::Labels => gmail_client.list_labels().await.map(|labels| {
+                let count = labels.len();
+                update_account_snapshot(&state, |s| s.labels = labels);
+                count
+            }),
+            OnboardingStage::RecentThreads => {
+                onboarding::sync_recent_threads(&gmail_client, &state.message_cache).await
+            }
+            OnboardingStage::Contacts => people::PeopleClient::new(&tokens)
+                .list_contacts(onboarding::CONTACT_IMPORT_LIMIT)
+                .await
+                .map(|contacts| {
+                    let count = contacts.len();
+                    update_account_snapshot(&state, |s| s.contacts = contacts);
+                    count
+                }),
+            OnboardingStage::Signatures => gmail_client.list_send_as().await.map(|send_as| {
+                let count = send_as.iter().filter(|a| a.signature.is_some()).count();
+                update_account_snapshot(&state, |s| s.send_as = send_as);
+                count
+            }),
+            OnboardingStage::History => sync_history(&app, &state, &gmail_client).await,
+        };
+
+        let progress = match result {
+            Ok(items) => {
+                let status = {
+                    let mut onboarding = state.onboarding.lock().unwrap();
+                    if stage != OnboardingStage::History {
+                        onboarding.mark_complete(stage);
+                    }
+                    if onboarding.is_complete(stage) {
+                        StageStatus::Completed
+                    } else {
+                        StageStatus::Deferred
+                    }
+                };
+                save_onboarding_state(&state);
+                OnboardingProgress::new(stage, status, items)
+            }
+            Err(e) => OnboardingProgress::failed(stage, e.to_string()),
+        };
+        let _ = app.emit("onboarding-progress", &progress);
+    }
+
+    state.onboarding.lock().unwrap().running = false;
+}
+
+/// Start (or restart) onboarding. With `defer_history` the older-mail sync
+/// waits until `resume_history_sync`.
+#[tauri::command]
+async fn start_onboarding(
+    defer_history: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<OnboardingState, String> {
+    let status = {
+        let mut onboarding = state.onboarding.lock().unwrap();
+        if let Some(defer_history) = defer_history {
+            onboarding.history_deferred = defer_history;
+        }
+        onboarding.save()?;
+        onboarding.clone()
+    };
+
+    tauri::async_runtime::spawn(run_onboarding(app));
+    Ok(status)
+}
+
+#[tauri::command]
+async fn get_onboarding_status(state: State<'_, AppState>) -> Result<OnboardingState, String> {
+    Ok(state.onboarding.lock().unwrap().clone())
+}
+
+/// Stop the history sync after the current page; it can be resumed later
+#[tauri::command]
+async fn defer_history_sync(state: State<'_, AppState>) -> Result<(), String> {
+    let mut onboarding = state.onboarding.lock().unwrap();
+    onboarding.history_deferred = true;
+    onboarding.save()
+}
+
+#[tauri::command]
+async fn resume_history_sync(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<OnboardingState, String> {
+    start_onboarding(Some(false), app, state).await
+}
+
+/// Labels, contacts and signatures as of the last sync, for drawing the UI
+/// before any request returns
+#[tauri::command]
+async fn get_account_snapshot(state: State<'_, AppState>) -> Result<AccountSnapshot, String> {
+    Ok(state.account_snapshot.lock().unwrap().clone())
+}
+
+/// Look up storage usage, keep it on the widget summary and warn the frontend
+/// when the account moves closer to full
+async fn refresh_storage_status(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    tokens: &AuthTokens,
+) -> Result<StorageStatus, String> {
+    let status = StorageClient::new(tokens)
+        .get_status(state.clock.unix_now())
+        .await
+        .map_err(|e| format!("Failed to load storage quota: {}", e))?;
+
+    let previous = state.widget_summary.lock().unwrap().storage.clone();
+    if status.should_warn(previous.as_ref()) {
+        let _ = app.emit("storage-quota-warning", &status);
+    }
+    update_widget_summary(state, |summary| summary.storage = Some(status.clone()));
+    Ok(status)
+}
+
+/// Google storage usage, from the last lookup when it is recent enough
+#[tauri::command]
+async fn get_storage_status(
+    refresh: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<StorageStatus, String> {
+    let cached = state.widget_summary.lock().unwrap().storage.clone();
+    if let Some(status) = cached.filter(|s| {
+        !refresh.unwrap_or(false)
+            && state.clock.unix_now() < s.checked_at + storage_quota::POLL_INTERVAL_SECS
+    }) {
+        return Ok(status);
+    }
+
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_storage_status")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    refresh_storage_status(&app, &state, &tokens).await
+}
+
+/// Poll storage usage once the last lookup is older than the poll interval
+async fn refresh_stale_storage_status(app: &tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    let stale = state
+        .widget_summary
+        .lock()
+        .unwrap()
+        .storage
+        .as_ref()
+        .is_none_or(|s| state.clock.unix_now() >= s.checked_at + storage_quota::POLL_INTERVAL_SECS);
+    if !stale {
+        return;
+    }
+
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(_) => return, // Not signed in; try again next tick
+    };
+    if let Err(e) = state
+        .rate_limiter
+        .check_background_rate_limit("storage_status")
+    {
+        eprintln!("Deferring storage check: {}", e);
+        return;
+    }
+
+    if let Err(e) = refresh_storage_status(app, &state, &tokens).await {
+        eprintln!("{}", e);
+    }
+}
+
+/// Build the weekly digest from the last 7 days of inbox mail
+async fn build_weekly_digest(
+    state: &AppState,
+    gmail_client: &GmailClient,
+) -> Result<WeeklyDigest, String> {
+    let zone = schedule_zone(state);
+    let page = mailbox::search(
+        gmail_client,
+        ViewMode::Messages,
+        Some(digest::DIGEST_QUERY),
+        None,
+        mailbox::MAX_PAGE_SIZE,
+    )
+    .await
+    .map_err(|e| format!("Failed to load the week's mail: {}", e))?;
+
+    Ok(WeeklyDigest::build(
+        &page.items.into_emails(),
+        page.result_size_estimate,
+        &scheduler::now_in(&*state.clock, &zone),
+    ))
+}
+
+/// Email the weekly digest to the signed-in account through the normal send
+/// path, so it counts against the sending caps like any other message
+async fn deliver_weekly_digest(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    gmail_client: &GmailClient,
+) -> Result<String, String> {
+    let own_address = load_profile(state, gmail_client)
+        .await
+        .map_err(|e| format!("Failed to load profile: {}", e))?
+        .email_address;
+    let recipients = vec![own_address.clone()];
+    if let SendDecision::Deferred { reason, .. } = check_send_limits(state, &recipients) {
+        return Err(reason);
+    }
+
+    let digest = build_weekly_digest(state, gmail_client).await?;
+    let email = digest.to_outgoing(&own_address);
+    let message_id = gmail_client
+        .send_message(&email, None)
+        .await
+        .map_err(|e| format!("Failed to send digest: {}", e))?;
+    record_send(app, state, &recipients, &email.subject, &message_id);
+
+    {
+        let mut digest_state = state.digest_state.lock().unwrap();
+        digest_state.mark_sent(&schedule_now(state));
+        if let Err(e) = digest_state.save() {
+            eprintln!("Failed to save digest state: {}", e);
+        }
+    }
+
+    Ok(message_id)
+}
+
+/// The digest as it would be sent right now, for the settings screen
+#[tauri::command]
+async fn preview_weekly_digest(state: State<'_, AppState>) -> Result<WeeklyDigest, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("weekly_digest")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    build_weekly_digest(&state, &GmailClient::new(&tokens)).await
+}
+
+#[tauri::command]
+async fn send_weekly_digest_now(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("weekly_digest")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    deliver_weekly_digest(&app, &state, &GmailClient::new(&tokens)).await
+}
+
+/// Send the scheduled digest once its slot has passed
+async fn send_due_digest(app: &tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    let settings = state.settings.lock().unwrap().digest.clone();
+    if !state
+        .digest_state
+        .lock()
+        .unwrap()
+        .is_due(&settings, &schedule_now(&state))
+    {
+        return;
+    }
+
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(_) => return, // Not signed in; try again next tick
+    };
+    if let Err(e) = state
+        .rate_limiter
+        .check_background_rate_limit("weekly_digest")
+    {
+        eprintln!("Deferring weekly digest: {}", e);
+        return;
+    }
+
+    match deliver_weekly_digest(app, &state, &GmailClient::new(&tokens)).await {
+        Ok(message_id) => {
+            let _ = app.emit("weekly-digest-sent", &message_id);
+        }
+        Err(e) => eprintln!("Failed to send weekly digest: {}", e),
+    }
+}
+
+/// Deliverability checks for a session's message: broken or oversized images,
+/// missing alt text, misleading links and spam-filter triggers. Remote images
+/// are only fetched when `check_remote_images` is set.
+#[tauri::command]
+async fn preflight_compose_session(
+    session_id: String,
+    check_remote_images: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<PreflightReport, String> {
+    let session = get_compose_session(session_id, state).await?;
+    Ok(preflight::run(
+        &session.subject,
+        &session.body,
+        check_remote_images.unwrap_or(false),
+    )
+    .await)
+}
+
+#[tauri::command]
+async fn discard_compose_session(
+    session_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let session = {
+        let mut store = state.compose.lock().unwrap();
+        let session = store
+            .remove(&session_id)
+            .ok_or_else(|| format!("Compose session not found: {}", session_id))?;
+        save_compose_store(&store);
+        session
+    };
+
+    if let Some(draft_id) = &session.draft_id {
+        let tokens = match refresh_tokens_if_needed(&state).await {
+            Ok(tokens) => tokens,
+            Err(e) => return Err(format!("Authentication required: {}", e)),
+        };
+        GmailClient::new(&tokens)
+            .delete_draft(draft_id)
+            .await
+            .map_err(|e| format!("Failed to delete draft: {}", e))?;
+    }
+
+    let _ = app.emit("compose-session-discarded", &session_id);
+    Ok(())
+}
+
+/// Saved Gmail drafts, newest first, including ones written in other clients
+#[tauri::command]
+async fn list_drafts(
+    max_results: Option<u32>,
+    page_token: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DraftPage, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("drafts")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+    let response = gmail_client
+        .list_drafts(Some(mailbox::page_size(max_results)), page_token.as_deref())
+        .await
+        .map_err(|e| format!("Failed to list drafts: {}", e))?;
+
+    let drafts = response.drafts.unwrap_or_default();
+    let message_ids: Vec<String> = drafts
+        .iter()
+        .filter_map(|d| d.message.as_ref().map(|m| m.id.clone()))
+        .collect();
+    let messages = gmail_client
+        .get_messages_metadata_batch(&message_ids)
+        .await
+        .map_err(|e| format!("Failed to load drafts: {}", e))?;
+
+    let store = state.compose.lock().unwrap();
+    let summaries = drafts
+        .iter()
+        .filter_map(|draft| {
+            let message_id = &draft.message.as_ref()?.id;
+            let message = messages.iter().find(|m| &m.id == message_id)?;
+            let session_id = store.find_by_draft(&draft.id).map(|s| s.id.clone());
+            Some(DraftSummary::new(&draft.id, message, session_id))
+        })
+        .collect();
+
+    Ok(DraftPage {
+        drafts: summaries,
+        next_page_token: response.next_page_token,
+    })
+}
+
+#[tauri::command]
+async fn get_draft(
+    draft_id: String,
+    state: State<'_, AppState>,
+) -> Result<GmailDraftMessage, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("drafts")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    GmailClient::new(&tokens)
+        .get_draft(&draft_id)
+        .await
+        .map_err(|e| format!("Failed to load draft: {}", e))
+}
+
+/// Open a saved draft in a compose session so it can be edited and sent.
+/// A draft that already has a session gets that session back.
+#[tauri::command]
+async fn resume_draft(
+    draft_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ComposeSession, String> {
+    if let Some(session) = state.compose.lock().unwrap().find_by_draft(&draft_id) {
+        return Ok(session.clone());
+    }
+
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("drafts")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+    let draft = gmail_client
+        .get_draft(&draft_id)
+        .await
+        .map_err(|e| format!("Failed to load draft: {}", e))?;
+    let thread = fetch_thread_messages(&gmail_client, &draft.message.thread_id).await?;
+
+    let session = {
+        let mut store = state.compose.lock().unwrap();
+        let session = ComposeSession::from_draft(store.new_session_id(), &draft, &thread);
+        store.insert(session.clone());
+        save_compose_store(&store);
+        session
+    };
+
+    let _ = app.emit("compose-session-updated", &session);
+    Ok(session)
+}
+
+/// Save a compose session to its Gmail draft now instead of waiting for
+/// autosave, returning the draft id
+#[tauri::command]
+async fn save_compose_draft(
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("drafts")?;
+    let session = get_compose_session(session_id, state.clone()).await?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    save_session_draft(&state, &GmailClient::new(&tokens), &session)
+        .await?
+        .ok_or_else(|| "Compose session was discarded".to_string())
+}
+
+/// Send a saved draft. A compose session editing it is saved first so
+/// unsaved edits go out too, then closed.
+#[tauri::command]
+async fn send_draft(
+    draft_id: String,
+    ignore_outbound_warnings: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    // Check rate limit
+    state
+        .rate_limiter
+        .check_rate_limit("send_compose_session")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+    let session = state
+        .compose
+        .lock()
+        .unwrap()
+        .find_by_draft(&draft_id)
+        .cloned();
+    if let Some(session) = session.as_ref().filter(|s| s.needs_autosave()) {
+        save_session_draft(&state, &gmail_client, session).await?;
+    }
+
+    let draft = gmail_client
+        .get_draft(&draft_id)
+        .await
+        .map_err(|e| format!("Failed to load draft: {}", e))?;
+    let message = &draft.message;
+    let recipients: Vec<String> = [message.get_to(), message.get_cc(), message.get_bcc()]
+        .iter()
+        .flatten()
+        .flat_map(|header| split_recipients(header))
+        .collect();
+    if recipients.is_empty() {
+        return Err("Add at least one recipient before sending".to_string());
+    }
+    let body = message
+        .get_body_html()
+        .unwrap_or_else(|| message.get_body_text());
+    if let Some(warning) = check_outbound_rules(
+        &state,
+        &message.get_subject(),
+        &body,
+        &recipients,
+        ignore_outbound_warnings.unwrap_or(false),
+    )? {
+        return Err(warning.message());
+    }
+    if let SendDecision::Deferred { until, reason } = check_send_limits(&state, &recipients) {
+        return Err(format!(
+            "{}; try again in {} minutes",
+            reason,
+            until.saturating_sub(state.clock.unix_now()).div_ceil(60)
+        ));
+    }
+
+    let message_id = gmail_client
+        .send_draft(&draft_id)
+        .await
+        .map_err(|e| format!("Failed to send draft: {}", e))?;
+    record_send(
+        &app,
+        &state,
+        &recipients,
+        &message.get_subject(),
+        &message_id,
+    );
+
+    if let Some(session) = session {
+        let mut store = state.compose.lock().unwrap();
+        store.remove(&session.id);
+        save_compose_store(&store);
+        let _ = app.emit("compose-session-discarded", &session.id);
+    }
+    Ok(message_id)
+}
+
+#[tauri::command]
+async fn delete_draft(
+    draft_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    // A session editing the draft goes with it
+    let session_id = state
+        .compose
+        .lock()
+        .unwrap()
+        .find_by_draft(&draft_id)
+        .map(|s| s.id.clone());
+    if let Some(session_id) = session_id {
+        return discard_compose_session(session_id, app, state).await;
+    }
+
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("drafts")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    GmailClient::new(&tokens)
+        .delete_draft(&draft_id)
+        .await
+        .map_err(|e| format!("Failed to delete draft: {}", e))
+}
+
+#[tauri::command]
+async fn check_for_new_emails_since_last_check(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    check_new_mail(&app, &state).await
+}
+
+/// Carry label and read changes made in Gmail web or on other devices into
+/// the message cache, the cached inbox and the unread badge, raising
+/// "message-updated" for each changed message
+async fn sync_remote_changes(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    gmail_client: &GmailClient,
+    delta: &MailboxDelta,
+) {
+    {
+        let mut cache = state.message_cache.lock().unwrap();
+        for change in &delta.label_changes {
+            if let Err(e) = cache.apply_label_change(change) {
+                eprintln!("Failed to update cached {}: {}", change.message_id, e);
+            }
+        }
+        for message_id in &delta.deleted {
+            cache.remove(message_id);
+        }
+        if let Err(e) = cache.save_index() {
+            eprintln!("Failed to save message cache index: {}", e);
+        }
+    }
+
+    for change in &delta.label_changes {
+        if change.adds("TRASH") {
+            track_trash(state, &change.message_id, true);
+        } else if change.removes("TRASH") {
+            track_trash(state, &change.message_id, false);
+        }
+    }
+    for message_id in &delta.deleted {
+        track_trash(state, message_id, false);
+    }
+
+    let mut inbox = None;
+    update_account_snapshot(state, |snapshot| {
+        if snapshot.apply_delta(delta) {
+            inbox = Some(snapshot.inbox.clone());
+        }
+    });
+    for change in &delta.label_changes {
+        let _ = app.emit("message-updated", change);
+    }
+
+    // Recount rather than adjust, so the badge can't drift from Gmail's
+    let read_state_changed = delta
+        .label_changes
+        .iter()
+        .any(|c| c.adds("UNREAD") || c.removes("UNREAD"));
+    let unread = if read_state_changed {
+        match gmail_client
+            .list_messages(Some(1), None, Some("is:unread"))
+            .await
+        {
+            Ok(response) => response.result_size_estimate,
+            Err(e) => {
+                eprintln!("Failed to recount unread mail: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    if inbox.is_some() || unread.is_some() {
+        update_widget_summary(state, |summary| {
+            if let Some(inbox) = &inbox {
+                summary.record_inbox(inbox);
+            }
+            if let Some(unread) = unread {
+                summary.unread_count = Some(unread);
+            }
+        });
+    }
+}
+
+/// Flag new spam from saved contacts and people the user has written to,
+/// raising "spam-review-suggested" on the first one since Spam was last opened
+async fn review_new_spam(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    gmail_client: &GmailClient,
+    spam_ids: &[String],
+) {
+    let mut known: Vec<String> = state
+        .account_snapshot
+        .lock()
+        .unwrap()
+        .contacts
+        .iter()
+        .flat_map(|contact| contact.email_addresses.clone())
+        .collect();
+    known.extend(state.send_receipts.lock().unwrap().recipients());
+    if known.is_empty() {
+        return;
+    }
+
+    let messages = match gmail_client.get_messages_metadata_batch(spam_ids).await {
+        Ok(messages) => messages,
+        Err(e) => {
+            eprintln!("Failed to check new spam for known senders: {}", e);
+            return;
+        }
+    };
+    let now = state.clock.unix_now();
+    let mut remind = false;
+    let flagged = {
+        let mut review = state.spam_review.lock().unwrap();
+        for message in messages
+            .iter()
+            .filter(|m| spam_review::is_from_known_contact(m, &known))
+        {
+            remind |= review.flag(ProbableFalsePositive::from_message(message, now));
+        }
+        if let Err(e) = review.save() {
+            eprintln!("Failed to save spam review: {}", e);
+        }
+        review.probable_false_positives()
+    };
+    if remind {
+        let _ = app.emit("spam-review-suggested", &flagged);
+    }
+}
+
+/// Start a new review period once the user opens Spam
+fn mark_spam_viewed(state: &AppState) {
+    let mut review = state.spam_review.lock().unwrap();
+    review.mark_viewed(state.clock.unix_now());
+    if let Err(e) = review.save() {
+        eprintln!("Failed to save spam review: {}", e);
+    }
+}
+
+/// Spam from known senders that arrived since Spam was last opened, newest first
+#[tauri::command]
+async fn get_probable_false_positives(
+    state: State<'_, AppState>,
+) -> Result<Vec<ProbableFalsePositive>, String> {
+    Ok(state.spam_review.lock().unwrap().probable_false_positives())
+}
+
+/// The inbox page as last loaded, which a history gap is reconciled against
+fn known_inbox(state: &AppState) -> Vec<MessageLabels> {
+    state
+        .account_snapshot
+        .lock()
+        .unwrap()
+        .inbox
+        .iter()
+        .map(|email| {
+            let mut label_ids = vec!["INBOX".to_string()];
+            if !email.is_read {
+                label_ids.push("UNREAD".to_string());
+            }
+            MessageLabels {
+                id: email.id.clone(),
+                thread_id: email.thread_id.clone(),
+                label_ids,
+            }
+        })
+        .collect()
+}
+
+/// Run one history check, apply rules and watches, and return the ids ready
+/// to surface; shared by the polling command and the push listener
+async fn check_new_mail(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    // Get auth tokens
+    let tokens = match refresh_tokens_if_needed(state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    // Get last check time and history position
+    let last_check = {
+        let guard = state.last_check_time.lock().unwrap();
+        guard.clone()
+    };
+    let last_history_id = state.last_history_id.lock().unwrap().clone();
+    let known_inbox = known_inbox(state);
+
+    // Create Gmail client
+    let gmail_client = GmailClient::new(&tokens);
+
+    // Fetch everything that changed since the last check in one delta
+    match gmail_client
+        .check_for_new_emails(last_history_id.as_deref(), &known_inbox)
+        .await
+    {
+        Ok(delta) => {
+            // Update last check time to current Unix timestamp
+            let current_time = state.clock.unix_now();
+
+            *state.last_check_time.lock().unwrap() = Some(current_time.to_string());
+            *state.last_history_id.lock().unwrap() = Some(delta.history_id.clone());
+
+            // Deletions and label changes let the frontend patch its list in
+            // place instead of reloading it
+            if !delta.deleted.is_empty() || !delta.label_changes.is_empty() {
+                let _ = app.emit("mailbox-changed", &delta);
+                sync_remote_changes(app, state, &gmail_client, &delta).await;
+            }
+            if !delta.spam_added.is_empty() {
+                review_new_spam(app, state, &gmail_client, &delta.spam_added).await;
+            }
+            // Headers of new mail, for muted threads, mailing lists, SLA
+            // queues and newsletter feeds
+            let new_messages = new_mail_metadata(&gmail_client, &delta.added).await;
+            record_mailing_lists(state, &new_messages);
+            track_sla(
+                app,
+                state,
+                &gmail_client,
+                &new_messages,
+                last_check.as_deref(),
+            )
+            .await;
+            let new_ids =
+                update_newsletter_feeds(state, &gmail_client, &new_messages, delta.added).await;
+            let new_email_ids =
+                archive_muted_arrivals(state, &gmail_client, &new_messages, new_ids).await;
+            state
+                .poll_schedule
+                .lock()
+                .unwrap()
+                .record_check(new_email_ids.len());
+
+            // Apply new-mail rules, drawing on the background budget so rule
+            // work never blocks interactive commands
+            if !new_email_ids.is_empty()
+                && state
+                    .rate_limiter
+                    .check_background_rate_limit("new_mail_rules")
+                    .is_ok()
+            {
+                let rules = state.rules.lock().unwrap().rules.clone();
+                for summary in rules::apply_to_new_mail(&gmail_client, &rules, &new_email_ids).await
+                {
+                    if summary.failed > 0 {
+                        eprintln!("Rule run had failures: {:?}", summary.errors);
+                    }
+                }
+            }
+
+            // Watched-thread replies skip the focus hold below
+            let watched_ids: Vec<String> = match last_check.as_deref() {
+                Some(since) => find_watched_replies(state, &gmail_client, since)
+                    .await
+                    .into_iter()
+                    .map(|reply| {
+                        let _ = app.emit("watched-thread-reply", &reply);
+                        reply.message_id
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+            // So do messages whose notification rule bypasses quiet hours
+            let styles = notification_styles(state, &gmail_client, &new_email_ids).await;
+            let (watched_new, to_hold): (Vec<String>, Vec<String>) =
+                new_email_ids.into_iter().partition(|id| {
+                    watched_ids.contains(id)
+                        || styles
+                            .iter()
+                            .any(|s| s.bypass_quiet_hours && &s.message_id == id)
+                });
+
+            // In focus mode new mail is held and surfaced in batches
+            let focus_mode = state.settings.lock().unwrap().focus_mode.clone();
+            let mut delivered =
+                state
+                    .focus_buffer
+                    .lock()
+                    .unwrap()
+                    .process(&to_hold, current_time, &focus_mode);
+            delivered.extend(watched_new);
+            update_widget_summary(state, |summary| summary.record_new_mail(delivered.len()));
+            let delivered_styles: Vec<&NotificationStyle> = styles
+                .iter()
+                .filter(|s| delivered.contains(&s.message_id))
+                .collect();
+            if !delivered_styles.is_empty() {
+                let _ = app.emit("notification-styles", &delivered_styles);
+            }
+
+            Ok(delivered)
+        }
+        Err(e) => {
+            eprintln!("Error checking for new emails: {}", e);
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Sounds and quiet-hours overrides for new messages matching a notification
+/// rule; messages no rule matches are left out
+async fn notification_styles(
+    state: &AppState,
+    gmail_client: &GmailClient,
+    message_ids: &[String],
+) -> Vec<NotificationStyle> {
+    let rules = state.settings.lock().unwrap().notifications.rules.clone();
+    if rules.is_empty() || message_ids.is_empty() {
+        return Vec::new();
+    }
+    match gmail_client.get_messages_metadata_batch(message_ids).await {
+        Ok(messages) => messages
+            .iter()
+            .filter_map(|message| notify_priority::resolve(&rules, message))
+            .collect(),
+        Err(e) => {
+            eprintln!("Failed to match new mail against notification rules: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// How the notifier should announce each of `email_ids`, for mail surfaced
+/// outside the background check
+#[tauri::command]
+async fn get_notification_styles(
+    email_ids: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<NotificationStyle>, String> {
+    // Check rate limit
+    state
+        .rate_limiter
+        .check_rate_limit("get_notification_styles")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+    let gmail_client = GmailClient::new(&tokens);
+
+    Ok(notification_styles(&state, &gmail_client, &email_ids).await)
+}
+
+/// Whether mailbox changes arrive by push or by polling
+#[tauri::command]
+async fn get_push_status(state: State<'_, AppState>) -> Result<PushStatus, String> {
+    Ok(state.push_status.lock().unwrap().clone())
+}
+
+/// End Gmail's push notifications if a watch is running. Failures are only
+/// logged; a watch nobody renews lapses within a week.
+async fn stop_push_watch(state: &State<'_, AppState>) {
+    if state.push_status.lock().unwrap().mode != PushMode::Push {
+        return;
+    }
+    let stopped = match refresh_tokens_if_needed(state).await {
+        Ok(tokens) => GmailClient::new(&tokens)
+            .stop_watch()
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = stopped {
+        eprintln!("Failed to stop Gmail watch: {}", e);
+    }
+}
+
+fn set_push_status(app: &tauri::AppHandle, state: &AppState, status: PushStatus) {
+    let mut current = state.push_status.lock().unwrap();
+    if *current != status {
+        *current = status.clone();
+        let _ = app.emit("push-status", &status);
+    }
+}
+
+/// Check history from the sync scheduler and raise "new-emails" for anything
+/// ready to surface
+async fn check_new_mail_in_background(app: &tauri::AppHandle, state: &State<'_, AppState>) {
+    if let Err(e) = state
+        .rate_limiter
+        .check_background_rate_limit("new_mail_check")
+    {
+        eprintln!("Deferring new-mail check: {}", e);
+        return;
+    }
+    match check_new_mail(app, state).await {
+        Ok(ids) if !ids.is_empty() => {
+            let _ = app.emit("new-emails", &ids);
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Background new-mail check failed: {}", e),
+    }
+}
+
+/// Seconds until the next polled check, backed off per the polling settings
+fn next_poll_interval(state: &AppState) -> u64 {
+    let polling = state.settings.lock().unwrap().polling.clone();
+    state.poll_schedule.lock().unwrap().next_interval(&polling)
+}
+
+/// Poll history now and schedule the next check
+async fn poll_new_mail(app: &tauri::AppHandle, state: &State<'_, AppState>) -> u64 {
+    check_new_mail_in_background(app, state).await;
+    next_poll_interval(state)
+}
+
+/// One sync scheduler step, returning the seconds to wait before the next.
+/// With push configured it keeps the Gmail watch renewed and checks history
+/// on each notification; otherwise, or while the watch or pull is failing,
+/// history is polled on the adaptive schedule.
+async fn run_sync_cycle(app: &tauri::AppHandle) -> u64 {
+    let state = app.state::<AppState>();
+    let push_settings = state.settings.lock().unwrap().push.clone();
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(_) => return next_poll_interval(&state), // Not signed in; try again later
+    };
+    if !push_settings.is_configured() {
+        stop_push_watch(&state).await;
+        set_push_status(app, &state, PushStatus::default());
+        return poll_new_mail(app, &state).await;
+    }
+
+    let now = state.clock.unix_now();
+    let status = state.push_status.lock().unwrap().clone();
+    if status.needs_watch(now) {
+        if status.retry_at.is_some_and(|retry_at| now < retry_at) {
+            return poll_new_mail(app, &state).await;
+        }
+        match GmailClient::new(&tokens)
+            .watch(&push_settings.topic_name)
+            .await
+        {
+            Ok(watch) => set_push_status(app, &state, PushStatus::watching(watch.expires_at())),
+            Err(e) => {
+                eprintln!("Gmail watch failed, polling instead: {}", e);
+                set_push_status(app, &state, PushStatus::polling(e.to_string(), now));
+                return poll_new_mail(app, &state).await;
+            }
+        }
+    }
+
+    match PubSubClient::new(&tokens)
+        .pull(&push_settings.subscription)
+        .await
+    {
+        Ok(notifications) => {
+            if !notifications.is_empty()
+                && notifies_signed_in_account(&state, &tokens, &notifications).await
+            {
+                check_new_mail_in_background(app, &state).await;
+            }
+            // Pulls wait server-side for news, so the next one can start right away
+            0
+        }
+        Err(e) => {
+            eprintln!("Pub/Sub pull failed, polling instead: {}", e);
+            set_push_status(app, &state, PushStatus::polling(e.to_string(), now));
+            poll_new_mail(app, &state).await
+        }
+    }
+}
+
+/// Whether any notification is about the signed-in mailbox. If the profile
+/// can't be loaded they're all taken as ours, costing at most one history check.
+async fn notifies_signed_in_account(
+    state: &AppState,
+    tokens: &AuthTokens,
+    notifications: &[GmailNotification],
+) -> bool {
+    match load_profile(state, &GmailClient::new(tokens)).await {
+        Ok(profile) => notifications
+            .iter()
+            .any(|n| n.is_for(&profile.email_address)),
+        Err(e) => {
+            eprintln!("Failed to load profile for push notifications: {}", e);
+            true
+        }
+    }
+}
+
+/// Run sync cycles forever; a window gaining focus cuts the wait short
+fn spawn_sync_scheduler(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let wait = run_sync_cycle(&app).await;
+            let state = app.state::<AppState>();
+            // Cache fetches short of a full batch still reach disk each cycle
+            state.message_cache.lock().unwrap().flush();
+            tokio::select! {
+                _ = tokio::time::sleep(std::time::Duration::from_secs(wait)) => {}
+                _ = state.sync_wake.notified() => {}
+            }
+        }
+    });
+}
+
+/// Replies that arrived in watched threads since `since`, each reported once
+async fn find_watched_replies(
+    state: &AppState,
+    gmail_client: &GmailClient,
+    since: &str,
+) -> Vec<WatchedReply> {
+    if state.watched_threads.lock().unwrap().threads().is_empty() {
+        return Vec::new();
+    }
+
+    let response = match gmail_client
+        .list_messages(
+            Some(mailbox::MAX_PAGE_SIZE),
+            None,
+            Some(&thread_watch::replies_query(since)),
+        )
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("Failed to check watched threads: {}", e);
+            return Vec::new();
+        }
+    };
+    let reply_ids: Vec<String> = {
+        let mut watched = state.watched_threads.lock().unwrap();
+        response
+            .messages
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|m| watched.record_reply(&m.thread_id, &m.id))
+            .map(|m| m.id)
+            .collect()
+    };
+    if reply_ids.is_empty() {
+        return Vec::new();
+    }
+    if let Err(e) = state.watched_threads.lock().unwrap().save() {
+        eprintln!("Failed to save watched threads: {}", e);
+    }
+
+    match gmail_client.get_messages_metadata_batch(&reply_ids).await {
+        Ok(messages) => messages.iter().map(WatchedReply::from_message).collect(),
+        Err(e) => {
+            eprintln!("Failed to load watched-thread replies: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Raise a "watched-thread-reply" event for every reply that arrives in the
+/// thread, even in focus mode
+#[tauri::command]
+async fn watch_thread(
+    thread_id: String,
+    state: State<'_, AppState>,
+) -> Result<WatchedThread, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_conversation")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let thread = gmail_client
+        .get_thread(&thread_id)
+        .await
+        .map_err(|e| format!("Failed to load thread: {}", e))?;
+    let subject = thread
+        .messages
+        .as_ref()
+        .and_then(|messages| messages.first())
+        .map(|message| message.get_subject())
+        .unwrap_or_default();
+
+    let mut watched = state.watched_threads.lock().unwrap();
+    let thread = watched
+        .watch(&thread.id, &subject, state.clock.unix_now())
+        .clone();
+    watched.save()?;
+    Ok(thread)
+}
+
+#[tauri::command]
+async fn unwatch_thread(thread_id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    let mut watched = state.watched_threads.lock().unwrap();
+    let removed = watched.unwatch(&thread_id);
+    watched.save()?;
+    Ok(removed)
+}
+
+#[tauri::command]
+async fn list_watched_threads(state: State<'_, AppState>) -> Result<Vec<WatchedThread>, String> {
+    Ok(state.watched_threads.lock().unwrap().threads().to_vec())
+}
+
+/// Mute a conversation: archive it now and, as sync finds them, any new
+/// messages that arrive in it
+#[tauri::command]
+async fn mute_thread(thread_id: String, state: State<'_, AppState>) -> Result<MutedThread, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("mute_thread")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let thread = gmail_client
+        .get_thread(&thread_id)
+        .await
+        .map_err(|e| format!("Failed to load thread: {}", e))?;
+    let subject = thread
+        .messages
+        .as_ref()
+        .and_then(|messages| messages.first())
+        .map(|message| message.get_subject())
+        .unwrap_or_default();
+    gmail_client
+        .modify_thread(&thread.id, &[], &["INBOX"])
+        .await
+        .map_err(|e| format!("Failed to archive conversation: {}", e))?;
+
+    let mut muted = state.muted_threads.lock().unwrap();
+    let thread = muted
+        .mute(&thread.id, &subject, state.clock.unix_now())
+        .clone();
+    muted.save()?;
+    Ok(thread)
+}
+
+/// Let new messages in a muted conversation reach the inbox again. Like
+/// Gmail, the conversation stays archived unless `move_to_inbox` is set.
+#[tauri::command]
+async fn unmute_thread(
+    thread_id: String,
+    move_to_inbox: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("mute_thread")?;
+    if move_to_inbox.unwrap_or(false) {
+        let tokens = match refresh_tokens_if_needed(&state).await {
+            Ok(tokens) => tokens,
+            Err(e) => return Err(format!("Authentication required: {}", e)),
+        };
+        GmailClient::new(&tokens)
+            .modify_thread(&thread_id, &["INBOX"], &[])
+            .await
+            .map_err(|e| format!("Failed to move conversation to the inbox: {}", e))?;
+    }
+
+    let mut muted = state.muted_threads.lock().unwrap();
+    let removed = muted.unmute(&thread_id);
+    muted.save()?;
+    Ok(removed)
+}
+
+#[tauri::command]
+async fn list_muted_threads(state: State<'_, AppState>) -> Result<Vec<MutedThread>, String> {
+    Ok(state.muted_threads.lock().unwrap().threads().to_vec())
+}
+
+/// Mark a conversation as handled by the signed-in account with its claim
+/// label, so teammates working a shared or delegated inbox see the claim
+/// after sync. A conversation a teammate already claimed is refused unless
+/// `take_over` is set, which removes their claim.
+#[tauri::command]
+async fn claim_thread(
+    thread_id: String,
+    take_over: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<ThreadClaim, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("claim_thread")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let own_address = load_profile(&state, &gmail_client)
+        .await
+        .map_err(|e| format!("Failed to load profile: {}", e))?
+        .email_address;
+    let labels = gmail_client
+        .list_labels()
+        .await
+        .map_err(|e| format!("Failed to load labels: {}", e))?;
+    let thread = gmail_client
+        .get_thread(&thread_id)
+        .await
+        .map_err(|e| format!("Failed to load thread: {}", e))?;
+
+    let claims = thread_claims::thread_claims(&thread, &labels, &own_address);
+    let others: Vec<&str> = claims
+        .iter()
+        .filter(|c| !c.mine)
+        .map(|c| c.label_id.as_str())
+        .collect();
+    if !others.is_empty() && !take_over.unwrap_or(false) {
+        return Err(thread_claims::conflict_message(&claims));
+    }
+
+    let name = thread_claims::claim_label_name(&own_address);
+    let label = match labels.iter().find(|l| l.name.eq_ignore_ascii_case(&name)) {
+        Some(label) => label.clone(),
+        None => gmail_client
+            .create_label(&name)
+            .await
+            .map_err(|e| format!("Failed to create claim label: {}", e))?,
+    };
+    gmail_client
+        .modify_thread(&thread.id, &[label.id.as_str()], &others)
+        .await
+        .map_err(|e| format!("Failed to claim conversation: {}", e))?;
+
+    ThreadClaim::new(&thread.id, &label, &own_address)
+        .ok_or_else(|| format!("{} is not a claim label", label.name))
+}
+
+/// Drop the signed-in account's claim on a conversation, returning whether
+/// there was one
+#[tauri::command]
+async fn release_thread_claim(
+    thread_id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("claim_thread")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let own_address = load_profile(&state, &gmail_client)
+        .await
+        .map_err(|e| format!("Failed to load profile: {}", e))?
+        .email_address;
+    let labels = gmail_client
+        .list_labels()
+        .await
+        .map_err(|e| format!("Failed to load labels: {}", e))?;
+    let thread = gmail_client
+        .get_thread(&thread_id)
+        .await
+        .map_err(|e| format!("Failed to load thread: {}", e))?;
+
+    let mine: Vec<String> = thread_claims::thread_claims(&thread, &labels, &own_address)
+        .into_iter()
+        .filter(|c| c.mine)
+        .map(|c| c.label_id)
+        .collect();
+    if mine.is_empty() {
+        return Ok(false);
+    }
+    let mine: Vec<&str> = mine.iter().map(String::as_str).collect();
+    gmail_client
+        .modify_thread(&thread.id, &[], &mine)
+        .await
+        .map_err(|e| format!("Failed to release conversation: {}", e))?;
+    Ok(true)
+}
+
+/// Who is handling a conversation, to show before replying to it
+#[tauri::command]
+async fn get_thread_claims(
+    thread_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<ThreadClaim>, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_thread_claims")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let own_address = load_profile(&state, &gmail_client)
+        .await
+        .map_err(|e| format!("Failed to load profile: {}", e))?
+        .email_address;
+    let labels = gmail_client
+        .list_labels()
+        .await
+        .map_err(|e| format!("Failed to load labels: {}", e))?;
+    let thread = gmail_client
+        .get_thread(&thread_id)
+        .await
+        .map_err(|e| format!("Failed to load thread: {}", e))?;
+    Ok(thread_claims::thread_claims(&thread, &labels, &own_address))
+}
+
+/// Every claimed conversation in the mailbox, up to a page per teammate, so
+/// the list view can mark them after sync
+#[tauri::command]
+async fn list_claimed_threads(state: State<'_, AppState>) -> Result<Vec<ThreadClaim>, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_thread_claims")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let own_address = load_profile(&state, &gmail_client)
+        .await
+        .map_err(|e| format!("Failed to load profile: {}", e))?
+        .email_address;
+    let labels = gmail_client
+        .list_labels()
+        .await
+        .map_err(|e| format!("Failed to load labels: {}", e))?;
+
+    let mut claims = Vec::new();
+    for label in thread_claims::claim_labels(&labels) {
+        let response = gmail_client
+            .list_threads_with_label(&label.id, mailbox::MAX_PAGE_SIZE)
+            .await
+            .map_err(|e| format!("Failed to list claimed conversations: {}", e))?;
+        claims.extend(
+            response
+                .threads
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|thread| ThreadClaim::new(&thread.id, label, &own_address)),
+        );
+    }
+    Ok(claims)
+}
+
+/// Headers and labels of newly arrived messages; empty if they can't be loaded
+async fn new_mail_metadata(gmail_client: &GmailClient, new_ids: &[String]) -> Vec<GmailMessage> {
+    match gmail_client.get_messages_metadata_batch(new_ids).await {
+        Ok(messages) => messages,
+        Err(e) => {
+            eprintln!("Failed to load new mail headers: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Archive new inbox mail in muted threads, returning the rest of `new_ids`.
+/// Mail whose headers couldn't be loaded is left in the inbox.
+async fn archive_muted_arrivals(
+    state: &AppState,
+    gmail_client: &GmailClient,
+    messages: &[GmailMessage],
+    new_ids: Vec<String>,
+) -> Vec<String> {
+    let muted = state
+        .muted_threads
+        .lock()
+        .unwrap()
+        .arrivals_to_archive(messages);
+    if muted.is_empty() {
+        return new_ids;
+    }
+    if let Err(e) = gmail_client
+        .batch_modify_messages(&muted, &[], &["INBOX".to_string()])
+        .await
+    {
+        eprintln!("Failed to archive mail in muted threads: {}", e);
+        return new_ids;
+    }
+    new_ids
+        .into_iter()
+        .filter(|id| !muted.contains(id))
+        .collect()
+}
+
+/// Add new issues from senders with a newsletter feed to their feed files,
+/// archiving them when the feed is set to, and return the rest of `new_ids`.
+/// Issues that couldn't be loaded stay in the inbox.
+async fn update_newsletter_feeds(
+    state: &AppState,
+    gmail_client: &GmailClient,
+    messages: &[GmailMessage],
+    new_ids: Vec<String>,
+) -> Vec<String> {
+    let arrivals: Vec<(String, String)> = state
+        .newsletter_feeds
+        .lock()
+        .unwrap()
+        .arrivals(messages)
+        .into_iter()
+        .map(|m| (m.id.clone(), m.get_from_address()))
+        .collect();
+    if arrivals.is_empty() {
+        return new_ids;
+    }
+
+    // Feeds are written from the cache, so bring the full issues into it
+    let mut loaded = Vec::new();
+    for (message_id, sender) in arrivals {
+        match message_cache::load_message(gmail_client, &state.message_cache, &message_id).await {
+            Ok(_) => loaded.push((message_id, sender)),
+            Err(e) => eprintln!("Failed to load newsletter {}: {}", message_id, e),
+        }
+    }
+    let mut senders: Vec<String> = loaded
+        .iter()
+        .map(|(_, sender)| sender.to_ascii_lowercase())
+        .collect();
+    senders.sort();
+    senders.dedup();
+
+    let mut to_archive = Vec::new();
+    for sender in senders {
+        let Some(feed) = state.newsletter_feeds.lock().unwrap().get(&sender).cloned() else {
+            continue;
+        };
+        let cached = state.message_cache.lock().unwrap().messages_from(&sender);
+        match newsletter_feed::write_feed(Path::new(&feed.path), &feed.sender, &cached) {
+            Ok((title, entries)) => {
+                if feed.archive_from_inbox {
+                    to_archive.extend(
+                        loaded
+                            .iter()
+                            .filter(|(_, s)| s.eq_ignore_ascii_case(&sender))
+                            .map(|(id, _)| id.clone()),
+                    );
+                }
+                state
+                    .newsletter_feeds
+                    .lock()
+                    .unwrap()
+                    .upsert(NewsletterFeed {
+                        title,
+                        entries,
+                        updated_at: state.clock.unix_now(),
+                        ..feed
+                    });
+            }
+            Err(e) => eprintln!("Failed to update the feed for {}: {}", sender, e),
+        }
+    }
+    if let Err(e) = state.newsletter_feeds.lock().unwrap().save() {
+        eprintln!("Failed to save newsletter feeds: {}", e);
+    }
+
+    if to_archive.is_empty() {
+        return new_ids;
+    }
+    if let Err(e) = gmail_client
+        .batch_modify_messages(&to_archive, &[], &["INBOX".to_string()])
+        .await
+    {
+        eprintln!("Failed to archive newsletters: {}", e);
+        return new_ids;
+    }
+    new_ids
+        .into_iter()
+        .filter(|id| !to_archive.contains(id))
+        .collect()
+}
+
+/// Keep an Atom feed of a newsletter sender's cached issues for reading in a
+/// feed reader; new issues are added as they arrive. With
+/// `archive_from_inbox` the sender's issues skip the inbox, starting with
+/// those already there. Calling it again rewrites the feed and keeps the
+/// archive choice unless given.
+#[tauri::command]
+async fn generate_feed(
+    sender: String,
+    archive_from_inbox: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<NewsletterFeed, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("generate_feed")?;
+    let sender = gmail_client::extract_email_address(&sender).to_ascii_lowercase();
+    let messages = state.message_cache.lock().unwrap().messages_from(&sender);
+    let issues = newsletter_feed::issues(&sender, &messages);
+    if issues.is_empty() {
+        return Err(format!("No newsletters from {} in the cache", sender));
+    }
+    let archive_from_inbox = archive_from_inbox
+        .or_else(|| {
+            state
+                .newsletter_feeds
+                .lock()
+                .unwrap()
+                .get(&sender)
+                .map(|f| f.archive_from_inbox)
+        })
+        .unwrap_or(false);
+
+    if archive_from_inbox {
+        let in_inbox: Vec<String> = issues
+            .iter()
+            .filter(|m| {
+                m.label_ids
+                    .as_ref()
+                    .is_some_and(|ids| ids.iter().any(|l| l == "INBOX"))
+            })
+            .map(|m| m.id.clone())
+            .collect();
+        if !in_inbox.is_empty() {
+            let tokens = match refresh_tokens_if_needed(&state).await {
+                Ok(tokens) => tokens,
+                Err(e) => return Err(format!("Authentication required: {}", e)),
+            };
+            GmailClient::new(&tokens)
+                .batch_modify_messages(&in_inbox, &[], &["INBOX".to_string()])
+                .await
+                .map_err(|e| format!("Failed to archive newsletters: {}", e))?;
+        }
+    }
+
+    let path = newsletter_feed::feed_path(&sender);
+    let (title, entries) = newsletter_feed::write_feed(&path, &sender, &messages)?;
+    let feed = NewsletterFeed {
+        sender,
+        title,
+        path: path.to_string_lossy().into_owned(),
+        archive_from_inbox,
+        entries,
+        updated_at: state.clock.unix_now(),
+    };
+    let mut feeds = state.newsletter_feeds.lock().unwrap();
+    feeds.upsert(feed.clone());
+    feeds.save()?;
+    Ok(feed)
+}
+
+/// Newsletter feeds being kept up to date
+#[tauri::command]
+async fn list_newsletter_feeds(state: State<'_, AppState>) -> Result<Vec<NewsletterFeed>, String> {
+    Ok(state.newsletter_feeds.lock().unwrap().feeds())
+}
+
+/// Stop a sender's feed and delete its file, returning whether there was one.
+/// Mail already archived stays archived.
+#[tauri::command]
+async fn remove_newsletter_feed(
+    sender: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let mut feeds = state.newsletter_feeds.lock().unwrap();
+    let Some(feed) = feeds.remove(&sender) else {
+        return Ok(false);
+    };
+    feeds.save()?;
+    if let Err(e) = std::fs::remove_file(&feed.path) {
+        eprintln!("Failed to delete feed {}: {}", feed.path, e);
+    }
+    Ok(true)
+}
+
+/// Group new mail by the List-Id header mailing list software adds
+fn record_mailing_lists(state: &AppState, messages: &[GmailMessage]) {
+    let mut directory = state.mailing_lists.lock().unwrap();
+    if directory.record(messages, state.clock.unix_now()) {
+        if let Err(e) = directory.save() {
+            eprintln!("Failed to save mailing lists: {}", e);
+        }
+    }
+}
+
+/// Mailing lists sync has seen, most recent first, with each list's unread
+/// inbox count for the most recent `MAX_COUNTED_LISTS`
+#[tauri::command]
+async fn get_mailing_lists(state: State<'_, AppState>) -> Result<Vec<MailingListSummary>, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_mailing_lists")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let lists = state.mailing_lists.lock().unwrap().lists();
+    let mut summaries = Vec::with_capacity(lists.len());
+    for (i, list) in lists.iter().enumerate() {
+        let unread_count = if i < mailing_lists::MAX_COUNTED_LISTS {
+            let query = format!("{} is:unread in:inbox", list.query());
+            match gmail_client
+                .list_messages(Some(1), None, Some(&query))
+                .await
+            {
+                Ok(response) => Some(response.result_size_estimate.unwrap_or(0)),
+                Err(e) => {
+                    eprintln!("Failed to count unread mail from {}: {}", list.list_id, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        summaries.push(MailingListSummary::new(list, unread_count));
+    }
+    Ok(summaries)
+}
+
+/// Archive everything in the inbox from one mailing list, undoable like
+/// any bulk action
+#[tauri::command]
+async fn archive_mailing_list(
+    list_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<BulkSummary, String> {
+    let query = format!("{} in:inbox", mailing_lists::list_query(&list_id));
+    bulk_action_by_query(query, BulkAction::Archive, None, app, state).await
+}
+
+/// GitHub and GitLab notifications from recent mail, grouped by PR, issue
+/// or CI run. With `reason` set only threads including it are returned, e.g.
+/// review requests only.
+#[tauri::command]
+async fn get_code_notifications(
+    reason: Option<NotificationReason>,
+    query: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CodeThread>, String> {
+    // Check rate limit
+    state
+        .rate_limiter
+        .check_rate_limit("get_code_notifications")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let query = query
+        .filter(|q| !q.trim().is_empty())
+        .unwrap_or_else(|| code_notifications::DEFAULT_QUERY.to_string());
+    let response = gmail_client
+        .list_messages(Some(code_notifications::MAX_MESSAGES), None, Some(&query))
+        .await
+        .map_err(|e| e.to_string())?;
+    let ids: Vec<String> = response
+        .messages
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let messages = gmail_client
+        .get_messages_metadata_batch(&ids)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(code_notifications::group_threads(&messages, reason))
+}
+
+/// Start SLA clocks for new queue mail, stop them at replies sent since
+/// `since` (unix seconds), and raise "sla-warning" for threads nearing or
+/// past their deadline
+async fn track_sla(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    gmail_client: &GmailClient,
+    messages: &[GmailMessage],
+    since: Option<&str>,
+) {
+    let queues = state.settings.lock().unwrap().sla.queues.clone();
+    if queues.is_empty() {
+        return;
+    }
+    let now = state.clock.unix_now();
+    let mut changed = state.sla.lock().unwrap().open(&queues, messages, now);
+
+    let pending = state.sla.lock().unwrap().has_pending();
+    if let (Some(since), true) = (since, pending) {
+        match gmail_client
+            .list_messages(
+                Some(mailbox::MAX_PAGE_SIZE),
+                None,
+                Some(&sla::replies_query(since)),
+            )
+            .await
+        {
+            Ok(response) => {
+                let reply_ids: Vec<String> = {
+                    let tracker = state.sla.lock().unwrap();
+                    response
+                        .messages
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|m| tracker.is_pending(&m.thread_id))
+                        .map(|m| m.id)
+                        .collect()
+                };
+                // Time replies by when Gmail got them rather than this poll,
+                // earliest first so a thread's first reply is the one kept
+                let mut replies = if reply_ids.is_empty() {
+                    Vec::new()
+                } else {
+                    gmail_client
+                        .get_messages_metadata_batch(&reply_ids)
+                        .await
+                        .unwrap_or_else(|e| {
+                            eprintln!("Failed to load SLA replies: {}", e);
+                            Vec::new()
+                        })
+                };
+                replies.sort_by_key(sla::received_at);
+                let mut tracker = state.sla.lock().unwrap();
+                for reply in &replies {
+                    let at = sla::received_at(reply).unwrap_or(now);
+                    changed |= tracker.respond(&reply.thread_id, at);
+                }
+            }
+            Err(e) => eprintln!("Failed to check SLA replies: {}", e),
+        }
+    }
+
+    let warnings = {
+        let mut tracker = state.sla.lock().unwrap();
+        let warnings = tracker.warnings(&queues, now);
+        changed |= tracker.prune(now) || !warnings.is_empty();
+        warnings
+    };
+    for warning in &warnings {
+        let _ = app.emit("sla-warning", warning);
+    }
+    if changed {
+        if let Err(e) = state.sla.lock().unwrap().save() {
+            eprintln!("Failed to save SLA tracking: {}", e);
+        }
+    }
+}
+
+/// First-response times for the SLA queues in settings, per queue and per
+/// thread over the last 30 days
+#[tauri::command]
+async fn get_sla_report(state: State<'_, AppState>) -> Result<SlaReport, String> {
+    let queues = state.settings.lock().unwrap().sla.queues.clone();
+    Ok(state
+        .sla
+        .lock()
+        .unwrap()
+        .report(&queues, state.clock.unix_now()))
+}
+
+/// Archive a message until `until` (unix seconds) or a preset such as
+/// tomorrow morning, when the snooze scheduler puts it back in the inbox and
+/// raises "snooze-returned". A preset keeps its wall-clock time if the user
+/// changes zone.
+#[tauri::command]
+async fn snooze_email(
+    email_id: String,
+    until: Option<u64>,
+    preset: Option<Preset>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<SnoozedEmail, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("archive_email")?;
+    let now = state.clock.unix_now();
+    let wake = match preset {
+        Some(preset) => Some(
+            preset
+                .schedule(&schedule_now(&state))
+                .ok_or("That snooze time has already passed")?,
+        ),
+        None => None,
+    };
+    let until = match (&wake, until) {
+        (Some(wake), _) => wake.at,
+        (None, Some(until)) => until,
+        (None, None) => return Err("Choose when the message should come back".to_string()),
+    };
+    if until <= now {
+        return Err("Snooze time must be in the future".to_string());
+    }
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let message = gmail_client
+        .get_messages_metadata_batch(std::slice::from_ref(&email_id))
+        .await
+        .map_err(|e| format!("Failed to load email: {}", e))?
+        .into_iter()
+        .next()
+        .ok_or("Email not found")?;
+    run_mutation(&app, &state, &gmail_client, Mutation::Archive, &email_id)
+        .await
+        .map_err(|e| format!("Failed to snooze email: {}", e))?;
+
+    let entry = SnoozedEmail {
+        wake,
+        ..SnoozedEmail::from_message(&message, now, until)
+    };
+    let mut snoozed = state.snoozed.lock().unwrap();
+    snoozed.snooze(entry.clone());
+    snoozed.save()?;
+    Ok(entry)
+}
+
+/// The zone schedules are worked out in, from settings or the device
+fn schedule_zone(state: &AppState) -> chrono_tz::Tz {
+    scheduler::current_zone(state.settings.lock().unwrap().time_zone.as_deref())
+}
+
+/// The current time in the zone schedules are worked out in
+fn schedule_now(state: &AppState) -> chrono::DateTime<chrono_tz::Tz> {
+    scheduler::now_in(&*state.clock, &schedule_zone(state))
+}
+
+/// Snooze presets with the times they come to right now, for the picker
+#[tauri::command]
+async fn get_schedule_presets(state: State<'_, AppState>) -> Result<Vec<PresetTime>, String> {
+    Ok(scheduler::preset_times(&schedule_now(&state)))
+}
+
+/// Return a snoozed message to the inbox now
+#[tauri::command]
+async fn unsnooze_email(email_id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("archive_email")?;
+    if !state
+        .snoozed
+        .lock()
+        .unwrap()
+        .entries()
+        .iter()
+        .any(|e| e.message_id == email_id)
+    {
+        return Ok(false);
+    }
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    GmailClient::new(&tokens)
+        .move_to_inbox(&email_id)
+        .await
+        .map_err(|e| format!("Failed to return email to inbox: {}", e))?;
+
+    let mut snoozed = state.snoozed.lock().unwrap();
+    snoozed.remove(&email_id);
+    snoozed.save()?;
+    Ok(true)
+}
+
+#[tauri::command]
+async fn list_snoozed(state: State<'_, AppState>) -> Result<Vec<SnoozedEmail>, String> {
+    Ok(state.snoozed.lock().unwrap().entries().to_vec())
+}
+
+/// Put snoozed messages whose time has come back in the inbox and raise
+/// "snooze-returned" for them. Failures stay snoozed and are retried on the
+/// next check.
+async fn return_due_snoozes(app: &tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    let zone = schedule_zone(&state);
+    let due = {
+        let mut snoozed = state.snoozed.lock().unwrap();
+        if snoozed.follow_zone(&zone) {
+            if let Err(e) = snoozed.save() {
+                eprintln!("Failed to save snoozed list: {}", e);
+            }
+        }
+        snoozed.due(state.clock.unix_now())
+    };
+    if due.is_empty() {
+        return;
+    }
+
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(_) => return, // Not signed in; try again next tick
+    };
+    if let Err(e) = state
+        .rate_limiter
+        .check_background_rate_limit("snooze_return")
+    {
+        eprintln!("Deferring snoozed returns: {}", e);
+        return;
+    }
+
+    let gmail_client = GmailClient::new(&tokens);
+    let mut returned = Vec::new();
+    for entry in due {
+        match gmail_client.move_to_inbox(&entry.message_id).await {
+            Ok(_) => returned.push(entry),
+            // Deleted while snoozed: nothing left to return
+            Err(e) if is_not_found_error(e.as_ref()) => {
+                state.snoozed.lock().unwrap().remove(&entry.message_id);
+            }
+            Err(e) => eprintln!("Failed to return snoozed {}: {}", entry.message_id, e),
+        }
+    }
+
+    {
+        let mut snoozed = state.snoozed.lock().unwrap();
+        for entry in &returned {
+            snoozed.remove(&entry.message_id);
+        }
+        if let Err(e) = snoozed.save() {
+            eprintln!("Failed to save snoozed list: {}", e);
+        }
+    }
+    if !returned.is_empty() {
+        let _ = app.emit("snooze-returned", &returned);
+    }
+}
+
 #[tauri::command]
-async fn send_reply(
-    original_email_id: String,
-    reply_body: String,
+async fn get_focus_status(state: State<'_, AppState>) -> Result<FocusStatus, String> {
+    let focus_mode = state.settings.lock().unwrap().focus_mode.clone();
+    Ok(state
+        .focus_buffer
+        .lock()
+        .unwrap()
+        .status(state.clock.unix_now(), &focus_mode))
+}
+
+/// Release held mail now instead of waiting for the next scheduled batch
+#[tauri::command]
+async fn deliver_focus_batch(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let delivered = state
+        .focus_buffer
+        .lock()
+        .unwrap()
+        .deliver(state.clock.unix_now());
+    update_widget_summary(&state, |summary| summary.record_new_mail(delivered.len()));
+    Ok(delivered)
+}
+
+#[tauri::command]
+async fn prepare_offline_bundle(
+    query: String,
+    days: u32,
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<OfflineBundleSummary, String> {
     // Check rate limit
-    state.rate_limiter.check_rate_limit("send_reply")?;
+    state
+        .rate_limiter
+        .check_rate_limit("prepare_offline_bundle")?;
     let tokens = match refresh_tokens_if_needed(&state).await {
         Ok(tokens) => tokens,
         Err(e) => return Err(format!("Authentication required: {}", e)),
@@ -426,111 +5634,546 @@ async fn send_reply(
 
     let gmail_client = GmailClient::new(&tokens);
 
-    // Get the original email to extract reply information
-    let original_email = gmail_client
-        .get_message(&original_email_id)
+    offline::prepare_bundle(
+        &gmail_client,
+        &state.message_cache,
+        &bulk::new_operation_id(),
+        &query,
+        days,
+        |progress| {
+            let _ = app.emit("offline-bundle-progress", progress);
+        },
+    )
+    .await
+    .map_err(|e| format!("Failed to prepare offline bundle: {}", e))
+}
+
+/// Unpin offline mail so normal cache eviction applies again
+#[tauri::command]
+async fn release_offline_bundle(state: State<'_, AppState>) -> Result<usize, String> {
+    let mut cache = state.message_cache.lock().unwrap();
+    let released = cache.unpin_all();
+    cache.save_index()?;
+    Ok(released)
+}
+
+#[tauri::command]
+async fn analyze_cleanup(state: State<'_, AppState>) -> Result<Vec<CleanupProposal>, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("analyze_cleanup")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    cleanup::analyze(&gmail_client)
         .await
-        .map_err(|e| format!("Failed to get original email: {}", e))?;
+        .map_err(|e| format!("Failed to analyze mailbox: {}", e))
+}
 
-    // Extract sender email from "From" header
-    let original_sender = original_email.get_from();
+#[tauri::command]
+async fn bulk_action_by_query(
+    query: String,
+    action: BulkAction,
+    dry_run: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<BulkSummary, String> {
+    // Check rate limit
+    state
+        .rate_limiter
+        .check_rate_limit("bulk_action_by_query")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
 
-    // Parse email from "Name <email@domain.com>" format
-    let to_email = if let Some(start) = original_sender.find('<') {
-        if let Some(end) = original_sender.find('>') {
-            original_sender[start + 1..end].to_string()
-        } else {
-            original_sender
-        }
-    } else {
-        original_sender
+    let gmail_client = GmailClient::new(&tokens);
+
+    let message_ids = gmail_client
+        .list_all_message_ids(&query, bulk::MAX_BULK_MESSAGES)
+        .await
+        .map_err(|e| format!("Failed to list messages: {}", e))?;
+
+    if dry_run.unwrap_or(false) {
+        return Ok(BulkSummary::preview(
+            &bulk::new_operation_id(),
+            &message_ids,
+        ));
+    }
+    Ok(run_bulk_and_record(&app, &state, &gmail_client, &message_ids, action).await)
+}
+
+/// Apply an action message by message, with an outcome for each in the
+/// summary; a dry run only previews the selection
+#[tauri::command]
+async fn bulk_modify_emails(
+    message_ids: Vec<String>,
+    action: BulkAction,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<BulkSummary, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("bulk_modify_emails")?;
+    check_bulk_selection(&message_ids)?;
+    let operation_id = bulk::new_operation_id();
+    if dry_run.unwrap_or(false) {
+        return Ok(BulkSummary::preview(&operation_id, &message_ids));
+    }
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
     };
 
-    // Create reply subject
-    let original_subject = original_email.get_subject();
-    let reply_subject = if original_subject.starts_with("Re: ") {
-        original_subject
-    } else {
-        format!("Re: {}", original_subject)
+    let gmail_client = GmailClient::new(&tokens);
+
+    let actions = std::slice::from_ref(&action);
+    let before = read_labels_before(&gmail_client, &message_ids).await;
+    let outcomes = bulk::modify_each(&gmail_client, &message_ids, actions).await;
+    let (mut summary, succeeded_ids) = BulkSummary::from_outcomes(&operation_id, outcomes);
+    let changed_ids = bulk::actually_changed(succeeded_ids, actions, &before);
+    record_bulk_run(&state, &mut summary, changed_ids, actions);
+    Ok(summary)
+}
+
+/// Apply an action to a selection of up to `MAX_BULK_MESSAGES` messages,
+/// e.g. hundreds picked for a cleanup. Runs in batchModify chunks with
+/// "bulk-progress" after each, backs off when Gmail rate limits a chunk, and
+/// returns a summary whose `undo_id` reverts what succeeded; a dry run only
+/// previews the selection.
+#[tauri::command]
+async fn bulk_operation(
+    message_ids: Vec<String>,
+    action: BulkAction,
+    dry_run: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<BulkSummary, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("bulk_operation")?;
+    let mut message_ids = message_ids;
+    let mut seen = std::collections::HashSet::new();
+    message_ids.retain(|id| seen.insert(id.clone()));
+    check_bulk_selection(&message_ids)?;
+    if dry_run.unwrap_or(false) {
+        return Ok(BulkSummary::preview(
+            &bulk::new_operation_id(),
+            &message_ids,
+        ));
+    }
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
     };
 
-    // Get message threading headers
-    let message_id = original_email.get_message_id();
-    let references = original_email.get_references();
+    let gmail_client = GmailClient::new(&tokens);
+
+    Ok(run_bulk_and_record(&app, &state, &gmail_client, &message_ids, action).await)
+}
 
-    // Build references chain for proper threading
-    let reply_references = match (message_id.as_ref(), references.as_ref()) {
-        (Some(msg_id), Some(refs)) => Some(format!("{} {}", refs, msg_id)),
-        (Some(msg_id), None) => Some(msg_id.clone()),
-        _ => None,
+fn check_bulk_selection(message_ids: &[String]) -> Result<(), String> {
+    if message_ids.len() > bulk::MAX_BULK_MESSAGES {
+        return Err(format!(
+            "Too many messages selected: {} (max {})",
+            message_ids.len(),
+            bulk::MAX_BULK_MESSAGES
+        ));
+    }
+    Ok(())
+}
+
+/// Run an action over messages in chunks with "bulk-progress" events, then
+/// record the run like `record_bulk_run`
+async fn run_bulk_and_record(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    gmail_client: &GmailClient,
+    message_ids: &[String],
+    action: BulkAction,
+) -> BulkSummary {
+    let actions = std::slice::from_ref(&action);
+    let before = read_labels_before(gmail_client, message_ids).await;
+    let (mut summary, succeeded_ids) = bulk::run_bulk_action(
+        gmail_client,
+        &bulk::new_operation_id(),
+        message_ids,
+        actions,
+        |progress| {
+            let _ = app.emit("bulk-progress", progress);
+        },
+    )
+    .await;
+    let changed_ids = bulk::actually_changed(succeeded_ids, actions, &before);
+    record_bulk_run(state, &mut summary, changed_ids, actions);
+    summary
+}
+
+/// Labels before a bulk run; without them undo reverts every message the run
+/// touched
+async fn read_labels_before(
+    gmail_client: &GmailClient,
+    message_ids: &[String],
+) -> std::collections::HashMap<String, Vec<String>> {
+    bulk::labels_before(gmail_client, message_ids)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to read labels before bulk run: {}", e);
+            std::collections::HashMap::new()
+        })
+}
+
+/// Note trash moves among the changed messages and keep them for undo
+fn record_bulk_run(
+    state: &AppState,
+    summary: &mut BulkSummary,
+    changed_ids: Vec<String>,
+    actions: &[BulkAction],
+) {
+    track_bulk_trash(state, actions, &changed_ids);
+    state
+        .undo_history
+        .lock()
+        .unwrap()
+        .record(summary, changed_ids, actions);
+}
+
+#[tauri::command]
+async fn undo_bulk_action(
+    undo_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<BulkSummary, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("undo_bulk_action")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
     };
 
-    // Send the reply
-    match gmail_client
-        .send_email(
-            &to_email,
-            &reply_subject,
-            &reply_body,
-            message_id.as_deref(),
-            reply_references.as_deref(),
-            Some(&original_email.thread_id),
-        )
+    let entry = state
+        .undo_history
+        .lock()
+        .unwrap()
+        .take(&undo_id)
+        .ok_or("Nothing to undo for this operation")?;
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let operation_id = bulk::new_operation_id();
+    let (summary, changed_ids) = bulk::run_bulk_action(
+        &gmail_client,
+        &operation_id,
+        &entry.message_ids,
+        &entry.actions,
+        |progress| {
+            let _ = app.emit("bulk-progress", progress);
+        },
+    )
+    .await;
+    track_bulk_trash(&state, &entry.actions, &changed_ids);
+
+    Ok(summary)
+}
+
+#[tauri::command]
+async fn get_rules(state: State<'_, AppState>) -> Result<Vec<Rule>, String> {
+    Ok(state.rules.lock().unwrap().rules.clone())
+}
+
+#[tauri::command]
+async fn save_rule(rule: Rule, state: State<'_, AppState>) -> Result<Rule, String> {
+    let mut rules = state.rules.lock().unwrap();
+    let saved = rules.upsert(rule);
+    rules.save()?;
+    Ok(saved)
+}
+
+#[tauri::command]
+async fn delete_rule(rule_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let mut rules = state.rules.lock().unwrap();
+    if !rules.remove(&rule_id) {
+        return Err("Rule not found".to_string());
+    }
+    rules.save()
+}
+
+#[tauri::command]
+async fn list_send_as_aliases(state: State<'_, AppState>) -> Result<Vec<SendAsAlias>, String> {
+    // Check rate limit
+    state
+        .rate_limiter
+        .check_rate_limit("list_send_as_aliases")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    GmailClient::new(&tokens)
+        .list_send_as()
         .await
-    {
-        Ok(message_id) => Ok(format!(
-            "Reply sent successfully! Message ID: {}",
-            message_id
-        )),
-        Err(e) => Err(format!("Failed to send reply: {}", e)),
+        .map_err(|e| format!("Failed to load send-as aliases: {}", e))
+}
+
+/// The Gmail signature for `send_as_email`, or for the default address when omitted
+#[tauri::command]
+async fn get_signature(
+    send_as_email: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<Signature>, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("get_signature")?;
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Authentication required: {}", e)),
+    };
+
+    let aliases = GmailClient::new(&tokens)
+        .list_send_as()
+        .await
+        .map_err(|e| format!("Failed to load send-as settings: {}", e))?;
+    update_account_snapshot(&state, |s| s.send_as = aliases.clone());
+
+    Ok(Signature::for_sender(&aliases, send_as_email.as_deref()))
+}
+
+#[tauri::command]
+async fn get_alias_rules(state: State<'_, AppState>) -> Result<Vec<AliasRule>, String> {
+    Ok(state.alias_rules.lock().unwrap().rules.clone())
+}
+
+#[tauri::command]
+async fn save_alias_rule(rule: AliasRule, state: State<'_, AppState>) -> Result<AliasRule, String> {
+    if rule.recipient.is_none() && rule.label_id.is_none() {
+        return Err("An alias rule needs a recipient or a label to match".to_string());
+    }
+
+    let mut rules = state.alias_rules.lock().unwrap();
+    let saved = rules.upsert(rule);
+    rules.save()?;
+    Ok(saved)
+}
+
+#[tauri::command]
+async fn delete_alias_rule(rule_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let mut rules = state.alias_rules.lock().unwrap();
+    if !rules.remove(&rule_id) {
+        return Err("Alias rule not found".to_string());
     }
+    rules.save()
 }
 
 #[tauri::command]
-async fn check_for_new_emails_since_last_check(
+async fn run_rule_now(
+    rule_id: String,
+    scope: RuleScope,
+    dry_run: Option<bool>,
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<Vec<String>, String> {
-    // Get auth tokens
+) -> Result<BulkSummary, String> {
+    // Check rate limit
+    state.rate_limiter.check_rate_limit("run_rule_now")?;
     let tokens = match refresh_tokens_if_needed(&state).await {
         Ok(tokens) => tokens,
         Err(e) => return Err(format!("Authentication required: {}", e)),
     };
 
-    // Get last check time
-    let last_check = {
-        let guard = state.last_check_time.lock().unwrap();
-        guard.clone()
+    let rule = state
+        .rules
+        .lock()
+        .unwrap()
+        .get(&rule_id)
+        .cloned()
+        .ok_or("Rule not found")?;
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    let (mut summary, changed_ids) = rules::run_rule(
+        &gmail_client,
+        &rule,
+        &scope,
+        dry_run.unwrap_or(false),
+        |progress| {
+            let _ = app.emit("bulk-progress", progress);
+        },
+    )
+    .await
+    .map_err(|e| format!("Failed to run rule: {}", e))?;
+
+    state
+        .undo_history
+        .lock()
+        .unwrap()
+        .record(&mut summary, changed_ids, &rule.actions);
+
+    Ok(summary)
+}
+
+/// Run scheduled rules that are due, within the rate limiter's background tier
+async fn run_due_rules(app: &tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    let now = schedule_now(&state);
+
+    let due: Vec<Rule> = state
+        .rules
+        .lock()
+        .unwrap()
+        .rules
+        .iter()
+        .filter(|r| r.is_due(&now))
+        .cloned()
+        .collect();
+
+    if due.is_empty() {
+        return;
+    }
+
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(_) => return, // Not signed in; try again next tick
     };
 
-    // Create Gmail client
     let gmail_client = GmailClient::new(&tokens);
 
-    // Check for new emails
-    match gmail_client
-        .check_for_new_emails(last_check.as_deref())
-        .await
-    {
-        Ok(new_email_ids) => {
-            // Update last check time to current Unix timestamp
-            let current_time = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-                .to_string();
+    for rule in due {
+        // Out of background budget: leave the rest for the next tick
+        if let Err(e) = state
+            .rate_limiter
+            .check_background_rate_limit("scheduled_rule")
+        {
+            eprintln!("Deferring scheduled rules: {}", e);
+            break;
+        }
 
-            *state.last_check_time.lock().unwrap() = Some(current_time);
+        let scope = rule
+            .schedule
+            .as_ref()
+            .map(|s| s.scope.clone())
+            .unwrap_or(RuleScope::Inbox);
 
-            Ok(new_email_ids)
+        match rules::run_rule(&gmail_client, &rule, &scope, false, |_| {}).await {
+            Ok((summary, _)) => {
+                let _ = app.emit("rule-run-completed", &summary);
+            }
+            Err(e) => eprintln!("Scheduled rule '{}' failed: {}", rule.name, e),
         }
-        Err(e) => {
-            eprintln!("Error checking for new emails: {}", e);
-            Err(e.to_string())
+
+        {
+            let mut rules = state.rules.lock().unwrap();
+            rules.mark_scheduled_run(&rule.id, &now);
+            if let Err(e) = rules.save() {
+                eprintln!("Failed to save rule run state: {}", e);
+            }
+        }
+    }
+}
+
+/// Push edited compose sessions to their Gmail drafts
+async fn autosave_compose_sessions(app: &tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    let pending = state.compose.lock().unwrap().pending_autosave();
+    if pending.is_empty() {
+        return;
+    }
+
+    let tokens = match refresh_tokens_if_needed(&state).await {
+        Ok(tokens) => tokens,
+        Err(_) => return, // Not signed in; sessions are still saved locally
+    };
+
+    let gmail_client = GmailClient::new(&tokens);
+
+    for session in pending {
+        if let Err(e) = state
+            .rate_limiter
+            .check_background_rate_limit("compose_autosave")
+        {
+            eprintln!("Deferring draft autosave: {}", e);
+            break;
+        }
+
+        if let Err(e) = save_session_draft(&state, &gmail_client, &session).await {
+            eprintln!("Failed to autosave draft for {}: {}", session.id, e);
+        }
+    }
+}
+
+/// Push a session's current content to its Gmail draft, creating the draft on
+/// first save. Returns the draft id, or None when the session was discarded
+/// while the save was in flight.
+async fn save_session_draft(
+    state: &AppState,
+    gmail_client: &GmailClient,
+    session: &ComposeSession,
+) -> Result<Option<String>, String> {
+    let email = session.to_outgoing()?;
+    let result = match &session.draft_id {
+        Some(draft_id) => {
+            gmail_client
+                .update_draft(draft_id, &email, session.thread_id())
+                .await
         }
+        None => gmail_client.create_draft(&email, session.thread_id()).await,
+    };
+    let draft = result.map_err(|e| format!("Failed to save draft: {}", e))?;
+
+    let still_open = {
+        let mut store = state.compose.lock().unwrap();
+        let still_open = store.mark_saved(&session.id, session.revision, draft.id.clone());
+        save_compose_store(&store);
+        still_open
+    };
+
+    // Discarded while saving: don't leave the draft behind
+    if !still_open {
+        let _ = gmail_client.delete_draft(&draft.id).await;
+        return Ok(None);
     }
+    Ok(Some(draft.id))
+}
+
+fn spawn_compose_autosave(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            COMPOSE_AUTOSAVE_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+            autosave_compose_sessions(&app).await;
+            send_deferred_sessions(&app).await;
+        }
+    });
+}
+
+fn spawn_snooze_scheduler(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(SNOOZE_CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            return_due_snoozes(&app).await;
+        }
+    });
+}
+
+fn spawn_rule_scheduler(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(RULE_SCHEDULER_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            run_due_rules(&app).await;
+            send_due_digest(&app).await;
+            refresh_stale_storage_status(&app).await;
+        }
+    });
 }
 
 fn main() {
     // Load saved tokens on startup
     let saved_tokens = load_tokens();
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_updater::Builder::new().build())
@@ -540,10 +6183,105 @@ fn main() {
             gmail_auth: Mutex::new(None),
             auth_tokens: Mutex::new(saved_tokens),
             last_check_time: Mutex::new(None),
-            rate_limiter: RateLimiter::new(),
+            last_history_id: Mutex::new(None),
+            push_status: Mutex::new(PushStatus::default()),
+            poll_schedule: Mutex::new(PollSchedule::default()),
+            sync_wake: tokio::sync::Notify::new(),
+            rate_limiter: RateLimiter::with_clock(clock.clone()),
+            clock,
+            settings: Mutex::new(BackendSettings::load()),
+            undo_history: Mutex::new(UndoHistory::default()),
+            rules: Mutex::new(RuleSet::load()),
+            widget_summary: Mutex::new(WidgetSummary::load()),
+            focus_buffer: Mutex::new(FocusBuffer::default()),
+            message_cache: Mutex::new(MessageCache::open_default()),
+            profile_cache: Mutex::new(ProfileCache::default()),
+            compose: Mutex::new(ComposeStore::load()),
+            send_log: Mutex::new(SendLog::load()),
+            send_receipts: Mutex::new(ReceiptLog::load()),
+            outbound_audit: Mutex::new(AuditLog::load()),
+            recent_recipients: Mutex::new(RecipientStore::load()),
+            alias_rules: Mutex::new(AliasRuleSet::load()),
+            attachment_index: Mutex::new(AttachmentIndex::load()),
+            digest_state: Mutex::new(DigestState::load()),
+            onboarding: Mutex::new(OnboardingState::load()),
+            account_snapshot: Mutex::new(AccountSnapshot::load()),
+            watched_threads: Mutex::new(WatchList::load()),
+            muted_threads: Mutex::new(MuteList::load()),
+            mailing_lists: Mutex::new(ListDirectory::load()),
+            newsletter_feeds: Mutex::new(FeedList::load()),
+            sla: Mutex::new(SlaTracker::load()),
+            pending_actions: Mutex::new(PendingActions::load()),
+            snoozed: Mutex::new(SnoozeList::load()),
+            spam_review: Mutex::new(SpamReview::load()),
+            trash_log: Mutex::new(TrashLog::load()),
+            secrets: Mutex::new(SecretVault::load()),
+        })
+        .setup(|app| {
+            spawn_rule_scheduler(app.handle().clone());
+            spawn_compose_autosave(app.handle().clone());
+            spawn_sync_scheduler(app.handle().clone());
+            spawn_snooze_scheduler(app.handle().clone());
+            tauri::async_runtime::spawn(serve_cached_inbox(app.handle().clone()));
+
+            // Pick up an onboarding that was interrupted, e.g. by quitting mid-history
+            let resume_onboarding = {
+                let state = app.state::<AppState>();
+                let onboarding = state.onboarding.lock().unwrap();
+                !onboarding.completed_stages.is_empty() && !onboarding.pending_stages().is_empty()
+            };
+            if resume_onboarding {
+                tauri::async_runtime::spawn(run_onboarding(app.handle().clone()));
+            }
+
+            #[cfg(any(target_os = "macos", windows))]
+            {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = automation::serve(handle).await {
+                        eprintln!("Automation listener stopped: {}", e);
+                    }
+                });
+            }
+
+            #[cfg(feature = "local-api")]
+            {
+                let local_api = app
+                    .state::<AppState>()
+                    .settings
+                    .lock()
+                    .unwrap()
+                    .local_api
+                    .clone();
+                if local_api.enabled {
+                    let handle = app.handle().clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = local_api::serve(handle, local_api.port).await {
+                            eprintln!("Local API stopped: {}", e);
+                        }
+                    });
+                }
+            }
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            let state = window.app_handle().state::<AppState>();
+            match event {
+                // Coming back to the app resets the backoff and checks right away
+                tauri::WindowEvent::Focused(focused)
+                    if state.poll_schedule.lock().unwrap().set_focused(*focused) =>
+                {
+                    state.sync_wake.notify_one()
+                }
+                // Write out cache fetches still waiting for their batch
+                tauri::WindowEvent::Destroyed => state.message_cache.lock().unwrap().flush(),
+                _ => {}
+            }
         })
         .invoke_handler(tauri::generate_handler![
             get_emails,
+            get_cached_inbox,
+            get_account_profile,
             get_inbox_stats,
             check_for_updates,
             install_update,
@@ -552,11 +6290,135 @@ fn main() {
             get_auth_status,
             open_url,
             logout_gmail,
+            remove_account,
             get_email_content,
+            load_full_message,
+            get_attachment,
+            get_sender_profile,
+            search_contacts,
+            suggest_recipients,
+            list_stored_secrets,
+            store_secret,
+            delete_stored_secret,
+            download_attachment,
+            save_attachment,
+            export_message_eml,
+            export_message_print,
+            save_email_to_notes,
+            export_mbox,
+            import_messages,
+            index_cached_attachments,
+            preview_weekly_digest,
+            send_weekly_digest_now,
+            get_storage_status,
+            get_available_actions,
+            start_onboarding,
+            get_onboarding_status,
+            defer_history_sync,
+            resume_history_sync,
+            get_account_snapshot,
+            get_labels,
+            create_label,
+            rename_label,
+            delete_label,
+            list_filters,
+            create_filter,
+            delete_filter,
+            list_forwarding_addresses,
+            get_auto_forwarding,
+            set_auto_forwarding,
+            get_conversation,
+            get_conversations,
+            list_mailbox,
+            search_emails,
+            search_emails_structured,
+            get_backend_settings,
+            update_backend_settings,
             check_for_new_emails_since_last_check,
+            get_push_status,
+            watch_thread,
+            unwatch_thread,
+            list_watched_threads,
+            mute_thread,
+            unmute_thread,
+            list_muted_threads,
+            claim_thread,
+            release_thread_claim,
+            get_thread_claims,
+            list_claimed_threads,
+            get_mailing_lists,
+            generate_feed,
+            list_newsletter_feeds,
+            remove_newsletter_feed,
+            archive_mailing_list,
+            get_code_notifications,
+            get_sla_report,
+            snooze_email,
+            get_schedule_presets,
+            unsnooze_email,
+            list_snoozed,
+            get_focus_status,
+            deliver_focus_batch,
             mark_email_as_read,
             mark_email_as_unread,
-            send_reply
+            mark_thread_as_read,
+            mark_thread_as_unread,
+            archive_email,
+            report_spam,
+            report_phishing,
+            mark_not_spam,
+            get_probable_false_positives,
+            get_notification_styles,
+            mark_as_important,
+            mark_not_important,
+            get_important,
+            unsubscribe_from_sender,
+            get_emails_by_category,
+            trash_email,
+            untrash_email,
+            get_pending_actions,
+            retry_action,
+            discard_action,
+            delete_email_permanently,
+            get_trash_countdowns,
+            empty_trash,
+            send_reply,
+            rsvp_invite,
+            forward_email,
+            create_compose_session,
+            get_compose_session,
+            list_compose_sessions,
+            update_compose_session,
+            add_compose_attachment,
+            discard_compose_session,
+            list_drafts,
+            get_draft,
+            resume_draft,
+            save_compose_draft,
+            send_draft,
+            delete_draft,
+            check_reply_freshness,
+            send_compose_session,
+            preflight_compose_session,
+            get_send_quota,
+            export_send_log,
+            get_outbound_audit_log,
+            prepare_offline_bundle,
+            release_offline_bundle,
+            analyze_cleanup,
+            bulk_action_by_query,
+            bulk_modify_emails,
+            bulk_operation,
+            undo_bulk_action,
+            get_rules,
+            save_rule,
+            delete_rule,
+            list_send_as_aliases,
+            get_signature,
+            get_alias_rules,
+            save_alias_rule,
+            delete_alias_rule,
+            run_rule_now
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,874 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::{
+    is_not_found_error, GmailClient, GmailMessage, LabelChange, MessageAttachment,
+};
+use crate::local_store::{app_data_path, load_json, save_json};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const CACHE_DIR: &str = "cache";
+const INDEX_FILE: &str = "index.json";
+
+/// Unpinned entries are evicted, least recently used first, once the cache grows past this
+pub const DEFAULT_CACHE_BUDGET_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Fetches stored before the index is evicted and written out again
+const INDEX_SAVE_BATCH: usize = 20;
+
+/// Bookkeeping for one cached message and the attachments stored alongside it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub size_bytes: u64,
+    /// Indexes into `GmailMessage::get_attachments` that have been downloaded
+    #[serde(default)]
+    pub attachments: Vec<usize>,
+    /// SHA-256 of the stored message JSON, checked on every read
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// SHA-256 of each stored attachment, keyed like `attachments`
+    #[serde(default)]
+    pub attachment_hashes: HashMap<usize, String>,
+    /// Pinned entries survive eviction, e.g. mail prepared for offline use
+    #[serde(default)]
+    pub pinned: bool,
+    /// Monotonic access counter used for LRU ordering
+    #[serde(default)]
+    pub last_access: u64,
+    /// Lowercased From address, so one sender's mail can be found without
+    /// reading every message
+    #[serde(default)]
+    pub sender: Option<String>,
+}
+
+/// Result of reading a message from the cache
+#[derive(Debug)]
+pub enum CacheRead {
+    Hit(Box<GmailMessage>),
+    Miss,
+    /// The stored copy failed verification and was dropped; refetch it
+    Corrupted {
+        was_pinned: bool,
+    },
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheIndex {
+    entries: HashMap<String, CacheEntry>,
+    access_counter: u64,
+    #[serde(default)]
+    account_id: Option<String>,
+}
+
+/// On-disk cache of full messages and their attachments.
+///
+/// Messages are stored as `<id>.json` and attachments as `<id>.<index>.bin`,
+/// keyed by position rather than Gmail attachment id because Gmail issues a
+/// new attachment id on every fetch. Every file's SHA-256 is recorded in the
+/// index so disk corruption is caught on read instead of shown to the user.
+pub struct MessageCache {
+    root: PathBuf,
+    index: CacheIndex,
+    /// Fetches stored since the index was last written by `flush`
+    unsaved_fetches: usize,
+}
+
+impl MessageCache {
+    pub fn open(root: PathBuf) -> Self {
+        std::fs::create_dir_all(&root).ok();
+        let index = load_json(&root.join(INDEX_FILE));
+        MessageCache {
+            root,
+            index,
+            unsaved_fetches: 0,
+        }
+    }
+
+    pub fn open_default() -> Self {
+        Self::open(app_data_path(CACHE_DIR))
+    }
+
+    pub fn save_index(&self) -> Result<(), String> {
+        save_json(&self.root.join(INDEX_FILE), &self.index)
+    }
+
+    /// Count a message or attachment stored from Gmail, flushing once every
+    /// `INDEX_SAVE_BATCH` of them rather than rewriting the index per fetch.
+    /// Files written since the last flush are simply refetched if the app
+    /// stops before the next one.
+    fn note_fetch(&mut self) {
+        self.unsaved_fetches += 1;
+        if self.unsaved_fetches >= INDEX_SAVE_BATCH {
+            self.flush();
+        }
+    }
+
+    /// Evict down to the budget and write the index if fetches are pending.
+    /// A failed write is logged and retried with the next batch.
+    pub fn flush(&mut self) {
+        if self.unsaved_fetches == 0 {
+            return;
+        }
+        self.evict_to(DEFAULT_CACHE_BUDGET_BYTES);
+        match self.save_index() {
+            Ok(()) => self.unsaved_fetches = 0,
+            Err(e) => eprintln!("Failed to save message cache index: {}", e),
+        }
+    }
+
+    fn message_path(&self, message_id: &str) -> PathBuf {
+        self.root.join(format!("{}.json", message_id))
+    }
+
+    fn attachment_path(&self, message_id: &str, index: usize) -> PathBuf {
+        self.root.join(format!("{}.{}.bin", message_id, index))
+    }
+
+    fn touch(&mut self, message_id: &str) {
+        self.index.access_counter += 1;
+        let counter = self.index.access_counter;
+        if let Some(entry) = self.index.entries.get_mut(message_id) {
+            entry.last_access = counter;
+        }
+    }
+
+    pub fn contains(&self, message_id: &str) -> bool {
+        self.index.entries.contains_key(message_id)
+    }
+
+    pub fn entry(&self, message_id: &str) -> Option<&CacheEntry> {
+        self.index.entries.get(message_id)
+    }
+
+    /// Read a message, verifying it against the hash recorded when it was stored
+    pub fn read_message(&mut self, message_id: &str) -> CacheRead {
+        let expected_hash = match self.entry(message_id) {
+            Some(entry) => entry.content_hash.clone(),
+            None => return CacheRead::Miss,
+        };
+
+        let message = std::fs::read(self.message_path(message_id))
+            .ok()
+            .filter(|bytes| expected_hash.as_deref() == Some(sha256_hex(bytes).as_str()))
+            .and_then(|bytes| serde_json::from_slice::<GmailMessage>(&bytes).ok());
+
+        match message {
+            Some(message) => {
+                self.touch(message_id);
+                CacheRead::Hit(Box::new(message))
+            }
+            None => {
+                // Missing, corrupt, or stored before hashing; drop it so it gets refetched
+                let was_pinned = self.entry(message_id).is_some_and(|e| e.pinned);
+                eprintln!("Cached message {} failed verification", message_id);
+                self.remove(message_id);
+                CacheRead::Corrupted { was_pinned }
+            }
+        }
+    }
+
+    /// Every cached message that passes verification, without counting as an
+    /// access for eviction; used for local aggregates over the cache
+    pub fn scan_messages(&self) -> Vec<GmailMessage> {
+        self.index
+            .entries
+            .keys()
+            .filter_map(|message_id| self.verified_message(message_id))
+            .collect()
+    }
+
+    /// Verified cached messages from `sender`, without counting as an access.
+    /// Only entries recorded with that sender are read; ones cached before
+    /// senders were recorded are read once to fill theirs in, kept in the
+    /// index from its next save.
+    pub fn messages_from(&mut self, sender: &str) -> Vec<GmailMessage> {
+        let sender = sender.to_ascii_lowercase();
+        let candidates: Vec<String> = self
+            .index
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.sender.as_ref().is_none_or(|s| *s == sender))
+            .map(|(message_id, _)| message_id.clone())
+            .collect();
+
+        let mut messages = Vec::new();
+        for message_id in candidates {
+            let Some(message) = self.verified_message(&message_id) else {
+                continue;
+            };
+            let from = message.get_from_address().to_ascii_lowercase();
+            if let Some(entry) = self.index.entries.get_mut(&message_id) {
+                entry.sender = Some(from.clone());
+            }
+            if from == sender {
+                messages.push(message);
+            }
+        }
+        messages
+    }
+
+    fn verified_message(&self, message_id: &str) -> Option<GmailMessage> {
+        let entry = self.entry(message_id)?;
+        let bytes = std::fs::read(self.message_path(message_id)).ok()?;
+        if entry.content_hash.as_deref() != Some(sha256_hex(&bytes).as_str()) {
+            return None;
+        }
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Store a full message, returning the bytes written
+    pub fn put_message(&mut self, message: &GmailMessage) -> Result<u64, String> {
+        let json = serde_json::to_vec(message)
+            .map_err(|e| format!("Failed to serialize message {}: {}", message.id, e))?;
+        write_file(&self.message_path(&message.id), &json)?;
+
+        let entry = self.index.entries.entry(message.id.clone()).or_default();
+        entry.content_hash = Some(sha256_hex(&json));
+        entry.sender = Some(message.get_from_address().to_ascii_lowercase());
+        let attachment_bytes: u64 = entry
+            .attachments
+            .iter()
+            .filter_map(|index| {
+                std::fs::metadata(self.root.join(format!("{}.{}.bin", message.id, index))).ok()
+            })
+            .map(|m| m.len())
+            .sum();
+        entry.size_bytes = json.len() as u64 + attachment_bytes;
+        self.touch(&message.id);
+
+        Ok(json.len() as u64)
+    }
+
+    /// Bring a cached message's labels in line with a change made elsewhere,
+    /// returning false when the message isn't cached
+    pub fn apply_label_change(&mut self, change: &LabelChange) -> Result<bool, String> {
+        let mut message = match self.read_message(&change.message_id) {
+            CacheRead::Hit(message) => message,
+            _ => return Ok(false),
+        };
+        change.apply_to(message.label_ids.get_or_insert_with(Vec::new));
+        self.put_message(&message)?;
+        Ok(true)
+    }
+
+    /// Whether a stored attachment still matches its recorded hash
+    pub fn verify_attachment(&self, message_id: &str, index: usize) -> bool {
+        let expected = match self
+            .entry(message_id)
+            .and_then(|e| e.attachment_hashes.get(&index))
+        {
+            Some(hash) => hash,
+            None => return false,
+        };
+
+        std::fs::read(self.attachment_path(message_id, index))
+            .is_ok_and(|bytes| sha256_hex(&bytes) == *expected)
+    }
+
+    /// Read a stored attachment, dropping it if it no longer matches its hash
+    pub fn read_attachment(&mut self, message_id: &str, index: usize) -> Option<Vec<u8>> {
+        let expected = self
+            .entry(message_id)?
+            .attachment_hashes
+            .get(&index)?
+            .clone();
+
+        match std::fs::read(self.attachment_path(message_id, index)) {
+            Ok(bytes) if sha256_hex(&bytes) == expected => {
+                self.touch(message_id);
+                Some(bytes)
+            }
+            _ => {
+                eprintln!(
+                    "Cached attachment {} of {} failed verification",
+                    index, message_id
+                );
+                if let Some(entry) = self.index.entries.get_mut(message_id) {
+                    entry.attachment_hashes.remove(&index);
+                }
+                None
+            }
+        }
+    }
+
+    /// Store attachment bytes for a message that is already cached
+    pub fn put_attachment(
+        &mut self,
+        message_id: &str,
+        index: usize,
+        data: &[u8],
+    ) -> Result<u64, String> {
+        if !self.contains(message_id) {
+            return Err(format!("Message {} is not cached", message_id));
+        }
+
+        write_file(&self.attachment_path(message_id, index), data)?;
+        if let Some(entry) = self.index.entries.get_mut(message_id) {
+            if !entry.attachments.contains(&index) {
+                entry.attachments.push(index);
+                entry.size_bytes += data.len() as u64;
+            }
+            entry.attachment_hashes.insert(index, sha256_hex(data));
+        }
+        self.touch(message_id);
+
+        Ok(data.len() as u64)
+    }
+
+    pub fn set_pinned(&mut self, message_id: &str, pinned: bool) {
+        if let Some(entry) = self.index.entries.get_mut(message_id) {
+            entry.pinned = pinned;
+        }
+    }
+
+    /// Unpin everything, returning how many entries were released
+    pub fn unpin_all(&mut self) -> usize {
+        let mut released = 0;
+        for entry in self.index.entries.values_mut().filter(|e| e.pinned) {
+            entry.pinned = false;
+            released += 1;
+        }
+        released
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.index.entries.values().map(|e| e.size_bytes).sum()
+    }
+
+    pub fn remove(&mut self, message_id: &str) {
+        if let Some(entry) = self.index.entries.remove(message_id) {
+            for index in entry.attachments {
+                let _ = std::fs::remove_file(self.attachment_path(message_id, index));
+            }
+        }
+        let _ = std::fs::remove_file(self.message_path(message_id));
+    }
+
+    /// Drop least recently used unpinned entries until the cache fits `budget_bytes`
+    pub fn evict_to(&mut self, budget_bytes: u64) -> Vec<String> {
+        let mut candidates: Vec<(String, u64)> = self
+            .index
+            .entries
+            .iter()
+            .filter(|(_, e)| !e.pinned)
+            .map(|(id, e)| (id.clone(), e.last_access))
+            .collect();
+        candidates.sort_by_key(|(_, last_access)| *last_access);
+
+        let mut evicted = Vec::new();
+        let mut total = self.total_size();
+        for (id, _) in candidates {
+            if total <= budget_bytes {
+                break;
+            }
+            total -= self.index.entries.get(&id).map_or(0, |e| e.size_bytes);
+            self.remove(&id);
+            evicted.push(id);
+        }
+        evicted
+    }
+}
+
+/// A message Gmail no longer has, deleted or moved out of reach since it was
+/// listed or cached
+#[derive(Debug)]
+pub struct MessageNotFound {
+    pub message_id: String,
+}
+
+impl std::fmt::Display for MessageNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Message {} no longer exists", self.message_id)
+    }
+}
+
+impl std::error::Error for MessageNotFound {}
+
+/// Why a command couldn't load a message, tagged by `kind` so the UI can
+/// drop a message deleted elsewhere instead of showing an error
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MessageError {
+    NotFound { message_id: String },
+    Failed { message: String },
+}
+
+impl std::fmt::Display for MessageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageError::NotFound { message_id } => {
+                write!(f, "Message {} no longer exists", message_id)
+            }
+            MessageError::Failed { message } => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for MessageError {
+    fn from(message: String) -> Self {
+        MessageError::Failed { message }
+    }
+}
+
+impl From<&str> for MessageError {
+    fn from(message: &str) -> Self {
+        MessageError::Failed {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error + Send + Sync>> for MessageError {
+    fn from(error: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        match error.downcast::<MessageNotFound>() {
+            Ok(gone) => MessageError::NotFound {
+                message_id: gone.message_id,
+            },
+            Err(error) => MessageError::Failed {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+/// Fetch a message through the cache: verified cached copies are returned as-is,
+/// anything missing or corrupted is fetched from Gmail and stored again. A
+/// message Gmail no longer has is dropped from the cache and reported as
+/// `MessageNotFound`.
+pub async fn load_message(
+    gmail_client: &GmailClient,
+    cache: &Mutex<MessageCache>,
+    message_id: &str,
+) -> Result<GmailMessage, Box<dyn std::error::Error + Send + Sync>> {
+    let was_pinned = match cache.lock().unwrap().read_message(message_id) {
+        CacheRead::Hit(message) => return Ok(*message),
+        CacheRead::Miss => false,
+        CacheRead::Corrupted { was_pinned } => was_pinned,
+    };
+    fetch_message(gmail_client, cache, message_id, was_pinned).await
+}
+
+/// Fetch a message from Gmail into the cache, pinned again if the copy it
+/// replaces was
+async fn fetch_message(
+    gmail_client: &GmailClient,
+    cache: &Mutex<MessageCache>,
+    message_id: &str,
+    was_pinned: bool,
+) -> Result<GmailMessage, Box<dyn std::error::Error + Send + Sync>> {
+    let message = match gmail_client.get_message(message_id).await {
+        Ok(message) => message,
+        Err(e) => return Err(forget_if_gone(cache, message_id, e)),
+    };
+
+    let mut cache = cache.lock().unwrap();
+    if let Err(e) = cache.put_message(&message) {
+        eprintln!("Failed to cache message {}: {}", message_id, e);
+    }
+    if was_pinned {
+        cache.set_pinned(message_id, true);
+    }
+    cache.note_fetch();
+
+    Ok(message)
+}
+
+/// Fetch a message for the reading pane. Cached copies are returned as-is;
+/// otherwise Gmail's size estimate is checked first, and a large message comes
+/// back as headers plus its plain-text body without being cached, leaving the
+/// whole message to `load_message`.
+pub async fn load_message_for_reading(
+    gmail_client: &GmailClient,
+    cache: &Mutex<MessageCache>,
+    message_id: &str,
+) -> Result<GmailMessage, Box<dyn std::error::Error + Send + Sync>> {
+    let was_pinned = match cache.lock().unwrap().read_message(message_id) {
+        CacheRead::Hit(message) => return Ok(*message),
+        CacheRead::Miss => false,
+        CacheRead::Corrupted { was_pinned } => was_pinned,
+    };
+
+    let metadata = match gmail_client.get_message_metadata(message_id).await {
+        Ok(metadata) => metadata,
+        Err(e) => return Err(forget_if_gone(cache, message_id, e)),
+    };
+    if !metadata.is_large() {
+        return fetch_message(gmail_client, cache, message_id, was_pinned).await;
+    }
+    let mut message = match gmail_client.get_message_text(message_id).await {
+        Ok(message) => message,
+        Err(e) => return Err(forget_if_gone(cache, message_id, e)),
+    };
+    message.size_estimate = message.size_estimate.or(metadata.size_estimate);
+    Ok(message)
+}
+
+/// Drop a message Gmail reports missing from the cache, turning the error into
+/// `MessageNotFound`; other errors pass through
+fn forget_if_gone(
+    cache: &Mutex<MessageCache>,
+    message_id: &str,
+    error: Box<dyn std::error::Error + Send + Sync>,
+) -> Box<dyn std::error::Error + Send + Sync> {
+    if !is_not_found_error(error.as_ref()) {
+        return error;
+    }
+    let mut cache = cache.lock().unwrap();
+    cache.remove(message_id);
+    if let Err(e) = cache.save_index() {
+        eprintln!("Failed to save message cache index: {}", e);
+    }
+    Box::new(MessageNotFound {
+        message_id: message_id.to_string(),
+    })
+}
+
+/// Keep attachment bytes downloaded for `message`, caching the message first
+/// if it isn't yet. Failures are only logged; the caller has the bytes.
+pub fn store_attachment(
+    cache: &Mutex<MessageCache>,
+    message: &GmailMessage,
+    index: usize,
+    data: &[u8],
+) {
+    let mut cache = cache.lock().unwrap();
+    if !cache.contains(&message.id) {
+        if let Err(e) = cache.put_message(message) {
+            eprintln!("Failed to cache message {}: {}", message.id, e);
+            return;
+        }
+    }
+    if let Err(e) = cache.put_attachment(&message.id, index, data) {
+        eprintln!("Failed to cache attachment of {}: {}", message.id, e);
+    }
+    cache.note_fetch();
+}
+
+/// An attachment fetched through the cache
+#[derive(Debug, Clone)]
+pub struct LoadedAttachment {
+    pub attachment: MessageAttachment,
+    pub thread_id: String,
+    /// Position in the message's attachments; None when the id didn't match the cached copy
+    pub index: Option<usize>,
+    pub data: Vec<u8>,
+}
+
+/// Fetch an attachment through the cache. Attachment ids change between fetches,
+/// so the id is resolved against the cached copy of the message; ids from
+/// some other fetch are downloaded directly without being stored.
+pub async fn load_attachment(
+    gmail_client: &GmailClient,
+    cache: &Mutex<MessageCache>,
+    message_id: &str,
+    attachment_id: &str,
+) -> Result<LoadedAttachment, Box<dyn std::error::Error + Send + Sync>> {
+    let message = load_message(gmail_client, cache, message_id).await?;
+    let attachments = message.get_attachments();
+    let position = attachments
+        .iter()
+        .position(|a| a.attachment_id == attachment_id);
+
+    let index = match position {
+        Some(index) => index,
+        None => {
+            let data = gmail_client
+                .get_attachment(message_id, attachment_id)
+                .await?;
+            let attachment = MessageAttachment {
+                message_id: message_id.to_string(),
+                attachment_id: attachment_id.to_string(),
+                filename: "attachment".to_string(),
+                mime_type: "application/octet-stream".to_string(),
+                size: data.len() as u64,
+            };
+            return Ok(LoadedAttachment {
+                attachment,
+                thread_id: message.thread_id,
+                index: None,
+                data,
+            });
+        }
+    };
+
+    let loaded = |data| LoadedAttachment {
+        attachment: attachments[index].clone(),
+        thread_id: message.thread_id.clone(),
+        index: Some(index),
+        data,
+    };
+    if let Some(data) = cache.lock().unwrap().read_attachment(message_id, index) {
+        return Ok(loaded(data));
+    }
+
+    let data = gmail_client
+        .get_attachment(message_id, attachment_id)
+        .await?;
+    let mut cache = cache.lock().unwrap();
+    if let Err(e) = cache.put_attachment(message_id, index, &data) {
+        eprintln!("Failed to cache attachment of {}: {}", message_id, e);
+    }
+    cache.note_fetch();
+
+    Ok(loaded(data))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), String> {
+    std::fs::write(path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+impl AccountScoped for MessageCache {
+    fn account_id(&self) -> Option<&str> {
+        self.index.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.index.account_id = Some(account_id.to_string());
+    }
+
+    /// Deletes every cached message and attachment along with the index
+    fn clear(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.root) {
+            eprintln!("Failed to remove {}: {}", self.root.display(), e);
+        }
+        std::fs::create_dir_all(&self.root).ok();
+        self.index = CacheIndex::default();
+        self.unsaved_fetches = 0;
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save_index()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::{MessageHeader, MessagePayload};
+
+    fn message(id: &str) -> GmailMessage {
+        GmailMessage {
+            id: id.to_string(),
+            thread_id: format!("thread_{}", id),
+            snippet: "x".repeat(100),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_message_errors_are_tagged_for_the_ui() {
+        let gone: Box<dyn std::error::Error + Send + Sync> = Box::new(MessageNotFound {
+            message_id: "m1".to_string(),
+        });
+        assert_eq!(
+            serde_json::to_value(MessageError::from(gone)).unwrap(),
+            serde_json::json!({ "kind": "not_found", "message_id": "m1" })
+        );
+
+        let failed: Box<dyn std::error::Error + Send + Sync> = "Gmail API error: 500".into();
+        assert_eq!(
+            serde_json::to_value(MessageError::from(failed)).unwrap(),
+            serde_json::json!({ "kind": "failed", "message": "Gmail API error: 500" })
+        );
+    }
+
+    #[test]
+    fn test_label_change_rewrites_cached_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MessageCache::open(dir.path().to_path_buf());
+        let mut unread = message("m1");
+        unread.label_ids = Some(vec!["INBOX".to_string(), "UNREAD".to_string()]);
+        cache.put_message(&unread).unwrap();
+
+        let change = LabelChange {
+            message_id: "m1".to_string(),
+            thread_id: "thread_m1".to_string(),
+            added_label_ids: vec!["STARRED".to_string()],
+            removed_label_ids: vec!["UNREAD".to_string()],
+        };
+        assert_eq!(cache.apply_label_change(&change), Ok(true));
+        match cache.read_message("m1") {
+            CacheRead::Hit(message) => assert_eq!(
+                message.label_ids,
+                Some(vec!["INBOX".to_string(), "STARRED".to_string()])
+            ),
+            other => panic!("expected a cache hit, got {:?}", other),
+        }
+
+        let uncached = LabelChange {
+            message_id: "m2".to_string(),
+            ..change
+        };
+        assert_eq!(cache.apply_label_change(&uncached), Ok(false));
+    }
+
+    #[test]
+    fn test_message_and_attachment_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MessageCache::open(dir.path().to_path_buf());
+
+        cache.put_message(&message("m1")).unwrap();
+        cache.put_attachment("m1", 0, b"attachment bytes").unwrap();
+        cache.save_index().unwrap();
+
+        let mut reopened = MessageCache::open(dir.path().to_path_buf());
+        match reopened.read_message("m1") {
+            CacheRead::Hit(message) => assert_eq!(message.thread_id, "thread_m1"),
+            other => panic!("expected a cache hit, got {:?}", other),
+        }
+        assert!(reopened.verify_attachment("m1", 0));
+        assert!(!reopened.verify_attachment("m1", 1));
+        assert_eq!(reopened.scan_messages().len(), 1);
+        assert_eq!(
+            reopened.read_attachment("m1", 0).as_deref(),
+            Some(&b"attachment bytes"[..])
+        );
+        assert_eq!(
+            reopened.entry("m1").unwrap().size_bytes,
+            std::fs::metadata(dir.path().join("m1.json")).unwrap().len() + 16
+        );
+        assert!(reopened.put_attachment("missing", 0, b"x").is_err());
+    }
+
+    #[test]
+    fn test_index_is_written_once_per_batch_of_fetches() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MessageCache::open(dir.path().to_path_buf());
+        let saved = || {
+            MessageCache::open(dir.path().to_path_buf())
+                .index
+                .entries
+                .len()
+        };
+
+        for i in 0..INDEX_SAVE_BATCH - 1 {
+            cache.put_message(&message(&format!("m{}", i))).unwrap();
+            cache.note_fetch();
+        }
+        assert_eq!(saved(), 0);
+
+        cache.put_message(&message("last")).unwrap();
+        cache.note_fetch();
+        assert_eq!(saved(), INDEX_SAVE_BATCH);
+
+        cache.put_message(&message("extra")).unwrap();
+        cache.note_fetch();
+        cache.flush();
+        assert_eq!(saved(), INDEX_SAVE_BATCH + 1);
+    }
+
+    #[test]
+    fn test_messages_from_reads_only_the_senders_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MessageCache::open(dir.path().to_path_buf());
+        let from = |id: &str, sender: &str| {
+            let mut message = message(id);
+            message.payload = Some(MessagePayload {
+                headers: Some(vec![MessageHeader {
+                    name: "From".to_string(),
+                    value: sender.to_string(),
+                }]),
+                ..Default::default()
+            });
+            message
+        };
+        cache
+            .put_message(&from("m1", "News <News@example.com>"))
+            .unwrap();
+        cache.put_message(&from("m2", "other@example.com")).unwrap();
+        cache.put_message(&from("m3", "news@example.com")).unwrap();
+        assert_eq!(
+            cache.entry("m1").unwrap().sender.as_deref(),
+            Some("news@example.com")
+        );
+
+        // An entry from before senders were recorded is read to find its sender
+        cache.index.entries.get_mut("m3").unwrap().sender = None;
+        std::fs::write(dir.path().join("m2.json"), b"unreadable").unwrap();
+        let mut ids: Vec<String> = cache
+            .messages_from("NEWS@example.com")
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["m1", "m3"]);
+        assert_eq!(
+            cache.entry("m3").unwrap().sender.as_deref(),
+            Some("news@example.com")
+        );
+    }
+
+    #[test]
+    fn test_eviction_is_lru_and_skips_pinned() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MessageCache::open(dir.path().to_path_buf());
+
+        for id in ["old", "pinned", "recent"] {
+            cache.put_message(&message(id)).unwrap();
+        }
+        cache.set_pinned("pinned", true);
+        cache.read_message("recent");
+        cache.read_message("old");
+
+        let one_entry = cache.entry("recent").unwrap().size_bytes;
+        let evicted = cache.evict_to(2 * one_entry);
+
+        assert_eq!(evicted, vec!["recent".to_string()]);
+        assert!(cache.contains("old"));
+        assert!(cache.contains("pinned"));
+        assert_eq!(cache.unpin_all(), 1);
+    }
+
+    #[test]
+    fn test_corruption_is_detected_and_entry_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MessageCache::open(dir.path().to_path_buf());
+
+        cache.put_message(&message("m1")).unwrap();
+        cache.put_attachment("m1", 0, b"original").unwrap();
+        cache.set_pinned("m1", true);
+
+        // Same length, different content: still valid JSON, so only the hash can tell
+        let path = dir.path().join("m1.json");
+        let tampered = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace("xxxx", "yyyy");
+        std::fs::write(&path, tampered).unwrap();
+        std::fs::write(dir.path().join("m1.0.bin"), b"corrupt!").unwrap();
+
+        assert!(!cache.verify_attachment("m1", 0));
+        assert!(cache.read_attachment("m1", 0).is_none());
+        assert!(matches!(
+            cache.read_message("m1"),
+            CacheRead::Corrupted { was_pinned: true }
+        ));
+        assert!(!cache.contains("m1"));
+        assert!(matches!(cache.read_message("m1"), CacheRead::Miss));
+    }
+
+    #[test]
+    fn test_purge_removes_only_the_owners_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MessageCache::open(dir.path().to_path_buf());
+        cache.put_message(&message("m1")).unwrap();
+        cache.put_attachment("m1", 0, b"bytes").unwrap();
+        cache.claim("me@example.com").unwrap();
+
+        let mut reopened = MessageCache::open(dir.path().to_path_buf());
+        assert!(!reopened.purge("other@example.com").unwrap());
+        assert!(reopened.contains("m1"));
+
+        assert!(reopened.purge("me@example.com").unwrap());
+        assert!(!reopened.contains("m1"));
+        assert!(!dir.path().join("m1.0.bin").exists());
+        assert!(MessageCache::open(dir.path().to_path_buf())
+            .account_id()
+            .is_none());
+    }
+}
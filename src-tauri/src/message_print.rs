@@ -0,0 +1,551 @@
+use crate::gmail_client::GmailMessage;
+use crate::html_text;
+use serde::{Deserialize, Serialize};
+
+/// Blocks scripts, plugins, forms and anything loaded from the network when
+/// the saved file is opened in a browser; inline styles and embedded images
+/// still work
+const PRINT_CSP: &str = "default-src 'none'; style-src 'unsafe-inline'; img-src data:";
+
+const PRINT_CSS: &str = "body { font-family: sans-serif; margin: 2em; } \
+     .headers { border-bottom: 1px solid #ccc; margin-bottom: 1.5em; padding-bottom: 1em; } \
+     .headers h1 { font-size: 1.4em; margin: 0 0 0.5em; } \
+     .headers th { color: #555; font-weight: normal; padding-right: 1em; text-align: left; vertical-align: top; } \
+     pre.body { font-family: inherit; white-space: pre-wrap; } \
+     @media print { body { margin: 0; } }";
+
+/// Elements removed along with everything inside them
+const DROPPED_ELEMENTS: &[&str] = &[
+    "script", "iframe", "frame", "frameset", "object", "embed", "applet", "noscript", "template",
+    "title",
+];
+
+/// Elements whose tags are removed but whose content is kept; the document
+/// ones because the message is placed inside the print document's own body
+const UNWRAPPED_ELEMENTS: &[&str] = &[
+    "html", "head", "body", "meta", "link", "base", "form", "input", "button", "select",
+    "textarea", "option",
+];
+
+/// Attributes that take a URL
+const URL_ATTRIBUTES: &[&str] = &[
+    "href",
+    "src",
+    "background",
+    "poster",
+    "xlink:href",
+    "action",
+];
+
+/// A4 in points, with the margins and font the PDF text is set in
+const PAGE_WIDTH: u32 = 595;
+const PAGE_HEIGHT: u32 = 842;
+const MARGIN: u32 = 56;
+const FONT_SIZE: u32 = 10;
+const LINE_HEIGHT: u32 = 13;
+/// Courier is 0.6em wide, so 80 columns fit between the margins
+const COLUMNS: usize = 80;
+
+/// What `export_message_print` writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrintFormat {
+    /// Text-only PDF: headers and the body as plain text
+    Pdf,
+    /// Self-contained HTML document ready for the browser's print dialog
+    Html,
+}
+
+impl PrintFormat {
+    /// PDF for a ".pdf" path, HTML otherwise
+    pub fn for_path(path: &str) -> Self {
+        if path.to_ascii_lowercase().ends_with(".pdf") {
+            PrintFormat::Pdf
+        } else {
+            PrintFormat::Html
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            PrintFormat::Pdf => "pdf",
+            PrintFormat::Html => "html",
+        }
+    }
+}
+
+/// The parts of a message that go on paper
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintableMessage {
+    pub subject: String,
+    /// Header names and values in print order; missing headers are left out
+    pub headers: Vec<(&'static str, String)>,
+    pub body_html: Option<String>,
+    pub body_text: String,
+}
+
+impl PrintableMessage {
+    pub fn from_message(message: &GmailMessage) -> Self {
+        let headers = [
+            ("From", Some(message.get_from())),
+            ("To", message.get_to()),
+            ("Cc", message.get_cc()),
+            ("Date", message.get_date()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+        .collect();
+        PrintableMessage {
+            subject: message.get_subject(),
+            headers,
+            body_html: message.get_body_html(),
+            body_text: message.get_body_text(),
+        }
+    }
+
+    pub fn render(&self, format: PrintFormat) -> Vec<u8> {
+        match format {
+            PrintFormat::Html => self.to_html().into_bytes(),
+            PrintFormat::Pdf => self.to_pdf(),
+        }
+    }
+
+    /// A standalone HTML document: escaped headers, then the sanitized HTML
+    /// body or the text body
+    pub fn to_html(&self) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+             <meta http-equiv=\"Content-Security-Policy\" content=\"{}\">\
+             <title>{}</title><style>{}</style></head><body>\n\
+             <div class=\"headers\"><h1>{}</h1><table>",
+            PRINT_CSP,
+            escape_html(&self.subject),
+            PRINT_CSS,
+            escape_html(&self.subject)
+        );
+        for (name, value) in &self.headers {
+            html.push_str(&format!(
+                "<tr><th>{}</th><td>{}</td></tr>",
+                name,
+                escape_html(value)
+            ));
+        }
+        html.push_str("</table></div>\n");
+        match &self.body_html {
+            Some(body) => {
+                html.push_str("<div class=\"body\">");
+                html.push_str(&sanitize_html(body));
+                html.push_str("</div>");
+            }
+            None => {
+                html.push_str("<pre class=\"body\">");
+                html.push_str(&escape_html(&self.body_text));
+                html.push_str("</pre>");
+            }
+        }
+        html.push_str("\n</body></html>\n");
+        html
+    }
+
+    /// The message as text, for the PDF
+    fn text_lines(&self) -> Vec<String> {
+        let mut text = format!("Subject: {}\n", self.subject);
+        for (name, value) in &self.headers {
+            text.push_str(&format!("{}: {}\n", name, value));
+        }
+        text.push('\n');
+        match &self.body_html {
+            Some(body) => text.push_str(&html_text::html_to_text(body)),
+            None => text.push_str(&self.body_text),
+        }
+        text.lines().flat_map(wrap_line).collect()
+    }
+
+    /// A text-only PDF in Courier. Characters outside Windows-1252 print
+    /// as '?', since the standard PDF fonts carry nothing else.
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let lines_per_page = ((PAGE_HEIGHT - 2 * MARGIN) / LINE_HEIGHT) as usize;
+        let lines = self.text_lines();
+        let pages: Vec<&[String]> = if lines.is_empty() {
+            vec![&[]]
+        } else {
+            lines.chunks(lines_per_page).collect()
+        };
+
+        // 1 catalog, 2 page tree, 3 font, 4 info, then a page and its
+        // content stream for each page
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+        let kids: Vec<String> = (0..pages.len())
+            .map(|i| format!("{} 0 R", 5 + 2 * i))
+            .collect();
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        objects.push(
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                pages.len()
+            )
+            .into_bytes(),
+        );
+        objects.push(
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+        );
+        let mut info = b"<< /Title (".to_vec();
+        info.extend(pdf_string(&self.subject));
+        info.extend_from_slice(b") /Producer (Aisle3) >>");
+        objects.push(info);
+
+        for (i, page) in pages.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                     /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                    PAGE_WIDTH,
+                    PAGE_HEIGHT,
+                    6 + 2 * i
+                )
+                .into_bytes(),
+            );
+            let mut content = format!(
+                "BT /F1 {} Tf {} TL {} {} Td\n",
+                FONT_SIZE,
+                LINE_HEIGHT,
+                MARGIN,
+                PAGE_HEIGHT - MARGIN - FONT_SIZE
+            )
+            .into_bytes();
+            for line in page.iter() {
+                content.push(b'(');
+                content.extend(pdf_string(line));
+                content.extend_from_slice(b") Tj T*\n");
+            }
+            content.extend_from_slice(b"ET");
+            let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+            stream.extend(content);
+            stream.extend_from_slice(b"\nendstream");
+            objects.push(stream);
+        }
+
+        let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref_at = pdf.len();
+        pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+        for offset in offsets {
+            pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+        }
+        pdf.extend(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R /Info 4 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref_at
+            )
+            .into_bytes(),
+        );
+        pdf
+    }
+}
+
+/// Split a line into pieces of at most `COLUMNS` characters, at spaces where
+/// possible
+fn wrap_line(line: &str) -> Vec<String> {
+    let line = line.replace('\t', "    ");
+    let mut pieces = Vec::new();
+    let mut rest = line.trim_end();
+    while rest.chars().count() > COLUMNS {
+        let limit = rest
+            .char_indices()
+            .nth(COLUMNS)
+            .map_or(rest.len(), |(i, _)| i);
+        let split = rest[..limit].rfind(' ').filter(|&i| i > 0).unwrap_or(limit);
+        pieces.push(rest[..split].to_string());
+        rest = rest[split..].strip_prefix(' ').unwrap_or(&rest[split..]);
+    }
+    pieces.push(rest.to_string());
+    pieces
+}
+
+/// Text as the inside of a PDF literal string in WinAnsiEncoding
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    for ch in text.chars() {
+        let byte = match ch {
+            '(' | ')' | '\\' => {
+                out.push(b'\\');
+                ch as u8
+            }
+            ' '..='~' => ch as u8,
+            '\u{a0}'..='\u{ff}' => ch as u32 as u8,
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '™' => 0x99,
+            _ => b'?',
+        };
+        if byte < 0x80 {
+            out.push(byte);
+        } else {
+            out.extend(format!("\\{:03o}", byte).into_bytes());
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Strip an email's HTML down to markup that can't run or submit anything:
+/// script-like elements go with their content, forms and document-level
+/// tags are unwrapped, and every kept tag is rebuilt from its attributes
+/// minus event handlers and script URLs. Remote images are left to the
+/// print document's CSP to block.
+pub fn sanitize_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(open) = rest.find('<') {
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(close) = tag_end(rest) else {
+            out.push_str(&escape_html(rest));
+            rest = "";
+            break;
+        };
+        let body = &rest[1..close];
+        rest = &rest[close + 1..];
+
+        let (closing, body) = match body.strip_prefix('/') {
+            Some(body) => (true, body),
+            None => (false, body),
+        };
+        let name_end = body
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(body.len());
+        let name = body[..name_end].to_ascii_lowercase();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+            // Doctype, processing instruction or a stray '<'
+            if !body.starts_with(['!', '?']) {
+                out.push_str(&escape_html(&format!("<{}>", body)));
+            }
+            continue;
+        }
+
+        if DROPPED_ELEMENTS.contains(&name.as_str()) {
+            if !closing && !body.trim_end().ends_with('/') {
+                rest = skip_past_close(rest, &name);
+            }
+            continue;
+        }
+        if UNWRAPPED_ELEMENTS.contains(&name.as_str()) {
+            continue;
+        }
+        if closing {
+            out.push_str(&format!("</{}>", name));
+            continue;
+        }
+
+        out.push('<');
+        out.push_str(&name);
+        for (attribute, value) in attributes(&body[name_end..]) {
+            if is_safe_attribute(&attribute, &value) {
+                out.push_str(&format!(" {}=\"{}\"", attribute, escape_html(&value)));
+            }
+        }
+        if body.trim_end().ends_with('/') {
+            out.push_str(" /");
+        }
+        out.push('>');
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Index of the '>' closing the tag `rest` starts with, skipping any inside
+/// quoted attribute values
+fn tag_end(rest: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in rest.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// What follows the closing tag of `name`, or nothing when it is never closed
+fn skip_past_close<'a>(rest: &'a str, name: &str) -> &'a str {
+    let lower = rest.to_ascii_lowercase();
+    let Some(start) = lower.find(&format!("</{}", name)) else {
+        return "";
+    };
+    match rest[start..].find('>') {
+        Some(end) => &rest[start + end + 1..],
+        None => "",
+    }
+}
+
+/// Attribute names, lowercased, with their entity-decoded values
+fn attributes(mut rest: &str) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            return found;
+        }
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (raw, remaining) = match after.chars().next() {
+                Some(q @ ('"' | '\'')) => match after[1..].find(q) {
+                    Some(end) => (&after[1..end + 1], &after[end + 2..]),
+                    None => (&after[1..], ""),
+                },
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = html_text::decode_entities(raw);
+            rest = remaining;
+        }
+
+        let valid_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':'));
+        if valid_name {
+            found.push((name, value));
+        }
+    }
+}
+
+fn is_safe_attribute(name: &str, value: &str) -> bool {
+    if name.starts_with("on") || matches!(name, "srcdoc" | "formaction" | "http-equiv") {
+        return false;
+    }
+    if !URL_ATTRIBUTES.contains(&name) {
+        return true;
+    }
+    // Browsers ignore whitespace and control characters inside the scheme
+    let url: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let scheme = url.split_once(':').map(|(scheme, _)| scheme);
+    match scheme {
+        Some("javascript" | "vbscript") => false,
+        Some("data") => name == "src" && url.starts_with("data:image/"),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(body_html: Option<&str>, body_text: &str) -> PrintableMessage {
+        PrintableMessage {
+            subject: "Lunch <Friday>".to_string(),
+            headers: vec![
+                ("From", "Alice <alice@example.com>".to_string()),
+                ("Date", "Fri, 13 Jun 2025 09:00:00 +0000".to_string()),
+            ],
+            body_html: body_html.map(str::to_string),
+            body_text: body_text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_sanitize_drops_scripts_and_handlers() {
+        let html = "<HTML><body onload=\"steal()\"><p class=x>Hi <b>there</b></p>\
+                    <script>alert(1)</script><img src=\"cid:logo\" onerror='x()'>\
+                    <a href=\" java\tscript:alert(1)\">bad</a><a href=\"https://example.com/?a=1&amp;b=2\">ok</a>\
+                    <img alt=\">\" onerror=x()><iframe src=x>inner</iframe><!-- note -->\
+                    <img src=\"data:image/png;base64,AAAA\"/><a href=\"data:text/html,x\">d</a> 1 < 2";
+        assert_eq!(
+            sanitize_html(html),
+            "<p class=\"x\">Hi <b>there</b></p><img src=\"cid:logo\">\
+             <a>bad</a><a href=\"https://example.com/?a=1&amp;b=2\">ok</a>\
+             <img alt=\"&gt;\"><img src=\"data:image/png;base64,AAAA\" /><a>d</a> 1 &lt; 2"
+        );
+    }
+
+    #[test]
+    fn test_html_document_escapes_headers() {
+        let html = message(None, "Plain <text> body").to_html();
+        assert!(html.contains(PRINT_CSP));
+        assert!(html.contains("<h1>Lunch &lt;Friday&gt;</h1>"));
+        assert!(html.contains("<td>Alice &lt;alice@example.com&gt;</td>"));
+        assert!(html.contains("<pre class=\"body\">Plain &lt;text&gt; body</pre>"));
+
+        let html = message(Some("<p>Rich</p><script>x</script>"), "").to_html();
+        assert!(html.contains("<div class=\"body\"><p>Rich</p></div>"));
+    }
+
+    #[test]
+    fn test_pdf_pages_and_text() {
+        let body = (1..=100)
+            .map(|i| format!("Line {} (of 100) caf\u{e9}", i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let pdf = message(None, &body).to_pdf();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(text.ends_with("%%EOF\n"));
+        // 104 lines at 56 a page
+        assert!(text.contains("/Count 2 >>"));
+        assert!(text.contains("(Line 7 \\(of 100\\) caf\\351) Tj T*"));
+        assert!(text.contains("(Subject: Lunch <Friday>) Tj T*"));
+
+        let xref_at: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|tail| tail.lines().next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(pdf[xref_at..].starts_with(b"xref\n0 9\n"));
+    }
+
+    #[test]
+    fn test_long_lines_wrap_at_spaces() {
+        let line = format!("{} {}", "a".repeat(70), "b".repeat(30));
+        assert_eq!(wrap_line(&line), vec!["a".repeat(70), "b".repeat(30)]);
+        assert_eq!(wrap_line(&"c".repeat(170)).len(), 3);
+        assert_eq!(wrap_line(""), vec![String::new()]);
+        assert_eq!(PrintFormat::for_path("/tmp/Mail.PDF"), PrintFormat::Pdf);
+        assert_eq!(PrintFormat::for_path("/tmp/mail.html"), PrintFormat::Html);
+    }
+}
@@ -0,0 +1,308 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::{extract_email_address, GmailMessage};
+use crate::local_store::{app_data_path, load_json, save_json};
+use crate::message_print::sanitize_html;
+use crate::sender_profile::display_name;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const FEEDS_FILE: &str = "newsletter_feeds.json";
+
+/// Newest issues kept in a feed file
+pub const MAX_FEED_ENTRIES: usize = 50;
+
+/// Whether a message is newsletter mail: bulk mail carrying the List-Id or
+/// List-Unsubscribe headers list software adds
+pub fn is_newsletter(message: &GmailMessage) -> bool {
+    message.get_list_id().is_some() || message.get_unsubscribe_options().is_some()
+}
+
+/// A newsletter sender whose issues are kept as an Atom file a feed reader
+/// can subscribe to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewsletterFeed {
+    /// Lowercased sender address
+    pub sender: String,
+    pub title: String,
+    /// The feed file on disk
+    pub path: String,
+    /// Archive the sender's new mail once it's in the feed
+    pub archive_from_inbox: bool,
+    pub entries: usize,
+    /// Unix timestamp (seconds) of the last rewrite
+    pub updated_at: u64,
+}
+
+/// Feeds `generate_feed` keeps up to date as new issues arrive
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedList {
+    feeds: Vec<NewsletterFeed>,
+    account_id: Option<String>,
+}
+
+impl FeedList {
+    pub fn load() -> Self {
+        load_json(&app_data_path(FEEDS_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(FEEDS_FILE), self)
+    }
+
+    pub fn feeds(&self) -> Vec<NewsletterFeed> {
+        self.feeds.clone()
+    }
+
+    pub fn get(&self, sender: &str) -> Option<&NewsletterFeed> {
+        let sender = extract_email_address(sender);
+        self.feeds
+            .iter()
+            .find(|f| f.sender.eq_ignore_ascii_case(&sender))
+    }
+
+    /// Add a feed or replace the one for the same sender
+    pub fn upsert(&mut self, feed: NewsletterFeed) {
+        match self.feeds.iter_mut().find(|f| f.sender == feed.sender) {
+            Some(existing) => *existing = feed,
+            None => self.feeds.push(feed),
+        }
+    }
+
+    pub fn remove(&mut self, sender: &str) -> Option<NewsletterFeed> {
+        let sender = extract_email_address(sender);
+        let index = self
+            .feeds
+            .iter()
+            .position(|f| f.sender.eq_ignore_ascii_case(&sender))?;
+        Some(self.feeds.remove(index))
+    }
+
+    /// Newsletter issues among `messages` from senders with a feed
+    pub fn arrivals<'a>(&self, messages: &'a [GmailMessage]) -> Vec<&'a GmailMessage> {
+        messages
+            .iter()
+            .filter(|m| is_newsletter(m) && self.get(&m.get_from_address()).is_some())
+            .collect()
+    }
+}
+
+impl AccountScoped for FeedList {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+/// Where a sender's feed file lives, e.g. ".../aisle3/feeds/news-example-com.xml"
+pub fn feed_path(sender: &str) -> PathBuf {
+    let slug: String = sender
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let dir = app_data_path("feeds");
+    std::fs::create_dir_all(&dir).ok();
+    dir.join(format!("{}.xml", slug))
+}
+
+/// The sender's newsletter issues among `messages`, newest first and at most
+/// `MAX_FEED_ENTRIES`
+pub fn issues<'a>(sender: &str, messages: &'a [GmailMessage]) -> Vec<&'a GmailMessage> {
+    let sender = extract_email_address(sender);
+    let mut issues: Vec<&GmailMessage> = messages
+        .iter()
+        .filter(|m| is_newsletter(m) && m.get_from_address().eq_ignore_ascii_case(&sender))
+        .collect();
+    issues.sort_by_key(|m| std::cmp::Reverse(received_ms(m)));
+    issues.truncate(MAX_FEED_ENTRIES);
+    issues
+}
+
+/// Feed title for a sender: the display name on its latest issue
+pub fn feed_title(sender: &str, issues: &[&GmailMessage]) -> String {
+    issues
+        .first()
+        .and_then(|m| display_name(&m.get_from()))
+        .unwrap_or_else(|| extract_email_address(sender))
+}
+
+/// An Atom 1.0 document with one entry per issue, HTML bodies sanitized
+pub fn render_atom(sender: &str, title: &str, issues: &[&GmailMessage]) -> String {
+    let sender = extract_email_address(sender);
+    let updated = issues.first().map(|m| received_ms(m)).unwrap_or(0);
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\">\n\
+         <id>mailto:{}</id>\n<title>{}</title>\n<updated>{}</updated>\n\
+         <author><name>{}</name><email>{}</email></author>\n",
+        escape_xml(&sender),
+        escape_xml(title),
+        atom_date(updated),
+        escape_xml(title),
+        escape_xml(&sender),
+    );
+    for message in issues {
+        let (kind, content) = match message.get_body_html() {
+            Some(html) => ("html", sanitize_html(&html)),
+            None => ("text", message.get_body_text()),
+        };
+        xml.push_str(&format!(
+            "<entry>\n<id>urn:gmail:message:{}</id>\n<title>{}</title>\n\
+             <updated>{}</updated>\n<summary>{}</summary>\n\
+             <content type=\"{}\">{}</content>\n</entry>\n",
+            escape_xml(&message.id),
+            escape_xml(&message.get_subject()),
+            atom_date(received_ms(message)),
+            escape_xml(&message.snippet),
+            kind,
+            escape_xml(&content),
+        ));
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// Write the sender's feed from `messages`, replacing the file in one rename
+/// so a reader polling it never sees half a document. Returns the issues
+/// written.
+pub fn write_feed(
+    path: &Path,
+    sender: &str,
+    messages: &[GmailMessage],
+) -> Result<(String, usize), String> {
+    let issues = issues(sender, messages);
+    let title = feed_title(sender, &issues);
+    let partial = path.with_extension("xml.part");
+    std::fs::write(&partial, render_atom(sender, &title, &issues))
+        .map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+    std::fs::rename(&partial, path).map_err(|e| {
+        let _ = std::fs::remove_file(&partial);
+        format!("Failed to move feed into place: {}", e)
+    })?;
+    Ok((title, issues.len()))
+}
+
+fn received_ms(message: &GmailMessage) -> i64 {
+    message
+        .internal_date
+        .as_deref()
+        .and_then(|d| d.parse().ok())
+        .unwrap_or(0)
+}
+
+fn atom_date(millis: i64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(millis)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.chars()
+        .filter(|&c| matches!(c, '\t' | '\n' | '\r') || !c.is_control())
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::{MessageHeader, MessagePayload};
+
+    fn message(id: &str, from: &str, list: bool, date: &str) -> GmailMessage {
+        let mut headers = vec![
+            MessageHeader {
+                name: "From".to_string(),
+                value: from.to_string(),
+            },
+            MessageHeader {
+                name: "Subject".to_string(),
+                value: format!("Issue {} & more", id),
+            },
+        ];
+        if list {
+            headers.push(MessageHeader {
+                name: "List-Unsubscribe".to_string(),
+                value: "<https://news.example.com/u>".to_string(),
+            });
+        }
+        GmailMessage {
+            id: id.to_string(),
+            thread_id: id.to_string(),
+            snippet: "<b>Hi</b>".to_string(),
+            label_ids: Some(vec!["INBOX".to_string()]),
+            payload: Some(MessagePayload {
+                headers: Some(headers),
+                ..Default::default()
+            }),
+            internal_date: Some(date.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_feed_holds_the_senders_newsletters_newest_first() {
+        let messages = vec![
+            message("1", "Weekly News <News@Example.com>", true, "1700000000000"),
+            message("2", "news@example.com", false, "1700000100000"),
+            message("3", "Weekly News <news@example.com>", true, "1700000200000"),
+            message("4", "other@example.com", true, "1700000300000"),
+        ];
+        let issues = issues("news@example.com", &messages);
+        assert_eq!(
+            issues.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(),
+            vec!["3", "1"]
+        );
+        assert_eq!(feed_title("news@example.com", &issues), "Weekly News");
+
+        let atom = render_atom("news@example.com", "Weekly News", &issues);
+        assert!(atom.contains("<id>mailto:news@example.com</id>"));
+        assert!(atom.contains("<updated>2023-11-14T22:16:40Z</updated>"));
+        assert!(atom.contains("<title>Issue 3 &amp; more</title>"));
+        assert!(atom.contains("<summary>&lt;b&gt;Hi&lt;/b&gt;</summary>"));
+        assert_eq!(atom.matches("<entry>").count(), 2);
+    }
+
+    #[test]
+    fn test_arrivals_only_from_feed_senders() {
+        let mut list = FeedList::default();
+        list.upsert(NewsletterFeed {
+            sender: "news@example.com".to_string(),
+            title: "Weekly News".to_string(),
+            path: "news.xml".to_string(),
+            archive_from_inbox: true,
+            entries: 1,
+            updated_at: 0,
+        });
+        let messages = vec![
+            message("1", "News <NEWS@example.com>", true, "0"),
+            message("2", "news@example.com", false, "0"),
+            message("3", "other@example.com", true, "0"),
+        ];
+        let arrivals = list.arrivals(&messages);
+        assert_eq!(arrivals.len(), 1);
+        assert_eq!(arrivals[0].id, "1");
+        assert!(list.remove("News <news@example.com>").is_some());
+        assert!(list.arrivals(&messages).is_empty());
+    }
+}
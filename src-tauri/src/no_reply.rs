@@ -0,0 +1,184 @@
+use crate::gmail_client::{extract_email_address, GmailMessage};
+use serde::Serialize;
+
+/// Local parts that mark an address as unattended, compared without separators
+const NO_REPLY_LOCAL_PARTS: &[&str] = &["noreply", "donotreply", "mailerdaemon"];
+
+/// Alternative addresses offered in a warning
+const MAX_SUGGESTED_CONTACTS: usize = 3;
+
+/// Words that suggest a nearby address is the one to write to instead
+const CONTACT_HINTS: &[&str] = &[
+    "contact",
+    "support",
+    "help",
+    "reach",
+    "questions",
+    "email us",
+];
+
+/// Returned instead of sending when the recipient is a no-reply address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NoReplyWarning {
+    pub address: String,
+    /// Reply-To or addresses mentioned in the message, best guess first
+    pub suggested_contacts: Vec<String>,
+}
+
+/// Whether an address is one nobody reads, e.g. no-reply@, do_not_reply@
+/// or noreply-billing@
+pub fn is_no_reply_address(address: &str) -> bool {
+    let address = extract_email_address(address).to_lowercase();
+    let local_part = match address.split_once('@') {
+        Some((local_part, _)) => local_part,
+        None => return false,
+    };
+
+    let squashed: String = local_part
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    NO_REPLY_LOCAL_PARTS
+        .iter()
+        .any(|pattern| squashed.starts_with(pattern))
+}
+
+/// Warning for replying to `original`, if it came from a no-reply address
+pub fn check_reply(original: &GmailMessage) -> Option<NoReplyWarning> {
+    let address = original.get_from_address();
+    is_no_reply_address(&address).then(|| NoReplyWarning {
+        suggested_contacts: suggested_contacts(original),
+        address,
+    })
+}
+
+/// Where a reply could go instead: a usable Reply-To first, then addresses
+/// in the body, preferring ones near words like "contact" or "support"
+pub fn suggested_contacts(message: &GmailMessage) -> Vec<String> {
+    let mut hinted = Vec::new();
+    let mut others = Vec::new();
+
+    if let Some(reply_to) = message.get_reply_to() {
+        hinted.extend(
+            reply_to
+                .split(',')
+                .map(extract_email_address)
+                .filter(|a| a.contains('@')),
+        );
+    }
+
+    for line in message.get_body_text().lines() {
+        let lower = line.to_lowercase();
+        let near_hint = CONTACT_HINTS.iter().any(|hint| lower.contains(hint));
+        for address in addresses_in(line) {
+            if near_hint {
+                hinted.push(address);
+            } else {
+                others.push(address);
+            }
+        }
+    }
+
+    let mut suggestions: Vec<String> = Vec::new();
+    for address in hinted.into_iter().chain(others) {
+        if !is_no_reply_address(&address)
+            && !suggestions.iter().any(|s| s.eq_ignore_ascii_case(&address))
+        {
+            suggestions.push(address);
+        }
+    }
+    suggestions.truncate(MAX_SUGGESTED_CONTACTS);
+    suggestions
+}
+
+/// Things that look like email addresses in free text
+fn addresses_in(text: &str) -> Vec<String> {
+    text.split(|c: char| c.is_whitespace() || "<>()[]{},;:\"'".contains(c))
+        .map(|token| token.trim_end_matches(['.', '!', '?']))
+        .filter(|token| {
+            token.split_once('@').is_some_and(|(local, domain)| {
+                !local.is_empty()
+                    && domain.contains('.')
+                    && !domain.starts_with('.')
+                    && !domain.contains('@')
+            })
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::{MessageBody, MessageHeader, MessagePayload};
+    use base64::{engine::general_purpose::URL_SAFE, Engine as _};
+
+    fn message(headers: &[(&str, &str)], body: &str) -> GmailMessage {
+        GmailMessage {
+            id: "m1".to_string(),
+            thread_id: "t1".to_string(),
+            payload: Some(MessagePayload {
+                headers: Some(
+                    headers
+                        .iter()
+                        .map(|(name, value)| MessageHeader {
+                            name: name.to_string(),
+                            value: value.to_string(),
+                        })
+                        .collect(),
+                ),
+                body: Some(MessageBody {
+                    data: Some(URL_SAFE.encode(body)),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_is_no_reply_address() {
+        assert!(is_no_reply_address("no-reply@example.com"));
+        assert!(is_no_reply_address("Shop <NoReply@shop.example>"));
+        assert!(is_no_reply_address("do_not_reply@bank.example"));
+        assert!(is_no_reply_address("noreply-billing@example.com"));
+        assert!(is_no_reply_address("MAILER-DAEMON@mx.example.com"));
+        assert!(!is_no_reply_address("support@example.com"));
+        assert!(!is_no_reply_address("norah.eply@example.com"));
+    }
+
+    #[test]
+    fn test_check_reply_suggests_contacts() {
+        let original = message(
+            &[("From", "Billing <noreply@shop.example>")],
+            "Your order shipped. Sent from noreply@shop.example.\n\
+             Questions? Contact support@shop.example.\n\
+             Unrelated: press@shop.example",
+        );
+
+        let warning = check_reply(&original).unwrap();
+        assert_eq!(warning.address, "noreply@shop.example");
+        assert_eq!(
+            warning.suggested_contacts,
+            vec![
+                "support@shop.example".to_string(),
+                "press@shop.example".to_string()
+            ]
+        );
+
+        let with_reply_to = message(
+            &[
+                ("From", "no-reply@shop.example"),
+                ("Reply-To", "Orders <orders@shop.example>"),
+            ],
+            "",
+        );
+        assert_eq!(
+            check_reply(&with_reply_to).unwrap().suggested_contacts,
+            vec!["orders@shop.example".to_string()]
+        );
+
+        assert!(check_reply(&message(&[("From", "alice@example.com")], "")).is_none());
+    }
+}
@@ -0,0 +1,528 @@
+use crate::gmail_client::GmailMessage;
+use crate::settings::NotesTarget;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::{Component, Path, PathBuf};
+
+const NOTION_API: &str = "https://api.notion.com/v1";
+const NOTION_VERSION: &str = "2022-06-28";
+
+/// Notion caps a rich-text run at 2000 characters and a children list at
+/// 100 blocks per request
+const NOTION_TEXT_LIMIT: usize = 2000;
+const NOTION_BLOCK_LIMIT: usize = 100;
+
+/// Largest file Notion accepts in a single-part upload; bigger attachments
+/// are listed by name only
+const NOTION_UPLOAD_LIMIT: usize = 20 * 1024 * 1024;
+
+/// Longest note or attachment file name written to a vault
+const MAX_FILE_NAME_CHARS: usize = 100;
+
+/// An attachment downloaded to go with a note
+#[derive(Debug, Clone)]
+pub struct NoteAttachment {
+    pub filename: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// A message cleaned up for a notes app: headers worth keeping and the body
+/// as tidy plain text, HTML rendered
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailNote {
+    pub title: String,
+    pub from: String,
+    pub to: Option<String>,
+    pub date: Option<String>,
+    pub message_id: String,
+    pub body: String,
+}
+
+/// Where `save_email_to_notes` put a message
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SavedNote {
+    pub target: String,
+    /// The note's file path, or the Notion page URL
+    pub location: String,
+    /// Attachments saved alongside the note
+    pub attachments: usize,
+}
+
+impl EmailNote {
+    pub fn from_message(message: &GmailMessage) -> Self {
+        EmailNote {
+            title: message.get_subject(),
+            from: message.get_from(),
+            to: message.get_to(),
+            date: message.get_date(),
+            message_id: message.id.clone(),
+            body: clean_body(&message.get_body_text()),
+        }
+    }
+
+    /// The note as Markdown, with `attachment_links` listed after the body
+    pub fn to_markdown(&self, attachment_links: &[String]) -> String {
+        let mut markdown = format!("# {}\n\n", self.title);
+        for (name, value) in self.header_lines() {
+            markdown.push_str(&format!("- **{}:** {}\n", name, value));
+        }
+        if !self.body.is_empty() {
+            markdown.push('\n');
+            markdown.push_str(&self.body);
+            markdown.push('\n');
+        }
+        if !attachment_links.is_empty() {
+            markdown.push_str("\n## Attachments\n\n");
+            for link in attachment_links {
+                markdown.push_str(&format!("- {}\n", link));
+            }
+        }
+        markdown
+    }
+
+    fn header_lines(&self) -> Vec<(&'static str, &str)> {
+        let mut lines = vec![("From", self.from.as_str())];
+        if let Some(to) = &self.to {
+            lines.push(("To", to));
+        }
+        if let Some(date) = &self.date {
+            lines.push(("Date", date));
+        }
+        lines
+    }
+}
+
+/// Body text with CRLFs normalized, trailing spaces dropped and runs of
+/// blank lines collapsed to one
+pub fn clean_body(text: &str) -> String {
+    let mut cleaned = String::with_capacity(text.len());
+    let mut blank_run = 0;
+    for line in text.replace("\r\n", "\n").split('\n') {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_run += 1;
+            if blank_run > 1 {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        cleaned.push_str(line);
+        cleaned.push('\n');
+    }
+    cleaned.trim().to_string()
+}
+
+/// A name safe on every OS and in Obsidian links, which can't contain
+/// `# ^ [ ] |`
+pub fn note_file_name(name: &str, fallback: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    let name: String = name.chars().take(MAX_FILE_NAME_CHARS).collect();
+    match name.trim_matches(|c: char| c == '.' || c.is_whitespace()) {
+        "" => fallback.to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// Write the note and its attachments into the target's Obsidian vault.
+/// Attachments go in "attachments/<note>" beside the note and are linked
+/// from it; an existing note of the same name is never overwritten.
+pub fn save_to_obsidian(
+    target: &NotesTarget,
+    note: &EmailNote,
+    attachments: &[NoteAttachment],
+) -> Result<SavedNote, String> {
+    let vault = target
+        .vault_path
+        .as_deref()
+        .filter(|p| !p.trim().is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("Notes target {} has no vault path", target.name))?;
+    if !vault.is_dir() {
+        return Err(format!("Obsidian vault {} not found", vault.display()));
+    }
+    let folder = vault_folder(target.folder.as_deref().unwrap_or_default())?;
+    let dir = vault.join(&folder);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let file_name = unused_name(
+        &dir,
+        &format!("{}.md", note_file_name(&note.title, "Email")),
+    );
+    let path = dir.join(&file_name);
+    let name = file_name.trim_end_matches(".md");
+
+    let mut links = Vec::new();
+    if !attachments.is_empty() {
+        let attachment_dir = dir.join("attachments").join(name);
+        std::fs::create_dir_all(&attachment_dir)
+            .map_err(|e| format!("Failed to create {}: {}", attachment_dir.display(), e))?;
+        for attachment in attachments {
+            let filename = unused_name(
+                &attachment_dir,
+                &note_file_name(&attachment.filename, "attachment"),
+            );
+            std::fs::write(attachment_dir.join(&filename), &attachment.data)
+                .map_err(|e| format!("Failed to save {}: {}", filename, e))?;
+            // Vault-relative, so the link resolves wherever the note moves
+            let target = folder.join("attachments").join(name).join(&filename);
+            let embed = if attachment.mime_type.starts_with("image/") {
+                "!"
+            } else {
+                ""
+            };
+            links.push(format!(
+                "{}[[{}]]",
+                embed,
+                target.to_string_lossy().replace('\\', "/")
+            ));
+        }
+    }
+
+    let document = format!("{}{}", frontmatter(note), note.to_markdown(&links));
+    let partial = path.with_extension("md.part");
+    std::fs::write(&partial, document)
+        .map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+    std::fs::rename(&partial, &path).map_err(|e| {
+        let _ = std::fs::remove_file(&partial);
+        format!("Failed to move note into place: {}", e)
+    })?;
+
+    Ok(SavedNote {
+        target: target.name.clone(),
+        location: path.to_string_lossy().into_owned(),
+        attachments: attachments.len(),
+    })
+}
+
+/// The folder setting as a path inside the vault; absolute paths and ".."
+/// are refused so a note can't land outside it
+fn vault_folder(folder: &str) -> Result<PathBuf, String> {
+    let path = Path::new(folder.trim());
+    if path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!("Notes folder {} must be inside the vault", folder));
+    }
+    Ok(path.to_path_buf())
+}
+
+/// `file_name`, or the same with " (2)", " (3)"… before its extension,
+/// whichever `dir` doesn't have yet
+fn unused_name(dir: &Path, file_name: &str) -> String {
+    let path = Path::new(file_name);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut name = file_name.to_string();
+    let mut suffix = 1;
+    while dir.join(&name).exists() {
+        suffix += 1;
+        name = format!("{} ({}){}", stem, suffix, extension);
+    }
+    name
+}
+
+/// YAML properties Obsidian shows above the note
+fn frontmatter(note: &EmailNote) -> String {
+    let quote = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut yaml = format!(
+        "---\ntitle: {}\nfrom: {}\n",
+        quote(&note.title),
+        quote(&note.from)
+    );
+    if let Some(date) = &note.date {
+        yaml.push_str(&format!("date: {}\n", quote(date)));
+    }
+    yaml.push_str(&format!(
+        "gmail_id: {}\ntags: [email]\n---\n\n",
+        quote(&note.message_id)
+    ));
+    yaml
+}
+
+/// Notion pages through an internal integration's token
+pub struct NotionClient {
+    client: Client,
+    token: String,
+    base_url: String,
+}
+
+impl NotionClient {
+    pub fn new(token: &str) -> Self {
+        Self::with_base_url(token, NOTION_API)
+    }
+
+    pub fn with_base_url(token: &str, base_url: &str) -> Self {
+        NotionClient {
+            client: Client::new(),
+            token: token.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Create a page for the note under `parent_page_id`, uploading the
+    /// attachments as file blocks
+    pub async fn save(
+        &self,
+        target_name: &str,
+        parent_page_id: &str,
+        note: &EmailNote,
+        attachments: &[NoteAttachment],
+    ) -> Result<SavedNote, Box<dyn std::error::Error + Send + Sync>> {
+        let mut blocks = note_blocks(note);
+        let mut uploaded = 0;
+        if !attachments.is_empty() {
+            blocks.push(json!({
+                "type": "heading_2",
+                "heading_2": { "rich_text": rich_text("Attachments") },
+            }));
+            for attachment in attachments {
+                if attachment.data.len() > NOTION_UPLOAD_LIMIT {
+                    blocks.push(paragraph(&format!(
+                        "{} (too large to upload)",
+                        attachment.filename
+                    )));
+                    continue;
+                }
+                let upload_id = self.upload(attachment).await?;
+                blocks.push(json!({
+                    "type": "file",
+                    "file": {
+                        "type": "file_upload",
+                        "file_upload": { "id": upload_id },
+                        "name": attachment.filename,
+                    },
+                }));
+                uploaded += 1;
+            }
+        }
+
+        let mut chunks = blocks.chunks(NOTION_BLOCK_LIMIT);
+        let page = self
+            .request(
+                self.client.post(format!("{}/pages", self.base_url)),
+                json!({
+                    "parent": { "page_id": parent_page_id },
+                    "properties": { "title": { "title": rich_text(&note.title) } },
+                    "children": chunks.next().unwrap_or_default(),
+                }),
+            )
+            .await?;
+        let page_id = page["id"].as_str().ok_or("Notion returned no page id")?;
+        for chunk in chunks {
+            self.request(
+                self.client
+                    .patch(format!("{}/blocks/{}/children", self.base_url, page_id)),
+                json!({ "children": chunk }),
+            )
+            .await?;
+        }
+
+        Ok(SavedNote {
+            target: target_name.to_string(),
+            location: page["url"].as_str().unwrap_or(page_id).to_string(),
+            attachments: uploaded,
+        })
+    }
+
+    /// Send one attachment through Notion's single-part file upload,
+    /// returning the upload id blocks refer to
+    async fn upload(
+        &self,
+        attachment: &NoteAttachment,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let filename = note_file_name(&attachment.filename, "attachment");
+        let created = self
+            .request(
+                self.client.post(format!("{}/file_uploads", self.base_url)),
+                json!({ "filename": filename, "content_type": attachment.mime_type }),
+            )
+            .await?;
+        let upload_id = created["id"]
+            .as_str()
+            .ok_or("Notion returned no upload id")?
+            .to_string();
+
+        let boundary = format!("aisle3_upload_{}", upload_id.replace('-', ""));
+        let body = multipart_file(
+            &boundary,
+            &filename,
+            &attachment.mime_type,
+            &attachment.data,
+        );
+        let response = self
+            .client
+            .post(format!("{}/file_uploads/{}/send", self.base_url, upload_id))
+            .bearer_auth(&self.token)
+            .header("Notion-Version", NOTION_VERSION)
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Notion upload error: {}", error_text).into());
+        }
+        Ok(upload_id)
+    }
+
+    async fn request(
+        &self,
+        builder: reqwest::RequestBuilder,
+        body: Value,
+    ) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        let response = builder
+            .bearer_auth(&self.token)
+            .header("Notion-Version", NOTION_VERSION)
+            .json(&body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Notion API error: {}", error_text).into());
+        }
+        Ok(response.json().await?)
+    }
+}
+
+/// Header bullets and body paragraphs as Notion blocks
+fn note_blocks(note: &EmailNote) -> Vec<Value> {
+    let mut blocks: Vec<Value> = note
+        .header_lines()
+        .into_iter()
+        .map(|(name, value)| {
+            json!({
+                "type": "bulleted_list_item",
+                "bulleted_list_item": { "rich_text": rich_text(&format!("{}: {}", name, value)) },
+            })
+        })
+        .collect();
+    blocks.extend(
+        note.body
+            .split("\n\n")
+            .filter(|p| !p.trim().is_empty())
+            .map(paragraph),
+    );
+    blocks
+}
+
+fn paragraph(text: &str) -> Value {
+    json!({ "type": "paragraph", "paragraph": { "rich_text": rich_text(text) } })
+}
+
+/// Text as rich-text runs no longer than Notion allows
+fn rich_text(text: &str) -> Value {
+    let chars: Vec<char> = text.chars().collect();
+    let runs: Vec<Value> = chars
+        .chunks(NOTION_TEXT_LIMIT)
+        .map(|run| json!({ "type": "text", "text": { "content": run.iter().collect::<String>() } }))
+        .collect();
+    json!(runs)
+}
+
+/// A multipart/form-data body holding one file field
+fn multipart_file(boundary: &str, filename: &str, mime_type: &str, data: &[u8]) -> Vec<u8> {
+    let mut body = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+        boundary,
+        filename.replace('"', ""),
+        mime_type
+    )
+    .into_bytes();
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note() -> EmailNote {
+        EmailNote {
+            title: "Q3 plan: draft #2".to_string(),
+            from: "Alice <alice@example.com>".to_string(),
+            to: Some("team@example.com".to_string()),
+            date: None,
+            message_id: "m1".to_string(),
+            body: clean_body("Hi all,  \r\n\r\n\r\n\r\nSee attached.\r\n"),
+        }
+    }
+
+    #[test]
+    fn test_markdown_and_file_names() {
+        let note = note();
+        assert_eq!(note.body, "Hi all,\n\nSee attached.");
+        assert_eq!(
+            note.to_markdown(&["[[Mail/attachments/plan.pdf]]".to_string()]),
+            "# Q3 plan: draft #2\n\n\
+             - **From:** Alice <alice@example.com>\n\
+             - **To:** team@example.com\n\n\
+             Hi all,\n\nSee attached.\n\n\
+             ## Attachments\n\n\
+             - [[Mail/attachments/plan.pdf]]\n"
+        );
+
+        assert_eq!(note_file_name(&note.title, "Email"), "Q3 plan draft 2");
+        assert_eq!(note_file_name("../..", "Email"), "Email");
+        assert!(vault_folder("Inbox/Email").is_ok());
+        assert!(vault_folder("../elsewhere").is_err());
+        assert!(vault_folder("/etc").is_err());
+    }
+
+    #[test]
+    fn test_obsidian_notes_never_overwrite() {
+        let vault = tempfile::tempdir().unwrap();
+        let target = NotesTarget {
+            name: "Vault".to_string(),
+            vault_path: Some(vault.path().to_string_lossy().into_owned()),
+            folder: Some("Mail".to_string()),
+            ..Default::default()
+        };
+        let chart = |data: Vec<u8>| NoteAttachment {
+            filename: "chart.png".to_string(),
+            mime_type: "image/png".to_string(),
+            data,
+        };
+        let attachments = vec![chart(vec![1, 2, 3]), chart(vec![4, 5, 6])];
+
+        let first = save_to_obsidian(&target, &note(), &attachments).unwrap();
+        let second = save_to_obsidian(&target, &note(), &[]).unwrap();
+        assert!(first.location.ends_with("Q3 plan draft 2.md"));
+        assert!(second.location.ends_with("Q3 plan draft 2 (2).md"));
+
+        let saved = std::fs::read_to_string(&first.location).unwrap();
+        assert!(saved.starts_with("---\ntitle: \"Q3 plan: draft #2\"\n"));
+        assert!(saved.contains("- ![[Mail/attachments/Q3 plan draft 2/chart.png]]\n"));
+        assert!(saved.contains("- ![[Mail/attachments/Q3 plan draft 2/chart (2).png]]\n"));
+        let saved_chart = |name: &str| {
+            std::fs::read(
+                vault
+                    .path()
+                    .join("Mail/attachments/Q3 plan draft 2")
+                    .join(name),
+            )
+            .unwrap()
+        };
+        assert_eq!(saved_chart("chart.png"), vec![1, 2, 3]);
+        assert_eq!(saved_chart("chart (2).png"), vec![4, 5, 6]);
+    }
+}
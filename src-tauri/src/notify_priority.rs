@@ -0,0 +1,132 @@
+use crate::gmail_client::{extract_email_address, GmailMessage};
+use crate::settings::NotificationRule;
+use serde::Serialize;
+
+/// How the notifier should announce one new message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NotificationStyle {
+    pub message_id: String,
+    /// Name of the rule that matched
+    pub rule: String,
+    pub sound: Option<String>,
+    pub bypass_quiet_hours: bool,
+}
+
+impl NotificationRule {
+    /// Whether the message is from one of the rule's senders or carries one
+    /// of its labels. A rule with neither matches nothing.
+    pub fn matches(&self, message: &GmailMessage) -> bool {
+        let sender = extract_email_address(&message.get_from()).to_ascii_lowercase();
+        let from_sender = !sender.is_empty()
+            && self
+                .senders
+                .iter()
+                .any(|pattern| sender_matches(&sender, pattern));
+        let labelled = message
+            .label_ids
+            .as_ref()
+            .is_some_and(|labels| self.label_ids.iter().any(|l| labels.contains(l)));
+        from_sender || labelled
+    }
+}
+
+/// `pattern` is an address, "@domain" or a bare domain; a domain also covers
+/// its subdomains
+fn sender_matches(sender: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    if pattern.is_empty() {
+        return false;
+    }
+    if pattern.contains('@') && !pattern.starts_with('@') {
+        return extract_email_address(&pattern) == sender;
+    }
+    let domain = pattern.trim_start_matches('@');
+    let Some((_, sender_domain)) = sender.rsplit_once('@') else {
+        return false;
+    };
+    sender_domain == domain || sender_domain.ends_with(&format!(".{}", domain))
+}
+
+/// Style from the first rule the message matches
+pub fn resolve(rules: &[NotificationRule], message: &GmailMessage) -> Option<NotificationStyle> {
+    rules
+        .iter()
+        .find(|rule| rule.matches(message))
+        .map(|rule| NotificationStyle {
+            message_id: message.id.clone(),
+            rule: rule.name.clone(),
+            sound: rule.sound.clone(),
+            bypass_quiet_hours: rule.bypass_quiet_hours,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::{MessageHeader, MessagePayload};
+
+    fn message(from: &str, labels: &[&str]) -> GmailMessage {
+        GmailMessage {
+            id: "m1".to_string(),
+            thread_id: "t1".to_string(),
+            label_ids: Some(labels.iter().map(|l| l.to_string()).collect()),
+            payload: Some(MessagePayload {
+                headers: Some(vec![MessageHeader {
+                    name: "From".to_string(),
+                    value: from.to_string(),
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn rule(name: &str, senders: &[&str], label_ids: &[&str]) -> NotificationRule {
+        NotificationRule {
+            name: name.to_string(),
+            senders: senders.iter().map(|s| s.to_string()).collect(),
+            label_ids: label_ids.iter().map(|l| l.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_senders_match_addresses_and_domains() {
+        let boss = rule("Boss", &["Boss@Example.com"], &[]);
+        assert!(boss.matches(&message("The Boss <boss@example.com>", &[])));
+        assert!(!boss.matches(&message("other@example.com", &[])));
+
+        let pager = rule("Pager", &["@pager.example.com", "alerts.io"], &[]);
+        assert!(pager.matches(&message("oncall@pager.example.com", &[])));
+        assert!(pager.matches(&message("bot@eu.alerts.io", &[])));
+        assert!(!pager.matches(&message("bot@notalerts.io", &[])));
+
+        assert!(!rule("Empty", &[""], &[]).matches(&message("a@b.com", &[])));
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rules = vec![
+            NotificationRule {
+                sound: Some("siren".to_string()),
+                bypass_quiet_hours: true,
+                ..rule("Pager", &[], &["Label_7"])
+            },
+            NotificationRule {
+                sound: Some("chime".to_string()),
+                ..rule("Team", &["example.com"], &[])
+            },
+        ];
+
+        let style = resolve(&rules, &message("ops@example.com", &["INBOX", "Label_7"])).unwrap();
+        assert_eq!(style.rule, "Pager");
+        assert_eq!(style.sound.as_deref(), Some("siren"));
+        assert!(style.bypass_quiet_hours);
+
+        let style = resolve(&rules, &message("ops@example.com", &["INBOX"])).unwrap();
+        assert_eq!(style.sound.as_deref(), Some("chime"));
+        assert!(!style.bypass_quiet_hours);
+
+        assert_eq!(resolve(&rules, &message("x@other.org", &["INBOX"])), None);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::gmail_client::GmailClient;
+use crate::message_cache::{self, MessageCache, DEFAULT_CACHE_BUDGET_BYTES};
+use serde::Serialize;
+use std::sync::Mutex;
+
+/// Upper bound on messages a single offline bundle will prefetch
+pub const MAX_OFFLINE_MESSAGES: usize = 1000;
+
+/// Messages hydrated per batch request
+const OFFLINE_CHUNK_SIZE: usize = 50;
+
+/// Progress payload emitted as messages and attachments land in the cache
+#[derive(Debug, Clone, Serialize)]
+pub struct OfflineProgress {
+    pub operation_id: String,
+    pub processed: usize,
+    pub total: usize,
+    /// Bytes stored so far, including mail that was already cached
+    pub bytes: u64,
+}
+
+/// What ended up pinned for offline reading
+#[derive(Debug, Clone, Serialize)]
+pub struct OfflineBundleSummary {
+    pub operation_id: String,
+    pub query: String,
+    pub message_count: usize,
+    pub attachment_count: usize,
+    pub total_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+/// Gmail query for a bundle: matches of `query` from the last `days` days
+pub fn bundle_query(query: &str, days: u32) -> String {
+    if query.trim().is_empty() {
+        format!("newer_than:{}d", days)
+    } else {
+        format!("({}) newer_than:{}d", query, days)
+    }
+}
+
+/// Prefetch full messages and attachments into the cache and pin them against eviction.
+///
+/// The cache lock is only held between requests, never across them, so the UI
+/// keeps reading from the cache while a bundle downloads.
+pub async fn prepare_bundle<F>(
+    gmail_client: &GmailClient,
+    cache: &Mutex<MessageCache>,
+    operation_id: &str,
+    query: &str,
+    days: u32,
+    mut on_progress: F,
+) -> Result<OfflineBundleSummary, Box<dyn std::error::Error + Send + Sync>>
+where
+    F: FnMut(&OfflineProgress),
+{
+    let query = bundle_query(query, days);
+    let message_ids = gmail_client
+        .list_all_message_ids(&query, MAX_OFFLINE_MESSAGES)
+        .await?;
+
+    let mut summary = OfflineBundleSummary {
+        operation_id: operation_id.to_string(),
+        query,
+        message_count: 0,
+        attachment_count: 0,
+        total_bytes: 0,
+        errors: Vec::new(),
+    };
+    let mut processed = 0;
+
+    for chunk in message_ids.chunks(OFFLINE_CHUNK_SIZE) {
+        let missing: Vec<String> = {
+            let cache = cache.lock().unwrap();
+            chunk
+                .iter()
+                .filter(|id| !cache.contains(id))
+                .cloned()
+                .collect()
+        };
+
+        if !missing.is_empty() {
+            match gmail_client.get_messages_batch(&missing).await {
+                Ok(messages) => {
+                    let mut cache = cache.lock().unwrap();
+                    for message in &messages {
+                        if let Err(e) = cache.put_message(message) {
+                            summary.errors.push(e);
+                        }
+                    }
+                }
+                Err(e) => summary.errors.push(e.to_string()),
+            }
+        }
+
+        for message_id in chunk {
+            let message = match message_cache::load_message(gmail_client, cache, message_id).await {
+                Ok(message) => message,
+                Err(e) => {
+                    summary.errors.push(format!("{}: {}", message_id, e));
+                    continue;
+                }
+            };
+
+            for (index, attachment) in message.get_attachments().iter().enumerate() {
+                if cache.lock().unwrap().verify_attachment(message_id, index) {
+                    summary.attachment_count += 1;
+                    continue;
+                }
+
+                match gmail_client
+                    .get_attachment(message_id, &attachment.attachment_id)
+                    .await
+                {
+                    Ok(data) => match cache
+                        .lock()
+                        .unwrap()
+                        .put_attachment(message_id, index, &data)
+                    {
+                        Ok(_) => summary.attachment_count += 1,
+                        Err(e) => summary.errors.push(e),
+                    },
+                    Err(e) => summary
+                        .errors
+                        .push(format!("{} ({}): {}", attachment.filename, message_id, e)),
+                }
+            }
+
+            let mut cache = cache.lock().unwrap();
+            cache.set_pinned(message_id, true);
+            summary.total_bytes += cache.entry(message_id).map_or(0, |e| e.size_bytes);
+            summary.message_count += 1;
+        }
+
+        processed += chunk.len();
+        on_progress(&OfflineProgress {
+            operation_id: operation_id.to_string(),
+            processed,
+            total: message_ids.len(),
+            bytes: summary.total_bytes,
+        });
+    }
+
+    let mut cache = cache.lock().unwrap();
+    cache.evict_to(DEFAULT_CACHE_BUDGET_BYTES);
+    cache.save_index()?;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_query() {
+        assert_eq!(
+            bundle_query("label:travel", 14),
+            "(label:travel) newer_than:14d"
+        );
+        assert_eq!(bundle_query("  ", 3), "newer_than:3d");
+    }
+}
@@ -0,0 +1,442 @@
+use crate::account::AccountScoped;
+use crate::email::Email;
+use crate::gmail_client::{GmailClient, GmailLabel, MailboxDelta, SendAsAlias};
+use crate::local_store::{app_data_path, load_json, save_json};
+use crate::message_cache::MessageCache;
+use crate::people::ContactInfo;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+const ONBOARDING_FILE: &str = "onboarding.json";
+const SNAPSHOT_FILE: &str = "account_snapshot.json";
+
+/// Inbox messages cached up front so the first screen is full
+pub const RECENT_MESSAGE_COUNT: u32 = 50;
+
+/// Contacts imported for recipient lookups
+pub const CONTACT_IMPORT_LIMIT: u32 = 1000;
+
+/// Where the deep history sync stops; older mail is fetched on demand
+pub const HISTORY_SYNC_LIMIT: usize = 2000;
+
+/// Messages per history page
+pub const HISTORY_PAGE_SIZE: u32 = 100;
+
+/// Onboarding steps, in the order they run. Everything before `History` is
+/// quick; `History` pages through older mail and can be deferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStage {
+    Labels,
+    RecentThreads,
+    Contacts,
+    Signatures,
+    History,
+}
+
+impl OnboardingStage {
+    pub const QUICK: [OnboardingStage; 4] = [
+        OnboardingStage::Labels,
+        OnboardingStage::RecentThreads,
+        OnboardingStage::Contacts,
+        OnboardingStage::Signatures,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StageStatus {
+    Started,
+    /// History pages report progress with this status until the stage completes
+    InProgress,
+    Completed,
+    Failed,
+    Deferred,
+}
+
+/// Payload of the "onboarding-progress" event
+#[derive(Debug, Clone, Serialize)]
+pub struct OnboardingProgress {
+    pub stage: OnboardingStage,
+    pub status: StageStatus,
+    /// Items imported by the stage so far
+    pub items: usize,
+    pub error: Option<String>,
+}
+
+impl OnboardingProgress {
+    pub fn new(stage: OnboardingStage, status: StageStatus, items: usize) -> Self {
+        OnboardingProgress {
+            stage,
+            status,
+            items,
+            error: None,
+        }
+    }
+
+    pub fn failed(stage: OnboardingStage, error: String) -> Self {
+        OnboardingProgress {
+            error: Some(error),
+            ..Self::new(stage, StageStatus::Failed, 0)
+        }
+    }
+}
+
+/// Labels, contacts, send-as signatures and the first inbox page from the
+/// last sync, so the UI can draw them before the network answers
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountSnapshot {
+    pub labels: Vec<GmailLabel>,
+    pub contacts: Vec<ContactInfo>,
+    pub send_as: Vec<SendAsAlias>,
+    pub inbox: Vec<Email>,
+    /// Unix timestamp (seconds) of the inbox page
+    pub inbox_updated_at: Option<u64>,
+    pub account_id: Option<String>,
+}
+
+/// Payload of the "cached-inbox-ready" event and `get_cached_inbox`
+#[derive(Debug, Clone, Serialize)]
+pub struct CachedInbox {
+    pub emails: Vec<Email>,
+    pub updated_at: Option<u64>,
+}
+
+impl AccountSnapshot {
+    pub fn load() -> Self {
+        load_json(&app_data_path(SNAPSHOT_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(SNAPSHOT_FILE), self)
+    }
+
+    pub fn record_inbox(&mut self, emails: &[Email], now: u64) {
+        self.inbox = emails.to_vec();
+        self.inbox_updated_at = Some(now);
+    }
+
+    /// Fold changes made on other devices into the cached inbox page: read
+    /// and importance flags follow their labels, and messages deleted or
+    /// moved out of the inbox are dropped. Returns whether the page changed.
+    pub fn apply_delta(&mut self, delta: &MailboxDelta) -> bool {
+        let before = self.inbox.len();
+        self.inbox.retain(|email| {
+            !delta.deleted.contains(&email.id)
+                && !delta
+                    .label_changes
+                    .iter()
+                    .any(|c| c.message_id == email.id && c.removes("INBOX"))
+        });
+        let mut changed = self.inbox.len() != before;
+
+        for change in &delta.label_changes {
+            let Some(email) = self.inbox.iter_mut().find(|e| e.id == change.message_id) else {
+                continue;
+            };
+            let is_read = if change.adds("UNREAD") {
+                false
+            } else if change.removes("UNREAD") {
+                true
+            } else {
+                email.is_read
+            };
+            let is_important = if change.adds("IMPORTANT") {
+                true
+            } else if change.removes("IMPORTANT") {
+                false
+            } else {
+                email.is_important
+            };
+            changed |= is_read != email.is_read || is_important != email.is_important;
+            email.is_read = is_read;
+            email.is_important = is_important;
+        }
+        changed
+    }
+
+    /// The last inbox page, None before one was ever loaded
+    pub fn cached_inbox(&self) -> Option<CachedInbox> {
+        self.inbox_updated_at.map(|updated_at| CachedInbox {
+            emails: self.inbox.clone(),
+            updated_at: Some(updated_at),
+        })
+    }
+}
+
+impl AccountScoped for AccountSnapshot {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+/// How far onboarding got, persisted so a restart picks up where it stopped
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OnboardingState {
+    pub completed_stages: Vec<OnboardingStage>,
+    /// The user asked to hold off on the history sync
+    pub history_deferred: bool,
+    /// Next history page; None before the first page and after the last
+    pub history_page_token: Option<String>,
+    pub history_synced: usize,
+    /// Account being onboarded, also the owner of the saved tokens
+    pub account_id: Option<String>,
+    /// A sync is running in this process
+    #[serde(skip_deserializing)]
+    pub running: bool,
+}
+
+impl OnboardingState {
+    pub fn load() -> Self {
+        load_json(&app_data_path(ONBOARDING_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(ONBOARDING_FILE), self)
+    }
+
+    pub fn is_complete(&self, stage: OnboardingStage) -> bool {
+        self.completed_stages.contains(&stage)
+    }
+
+    pub fn mark_complete(&mut self, stage: OnboardingStage) {
+        if !self.is_complete(stage) {
+            self.completed_stages.push(stage);
+        }
+    }
+
+    /// Stages still to run, honouring a deferred history sync
+    pub fn pending_stages(&self) -> Vec<OnboardingStage> {
+        let mut stages: Vec<OnboardingStage> = OnboardingStage::QUICK
+            .into_iter()
+            .filter(|s| !self.is_complete(*s))
+            .collect();
+        if !self.history_deferred && !self.is_complete(OnboardingStage::History) {
+            stages.push(OnboardingStage::History);
+        }
+        stages
+    }
+
+    /// Record a synced history page, completing the stage at the last page or the limit
+    pub fn record_history_page(&mut self, synced: usize, next_page_token: Option<String>) {
+        self.history_synced += synced;
+        self.history_page_token = next_page_token;
+        if self.history_page_token.is_none() || self.history_synced >= HISTORY_SYNC_LIMIT {
+            self.history_page_token = None;
+            self.mark_complete(OnboardingStage::History);
+        }
+    }
+}
+
+impl AccountScoped for OnboardingState {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    /// A running sync keeps its flag so a second one isn't started alongside it
+    fn clear(&mut self) {
+        *self = OnboardingState {
+            running: self.running,
+            ..Default::default()
+        };
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+/// Fetch full messages for `message_ids` that aren't cached yet, returning how many were stored
+async fn cache_messages(
+    gmail_client: &GmailClient,
+    cache: &Mutex<MessageCache>,
+    message_ids: &[String],
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let missing: Vec<String> = {
+        let cache = cache.lock().unwrap();
+        message_ids
+            .iter()
+            .filter(|id| !cache.contains(id))
+            .cloned()
+            .collect()
+    };
+    if missing.is_empty() {
+        return Ok(0);
+    }
+
+    let messages = gmail_client.get_messages_batch(&missing).await?;
+    let mut cache = cache.lock().unwrap();
+    let mut stored = 0;
+    for message in &messages {
+        match cache.put_message(message) {
+            Ok(_) => stored += 1,
+            Err(e) => eprintln!("Failed to cache {}: {}", message.id, e),
+        }
+    }
+    Ok(stored)
+}
+
+/// Cache the newest inbox messages, returning how many the inbox shows
+pub async fn sync_recent_threads(
+    gmail_client: &GmailClient,
+    cache: &Mutex<MessageCache>,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let response = gmail_client
+        .list_messages(Some(RECENT_MESSAGE_COUNT), None, Some("in:inbox"))
+        .await?;
+    let ids: Vec<String> = response
+        .messages
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+
+    cache_messages(gmail_client, cache, &ids).await?;
+    Ok(ids.len())
+}
+
+/// Cache one page of older mail, returning the page size and the next page token
+pub async fn sync_history_page(
+    gmail_client: &GmailClient,
+    cache: &Mutex<MessageCache>,
+    page_token: Option<&str>,
+) -> Result<(usize, Option<String>), Box<dyn std::error::Error + Send + Sync>> {
+    let response = gmail_client
+        .list_messages(Some(HISTORY_PAGE_SIZE), page_token, None)
+        .await?;
+    let ids: Vec<String> = response
+        .messages
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+
+    cache_messages(gmail_client, cache, &ids).await?;
+    Ok((ids.len(), response.next_page_token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::LabelChange;
+
+    #[test]
+    fn test_pending_stages_in_order_and_history_deferrable() {
+        let mut state = OnboardingState::default();
+        assert_eq!(
+            state.pending_stages(),
+            vec![
+                OnboardingStage::Labels,
+                OnboardingStage::RecentThreads,
+                OnboardingStage::Contacts,
+                OnboardingStage::Signatures,
+                OnboardingStage::History,
+            ]
+        );
+
+        state.mark_complete(OnboardingStage::Labels);
+        state.history_deferred = true;
+        assert_eq!(state.pending_stages().len(), 3);
+        assert!(!state.pending_stages().contains(&OnboardingStage::History));
+    }
+
+    #[test]
+    fn test_history_completes_at_last_page_or_limit() {
+        let mut state = OnboardingState::default();
+        state.record_history_page(100, Some("page2".to_string()));
+        assert!(!state.is_complete(OnboardingStage::History));
+        assert_eq!(state.history_page_token.as_deref(), Some("page2"));
+
+        state.record_history_page(40, None);
+        assert!(state.is_complete(OnboardingStage::History));
+        assert_eq!(state.history_synced, 140);
+
+        let mut capped = OnboardingState::default();
+        capped.record_history_page(HISTORY_SYNC_LIMIT, Some("more".to_string()));
+        assert!(capped.is_complete(OnboardingStage::History));
+        assert!(capped.history_page_token.is_none());
+    }
+
+    #[test]
+    fn test_remote_changes_update_cached_inbox() {
+        let email = |id: &str, is_read: bool| Email {
+            id: id.to_string(),
+            thread_id: id.to_string(),
+            subject: String::new(),
+            sender: String::new(),
+            snippet: String::new(),
+            is_read,
+            is_no_reply: false,
+            is_important: false,
+            category: None,
+        };
+        let change = |id: &str, added: &[&str], removed: &[&str]| LabelChange {
+            message_id: id.to_string(),
+            thread_id: id.to_string(),
+            added_label_ids: added.iter().map(|l| l.to_string()).collect(),
+            removed_label_ids: removed.iter().map(|l| l.to_string()).collect(),
+        };
+        let mut snapshot = AccountSnapshot::default();
+        snapshot.record_inbox(
+            &[
+                email("m1", false),
+                email("m2", true),
+                email("m3", true),
+                email("m4", true),
+            ],
+            1_700_000_000,
+        );
+
+        let delta = MailboxDelta {
+            deleted: vec!["m4".to_string()],
+            label_changes: vec![
+                change("m1", &[], &["UNREAD"]),
+                change("m2", &["UNREAD", "IMPORTANT"], &[]),
+                change("m3", &[], &["INBOX"]),
+                change("elsewhere", &["STARRED"], &[]),
+            ],
+            ..Default::default()
+        };
+        assert!(snapshot.apply_delta(&delta));
+
+        let state: Vec<(&str, bool, bool)> = snapshot
+            .inbox
+            .iter()
+            .map(|e| (e.id.as_str(), e.is_read, e.is_important))
+            .collect();
+        assert_eq!(state, vec![("m1", true, false), ("m2", false, true)]);
+
+        // Already applied, e.g. the history echo of a change made in this app
+        assert!(!snapshot.apply_delta(&delta));
+    }
+
+    #[test]
+    fn test_cached_inbox_only_after_a_load() {
+        let mut snapshot = AccountSnapshot::default();
+        assert!(snapshot.cached_inbox().is_none());
+
+        // An empty inbox is still a known inbox
+        snapshot.record_inbox(&[], 1_700_000_000);
+        let cached = snapshot.cached_inbox().unwrap();
+        assert!(cached.emails.is_empty());
+        assert_eq!(cached.updated_at, Some(1_700_000_000));
+    }
+}
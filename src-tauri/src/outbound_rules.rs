@@ -0,0 +1,336 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::is_html_body;
+use crate::html_text;
+use crate::local_store::{app_data_path, load_json, save_json};
+use crate::settings::{OutboundAction, OutboundRule};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+const AUDIT_FILE: &str = "outbound_audit.json";
+
+/// Audit entries kept; older ones are dropped first
+const MAX_AUDIT_ENTRIES: usize = 1000;
+
+/// Digit runs that could be a card number, allowing single spaces or dashes
+/// between groups
+const CARD_CANDIDATE: &str = r"\b\d(?:[ -]?\d){12,18}\b";
+
+/// One rule that matched an outgoing message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboundMatch {
+    pub rule: String,
+    pub action: OutboundAction,
+    /// The first text that matched. Card numbers and pattern matches are
+    /// masked to their last four characters so the audit log never holds them.
+    pub matched: String,
+    /// Matches of this rule across the subject and body
+    pub occurrences: usize,
+}
+
+/// Returned instead of sending when a warning rule matched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutboundRuleWarning {
+    pub matches: Vec<OutboundMatch>,
+}
+
+impl OutboundRuleWarning {
+    /// The warning as one line, for commands that can only return an error
+    pub fn message(&self) -> String {
+        let found: Vec<String> = self
+            .matches
+            .iter()
+            .map(|m| format!("{} ({})", m.rule, m.matched))
+            .collect();
+        format!(
+            "The message matches outbound rules: {}; send again to confirm",
+            found.join(", ")
+        )
+    }
+}
+
+/// Check an outgoing subject and body against the rules. HTML bodies are
+/// checked as their text, so markup can't split or hide a match. Err when a
+/// rule's pattern doesn't compile.
+pub fn check(
+    rules: &[OutboundRule],
+    subject: &str,
+    body: &str,
+) -> Result<Vec<OutboundMatch>, String> {
+    let text = if is_html_body(body) {
+        format!("{}\n{}", subject, html_text::html_to_text(body))
+    } else {
+        format!("{}\n{}", subject, body)
+    };
+
+    let mut matches = Vec::new();
+    for rule in rules {
+        if let Some(found) = check_rule(rule, &text)? {
+            matches.push(found);
+        }
+    }
+    Ok(matches)
+}
+
+/// Whether any match is from a blocking rule
+pub fn is_blocked(matches: &[OutboundMatch]) -> bool {
+    matches.iter().any(|m| m.action == OutboundAction::Block)
+}
+
+/// Why a send was refused, naming the rules that blocked it
+pub fn block_message(matches: &[OutboundMatch]) -> String {
+    let rules: Vec<&str> = matches
+        .iter()
+        .filter(|m| m.action == OutboundAction::Block)
+        .map(|m| m.rule.as_str())
+        .collect();
+    format!("Not sent, blocked by outbound rules: {}", rules.join(", "))
+}
+
+fn check_rule(rule: &OutboundRule, text: &str) -> Result<Option<OutboundMatch>, String> {
+    let mut first: Option<String> = None;
+    let mut occurrences = 0;
+
+    let keywords: Vec<String> = rule
+        .keywords
+        .iter()
+        .map(|k| k.trim())
+        .filter(|k| !k.is_empty())
+        .map(|k| regex::escape(k).replace(r"\ ", r"\s+"))
+        .collect();
+    if !keywords.is_empty() {
+        let keyword_regex = RegexBuilder::new(&format!(r"\b(?:{})\b", keywords.join("|")))
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| format!("Outbound rule {} has a bad keyword: {}", rule.name, e))?;
+        for found in keyword_regex.find_iter(text) {
+            first.get_or_insert_with(|| found.as_str().to_string());
+            occurrences += 1;
+        }
+    }
+
+    if let Some(pattern) = rule.pattern.as_deref().filter(|p| !p.trim().is_empty()) {
+        let pattern_regex = Regex::new(pattern)
+            .map_err(|e| format!("Outbound rule {} has a bad pattern: {}", rule.name, e))?;
+        for found in pattern_regex.find_iter(text) {
+            first.get_or_insert_with(|| mask(found.as_str()));
+            occurrences += 1;
+        }
+    }
+
+    if rule.card_numbers {
+        let candidates = Regex::new(CARD_CANDIDATE).expect("card pattern compiles");
+        for found in candidates.find_iter(text) {
+            let digits: String = found
+                .as_str()
+                .chars()
+                .filter(char::is_ascii_digit)
+                .collect();
+            if passes_luhn(&digits) {
+                first.get_or_insert_with(|| mask(&digits));
+                occurrences += 1;
+            }
+        }
+    }
+
+    Ok(first.map(|matched| OutboundMatch {
+        rule: rule.name.clone(),
+        action: rule.action,
+        matched,
+        occurrences,
+    }))
+}
+
+/// The Luhn checksum every payment card number carries
+fn passes_luhn(digits: &str) -> bool {
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| match i % 2 {
+            1 if d * 2 > 9 => d * 2 - 9,
+            1 => d * 2,
+            _ => d,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Everything but the last four characters replaced with bullets
+fn mask(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let keep = chars.len().saturating_sub(4);
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, c)| if i < keep { '•' } else { *c })
+        .collect()
+}
+
+/// What became of a message that matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    /// Held back for the user to confirm
+    Warned,
+    /// Sent after the user confirmed a warning
+    Overridden,
+    Blocked,
+}
+
+/// One outgoing message that matched an outbound rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Unix timestamp (seconds)
+    pub at: u64,
+    pub subject: String,
+    pub recipients: Vec<String>,
+    pub outcome: AuditOutcome,
+    pub matches: Vec<OutboundMatch>,
+}
+
+/// Outgoing messages that tripped an outbound rule and what happened to
+/// them, newest last
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+    account_id: Option<String>,
+}
+
+impl AuditLog {
+    pub fn load() -> Self {
+        load_json(&app_data_path(AUDIT_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(AUDIT_FILE), self)
+    }
+
+    pub fn record(&mut self, entry: AuditEntry) {
+        self.entries.push(entry);
+        let excess = self.entries.len().saturating_sub(MAX_AUDIT_ENTRIES);
+        self.entries.drain(..excess);
+    }
+
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+}
+
+impl AccountScoped for AuditLog {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, action: OutboundAction) -> OutboundRule {
+        OutboundRule {
+            name: name.to_string(),
+            action,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_keywords_patterns_and_card_numbers() {
+        let rules = vec![
+            OutboundRule {
+                keywords: vec!["confidential".to_string(), "internal only".to_string()],
+                ..rule("Confidential", OutboundAction::Warn)
+            },
+            OutboundRule {
+                pattern: Some(r"\b\d{3}-\d{2}-\d{4}\b".to_string()),
+                ..rule("SSN", OutboundAction::Block)
+            },
+            OutboundRule {
+                card_numbers: true,
+                ..rule("Cards", OutboundAction::Block)
+            },
+        ];
+
+        let clean = check(
+            &rules,
+            "Lunch",
+            "Nonconfidential notes, order 1234 5678 9012 3456",
+        )
+        .unwrap();
+        assert!(clean.is_empty());
+
+        let found = check(
+            &rules,
+            "CONFIDENTIAL: numbers",
+            "<p>Internal&nbsp;only. Card 4111 1111 1111 1111, SSN 123-45-6789.</p>",
+        )
+        .unwrap();
+        assert_eq!(
+            found,
+            vec![
+                OutboundMatch {
+                    rule: "Confidential".to_string(),
+                    action: OutboundAction::Warn,
+                    matched: "CONFIDENTIAL".to_string(),
+                    occurrences: 2,
+                },
+                OutboundMatch {
+                    rule: "SSN".to_string(),
+                    action: OutboundAction::Block,
+                    matched: "•••••••6789".to_string(),
+                    occurrences: 1,
+                },
+                OutboundMatch {
+                    rule: "Cards".to_string(),
+                    action: OutboundAction::Block,
+                    matched: "••••••••••••1111".to_string(),
+                    occurrences: 1,
+                },
+            ]
+        );
+        assert!(is_blocked(&found));
+        assert_eq!(
+            block_message(&found),
+            "Not sent, blocked by outbound rules: SSN, Cards"
+        );
+
+        let broken = vec![OutboundRule {
+            pattern: Some("(unclosed".to_string()),
+            ..rule("Broken", OutboundAction::Warn)
+        }];
+        assert!(check(&broken, "", "anything").is_err());
+    }
+
+    #[test]
+    fn test_audit_log_keeps_the_newest_entries() {
+        let mut log = AuditLog::default();
+        for at in 0..(MAX_AUDIT_ENTRIES as u64 + 5) {
+            log.record(AuditEntry {
+                at,
+                subject: String::new(),
+                recipients: Vec::new(),
+                outcome: AuditOutcome::Warned,
+                matches: Vec::new(),
+            });
+        }
+        assert_eq!(log.entries().len(), MAX_AUDIT_ENTRIES);
+        assert_eq!(log.entries()[0].at, 5);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::{is_transient_error, GmailClient, MessageLabels};
+use crate::local_store::{app_data_path, load_json, save_json};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const PENDING_FILE: &str = "pending_actions.json";
+
+/// Extra attempts made right away before a transient failure is parked
+pub const IMMEDIATE_RETRIES: u32 = 2;
+
+/// Wait before the first immediate retry; doubles for each one after
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// A single-message change the user asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mutation {
+    MarkRead,
+    MarkUnread,
+    Archive,
+    ReportSpam,
+    MarkNotSpam,
+    Trash,
+    Untrash,
+    MarkImportant,
+    MarkNotImportant,
+    ReportPhishing,
+}
+
+impl Mutation {
+    pub async fn apply(
+        &self,
+        gmail_client: &GmailClient,
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            Mutation::MarkRead => gmail_client.mark_as_read(message_id).await,
+            Mutation::MarkUnread => gmail_client.mark_as_unread(message_id).await,
+            Mutation::Archive => gmail_client.archive_message(message_id).await,
+            Mutation::ReportSpam => gmail_client.report_spam(message_id).await,
+            Mutation::MarkNotSpam => gmail_client.mark_not_spam(message_id).await,
+            Mutation::Trash => gmail_client.trash_message(message_id).await,
+            Mutation::Untrash => gmail_client.untrash_message(message_id).await,
+            Mutation::MarkImportant => gmail_client.mark_as_important(message_id).await,
+            Mutation::MarkNotImportant => gmail_client.mark_not_important(message_id).await,
+            Mutation::ReportPhishing => gmail_client.report_phishing(message_id).await,
+        }
+    }
+
+    /// Apply, trying again after a short backoff while failures look transient
+    pub async fn apply_with_retries(
+        &self,
+        gmail_client: &GmailClient,
+        message_id: &str,
+    ) -> Result<MessageLabels, Box<dyn std::error::Error + Send + Sync>> {
+        let mut attempt = 0;
+        loop {
+            match self.apply(gmail_client, message_id).await {
+                Err(e) if attempt < IMMEDIATE_RETRIES && is_transient_error(e.as_ref()) => {
+                    tokio::time::sleep(RETRY_BACKOFF * 2u32.pow(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// A mutation that kept failing transiently, waiting for the user to retry or discard it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingAction {
+    pub id: String,
+    pub mutation: Mutation,
+    pub message_id: String,
+    pub last_error: String,
+    /// Failed runs, each including its immediate retries
+    pub attempts: u32,
+    /// Unix timestamps (seconds)
+    pub first_failed_at: u64,
+    pub last_attempt_at: u64,
+}
+
+/// Parked mutations, persisted so a restart doesn't lose them
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PendingActions {
+    actions: Vec<PendingAction>,
+    next_id: u64,
+    account_id: Option<String>,
+}
+
+impl PendingActions {
+    pub fn load() -> Self {
+        load_json(&app_data_path(PENDING_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(PENDING_FILE), self)
+    }
+
+    pub fn actions(&self) -> &[PendingAction] {
+        &self.actions
+    }
+
+    pub fn get(&self, action_id: &str) -> Option<&PendingAction> {
+        self.actions.iter().find(|a| a.id == action_id)
+    }
+
+    /// Queue a failed mutation. The same change to the same message is queued once.
+    pub fn park(
+        &mut self,
+        mutation: Mutation,
+        message_id: &str,
+        error: &str,
+        now: u64,
+    ) -> &PendingAction {
+        let index = match self
+            .actions
+            .iter()
+            .position(|a| a.mutation == mutation && a.message_id == message_id)
+        {
+            Some(index) => index,
+            None => {
+                self.next_id += 1;
+                self.actions.push(PendingAction {
+                    id: format!("pending_{}", self.next_id),
+                    mutation,
+                    message_id: message_id.to_string(),
+                    last_error: String::new(),
+                    attempts: 0,
+                    first_failed_at: now,
+                    last_attempt_at: now,
+                });
+                self.actions.len() - 1
+            }
+        };
+        self.record_failure_at(index, error, now);
+        &self.actions[index]
+    }
+
+    pub fn record_failure(&mut self, action_id: &str, error: &str, now: u64) {
+        if let Some(index) = self.actions.iter().position(|a| a.id == action_id) {
+            self.record_failure_at(index, error, now);
+        }
+    }
+
+    fn record_failure_at(&mut self, index: usize, error: &str, now: u64) {
+        let action = &mut self.actions[index];
+        action.attempts += 1;
+        action.last_error = error.to_string();
+        action.last_attempt_at = now;
+    }
+
+    pub fn remove(&mut self, action_id: &str) -> Option<PendingAction> {
+        let index = self.actions.iter().position(|a| a.id == action_id)?;
+        Some(self.actions.remove(index))
+    }
+}
+
+impl AccountScoped for PendingActions {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_park_dedupes_and_counts_attempts() {
+        let mut pending = PendingActions::default();
+        let first = pending
+            .park(Mutation::Archive, "m1", "timed out", 10)
+            .clone();
+        let again = pending.park(Mutation::Archive, "m1", "503", 20).clone();
+        pending.park(Mutation::MarkRead, "m1", "timed out", 30);
+
+        assert_eq!(pending.actions().len(), 2);
+        assert_eq!(again.id, first.id);
+        assert_eq!(again.attempts, 2);
+        assert_eq!(again.first_failed_at, 10);
+        assert_eq!(again.last_error, "503");
+
+        pending.record_failure(&first.id, "still down", 40);
+        assert_eq!(pending.get(&first.id).unwrap().attempts, 3);
+
+        assert!(pending.remove(&first.id).is_some());
+        assert!(pending.remove(&first.id).is_none());
+        // Ids aren't reused after a removal
+        let next = pending.park(Mutation::Archive, "m1", "x", 50).id.clone();
+        assert_ne!(next, first.id);
+    }
+}
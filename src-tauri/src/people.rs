@@ -0,0 +1,345 @@
+use crate::gmail_auth::AuthTokens;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const PERSON_FIELDS: &str = "names,emailAddresses,organizations,phoneNumbers,photos";
+
+/// Other contacts only carry these
+const OTHER_CONTACT_FIELDS: &str = "names,emailAddresses";
+
+/// Largest page either search endpoint returns
+pub const MAX_SEARCH_RESULTS: u32 = 30;
+
+/// The search endpoints answer from a cache that the first, empty query of a
+/// session fills; queries before that can come back empty
+static SEARCH_WARMED_UP: AtomicBool = AtomicBool::new(false);
+
+/// What the People API knows about a contact
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContactInfo {
+    pub resource_name: String,
+    pub display_name: Option<String>,
+    pub email_addresses: Vec<String>,
+    pub organization: Option<String>,
+    pub job_title: Option<String>,
+    pub phone_numbers: Vec<String>,
+    pub photo_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    results: Option<Vec<SearchResult>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConnectionsResponse {
+    connections: Option<Vec<Person>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    person: Person,
+}
+
+#[derive(Debug, Deserialize)]
+struct Person {
+    #[serde(rename = "resourceName")]
+    resource_name: String,
+    names: Option<Vec<PersonName>>,
+    #[serde(rename = "emailAddresses")]
+    email_addresses: Option<Vec<PersonValue>>,
+    organizations: Option<Vec<Organization>>,
+    #[serde(rename = "phoneNumbers")]
+    phone_numbers: Option<Vec<PersonValue>>,
+    photos: Option<Vec<Photo>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PersonName {
+    #[serde(rename = "displayName")]
+    display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PersonValue {
+    value: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Organization {
+    name: Option<String>,
+    title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Photo {
+    url: Option<String>,
+}
+
+impl Person {
+    fn has_email(&self, email: &str) -> bool {
+        self.email_addresses.iter().flatten().any(|e| {
+            e.value
+                .as_deref()
+                .is_some_and(|v| v.eq_ignore_ascii_case(email))
+        })
+    }
+
+    fn into_contact(self) -> ContactInfo {
+        let organization = self.organizations.and_then(|o| o.into_iter().next());
+        ContactInfo {
+            resource_name: self.resource_name,
+            display_name: self
+                .names
+                .and_then(|n| n.into_iter().find_map(|n| n.display_name)),
+            email_addresses: self
+                .email_addresses
+                .into_iter()
+                .flatten()
+                .filter_map(|e| e.value)
+                .collect(),
+            job_title: organization.as_ref().and_then(|o| o.title.clone()),
+            organization: organization.and_then(|o| o.name),
+            phone_numbers: self
+                .phone_numbers
+                .into_iter()
+                .flatten()
+                .filter_map(|p| p.value)
+                .collect(),
+            photo_url: self.photos.and_then(|p| p.into_iter().find_map(|p| p.url)),
+        }
+    }
+}
+
+/// Saved contacts first, then other contacts (addresses Gmail collected
+/// from mail) that add an address not already listed, up to `limit`
+fn merge_search_results(
+    saved: Vec<ContactInfo>,
+    other: Vec<ContactInfo>,
+    limit: usize,
+) -> Vec<ContactInfo> {
+    let mut seen = HashSet::new();
+    saved
+        .into_iter()
+        .chain(other)
+        .filter(|contact| {
+            let mut new_address = false;
+            for email in &contact.email_addresses {
+                new_address |= seen.insert(email.to_ascii_lowercase());
+            }
+            new_address
+        })
+        .take(limit)
+        .collect()
+}
+
+/// Google People API access with the same tokens as Gmail. Needs the
+/// contacts.readonly scope, and contacts.other.readonly for other contacts,
+/// so accounts authorized before they were requested get errors until they
+/// sign in again.
+pub struct PeopleClient {
+    client: Client,
+    access_token: String,
+}
+
+impl PeopleClient {
+    pub fn new(tokens: &AuthTokens) -> Self {
+        Self {
+            client: Client::new(),
+            access_token: tokens.access_token.clone(),
+        }
+    }
+
+    /// The saved contact with this email address, if there is one
+    pub async fn find_contact(
+        &self,
+        email: &str,
+    ) -> Result<Option<ContactInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self
+            .client
+            .get("https://people.googleapis.com/v1/people:searchContacts")
+            .query(&[("query", email), ("readMask", PERSON_FIELDS)])
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("People API error: {}", error_text).into());
+        }
+
+        let search: SearchResponse = response.json().await?;
+        // searchContacts matches prefixes, so confirm the address is really there
+        Ok(search
+            .results
+            .unwrap_or_default()
+            .into_iter()
+            .map(|r| r.person)
+            .find(|p| p.has_email(email))
+            .map(Person::into_contact))
+    }
+
+    /// Contacts with an email address whose name, address or other details
+    /// start with `query`, for recipient lookup. Other contacts are skipped
+    /// when the account hasn't granted their scope.
+    pub async fn search_contacts(
+        &self,
+        query: &str,
+        limit: u32,
+    ) -> Result<Vec<ContactInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        let page_size = limit.clamp(1, MAX_SEARCH_RESULTS);
+        if !SEARCH_WARMED_UP.swap(true, Ordering::Relaxed) {
+            let _ = tokio::join!(
+                self.search("people:searchContacts", "", PERSON_FIELDS, page_size),
+                self.search("otherContacts:search", "", OTHER_CONTACT_FIELDS, page_size),
+            );
+        }
+
+        let (saved, other) = tokio::join!(
+            self.search("people:searchContacts", query, PERSON_FIELDS, page_size),
+            self.search(
+                "otherContacts:search",
+                query,
+                OTHER_CONTACT_FIELDS,
+                page_size
+            ),
+        );
+        let other = other.unwrap_or_else(|e| {
+            eprintln!("Other contacts search failed: {}", e);
+            Vec::new()
+        });
+        Ok(merge_search_results(saved?, other, page_size as usize))
+    }
+
+    async fn search(
+        &self,
+        endpoint: &str,
+        query: &str,
+        read_mask: &str,
+        page_size: u32,
+    ) -> Result<Vec<ContactInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self
+            .client
+            .get(format!("https://people.googleapis.com/v1/{}", endpoint))
+            .query(&[
+                ("query", query),
+                ("readMask", read_mask),
+                ("pageSize", page_size.to_string().as_str()),
+            ])
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("People API error: {}", error_text).into());
+        }
+
+        let search: SearchResponse = response.json().await?;
+        Ok(search
+            .results
+            .unwrap_or_default()
+            .into_iter()
+            .map(|r| r.person.into_contact())
+            .filter(|c| !c.email_addresses.is_empty())
+            .collect())
+    }
+
+    /// Saved contacts that have an email address, up to `limit` (at most 1000)
+    pub async fn list_contacts(
+        &self,
+        limit: u32,
+    ) -> Result<Vec<ContactInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        let page_size = limit.clamp(1, 1000).to_string();
+        let response = self
+            .client
+            .get("https://people.googleapis.com/v1/people/me/connections")
+            .query(&[
+                ("personFields", PERSON_FIELDS),
+                ("pageSize", page_size.as_str()),
+                ("sortOrder", "LAST_MODIFIED_DESCENDING"),
+            ])
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("People API error: {}", error_text).into());
+        }
+
+        let connections: ConnectionsResponse = response.json().await?;
+        Ok(connections
+            .connections
+            .unwrap_or_default()
+            .into_iter()
+            .map(Person::into_contact)
+            .filter(|c| !c.email_addresses.is_empty())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_response_to_contact() {
+        let search: SearchResponse = serde_json::from_value(serde_json::json!({
+            "results": [
+                { "person": {
+                    "resourceName": "people/c1",
+                    "emailAddresses": [{ "value": "alice.smith@example.com" }]
+                }},
+                { "person": {
+                    "resourceName": "people/c2",
+                    "names": [{ "displayName": "Alice" }],
+                    "emailAddresses": [{ "value": "Alice@Example.com" }],
+                    "organizations": [{ "name": "Example Inc", "title": "CTO" }],
+                    "phoneNumbers": [{ "value": "+1 555 0100" }]
+                }}
+            ]
+        }))
+        .unwrap();
+
+        let contact = search
+            .results
+            .unwrap()
+            .into_iter()
+            .map(|r| r.person)
+            .find(|p| p.has_email("alice@example.com"))
+            .map(Person::into_contact)
+            .unwrap();
+
+        assert_eq!(contact.resource_name, "people/c2");
+        assert_eq!(contact.display_name.as_deref(), Some("Alice"));
+        assert_eq!(contact.organization.as_deref(), Some("Example Inc"));
+        assert_eq!(contact.job_title.as_deref(), Some("CTO"));
+        assert_eq!(contact.phone_numbers, vec!["+1 555 0100".to_string()]);
+    }
+
+    #[test]
+    fn test_search_results_merge_without_duplicates() {
+        let contact = |name: &str, emails: &[&str]| ContactInfo {
+            resource_name: format!("people/{}", name),
+            display_name: Some(name.to_string()),
+            email_addresses: emails.iter().map(|e| e.to_string()).collect(),
+            ..Default::default()
+        };
+        let saved = vec![contact("alice", &["alice@example.com"])];
+        let other = vec![
+            contact("alice-other", &["Alice@Example.com"]),
+            contact("alan", &["alan@example.com"]),
+            contact("alice-both", &["alice@example.com", "alice@home.example"]),
+            contact("al", &["al@example.com"]),
+        ];
+
+        let names: Vec<String> = merge_search_results(saved, other, 3)
+            .into_iter()
+            .filter_map(|c| c.display_name)
+            .collect();
+        assert_eq!(names, vec!["alice", "alan", "alice-both"]);
+    }
+}
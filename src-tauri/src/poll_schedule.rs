@@ -0,0 +1,115 @@
+use crate::settings::PollingSettings;
+
+/// Checks in a row without new mail before the interval doubles
+const IDLE_CHECKS_PER_STEP: u32 = 3;
+
+/// Doublings applied on top of idle backoff while no window has focus
+const UNFOCUSED_STEPS: u32 = 2;
+
+/// Adaptive new-mail polling: the interval doubles after runs of empty checks
+/// and while the app is in the background, up to the configured ceiling, and
+/// snaps back to the base interval on new mail or focus.
+#[derive(Debug, Clone)]
+pub struct PollSchedule {
+    idle_checks: u32,
+    focused: bool,
+}
+
+impl Default for PollSchedule {
+    fn default() -> Self {
+        PollSchedule {
+            idle_checks: 0,
+            focused: true,
+        }
+    }
+}
+
+impl PollSchedule {
+    /// Note a finished check and how many new messages it found
+    pub fn record_check(&mut self, new_mail: usize) {
+        if new_mail > 0 {
+            self.idle_checks = 0;
+        } else {
+            self.idle_checks = self.idle_checks.saturating_add(1);
+        }
+    }
+
+    /// Track window focus; returns true when the app just came to the
+    /// foreground and a check should run right away
+    pub fn set_focused(&mut self, focused: bool) -> bool {
+        let woke = focused && !self.focused;
+        self.focused = focused;
+        if woke {
+            self.idle_checks = 0;
+        }
+        woke
+    }
+
+    /// Seconds until the next check
+    pub fn next_interval(&self, settings: &PollingSettings) -> u64 {
+        let base = settings.interval_secs.max(1);
+        if !settings.adaptive {
+            return base;
+        }
+
+        let mut steps = self.idle_checks / IDLE_CHECKS_PER_STEP;
+        if !self.focused {
+            steps += UNFOCUSED_STEPS;
+        }
+        let ceiling = settings.max_interval_secs.max(base);
+        base.saturating_mul(1u64 << steps.min(16)).min(ceiling)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backs_off_when_idle_and_resets_on_mail() {
+        let settings = PollingSettings::default();
+        let mut schedule = PollSchedule::default();
+        assert_eq!(schedule.next_interval(&settings), 60);
+
+        for _ in 0..IDLE_CHECKS_PER_STEP {
+            schedule.record_check(0);
+        }
+        assert_eq!(schedule.next_interval(&settings), 120);
+
+        for _ in 0..(IDLE_CHECKS_PER_STEP * 10) {
+            schedule.record_check(0);
+        }
+        assert_eq!(schedule.next_interval(&settings), 15 * 60);
+
+        schedule.record_check(2);
+        assert_eq!(schedule.next_interval(&settings), 60);
+    }
+
+    #[test]
+    fn test_unfocused_slows_down_and_focus_wakes() {
+        let settings = PollingSettings::default();
+        let mut schedule = PollSchedule::default();
+
+        assert!(!schedule.set_focused(false));
+        assert_eq!(schedule.next_interval(&settings), 240);
+
+        assert!(schedule.set_focused(true));
+        assert!(!schedule.set_focused(true));
+        assert_eq!(schedule.next_interval(&settings), 60);
+    }
+
+    #[test]
+    fn test_fixed_interval_when_not_adaptive() {
+        let settings = PollingSettings {
+            interval_secs: 120,
+            adaptive: false,
+            ..Default::default()
+        };
+        let mut schedule = PollSchedule::default();
+        schedule.set_focused(false);
+        for _ in 0..20 {
+            schedule.record_check(0);
+        }
+        assert_eq!(schedule.next_interval(&settings), 120);
+    }
+}
@@ -0,0 +1,531 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Gmail clips messages whose HTML is bigger than this, hiding the rest behind a link
+const GMAIL_CLIP_BYTES: usize = 102 * 1024;
+
+/// Inline (data: URI) images above these decoded sizes bloat every copy of the message
+const LARGE_INLINE_IMAGE_BYTES: usize = 100 * 1024;
+const HUGE_INLINE_IMAGE_BYTES: usize = 1024 * 1024;
+
+/// Remote images checked per preflight, and how long each check may take
+const MAX_REMOTE_IMAGE_CHECKS: usize = 20;
+const REMOTE_IMAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Image-only mail with less visible text than this looks like spam to filters
+const MIN_TEXT_WITH_IMAGES: usize = 200;
+
+/// Phrases spam filters weigh heavily, lowercased
+const SPAM_PHRASES: &[&str] = &[
+    "100% free",
+    "act now",
+    "click here",
+    "earn money",
+    "guaranteed",
+    "limited time offer",
+    "no credit check",
+    "risk-free",
+    "this is not spam",
+    "you are a winner",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightSeverity {
+    /// Worth a look, but fine to send
+    Warning,
+    /// Recipients will likely see something broken
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightCheck {
+    BrokenImage,
+    MissingAltText,
+    LargeInlineImage,
+    MessageClipped,
+    ImageHeavy,
+    MisleadingLink,
+    SpamTrigger,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PreflightIssue {
+    pub check: PreflightCheck,
+    pub severity: PreflightSeverity,
+    pub message: String,
+}
+
+/// Findings for one outgoing message
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PreflightReport {
+    pub issues: Vec<PreflightIssue>,
+    pub image_count: usize,
+    pub link_count: usize,
+    pub has_errors: bool,
+}
+
+impl PreflightReport {
+    fn push(&mut self, check: PreflightCheck, severity: PreflightSeverity, message: String) {
+        self.has_errors |= severity == PreflightSeverity::Error;
+        self.issues.push(PreflightIssue {
+            check,
+            severity,
+            message,
+        });
+    }
+}
+
+/// Run every check, including fetching remote images when `check_remote_images` is set
+pub async fn run(subject: &str, body: &str, check_remote_images: bool) -> PreflightReport {
+    let mut report = analyze(subject, body);
+    if !check_remote_images {
+        return report;
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(REMOTE_IMAGE_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+
+    for src in remote_image_sources(body)
+        .into_iter()
+        .take(MAX_REMOTE_IMAGE_CHECKS)
+    {
+        if let Err(reason) = check_remote_image(&client, &src).await {
+            report.push(
+                PreflightCheck::BrokenImage,
+                PreflightSeverity::Error,
+                format!("Image {} could not be loaded: {}", src, reason),
+            );
+        }
+    }
+    report
+}
+
+/// Checks that need no network access
+pub fn analyze(subject: &str, body: &str) -> PreflightReport {
+    let mut report = PreflightReport::default();
+
+    if body.len() > GMAIL_CLIP_BYTES {
+        report.push(
+            PreflightCheck::MessageClipped,
+            PreflightSeverity::Warning,
+            format!(
+                "Message is {} KB; Gmail clips anything over {} KB",
+                body.len() / 1024,
+                GMAIL_CLIP_BYTES / 1024
+            ),
+        );
+    }
+
+    let images = find_tags(body, "img");
+    report.image_count = images.len();
+    for image in &images {
+        let src = image.get("src").map(String::as_str).unwrap_or("").trim();
+        let label = short(src);
+
+        if !image.contains_key("alt") {
+            report.push(
+                PreflightCheck::MissingAltText,
+                PreflightSeverity::Warning,
+                format!("Image {} has no alt text", label),
+            );
+        }
+
+        if src.is_empty() {
+            report.push(
+                PreflightCheck::BrokenImage,
+                PreflightSeverity::Error,
+                "Image has no source".to_string(),
+            );
+        } else if let Some(size) = inline_image_size(src) {
+            if size > LARGE_INLINE_IMAGE_BYTES {
+                let severity = if size > HUGE_INLINE_IMAGE_BYTES {
+                    PreflightSeverity::Error
+                } else {
+                    PreflightSeverity::Warning
+                };
+                report.push(
+                    PreflightCheck::LargeInlineImage,
+                    severity,
+                    format!("Inline image is {} KB", size / 1024),
+                );
+            }
+        } else if !is_absolute_source(src) {
+            report.push(
+                PreflightCheck::BrokenImage,
+                PreflightSeverity::Error,
+                format!("Image {} uses a path recipients can't load", label),
+            );
+        }
+    }
+
+    let text = visible_text(body);
+    if !images.is_empty() && text.chars().count() < MIN_TEXT_WITH_IMAGES {
+        report.push(
+            PreflightCheck::ImageHeavy,
+            PreflightSeverity::Warning,
+            "Message is mostly images with little text".to_string(),
+        );
+    }
+
+    let links = find_links(body);
+    report.link_count = links.len();
+    for (href, link_text) in links {
+        if let (Some(shown), Some(target)) = (url_host(&link_text), url_host(&href)) {
+            if !shown.eq_ignore_ascii_case(&target) {
+                report.push(
+                    PreflightCheck::MisleadingLink,
+                    PreflightSeverity::Warning,
+                    format!("Link text shows {} but points to {}", shown, target),
+                );
+            }
+        }
+    }
+
+    check_spam_triggers(&mut report, subject, &text);
+    report
+}
+
+fn check_spam_triggers(report: &mut PreflightReport, subject: &str, text: &str) {
+    let letters: Vec<char> = subject.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() >= 5 && letters.iter().all(|c| c.is_uppercase()) {
+        report.push(
+            PreflightCheck::SpamTrigger,
+            PreflightSeverity::Warning,
+            "Subject is all capitals".to_string(),
+        );
+    }
+
+    if subject.matches('!').count() > 1 {
+        report.push(
+            PreflightCheck::SpamTrigger,
+            PreflightSeverity::Warning,
+            "Subject has several exclamation marks".to_string(),
+        );
+    }
+
+    let haystack = format!("{} {}", subject, text).to_lowercase();
+    for phrase in SPAM_PHRASES {
+        if haystack.contains(phrase) {
+            report.push(
+                PreflightCheck::SpamTrigger,
+                PreflightSeverity::Warning,
+                format!("\"{}\" is a common spam-filter trigger", phrase),
+            );
+        }
+    }
+}
+
+async fn check_remote_image(client: &reqwest::Client, src: &str) -> Result<(), String> {
+    let mut response = client.head(src).send().await.map_err(|e| e.to_string())?;
+
+    // Some image hosts refuse HEAD
+    if response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+        response = client.get(src).send().await.map_err(|e| e.to_string())?;
+    }
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if !content_type.is_empty() && !content_type.starts_with("image/") {
+        return Err(format!("served as {}", content_type));
+    }
+    Ok(())
+}
+
+fn remote_image_sources(body: &str) -> Vec<String> {
+    find_tags(body, "img")
+        .into_iter()
+        .filter_map(|tag| tag.get("src").map(|src| src.trim().to_string()))
+        .filter(|src| src.starts_with("http://") || src.starts_with("https://"))
+        .collect()
+}
+
+/// Decoded size of a base64 data: URI image
+fn inline_image_size(src: &str) -> Option<usize> {
+    let rest = src.strip_prefix("data:")?;
+    let (_, data) = rest.split_once(";base64,")?;
+    Some(data.trim().len() * 3 / 4)
+}
+
+fn is_absolute_source(src: &str) -> bool {
+    ["http://", "https://", "cid:"]
+        .iter()
+        .any(|scheme| src.len() >= scheme.len() && src[..scheme.len()].eq_ignore_ascii_case(scheme))
+}
+
+fn url_host(value: &str) -> Option<String> {
+    let value = value.trim();
+    let rest = value
+        .strip_prefix("https://")
+        .or_else(|| value.strip_prefix("http://"))
+        .or_else(|| value.strip_prefix("www.").map(|_| value))?;
+    let host = rest.split(['/', '?', '#', ' ']).next()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    (!host.is_empty() && host.contains('.')).then(|| host.to_string())
+}
+
+fn short(src: &str) -> String {
+    if src.starts_with("data:") {
+        "(inline)".to_string()
+    } else if src.chars().count() > 60 {
+        format!("{}...", src.chars().take(60).collect::<String>())
+    } else {
+        src.to_string()
+    }
+}
+
+/// Attributes of every `<name ...>` tag, with lowercased attribute names
+fn find_tags(html: &str, name: &str) -> Vec<HashMap<String, String>> {
+    let lower = html.to_ascii_lowercase();
+    let opener = format!("<{}", name);
+    let mut tags = Vec::new();
+    let mut pos = 0;
+
+    while let Some(found) = lower[pos..].find(&opener) {
+        let start = pos + found + opener.len();
+        pos = start;
+        // Skip longer tag names sharing the prefix, e.g. <imgx>
+        if !html[start..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_whitespace() || c == '/' || c == '>')
+        {
+            continue;
+        }
+
+        let end = tag_end(html, start);
+        tags.push(parse_attributes(&html[start..end]));
+        pos = end;
+    }
+    tags
+}
+
+/// Index of the `>` closing a tag, skipping over quoted attribute values
+fn tag_end(html: &str, from: usize) -> usize {
+    let mut quote = None;
+    for (i, c) in html[from..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return from + i,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+fn parse_attributes(source: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() || c == '/' {
+            chars.next();
+            continue;
+        }
+
+        let mut name_end = start;
+        while let Some(&(i, c)) = chars.peek() {
+            if c.is_whitespace() || c == '=' || c == '/' {
+                break;
+            }
+            name_end = i + c.len_utf8();
+            chars.next();
+        }
+        let name = source[start..name_end].to_ascii_lowercase();
+        if name.is_empty() {
+            chars.next();
+            continue;
+        }
+
+        while chars.peek().is_some_and(|&(_, c)| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().map(|&(_, c)| c) != Some('=') {
+            attributes.insert(name, String::new());
+            continue;
+        }
+        chars.next();
+        while chars.peek().is_some_and(|&(_, c)| c.is_whitespace()) {
+            chars.next();
+        }
+
+        let value = match chars.peek().copied() {
+            Some((i, q @ ('"' | '\''))) => {
+                chars.next();
+                let value_start = i + 1;
+                let mut value_end = source.len();
+                for (j, c) in chars.by_ref() {
+                    if c == q {
+                        value_end = j;
+                        break;
+                    }
+                }
+                &source[value_start..value_end]
+            }
+            Some((i, _)) => {
+                let mut value_end = source.len();
+                while let Some(&(j, c)) = chars.peek() {
+                    if c.is_whitespace() {
+                        value_end = j;
+                        break;
+                    }
+                    chars.next();
+                }
+                &source[i..value_end]
+            }
+            None => "",
+        };
+        attributes.insert(name, value.to_string());
+    }
+    attributes
+}
+
+/// (href, visible text) for each `<a>` element
+fn find_links(html: &str) -> Vec<(String, String)> {
+    let lower = html.to_ascii_lowercase();
+    let mut links = Vec::new();
+    let mut pos = 0;
+
+    while let Some(found) = lower[pos..].find("<a") {
+        let start = pos + found + 2;
+        pos = start;
+        if !html[start..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_whitespace() || c == '>')
+        {
+            continue;
+        }
+
+        let open_end = tag_end(html, start);
+        let href = parse_attributes(&html[start..open_end])
+            .remove("href")
+            .unwrap_or_default();
+        let content_start = (open_end + 1).min(html.len());
+        let content_end = lower[content_start..]
+            .find("</a")
+            .map(|i| content_start + i)
+            .unwrap_or(html.len());
+
+        if !href.is_empty() {
+            links.push((href, visible_text(&html[content_start..content_end])));
+        }
+        pos = content_end;
+    }
+    links
+}
+
+/// Text outside of tags, with style and script contents left out
+fn visible_text(html: &str) -> String {
+    let mut text = String::new();
+    let lower = html.to_ascii_lowercase();
+    let mut pos = 0;
+
+    while pos < html.len() {
+        match html[pos..].find('<') {
+            Some(offset) => {
+                text.push_str(&html[pos..pos + offset]);
+                let tag_start = pos + offset;
+                let mut next = tag_end(html, tag_start + 1) + 1;
+
+                for hidden in ["style", "script"] {
+                    if lower[tag_start + 1..].starts_with(hidden) {
+                        let close = format!("</{}", hidden);
+                        next = lower[tag_start..]
+                            .find(&close)
+                            .map(|i| tag_end(html, tag_start + i) + 1)
+                            .unwrap_or(html.len());
+                    }
+                }
+                text.push(' ');
+                pos = next.min(html.len());
+            }
+            None => {
+                text.push_str(&html[pos..]);
+                break;
+            }
+        }
+    }
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checks(report: &PreflightReport) -> Vec<PreflightCheck> {
+        report.issues.iter().map(|issue| issue.check).collect()
+    }
+
+    #[test]
+    fn test_image_checks() {
+        let inline = format!("data:image/png;base64,{}", "A".repeat(200 * 1024));
+        let body = format!(
+            r#"<p>{}</p>
+            <img src="https://cdn.example.com/logo.png" alt="Logo">
+            <IMG SRC='images/banner.png'>
+            <img alt="" src="{}" />"#,
+            "Plenty of text. ".repeat(20),
+            inline
+        );
+
+        let report = analyze("Monthly update", &body);
+        assert_eq!(report.image_count, 3);
+        assert!(report.has_errors);
+        assert_eq!(
+            checks(&report),
+            vec![
+                PreflightCheck::MessageClipped,
+                PreflightCheck::MissingAltText,
+                PreflightCheck::BrokenImage,
+                PreflightCheck::LargeInlineImage,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_misleading_links_and_spam_triggers() {
+        let body = r#"<a href="https://tracker.example.net/x">https://www.bank.example.com/login</a>
+            <a href="https://example.com/docs">the docs</a> Act now!"#;
+
+        let report = analyze("FREE OFFER!!", body);
+        assert_eq!(report.link_count, 2);
+        assert!(!report.has_errors);
+        assert_eq!(
+            checks(&report),
+            vec![
+                PreflightCheck::MisleadingLink,
+                PreflightCheck::SpamTrigger,
+                PreflightCheck::SpamTrigger,
+                PreflightCheck::SpamTrigger,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plain_text_passes() {
+        let report = analyze("Lunch tomorrow?", "Want to grab lunch at noon?");
+        assert!(report.issues.is_empty());
+    }
+
+    #[test]
+    fn test_visible_text_skips_tags_and_styles() {
+        assert_eq!(
+            visible_text("<style>p { color: red }</style><p>Hello <b>there</b></p>"),
+            "Hello there"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use crate::gmail_client::{GmailClient, GmailProfile};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a fetched profile is reused. Message totals move with every
+/// new mail, so this stays short.
+pub const PROFILE_TTL: Duration = Duration::from_secs(60);
+
+/// Recently fetched Gmail profiles, so account-info commands don't each
+/// spend a profile request
+#[derive(Debug, Default)]
+pub struct ProfileCache {
+    /// Keyed by the access token the profile was fetched with, so one
+    /// account's profile is never handed to another account's session
+    entries: HashMap<String, (GmailProfile, Instant)>,
+}
+
+impl ProfileCache {
+    /// The profile fetched with `access_token`, if it's under `PROFILE_TTL` old
+    pub fn get(&self, access_token: &str, now: Instant) -> Option<GmailProfile> {
+        self.entries
+            .get(access_token)
+            .filter(|(_, fetched_at)| now.saturating_duration_since(*fetched_at) < PROFILE_TTL)
+            .map(|(profile, _)| profile.clone())
+    }
+
+    /// Remember `profile`, dropping anything expired
+    pub fn insert(&mut self, access_token: &str, profile: GmailProfile, now: Instant) {
+        self.entries
+            .retain(|_, (_, fetched_at)| now.saturating_duration_since(*fetched_at) < PROFILE_TTL);
+        self.entries
+            .insert(access_token.to_string(), (profile, now));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// The signed-in account's profile, from the cache while it's fresh
+pub async fn load_profile(
+    gmail_client: &GmailClient,
+    cache: &Mutex<ProfileCache>,
+    now: Instant,
+) -> Result<GmailProfile, Box<dyn std::error::Error + Send + Sync>> {
+    let access_token = gmail_client.access_token();
+    if let Some(profile) = cache.lock().unwrap().get(access_token, now) {
+        return Ok(profile);
+    }
+
+    let profile = gmail_client.get_profile().await?;
+    cache
+        .lock()
+        .unwrap()
+        .insert(access_token, profile.clone(), now);
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(address: &str) -> GmailProfile {
+        GmailProfile {
+            email_address: address.to_string(),
+            messages_total: Some(10),
+            threads_total: Some(5),
+            history_id: Some("1".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_profiles_expire_and_stay_per_token() {
+        let start = Instant::now();
+        let mut cache = ProfileCache::default();
+        cache.insert("token-a", profile("a@example.com"), start);
+
+        let hit = cache
+            .get("token-a", start + Duration::from_secs(59))
+            .unwrap();
+        assert_eq!(hit.email_address, "a@example.com");
+        assert!(cache.get("token-b", start).is_none());
+        assert!(cache.get("token-a", start + PROFILE_TTL).is_none());
+
+        // Expired entries are dropped when something new is stored
+        cache.insert("token-b", profile("b@example.com"), start + PROFILE_TTL);
+        assert_eq!(cache.entries.len(), 1);
+
+        cache.clear();
+        assert!(cache.get("token-b", start + PROFILE_TTL).is_none());
+    }
+}
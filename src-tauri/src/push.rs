@@ -0,0 +1,225 @@
+use crate::gmail_auth::AuthTokens;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+/// Re-watch this long before Gmail's 7-day watch lapses
+pub const RENEW_MARGIN_SECS: u64 = 24 * 60 * 60;
+
+/// Wait before retrying a failed watch; mail is polled meanwhile
+pub const RETRY_SECS: u64 = 5 * 60;
+
+/// Notifications taken per pull; one is enough to trigger a history check
+const PULL_MAX_MESSAGES: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PushMode {
+    /// Changes arrive through the Pub/Sub subscription
+    Push,
+    /// Push is off or failed; the backend polls history instead
+    #[default]
+    Polling,
+}
+
+/// Payload of the "push-status" event and `get_push_status`
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PushStatus {
+    pub mode: PushMode,
+    /// Unix timestamp (seconds) when the current watch lapses
+    pub expires_at: Option<u64>,
+    /// Why push isn't active, when it was asked for
+    pub last_error: Option<String>,
+    /// Unix timestamp (seconds) before which a failed watch isn't retried
+    pub retry_at: Option<u64>,
+}
+
+impl PushStatus {
+    /// Whether a users.watch call is due: not watching yet, or close to expiry
+    pub fn needs_watch(&self, now: u64) -> bool {
+        match (self.mode, self.expires_at) {
+            (PushMode::Push, Some(expires_at)) => now + RENEW_MARGIN_SECS >= expires_at,
+            _ => true,
+        }
+    }
+
+    pub fn watching(expires_at: Option<u64>) -> Self {
+        PushStatus {
+            mode: PushMode::Push,
+            expires_at,
+            last_error: None,
+            retry_at: None,
+        }
+    }
+
+    /// Fallback after a watch or pull failure at `now`
+    pub fn polling(error: String, now: u64) -> Self {
+        PushStatus {
+            mode: PushMode::Polling,
+            expires_at: None,
+            last_error: Some(error),
+            retry_at: Some(now + RETRY_SECS),
+        }
+    }
+}
+
+/// What Gmail publishes on each mailbox change
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GmailNotification {
+    #[serde(rename = "emailAddress")]
+    pub email_address: String,
+    /// Gmail sends this as a number, unlike everywhere else
+    #[serde(rename = "historyId")]
+    pub history_id: u64,
+}
+
+impl GmailNotification {
+    /// Decode the base64 JSON `data` of a Pub/Sub message
+    pub fn decode(data: &str) -> Option<Self> {
+        let bytes = STANDARD.decode(data).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Whether this is news about `address`'s mailbox; one topic can carry
+    /// watches for several accounts
+    pub fn is_for(&self, address: &str) -> bool {
+        self.email_address.eq_ignore_ascii_case(address.trim())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PullResponse {
+    #[serde(rename = "receivedMessages", default)]
+    received_messages: Vec<ReceivedMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReceivedMessage {
+    #[serde(rename = "ackId")]
+    ack_id: String,
+    message: PubsubMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct PubsubMessage {
+    #[serde(default)]
+    data: String,
+}
+
+/// Pulls Gmail notifications from the account's Pub/Sub subscription
+pub struct PubSubClient {
+    client: Client,
+    access_token: String,
+}
+
+impl PubSubClient {
+    pub fn new(tokens: &AuthTokens) -> Self {
+        Self {
+            client: Client::new(),
+            access_token: tokens.access_token.clone(),
+        }
+    }
+
+    /// Wait for notifications on `subscription` and acknowledge them. Returns
+    /// an empty list when the pull times out with nothing new.
+    pub async fn pull(
+        &self,
+        subscription: &str,
+    ) -> Result<Vec<GmailNotification>, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self
+            .client
+            .post(format!(
+                "https://pubsub.googleapis.com/v1/{}:pull",
+                subscription
+            ))
+            .bearer_auth(&self.access_token)
+            .json(&serde_json::json!({ "maxMessages": PULL_MAX_MESSAGES }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Pub/Sub pull error: {}", error_text).into());
+        }
+
+        let pulled: PullResponse = response.json().await?;
+        if pulled.received_messages.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ack_ids: Vec<&str> = pulled
+            .received_messages
+            .iter()
+            .map(|m| m.ack_id.as_str())
+            .collect();
+        self.acknowledge(subscription, &ack_ids).await?;
+
+        Ok(pulled
+            .received_messages
+            .iter()
+            .filter_map(|m| GmailNotification::decode(&m.message.data))
+            .collect())
+    }
+
+    async fn acknowledge(
+        &self,
+        subscription: &str,
+        ack_ids: &[&str],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let response = self
+            .client
+            .post(format!(
+                "https://pubsub.googleapis.com/v1/{}:acknowledge",
+                subscription
+            ))
+            .bearer_auth(&self.access_token)
+            .json(&serde_json::json!({ "ackIds": ack_ids }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Pub/Sub acknowledge error: {}", error_text).into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_watch_before_expiry() {
+        let now = 1_000_000;
+        assert!(PushStatus::default().needs_watch(now));
+        assert!(PushStatus::watching(None).needs_watch(now));
+
+        let fresh = PushStatus::watching(Some(now + 7 * 24 * 60 * 60));
+        assert!(!fresh.needs_watch(now));
+
+        let expiring = PushStatus::watching(Some(now + RENEW_MARGIN_SECS - 1));
+        assert!(expiring.needs_watch(now));
+
+        let failed = PushStatus::polling("403".to_string(), now);
+        assert!(failed.needs_watch(now));
+        assert_eq!(failed.retry_at, Some(now + RETRY_SECS));
+    }
+
+    #[test]
+    fn test_decode_notification() {
+        let data = STANDARD.encode(r#"{"emailAddress":"me@example.com","historyId":9876}"#);
+        assert_eq!(
+            GmailNotification::decode(&data),
+            Some(GmailNotification {
+                email_address: "me@example.com".to_string(),
+                history_id: 9876,
+            })
+        );
+        assert_eq!(GmailNotification::decode("not base64!"), None);
+
+        let notification = GmailNotification::decode(&data).unwrap();
+        assert!(notification.is_for("Me@Example.com"));
+        assert!(!notification.is_for("someone@example.com"));
+    }
+}
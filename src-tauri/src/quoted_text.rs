@@ -0,0 +1,282 @@
+use serde::Serialize;
+
+/// What part of a received message a stretch of its body is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentKind {
+    /// What the sender wrote in this message
+    Content,
+    /// Earlier messages carried along in a reply or inline quotes
+    Quoted,
+    Signature,
+}
+
+/// A stretch of a body; a body's segments joined give the body back
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BodySegment {
+    pub kind: SegmentKind,
+    pub text: String,
+}
+
+/// Endings of reply attribution lines: "On Mon, Alice <a@example.com> wrote:"
+/// and the same line from clients in other languages
+const ATTRIBUTION_MARKERS: &[&str] = &[
+    "wrote:",
+    "a écrit :",
+    "a écrit:",
+    "schrieb:",
+    "escribió:",
+    "scrisse:",
+    "escreveu:",
+    "schreef:",
+];
+
+/// Footers phone clients add in place of a signature
+const MOBILE_FOOTERS: &[&str] = &["Sent from my ", "Get Outlook for ", "Sent from Mail for "];
+
+/// Elements clients wrap quoted history in: Gmail, Apple Mail, Outlook
+/// (two layouts) and Yahoo
+const HTML_QUOTE_MARKERS: &[&str] = &[
+    "class=\"gmail_quote",
+    "type=\"cite\"",
+    "id=\"appendonsend\"",
+    "id=\"divrplyfwdmsg\"",
+    "class=\"yahoo_quoted",
+];
+
+const HTML_SIGNATURE_MARKER: &str = "class=\"gmail_signature\"";
+
+/// Split a plain-text body into what's new, quoted history and the
+/// signature. Everything from the first reply header ("On … wrote:",
+/// "-----Original Message-----", an Outlook From:/Sent: block) is history;
+/// a "-- " line or phone footer before it starts the signature; "> " runs
+/// above that are inline quotes.
+pub fn segment_text(text: &str) -> Vec<BodySegment> {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let content: Vec<&str> = lines
+        .iter()
+        .map(|l| l.trim_end_matches(['\n', '\r']))
+        .collect();
+
+    let history = (0..content.len())
+        .find(|&i| starts_history(&content, i))
+        .unwrap_or(content.len());
+    let signature = (0..history)
+        .rev()
+        .find(|&i| content[i].trim_end() == "--")
+        .or_else(|| {
+            let last = (0..history)
+                .rev()
+                .find(|&i| !content[i].trim().is_empty())?;
+            MOBILE_FOOTERS
+                .iter()
+                .any(|footer| content[last].trim_start().starts_with(footer))
+                .then_some(last)
+        })
+        .unwrap_or(history);
+
+    let mut kinds = vec![SegmentKind::Content; content.len()];
+    for kind in &mut kinds[signature..history] {
+        *kind = SegmentKind::Signature;
+    }
+    for kind in &mut kinds[history..] {
+        *kind = SegmentKind::Quoted;
+    }
+    for i in 0..signature {
+        if is_quote_line(content[i]) {
+            kinds[i] = SegmentKind::Quoted;
+            // The attribution line heading an inline quote goes with it
+            if let Some(prev) = (0..i).rev().find(|&j| !content[j].trim().is_empty()) {
+                if is_attribution(content[prev]) {
+                    for kind in &mut kinds[prev..i] {
+                        *kind = SegmentKind::Quoted;
+                    }
+                }
+            }
+        } else if content[i].trim().is_empty() && i > 0 {
+            // Blank lines stay with the stretch they follow
+            kinds[i] = kinds[i - 1];
+        }
+    }
+
+    let mut segments: Vec<BodySegment> = Vec::new();
+    for (line, kind) in lines.iter().zip(kinds) {
+        match segments.last_mut() {
+            Some(segment) if segment.kind == kind => segment.text.push_str(line),
+            _ => segments.push(BodySegment {
+                kind,
+                text: line.to_string(),
+            }),
+        }
+    }
+    segments
+}
+
+/// Split an HTML body before the first element a mail client wraps quoted
+/// history or a Gmail signature in. The pieces aren't balanced documents,
+/// but browsers render each one.
+pub fn segment_html(html: &str) -> Vec<BodySegment> {
+    let lower = html.to_ascii_lowercase();
+    let element_start = |marker: &str| {
+        let at = lower.find(marker)?;
+        lower[..at].rfind('<')
+    };
+    let quote = HTML_QUOTE_MARKERS
+        .iter()
+        .filter_map(|marker| element_start(marker))
+        .min();
+    let signature = element_start(HTML_SIGNATURE_MARKER).filter(|&s| quote.is_none_or(|q| s < q));
+
+    let mut bounds = vec![(0, SegmentKind::Content)];
+    bounds.extend(signature.map(|s| (s, SegmentKind::Signature)));
+    bounds.extend(quote.map(|q| (q, SegmentKind::Quoted)));
+    bounds.push((html.len(), SegmentKind::Content));
+    bounds
+        .windows(2)
+        .filter(|pair| pair[0].0 < pair[1].0)
+        .map(|pair| BodySegment {
+            kind: pair[0].1,
+            text: html[pair[0].0..pair[1].0].to_string(),
+        })
+        .collect()
+}
+
+fn is_quote_line(line: &str) -> bool {
+    line.trim_start().starts_with('>')
+}
+
+fn is_attribution(line: &str) -> bool {
+    let line = line.trim();
+    ATTRIBUTION_MARKERS
+        .iter()
+        .any(|marker| line.ends_with(marker))
+}
+
+/// Whether line `i` is a reply header everything below belongs to
+fn starts_history(lines: &[&str], i: usize) -> bool {
+    let line = lines[i].trim();
+    let next_text = |from: usize| {
+        lines[from..]
+            .iter()
+            .map(|l| l.trim())
+            .find(|l| !l.is_empty())
+    };
+
+    // "On Mon, 1 Jan 2024 at 10:00, Alice" / "<alice@example.com> wrote:"
+    // often wraps onto a second line
+    let attribution_end = if is_attribution(line) {
+        Some(i + 1)
+    } else if line.len() > 3
+        && line.starts_with("On ")
+        && lines.get(i + 1).is_some_and(|l| is_attribution(l))
+    {
+        Some(i + 2)
+    } else {
+        None
+    };
+    if let Some(end) = attribution_end {
+        // Unquoted text after the quote means an inline reply, not history
+        return next_text(end).is_some_and(|l| l.starts_with('>'))
+            && lines[end..]
+                .iter()
+                .all(|l| is_quote_line(l) || l.trim().is_empty());
+    }
+
+    let dashes = line.trim_matches('-').trim();
+    if line.starts_with("--") && dashes.eq_ignore_ascii_case("original message") {
+        return true;
+    }
+    if line.len() >= 10 && line.chars().all(|c| c == '_') {
+        return next_text(i + 1).is_some_and(|l| l.starts_with("From:"));
+    }
+    // Outlook's plain-text header block without the rule above it
+    line.starts_with("From:")
+        && lines[i + 1..]
+            .iter()
+            .take(3)
+            .any(|l| l.trim_start().starts_with("Sent:"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(segments: &[BodySegment]) -> Vec<(SegmentKind, &str)> {
+        segments.iter().map(|s| (s.kind, s.text.as_str())).collect()
+    }
+
+    #[test]
+    fn test_top_posted_reply_with_signature() {
+        let body = "Sounds good, see you then.\n\n-- \nAlice\nACME Corp\n\n\
+                    On Mon, 1 Jan 2024 at 10:00, Bob <bob@example.com>\nwrote:\n\
+                    > Lunch on Friday?\n>\n> Bob\n";
+        let segments = segment_text(body);
+        assert_eq!(
+            kinds(&segments),
+            vec![
+                (SegmentKind::Content, "Sounds good, see you then.\n\n"),
+                (SegmentKind::Signature, "-- \nAlice\nACME Corp\n\n"),
+                (
+                    SegmentKind::Quoted,
+                    "On Mon, 1 Jan 2024 at 10:00, Bob <bob@example.com>\nwrote:\n\
+                     > Lunch on Friday?\n>\n> Bob\n"
+                ),
+            ]
+        );
+        let joined: String = segments.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(joined, body);
+    }
+
+    #[test]
+    fn test_inline_quotes_and_outlook_history() {
+        let body = "Bob wrote:\n> Can you make Friday?\n\nYes.\n\n> And Saturday?\n\nNo.\n\
+                    Sent from my iPhone\n\
+                    -----Original Message-----\nFrom: Bob\nSent: Monday\n\nHi\n";
+        assert_eq!(
+            kinds(&segment_text(body)),
+            vec![
+                (
+                    SegmentKind::Quoted,
+                    "Bob wrote:\n> Can you make Friday?\n\n"
+                ),
+                (SegmentKind::Content, "Yes.\n\n"),
+                (SegmentKind::Quoted, "> And Saturday?\n\n"),
+                (SegmentKind::Content, "No.\n"),
+                (SegmentKind::Signature, "Sent from my iPhone\n"),
+                (
+                    SegmentKind::Quoted,
+                    "-----Original Message-----\nFrom: Bob\nSent: Monday\n\nHi\n"
+                ),
+            ]
+        );
+        assert_eq!(
+            kinds(&segment_text("Just a note\nFrom: me")),
+            vec![(SegmentKind::Content, "Just a note\nFrom: me")]
+        );
+        assert!(segment_text("").is_empty());
+    }
+
+    #[test]
+    fn test_html_split_at_client_quote_wrappers() {
+        let html = "<div dir=\"ltr\">Thanks!<div class=\"gmail_signature\">Alice</div></div>\
+                    <br><DIV CLASS=\"gmail_quote gmail_quote_container\"><blockquote>Hi</blockquote></DIV>";
+        assert_eq!(
+            kinds(&segment_html(html)),
+            vec![
+                (SegmentKind::Content, "<div dir=\"ltr\">Thanks!"),
+                (
+                    SegmentKind::Signature,
+                    "<div class=\"gmail_signature\">Alice</div></div><br>"
+                ),
+                (
+                    SegmentKind::Quoted,
+                    "<DIV CLASS=\"gmail_quote gmail_quote_container\"><blockquote>Hi</blockquote></DIV>"
+                ),
+            ]
+        );
+        assert_eq!(
+            kinds(&segment_html("<p>No history</p>")),
+            vec![(SegmentKind::Content, "<p>No history</p>")]
+        );
+    }
+}
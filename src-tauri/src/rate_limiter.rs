@@ -1,11 +1,16 @@
+use crate::clock::{Clock, SystemClock};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+/// Key for the budget shared by all background work (scheduled rules, sync jobs)
+const BACKGROUND_TIER_KEY: &str = "__background__";
+
 /// Rate limiter for API calls to prevent abuse
 #[derive(Debug)]
 pub struct RateLimiter {
     limits: Arc<Mutex<HashMap<String, RateLimit>>>,
+    clock: Arc<dyn Clock>,
 }
 
 #[derive(Debug, Clone)]
@@ -17,8 +22,14 @@ struct RateLimit {
 
 impl RateLimiter {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// A limiter whose windows are measured on `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
         RateLimiter {
             limits: Arc::new(Mutex::new(HashMap::new())),
+            clock,
         }
     }
 
@@ -31,10 +42,66 @@ impl RateLimiter {
             match operation {
                 "get_emails" => RateLimit::new(10, Duration::from_secs(60)), // 10 requests per minute
                 "get_email_content" => RateLimit::new(30, Duration::from_secs(60)), // 30 requests per minute
+                "get_sender_profile" => RateLimit::new(30, Duration::from_secs(60)), // 30 lookups per minute
+                "search_contacts" => RateLimit::new(120, Duration::from_secs(60)), // 120 searches per minute, one per keystroke
+                "store_secret" => RateLimit::new(20, Duration::from_secs(60)), // 20 writes per minute
+                "delete_stored_secret" => RateLimit::new(20, Duration::from_secs(60)), // 20 deletions per minute
+                "get_attachment" => RateLimit::new(30, Duration::from_secs(60)), // 30 downloads per minute
+                "get_conversation" => RateLimit::new(30, Duration::from_secs(60)), // 30 requests per minute
+                "get_labels" => RateLimit::new(10, Duration::from_secs(60)), // 10 label refreshes per minute
+                "manage_labels" => RateLimit::new(20, Duration::from_secs(60)), // 20 label changes per minute
+                "get_filters" => RateLimit::new(10, Duration::from_secs(60)), // 10 filter refreshes per minute
+                "manage_filters" => RateLimit::new(20, Duration::from_secs(60)), // 20 filter changes per minute
+                "forwarding" => RateLimit::new(10, Duration::from_secs(60)), // 10 forwarding requests per minute
+                "get_conversations" => RateLimit::new(10, Duration::from_secs(60)), // 10 requests per minute
+                "list_send_as_aliases" => RateLimit::new(10, Duration::from_secs(60)), // 10 requests per minute
+                "get_signature" => RateLimit::new(10, Duration::from_secs(60)), // 10 requests per minute
+                "remove_account" => RateLimit::new(5, Duration::from_secs(60)), // 5 removals per minute
+                "search_emails" => RateLimit::new(30, Duration::from_secs(60)), // 30 searches per minute
+                "weekly_digest" => RateLimit::new(5, Duration::from_secs(60)), // 5 digests per minute
+                "get_storage_status" => RateLimit::new(10, Duration::from_secs(60)), // 10 lookups per minute
+                "get_available_actions" => RateLimit::new(30, Duration::from_secs(60)), // 30 palette opens per minute
+                "drafts" => RateLimit::new(30, Duration::from_secs(60)), // 30 draft requests per minute
+                "list_mailbox" => RateLimit::new(10, Duration::from_secs(60)), // 10 pages per minute
                 "send_reply" => RateLimit::new(10, Duration::from_secs(60)), // 10 replies per minute
+                "rsvp_invite" => RateLimit::new(10, Duration::from_secs(60)), // 10 replies per minute
+                "send_compose_session" => RateLimit::new(10, Duration::from_secs(60)), // 10 sends per minute
                 "mark_email_as_read" => RateLimit::new(20, Duration::from_secs(60)), // 20 marks per minute
                 "mark_email_as_unread" => RateLimit::new(20, Duration::from_secs(60)), // 20 marks per minute
+                "mark_thread" => RateLimit::new(20, Duration::from_secs(60)), // 20 conversation marks per minute
+                "mute_thread" => RateLimit::new(20, Duration::from_secs(60)), // 20 mutes or unmutes per minute
+                "claim_thread" => RateLimit::new(20, Duration::from_secs(60)), // 20 claims or releases per minute
+                "get_thread_claims" => RateLimit::new(30, Duration::from_secs(60)), // 30 claim lookups per minute
+                "archive_email" => RateLimit::new(30, Duration::from_secs(60)), // 30 archives per minute
+                "report_spam" => RateLimit::new(30, Duration::from_secs(60)), // 30 spam reports per minute
+                "report_phishing" => RateLimit::new(10, Duration::from_secs(60)), // 10 phishing reports per minute
+                "mark_not_spam" => RateLimit::new(30, Duration::from_secs(60)), // 30 not-spam marks per minute
+                "mark_important" => RateLimit::new(30, Duration::from_secs(60)), // 30 importance marks per minute
+                "unsubscribe_from_sender" => RateLimit::new(5, Duration::from_secs(60)), // 5 unsubscribes per minute
+                "trash_email" => RateLimit::new(30, Duration::from_secs(60)), // 30 trash moves per minute
+                "untrash_email" => RateLimit::new(30, Duration::from_secs(60)), // 30 restores per minute
+                "retry_action" => RateLimit::new(30, Duration::from_secs(60)), // 30 retries per minute
+                "delete_email_permanently" => RateLimit::new(30, Duration::from_secs(60)), // 30 deletes per minute
+                "empty_trash" => RateLimit::new(2, Duration::from_secs(60)), // 2 empty-trash runs per minute
+                "get_trash_countdowns" => RateLimit::new(20, Duration::from_secs(60)), // 20 lookups per minute
                 "get_inbox_stats" => RateLimit::new(20, Duration::from_secs(60)), // 20 stats per minute
+                "get_account_profile" => RateLimit::new(20, Duration::from_secs(60)), // 20 lookups per minute
+                "prepare_offline_bundle" => RateLimit::new(2, Duration::from_secs(60)), // 2 bundles per minute
+                "export_message_eml" => RateLimit::new(20, Duration::from_secs(60)), // 20 exports per minute
+                "export_message_print" => RateLimit::new(20, Duration::from_secs(60)), // 20 exports per minute
+                "save_email_to_notes" => RateLimit::new(10, Duration::from_secs(60)), // 10 notes per minute
+                "import_messages" => RateLimit::new(2, Duration::from_secs(60)), // 2 import runs per minute
+                "export_mbox" => RateLimit::new(2, Duration::from_secs(60)), // 2 export runs per minute
+                "get_notification_styles" => RateLimit::new(30, Duration::from_secs(60)), // 30 lookups per minute
+                "analyze_cleanup" => RateLimit::new(5, Duration::from_secs(60)), // 5 analyses per minute
+                "bulk_action_by_query" => RateLimit::new(5, Duration::from_secs(60)), // 5 bulk runs per minute
+                "get_mailing_lists" => RateLimit::new(5, Duration::from_secs(60)), // 5 list overviews per minute
+                "generate_feed" => RateLimit::new(10, Duration::from_secs(60)), // 10 feed rewrites per minute
+                "get_code_notifications" => RateLimit::new(10, Duration::from_secs(60)), // 10 triage views per minute
+                "bulk_modify_emails" => RateLimit::new(20, Duration::from_secs(60)), // 20 multi-select actions per minute
+                "bulk_operation" => RateLimit::new(5, Duration::from_secs(60)), // 5 large selections per minute
+                "undo_bulk_action" => RateLimit::new(10, Duration::from_secs(60)), // 10 undos per minute
+                "run_rule_now" => RateLimit::new(5, Duration::from_secs(60)), // 5 rule runs per minute
                 "check_for_new_emails_since_last_check" => {
                     RateLimit::new(30, Duration::from_secs(60))
                 } // 30 checks per minute
@@ -42,7 +109,7 @@ impl RateLimiter {
             }
         });
 
-        if limit.is_allowed() {
+        if limit.is_allowed(self.clock.instant()) {
             Ok(())
         } else {
             Err(format!(
@@ -54,6 +121,30 @@ impl RateLimiter {
         }
     }
 
+    /// Check the shared background tier before doing scheduled or automatic work.
+    ///
+    /// Background jobs draw from one budget that is separate from the per-command
+    /// limits, so a busy schedule never eats into what interactive commands can use.
+    /// Callers should skip or defer their run when this returns an error.
+    pub fn check_background_rate_limit(&self, operation: &str) -> Result<(), String> {
+        let mut limits = self.limits.lock().unwrap();
+
+        let limit = limits
+            .entry(BACKGROUND_TIER_KEY.to_string())
+            .or_insert_with(|| RateLimit::new(20, Duration::from_secs(60))); // 20 background jobs per minute
+
+        if limit.is_allowed(self.clock.instant()) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Background rate limit exceeded for '{}'. Max {} background requests per {} seconds",
+                operation,
+                limit.max_requests,
+                limit.window_duration.as_secs()
+            ))
+        }
+    }
+
     /// Reset rate limits for all operations (useful for testing)
     #[cfg(test)]
     pub fn reset_all(&self) {
@@ -78,9 +169,7 @@ impl RateLimit {
         }
     }
 
-    fn is_allowed(&mut self) -> bool {
-        let now = Instant::now();
-
+    fn is_allowed(&mut self, now: Instant) -> bool {
         // Clean up old requests outside the window
         self.requests
             .retain(|&req_time| now.duration_since(req_time) <= self.window_duration);
@@ -104,6 +193,8 @@ impl Default for RateLimiter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::FakeClock;
+    use chrono::Utc;
 
     #[test]
     fn test_rate_limit_allows_requests_under_limit() {
@@ -142,6 +233,21 @@ mod tests {
         assert!(limiter.check_rate_limit("send_reply").is_ok());
     }
 
+    #[test]
+    fn test_background_tier_is_separate_from_interactive_limits() {
+        let limiter = RateLimiter::new();
+
+        // Exhaust the shared background budget across different jobs
+        for i in 0..20 {
+            let job = if i % 2 == 0 { "rule_run" } else { "sync" };
+            assert!(limiter.check_background_rate_limit(job).is_ok());
+        }
+        assert!(limiter.check_background_rate_limit("rule_run").is_err());
+
+        // Interactive commands are unaffected
+        assert!(limiter.check_rate_limit("get_emails").is_ok());
+    }
+
     #[test]
     fn test_reset_operation_clears_limit() {
         let limiter = RateLimiter::new();
@@ -177,4 +283,22 @@ mod tests {
         assert!(limiter.check_rate_limit("get_emails").is_ok());
         assert!(limiter.check_rate_limit("send_reply").is_ok());
     }
+
+    #[test]
+    fn test_window_expiry() {
+        let clock = Arc::new(FakeClock::at(Utc::now()));
+        let limiter = RateLimiter::with_clock(clock.clone());
+
+        for _ in 0..10 {
+            limiter.check_rate_limit("get_emails").unwrap();
+        }
+        assert!(limiter.check_rate_limit("get_emails").is_err());
+
+        // Requests exactly a window old still count
+        clock.advance(Duration::from_secs(60));
+        assert!(limiter.check_rate_limit("get_emails").is_err());
+
+        clock.advance(Duration::from_secs(1));
+        assert!(limiter.check_rate_limit("get_emails").is_ok());
+    }
 }
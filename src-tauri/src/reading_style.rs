@@ -0,0 +1,121 @@
+use crate::settings::ReadingSettings;
+
+/// Font sizes and zoom accepted from settings; anything outside is clamped
+const FONT_SIZE_RANGE: (u32, u32) = (10, 32);
+const ZOOM_RANGE: (u32, u32) = (50, 300);
+
+/// Generic families that need no fallback after them
+const GENERIC_FAMILIES: &[&str] = &["serif", "sans-serif", "monospace", "cursive", "system-ui"];
+
+/// CSS applying the reading preferences to a message document, appended
+/// after the viewer's own rules so it wins. Family names are reduced to
+/// letters, digits, spaces, '-' and '_' so a setting can't break out of the
+/// declaration. Empty when everything is left at its default.
+pub fn reading_css(settings: &ReadingSettings) -> String {
+    let mut body = Vec::new();
+    if let Some(family) = settings.font_family.as_deref().and_then(font_family_value) {
+        body.push(format!("font-family: {} !important;", family));
+    }
+    if let Some(size) = settings.font_size_px {
+        let size = size.clamp(FONT_SIZE_RANGE.0, FONT_SIZE_RANGE.1);
+        body.push(format!("font-size: {}px !important;", size));
+    }
+    let zoom = settings.zoom_percent.clamp(ZOOM_RANGE.0, ZOOM_RANGE.1);
+    if zoom != 100 {
+        body.push(format!("zoom: {}%;", zoom));
+    }
+
+    if body.is_empty() {
+        return String::new();
+    }
+    // Message HTML sets fonts on inner elements too; make them inherit
+    let mut inherit = Vec::new();
+    if settings.font_family.is_some() {
+        inherit.push("font-family: inherit !important;");
+    }
+    if settings.font_size_px.is_some() {
+        inherit.push("font-size: inherit !important;");
+    }
+    let mut css = format!("body {{ {} }}", body.join(" "));
+    if !inherit.is_empty() {
+        css.push_str(&format!(" body * {{ {} }}", inherit.join(" ")));
+    }
+    css
+}
+
+/// A safe `font-family` value, or None when no usable name is left
+fn font_family_value(setting: &str) -> Option<String> {
+    let families: Vec<String> = setting
+        .split(',')
+        .map(|name| {
+            name.chars()
+                .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+                .collect::<String>()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|name| !name.is_empty())
+        .collect();
+    if families.is_empty() {
+        return None;
+    }
+
+    let mut quoted: Vec<String> = families
+        .iter()
+        .map(|name| {
+            if GENERIC_FAMILIES.contains(&name.to_ascii_lowercase().as_str()) {
+                name.to_ascii_lowercase()
+            } else {
+                format!("'{}'", name)
+            }
+        })
+        .collect();
+    let has_generic = families
+        .iter()
+        .any(|name| GENERIC_FAMILIES.contains(&name.to_ascii_lowercase().as_str()));
+    if !has_generic {
+        quoted.push("sans-serif".to_string());
+    }
+    Some(quoted.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_add_nothing() {
+        assert_eq!(reading_css(&ReadingSettings::default()), "");
+    }
+
+    #[test]
+    fn test_preferences_become_css() {
+        let settings = ReadingSettings {
+            font_family: Some("Atkinson Hyperlegible, serif".to_string()),
+            font_size_px: Some(18),
+            zoom_percent: 125,
+        };
+        assert_eq!(
+            reading_css(&settings),
+            "body { font-family: 'Atkinson Hyperlegible', serif !important; \
+             font-size: 18px !important; zoom: 125%; } \
+             body * { font-family: inherit !important; font-size: inherit !important; }"
+        );
+
+        let zoom_only = ReadingSettings {
+            zoom_percent: 1000,
+            ..Default::default()
+        };
+        assert_eq!(reading_css(&zoom_only), "body { zoom: 300%; }");
+    }
+
+    #[test]
+    fn test_font_family_cannot_escape_the_declaration() {
+        assert_eq!(
+            font_family_value("Evil'; } body { background: url(x) }"),
+            Some("'Evil body background urlx', sans-serif".to_string())
+        );
+        assert_eq!(font_family_value(" ;{}, "), None);
+    }
+}
@@ -0,0 +1,270 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::extract_email_address;
+use crate::local_store::{app_data_path, load_json, save_json};
+use crate::no_reply::is_no_reply_address;
+use crate::sender_profile::display_name;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+const RECIPIENTS_FILE: &str = "recent_recipients.json";
+
+/// Sending to someone says more about who they'll write to next than
+/// hearing from them does
+const SENT_WEIGHT: f64 = 3.0;
+const RECEIVED_WEIGHT: f64 = 1.0;
+
+/// A score halves every this many seconds without contact (30 days)
+const HALF_LIFE_SECS: f64 = 30.0 * 24.0 * 3600.0;
+
+/// Addresses kept; the lowest scoring go first
+const MAX_ADDRESSES: usize = 2000;
+
+/// Received message ids remembered so re-listing the inbox doesn't count the
+/// same message twice
+const MAX_SEEN_MESSAGES: usize = 1000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct RecipientEntry {
+    /// Address as last written, for display
+    address: String,
+    name: Option<String>,
+    sent_count: u32,
+    received_count: u32,
+    /// Weighted, decaying count of contact as of `updated_at`
+    score: f64,
+    /// Unix timestamp (seconds) of the last contact
+    updated_at: u64,
+}
+
+impl RecipientEntry {
+    fn score_at(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.updated_at) as f64;
+        self.score * 0.5f64.powf(age / HALF_LIFE_SECS)
+    }
+
+    fn touch(&mut self, weight: f64, now: u64) {
+        self.score = self.score_at(now) + weight;
+        self.updated_at = self.updated_at.max(now);
+    }
+
+    fn matches(&self, prefix: &str) -> bool {
+        let address = self.address.to_ascii_lowercase();
+        let domain = address.rsplit_once('@').map(|(_, domain)| domain);
+        address.starts_with(prefix)
+            || domain.is_some_and(|domain| domain.starts_with(prefix))
+            || self.name.as_deref().is_some_and(|name| {
+                name.split_whitespace()
+                    .any(|word| word.to_lowercase().starts_with(prefix))
+            })
+    }
+}
+
+/// A compose autocomplete entry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecipientSuggestion {
+    pub address: String,
+    pub name: Option<String>,
+    pub sent_count: u32,
+    pub received_count: u32,
+    /// Unix timestamp (seconds) of the last message either way
+    pub last_contact: u64,
+}
+
+/// Addresses the user has sent to or heard from, ranked by how often and
+/// how recently, for recipient autocomplete without the People API
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecipientStore {
+    /// Keyed by lowercased address
+    entries: HashMap<String, RecipientEntry>,
+    seen_message_ids: VecDeque<String>,
+    account_id: Option<String>,
+}
+
+impl RecipientStore {
+    pub fn load() -> Self {
+        load_json(&app_data_path(RECIPIENTS_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(RECIPIENTS_FILE), self)
+    }
+
+    /// Count a message sent to `recipients` (bare or "Name <address>")
+    pub fn record_sent(&mut self, recipients: &[String], now: u64) {
+        for recipient in recipients {
+            if let Some(entry) = self.entry(recipient) {
+                entry.sent_count += 1;
+                entry.touch(SENT_WEIGHT, now);
+            }
+        }
+        self.prune();
+    }
+
+    /// Count a received message by its From header, once per message id.
+    /// Returns whether the store changed.
+    pub fn record_received(&mut self, message_id: &str, from: &str, now: u64) -> bool {
+        if self.seen_message_ids.iter().any(|id| id == message_id)
+            || is_no_reply_address(&extract_email_address(from))
+        {
+            return false;
+        }
+        self.seen_message_ids.push_back(message_id.to_string());
+        while self.seen_message_ids.len() > MAX_SEEN_MESSAGES {
+            self.seen_message_ids.pop_front();
+        }
+        if let Some(entry) = self.entry(from) {
+            entry.received_count += 1;
+            entry.touch(RECEIVED_WEIGHT, now);
+        }
+        self.prune();
+        true
+    }
+
+    /// Up to `limit` addresses whose address, domain or a word of the name
+    /// starts with `prefix`, best first. The account's own address is left out.
+    pub fn suggest(&self, prefix: &str, limit: usize, now: u64) -> Vec<RecipientSuggestion> {
+        let prefix = prefix.trim().to_lowercase();
+        let own = self.account_id.as_deref().map(str::to_ascii_lowercase);
+        let mut matches: Vec<(&String, &RecipientEntry)> = self
+            .entries
+            .iter()
+            .filter(|(key, _)| Some(key.as_str()) != own.as_deref())
+            .filter(|(_, entry)| entry.matches(&prefix))
+            .collect();
+        matches.sort_by(|(a_key, a), (b_key, b)| {
+            b.score_at(now)
+                .total_cmp(&a.score_at(now))
+                .then_with(|| a_key.cmp(b_key))
+        });
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, entry)| RecipientSuggestion {
+                address: entry.address.clone(),
+                name: entry.name.clone(),
+                sent_count: entry.sent_count,
+                received_count: entry.received_count,
+                last_contact: entry.updated_at,
+            })
+            .collect()
+    }
+
+    fn entry(&mut self, mailbox: &str) -> Option<&mut RecipientEntry> {
+        let address = extract_email_address(mailbox);
+        if !address.contains('@') || address.contains(char::is_whitespace) {
+            return None;
+        }
+        let entry = self
+            .entries
+            .entry(address.to_ascii_lowercase())
+            .or_default();
+        entry.address = address;
+        if let Some(name) = display_name(mailbox) {
+            entry.name = Some(name);
+        }
+        Some(entry)
+    }
+
+    fn prune(&mut self) {
+        if self.entries.len() <= MAX_ADDRESSES {
+            return;
+        }
+        let now = self
+            .entries
+            .values()
+            .map(|e| e.updated_at)
+            .max()
+            .unwrap_or(0);
+        let mut scores: Vec<(String, f64)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.score_at(now)))
+            .collect();
+        scores.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let excess = self.entries.len() - MAX_ADDRESSES;
+        for (key, _) in scores.into_iter().take(excess) {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+impl AccountScoped for RecipientStore {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 3600;
+
+    fn addresses(suggestions: &[RecipientSuggestion]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.address.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sent_outranks_received_and_recent_outranks_old() {
+        let mut store = RecipientStore::default();
+        let now = 1_000 * DAY;
+        assert!(store.record_received("m1", "\"Anna Lee\" <anna@example.com>", now));
+        store.record_sent(&["andy@example.com".to_string()], now);
+        store.record_sent(&["Ann@Old.example".to_string()], now - 120 * DAY);
+        store.record_sent(&["ann@old.example".to_string()], now - 120 * DAY);
+
+        let suggestions = store.suggest("an", 10, now);
+        assert_eq!(
+            addresses(&suggestions),
+            vec!["andy@example.com", "anna@example.com", "ann@old.example"]
+        );
+        assert_eq!(suggestions[1].name.as_deref(), Some("Anna Lee"));
+        assert_eq!(suggestions[2].sent_count, 2);
+
+        // Names and domains match too
+        assert_eq!(
+            addresses(&store.suggest("LEE", 10, now)),
+            vec!["anna@example.com"]
+        );
+        assert_eq!(
+            addresses(&store.suggest("old", 10, now)),
+            vec!["ann@old.example"]
+        );
+        assert_eq!(store.suggest("an", 1, now).len(), 1);
+    }
+
+    #[test]
+    fn test_received_messages_count_once_and_skip_no_reply() {
+        let mut store = RecipientStore::default();
+        assert!(store.record_received("m1", "bob@example.com", DAY));
+        assert!(!store.record_received("m1", "bob@example.com", DAY));
+        assert!(!store.record_received("m2", "noreply@example.com", DAY));
+        store.record_sent(&["undisclosed-recipients:;".to_string()], DAY);
+
+        let suggestions = store.suggest("", 10, DAY);
+        assert_eq!(addresses(&suggestions), vec!["bob@example.com"]);
+        assert_eq!(suggestions[0].received_count, 1);
+
+        // The signed-in account never suggests itself
+        store.record_sent(&["me@example.com".to_string()], DAY);
+        store.set_account_id("Me@example.com");
+        assert_eq!(
+            addresses(&store.suggest("", 10, DAY)),
+            vec!["bob@example.com"]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::{GmailMessage, SendAsAlias};
+use crate::local_store::{app_data_path, load_json, save_json};
+use serde::{Deserialize, Serialize};
+
+const ALIAS_RULES_FILE: &str = "alias_rules.json";
+
+/// Reply from a send-as alias when the incoming message matches, e.g. mail to
+/// support@ is always answered from support@. Every condition that is set
+/// must match; a rule with no conditions never matches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AliasRule {
+    pub id: String,
+    pub name: String,
+    /// Address the incoming message was sent to (To, Cc or Delivered-To)
+    #[serde(default)]
+    pub recipient: Option<String>,
+    /// Gmail label id the incoming message carries
+    #[serde(default)]
+    pub label_id: Option<String>,
+    /// sendAs address to reply from
+    pub send_as: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl AliasRule {
+    pub fn matches(&self, message: &GmailMessage) -> bool {
+        if !self.enabled || (self.recipient.is_none() && self.label_id.is_none()) {
+            return false;
+        }
+
+        let recipient_matches = self.recipient.as_ref().is_none_or(|wanted| {
+            message
+                .get_recipient_addresses()
+                .iter()
+                .any(|address| address.eq_ignore_ascii_case(wanted))
+        });
+        let label_matches = self.label_id.as_ref().is_none_or(|wanted| {
+            message
+                .label_ids
+                .as_ref()
+                .is_some_and(|labels| labels.contains(wanted))
+        });
+
+        recipient_matches && label_matches
+    }
+}
+
+/// Persisted alias rules, checked in order; the first match wins
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AliasRuleSet {
+    pub rules: Vec<AliasRule>,
+    /// Aliases are per account, so the rules are too
+    #[serde(default)]
+    pub account_id: Option<String>,
+}
+
+impl AliasRuleSet {
+    pub fn load() -> Self {
+        load_json(&app_data_path(ALIAS_RULES_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(ALIAS_RULES_FILE), self)
+    }
+
+    /// Insert a new rule or replace the one with the same id
+    pub fn upsert(&mut self, mut rule: AliasRule) -> AliasRule {
+        if rule.id.is_empty() {
+            let millis = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis();
+            rule.id = format!("alias_rule_{}", millis);
+        }
+
+        match self.rules.iter_mut().find(|r| r.id == rule.id) {
+            Some(existing) => *existing = rule.clone(),
+            None => self.rules.push(rule.clone()),
+        }
+        rule
+    }
+
+    pub fn remove(&mut self, rule_id: &str) -> bool {
+        let before = self.rules.len();
+        self.rules.retain(|r| r.id != rule_id);
+        self.rules.len() != before
+    }
+
+    /// The rule that decides which alias answers `message`, if any
+    pub fn pick(&self, message: &GmailMessage) -> Option<&AliasRule> {
+        self.rules.iter().find(|r| r.matches(message))
+    }
+}
+
+/// The From header for a rule's alias, or None when the alias is gone or unverified
+pub fn resolve_from(rule: &AliasRule, aliases: &[SendAsAlias]) -> Option<String> {
+    aliases
+        .iter()
+        .find(|a| a.send_as_email.eq_ignore_ascii_case(&rule.send_as))
+        .filter(|a| a.can_send())
+        .map(SendAsAlias::mailbox)
+}
+
+impl AccountScoped for AliasRuleSet {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::{MessageHeader, MessagePayload};
+
+    fn incoming(to: &str, labels: &[&str]) -> GmailMessage {
+        GmailMessage {
+            id: "msg1".to_string(),
+            thread_id: "thread1".to_string(),
+            label_ids: Some(labels.iter().map(|l| l.to_string()).collect()),
+            payload: Some(MessagePayload {
+                headers: Some(vec![MessageHeader {
+                    name: "To".to_string(),
+                    value: to.to_string(),
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn support_rule() -> AliasRule {
+        AliasRule {
+            id: "r1".to_string(),
+            name: "Support".to_string(),
+            recipient: Some("support@example.com".to_string()),
+            label_id: None,
+            send_as: "support@example.com".to_string(),
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_rule_matches_recipient_and_label() {
+        let mut rules = AliasRuleSet::default();
+        rules.upsert(support_rule());
+
+        let to_support = incoming("Me <me@example.com>, Support <SUPPORT@example.com>", &[]);
+        assert_eq!(rules.pick(&to_support).unwrap().id, "r1");
+        assert!(rules.pick(&incoming("me@example.com", &[])).is_none());
+
+        let mut labelled = support_rule();
+        labelled.label_id = Some("Label_1".to_string());
+        assert!(!labelled.matches(&to_support));
+        assert!(labelled.matches(&incoming("support@example.com", &["Label_1"])));
+
+        let mut disabled = support_rule();
+        disabled.enabled = false;
+        assert!(!disabled.matches(&to_support));
+    }
+
+    #[test]
+    fn test_resolve_from_requires_verified_alias() {
+        let alias = |status: &str| SendAsAlias {
+            send_as_email: "support@example.com".to_string(),
+            display_name: Some("Support".to_string()),
+            is_primary: Some(false),
+            is_default: Some(false),
+            verification_status: Some(status.to_string()),
+            signature: None,
+        };
+
+        assert_eq!(
+            resolve_from(&support_rule(), &[alias("accepted")]).as_deref(),
+            Some("Support <support@example.com>")
+        );
+        assert!(resolve_from(&support_rule(), &[alias("pending")]).is_none());
+        assert!(resolve_from(&support_rule(), &[]).is_none());
+    }
+}
@@ -0,0 +1,339 @@
+use crate::account::AccountScoped;
+use crate::bulk::{self, BulkAction, BulkProgress, BulkSummary};
+use crate::gmail_client::GmailClient;
+use crate::label_rename::{self, LabelRename};
+use crate::local_store::{app_data_path, load_json, save_json};
+use chrono::{DateTime, Timelike};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+const RULES_FILE: &str = "rules.json";
+
+/// New mail is matched against rules within this window, which comfortably
+/// covers the gap between two polls
+const NEW_MAIL_WINDOW: &str = "newer_than:1d";
+
+/// Which existing mail a rule run covers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleScope {
+    Inbox,
+    RecentDays { days: u32 },
+    AllMail,
+}
+
+/// Run a rule once a day at a local hour, e.g. a nightly cleanup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleSchedule {
+    pub hour: u32,
+    pub scope: RuleScope,
+}
+
+/// A user-defined rule: mail matching `query` (Gmail search syntax) gets `actions` applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: String,
+    pub name: String,
+    pub query: String,
+    pub actions: Vec<BulkAction>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Apply to newly arrived mail when polling finds it
+    #[serde(default = "default_true")]
+    pub apply_to_new_mail: bool,
+    #[serde(default)]
+    pub schedule: Option<RuleSchedule>,
+    /// Local date (YYYY-MM-DD) of the last scheduled run
+    #[serde(default)]
+    pub last_scheduled_run: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Rule {
+    /// Gmail query covering this rule's matches within a scope
+    pub fn scoped_query(&self, scope: &RuleScope) -> String {
+        match scope {
+            RuleScope::Inbox => format!("({}) in:inbox", self.query),
+            RuleScope::RecentDays { days } => format!("({}) newer_than:{}d", self.query, days),
+            RuleScope::AllMail => format!("({})", self.query),
+        }
+    }
+
+    /// Whether the schedule wants a run at `now` that hasn't happened yet today
+    pub fn is_due(&self, now: &DateTime<Tz>) -> bool {
+        let schedule = match (&self.schedule, self.enabled) {
+            (Some(schedule), true) => schedule,
+            _ => return false,
+        };
+
+        let today = now.format("%Y-%m-%d").to_string();
+        now.hour() >= schedule.hour && self.last_scheduled_run.as_deref() != Some(today.as_str())
+    }
+}
+
+/// Persisted rule list
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuleSet {
+    pub rules: Vec<Rule>,
+    /// Rules refer to the account's label ids
+    #[serde(default)]
+    pub account_id: Option<String>,
+}
+
+impl RuleSet {
+    pub fn load() -> Self {
+        load_json(&app_data_path(RULES_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(RULES_FILE), self)
+    }
+
+    pub fn get(&self, rule_id: &str) -> Option<&Rule> {
+        self.rules.iter().find(|r| r.id == rule_id)
+    }
+
+    /// Insert a new rule or replace the one with the same id
+    pub fn upsert(&mut self, mut rule: Rule) -> Rule {
+        if rule.id.is_empty() {
+            rule.id = new_rule_id();
+        }
+
+        match self.rules.iter_mut().find(|r| r.id == rule.id) {
+            Some(existing) => *existing = rule.clone(),
+            None => self.rules.push(rule.clone()),
+        }
+        rule
+    }
+
+    pub fn remove(&mut self, rule_id: &str) -> bool {
+        let before = self.rules.len();
+        self.rules.retain(|r| r.id != rule_id);
+        self.rules.len() != before
+    }
+
+    /// Follow a label rename in rule queries, returning how many rules changed
+    pub fn rename_label_references(&mut self, renames: &[LabelRename]) -> usize {
+        let mut updated = 0;
+        for rule in &mut self.rules {
+            if let Some(query) = label_rename::rewrite_query(&rule.query, renames) {
+                rule.query = query;
+                updated += 1;
+            }
+        }
+        updated
+    }
+
+    /// Record that a scheduled run happened on `now`'s local date
+    pub fn mark_scheduled_run(&mut self, rule_id: &str, now: &DateTime<Tz>) {
+        if let Some(rule) = self.rules.iter_mut().find(|r| r.id == rule_id) {
+            rule.last_scheduled_run = Some(now.format("%Y-%m-%d").to_string());
+        }
+    }
+}
+
+fn new_rule_id() -> String {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    format!("rule_{}", millis)
+}
+
+/// Run a rule over existing mail in `scope`
+pub async fn run_rule<F>(
+    gmail_client: &GmailClient,
+    rule: &Rule,
+    scope: &RuleScope,
+    dry_run: bool,
+    on_progress: F,
+) -> Result<(BulkSummary, Vec<String>), Box<dyn std::error::Error + Send + Sync>>
+where
+    F: FnMut(&BulkProgress),
+{
+    let message_ids = gmail_client
+        .list_all_message_ids(&rule.scoped_query(scope), bulk::MAX_BULK_MESSAGES)
+        .await?;
+
+    let operation_id = bulk::new_operation_id();
+    if dry_run {
+        return Ok((
+            BulkSummary::preview(&operation_id, &message_ids),
+            Vec::new(),
+        ));
+    }
+
+    Ok(bulk::run_bulk_action(
+        gmail_client,
+        &operation_id,
+        &message_ids,
+        &rule.actions,
+        on_progress,
+    )
+    .await)
+}
+
+/// Apply enabled new-mail rules to freshly arrived messages
+pub async fn apply_to_new_mail(
+    gmail_client: &GmailClient,
+    rules: &[Rule],
+    new_message_ids: &[String],
+) -> Vec<BulkSummary> {
+    let mut summaries = Vec::new();
+    if new_message_ids.is_empty() {
+        return summaries;
+    }
+
+    for rule in rules.iter().filter(|r| r.enabled && r.apply_to_new_mail) {
+        let query = format!("({}) {}", rule.query, NEW_MAIL_WINDOW);
+        let matches = match gmail_client.list_all_message_ids(&query, 500).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                eprintln!("Rule '{}' failed to match new mail: {}", rule.name, e);
+                continue;
+            }
+        };
+
+        let matching: Vec<String> = matches
+            .into_iter()
+            .filter(|id| new_message_ids.contains(id))
+            .collect();
+        if matching.is_empty() {
+            continue;
+        }
+
+        let operation_id = bulk::new_operation_id();
+        let (summary, _) = bulk::run_bulk_action(
+            gmail_client,
+            &operation_id,
+            &matching,
+            &rule.actions,
+            |_| {},
+        )
+        .await;
+        summaries.push(summary);
+    }
+
+    summaries
+}
+
+impl AccountScoped for RuleSet {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use chrono_tz::Europe::London;
+
+    fn nightly_rule() -> Rule {
+        Rule {
+            id: "rule_1".to_string(),
+            name: "Nightly newsletter sweep".to_string(),
+            query: "category:promotions".to_string(),
+            actions: vec![BulkAction::Archive],
+            enabled: true,
+            apply_to_new_mail: false,
+            schedule: Some(RuleSchedule {
+                hour: 2,
+                scope: RuleScope::Inbox,
+            }),
+            last_scheduled_run: None,
+        }
+    }
+
+    #[test]
+    fn test_scoped_query() {
+        let rule = nightly_rule();
+        assert_eq!(
+            rule.scoped_query(&RuleScope::Inbox),
+            "(category:promotions) in:inbox"
+        );
+        assert_eq!(
+            rule.scoped_query(&RuleScope::RecentDays { days: 7 }),
+            "(category:promotions) newer_than:7d"
+        );
+        assert_eq!(
+            rule.scoped_query(&RuleScope::AllMail),
+            "(category:promotions)"
+        );
+    }
+
+    #[test]
+    fn test_is_due_once_per_day_after_hour() {
+        let mut rules = RuleSet::default();
+        rules.upsert(nightly_rule());
+
+        let before = London.with_ymd_and_hms(2025, 6, 8, 1, 30, 0).unwrap();
+        let after = London.with_ymd_and_hms(2025, 6, 8, 2, 5, 0).unwrap();
+        let next_day = London.with_ymd_and_hms(2025, 6, 9, 3, 0, 0).unwrap();
+
+        assert!(!rules.get("rule_1").unwrap().is_due(&before));
+        assert!(rules.get("rule_1").unwrap().is_due(&after));
+
+        rules.mark_scheduled_run("rule_1", &after);
+        assert!(!rules.get("rule_1").unwrap().is_due(&after));
+        assert!(rules.get("rule_1").unwrap().is_due(&next_day));
+    }
+
+    #[test]
+    fn test_disabled_or_unscheduled_rules_are_never_due() {
+        let now = London.with_ymd_and_hms(2025, 6, 8, 23, 0, 0).unwrap();
+
+        let mut disabled = nightly_rule();
+        disabled.enabled = false;
+        assert!(!disabled.is_due(&now));
+
+        let mut unscheduled = nightly_rule();
+        unscheduled.schedule = None;
+        assert!(!unscheduled.is_due(&now));
+    }
+
+    #[test]
+    fn test_upsert_assigns_id_and_replaces() {
+        let mut rules = RuleSet::default();
+        let mut rule = nightly_rule();
+        rule.id = String::new();
+
+        let saved = rules.upsert(rule);
+        assert!(saved.id.starts_with("rule_"));
+
+        let mut renamed = saved.clone();
+        renamed.name = "Renamed".to_string();
+        rules.upsert(renamed);
+
+        assert_eq!(rules.rules.len(), 1);
+        assert_eq!(rules.get(&saved.id).unwrap().name, "Renamed");
+        assert!(rules.remove(&saved.id));
+        assert!(!rules.remove(&saved.id));
+    }
+
+    #[test]
+    fn test_rule_defaults_when_deserializing() {
+        let rule: Rule = serde_json::from_str(
+            r#"{"id": "r", "name": "n", "query": "from:x", "actions": [{"type": "mark_read"}]}"#,
+        )
+        .unwrap();
+        assert!(rule.enabled);
+        assert!(rule.apply_to_new_mail);
+        assert!(rule.schedule.is_none());
+    }
+}
@@ -0,0 +1,287 @@
+use crate::clock::Clock;
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone,
+    Timelike, Weekday,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// Hour the morning presets land on
+pub const MORNING_HOUR: u32 = 8;
+
+/// Hour "this evening" lands on
+pub const EVENING_HOUR: u32 = 18;
+
+/// How far ahead "later today" is, before rounding to the hour
+const LATER_TODAY_HOURS: i64 = 3;
+
+/// Looking this far back always lands before a DST gap; the widest in use is
+/// an hour, Lord Howe's is half that
+const MAX_GAP_HOURS: i64 = 3;
+
+/// The zone schedules are worked out in: the configured one when it names
+/// an IANA zone, otherwise the device's, otherwise UTC
+pub fn current_zone(configured: Option<&str>) -> Tz {
+    configured
+        .and_then(|name| name.trim().parse().ok())
+        .or_else(|| iana_time_zone::get_timezone().ok()?.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// The current time on `clock` in `zone`
+pub fn now_in(clock: &dyn Clock, zone: &Tz) -> DateTime<Tz> {
+    clock.now().with_timezone(zone)
+}
+
+/// A wall-clock time in `zone` as an instant. In the hour that repeats when
+/// clocks go back the first one is used; a time skipped when clocks go
+/// forward moves past the jump by the same amount, so 02:30 becomes 03:30.
+pub fn resolve_local(zone: &Tz, local: NaiveDateTime) -> DateTime<Tz> {
+    match zone.from_local_datetime(&local) {
+        LocalResult::Single(at) => at,
+        LocalResult::Ambiguous(first, _) => first,
+        LocalResult::None => {
+            let before_gap = zone
+                .offset_from_local_datetime(&(local - Duration::hours(MAX_GAP_HOURS)))
+                .earliest()
+                .map_or(0, |offset| offset.fix().local_minus_utc());
+            zone.from_utc_datetime(&(local - Duration::seconds(before_gap as i64)))
+        }
+    }
+}
+
+fn at_hour(date: NaiveDate, hour: u32) -> NaiveDateTime {
+    date.and_hms_opt(hour.min(23), 0, 0)
+        .unwrap_or_else(|| date.and_time(Default::default()))
+}
+
+/// The most recent `weekday` at `hour`, at or before `now`
+pub fn last_weekly_slot(weekday: Weekday, hour: u32, now: &DateTime<Tz>) -> DateTime<Tz> {
+    let days_back = (7 + now.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+    let date = now.date_naive() - Duration::days(days_back as i64);
+    match resolve_local(&now.timezone(), at_hour(date, hour)) {
+        slot if slot <= *now => slot,
+        // Today is the day but the hour hasn't come yet
+        _ => resolve_local(&now.timezone(), at_hour(date - Duration::days(7), hour)),
+    }
+}
+
+/// Quick picks for when something should happen, e.g. a snooze ending
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    LaterToday,
+    ThisEvening,
+    TomorrowMorning,
+    ThisWeekend,
+    NextWeek,
+}
+
+pub const PRESETS: [Preset; 5] = [
+    Preset::LaterToday,
+    Preset::ThisEvening,
+    Preset::TomorrowMorning,
+    Preset::ThisWeekend,
+    Preset::NextWeek,
+];
+
+impl Preset {
+    /// The wall-clock time the preset means at `now`, or None once it no
+    /// longer makes sense (this evening after six, this weekend on a
+    /// Saturday or Sunday)
+    pub fn wall_clock(self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let today = now.date();
+        let weekday = today.weekday().num_days_from_monday() as i64;
+        match self {
+            Preset::LaterToday => {
+                let later = now + Duration::hours(LATER_TODAY_HOURS);
+                let rounded = at_hour(later.date(), later.hour());
+                (rounded.date() == today).then_some(rounded)
+            }
+            Preset::ThisEvening => {
+                (now.hour() < EVENING_HOUR).then(|| at_hour(today, EVENING_HOUR))
+            }
+            Preset::TomorrowMorning => Some(at_hour(today + Duration::days(1), MORNING_HOUR)),
+            Preset::ThisWeekend => {
+                (weekday < 5).then(|| at_hour(today + Duration::days(5 - weekday), MORNING_HOUR))
+            }
+            Preset::NextWeek => Some(at_hour(today + Duration::days(7 - weekday), MORNING_HOUR)),
+        }
+    }
+
+    /// The preset as a floating time for someone currently in `zone`
+    pub fn schedule(self, now: &DateTime<Tz>) -> Option<ScheduledTime> {
+        let local = self.wall_clock(now.naive_local())?;
+        Some(ScheduledTime::new(local, &now.timezone(), true))
+    }
+}
+
+/// A time the user picked, kept as the wall-clock time they meant so it can
+/// be worked out again when their zone changes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledTime {
+    pub local: NaiveDateTime,
+    /// IANA zone `local` was picked in
+    pub time_zone: String,
+    /// Follow the user's zone, so "tomorrow 08:00" is 08:00 wherever they
+    /// are by then. Otherwise the time stays pinned to `time_zone`.
+    pub floating: bool,
+    /// Unix timestamp (seconds) `local` came to when last worked out
+    pub at: u64,
+}
+
+impl ScheduledTime {
+    pub fn new(local: NaiveDateTime, zone: &Tz, floating: bool) -> Self {
+        ScheduledTime {
+            local,
+            time_zone: zone.name().to_string(),
+            floating,
+            at: unix_seconds(&resolve_local(zone, local)),
+        }
+    }
+
+    /// The instant this falls on with the user in `zone`
+    pub fn at_in(&self, zone: &Tz) -> u64 {
+        let pinned = if self.floating {
+            Some(*zone)
+        } else {
+            self.time_zone.parse::<Tz>().ok()
+        };
+        pinned.map_or(self.at, |pinned| {
+            unix_seconds(&resolve_local(&pinned, self.local))
+        })
+    }
+
+    /// Work the time out again for `zone`, returning whether it moved
+    pub fn follow(&mut self, zone: &Tz) -> bool {
+        let at = self.at_in(zone);
+        let moved = at != self.at;
+        self.at = at;
+        if self.floating {
+            self.time_zone = zone.name().to_string();
+        }
+        moved
+    }
+}
+
+fn unix_seconds(at: &DateTime<Tz>) -> u64 {
+    at.timestamp().max(0) as u64
+}
+
+/// A preset with the time it comes to right now, for the pickers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PresetTime {
+    pub preset: Preset,
+    #[serde(flatten)]
+    pub time: ScheduledTime,
+}
+
+/// Presets that apply at `now`, in order
+pub fn preset_times(now: &DateTime<Tz>) -> Vec<PresetTime> {
+    PRESETS
+        .iter()
+        .filter_map(|&preset| preset.schedule(now).map(|time| PresetTime { preset, time }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::{America::New_York, Asia::Tokyo, Europe::Berlin};
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_dst_gaps_and_overlaps() {
+        // Berlin springs forward at 02:00 on 2025-03-30 and falls back at
+        // 03:00 on 2025-10-26
+        let skipped = resolve_local(&Berlin, local(2025, 3, 30, 2, 30));
+        assert_eq!(skipped.naive_local(), local(2025, 3, 30, 3, 30));
+
+        let repeated = resolve_local(&Berlin, local(2025, 10, 26, 2, 30));
+        assert_eq!(repeated.offset().fix().local_minus_utc(), 2 * 3600);
+
+        let plain = resolve_local(&Berlin, local(2025, 6, 1, 8, 0));
+        assert_eq!(plain.naive_local(), local(2025, 6, 1, 8, 0));
+    }
+
+    #[test]
+    fn test_presets() {
+        // 2025-06-11 is a Wednesday
+        let wednesday = local(2025, 6, 11, 10, 20);
+        assert_eq!(
+            Preset::LaterToday.wall_clock(wednesday),
+            Some(local(2025, 6, 11, 13, 0))
+        );
+        assert_eq!(
+            Preset::ThisEvening.wall_clock(wednesday),
+            Some(local(2025, 6, 11, 18, 0))
+        );
+        assert_eq!(
+            Preset::TomorrowMorning.wall_clock(wednesday),
+            Some(local(2025, 6, 12, 8, 0))
+        );
+        assert_eq!(
+            Preset::ThisWeekend.wall_clock(wednesday),
+            Some(local(2025, 6, 14, 8, 0))
+        );
+        assert_eq!(
+            Preset::NextWeek.wall_clock(wednesday),
+            Some(local(2025, 6, 16, 8, 0))
+        );
+
+        let saturday_night = local(2025, 6, 14, 22, 0);
+        assert_eq!(Preset::LaterToday.wall_clock(saturday_night), None);
+        assert_eq!(Preset::ThisEvening.wall_clock(saturday_night), None);
+        assert_eq!(Preset::ThisWeekend.wall_clock(saturday_night), None);
+        assert_eq!(
+            Preset::NextWeek.wall_clock(saturday_night),
+            Some(local(2025, 6, 16, 8, 0))
+        );
+
+        let now = Berlin.with_ymd_and_hms(2025, 6, 14, 22, 0, 0).unwrap();
+        let presets: Vec<Preset> = preset_times(&now).iter().map(|p| p.preset).collect();
+        assert_eq!(presets, vec![Preset::TomorrowMorning, Preset::NextWeek]);
+    }
+
+    #[test]
+    fn test_floating_times_follow_the_traveller() {
+        let now = Berlin.with_ymd_and_hms(2025, 6, 11, 10, 0, 0).unwrap();
+        let mut morning = Preset::TomorrowMorning.schedule(&now).unwrap();
+        assert_eq!(morning.time_zone, "Europe/Berlin");
+        assert!(!morning.follow(&Berlin));
+
+        // Flying to Tokyo: 08:00 is now 08:00 Tokyo time
+        assert!(morning.follow(&Tokyo));
+        let tokyo_eight = Tokyo.with_ymd_and_hms(2025, 6, 12, 8, 0, 0).unwrap();
+        assert_eq!(morning.at, tokyo_eight.timestamp() as u64);
+        assert_eq!(morning.time_zone, "Asia/Tokyo");
+
+        // A pinned time keeps its instant wherever the user goes
+        let meeting = ScheduledTime::new(local(2025, 6, 12, 9, 0), &New_York, false);
+        let mut travelling = meeting.clone();
+        assert!(!travelling.follow(&Tokyo));
+        assert_eq!(travelling, meeting);
+    }
+
+    #[test]
+    fn test_last_weekly_slot() {
+        // 2025-06-09 is a Monday
+        let before = Berlin.with_ymd_and_hms(2025, 6, 9, 7, 30, 0).unwrap();
+        let after = Berlin.with_ymd_and_hms(2025, 6, 9, 8, 5, 0).unwrap();
+        assert_eq!(
+            last_weekly_slot(Weekday::Mon, 8, &before),
+            Berlin.with_ymd_and_hms(2025, 6, 2, 8, 0, 0).unwrap()
+        );
+        assert_eq!(
+            last_weekly_slot(Weekday::Mon, 8, &after),
+            Berlin.with_ymd_and_hms(2025, 6, 9, 8, 0, 0).unwrap()
+        );
+        assert_eq!(current_zone(Some("Asia/Tokyo")), Tokyo);
+    }
+}
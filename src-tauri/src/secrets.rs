@@ -0,0 +1,270 @@
+use crate::local_store::{app_data_path, load_json, save_json};
+use crate::secure_storage::{SecureStorage, SecureStorageBackend};
+use serde::{Deserialize, Serialize};
+
+/// Names of stored secrets, never their values; the keyring can't list its
+/// own entries
+const INDEX_FILE: &str = "secrets.json";
+
+/// Longest name a secret can be stored under
+const MAX_NAME_LEN: usize = 200;
+
+/// What a credential is for; each kind has its own keyring namespace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretKind {
+    /// API key for an AI provider, named after the provider
+    AiProviderKey,
+    /// Signing or bearer token for an outgoing webhook, named after the hook
+    WebhookToken,
+    /// Password for an IMAP account, named after the account address
+    ImapPassword,
+    /// Integration token for a notes app, named after the notes target
+    NotesToken,
+}
+
+impl SecretKind {
+    fn namespace(self) -> &'static str {
+        match self {
+            SecretKind::AiProviderKey => "ai_provider",
+            SecretKind::WebhookToken => "webhook",
+            SecretKind::ImapPassword => "imap",
+            SecretKind::NotesToken => "notes",
+        }
+    }
+}
+
+/// A stored credential as listed to the frontend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredSecret {
+    pub kind: SecretKind,
+    pub name: String,
+    /// Unix timestamp (seconds) the value was last changed
+    pub saved_at: u64,
+}
+
+/// Integration credentials kept in the OS keyring, with a local index of
+/// what is stored so it can be listed without reading any values
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecretVault {
+    secrets: Vec<StoredSecret>,
+}
+
+impl SecretVault {
+    pub fn load() -> Self {
+        load_json(&app_data_path(INDEX_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(INDEX_FILE), self)
+    }
+
+    /// Stored secrets by kind, then name
+    pub fn list(&self) -> Vec<StoredSecret> {
+        let mut secrets = self.secrets.clone();
+        secrets.sort_by(|a, b| (a.kind, &a.name).cmp(&(b.kind, &b.name)));
+        secrets
+    }
+
+    /// The value stored for `name`, or None when there isn't one
+    pub fn get<T: SecureStorageBackend>(
+        &self,
+        storage: &SecureStorage<T>,
+        kind: SecretKind,
+        name: &str,
+    ) -> Result<Option<String>, String> {
+        let name = valid_name(name)?;
+        if !storage.has_secret(kind.namespace(), name) {
+            return Ok(None);
+        }
+        storage.load_secret(kind.namespace(), name).map(Some)
+    }
+
+    /// Store `value` under `name`, replacing any earlier value. The keyring
+    /// isn't written when the value is unchanged. Returns the index entry.
+    pub fn store<T: SecureStorageBackend>(
+        &mut self,
+        storage: &SecureStorage<T>,
+        kind: SecretKind,
+        name: &str,
+        value: &str,
+        now: u64,
+    ) -> Result<StoredSecret, String> {
+        let name = valid_name(name)?;
+        if value.is_empty() {
+            return Err(format!("No value given for {}", name));
+        }
+
+        let unchanged = self.get(storage, kind, name)?.as_deref() == Some(value);
+        let existing = self.position(kind, name);
+        if !unchanged {
+            storage.save_secret(kind.namespace(), name, value)?;
+        }
+        let secret = match existing {
+            Some(index) if unchanged => self.secrets[index].clone(),
+            Some(index) => {
+                self.secrets[index].saved_at = now;
+                self.secrets[index].clone()
+            }
+            None => {
+                let secret = StoredSecret {
+                    kind,
+                    name: name.to_string(),
+                    saved_at: now,
+                };
+                self.secrets.push(secret.clone());
+                secret
+            }
+        };
+        Ok(secret)
+    }
+
+    /// Delete the value stored for `name`, returning whether it was listed
+    pub fn remove<T: SecureStorageBackend>(
+        &mut self,
+        storage: &SecureStorage<T>,
+        kind: SecretKind,
+        name: &str,
+    ) -> Result<bool, String> {
+        let name = valid_name(name)?;
+        storage.delete_secret(kind.namespace(), name)?;
+        Ok(match self.position(kind, name) {
+            Some(index) => {
+                self.secrets.remove(index);
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn position(&self, kind: SecretKind, name: &str) -> Option<usize> {
+        self.secrets
+            .iter()
+            .position(|s| s.kind == kind && s.name == name)
+    }
+}
+
+fn valid_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > MAX_NAME_LEN || name.contains(char::is_control) {
+        return Err(format!("Invalid secret name: {:?}", name));
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryBackend {
+        entries: Mutex<HashMap<String, String>>,
+        writes: AtomicUsize,
+    }
+
+    impl SecureStorageBackend for MemoryBackend {
+        fn save_password(&self, key: &str, password: &str) -> Result<(), String> {
+            self.writes.fetch_add(1, Ordering::SeqCst);
+            self.entries
+                .lock()
+                .unwrap()
+                .insert(key.to_string(), password.to_string());
+            Ok(())
+        }
+
+        fn get_password(&self, key: &str) -> Result<String, String> {
+            self.entries
+                .lock()
+                .unwrap()
+                .get(key)
+                .cloned()
+                .ok_or_else(|| "No entry".to_string())
+        }
+
+        fn delete_password(&self, key: &str) -> Result<(), String> {
+            self.entries.lock().unwrap().remove(key);
+            Ok(())
+        }
+
+        fn has_password(&self, key: &str) -> bool {
+            self.entries.lock().unwrap().contains_key(key)
+        }
+    }
+
+    #[test]
+    fn test_store_list_and_remove() {
+        let storage = SecureStorage::with_backend(MemoryBackend::default());
+        let mut vault = SecretVault::default();
+
+        vault
+            .store(&storage, SecretKind::WebhookToken, "deploys", "hook-1", 10)
+            .unwrap();
+        vault
+            .store(&storage, SecretKind::AiProviderKey, " openai ", "sk-1", 20)
+            .unwrap();
+        // Same name under another kind is a separate secret
+        vault
+            .store(&storage, SecretKind::ImapPassword, "deploys", "pw", 30)
+            .unwrap();
+
+        assert_eq!(vault.list().len(), 3);
+        assert_eq!(
+            vault.list()[0],
+            StoredSecret {
+                kind: SecretKind::AiProviderKey,
+                name: "openai".to_string(),
+                saved_at: 20,
+            }
+        );
+        assert_eq!(
+            vault
+                .get(&storage, SecretKind::WebhookToken, "deploys")
+                .unwrap()
+                .as_deref(),
+            Some("hook-1")
+        );
+        assert_eq!(
+            vault
+                .get(&storage, SecretKind::WebhookToken, "other")
+                .unwrap(),
+            None
+        );
+
+        assert!(vault
+            .remove(&storage, SecretKind::WebhookToken, "deploys")
+            .unwrap());
+        assert!(!vault
+            .remove(&storage, SecretKind::WebhookToken, "deploys")
+            .unwrap());
+        assert_eq!(
+            vault
+                .get(&storage, SecretKind::ImapPassword, "deploys")
+                .unwrap()
+                .as_deref(),
+            Some("pw")
+        );
+        assert_eq!(vault.list().len(), 2);
+    }
+
+    #[test]
+    fn test_unchanged_values_skip_the_keyring() {
+        let storage = SecureStorage::with_backend(MemoryBackend::default());
+        let mut vault = SecretVault::default();
+        let kind = SecretKind::AiProviderKey;
+
+        vault.store(&storage, kind, "anthropic", "k1", 10).unwrap();
+        let again = vault.store(&storage, kind, "anthropic", "k1", 20).unwrap();
+        assert_eq!(again.saved_at, 10);
+        let changed = vault.store(&storage, kind, "anthropic", "k2", 30).unwrap();
+        assert_eq!(changed.saved_at, 30);
+        assert_eq!(vault.list().len(), 1);
+        assert_eq!(storage.backend().writes.load(Ordering::SeqCst), 2);
+
+        assert!(vault.store(&storage, kind, "", "k", 40).is_err());
+        assert!(vault.store(&storage, kind, "empty", "", 40).is_err());
+    }
+}
@@ -1,8 +1,36 @@
 use crate::gmail_auth::AuthTokens;
 use keyring::{Entry, Error as KeyringError};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
 
 const SERVICE_NAME: &str = "com.aisle3.app";
-const TOKEN_KEY: &str = "gmail_tokens";
+const NO_ENTRY: &str = "No entry found in keyring";
+
+/// Separates a key's namespace from its name
+const NAMESPACE_SEPARATOR: char = '/';
+
+/// Namespace and name the OAuth tokens are kept under
+const AUTH_NAMESPACE: &str = "auth";
+const TOKEN_NAME: &str = "gmail_tokens";
+
+/// Where the tokens lived before keys were namespaced. Every entry was
+/// written here regardless of its key, so it can only ever hold tokens.
+const LEGACY_TOKEN_KEY: &str = "gmail_tokens";
+
+/// Storage shared by the static methods, so they share one cache
+static SHARED_STORAGE: OnceLock<DefaultSecureStorage> = OnceLock::new();
+
+/// The backend key for `name` in `namespace`, e.g. "auth/gmail_tokens".
+/// Namespaces keep secrets stored by different features from colliding.
+pub fn secret_key(namespace: &str, name: &str) -> Result<String, String> {
+    if namespace.is_empty() || namespace.contains(NAMESPACE_SEPARATOR) {
+        return Err(format!("Invalid secret namespace: {:?}", namespace));
+    }
+    if name.is_empty() {
+        return Err(format!("Missing secret name in namespace {}", namespace));
+    }
+    Ok(format!("{}{}{}", namespace, NAMESPACE_SEPARATOR, name))
+}
 
 /// Trait for secure storage backends
 pub trait SecureStorageBackend {
@@ -12,61 +40,132 @@ pub trait SecureStorageBackend {
     fn has_password(&self, key: &str) -> bool;
 }
 
-/// Real keyring implementation
+/// Real keyring implementation; each key is its own entry under the app's
+/// service name
 pub struct KeyringBackend;
 
+impl KeyringBackend {
+    fn entry(key: &str) -> Result<Entry, String> {
+        Entry::new(SERVICE_NAME, key).map_err(|e| format!("Failed to create keyring entry: {}", e))
+    }
+}
+
 impl SecureStorageBackend for KeyringBackend {
-    fn save_password(&self, _key: &str, password: &str) -> Result<(), String> {
-        let entry = Entry::new(SERVICE_NAME, TOKEN_KEY)
-            .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-        entry
+    fn save_password(&self, key: &str, password: &str) -> Result<(), String> {
+        Self::entry(key)?
             .set_password(password)
-            .map_err(|e| format!("Failed to save tokens to keyring: {}", e))?;
-        Ok(())
+            .map_err(|e| format!("Failed to save {} to keyring: {}", key, e))
     }
 
-    fn get_password(&self, _key: &str) -> Result<String, String> {
-        let entry = Entry::new(SERVICE_NAME, TOKEN_KEY)
-            .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-        entry.get_password().map_err(|e| match e {
-            KeyringError::NoEntry => "No tokens found in keyring".to_string(),
-            _ => format!("Failed to load tokens from keyring: {}", e),
+    fn get_password(&self, key: &str) -> Result<String, String> {
+        Self::entry(key)?.get_password().map_err(|e| match e {
+            KeyringError::NoEntry => NO_ENTRY.to_string(),
+            _ => format!("Failed to load {} from keyring: {}", key, e),
         })
     }
 
-    fn delete_password(&self, _key: &str) -> Result<(), String> {
-        let entry = Entry::new(SERVICE_NAME, TOKEN_KEY)
-            .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-        match entry.delete_password() {
+    fn delete_password(&self, key: &str) -> Result<(), String> {
+        match Self::entry(key)?.delete_password() {
             Ok(()) => Ok(()),
             Err(KeyringError::NoEntry) => Ok(()), // Already deleted
-            Err(e) => Err(format!("Failed to delete tokens from keyring: {}", e)),
+            Err(e) => Err(format!("Failed to delete {} from keyring: {}", key, e)),
         }
     }
 
-    fn has_password(&self, _key: &str) -> bool {
-        let entry = match Entry::new(SERVICE_NAME, TOKEN_KEY) {
-            Ok(entry) => entry,
-            Err(_) => return false,
-        };
-        entry.get_password().is_ok()
+    fn has_password(&self, key: &str) -> bool {
+        Self::entry(key).is_ok_and(|entry| entry.get_password().is_ok())
+    }
+}
+
+/// What the cache last saw for a key
+#[derive(Debug, Clone)]
+enum CachedValue {
+    Present(String),
+    Absent,
+}
+
+/// Keeps the last value read or written for each key in memory, so frequent
+/// token reads don't go back to the OS keyring: a possible access prompt on
+/// macOS, a D-Bus round trip on Linux. Writes reach the backend first and
+/// only update the cache once they succeed. Call `invalidate` when the
+/// keyring may have changed behind the app's back.
+pub struct CachedBackend<T: SecureStorageBackend> {
+    inner: T,
+    cache: Mutex<HashMap<String, CachedValue>>,
+}
+
+impl<T: SecureStorageBackend> CachedBackend<T> {
+    pub fn new(inner: T) -> Self {
+        CachedBackend {
+            inner,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Forget everything cached; the next read goes to the backend
+    pub fn invalidate(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    fn remember(&self, key: &str, value: CachedValue) {
+        self.cache.lock().unwrap().insert(key.to_string(), value);
+    }
+}
+
+impl<T: SecureStorageBackend> SecureStorageBackend for CachedBackend<T> {
+    fn save_password(&self, key: &str, password: &str) -> Result<(), String> {
+        self.inner.save_password(key, password)?;
+        self.remember(key, CachedValue::Present(password.to_string()));
+        Ok(())
+    }
+
+    fn get_password(&self, key: &str) -> Result<String, String> {
+        match self.cache.lock().unwrap().get(key) {
+            Some(CachedValue::Present(password)) => return Ok(password.clone()),
+            Some(CachedValue::Absent) => return Err(NO_ENTRY.to_string()),
+            None => {}
+        }
+        // Failed reads aren't cached; the keyring may just be locked
+        let password = self.inner.get_password(key)?;
+        self.remember(key, CachedValue::Present(password.clone()));
+        Ok(password)
+    }
+
+    fn delete_password(&self, key: &str) -> Result<(), String> {
+        self.inner.delete_password(key)?;
+        self.remember(key, CachedValue::Absent);
+        Ok(())
+    }
+
+    fn has_password(&self, key: &str) -> bool {
+        self.get_password(key).is_ok()
     }
 }
 
-/// Secure storage for OAuth tokens
+/// Secure storage for OAuth tokens and other secrets
 pub struct SecureStorage<T: SecureStorageBackend> {
     backend: T,
 }
 
-/// Default implementation using real keyring
-pub type DefaultSecureStorage = SecureStorage<KeyringBackend>;
+/// Default implementation using the real keyring behind an in-memory cache
+pub type DefaultSecureStorage = SecureStorage<CachedBackend<KeyringBackend>>;
 
 impl DefaultSecureStorage {
     pub fn new() -> Self {
         SecureStorage {
-            backend: KeyringBackend,
+            backend: CachedBackend::new(KeyringBackend),
         }
     }
+
+    /// The process-wide storage, whose cache every caller shares
+    pub fn shared() -> &'static Self {
+        SHARED_STORAGE.get_or_init(Self::new)
+    }
+
+    /// Drop cached tokens so the next load reads the keyring again
+    pub fn invalidate_cache(&self) {
+        self.backend.invalidate();
+    }
 }
 
 impl Default for DefaultSecureStorage {
@@ -76,17 +175,48 @@ impl Default for DefaultSecureStorage {
 }
 
 impl<T: SecureStorageBackend> SecureStorage<T> {
+    #[cfg(test)]
+    pub fn with_backend(backend: T) -> Self {
+        SecureStorage { backend }
+    }
+
+    #[cfg(test)]
+    pub fn backend(&self) -> &T {
+        &self.backend
+    }
+
+    /// Save a secret under `name` in `namespace`
+    pub fn save_secret(&self, namespace: &str, name: &str, value: &str) -> Result<(), String> {
+        self.backend
+            .save_password(&secret_key(namespace, name)?, value)
+    }
+
+    /// Load the secret saved under `name` in `namespace`
+    pub fn load_secret(&self, namespace: &str, name: &str) -> Result<String, String> {
+        self.backend.get_password(&secret_key(namespace, name)?)
+    }
+
+    /// Delete the secret saved under `name` in `namespace`
+    pub fn delete_secret(&self, namespace: &str, name: &str) -> Result<(), String> {
+        self.backend.delete_password(&secret_key(namespace, name)?)
+    }
+
+    /// Check if a secret is saved under `name` in `namespace`
+    pub fn has_secret(&self, namespace: &str, name: &str) -> bool {
+        secret_key(namespace, name).is_ok_and(|key| self.backend.has_password(&key))
+    }
+
     /// Save tokens to secure storage
     pub fn save_tokens(&self, tokens: &AuthTokens) -> Result<(), String> {
         let json = serde_json::to_string(tokens)
             .map_err(|e| format!("Failed to serialize tokens: {}", e))?;
 
-        self.backend.save_password(TOKEN_KEY, &json)
+        self.save_secret(AUTH_NAMESPACE, TOKEN_NAME, &json)
     }
 
     /// Load tokens from secure storage
     pub fn load_tokens(&self) -> Result<AuthTokens, String> {
-        let json = self.backend.get_password(TOKEN_KEY)?;
+        let json = self.load_secret(AUTH_NAMESPACE, TOKEN_NAME)?;
 
         let tokens: AuthTokens = serde_json::from_str(&json)
             .map_err(|e| format!("Failed to deserialize tokens: {}", e))?;
@@ -94,14 +224,34 @@ impl<T: SecureStorageBackend> SecureStorage<T> {
         Ok(tokens)
     }
 
-    /// Delete tokens from secure storage
+    /// Delete tokens from secure storage, including any left in the
+    /// pre-namespacing entry
     pub fn delete_tokens(&self) -> Result<(), String> {
-        self.backend.delete_password(TOKEN_KEY)
+        self.delete_secret(AUTH_NAMESPACE, TOKEN_NAME)?;
+        self.backend.delete_password(LEGACY_TOKEN_KEY)
     }
 
     /// Check if tokens exist in storage
     pub fn has_tokens(&self) -> bool {
-        self.backend.has_password(TOKEN_KEY)
+        self.has_secret(AUTH_NAMESPACE, TOKEN_NAME)
+    }
+
+    /// Move tokens from the entry every key used to share into their own
+    /// namespaced entry. Returns whether there was anything to move.
+    pub fn migrate_legacy_entry(&self) -> Result<bool, String> {
+        if !self.backend.has_password(LEGACY_TOKEN_KEY) {
+            return Ok(false);
+        }
+        let json = self.backend.get_password(LEGACY_TOKEN_KEY)?;
+        serde_json::from_str::<AuthTokens>(&json)
+            .map_err(|e| format!("Failed to parse legacy keyring entry: {}", e))?;
+
+        // Only drop the old entry once the new one is safely written
+        self.save_secret(AUTH_NAMESPACE, TOKEN_NAME, &json)?;
+        self.backend.delete_password(LEGACY_TOKEN_KEY)?;
+
+        println!("Migrated tokens to namespaced keyring entry");
+        Ok(true)
     }
 
     /// Migrate tokens from old file-based storage to keyring
@@ -129,54 +279,60 @@ impl<T: SecureStorageBackend> SecureStorage<T> {
     }
 }
 
-// Static methods for backward compatibility
+// Static methods for backward compatibility; all share one cache
 impl DefaultSecureStorage {
     /// Save tokens to secure OS keyring (static method for backward compatibility)
     pub fn save_tokens_static(tokens: &AuthTokens) -> Result<(), String> {
-        let storage = Self::new();
-        storage.save_tokens(tokens)
+        Self::shared().save_tokens(tokens)
     }
 
     /// Load tokens from secure OS keyring (static method for backward compatibility)
     pub fn load_tokens_static() -> Result<AuthTokens, String> {
-        let storage = Self::new();
-        storage.load_tokens()
+        Self::shared().load_tokens()
     }
 
     /// Delete tokens from secure OS keyring (static method for backward compatibility)
     pub fn delete_tokens_static() -> Result<(), String> {
-        let storage = Self::new();
-        storage.delete_tokens()
+        Self::shared().delete_tokens()
     }
 
     /// Check if tokens exist in keyring (static method for backward compatibility)
     pub fn has_tokens_static() -> bool {
-        let storage = Self::new();
-        storage.has_tokens()
+        Self::shared().has_tokens()
+    }
+
+    /// Drop cached tokens so the next load reads the keyring again (static method for backward compatibility)
+    pub fn invalidate_cache_static() {
+        Self::shared().invalidate_cache();
+    }
+
+    /// Move tokens out of the pre-namespacing keyring entry (static method for backward compatibility)
+    pub fn migrate_legacy_entry_static() -> Result<bool, String> {
+        Self::shared().migrate_legacy_entry()
     }
 
     /// Migrate tokens from old file-based storage to keyring (static method for backward compatibility)
     pub fn migrate_from_file_static(file_path: &std::path::Path) -> Result<bool, String> {
-        let storage = Self::new();
-        storage.migrate_from_file(file_path)
+        Self::shared().migrate_from_file(file_path)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
-    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     /// Mock storage backend for testing
     struct MockStorageBackend {
         storage: Mutex<HashMap<String, String>>,
+        reads: AtomicUsize,
     }
 
     impl MockStorageBackend {
         fn new() -> Self {
             Self {
                 storage: Mutex::new(HashMap::new()),
+                reads: AtomicUsize::new(0),
             }
         }
     }
@@ -189,11 +345,12 @@ mod tests {
         }
 
         fn get_password(&self, key: &str) -> Result<String, String> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
             let storage = self.storage.lock().unwrap();
             storage
                 .get(key)
                 .cloned()
-                .ok_or_else(|| "No tokens found in storage".to_string())
+                .ok_or_else(|| "No entry found in storage".to_string())
         }
 
         fn delete_password(&self, key: &str) -> Result<(), String> {
@@ -220,6 +377,7 @@ mod tests {
             access_token: "test_access_token".to_string(),
             refresh_token: Some("test_refresh_token".to_string()),
             expires_in: Some(3600),
+            obtained_at: None,
         };
 
         // Clean up any existing tokens
@@ -244,4 +402,92 @@ mod tests {
         storage.delete_tokens().unwrap();
         assert!(!storage.has_tokens());
     }
+
+    #[test]
+    fn test_cache_avoids_repeated_backend_reads() {
+        let storage = SecureStorage {
+            backend: CachedBackend::new(MockStorageBackend::new()),
+        };
+        let reads = || storage.backend.inner.reads.load(Ordering::SeqCst);
+        let tokens = AuthTokens {
+            access_token: "cached".to_string(),
+            refresh_token: None,
+            expires_in: None,
+            obtained_at: None,
+        };
+
+        // A miss isn't cached, so tokens saved elsewhere are still found
+        assert!(!storage.has_tokens());
+        assert_eq!(reads(), 1);
+        storage
+            .backend
+            .inner
+            .save_password(
+                &secret_key(AUTH_NAMESPACE, TOKEN_NAME).unwrap(),
+                &serde_json::to_string(&tokens).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(storage.load_tokens().unwrap().access_token, "cached");
+        assert!(storage.has_tokens());
+        assert_eq!(storage.load_tokens().unwrap().access_token, "cached");
+        assert_eq!(reads(), 2);
+
+        storage.backend.invalidate();
+        storage.load_tokens().unwrap();
+        assert_eq!(reads(), 3);
+
+        // Deleting is remembered without reading back
+        storage.delete_tokens().unwrap();
+        assert!(!storage.has_tokens());
+        assert!(storage.load_tokens().is_err());
+        storage.save_tokens(&tokens).unwrap();
+        assert!(storage.has_tokens());
+        assert_eq!(reads(), 3);
+    }
+
+    #[test]
+    fn test_secrets_are_namespaced_and_legacy_tokens_migrate() {
+        let storage = SecureStorage {
+            backend: MockStorageBackend::new(),
+        };
+        assert_eq!(
+            secret_key("auth", "gmail_tokens").unwrap(),
+            "auth/gmail_tokens"
+        );
+        assert!(secret_key("a/b", "c").is_err());
+        assert!(secret_key("webhooks", "").is_err());
+
+        // Same name, different namespaces: separate entries
+        storage.save_secret("webhooks", "key", "hook").unwrap();
+        storage.save_secret("ai", "key", "model").unwrap();
+        assert_eq!(storage.load_secret("webhooks", "key").unwrap(), "hook");
+        assert_eq!(storage.load_secret("ai", "key").unwrap(), "model");
+        storage.delete_secret("ai", "key").unwrap();
+        assert!(!storage.has_secret("ai", "key"));
+        assert!(storage.has_secret("webhooks", "key"));
+
+        // Tokens written by older versions sit in the shared entry
+        assert!(!storage.migrate_legacy_entry().unwrap());
+        let legacy = r#"{"access_token":"old","refresh_token":null,"expires_in":null}"#;
+        storage
+            .backend
+            .save_password(LEGACY_TOKEN_KEY, legacy)
+            .unwrap();
+        assert!(!storage.has_tokens());
+
+        assert!(storage.migrate_legacy_entry().unwrap());
+        assert_eq!(storage.load_tokens().unwrap().access_token, "old");
+        assert!(!storage.backend.has_password(LEGACY_TOKEN_KEY));
+        assert!(!storage.migrate_legacy_entry().unwrap());
+
+        // A corrupt legacy entry is left in place
+        storage
+            .backend
+            .save_password(LEGACY_TOKEN_KEY, "{")
+            .unwrap();
+        assert!(storage.migrate_legacy_entry().is_err());
+        assert!(storage.backend.has_password(LEGACY_TOKEN_KEY));
+        storage.delete_tokens().unwrap();
+        assert!(!storage.has_tokens());
+    }
 }
@@ -0,0 +1,239 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::extract_email_address;
+use crate::local_store::{app_data_path, load_json, save_json};
+use crate::settings::SendLimitSettings;
+use serde::{Deserialize, Serialize};
+
+const SEND_LOG_FILE: &str = "send_log.json";
+
+/// Gmail counts its sending limit over a rolling 24 hours
+pub const DAILY_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+/// Window for the per-recipient cap
+pub const RECIPIENT_WINDOW_SECS: u64 = 60 * 60;
+
+/// One sent message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendRecord {
+    pub sent_at: u64,
+    /// Lowercased recipient addresses
+    pub recipients: Vec<String>,
+}
+
+/// Where the account stands against its daily cap
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SendQuotaStatus {
+    pub sent_last_24h: u32,
+    pub daily_cap: u32,
+    pub remaining: u32,
+    /// At or past the warning threshold
+    pub near_cap: bool,
+    /// When the oldest counted send drops out of the window
+    pub next_slot_at: Option<u64>,
+}
+
+/// Whether a message may go out now
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendDecision {
+    Allowed,
+    /// Hold the message until `until` (unix seconds)
+    Deferred {
+        until: u64,
+        reason: String,
+    },
+}
+
+/// Sends within the last 24 hours, persisted so restarts don't reset the count
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SendLog {
+    records: Vec<SendRecord>,
+    #[serde(default)]
+    account_id: Option<String>,
+}
+
+impl SendLog {
+    pub fn load() -> Self {
+        load_json(&app_data_path(SEND_LOG_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(SEND_LOG_FILE), self)
+    }
+
+    /// Drop records that have left the daily window
+    fn prune(&mut self, now: u64) {
+        self.records.retain(|r| r.sent_at + DAILY_WINDOW_SECS > now);
+    }
+
+    fn in_window(&self, now: u64, window: u64) -> impl Iterator<Item = &SendRecord> {
+        self.records
+            .iter()
+            .filter(move |r| r.sent_at + window > now)
+    }
+
+    /// Check a message to `recipients` against the daily and per-recipient caps
+    pub fn check(
+        &self,
+        recipients: &[String],
+        now: u64,
+        limits: &SendLimitSettings,
+    ) -> SendDecision {
+        let daily: Vec<&SendRecord> = self.in_window(now, DAILY_WINDOW_SECS).collect();
+        if daily.len() as u32 >= limits.daily_cap {
+            // A slot frees up once enough old sends age out to get back under the cap
+            let excess = daily.len() - limits.daily_cap as usize;
+            let mut times: Vec<u64> = daily.iter().map(|r| r.sent_at).collect();
+            times.sort_unstable();
+            return SendDecision::Deferred {
+                until: times[excess] + DAILY_WINDOW_SECS,
+                reason: format!("Daily send limit of {} messages reached", limits.daily_cap),
+            };
+        }
+
+        if limits.per_recipient_hourly_cap == 0 {
+            return SendDecision::Allowed;
+        }
+
+        for recipient in recipients {
+            let recipient = recipient.to_lowercase();
+            let mut times: Vec<u64> = self
+                .in_window(now, RECIPIENT_WINDOW_SECS)
+                .filter(|r| r.recipients.contains(&recipient))
+                .map(|r| r.sent_at)
+                .collect();
+            if times.len() as u32 >= limits.per_recipient_hourly_cap {
+                times.sort_unstable();
+                let excess = times.len() - limits.per_recipient_hourly_cap as usize;
+                return SendDecision::Deferred {
+                    until: times[excess] + RECIPIENT_WINDOW_SECS,
+                    reason: format!(
+                        "Sent {} messages to {} in the last hour",
+                        times.len(),
+                        recipient
+                    ),
+                };
+            }
+        }
+
+        SendDecision::Allowed
+    }
+
+    /// Count a message that just went out
+    pub fn record(&mut self, recipients: &[String], now: u64) {
+        self.prune(now);
+        self.records.push(SendRecord {
+            sent_at: now,
+            recipients: recipients.iter().map(|r| r.to_lowercase()).collect(),
+        });
+    }
+
+    pub fn status(&self, now: u64, limits: &SendLimitSettings) -> SendQuotaStatus {
+        let sent_last_24h = self.in_window(now, DAILY_WINDOW_SECS).count() as u32;
+        let warn_at = limits.daily_cap as u64 * limits.warn_at_percent as u64 / 100;
+
+        SendQuotaStatus {
+            sent_last_24h,
+            daily_cap: limits.daily_cap,
+            remaining: limits.daily_cap.saturating_sub(sent_last_24h),
+            near_cap: sent_last_24h as u64 >= warn_at,
+            next_slot_at: self
+                .in_window(now, DAILY_WINDOW_SECS)
+                .map(|r| r.sent_at + DAILY_WINDOW_SECS)
+                .min(),
+        }
+    }
+}
+
+/// Bare addresses from a comma-separated recipient header value
+pub fn split_recipients(header: &str) -> Vec<String> {
+    header
+        .split(',')
+        .map(|r| r.trim())
+        .filter(|r| !r.is_empty())
+        .map(extract_email_address)
+        .collect()
+}
+
+impl AccountScoped for SendLog {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(daily_cap: u32, per_recipient_hourly_cap: u32) -> SendLimitSettings {
+        SendLimitSettings {
+            daily_cap,
+            warn_at_percent: 50,
+            per_recipient_hourly_cap,
+        }
+    }
+
+    #[test]
+    fn test_daily_cap_defers_until_oldest_send_ages_out() {
+        let mut log = SendLog::default();
+        let limits = limits(2, 0);
+        let to = vec!["bob@example.com".to_string()];
+
+        log.record(&to, 1_000);
+        assert!(log.status(1_000, &limits).near_cap);
+        log.record(&to, 2_000);
+
+        assert_eq!(
+            log.check(&to, 3_000, &limits),
+            SendDecision::Deferred {
+                until: 1_000 + DAILY_WINDOW_SECS,
+                reason: "Daily send limit of 2 messages reached".to_string(),
+            }
+        );
+        assert_eq!(
+            log.check(&to, 1_000 + DAILY_WINDOW_SECS, &limits),
+            SendDecision::Allowed
+        );
+        assert_eq!(log.status(3_000, &limits).remaining, 0);
+    }
+
+    #[test]
+    fn test_per_recipient_cap_is_case_insensitive() {
+        let mut log = SendLog::default();
+        let limits = limits(100, 2);
+
+        log.record(&["Bob@Example.com".to_string()], 0);
+        log.record(&["bob@example.com".to_string()], 60);
+
+        let decision = log.check(&["BOB@example.com".to_string()], 120, &limits);
+        assert!(
+            matches!(decision, SendDecision::Deferred { until, .. } if until == RECIPIENT_WINDOW_SECS)
+        );
+        assert_eq!(
+            log.check(&["alice@example.com".to_string()], 120, &limits),
+            SendDecision::Allowed
+        );
+    }
+
+    #[test]
+    fn test_split_recipients() {
+        assert_eq!(
+            split_recipients("Bob <bob@example.com>, alice@example.com, "),
+            vec![
+                "bob@example.com".to_string(),
+                "alice@example.com".to_string()
+            ]
+        );
+    }
+}
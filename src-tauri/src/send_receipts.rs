@@ -0,0 +1,163 @@
+use crate::account::AccountScoped;
+use crate::local_store::{app_data_path, load_json, save_json};
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const RECEIPTS_FILE: &str = "send_receipts.json";
+
+const CSV_HEADER: &str = "sent_at,message_id,subject,recipients";
+
+/// One message the app sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SendReceipt {
+    /// Unix timestamp (seconds)
+    pub sent_at: u64,
+    /// Gmail id of the sent message
+    pub message_id: String,
+    pub subject: String,
+    pub recipients: Vec<String>,
+}
+
+/// Every message sent from the app, kept for good. Unlike `SendLog` nothing
+/// ages out, and unlike the Sent label deleting a message in Gmail doesn't
+/// remove its receipt.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReceiptLog {
+    receipts: Vec<SendReceipt>,
+    account_id: Option<String>,
+}
+
+impl ReceiptLog {
+    pub fn load() -> Self {
+        load_json(&app_data_path(RECEIPTS_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(RECEIPTS_FILE), self)
+    }
+
+    pub fn record(&mut self, receipt: SendReceipt) {
+        self.receipts.push(receipt);
+    }
+
+    /// Everyone the app has sent to, each address once
+    pub fn recipients(&self) -> Vec<String> {
+        let mut recipients: Vec<String> = Vec::new();
+        for recipient in self.receipts.iter().flat_map(|r| &r.recipients) {
+            if !recipients.iter().any(|r| r.eq_ignore_ascii_case(recipient)) {
+                recipients.push(recipient.clone());
+            }
+        }
+        recipients
+    }
+
+    /// Receipts as CSV, oldest first, with UTC RFC 3339 timestamps and
+    /// recipients joined by "; "
+    pub fn to_csv(&self) -> String {
+        let mut receipts: Vec<&SendReceipt> = self.receipts.iter().collect();
+        receipts.sort_by_key(|r| r.sent_at);
+
+        let mut csv = format!("{}\r\n", CSV_HEADER);
+        for receipt in receipts {
+            let sent_at = Utc
+                .timestamp_opt(receipt.sent_at as i64, 0)
+                .single()
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default();
+            let fields = [
+                sent_at,
+                receipt.message_id.clone(),
+                receipt.subject.clone(),
+                receipt.recipients.join("; "),
+            ];
+            let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+            csv.push_str(&row.join(","));
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+
+    /// Write the CSV to `path`, returning how many receipts it holds
+    pub fn export_csv(&self, path: &Path) -> Result<usize, String> {
+        std::fs::write(path, self.to_csv())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(self.receipts.len())
+    }
+}
+
+/// Quote a field when it holds a delimiter, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl AccountScoped for ReceiptLog {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt(sent_at: u64, id: &str, subject: &str, recipients: &[&str]) -> SendReceipt {
+        SendReceipt {
+            sent_at,
+            message_id: id.to_string(),
+            subject: subject.to_string(),
+            recipients: recipients.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_csv_is_sorted_and_escaped() {
+        let mut log = ReceiptLog::default();
+        log.record(receipt(
+            86_400,
+            "m2",
+            "Invoice \"March\", final",
+            &["a@example.com", "b@example.com"],
+        ));
+        log.record(receipt(0, "m1", "Hello", &["c@example.com"]));
+
+        assert_eq!(
+            log.to_csv(),
+            "sent_at,message_id,subject,recipients\r\n\
+             1970-01-01T00:00:00+00:00,m1,Hello,c@example.com\r\n\
+             1970-01-02T00:00:00+00:00,m2,\"Invoice \"\"March\"\", final\",a@example.com; b@example.com\r\n"
+        );
+    }
+
+    #[test]
+    fn test_export_writes_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("receipts.csv");
+        let mut log = ReceiptLog::default();
+        log.record(receipt(0, "m1", "Line\nbreak", &["c@example.com"]));
+
+        assert_eq!(log.export_csv(&path).unwrap(), 1);
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.ends_with(",m1,\"Line\nbreak\",c@example.com\r\n"));
+        assert!(log
+            .export_csv(&dir.path().join("missing/receipts.csv"))
+            .is_err());
+    }
+}
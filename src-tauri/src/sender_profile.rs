@@ -0,0 +1,228 @@
+use crate::gmail_client::{GmailMessage, MessageAttachment};
+use crate::people::ContactInfo;
+use serde::Serialize;
+
+/// Threads and attachments listed in the sidebar, most recent first
+const MAX_COMMON_THREADS: usize = 5;
+const MAX_SHARED_ATTACHMENTS: usize = 10;
+
+/// A thread shared with the contact
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommonThread {
+    pub thread_id: String,
+    pub subject: String,
+    pub message_count: usize,
+}
+
+/// Everything the contact sidebar shows about one address. Counts cover mail
+/// in the local cache, not the whole mailbox.
+#[derive(Debug, Clone, Serialize)]
+pub struct SenderProfile {
+    pub email: String,
+    /// Display name from their most recent message
+    pub name: Option<String>,
+    pub received_count: usize,
+    pub sent_count: usize,
+    /// RFC 3339 time of the latest message either way
+    pub last_contact: Option<String>,
+    pub common_threads: Vec<CommonThread>,
+    pub shared_attachments: Vec<MessageAttachment>,
+    pub contact: Option<ContactInfo>,
+}
+
+impl SenderProfile {
+    /// Aggregate the messages exchanged with `email`
+    pub fn from_messages(email: &str, messages: &[GmailMessage]) -> Self {
+        let mut involved: Vec<&GmailMessage> = messages
+            .iter()
+            .filter(|m| is_from(m, email) || is_sent_to(m, email))
+            .collect();
+        // Newest first
+        involved.sort_by_key(|m| std::cmp::Reverse(internal_date(m)));
+
+        let received: Vec<&GmailMessage> = involved
+            .iter()
+            .copied()
+            .filter(|m| is_from(m, email))
+            .collect();
+
+        let mut common_threads: Vec<CommonThread> = Vec::new();
+        for message in &involved {
+            match common_threads
+                .iter_mut()
+                .find(|t| t.thread_id == message.thread_id)
+            {
+                Some(thread) => {
+                    thread.message_count += 1;
+                    // Keep the subject of the thread's earliest message
+                    thread.subject = message.get_subject();
+                }
+                None => common_threads.push(CommonThread {
+                    thread_id: message.thread_id.clone(),
+                    subject: message.get_subject(),
+                    message_count: 1,
+                }),
+            }
+        }
+        common_threads.truncate(MAX_COMMON_THREADS);
+
+        SenderProfile {
+            email: email.to_string(),
+            name: received.first().and_then(|m| display_name(&m.get_from())),
+            received_count: received.len(),
+            sent_count: involved.len() - received.len(),
+            last_contact: involved
+                .first()
+                .and_then(|m| chrono::DateTime::from_timestamp_millis(internal_date(m)))
+                .filter(|date| date.timestamp() > 0)
+                .map(|date| date.to_rfc3339()),
+            common_threads,
+            shared_attachments: involved
+                .iter()
+                .flat_map(|m| m.get_attachments())
+                .take(MAX_SHARED_ATTACHMENTS)
+                .collect(),
+            contact: None,
+        }
+    }
+}
+
+fn is_from(message: &GmailMessage, email: &str) -> bool {
+    message.get_from_address().eq_ignore_ascii_case(email)
+}
+
+fn is_sent_to(message: &GmailMessage, email: &str) -> bool {
+    let sent = message
+        .label_ids
+        .as_ref()
+        .is_some_and(|labels| labels.iter().any(|l| l == "SENT"));
+    sent && message
+        .get_recipient_addresses()
+        .iter()
+        .any(|address| address.eq_ignore_ascii_case(email))
+}
+
+fn internal_date(message: &GmailMessage) -> i64 {
+    message
+        .internal_date
+        .as_deref()
+        .and_then(|d| d.parse().ok())
+        .unwrap_or(0)
+}
+
+/// "Alice Smith" from `"Alice Smith" <alice@example.com>`
+pub fn display_name(from: &str) -> Option<String> {
+    let name = from.split('<').next()?.trim().trim_matches('"').trim();
+    (!name.is_empty() && from.contains('<')).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::{MessageBody, MessageHeader, MessagePart, MessagePayload};
+
+    fn message(
+        id: &str,
+        thread: &str,
+        date: i64,
+        headers: &[(&str, &str)],
+        labels: &[&str],
+    ) -> GmailMessage {
+        GmailMessage {
+            id: id.to_string(),
+            thread_id: thread.to_string(),
+            label_ids: Some(labels.iter().map(|l| l.to_string()).collect()),
+            payload: Some(MessagePayload {
+                headers: Some(
+                    headers
+                        .iter()
+                        .map(|(name, value)| MessageHeader {
+                            name: name.to_string(),
+                            value: value.to_string(),
+                        })
+                        .collect(),
+                ),
+                parts: Some(vec![MessagePart {
+                    filename: Some(format!("{}.pdf", id)),
+                    mime_type: Some("application/pdf".to_string()),
+                    body: Some(MessageBody {
+                        attachment_id: Some(format!("att_{}", id)),
+                        size: Some(10),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            internal_date: Some(date.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_profile_counts_both_directions() {
+        let messages = vec![
+            message(
+                "m1",
+                "t1",
+                1_700_000_000_000,
+                &[
+                    ("From", "\"Alice Smith\" <alice@example.com>"),
+                    ("Subject", "Plans"),
+                ],
+                &["INBOX"],
+            ),
+            message(
+                "m2",
+                "t1",
+                1_700_000_100_000,
+                &[
+                    ("From", "me@example.com"),
+                    ("To", "Alice <ALICE@example.com>"),
+                    ("Subject", "Re: Plans"),
+                ],
+                &["SENT"],
+            ),
+            message(
+                "m3",
+                "t2",
+                1_600_000_000_000,
+                &[("From", "alice@example.com"), ("Subject", "Old news")],
+                &["INBOX"],
+            ),
+            message(
+                "m4",
+                "t3",
+                1_800_000_000_000,
+                &[("From", "bob@example.com"), ("Subject", "Unrelated")],
+                &["INBOX"],
+            ),
+        ];
+
+        let profile = SenderProfile::from_messages("alice@example.com", &messages);
+        assert_eq!(profile.received_count, 2);
+        assert_eq!(profile.sent_count, 1);
+        assert_eq!(profile.name.as_deref(), Some("Alice Smith"));
+        assert_eq!(
+            profile.last_contact.as_deref(),
+            Some("2023-11-14T22:15:00+00:00")
+        );
+        assert_eq!(
+            profile.common_threads,
+            vec![
+                CommonThread {
+                    thread_id: "t1".to_string(),
+                    subject: "Plans".to_string(),
+                    message_count: 2,
+                },
+                CommonThread {
+                    thread_id: "t2".to_string(),
+                    subject: "Old news".to_string(),
+                    message_count: 1,
+                },
+            ]
+        );
+        assert_eq!(profile.shared_attachments.len(), 3);
+        assert_eq!(profile.shared_attachments[0].message_id, "m2");
+    }
+}
@@ -0,0 +1,403 @@
+use crate::account::AccountScoped;
+use crate::local_store::{app_data_path, load_json, save_json};
+use chrono::Weekday;
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_FILE: &str = "backend_settings.json";
+
+/// How list commands shape their results: `get_emails`, `list_mailbox`, the
+/// search commands and the inbox tabs
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewMode {
+    /// Group messages into threads (Gmail's default)
+    #[default]
+    Conversations,
+    /// One row per message, no threading
+    Messages,
+}
+
+/// Localhost HTTP API for scripts; only served in builds with the `local-api` feature.
+/// Changes take effect on the next launch.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LocalApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for LocalApiSettings {
+    fn default() -> Self {
+        LocalApiSettings {
+            enabled: false,
+            port: 7878,
+        }
+    }
+}
+
+/// Focus mode holds new-mail notices and releases them together on a fixed cadence
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct FocusModeSettings {
+    pub enabled: bool,
+    /// Minutes between batch deliveries, e.g. 60 for hourly
+    pub batch_interval_minutes: u32,
+}
+
+impl Default for FocusModeSettings {
+    fn default() -> Self {
+        FocusModeSettings {
+            enabled: false,
+            batch_interval_minutes: 60,
+        }
+    }
+}
+
+/// Sending caps, enforced before mail goes out. The default daily cap matches
+/// Gmail's limit for personal accounts; Workspace accounts can raise it to 2000.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SendLimitSettings {
+    /// Messages per rolling 24 hours
+    pub daily_cap: u32,
+    /// Warn once this share of the daily cap is used
+    pub warn_at_percent: u8,
+    /// Messages to any one recipient per hour; 0 disables the check
+    pub per_recipient_hourly_cap: u32,
+}
+
+impl Default for SendLimitSettings {
+    fn default() -> Self {
+        SendLimitSettings {
+            daily_cap: 500,
+            warn_at_percent: 80,
+            per_recipient_hourly_cap: 20,
+        }
+    }
+}
+
+/// A program run once per attachment, e.g. `clamdscan --no-summary`. The
+/// attachment is written to a scratch file whose path is appended to `args`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct ScanCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+/// Scanning of outgoing attachments before anything is sent, e.g. with a
+/// local ClamAV. A positive result blocks the send; so does a scanner that
+/// can't be run, unless `allow_on_error` is set.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct AttachmentScanSettings {
+    pub enabled: bool,
+    pub command: Option<ScanCommand>,
+    /// URL each attachment is POSTed to as the raw request body, with its
+    /// name in an X-Filename header. The reply is JSON:
+    /// `{"infected": bool, "detail": "..."}`.
+    pub endpoint: Option<String>,
+    /// Send anyway when the scanner fails rather than reporting a result
+    pub allow_on_error: bool,
+}
+
+/// Weekly inbox digest emailed to the account's own address
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct DigestSettings {
+    pub enabled: bool,
+    /// Day the digest goes out, e.g. "Mon"
+    pub weekday: Weekday,
+    /// Local hour the digest goes out
+    pub hour: u32,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        DigestSettings {
+            enabled: false,
+            weekday: Weekday::Mon,
+            hour: 8,
+        }
+    }
+}
+
+/// Background new-mail checks when push notifications aren't in use
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PollingSettings {
+    /// Seconds between checks while the app is in use
+    pub interval_secs: u64,
+    /// Ceiling the interval backs off to when idle or unfocused
+    pub max_interval_secs: u64,
+    /// Back off when no mail arrives or the window loses focus
+    pub adaptive: bool,
+}
+
+impl Default for PollingSettings {
+    fn default() -> Self {
+        PollingSettings {
+            interval_secs: 60,
+            max_interval_secs: 15 * 60,
+            adaptive: true,
+        }
+    }
+}
+
+/// How message HTML is shown, for readers who need larger or different type
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ReadingSettings {
+    /// Comma-separated font families, e.g. "Atkinson Hyperlegible, serif"
+    pub font_family: Option<String>,
+    pub font_size_px: Option<u32>,
+    /// Scale applied to the whole message
+    pub zoom_percent: u32,
+}
+
+impl Default for ReadingSettings {
+    fn default() -> Self {
+        ReadingSettings {
+            font_family: None,
+            font_size_px: None,
+            zoom_percent: 100,
+        }
+    }
+}
+
+/// A distinct notification for mail from particular senders or with
+/// particular labels, e.g. the boss or pager alerts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct NotificationRule {
+    pub name: String,
+    /// Addresses ("boss@example.com") or whole domains ("@pager.example.com")
+    pub senders: Vec<String>,
+    pub label_ids: Vec<String>,
+    /// Sound name passed to the OS notification; None keeps the default
+    pub sound: Option<String>,
+    /// Notify even during quiet hours and while focus mode holds mail
+    pub bypass_quiet_hours: bool,
+}
+
+/// Per-sender and per-label notification overrides; the first matching rule wins
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub rules: Vec<NotificationRule>,
+}
+
+/// What happens to mail an outbound rule matches
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboundAction {
+    /// Ask before sending; the user can send anyway
+    #[default]
+    Warn,
+    /// Refuse to send
+    Block,
+}
+
+/// A check on outgoing subjects and bodies, e.g. "confidential" or card
+/// numbers. Matches on any of its keywords, its pattern or, with
+/// `card_numbers` set, anything that passes the Luhn check.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct OutboundRule {
+    pub name: String,
+    /// Whole words or phrases, matched case-insensitively
+    pub keywords: Vec<String>,
+    /// Regular expression, e.g. `\b\d{3}-\d{2}-\d{4}\b`
+    pub pattern: Option<String>,
+    pub card_numbers: bool,
+    pub action: OutboundAction,
+}
+
+/// Rules every outgoing message is checked against before it's sent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct OutboundRuleSettings {
+    pub rules: Vec<OutboundRule>,
+}
+
+/// A label worked as a support queue: new mail arriving with the label
+/// should get its first reply within `target_minutes`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SlaQueue {
+    pub label_id: String,
+    /// Shown in reports and warnings; the label id when empty
+    pub name: String,
+    pub target_minutes: u32,
+    /// Warn once this share of the target has passed without a reply
+    pub warn_at_percent: u8,
+}
+
+impl Default for SlaQueue {
+    fn default() -> Self {
+        SlaQueue {
+            label_id: String::new(),
+            name: String::new(),
+            target_minutes: 240,
+            warn_at_percent: 80,
+        }
+    }
+}
+
+/// Labels tracked for first-response times
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct SlaSettings {
+    pub queues: Vec<SlaQueue>,
+}
+
+/// The app a notes target saves into
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NotesApp {
+    /// Markdown files in a local vault folder
+    #[default]
+    Obsidian,
+    /// Pages created through the Notion API
+    Notion,
+}
+
+/// Somewhere `save_email_to_notes` can send a message. A Notion target's
+/// integration token is the vault secret of kind `notes_token` stored under
+/// the target's name.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct NotesTarget {
+    pub name: String,
+    pub app: NotesApp,
+    /// Obsidian: the vault's folder on disk
+    pub vault_path: Option<String>,
+    /// Obsidian: folder inside the vault for saved mail, e.g. "Inbox/Email"
+    pub folder: Option<String>,
+    /// Notion: page new notes are created under, shared with the integration
+    pub parent_page_id: Option<String>,
+    pub include_attachments: bool,
+}
+
+impl Default for NotesTarget {
+    fn default() -> Self {
+        NotesTarget {
+            name: String::new(),
+            app: NotesApp::Obsidian,
+            vault_path: None,
+            folder: None,
+            parent_page_id: None,
+            include_attachments: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct NotesSettings {
+    pub targets: Vec<NotesTarget>,
+}
+
+/// Gmail push notifications through Cloud Pub/Sub. The topic must grant
+/// gmail-api-push@system.gserviceaccount.com publish rights, and the
+/// subscription is pulled by the signed-in account.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct PushSettings {
+    pub enabled: bool,
+    /// "projects/<project>/topics/<topic>"
+    pub topic_name: String,
+    /// "projects/<project>/subscriptions/<subscription>"
+    pub subscription: String,
+}
+
+impl PushSettings {
+    /// Whether push is switched on and both resource names are filled in
+    pub fn is_configured(&self) -> bool {
+        self.enabled && !self.topic_name.trim().is_empty() && !self.subscription.trim().is_empty()
+    }
+}
+
+/// Settings the backend needs to act on; UI-only preferences stay in the frontend store
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct BackendSettings {
+    pub view_mode: ViewMode,
+    pub local_api: LocalApiSettings,
+    pub focus_mode: FocusModeSettings,
+    pub send_limits: SendLimitSettings,
+    pub attachment_scan: AttachmentScanSettings,
+    pub outbound_rules: OutboundRuleSettings,
+    /// Extract text from downloaded attachments into the local search index
+    pub index_attachment_text: bool,
+    pub digest: DigestSettings,
+    /// Append the sending address's Gmail signature to replies and compose sends
+    pub append_signature: bool,
+    /// Open every message as plain text rendered from its HTML, links kept
+    /// as numbered references, for screen readers and text-only reading
+    pub plain_text_mode: bool,
+    pub reading: ReadingSettings,
+    pub notifications: NotificationSettings,
+    pub polling: PollingSettings,
+    pub push: PushSettings,
+    pub sla: SlaSettings,
+    pub notes: NotesSettings,
+    /// IANA zone snoozes, digests and scheduled rules are worked out in, e.g.
+    /// "Europe/Berlin"; None follows the device
+    pub time_zone: Option<String>,
+    /// Account whose preferences these are
+    pub account_id: Option<String>,
+}
+
+impl BackendSettings {
+    /// Load settings from disk, using defaults for anything missing
+    pub fn load() -> Self {
+        load_json(&app_data_path(SETTINGS_FILE))
+    }
+
+    /// Persist settings to disk
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(SETTINGS_FILE), self)
+    }
+}
+
+impl AccountScoped for BackendSettings {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let settings: BackendSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.view_mode, ViewMode::Conversations);
+        assert_eq!(settings.local_api, LocalApiSettings::default());
+        assert!(!settings.local_api.enabled);
+    }
+
+    #[test]
+    fn test_view_mode_serialization() {
+        let settings: BackendSettings =
+            serde_json::from_str(r#"{"view_mode": "messages"}"#).unwrap();
+        assert_eq!(settings.view_mode, ViewMode::Messages);
+
+        let json = serde_json::to_value(&settings).unwrap();
+        assert_eq!(json["view_mode"], "messages");
+    }
+}
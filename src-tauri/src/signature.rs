@@ -0,0 +1,157 @@
+use crate::gmail_client::{extract_email_address, is_html_body, SendAsAlias};
+use serde::Serialize;
+
+/// A Gmail signature, kept as Gmail stores it (HTML) plus the plain-text form
+/// used for text bodies
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Signature {
+    pub send_as_email: String,
+    pub html: String,
+    pub text: String,
+}
+
+impl Signature {
+    /// The signature for mail sent as `from` ("Name <address>" or a bare
+    /// address), or for the default address when `from` is None
+    pub fn for_sender(aliases: &[SendAsAlias], from: Option<&str>) -> Option<Self> {
+        let alias = match from {
+            Some(from) => {
+                let address = extract_email_address(from);
+                aliases
+                    .iter()
+                    .find(|a| a.send_as_email.eq_ignore_ascii_case(&address))
+            }
+            None => aliases
+                .iter()
+                .find(|a| a.is_default == Some(true))
+                .or_else(|| aliases.iter().find(|a| a.is_primary == Some(true))),
+        }?;
+
+        let html = alias
+            .signature
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())?;
+        Some(Signature {
+            send_as_email: alias.send_as_email.clone(),
+            html: html.to_string(),
+            text: html_to_text(html),
+        })
+    }
+
+    /// `body` with the signature after a "-- " separator, in HTML or text to
+    /// match the body. A body that already carries the signature is unchanged.
+    pub fn append_to(&self, body: &str) -> String {
+        if is_html_body(body) {
+            if body.contains(&self.html) {
+                return body.to_string();
+            }
+            format!(
+                "{}<br><br><div class=\"gmail_signature\">-- <br>{}</div>",
+                body, self.html
+            )
+        } else {
+            if self.text.is_empty() || body.contains(&self.text) {
+                return body.to_string();
+            }
+            format!("{}\n\n-- \n{}", body.trim_end(), self.text)
+        }
+    }
+}
+
+/// Line breaks for block tags, other tags dropped, common entities decoded
+fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut tag: Option<String> = None;
+    for ch in html.chars() {
+        match (&mut tag, ch) {
+            (None, '<') => tag = Some(String::new()),
+            (None, _) => text.push(ch),
+            (Some(name), '>') => {
+                let name = name
+                    .trim_start_matches('/')
+                    .split(|c: char| c.is_whitespace() || c == '/')
+                    .next()
+                    .unwrap_or("")
+                    .to_ascii_lowercase();
+                if matches!(name.as_str(), "br" | "p" | "div" | "li" | "tr") {
+                    text.push('\n');
+                }
+                tag = None;
+            }
+            (Some(name), _) => name.push(ch),
+        }
+    }
+
+    let decoded = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+
+    // Nested blocks leave runs of newlines; keep at most one blank line
+    let mut lines: Vec<&str> = Vec::new();
+    for line in decoded.lines().map(str::trim_end) {
+        if line.is_empty() && lines.last().is_none_or(|l| l.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alias(email: &str, default: bool, signature: Option<&str>) -> SendAsAlias {
+        SendAsAlias {
+            send_as_email: email.to_string(),
+            display_name: None,
+            is_primary: Some(default),
+            is_default: Some(default),
+            verification_status: None,
+            signature: signature.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_picks_signature_for_sender() {
+        let aliases = vec![
+            alias(
+                "me@example.com",
+                true,
+                Some("<div dir=\"ltr\">Alice<div>Acme &amp; Co</div></div>"),
+            ),
+            alias("support@example.com", false, Some("  ")),
+        ];
+
+        let default = Signature::for_sender(&aliases, None).unwrap();
+        assert_eq!(default.send_as_email, "me@example.com");
+        assert_eq!(default.text, "Alice\nAcme & Co");
+
+        let named = Signature::for_sender(&aliases, Some("Alice <ME@example.com>")).unwrap();
+        assert_eq!(named, default);
+        assert!(Signature::for_sender(&aliases, Some("support@example.com")).is_none());
+        assert!(Signature::for_sender(&aliases, Some("other@example.com")).is_none());
+    }
+
+    #[test]
+    fn test_append_matches_body_format_once() {
+        let signature = Signature {
+            send_as_email: "me@example.com".to_string(),
+            html: "<b>Alice</b>".to_string(),
+            text: "Alice".to_string(),
+        };
+
+        let plain = signature.append_to("Thanks!\n");
+        assert_eq!(plain, "Thanks!\n\n-- \nAlice");
+        assert_eq!(signature.append_to(&plain), plain);
+
+        let html = signature.append_to("<p>Thanks!</p>");
+        assert!(html.ends_with("<div class=\"gmail_signature\">-- <br><b>Alice</b></div>"));
+        assert_eq!(signature.append_to(&html), html);
+    }
+}
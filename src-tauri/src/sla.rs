@@ -0,0 +1,404 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::GmailMessage;
+use crate::local_store::{app_data_path, load_json, save_json};
+use crate::settings::SlaQueue;
+use serde::{Deserialize, Serialize};
+
+const SLA_FILE: &str = "sla_threads.json";
+
+/// How long a thread stays in reports after it opened, answered or not
+const RETENTION_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// A queue thread's first-response clock
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlaThread {
+    pub thread_id: String,
+    pub label_id: String,
+    pub subject: String,
+    pub from: String,
+    /// Unix timestamps (seconds)
+    pub opened_at: u64,
+    /// Fixed when the thread opens, so changing a target only affects new mail
+    pub due_at: u64,
+    pub responded_at: Option<u64>,
+    #[serde(default)]
+    warned: bool,
+    #[serde(default)]
+    breach_reported: bool,
+}
+
+/// Where a thread stands against its target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlaStatus {
+    OnTrack,
+    /// Unanswered and past the queue's warning point
+    AtRisk,
+    /// Unanswered and past due
+    Breached,
+    /// Answered in time
+    Met,
+    /// Answered late
+    Missed,
+}
+
+impl SlaThread {
+    pub fn status(&self, queue: Option<&SlaQueue>, now: u64) -> SlaStatus {
+        match self.responded_at {
+            Some(at) if at <= self.due_at => SlaStatus::Met,
+            Some(_) => SlaStatus::Missed,
+            None if now >= self.due_at => SlaStatus::Breached,
+            None if now >= self.warn_at(queue) => SlaStatus::AtRisk,
+            None => SlaStatus::OnTrack,
+        }
+    }
+
+    fn warn_at(&self, queue: Option<&SlaQueue>) -> u64 {
+        let percent = queue.map_or(100, |q| q.warn_at_percent.min(100)) as u64;
+        self.opened_at + (self.due_at - self.opened_at) * percent / 100
+    }
+}
+
+/// Payload of the "sla-warning" event
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlaWarning {
+    pub thread_id: String,
+    pub queue: String,
+    pub subject: String,
+    pub from: String,
+    pub due_at: u64,
+    /// False while the thread is only at risk
+    pub breached: bool,
+}
+
+/// Totals for one queue in `get_sla_report`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlaQueueReport {
+    pub label_id: String,
+    pub name: String,
+    pub target_minutes: u32,
+    /// Unanswered threads, including at-risk and breached ones
+    pub open: u32,
+    pub at_risk: u32,
+    pub breached: u32,
+    pub met: u32,
+    pub missed: u32,
+    /// Mean first-response time of answered threads
+    pub average_response_minutes: Option<u64>,
+}
+
+/// One thread in `get_sla_report`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlaThreadReport {
+    #[serde(flatten)]
+    pub thread: SlaThread,
+    pub status: SlaStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlaReport {
+    pub queues: Vec<SlaQueueReport>,
+    /// Threads from the last 30 days, soonest due first
+    pub threads: Vec<SlaThreadReport>,
+}
+
+/// First-response tracking for threads in SLA queues. A thread's clock
+/// starts when mail carrying a queue label arrives and stops at the first
+/// reply sent in the thread; later back-and-forth isn't timed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlaTracker {
+    threads: Vec<SlaThread>,
+    account_id: Option<String>,
+}
+
+impl SlaTracker {
+    pub fn load() -> Self {
+        load_json(&app_data_path(SLA_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(SLA_FILE), self)
+    }
+
+    /// Whether any thread is still waiting for its first reply
+    pub fn has_pending(&self) -> bool {
+        self.threads.iter().any(|t| t.responded_at.is_none())
+    }
+
+    pub fn is_pending(&self, thread_id: &str) -> bool {
+        self.threads
+            .iter()
+            .any(|t| t.thread_id == thread_id && t.responded_at.is_none())
+    }
+
+    /// Start clocks for new mail carrying a queue label, returning whether
+    /// any started. Threads already tracked keep their original clock.
+    pub fn open(&mut self, queues: &[SlaQueue], messages: &[GmailMessage], now: u64) -> bool {
+        let mut changed = false;
+        for message in messages {
+            let labels = message.label_ids.as_deref().unwrap_or_default();
+            if labels.iter().any(|l| l == "SENT") {
+                continue;
+            }
+            let Some(queue) = queues.iter().find(|q| labels.contains(&q.label_id)) else {
+                continue;
+            };
+            if self
+                .threads
+                .iter()
+                .any(|t| t.thread_id == message.thread_id)
+            {
+                continue;
+            }
+            let opened_at = received_at(message).unwrap_or(now);
+            self.threads.push(SlaThread {
+                thread_id: message.thread_id.clone(),
+                label_id: queue.label_id.clone(),
+                subject: message.get_subject(),
+                from: message.get_from(),
+                opened_at,
+                due_at: opened_at + queue.target_minutes as u64 * 60,
+                responded_at: None,
+                warned: false,
+                breach_reported: false,
+            });
+            changed = true;
+        }
+        changed
+    }
+
+    /// Stop a thread's clock at `at` (unix seconds), returning false when it
+    /// isn't waiting on a reply or the reply predates it
+    pub fn respond(&mut self, thread_id: &str, at: u64) -> bool {
+        match self
+            .threads
+            .iter_mut()
+            .find(|t| t.thread_id == thread_id && t.responded_at.is_none())
+        {
+            Some(thread) if at >= thread.opened_at => {
+                thread.responded_at = Some(at);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Unanswered threads that have newly crossed their warning point or
+    /// deadline; each crossing is reported once
+    pub fn warnings(&mut self, queues: &[SlaQueue], now: u64) -> Vec<SlaWarning> {
+        let mut warnings = Vec::new();
+        for thread in &mut self.threads {
+            let queue = queues.iter().find(|q| q.label_id == thread.label_id);
+            let breached = match thread.status(queue, now) {
+                SlaStatus::Breached if !thread.breach_reported => true,
+                SlaStatus::AtRisk if !thread.warned => false,
+                _ => continue,
+            };
+            thread.warned = true;
+            thread.breach_reported = breached;
+            warnings.push(SlaWarning {
+                thread_id: thread.thread_id.clone(),
+                queue: queue_name(queue, &thread.label_id),
+                subject: thread.subject.clone(),
+                from: thread.from.clone(),
+                due_at: thread.due_at,
+                breached,
+            });
+        }
+        warnings
+    }
+
+    /// Forget threads older than the retention window, returning whether
+    /// any were dropped
+    pub fn prune(&mut self, now: u64) -> bool {
+        let before = self.threads.len();
+        self.threads.retain(|t| t.opened_at + RETENTION_SECS > now);
+        self.threads.len() != before
+    }
+
+    /// Per-queue totals and every tracked thread in a configured queue
+    pub fn report(&self, queues: &[SlaQueue], now: u64) -> SlaReport {
+        let mut threads: Vec<SlaThreadReport> = self
+            .threads
+            .iter()
+            .filter_map(|thread| {
+                let queue = queues.iter().find(|q| q.label_id == thread.label_id)?;
+                Some(SlaThreadReport {
+                    thread: thread.clone(),
+                    status: thread.status(Some(queue), now),
+                })
+            })
+            .collect();
+        threads.sort_by_key(|t| t.thread.due_at);
+
+        let queues = queues
+            .iter()
+            .map(|queue| {
+                let mut report = SlaQueueReport {
+                    label_id: queue.label_id.clone(),
+                    name: queue_name(Some(queue), &queue.label_id),
+                    target_minutes: queue.target_minutes,
+                    open: 0,
+                    at_risk: 0,
+                    breached: 0,
+                    met: 0,
+                    missed: 0,
+                    average_response_minutes: None,
+                };
+                let mut response_secs = Vec::new();
+                for entry in threads
+                    .iter()
+                    .filter(|t| t.thread.label_id == queue.label_id)
+                {
+                    match entry.status {
+                        SlaStatus::OnTrack => report.open += 1,
+                        SlaStatus::AtRisk => {
+                            report.open += 1;
+                            report.at_risk += 1;
+                        }
+                        SlaStatus::Breached => {
+                            report.open += 1;
+                            report.breached += 1;
+                        }
+                        SlaStatus::Met => report.met += 1,
+                        SlaStatus::Missed => report.missed += 1,
+                    }
+                    if let Some(at) = entry.thread.responded_at {
+                        response_secs.push(at - entry.thread.opened_at);
+                    }
+                }
+                if !response_secs.is_empty() {
+                    let total: u64 = response_secs.iter().sum();
+                    report.average_response_minutes = Some(total / response_secs.len() as u64 / 60);
+                }
+                report
+            })
+            .collect();
+
+        SlaReport { queues, threads }
+    }
+}
+
+/// Search for mail the user sent since `since` (unix seconds), to find
+/// first replies in queue threads
+pub fn replies_query(since: &str) -> String {
+    format!("in:sent after:{}", since)
+}
+
+/// Gmail's receive time of a message, in unix seconds
+pub fn received_at(message: &GmailMessage) -> Option<u64> {
+    let millis: u64 = message.internal_date.as_deref()?.parse().ok()?;
+    Some(millis / 1000)
+}
+
+fn queue_name(queue: Option<&SlaQueue>, label_id: &str) -> String {
+    match queue {
+        Some(queue) if !queue.name.trim().is_empty() => queue.name.clone(),
+        _ => label_id.to_string(),
+    }
+}
+
+impl AccountScoped for SlaTracker {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue() -> SlaQueue {
+        SlaQueue {
+            label_id: "Label_support".to_string(),
+            name: "Support".to_string(),
+            target_minutes: 60,
+            warn_at_percent: 50,
+        }
+    }
+
+    fn message(id: &str, thread_id: &str, labels: &[&str], at: u64) -> GmailMessage {
+        GmailMessage {
+            id: id.to_string(),
+            thread_id: thread_id.to_string(),
+            label_ids: Some(labels.iter().map(|l| l.to_string()).collect()),
+            internal_date: Some((at * 1000).to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_clock_starts_on_queue_mail_and_stops_on_first_reply() {
+        let queues = vec![queue()];
+        let mut tracker = SlaTracker::default();
+        assert!(tracker.open(
+            &queues,
+            &[
+                message("m1", "t1", &["INBOX", "Label_support"], 1000),
+                message("m2", "t2", &["INBOX"], 1000),
+                message("m3", "t3", &["SENT", "Label_support"], 1000),
+            ],
+            2000,
+        ));
+        assert!(!tracker.open(
+            &queues,
+            &[message("m4", "t1", &["INBOX", "Label_support"], 1500)],
+            2000
+        ));
+        assert!(tracker.is_pending("t1"));
+        assert!(!tracker.is_pending("t2"));
+
+        assert!(!tracker.respond("t1", 900));
+        assert!(tracker.respond("t1", 1000 + 30 * 60));
+        assert!(!tracker.respond("t1", 5000));
+
+        let report = tracker.report(&queues, 9000);
+        assert_eq!(report.threads.len(), 1);
+        assert_eq!(report.threads[0].status, SlaStatus::Met);
+        assert_eq!(report.queues[0].met, 1);
+        assert_eq!(report.queues[0].average_response_minutes, Some(30));
+    }
+
+    #[test]
+    fn test_warnings_fire_once_at_risk_and_once_breached() {
+        let queues = vec![queue()];
+        let mut tracker = SlaTracker::default();
+        tracker.open(
+            &queues,
+            &[message("m1", "t1", &["INBOX", "Label_support"], 0)],
+            0,
+        );
+
+        assert!(tracker.warnings(&queues, 10 * 60).is_empty());
+        let at_risk = tracker.warnings(&queues, 40 * 60);
+        assert_eq!(at_risk.len(), 1);
+        assert!(!at_risk[0].breached);
+        assert_eq!(at_risk[0].queue, "Support");
+        assert!(tracker.warnings(&queues, 45 * 60).is_empty());
+
+        let breached = tracker.warnings(&queues, 61 * 60);
+        assert_eq!(breached.len(), 1);
+        assert!(breached[0].breached);
+        assert!(tracker.warnings(&queues, 90 * 60).is_empty());
+
+        let report = tracker.report(&queues, 90 * 60);
+        assert_eq!(report.queues[0].open, 1);
+        assert_eq!(report.queues[0].breached, 1);
+
+        assert!(tracker.prune(RETENTION_SECS + 1));
+        assert!(!tracker.has_pending());
+    }
+}
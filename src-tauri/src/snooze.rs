@@ -0,0 +1,202 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::GmailMessage;
+use crate::local_store::{app_data_path, load_json, save_json};
+use crate::scheduler::ScheduledTime;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+const SNOOZE_FILE: &str = "snoozed.json";
+
+/// A message archived until `until`, when it goes back to the inbox
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnoozedEmail {
+    pub message_id: String,
+    pub thread_id: String,
+    pub from: String,
+    pub subject: String,
+    /// Unix timestamps (seconds)
+    pub snoozed_at: u64,
+    pub until: u64,
+    /// The wall-clock time picked, when `until` came from a preset; `until`
+    /// is worked out again from it if the user changes zone
+    #[serde(default)]
+    pub wake: Option<ScheduledTime>,
+}
+
+impl SnoozedEmail {
+    pub fn from_message(message: &GmailMessage, now: u64, until: u64) -> Self {
+        SnoozedEmail {
+            message_id: message.id.clone(),
+            thread_id: message.thread_id.clone(),
+            from: message.get_from(),
+            subject: message.get_subject(),
+            snoozed_at: now,
+            until,
+            wake: None,
+        }
+    }
+}
+
+/// Snoozed messages, persisted so they still come back after a restart
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnoozeList {
+    entries: Vec<SnoozedEmail>,
+    account_id: Option<String>,
+}
+
+impl SnoozeList {
+    pub fn load() -> Self {
+        load_json(&app_data_path(SNOOZE_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(SNOOZE_FILE), self)
+    }
+
+    /// Entries soonest first
+    pub fn entries(&self) -> &[SnoozedEmail] {
+        &self.entries
+    }
+
+    /// Add an entry, replacing any earlier snooze of the same message
+    pub fn snooze(&mut self, entry: SnoozedEmail) {
+        self.entries.retain(|e| e.message_id != entry.message_id);
+        let index = self.entries.partition_point(|e| e.until <= entry.until);
+        self.entries.insert(index, entry);
+    }
+
+    pub fn remove(&mut self, message_id: &str) -> Option<SnoozedEmail> {
+        let index = self
+            .entries
+            .iter()
+            .position(|e| e.message_id == message_id)?;
+        Some(self.entries.remove(index))
+    }
+
+    /// Move floating wake times to `zone`, returning whether any changed
+    pub fn follow_zone(&mut self, zone: &Tz) -> bool {
+        let mut moved = false;
+        for entry in &mut self.entries {
+            if let Some(wake) = entry.wake.as_mut() {
+                if wake.follow(zone) {
+                    entry.until = wake.at;
+                    moved = true;
+                }
+            }
+        }
+        if moved {
+            self.entries.sort_by_key(|e| e.until);
+        }
+        moved
+    }
+
+    /// Entries whose time has come. They stay listed until `remove`d, so one
+    /// that fails to return is tried again on the next check.
+    pub fn due(&self, now: u64) -> Vec<SnoozedEmail> {
+        self.entries
+            .iter()
+            .take_while(|e| e.until <= now)
+            .cloned()
+            .collect()
+    }
+}
+
+impl AccountScoped for SnoozeList {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message_id: &str, until: u64) -> SnoozedEmail {
+        SnoozedEmail {
+            message_id: message_id.to_string(),
+            thread_id: format!("t_{}", message_id),
+            from: "alice@example.com".to_string(),
+            subject: "Later".to_string(),
+            snoozed_at: 0,
+            until,
+            wake: None,
+        }
+    }
+
+    #[test]
+    fn test_entries_are_kept_in_wake_order() {
+        let mut list = SnoozeList::default();
+        list.snooze(entry("m1", 300));
+        list.snooze(entry("m2", 100));
+        list.snooze(entry("m3", 200));
+        let order: Vec<&str> = list
+            .entries()
+            .iter()
+            .map(|e| e.message_id.as_str())
+            .collect();
+        assert_eq!(order, vec!["m2", "m3", "m1"]);
+    }
+
+    #[test]
+    fn test_resnooze_replaces_entry() {
+        let mut list = SnoozeList::default();
+        list.snooze(entry("m1", 100));
+        list.snooze(entry("m1", 500));
+        assert_eq!(list.entries().len(), 1);
+        assert_eq!(list.entries()[0].until, 500);
+    }
+
+    #[test]
+    fn test_due_leaves_entries_until_removed() {
+        let mut list = SnoozeList::default();
+        list.snooze(entry("m1", 100));
+        list.snooze(entry("m2", 200));
+
+        let due = list.due(150);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].message_id, "m1");
+        assert_eq!(list.due(150).len(), 1);
+
+        assert!(list.remove("m1").is_some());
+        assert!(list.remove("m1").is_none());
+        assert!(list.due(150).is_empty());
+        assert_eq!(list.due(200).len(), 1);
+    }
+
+    #[test]
+    fn test_preset_snoozes_follow_the_zone() {
+        use crate::scheduler::Preset;
+        use chrono::TimeZone;
+        use chrono_tz::{America::New_York, Europe::Berlin};
+
+        let now = Berlin.with_ymd_and_hms(2025, 6, 11, 10, 0, 0).unwrap();
+        let wake = Preset::TomorrowMorning.schedule(&now).unwrap();
+        let mut list = SnoozeList::default();
+        list.snooze(SnoozedEmail {
+            until: wake.at,
+            wake: Some(wake),
+            ..entry("morning", 0)
+        });
+        list.snooze(entry("fixed", 1_749_700_000));
+
+        assert!(!list.follow_zone(&Berlin));
+        assert!(list.follow_zone(&New_York));
+        let ny_eight = New_York.with_ymd_and_hms(2025, 6, 12, 8, 0, 0).unwrap();
+        assert_eq!(list.entries()[1].message_id, "morning");
+        assert_eq!(list.entries()[1].until, ny_eight.timestamp() as u64);
+        assert_eq!(list.entries()[0].until, 1_749_700_000);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::{extract_email_address, GmailMessage};
+use crate::local_store::{app_data_path, load_json, save_json};
+use serde::{Deserialize, Serialize};
+
+const SPAM_REVIEW_FILE: &str = "spam_review.json";
+
+/// Gmail deletes spam after 30 days, so older entries point at nothing
+const SPAM_RETENTION_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Mail from a known contact that Gmail filed as spam
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProbableFalsePositive {
+    pub message_id: String,
+    pub thread_id: String,
+    pub from: String,
+    pub subject: String,
+    /// Unix timestamp (seconds) when sync found it in Spam
+    pub found_at: u64,
+}
+
+impl ProbableFalsePositive {
+    pub fn from_message(message: &GmailMessage, now: u64) -> Self {
+        ProbableFalsePositive {
+            message_id: message.id.clone(),
+            thread_id: message.thread_id.clone(),
+            from: message.get_from(),
+            subject: message.get_subject(),
+            found_at: now,
+        }
+    }
+}
+
+/// When the user last opened Spam, and which spam since then looks like it
+/// was misfiled
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpamReview {
+    /// Unix timestamp (seconds)
+    last_viewed_at: Option<u64>,
+    flagged: Vec<ProbableFalsePositive>,
+    account_id: Option<String>,
+}
+
+impl SpamReview {
+    pub fn load() -> Self {
+        load_json(&app_data_path(SPAM_REVIEW_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(SPAM_REVIEW_FILE), self)
+    }
+
+    pub fn mark_viewed(&mut self, now: u64) {
+        self.last_viewed_at = Some(now);
+    }
+
+    /// Flag a message, returning true when it is the first one since Spam was
+    /// last viewed, so the user is reminded once rather than per message
+    pub fn flag(&mut self, entry: ProbableFalsePositive) -> bool {
+        let now = entry.found_at;
+        self.flagged
+            .retain(|e| e.found_at + SPAM_RETENTION_SECS > now && e.message_id != entry.message_id);
+        let first = self.probable_false_positives().is_empty();
+        self.flagged.push(entry);
+        first
+    }
+
+    /// Drop a message that left Spam
+    pub fn remove(&mut self, message_id: &str) -> bool {
+        let before = self.flagged.len();
+        self.flagged.retain(|e| e.message_id != message_id);
+        self.flagged.len() != before
+    }
+
+    /// Flagged messages found since Spam was last viewed, newest first
+    pub fn probable_false_positives(&self) -> Vec<ProbableFalsePositive> {
+        self.flagged
+            .iter()
+            .rev()
+            .filter(|e| self.last_viewed_at.is_none_or(|viewed| e.found_at > viewed))
+            .cloned()
+            .collect()
+    }
+}
+
+impl AccountScoped for SpamReview {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+/// Whether a mailbox query opens the Spam folder
+pub fn is_spam_query(query: &str) -> bool {
+    query
+        .split_whitespace()
+        .any(|term| matches!(term.to_ascii_lowercase().as_str(), "in:spam" | "label:spam"))
+}
+
+/// Whether a spam message's sender is someone the user knows: a saved
+/// contact or someone they have written to. `known` entries may be bare
+/// addresses or "Name <address>".
+pub fn is_from_known_contact(message: &GmailMessage, known: &[String]) -> bool {
+    let sender = extract_email_address(&message.get_from());
+    !sender.is_empty()
+        && known
+            .iter()
+            .any(|k| extract_email_address(k).eq_ignore_ascii_case(&sender))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message_id: &str, found_at: u64) -> ProbableFalsePositive {
+        ProbableFalsePositive {
+            message_id: message_id.to_string(),
+            thread_id: format!("t_{}", message_id),
+            from: "Alice <alice@example.com>".to_string(),
+            subject: "Dinner?".to_string(),
+            found_at,
+        }
+    }
+
+    #[test]
+    fn test_reminds_once_per_review() {
+        let mut review = SpamReview::default();
+        assert!(review.flag(entry("m1", 100)));
+        assert!(!review.flag(entry("m2", 200)));
+        let ids: Vec<String> = review
+            .probable_false_positives()
+            .into_iter()
+            .map(|e| e.message_id)
+            .collect();
+        assert_eq!(ids, vec!["m2", "m1"]);
+
+        review.mark_viewed(300);
+        assert!(review.probable_false_positives().is_empty());
+        assert!(review.flag(entry("m3", 400)));
+        assert_eq!(review.probable_false_positives().len(), 1);
+
+        assert!(review.remove("m3"));
+        assert!(!review.remove("m3"));
+        assert!(review.probable_false_positives().is_empty());
+    }
+
+    #[test]
+    fn test_expired_entries_are_dropped() {
+        let mut review = SpamReview::default();
+        review.flag(entry("old", 0));
+        review.flag(entry("new", SPAM_RETENTION_SECS + 1));
+        assert_eq!(review.probable_false_positives().len(), 1);
+    }
+
+    #[test]
+    fn test_spam_queries() {
+        assert!(is_spam_query("in:spam"));
+        assert!(is_spam_query("from:alice LABEL:SPAM"));
+        assert!(!is_spam_query("in:inbox spam"));
+    }
+}
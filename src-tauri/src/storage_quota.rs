@@ -0,0 +1,164 @@
+use crate::gmail_auth::AuthTokens;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+/// Warn once this share of the storage is used
+pub const WARN_PERCENT: f64 = 90.0;
+
+/// Past this, Gmail is about to stop sending and receiving
+pub const CRITICAL_PERCENT: f64 = 98.0;
+
+/// How stale the last lookup may get before the scheduler polls again
+pub const POLL_INTERVAL_SECS: u64 = 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageLevel {
+    Ok,
+    Warning,
+    Critical,
+}
+
+/// Google account storage, shared between Gmail, Drive and Photos
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageStatus {
+    pub usage_bytes: u64,
+    /// None for accounts without a storage limit
+    pub limit_bytes: Option<u64>,
+    pub usage_in_drive_bytes: Option<u64>,
+    pub usage_in_trash_bytes: Option<u64>,
+    pub percent_used: Option<f64>,
+    pub level: StorageLevel,
+    /// Unix timestamp (seconds) of the lookup
+    pub checked_at: u64,
+}
+
+impl StorageStatus {
+    /// Whether moving from `previous` to this status deserves a new warning
+    pub fn should_warn(&self, previous: Option<&StorageStatus>) -> bool {
+        self.level != StorageLevel::Ok && previous.is_none_or(|p| p.level < self.level)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AboutResponse {
+    #[serde(rename = "storageQuota")]
+    storage_quota: StorageQuota,
+}
+
+/// Drive reports byte counts as decimal strings
+#[derive(Debug, Deserialize)]
+struct StorageQuota {
+    limit: Option<String>,
+    usage: Option<String>,
+    #[serde(rename = "usageInDrive")]
+    usage_in_drive: Option<String>,
+    #[serde(rename = "usageInDriveTrash")]
+    usage_in_drive_trash: Option<String>,
+}
+
+fn parse_bytes(value: &Option<String>) -> Option<u64> {
+    value.as_deref().and_then(|v| v.parse().ok())
+}
+
+impl StorageQuota {
+    fn into_status(self, checked_at: u64) -> StorageStatus {
+        let usage_bytes = parse_bytes(&self.usage).unwrap_or(0);
+        let limit_bytes = parse_bytes(&self.limit).filter(|limit| *limit > 0);
+        let percent_used = limit_bytes.map(|limit| usage_bytes as f64 * 100.0 / limit as f64);
+        let level = match percent_used {
+            Some(percent) if percent >= CRITICAL_PERCENT => StorageLevel::Critical,
+            Some(percent) if percent >= WARN_PERCENT => StorageLevel::Warning,
+            _ => StorageLevel::Ok,
+        };
+
+        StorageStatus {
+            usage_bytes,
+            limit_bytes,
+            usage_in_drive_bytes: parse_bytes(&self.usage_in_drive),
+            usage_in_trash_bytes: parse_bytes(&self.usage_in_drive_trash),
+            percent_used,
+            level,
+            checked_at,
+        }
+    }
+}
+
+/// Storage lookups go through Drive's about endpoint; Gmail has no quota API.
+/// Needs the drive.file scope, so accounts authorized before it was requested
+/// get errors until they sign in again.
+pub struct StorageClient {
+    client: Client,
+    access_token: String,
+}
+
+impl StorageClient {
+    pub fn new(tokens: &AuthTokens) -> Self {
+        Self {
+            client: Client::new(),
+            access_token: tokens.access_token.clone(),
+        }
+    }
+
+    pub async fn get_status(
+        &self,
+        checked_at: u64,
+    ) -> Result<StorageStatus, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self
+            .client
+            .get("https://www.googleapis.com/drive/v3/about")
+            .query(&[("fields", "storageQuota")])
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Drive about API error: {}", error_text).into());
+        }
+
+        let about: AboutResponse = response.json().await?;
+        Ok(about.storage_quota.into_status(checked_at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(usage: &str, limit: Option<&str>) -> StorageStatus {
+        StorageQuota {
+            limit: limit.map(str::to_string),
+            usage: Some(usage.to_string()),
+            usage_in_drive: Some("100".to_string()),
+            usage_in_drive_trash: None,
+        }
+        .into_status(0)
+    }
+
+    #[test]
+    fn test_levels_from_usage() {
+        assert_eq!(status("50", Some("100")).level, StorageLevel::Ok);
+        assert_eq!(status("91", Some("100")).level, StorageLevel::Warning);
+        assert_eq!(status("99", Some("100")).level, StorageLevel::Critical);
+
+        let unlimited = status("5000", None);
+        assert_eq!(unlimited.level, StorageLevel::Ok);
+        assert_eq!(unlimited.percent_used, None);
+        assert_eq!(unlimited.usage_in_drive_bytes, Some(100));
+    }
+
+    #[test]
+    fn test_warns_only_when_level_rises() {
+        let ok = status("50", Some("100"));
+        let warning = status("91", Some("100"));
+        let critical = status("99", Some("100"));
+
+        assert!(!ok.should_warn(None));
+        assert!(warning.should_warn(None));
+        assert!(warning.should_warn(Some(&ok)));
+        assert!(!warning.should_warn(Some(&warning)));
+        assert!(critical.should_warn(Some(&warning)));
+        assert!(!warning.should_warn(Some(&critical)));
+    }
+}
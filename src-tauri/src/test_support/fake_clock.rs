@@ -0,0 +1,55 @@
+use crate::clock::Clock;
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A clock that only moves when told to; both readings advance together
+#[derive(Debug)]
+pub struct FakeClock {
+    start: Instant,
+    wall_start: DateTime<Utc>,
+    elapsed: Mutex<Duration>,
+}
+
+impl FakeClock {
+    pub fn at(wall_start: DateTime<Utc>) -> Self {
+        FakeClock {
+            start: Instant::now(),
+            wall_start,
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+}
+
+impl Clock for FakeClock {
+    fn instant(&self) -> Instant {
+        self.start + *self.elapsed.lock().unwrap()
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        let elapsed = *self.elapsed.lock().unwrap();
+        self.wall_start + chrono::Duration::from_std(elapsed).unwrap_or(chrono::Duration::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_fake_clock_moves_only_when_advanced() {
+        let clock = FakeClock::at(Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap());
+        let before = clock.instant();
+        assert_eq!(clock.instant(), before);
+        assert_eq!(clock.unix_now(), 1_748_779_200);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.instant() - before, Duration::from_secs(90));
+        assert_eq!(clock.unix_now(), 1_748_779_290);
+    }
+}
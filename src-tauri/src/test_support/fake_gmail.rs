@@ -0,0 +1,974 @@
+//! In-memory Gmail API server for integration tests.
+//!
+//! `FakeGmail::start` serves the subset of the Gmail REST API that
+//! `GmailClient` calls on a random localhost port, so sync, batching and retry
+//! paths can run end to end without Google. Point a client at it with
+//! `FakeGmail::client`.
+//!
+//! Fidelity is deliberately limited:
+//! - `q` understands `in:`, `label:`, `category:`, `is:unread/read/starred`,
+//!   `from:`, `subject:`, a leading `-` to negate, and plain words matched
+//!   against the subject and snippet. Date operators (`after:`,
+//!   `newer_than:`, ...) match everything.
+//! - `format` is ignored; messages come back as stored.
+//! - Sent, drafted and imported messages keep their raw RFC 2822 body as a
+//!   single part.
+//! - Filters are stored and listed but never applied to messages.
+//! - History records additions, deletions and label modifications made
+//!   through the API or `insert_message`; it never expires, so a stale
+//!   `startHistoryId` is only simulated with `fail_next(1, 404)`.
+
+use crate::gmail_auth::AuthTokens;
+use crate::gmail_client::{
+    GmailClient, GmailFilter, GmailLabel, GmailMessage, MessageBody, MessageHeader, MessagePayload,
+    SendAsAlias,
+};
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use base64::{
+    engine::general_purpose::{URL_SAFE, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+const API_PREFIX: &str = "/gmail/v1/users/me/";
+const BATCH_PATH: &str = "/batch/gmail/v1";
+const BATCH_BOUNDARY: &str = "batch_fake_gmail";
+
+/// Page size when a list request doesn't send maxResults
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+const SYSTEM_LABELS: [&str; 8] = [
+    "INBOX",
+    "SENT",
+    "DRAFT",
+    "UNREAD",
+    "STARRED",
+    "IMPORTANT",
+    "SPAM",
+    "TRASH",
+];
+
+/// A message with the given labels, headers and plain-text body, ready for
+/// `FakeGmail::insert_message`
+pub fn fixture_message(
+    id: &str,
+    thread_id: &str,
+    label_ids: &[&str],
+    headers: &[(&str, &str)],
+    body: &str,
+) -> GmailMessage {
+    GmailMessage {
+        id: id.to_string(),
+        thread_id: thread_id.to_string(),
+        snippet: body.chars().take(100).collect(),
+        label_ids: Some(label_ids.iter().map(|l| l.to_string()).collect()),
+        payload: Some(MessagePayload {
+            headers: Some(
+                headers
+                    .iter()
+                    .map(|(name, value)| MessageHeader {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                    })
+                    .collect(),
+            ),
+            body: Some(MessageBody {
+                data: Some(URL_SAFE.encode(body.as_bytes())),
+                attachment_id: None,
+                size: Some(body.len() as u64),
+            }),
+            ..Default::default()
+        }),
+        size_estimate: Some(body.len() as u64),
+        ..Default::default()
+    }
+}
+
+/// Turn a raw RFC 2822 message from send or drafts into a stored message
+fn message_from_raw(id: String, thread_id: String, raw: &str, label_ids: &[&str]) -> GmailMessage {
+    let (head, body) = raw
+        .split_once("\r\n\r\n")
+        .or_else(|| raw.split_once("\n\n"))
+        .unwrap_or((raw, ""));
+
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in head.lines() {
+        if line.starts_with([' ', '\t']) {
+            // Folded continuation of the previous header
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let header_refs: Vec<(&str, &str)> = headers
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    fixture_message(&id, &thread_id, label_ids, &header_refs, body)
+}
+
+fn decode_raw(raw: &str) -> Option<String> {
+    let bytes = URL_SAFE
+        .decode(raw)
+        .or_else(|_| URL_SAFE_NO_PAD.decode(raw))
+        .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn header_value<'a>(message: &'a GmailMessage, name: &str) -> Option<&'a str> {
+    message
+        .payload
+        .as_ref()?
+        .headers
+        .as_ref()?
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
+}
+
+fn has_label(message: &GmailMessage, label_id: &str) -> bool {
+    message
+        .label_ids
+        .as_ref()
+        .is_some_and(|ids| ids.iter().any(|id| id == label_id))
+}
+
+fn apply_labels(message: &mut GmailMessage, add: &[String], remove: &[String]) {
+    let ids = message.label_ids.get_or_insert_with(Vec::new);
+    ids.retain(|id| !remove.contains(id));
+    for id in add {
+        if !ids.contains(id) {
+            ids.push(id.clone());
+        }
+    }
+}
+
+fn string_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Distinct thread ids in first-seen order
+fn thread_ids(messages: &[&GmailMessage]) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    for message in messages {
+        if !ids.contains(&message.thread_id) {
+            ids.push(message.thread_id.clone());
+        }
+    }
+    ids
+}
+
+fn labels_response(message: &GmailMessage) -> Value {
+    json!({
+        "id": message.id,
+        "threadId": message.thread_id,
+        "labelIds": message.label_ids.clone().unwrap_or_default(),
+    })
+}
+
+fn not_found() -> (StatusCode, Value) {
+    error(StatusCode::NOT_FOUND, "Requested entity was not found.")
+}
+
+/// Gmail's JSON error envelope
+fn error(status: StatusCode, message: &str) -> (StatusCode, Value) {
+    (
+        status,
+        json!({
+            "error": {
+                "code": status.as_u16(),
+                "message": message,
+                "status": status.canonical_reason().unwrap_or("UNKNOWN"),
+            }
+        }),
+    )
+}
+
+/// Slice `items` by the offset-style page tokens this server hands out
+fn paginate<T: Clone>(items: &[T], query: &HashMap<String, String>) -> (Vec<T>, Option<String>) {
+    let start: usize = query
+        .get("pageToken")
+        .and_then(|t| t.parse().ok())
+        .unwrap_or(0);
+    let size: usize = query
+        .get("maxResults")
+        .and_then(|m| m.parse().ok())
+        .unwrap_or(DEFAULT_PAGE_SIZE);
+    let end = (start + size).min(items.len());
+    let page = items.get(start..end).unwrap_or_default().to_vec();
+    let next = (end < items.len()).then(|| end.to_string());
+    (page, next)
+}
+
+struct Draft {
+    id: String,
+    message: GmailMessage,
+    /// Decoded raw message, recorded as sent when the draft is sent
+    raw: String,
+}
+
+impl Draft {
+    fn summary(&self) -> Value {
+        json!({
+            "id": self.id,
+            "message": { "id": self.message.id, "threadId": self.message.thread_id },
+        })
+    }
+}
+
+#[derive(Default)]
+struct Mailbox {
+    email_address: String,
+    labels: Vec<GmailLabel>,
+    /// Newest first, like Gmail's list order
+    messages: Vec<GmailMessage>,
+    /// Attachment bytes by (message id, attachment id)
+    attachments: HashMap<(String, String), Vec<u8>>,
+    drafts: Vec<Draft>,
+    send_as: Vec<SendAsAlias>,
+    filters: Vec<GmailFilter>,
+    /// Decoded raw message of every send, oldest first
+    sent: Vec<String>,
+    /// history.list records, oldest first; a record's id is its position + 1
+    history: Vec<Value>,
+    /// Statuses to answer the next requests with, one per request
+    failures: VecDeque<StatusCode>,
+    /// "METHOD /path" of every HTTP request received
+    requests: Vec<String>,
+    next_id: u64,
+}
+
+impl Mailbox {
+    fn new_id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}{:x}", prefix, self.next_id)
+    }
+
+    fn history_id(&self) -> String {
+        self.history.len().to_string()
+    }
+
+    /// Append a history record of `kind` ("messagesAdded", "labelsRemoved", ...)
+    fn record_history(&mut self, kind: &str, message: &GmailMessage, label_ids: &[String]) {
+        let mut change = json!({ "message": labels_response(message) });
+        if kind.starts_with("labels") {
+            change["labelIds"] = json!(label_ids);
+        }
+        let id = self.history.len() + 1;
+        self.history
+            .push(json!({ "id": id.to_string(), kind: [change] }));
+    }
+
+    /// Apply a label modification and record what actually changed
+    fn relabel(&mut self, id: &str, add: &[String], remove: &[String]) -> Option<Value> {
+        let message = self.message_mut(id)?;
+        let before = message.label_ids.clone().unwrap_or_default();
+        apply_labels(message, add, remove);
+        let message = message.clone();
+        let after = message.label_ids.clone().unwrap_or_default();
+
+        let added: Vec<String> = after
+            .iter()
+            .filter(|l| !before.contains(l))
+            .cloned()
+            .collect();
+        let removed: Vec<String> = before
+            .iter()
+            .filter(|l| !after.contains(l))
+            .cloned()
+            .collect();
+        if !added.is_empty() {
+            self.record_history("labelsAdded", &message, &added);
+        }
+        if !removed.is_empty() {
+            self.record_history("labelsRemoved", &message, &removed);
+        }
+        Some(labels_response(&message))
+    }
+
+    /// Drop messages, recording each deletion
+    fn delete_messages(&mut self, ids: &[String]) -> usize {
+        let (deleted, kept): (Vec<GmailMessage>, Vec<GmailMessage>) =
+            std::mem::take(&mut self.messages)
+                .into_iter()
+                .partition(|m| ids.contains(&m.id));
+        self.messages = kept;
+        for message in &deleted {
+            self.record_history("messagesDeleted", message, &[]);
+        }
+        deleted.len()
+    }
+
+    fn label_matches(&self, message: &GmailMessage, name: &str) -> bool {
+        self.labels
+            .iter()
+            .filter(|l| l.id.eq_ignore_ascii_case(name) || l.name.eq_ignore_ascii_case(name))
+            .any(|l| has_label(message, &l.id))
+    }
+
+    fn term_matches(&self, message: &GmailMessage, term: &str) -> bool {
+        let (operator, value) = term.split_once(':').unwrap_or(("", term));
+        let value = value.trim_matches('"').to_lowercase();
+        let contains = |text: Option<&str>| text.is_some_and(|t| t.to_lowercase().contains(&value));
+
+        match operator {
+            "in" | "label" => self.label_matches(message, &value),
+            "category" => match value.as_str() {
+                "primary" => has_label(message, "CATEGORY_PERSONAL"),
+                other => has_label(message, &format!("CATEGORY_{}", other.to_uppercase())),
+            },
+            "is" => match value.as_str() {
+                "unread" => has_label(message, "UNREAD"),
+                "read" => !has_label(message, "UNREAD"),
+                "starred" => has_label(message, "STARRED"),
+                "important" => has_label(message, "IMPORTANT"),
+                _ => true,
+            },
+            "from" => contains(header_value(message, "From")),
+            "to" => contains(header_value(message, "To")),
+            "subject" => contains(header_value(message, "Subject")),
+            "" => {
+                contains(header_value(message, "Subject"))
+                    || contains(Some(message.snippet.as_str()))
+            }
+            _ => true,
+        }
+    }
+
+    /// Gmail leaves spam and trash out of results unless the query asks for them
+    fn query_matches(&self, message: &GmailMessage, q: Option<&str>) -> bool {
+        let q = q.unwrap_or("");
+        let lowered = q.to_lowercase();
+        for hidden in ["SPAM", "TRASH"] {
+            let asked = lowered.contains(&format!("in:{}", hidden.to_lowercase()));
+            if has_label(message, hidden) && !asked {
+                return false;
+            }
+        }
+
+        q.split_whitespace()
+            .all(|term| match term.strip_prefix('-') {
+                Some(negated) => !self.term_matches(message, negated),
+                None => self.term_matches(message, term),
+            })
+    }
+
+    fn matching_messages(&self, query: &HashMap<String, String>) -> Vec<&GmailMessage> {
+        let label_filter = query.get("labelIds");
+        self.messages
+            .iter()
+            .filter(|m| self.query_matches(m, query.get("q").map(String::as_str)))
+            .filter(|m| label_filter.is_none_or(|label| has_label(m, label)))
+            .collect()
+    }
+
+    fn message_mut(&mut self, id: &str) -> Option<&mut GmailMessage> {
+        self.messages.iter_mut().find(|m| m.id == id)
+    }
+
+    fn label_with_counts(&self, label: &GmailLabel) -> GmailLabel {
+        let tagged: Vec<&GmailMessage> = self
+            .messages
+            .iter()
+            .filter(|m| has_label(m, &label.id))
+            .collect();
+        let unread: Vec<&GmailMessage> = tagged
+            .iter()
+            .copied()
+            .filter(|m| has_label(m, "UNREAD"))
+            .collect();
+
+        GmailLabel {
+            messages_total: Some(tagged.len() as u32),
+            messages_unread: Some(unread.len() as u32),
+            threads_total: Some(thread_ids(&tagged).len() as u32),
+            threads_unread: Some(thread_ids(&unread).len() as u32),
+            ..label.clone()
+        }
+    }
+
+    fn store_sent(&mut self, raw: &str, thread_id: Option<&str>) -> Option<GmailMessage> {
+        let decoded = decode_raw(raw)?;
+        let id = self.new_id("sent");
+        let thread_id = thread_id.map(str::to_string).unwrap_or_else(|| id.clone());
+        let message = message_from_raw(id, thread_id, &decoded, &["SENT"]);
+        self.sent.push(decoded);
+        self.messages.insert(0, message.clone());
+        self.record_history("messagesAdded", &message, &[]);
+        Some(message)
+    }
+
+    /// Store a message from messages.import or messages.insert
+    fn store_imported(&mut self, raw: &str, label_ids: &[String]) -> Option<GmailMessage> {
+        let decoded = decode_raw(raw)?;
+        let id = self.new_id("imported");
+        let labels: Vec<&str> = label_ids.iter().map(String::as_str).collect();
+        let message = message_from_raw(id.clone(), id, &decoded, &labels);
+        self.messages.insert(0, message.clone());
+        self.record_history("messagesAdded", &message, &[]);
+        Some(message)
+    }
+
+    /// A draft built from a drafts.create or drafts.update body
+    fn draft_from_body(&mut self, id: String, body: &Value) -> Option<Draft> {
+        let message = &body["message"];
+        let raw = decode_raw(message["raw"].as_str()?)?;
+        let message_id = self.new_id("draftmsg");
+        let thread_id = message["threadId"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| message_id.clone());
+        Some(Draft {
+            id,
+            message: message_from_raw(message_id, thread_id, &raw, &["DRAFT"]),
+            raw,
+        })
+    }
+
+    /// Answer one API call. Used for plain requests and for each part of a batch.
+    fn handle(
+        &mut self,
+        method: &Method,
+        path_and_query: &str,
+        body: &[u8],
+    ) -> (StatusCode, Value) {
+        let (path, query_string) = path_and_query
+            .split_once('?')
+            .unwrap_or((path_and_query, ""));
+        let query: HashMap<String, String> = url::form_urlencoded::parse(query_string.as_bytes())
+            .into_owned()
+            .collect();
+        let body: Value = serde_json::from_slice(body).unwrap_or(Value::Null);
+
+        let segments: Vec<&str> = match path.strip_prefix(API_PREFIX) {
+            Some(rest) => rest.split('/').filter(|s| !s.is_empty()).collect(),
+            None => return not_found(),
+        };
+
+        match (method.as_str(), segments.as_slice()) {
+            ("GET", ["profile"]) => (
+                StatusCode::OK,
+                json!({
+                    "emailAddress": self.email_address,
+                    "messagesTotal": self.messages.len(),
+                    "threadsTotal": thread_ids(&self.messages.iter().collect::<Vec<_>>()).len(),
+                    "historyId": self.history_id(),
+                }),
+            ),
+            ("GET", ["history"]) => {
+                let Some(start) = query
+                    .get("startHistoryId")
+                    .and_then(|id| id.parse::<usize>().ok())
+                else {
+                    return error(StatusCode::BAD_REQUEST, "Invalid startHistoryId");
+                };
+                let records = self.history.get(start..).unwrap_or_default();
+                let (page, next) = paginate(records, &query);
+                (
+                    StatusCode::OK,
+                    json!({
+                        "history": page,
+                        "nextPageToken": next,
+                        "historyId": self.history_id(),
+                    }),
+                )
+            }
+            ("GET", ["settings", "sendAs"]) => (StatusCode::OK, json!({ "sendAs": self.send_as })),
+            ("GET", ["settings", "filters"]) => (StatusCode::OK, json!({ "filter": self.filters })),
+            ("POST", ["settings", "filters"]) => {
+                let Ok(mut filter) = serde_json::from_value::<GmailFilter>(body) else {
+                    return error(StatusCode::BAD_REQUEST, "Invalid filter");
+                };
+                filter.id = Some(self.new_id("filter"));
+                self.filters.push(filter.clone());
+                (StatusCode::OK, json!(filter))
+            }
+            ("DELETE", ["settings", "filters", id]) => {
+                let before = self.filters.len();
+                self.filters.retain(|f| f.id.as_deref() != Some(*id));
+                if self.filters.len() == before {
+                    return not_found();
+                }
+                (StatusCode::NO_CONTENT, Value::Null)
+            }
+
+            ("GET", ["labels"]) => (StatusCode::OK, json!({ "labels": self.labels })),
+            ("POST", ["labels"]) => {
+                let Some(name) = body["name"].as_str() else {
+                    return error(StatusCode::BAD_REQUEST, "Label name required");
+                };
+                if self
+                    .labels
+                    .iter()
+                    .any(|l| l.name.eq_ignore_ascii_case(name))
+                {
+                    return error(StatusCode::CONFLICT, "Label name exists or conflicts");
+                }
+                let label = GmailLabel {
+                    id: self.new_id("Label_"),
+                    name: name.to_string(),
+                    label_type: Some("user".to_string()),
+                    messages_total: None,
+                    messages_unread: None,
+                    threads_total: None,
+                    threads_unread: None,
+                };
+                self.labels.push(label.clone());
+                (StatusCode::OK, json!(label))
+            }
+            ("GET", ["labels", id]) => match self.labels.iter().find(|l| l.id == *id) {
+                Some(label) => (StatusCode::OK, json!(self.label_with_counts(label))),
+                None => not_found(),
+            },
+            ("PATCH", ["labels", id]) => {
+                let Some(label) = self.labels.iter_mut().find(|l| l.id == *id) else {
+                    return not_found();
+                };
+                if let Some(name) = body["name"].as_str() {
+                    label.name = name.to_string();
+                }
+                (StatusCode::OK, json!(label))
+            }
+            ("DELETE", ["labels", id]) => {
+                let before = self.labels.len();
+                self.labels.retain(|l| l.id != *id);
+                if self.labels.len() == before {
+                    return not_found();
+                }
+                let removed = vec![id.to_string()];
+                for message in &mut self.messages {
+                    apply_labels(message, &[], &removed);
+                }
+                (StatusCode::NO_CONTENT, Value::Null)
+            }
+
+            ("GET", ["messages"]) => {
+                let matching = self.matching_messages(&query);
+                let refs: Vec<Value> = matching
+                    .iter()
+                    .map(|m| json!({ "id": m.id, "threadId": m.thread_id }))
+                    .collect();
+                let (page, next) = paginate(&refs, &query);
+                (
+                    StatusCode::OK,
+                    json!({
+                        "messages": page,
+                        "nextPageToken": next,
+                        "resultSizeEstimate": refs.len(),
+                    }),
+                )
+            }
+            ("POST", ["messages", "send"]) => {
+                let raw = body["raw"].as_str().unwrap_or_default();
+                match self.store_sent(raw, body["threadId"].as_str()) {
+                    Some(message) => (StatusCode::OK, labels_response(&message)),
+                    None => error(StatusCode::BAD_REQUEST, "Invalid raw message"),
+                }
+            }
+            ("POST", ["messages", "import"] | ["messages"]) => {
+                let raw = body["raw"].as_str().unwrap_or_default();
+                match self.store_imported(raw, &string_list(&body["labelIds"])) {
+                    Some(message) => (StatusCode::OK, labels_response(&message)),
+                    None => error(StatusCode::BAD_REQUEST, "Invalid raw message"),
+                }
+            }
+            ("POST", ["messages", "batchDelete"]) => {
+                let ids = string_list(&body["ids"]);
+                self.delete_messages(&ids);
+                (StatusCode::NO_CONTENT, Value::Null)
+            }
+            ("POST", ["messages", "batchModify"]) => {
+                let ids = string_list(&body["ids"]);
+                let add = string_list(&body["addLabelIds"]);
+                let remove = string_list(&body["removeLabelIds"]);
+                for id in &ids {
+                    self.relabel(id, &add, &remove);
+                }
+                (StatusCode::NO_CONTENT, Value::Null)
+            }
+            ("GET", ["messages", id]) => match self.messages.iter().find(|m| m.id == *id) {
+                Some(message) => (StatusCode::OK, json!(message)),
+                None => not_found(),
+            },
+            ("DELETE", ["messages", id]) => {
+                if self.delete_messages(&[id.to_string()]) == 0 {
+                    return not_found();
+                }
+                (StatusCode::NO_CONTENT, Value::Null)
+            }
+            ("POST", ["messages", id, action]) => {
+                let (add, remove) = match *action {
+                    "modify" => (
+                        string_list(&body["addLabelIds"]),
+                        string_list(&body["removeLabelIds"]),
+                    ),
+                    "trash" => (vec!["TRASH".to_string()], vec![]),
+                    "untrash" => (vec![], vec!["TRASH".to_string()]),
+                    _ => return not_found(),
+                };
+                match self.relabel(id, &add, &remove) {
+                    Some(response) => (StatusCode::OK, response),
+                    None => not_found(),
+                }
+            }
+            ("GET", ["messages", id, "attachments", attachment_id]) => {
+                match self
+                    .attachments
+                    .get(&(id.to_string(), attachment_id.to_string()))
+                {
+                    Some(data) => (
+                        StatusCode::OK,
+                        json!({ "size": data.len(), "data": URL_SAFE.encode(data) }),
+                    ),
+                    None => not_found(),
+                }
+            }
+
+            ("GET", ["threads"]) => {
+                let matching = self.matching_messages(&query);
+                let threads: Vec<Value> = thread_ids(&matching)
+                    .into_iter()
+                    .map(|id| {
+                        let snippet = matching
+                            .iter()
+                            .find(|m| m.thread_id == id)
+                            .map(|m| m.snippet.clone());
+                        json!({ "id": id, "snippet": snippet })
+                    })
+                    .collect();
+                let (page, next) = paginate(&threads, &query);
+                (
+                    StatusCode::OK,
+                    json!({
+                        "threads": page,
+                        "nextPageToken": next,
+                        "resultSizeEstimate": threads.len(),
+                    }),
+                )
+            }
+            ("GET", ["threads", id]) => {
+                // Oldest first, as threads.get returns them
+                let messages: Vec<&GmailMessage> = self
+                    .messages
+                    .iter()
+                    .rev()
+                    .filter(|m| m.thread_id == *id)
+                    .collect();
+                if messages.is_empty() {
+                    return not_found();
+                }
+                (StatusCode::OK, json!({ "id": id, "messages": messages }))
+            }
+            ("POST", ["threads", id, "modify"]) => {
+                let add = string_list(&body["addLabelIds"]);
+                let remove = string_list(&body["removeLabelIds"]);
+                let message_ids: Vec<String> = self
+                    .messages
+                    .iter()
+                    .rev()
+                    .filter(|m| m.thread_id == *id)
+                    .map(|m| m.id.clone())
+                    .collect();
+                if message_ids.is_empty() {
+                    return not_found();
+                }
+                let messages: Vec<Value> = message_ids
+                    .iter()
+                    .filter_map(|message_id| self.relabel(message_id, &add, &remove))
+                    .collect();
+                (StatusCode::OK, json!({ "id": id, "messages": messages }))
+            }
+
+            ("GET", ["drafts"]) => {
+                let drafts: Vec<Value> = self.drafts.iter().map(Draft::summary).collect();
+                let (page, next) = paginate(&drafts, &query);
+                (
+                    StatusCode::OK,
+                    json!({
+                        "drafts": page,
+                        "nextPageToken": next,
+                        "resultSizeEstimate": drafts.len(),
+                    }),
+                )
+            }
+            ("POST", ["drafts"]) => {
+                let id = self.new_id("r");
+                let Some(draft) = self.draft_from_body(id, &body) else {
+                    return error(StatusCode::BAD_REQUEST, "Invalid draft message");
+                };
+                let response = draft.summary();
+                self.drafts.push(draft);
+                (StatusCode::OK, response)
+            }
+            ("POST", ["drafts", "send"]) => {
+                let Some(draft_id) = body["id"].as_str() else {
+                    return error(StatusCode::BAD_REQUEST, "Draft id required");
+                };
+                let Some(index) = self.drafts.iter().position(|d| d.id == draft_id) else {
+                    return not_found();
+                };
+                let mut draft = self.drafts.remove(index);
+                apply_labels(
+                    &mut draft.message,
+                    &["SENT".to_string()],
+                    &["DRAFT".to_string()],
+                );
+                let response = labels_response(&draft.message);
+                self.sent.push(draft.raw);
+                self.record_history("messagesAdded", &draft.message, &[]);
+                self.messages.insert(0, draft.message);
+                (StatusCode::OK, response)
+            }
+            ("GET", ["drafts", id]) => match self.drafts.iter().find(|d| d.id == *id) {
+                Some(draft) => (
+                    StatusCode::OK,
+                    json!({ "id": draft.id, "message": draft.message }),
+                ),
+                None => not_found(),
+            },
+            ("PUT", ["drafts", id]) => {
+                let Some(index) = self.drafts.iter().position(|d| d.id == *id) else {
+                    return not_found();
+                };
+                let Some(draft) = self.draft_from_body(id.to_string(), &body) else {
+                    return error(StatusCode::BAD_REQUEST, "Invalid draft message");
+                };
+                let response = draft.summary();
+                self.drafts[index] = draft;
+                (StatusCode::OK, response)
+            }
+            ("DELETE", ["drafts", id]) => {
+                let before = self.drafts.len();
+                self.drafts.retain(|d| d.id != *id);
+                if self.drafts.len() == before {
+                    return not_found();
+                }
+                (StatusCode::NO_CONTENT, Value::Null)
+            }
+
+            _ => not_found(),
+        }
+    }
+
+    /// Answer a multipart/mixed batch, one `application/http` part per request
+    fn handle_batch(&mut self, body: &str) -> String {
+        let mut response = String::new();
+        let requests = body.lines().filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let method = Method::from_bytes(words.next()?.as_bytes()).ok()?;
+            let path = words.next()?;
+            (words.next()? == "HTTP/1.1").then(|| (method, path.to_string()))
+        });
+
+        for (i, (method, path)) in requests.enumerate() {
+            let (status, value) = self.handle(&method, &path, &[]);
+            response.push_str(&format!("--{}\r\n", BATCH_BOUNDARY));
+            response.push_str("Content-Type: application/http\r\n");
+            response.push_str(&format!("Content-ID: <response-item{}>\r\n\r\n", i));
+            response.push_str(&format!(
+                "HTTP/1.1 {} {}\r\n",
+                status.as_u16(),
+                status.canonical_reason().unwrap_or("")
+            ));
+            response.push_str("Content-Type: application/json; charset=UTF-8\r\n\r\n");
+            response.push_str(&value.to_string());
+            response.push_str("\r\n");
+        }
+        response.push_str(&format!("--{}--\r\n", BATCH_BOUNDARY));
+        response
+    }
+}
+
+type Shared = Arc<Mutex<Mailbox>>;
+
+async fn handle_request(
+    State(mailbox): State<Shared>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let path_and_query = uri
+        .path_and_query()
+        .map(|pq| pq.as_str().to_string())
+        .unwrap_or_else(|| uri.path().to_string());
+
+    let mut mailbox = mailbox.lock().unwrap();
+    mailbox.requests.push(format!("{} {}", method, uri.path()));
+
+    if let Some(status) = mailbox.failures.pop_front() {
+        let (status, value) = error(status, "Injected failure");
+        return (status, axum::Json(value)).into_response();
+    }
+
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("Bearer ") && v.len() > "Bearer ".len());
+    if !authorized {
+        let (status, value) = error(StatusCode::UNAUTHORIZED, "Request is missing credentials");
+        return (status, axum::Json(value)).into_response();
+    }
+
+    if method == Method::POST && uri.path() == BATCH_PATH {
+        let body = String::from_utf8_lossy(&body);
+        let response = mailbox.handle_batch(&body);
+        return (
+            [(
+                header::CONTENT_TYPE,
+                format!("multipart/mixed; boundary={}", BATCH_BOUNDARY),
+            )],
+            response,
+        )
+            .into_response();
+    }
+
+    match mailbox.handle(&method, &path_and_query, &body) {
+        (StatusCode::NO_CONTENT, _) => StatusCode::NO_CONTENT.into_response(),
+        (status, value) => (status, axum::Json(value)).into_response(),
+    }
+}
+
+/// A running fake Gmail account. The server stops when this is dropped.
+pub struct FakeGmail {
+    mailbox: Shared,
+    base_url: String,
+    server: JoinHandle<()>,
+}
+
+impl FakeGmail {
+    /// Serve an empty mailbox for `email_address` with Gmail's system labels
+    pub async fn start(email_address: &str) -> Self {
+        let mailbox = Mailbox {
+            email_address: email_address.to_string(),
+            labels: SYSTEM_LABELS
+                .iter()
+                .map(|id| GmailLabel {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    label_type: Some("system".to_string()),
+                    messages_total: None,
+                    messages_unread: None,
+                    threads_total: None,
+                    threads_unread: None,
+                })
+                .collect(),
+            send_as: vec![SendAsAlias {
+                send_as_email: email_address.to_string(),
+                display_name: None,
+                is_primary: Some(true),
+                is_default: Some(true),
+                verification_status: None,
+                signature: None,
+            }],
+            ..Default::default()
+        };
+        let mailbox: Shared = Arc::new(Mutex::new(mailbox));
+
+        let router = Router::new()
+            .fallback(handle_request)
+            .with_state(mailbox.clone());
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .await
+            .expect("bind fake Gmail server");
+        let addr = listener.local_addr().expect("fake Gmail server address");
+        let server = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                eprintln!("Fake Gmail server stopped: {}", e);
+            }
+        });
+
+        FakeGmail {
+            mailbox,
+            base_url: format!("http://{}", addr),
+            server,
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// A client that talks to this server
+    pub fn client(&self, tokens: &AuthTokens) -> GmailClient {
+        let mut client = GmailClient::new(tokens);
+        client.base_url = self.base_url.clone();
+        client
+    }
+
+    /// Add a message as the newest in the mailbox
+    pub fn insert_message(&self, message: GmailMessage) {
+        let mut mailbox = self.mailbox.lock().unwrap();
+        mailbox.record_history("messagesAdded", &message, &[]);
+        mailbox.messages.insert(0, message);
+    }
+
+    pub fn insert_label(&self, label: GmailLabel) {
+        self.mailbox.lock().unwrap().labels.push(label);
+    }
+
+    pub fn insert_attachment(&self, message_id: &str, attachment_id: &str, data: &[u8]) {
+        self.mailbox.lock().unwrap().attachments.insert(
+            (message_id.to_string(), attachment_id.to_string()),
+            data.to_vec(),
+        );
+    }
+
+    pub fn insert_send_as(&self, alias: SendAsAlias) {
+        self.mailbox.lock().unwrap().send_as.push(alias);
+    }
+
+    /// The stored message, with whatever labels requests have left on it
+    pub fn message(&self, id: &str) -> Option<GmailMessage> {
+        let mailbox = self.mailbox.lock().unwrap();
+        mailbox.messages.iter().find(|m| m.id == id).cloned()
+    }
+
+    pub fn draft_count(&self) -> usize {
+        self.mailbox.lock().unwrap().drafts.len()
+    }
+
+    /// Raw RFC 2822 text of every message sent, oldest first
+    pub fn sent_messages(&self) -> Vec<String> {
+        self.mailbox.lock().unwrap().sent.clone()
+    }
+
+    /// Answer the next `count` requests with `status` and a Gmail error body,
+    /// whatever they ask for
+    pub fn fail_next(&self, count: usize, status: u16) {
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut mailbox = self.mailbox.lock().unwrap();
+        mailbox.failures.extend(std::iter::repeat_n(status, count));
+    }
+
+    /// "METHOD /path" of every HTTP request so far; a batch counts once
+    pub fn requests(&self) -> Vec<String> {
+        self.mailbox.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for FakeGmail {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
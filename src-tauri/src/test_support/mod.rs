@@ -0,0 +1,11 @@
+//! Fixtures for tests. The fake clock is available to unit tests; the Gmail
+//! server is only built with the `fake-gmail` feature, e.g.
+//! `cargo test --features fake-gmail`.
+
+pub mod fake_clock;
+#[cfg(feature = "fake-gmail")]
+pub mod fake_gmail;
+
+pub use fake_clock::FakeClock;
+#[cfg(feature = "fake-gmail")]
+pub use fake_gmail::{fixture_message, FakeGmail};
@@ -0,0 +1,157 @@
+use crate::gmail_client::{extract_email_address, GmailLabel, GmailThread};
+use serde::Serialize;
+
+/// Parent of the per-teammate claim labels. Labels belong to the mailbox, so
+/// everyone working a shared or delegated inbox sees a claim after sync.
+pub const CLAIM_LABEL_PREFIX: &str = "Aisle3/Handling";
+
+/// The label marking threads `address` is handling, e.g.
+/// "Aisle3/Handling/alice@example.com"
+pub fn claim_label_name(address: &str) -> String {
+    format!(
+        "{}/{}",
+        CLAIM_LABEL_PREFIX,
+        extract_email_address(address).to_ascii_lowercase()
+    )
+}
+
+/// The teammate a claim label belongs to, None for any other label
+pub fn claimant(label_name: &str) -> Option<&str> {
+    label_name
+        .strip_prefix(CLAIM_LABEL_PREFIX)?
+        .strip_prefix('/')
+        .filter(|address| !address.is_empty() && !address.contains('/'))
+}
+
+/// The claim labels that exist in the mailbox
+pub fn claim_labels(labels: &[GmailLabel]) -> Vec<&GmailLabel> {
+    labels
+        .iter()
+        .filter(|label| claimant(&label.name).is_some())
+        .collect()
+}
+
+/// A teammate's "I'm handling this" on a thread
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThreadClaim {
+    pub thread_id: String,
+    pub claimed_by: String,
+    pub label_id: String,
+    /// Claimed by the signed-in account
+    pub mine: bool,
+}
+
+impl ThreadClaim {
+    pub fn new(thread_id: &str, label: &GmailLabel, own_address: &str) -> Option<Self> {
+        let claimed_by = claimant(&label.name)?;
+        Some(ThreadClaim {
+            thread_id: thread_id.to_string(),
+            claimed_by: claimed_by.to_string(),
+            label_id: label.id.clone(),
+            mine: claimed_by.eq_ignore_ascii_case(&extract_email_address(own_address)),
+        })
+    }
+}
+
+/// Claims on a thread, read from the labels on any of its messages
+pub fn thread_claims(
+    thread: &GmailThread,
+    labels: &[GmailLabel],
+    own_address: &str,
+) -> Vec<ThreadClaim> {
+    let on_thread = |label: &GmailLabel| {
+        thread.messages.iter().flatten().any(|message| {
+            message
+                .label_ids
+                .as_ref()
+                .is_some_and(|ids| ids.contains(&label.id))
+        })
+    };
+    claim_labels(labels)
+        .into_iter()
+        .filter(|label| on_thread(label))
+        .filter_map(|label| ThreadClaim::new(&thread.id, label, own_address))
+        .collect()
+}
+
+/// Why a claim was refused: a teammate is already handling the thread
+pub fn conflict_message(claims: &[ThreadClaim]) -> String {
+    let others: Vec<&str> = claims
+        .iter()
+        .filter(|c| !c.mine)
+        .map(|c| c.claimed_by.as_str())
+        .collect();
+    format!(
+        "Already being handled by {}; claim again with take_over to take it over",
+        others.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmail_client::GmailMessage;
+
+    fn label(id: &str, name: &str) -> GmailLabel {
+        GmailLabel {
+            id: id.to_string(),
+            name: name.to_string(),
+            label_type: Some("user".to_string()),
+            messages_total: None,
+            messages_unread: None,
+            threads_total: None,
+            threads_unread: None,
+        }
+    }
+
+    fn message(id: &str, labels: &[&str]) -> GmailMessage {
+        GmailMessage {
+            id: id.to_string(),
+            thread_id: "t1".to_string(),
+            label_ids: Some(labels.iter().map(|l| l.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_claim_label_names_round_trip() {
+        let name = claim_label_name("Alice <Alice@Example.com>");
+        assert_eq!(name, "Aisle3/Handling/alice@example.com");
+        assert_eq!(claimant(&name), Some("alice@example.com"));
+        assert_eq!(claimant("Aisle3/Handling"), None);
+        assert_eq!(claimant("Aisle3/Handling/a/b"), None);
+        assert_eq!(claimant("Work/alice@example.com"), None);
+    }
+
+    #[test]
+    fn test_claims_are_read_from_any_message_in_the_thread() {
+        let labels = vec![
+            label("L1", "Aisle3/Handling/alice@example.com"),
+            label("L2", "Aisle3/Handling/bob@example.com"),
+            label("L3", "Clients"),
+        ];
+        let thread = GmailThread {
+            id: "t1".to_string(),
+            messages: Some(vec![
+                message("m1", &["INBOX", "L3"]),
+                message("m2", &["INBOX", "L2"]),
+            ]),
+        };
+
+        let claims = thread_claims(&thread, &labels, "alice@example.com");
+        assert_eq!(
+            claims,
+            vec![ThreadClaim {
+                thread_id: "t1".to_string(),
+                claimed_by: "bob@example.com".to_string(),
+                label_id: "L2".to_string(),
+                mine: false,
+            }]
+        );
+        assert_eq!(
+            conflict_message(&claims),
+            "Already being handled by bob@example.com; claim again with take_over to take it over"
+        );
+        assert!(thread_claims(&thread, &labels, "bob@example.com")[0].mine);
+    }
+}
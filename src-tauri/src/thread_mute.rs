@@ -0,0 +1,130 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::GmailMessage;
+use crate::local_store::{app_data_path, load_json, save_json};
+use serde::{Deserialize, Serialize};
+
+const MUTE_FILE: &str = "muted_threads.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MutedThread {
+    pub thread_id: String,
+    pub subject: String,
+    /// Unix timestamp (seconds)
+    pub muted_at: u64,
+}
+
+/// Threads kept out of the inbox. The Gmail API has no mute, so new mail in
+/// these threads is archived as sync finds it, the way Gmail web's mute
+/// behaves.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MuteList {
+    threads: Vec<MutedThread>,
+    account_id: Option<String>,
+}
+
+impl MuteList {
+    pub fn load() -> Self {
+        load_json(&app_data_path(MUTE_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(MUTE_FILE), self)
+    }
+
+    pub fn threads(&self) -> &[MutedThread] {
+        &self.threads
+    }
+
+    pub fn is_muted(&self, thread_id: &str) -> bool {
+        self.threads.iter().any(|t| t.thread_id == thread_id)
+    }
+
+    /// Mute a thread; muting it again keeps the original time
+    pub fn mute(&mut self, thread_id: &str, subject: &str, now: u64) -> &MutedThread {
+        let index = match self.threads.iter().position(|t| t.thread_id == thread_id) {
+            Some(index) => index,
+            None => {
+                self.threads.push(MutedThread {
+                    thread_id: thread_id.to_string(),
+                    subject: subject.to_string(),
+                    muted_at: now,
+                });
+                self.threads.len() - 1
+            }
+        };
+        &self.threads[index]
+    }
+
+    pub fn unmute(&mut self, thread_id: &str) -> bool {
+        let before = self.threads.len();
+        self.threads.retain(|t| t.thread_id != thread_id);
+        self.threads.len() != before
+    }
+
+    /// New messages that landed in the inbox of a muted thread and need
+    /// archiving
+    pub fn arrivals_to_archive(&self, messages: &[GmailMessage]) -> Vec<String> {
+        messages
+            .iter()
+            .filter(|m| self.is_muted(&m.thread_id))
+            .filter(|m| {
+                m.label_ids
+                    .as_ref()
+                    .is_some_and(|labels| labels.iter().any(|l| l == "INBOX"))
+            })
+            .map(|m| m.id.clone())
+            .collect()
+    }
+}
+
+impl AccountScoped for MuteList {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, thread_id: &str, labels: &[&str]) -> GmailMessage {
+        GmailMessage {
+            id: id.to_string(),
+            thread_id: thread_id.to_string(),
+            label_ids: Some(labels.iter().map(|l| l.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_only_inbox_arrivals_in_muted_threads_are_archived() {
+        let mut list = MuteList::default();
+        list.mute("t1", "Reply-all storm", 10);
+        assert_eq!(list.mute("t1", "Re: Reply-all storm", 20).muted_at, 10);
+        assert_eq!(list.threads().len(), 1);
+
+        let arrivals = [
+            message("m1", "t1", &["INBOX", "UNREAD"]),
+            message("m2", "t1", &["SENT"]),
+            message("m3", "t2", &["INBOX"]),
+        ];
+        assert_eq!(list.arrivals_to_archive(&arrivals), vec!["m1".to_string()]);
+
+        assert!(list.unmute("t1"));
+        assert!(!list.unmute("t1"));
+        assert!(list.arrivals_to_archive(&arrivals).is_empty());
+    }
+}
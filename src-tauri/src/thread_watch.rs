@@ -0,0 +1,165 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::GmailMessage;
+use crate::local_store::{app_data_path, load_json, save_json};
+use serde::{Deserialize, Serialize};
+
+const WATCH_FILE: &str = "watched_threads.json";
+
+/// Reply ids remembered per thread so a message straddling two polls only
+/// notifies once
+const NOTIFIED_LIMIT: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchedThread {
+    pub thread_id: String,
+    pub subject: String,
+    /// Unix timestamp (seconds)
+    pub watched_at: u64,
+    #[serde(default)]
+    notified: Vec<String>,
+}
+
+/// Payload of the "watched-thread-reply" event
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchedReply {
+    pub thread_id: String,
+    pub message_id: String,
+    pub from: String,
+    pub subject: String,
+    pub snippet: String,
+}
+
+impl WatchedReply {
+    pub fn from_message(message: &GmailMessage) -> Self {
+        WatchedReply {
+            thread_id: message.thread_id.clone(),
+            message_id: message.id.clone(),
+            from: message.get_from(),
+            subject: message.get_subject(),
+            snippet: message.snippet.clone(),
+        }
+    }
+}
+
+/// Threads whose replies notify on arrival, whatever the notification and
+/// focus settings say
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchList {
+    threads: Vec<WatchedThread>,
+    account_id: Option<String>,
+}
+
+impl WatchList {
+    pub fn load() -> Self {
+        load_json(&app_data_path(WATCH_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(WATCH_FILE), self)
+    }
+
+    pub fn threads(&self) -> &[WatchedThread] {
+        &self.threads
+    }
+
+    /// Start watching, or refresh the subject of a thread already watched
+    pub fn watch(&mut self, thread_id: &str, subject: &str, now: u64) -> &WatchedThread {
+        let index = match self.threads.iter().position(|t| t.thread_id == thread_id) {
+            Some(index) => {
+                self.threads[index].subject = subject.to_string();
+                index
+            }
+            None => {
+                self.threads.push(WatchedThread {
+                    thread_id: thread_id.to_string(),
+                    subject: subject.to_string(),
+                    watched_at: now,
+                    notified: Vec::new(),
+                });
+                self.threads.len() - 1
+            }
+        };
+        &self.threads[index]
+    }
+
+    pub fn unwatch(&mut self, thread_id: &str) -> bool {
+        let before = self.threads.len();
+        self.threads.retain(|t| t.thread_id != thread_id);
+        self.threads.len() != before
+    }
+
+    /// Note a reply, returning false when the thread isn't watched or the
+    /// reply was already reported
+    pub fn record_reply(&mut self, thread_id: &str, message_id: &str) -> bool {
+        let Some(thread) = self.threads.iter_mut().find(|t| t.thread_id == thread_id) else {
+            return false;
+        };
+        if thread.notified.iter().any(|id| id == message_id) {
+            return false;
+        }
+        thread.notified.push(message_id.to_string());
+        if thread.notified.len() > NOTIFIED_LIMIT {
+            thread.notified.remove(0);
+        }
+        true
+    }
+}
+
+/// Search for mail from others since `since` (unix seconds), in any folder,
+/// so replies a filter moved out of the inbox still count
+pub fn replies_query(since: &str) -> String {
+    format!("after:{} -from:me", since)
+}
+
+impl AccountScoped for WatchList {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_is_idempotent_and_unwatch_reports_change() {
+        let mut list = WatchList::default();
+        list.watch("t1", "Contract", 10);
+        let again = list.watch("t1", "Re: Contract", 20).clone();
+        assert_eq!(list.threads().len(), 1);
+        assert_eq!(again.subject, "Re: Contract");
+        assert_eq!(again.watched_at, 10);
+
+        assert!(list.unwatch("t1"));
+        assert!(!list.unwatch("t1"));
+        assert!(list.threads().is_empty());
+    }
+
+    #[test]
+    fn test_each_reply_is_reported_once() {
+        let mut list = WatchList::default();
+        list.watch("t1", "Contract", 0);
+
+        assert!(list.record_reply("t1", "m1"));
+        assert!(!list.record_reply("t1", "m1"));
+        assert!(!list.record_reply("t2", "m2"));
+
+        for i in 0..NOTIFIED_LIMIT {
+            list.record_reply("t1", &format!("x{}", i));
+        }
+        assert!(list.record_reply("t1", "m1"));
+    }
+}
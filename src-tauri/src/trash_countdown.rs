@@ -0,0 +1,172 @@
+use crate::account::AccountScoped;
+use crate::gmail_client::GmailMessage;
+use crate::local_store::{app_data_path, load_json, save_json};
+use serde::{Deserialize, Serialize};
+
+const TRASH_LOG_FILE: &str = "trash_log.json";
+
+/// Gmail deletes messages this long after they were moved to Trash
+pub const PURGE_AFTER_SECS: u64 = 30 * 24 * 60 * 60;
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// When a message was seen moving to Trash
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TrashedMessage {
+    message_id: String,
+    /// Unix timestamp (seconds)
+    trashed_at: u64,
+}
+
+/// How long a trashed message has before Gmail deletes it for good
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashCountdown {
+    pub message_id: String,
+    /// Unix timestamps (seconds)
+    pub trashed_at: u64,
+    pub purge_at: u64,
+    /// Whole days left, rounded down; 0 means it goes within a day
+    pub days_left: u64,
+    /// The trash date wasn't observed, so the message's own date stands in
+    /// for it and the real purge may come later than this
+    pub estimated: bool,
+}
+
+impl TrashCountdown {
+    pub fn new(message_id: &str, trashed_at: u64, estimated: bool, now: u64) -> Self {
+        let purge_at = trashed_at + PURGE_AFTER_SECS;
+        TrashCountdown {
+            message_id: message_id.to_string(),
+            trashed_at,
+            purge_at,
+            days_left: purge_at.saturating_sub(now) / DAY_SECS,
+            estimated,
+        }
+    }
+
+    /// Countdown from the message's date, the earliest it can have been trashed
+    pub fn estimate(message: &GmailMessage, now: u64) -> Self {
+        let received_at = message
+            .internal_date
+            .as_deref()
+            .and_then(|ms| ms.parse::<u64>().ok())
+            .map_or(now, |ms| ms / 1000);
+        Self::new(&message.id, received_at, true, now)
+    }
+}
+
+/// Trash dates taken from history as sync sees TRASH come and go, since
+/// Gmail doesn't report when a message was trashed
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrashLog {
+    trashed: Vec<TrashedMessage>,
+    account_id: Option<String>,
+}
+
+impl TrashLog {
+    pub fn load() -> Self {
+        load_json(&app_data_path(TRASH_LOG_FILE))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&app_data_path(TRASH_LOG_FILE), self)
+    }
+
+    /// Note a move to Trash. The first sighting wins, so a later sync of the
+    /// same change doesn't push the purge date back. Entries past their purge
+    /// date are dropped.
+    pub fn record(&mut self, message_id: &str, now: u64) {
+        self.trashed
+            .retain(|t| t.trashed_at + PURGE_AFTER_SECS > now);
+        if !self.trashed.iter().any(|t| t.message_id == message_id) {
+            self.trashed.push(TrashedMessage {
+                message_id: message_id.to_string(),
+                trashed_at: now,
+            });
+        }
+    }
+
+    /// Forget a message that was restored or deleted
+    pub fn forget(&mut self, message_id: &str) -> bool {
+        let before = self.trashed.len();
+        self.trashed.retain(|t| t.message_id != message_id);
+        self.trashed.len() != before
+    }
+
+    /// Countdown for a message whose trash date was observed
+    pub fn countdown(&self, message_id: &str, now: u64) -> Option<TrashCountdown> {
+        self.trashed
+            .iter()
+            .find(|t| t.message_id == message_id)
+            .map(|t| TrashCountdown::new(message_id, t.trashed_at, false, now))
+    }
+}
+
+impl AccountScoped for TrashLog {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_countdown_from_recorded_trash_date() {
+        let mut log = TrashLog::default();
+        log.record("m1", 1_000);
+        log.record("m1", 1_000 + DAY_SECS);
+        let countdown = log.countdown("m1", 1_000 + 10 * DAY_SECS + 1).unwrap();
+        assert_eq!(countdown.trashed_at, 1_000);
+        assert_eq!(countdown.days_left, 19);
+        assert!(!countdown.estimated);
+
+        assert_eq!(
+            log.countdown("m1", 1_000 + PURGE_AFTER_SECS)
+                .unwrap()
+                .days_left,
+            0
+        );
+        assert!(log.forget("m1"));
+        assert_eq!(log.countdown("m1", 1_000), None);
+    }
+
+    #[test]
+    fn test_expired_entries_are_dropped() {
+        let mut log = TrashLog::default();
+        log.record("old", 0);
+        log.record("new", PURGE_AFTER_SECS);
+        assert!(log.countdown("old", PURGE_AFTER_SECS).is_none());
+        assert!(log.countdown("new", PURGE_AFTER_SECS).is_some());
+    }
+
+    #[test]
+    fn test_estimate_uses_message_date() {
+        let message = GmailMessage {
+            id: "m2".to_string(),
+            thread_id: "t2".to_string(),
+            snippet: String::new(),
+            label_ids: Some(vec!["TRASH".to_string()]),
+            internal_date: Some((3 * DAY_SECS * 1000).to_string()),
+            ..Default::default()
+        };
+        let countdown = TrashCountdown::estimate(&message, 5 * DAY_SECS);
+        assert_eq!(countdown.trashed_at, 3 * DAY_SECS);
+        assert_eq!(countdown.days_left, 28);
+        assert!(countdown.estimated);
+    }
+}
@@ -0,0 +1,173 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Body RFC 8058 requires for a one-click unsubscribe POST
+const ONE_CLICK_BODY: &str = "List-Unsubscribe=One-Click";
+
+/// Subject used when a mailto link doesn't suggest one
+const DEFAULT_SUBJECT: &str = "unsubscribe";
+
+/// Unsubscribe methods a mailing list advertises in its List-Unsubscribe
+/// (RFC 2369) and List-Unsubscribe-Post (RFC 8058) headers
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct UnsubscribeOptions {
+    /// First https link; plain http is ignored
+    pub url: Option<String>,
+    pub mailto: Option<MailtoUnsubscribe>,
+    /// The list accepts a POST to `url` with no further interaction
+    pub one_click: bool,
+}
+
+/// A mailto unsubscribe link split into the message it asks for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MailtoUnsubscribe {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// How `unsubscribe_from_sender` went about it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum UnsubscribeResult {
+    OneClick {
+        url: String,
+    },
+    Mailto {
+        to: String,
+        message_id: String,
+    },
+    /// Only a web page is offered; the frontend opens it for the user
+    OpenLink {
+        url: String,
+    },
+}
+
+impl UnsubscribeOptions {
+    /// Parse the raw header values. None when the list offers nothing usable.
+    pub fn parse(list_unsubscribe: &str, list_unsubscribe_post: Option<&str>) -> Option<Self> {
+        let mut options = UnsubscribeOptions::default();
+        for uri in bracketed(list_unsubscribe) {
+            let Ok(parsed) = Url::parse(uri) else {
+                continue;
+            };
+            match parsed.scheme() {
+                "https" if options.url.is_none() => options.url = Some(parsed.to_string()),
+                "mailto" if options.mailto.is_none() => {
+                    options.mailto = MailtoUnsubscribe::from_url(&parsed)
+                }
+                _ => {}
+            }
+        }
+
+        options.one_click = options.url.is_some()
+            && list_unsubscribe_post.is_some_and(|post| {
+                post.split(';')
+                    .any(|field| field.trim().eq_ignore_ascii_case(ONE_CLICK_BODY))
+            });
+        (options.url.is_some() || options.mailto.is_some()).then_some(options)
+    }
+}
+
+impl MailtoUnsubscribe {
+    fn from_url(url: &Url) -> Option<Self> {
+        let to = urlencoding::decode(url.path()).ok()?.trim().to_string();
+        if !to.contains('@') {
+            return None;
+        }
+        let mut mailto = MailtoUnsubscribe {
+            to,
+            subject: DEFAULT_SUBJECT.to_string(),
+            body: String::new(),
+        };
+        for (key, value) in url.query_pairs() {
+            if key.eq_ignore_ascii_case("subject") && !value.trim().is_empty() {
+                mailto.subject = value.into_owned();
+            } else if key.eq_ignore_ascii_case("body") {
+                mailto.body = value.into_owned();
+            }
+        }
+        Some(mailto)
+    }
+}
+
+/// URIs inside angle brackets, in header order
+fn bracketed(header: &str) -> impl Iterator<Item = &str> {
+    header.split('<').skip(1).filter_map(|part| {
+        let uri = part.split('>').next()?.trim();
+        (!uri.is_empty() && part.contains('>')).then_some(uri)
+    })
+}
+
+/// Send the RFC 8058 one-click POST. Deliberately unauthenticated: the list
+/// host must not receive the user's Google credentials.
+pub async fn one_click(url: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let response = Client::new()
+        .post(url)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(ONE_CLICK_BODY)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(format!("Unsubscribe request failed: {}", response.status()).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_one_click_and_mailto() {
+        let options = UnsubscribeOptions::parse(
+            "<mailto:leave@lists.example.com?subject=Remove%20me>, \
+             <https://example.com/unsub?u=42&l=news>",
+            Some("List-Unsubscribe=One-Click"),
+        )
+        .unwrap();
+        assert_eq!(
+            options,
+            UnsubscribeOptions {
+                url: Some("https://example.com/unsub?u=42&l=news".to_string()),
+                mailto: Some(MailtoUnsubscribe {
+                    to: "leave@lists.example.com".to_string(),
+                    subject: "Remove me".to_string(),
+                    body: String::new(),
+                }),
+                one_click: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_one_click_needs_post_header_and_https() {
+        let no_post = UnsubscribeOptions::parse("<https://example.com/unsub>", None).unwrap();
+        assert!(!no_post.one_click);
+        assert_eq!(no_post.mailto, None);
+
+        let http_only = UnsubscribeOptions::parse(
+            "<http://example.com/unsub>, <mailto:unsub@example.com>",
+            Some("List-Unsubscribe=One-Click"),
+        )
+        .unwrap();
+        assert!(!http_only.one_click);
+        assert_eq!(http_only.url, None);
+        assert_eq!(http_only.mailto.unwrap().subject, DEFAULT_SUBJECT);
+    }
+
+    #[test]
+    fn test_unusable_headers() {
+        assert_eq!(UnsubscribeOptions::parse("", None), None);
+        assert_eq!(
+            UnsubscribeOptions::parse("https://example.com/no-brackets", None),
+            None
+        );
+        assert_eq!(
+            UnsubscribeOptions::parse("<mailto:?subject=x>, <ftp://example.com>", None),
+            None
+        );
+    }
+}
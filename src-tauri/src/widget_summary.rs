@@ -0,0 +1,156 @@
+use crate::account::AccountScoped;
+use crate::email::Email;
+use crate::local_store::{app_data_path, load_json, save_json};
+use crate::storage_quota::StorageStatus;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// File menubar widgets and status-bar tools poll; its location is stable across releases
+pub const SUMMARY_FILE: &str = "unread_summary.json";
+
+/// Unread messages listed in the summary
+const RECENT_UNREAD_LIMIT: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WidgetEmail {
+    pub id: String,
+    pub sender: String,
+    pub subject: String,
+}
+
+/// Unread/summary snapshot kept current from data the app already fetches,
+/// so reading it never costs a Gmail API call
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WidgetSummary {
+    pub unread_count: Option<u32>,
+    pub total_count: Option<u32>,
+    /// New messages found by the most recent poll
+    pub new_since_last_check: usize,
+    pub recent_unread: Vec<WidgetEmail>,
+    /// Last Google storage lookup
+    pub storage: Option<StorageStatus>,
+    /// Unix timestamp (seconds) of the last update
+    pub updated_at: Option<u64>,
+    pub account_id: Option<String>,
+}
+
+impl WidgetSummary {
+    pub fn path() -> PathBuf {
+        app_data_path(SUMMARY_FILE)
+    }
+
+    pub fn load() -> Self {
+        load_json(&Self::path())
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_json(&Self::path(), self)
+    }
+
+    pub fn record_counts(&mut self, total: u32, unread: u32) {
+        self.total_count = Some(total);
+        self.unread_count = Some(unread);
+    }
+
+    /// Refresh the recent-unread list from a freshly loaded inbox page
+    pub fn record_inbox(&mut self, emails: &[Email]) {
+        self.recent_unread = emails
+            .iter()
+            .filter(|e| !e.is_read)
+            .take(RECENT_UNREAD_LIMIT)
+            .map(|e| WidgetEmail {
+                id: e.id.clone(),
+                sender: e.sender.clone(),
+                subject: e.subject.clone(),
+            })
+            .collect();
+    }
+
+    pub fn record_new_mail(&mut self, new_count: usize) {
+        self.new_since_last_check = new_count;
+    }
+
+    /// Drop a message the user just read; the count only moves when we know it was unread
+    pub fn record_read(&mut self, email_id: &str) {
+        let before = self.recent_unread.len();
+        self.recent_unread.retain(|e| e.id != email_id);
+        if self.recent_unread.len() != before {
+            if let Some(unread) = self.unread_count.as_mut() {
+                *unread = unread.saturating_sub(1);
+            }
+        }
+    }
+
+    pub fn touch(&mut self) {
+        self.updated_at = Some(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        );
+    }
+}
+
+impl AccountScoped for WidgetSummary {
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn set_account_id(&mut self, account_id: &str) {
+        self.account_id = Some(account_id.to_string());
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(id: &str, is_read: bool) -> Email {
+        Email {
+            id: id.to_string(),
+            thread_id: id.to_string(),
+            subject: format!("Subject {}", id),
+            sender: "sender@example.com".to_string(),
+            snippet: String::new(),
+            is_read,
+            is_no_reply: false,
+            is_important: false,
+            category: None,
+        }
+    }
+
+    #[test]
+    fn test_record_inbox_keeps_only_recent_unread() {
+        let mut summary = WidgetSummary::default();
+        let emails: Vec<Email> = (0..10).map(|i| email(&i.to_string(), i % 3 == 0)).collect();
+
+        summary.record_inbox(&emails);
+
+        assert_eq!(summary.recent_unread.len(), RECENT_UNREAD_LIMIT);
+        assert_eq!(summary.recent_unread[0].id, "1");
+        assert!(summary.recent_unread.iter().all(|e| e.id != "3"));
+    }
+
+    #[test]
+    fn test_record_read_adjusts_count_only_for_known_unread() {
+        let mut summary = WidgetSummary::default();
+        summary.record_counts(100, 2);
+        summary.record_inbox(&[email("a", false), email("b", true)]);
+
+        summary.record_read("b");
+        assert_eq!(summary.unread_count, Some(2));
+
+        summary.record_read("a");
+        assert_eq!(summary.unread_count, Some(1));
+        assert!(summary.recent_unread.is_empty());
+    }
+}
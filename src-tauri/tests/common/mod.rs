@@ -6,6 +6,7 @@ pub fn create_test_tokens() -> AuthTokens {
         access_token: "test_access_token".to_string(),
         refresh_token: Some("test_refresh_token".to_string()),
         expires_in: Some(3600), // 1 hour in seconds
+        obtained_at: None,
     }
 }
 
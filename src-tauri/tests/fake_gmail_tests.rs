@@ -0,0 +1,707 @@
+//! End-to-end GmailClient tests against the in-memory server.
+//! Run with `cargo test --features fake-gmail`.
+#![cfg(feature = "fake-gmail")]
+
+use aisle3::bulk::{self, BulkAction};
+use aisle3::conversation;
+use aisle3::email::Category;
+use aisle3::gmail_client::{
+    is_not_found_error, is_transient_error, FilterAction, FilterCriteria, ImportMode, MailboxDelta,
+    MessageBody, MessageLabels, MessagePart, OutgoingEmail, LARGE_MESSAGE_BYTES,
+};
+use aisle3::mail_import;
+use aisle3::mailbox::{self, MailboxItems};
+use aisle3::message_cache::{self, MessageCache, MessageError, MessageNotFound};
+use aisle3::pending_actions::{Mutation, IMMEDIATE_RETRIES};
+use aisle3::settings::ViewMode;
+use aisle3::test_support::{fixture_message, FakeGmail};
+use base64::{engine::general_purpose::URL_SAFE, Engine as _};
+use std::sync::Mutex;
+
+mod common;
+use common::create_test_tokens;
+
+async fn mailbox_with_inbox(count: usize) -> FakeGmail {
+    let fake = FakeGmail::start("me@example.com").await;
+    for i in 0..count {
+        fake.insert_message(fixture_message(
+            &format!("msg{}", i),
+            &format!("thread{}", i % 2),
+            &["INBOX", "UNREAD"],
+            &[
+                ("From", "Alice <alice@example.com>"),
+                ("Subject", &format!("Report {}", i)),
+            ],
+            "Quarterly numbers attached",
+        ));
+    }
+    fake
+}
+
+#[tokio::test]
+async fn test_list_pages_and_batch_fetch() {
+    let fake = mailbox_with_inbox(5).await;
+    let client = fake.client(&create_test_tokens());
+
+    let first = client
+        .list_messages(Some(3), None, Some("in:inbox is:unread"))
+        .await
+        .unwrap();
+    let ids: Vec<String> = first.messages.unwrap().into_iter().map(|m| m.id).collect();
+    assert_eq!(ids, vec!["msg4", "msg3", "msg2"]);
+    assert_eq!(first.result_size_estimate, Some(5));
+
+    let second = client
+        .list_messages(Some(3), first.next_page_token.as_deref(), Some("in:inbox"))
+        .await
+        .unwrap();
+    assert_eq!(second.messages.unwrap().len(), 2);
+    assert!(second.next_page_token.is_none());
+
+    let messages = client.get_messages_batch(&ids).await.unwrap();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[0].get_subject(), "Report 4");
+
+    // Two list pages and one batch request
+    assert_eq!(fake.requests().len(), 3);
+    assert_eq!(fake.requests()[2], "POST /batch/gmail/v1");
+}
+
+#[tokio::test]
+async fn test_conversation_search_groups_by_thread() {
+    let fake = mailbox_with_inbox(4).await;
+    let client = fake.client(&create_test_tokens());
+
+    let conversations = mailbox::search_conversations(&client, Some("in:inbox"), 20)
+        .await
+        .unwrap();
+    assert_eq!(conversations.len(), 2);
+    assert!(conversations.iter().all(|c| c.message_count == 2));
+    assert!(conversations.iter().all(|c| c.has_unread));
+}
+
+#[tokio::test]
+async fn test_label_changes_are_visible_to_queries() {
+    let fake = mailbox_with_inbox(2).await;
+    let client = fake.client(&create_test_tokens());
+
+    client.archive_message("msg1").await.unwrap();
+    client.trash_message("msg0").await.unwrap();
+
+    let labels = fake.message("msg1").unwrap().label_ids.unwrap();
+    assert_eq!(labels, vec!["UNREAD"]);
+
+    let inbox = client
+        .list_messages(None, None, Some("in:inbox"))
+        .await
+        .unwrap();
+    assert!(inbox.messages.unwrap_or_default().is_empty());
+
+    let trash = client
+        .list_messages(None, None, Some("in:trash"))
+        .await
+        .unwrap();
+    assert_eq!(trash.messages.unwrap()[0].id, "msg0");
+}
+
+#[tokio::test]
+async fn test_threads_are_listed_by_label_id() {
+    let fake = mailbox_with_inbox(4).await;
+    let client = fake.client(&create_test_tokens());
+    let label = client
+        .create_label("Aisle3/Handling/alice@example.com")
+        .await
+        .unwrap();
+    client
+        .modify_message("msg1", &[&label.id], &[])
+        .await
+        .unwrap();
+
+    let claimed = client.list_threads_with_label(&label.id, 10).await.unwrap();
+    let ids: Vec<String> = claimed.threads.unwrap().into_iter().map(|t| t.id).collect();
+    assert_eq!(ids, vec!["thread1"]);
+}
+
+#[tokio::test]
+async fn test_thread_read_state_changes_every_message() {
+    let fake = mailbox_with_inbox(3).await;
+    let client = fake.client(&create_test_tokens());
+
+    let thread = client.mark_thread_as_read("thread0").await.unwrap();
+    let ids: Vec<&str> = thread.messages.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(ids, vec!["msg0", "msg2"]);
+    assert!(thread
+        .messages
+        .iter()
+        .all(|m| !m.label_ids.contains(&"UNREAD".to_string())));
+    assert!(fake
+        .message("msg1")
+        .unwrap()
+        .label_ids
+        .unwrap()
+        .contains(&"UNREAD".to_string()));
+
+    client.mark_thread_as_unread("thread0").await.unwrap();
+    let unread = client
+        .list_messages(None, None, Some("is:unread"))
+        .await
+        .unwrap();
+    assert_eq!(unread.messages.unwrap().len(), 3);
+
+    let error = client.mark_thread_as_read("missing").await.unwrap_err();
+    assert!(is_not_found_error(error.as_ref()));
+}
+
+#[tokio::test]
+async fn test_bulk_run_backs_off_when_rate_limited() {
+    let fake = mailbox_with_inbox(3).await;
+    // Skip the real backoff sleep
+    tokio::time::pause();
+    let client = fake.client(&create_test_tokens());
+    let ids: Vec<String> = (0..3).map(|i| format!("msg{}", i)).collect();
+
+    fake.fail_next(1, 429);
+    let mut progress = Vec::new();
+    let (summary, changed) =
+        bulk::run_bulk_action(&client, "op", &ids, &[BulkAction::Trash], |p| {
+            progress.push(p.processed)
+        })
+        .await;
+    assert_eq!(summary.succeeded, 3);
+    assert!(summary.errors.is_empty());
+    assert_eq!(changed, ids);
+    assert_eq!(progress, vec![3]);
+
+    let trash = client
+        .list_messages(None, None, Some("in:trash"))
+        .await
+        .unwrap();
+    assert_eq!(trash.messages.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_empty_trash_preview_counts_all_of_trash() {
+    let fake = FakeGmail::start("me@example.com").await;
+    let count = bulk::DRY_RUN_SAMPLE_SIZE + 5;
+    for i in 0..count {
+        let id = format!("trashed{}", i);
+        fake.insert_message(fixture_message(&id, &id, &["TRASH"], &[], ""));
+    }
+    fake.insert_message(fixture_message("kept", "kept", &["INBOX"], &[], ""));
+    let client = fake.client(&create_test_tokens());
+
+    let preview = bulk::preview_empty_trash(&client, "op").await.unwrap();
+    assert!(preview.dry_run);
+    assert_eq!(preview.total, count);
+    assert_eq!(preview.sample_ids.len(), bulk::DRY_RUN_SAMPLE_SIZE);
+
+    let summary = bulk::empty_trash(&client, "op", |_| {}).await;
+    assert_eq!(summary.succeeded, preview.total);
+    assert!(fake.message("kept").is_some());
+}
+
+#[tokio::test]
+async fn test_conversation_attachments_are_deduplicated_by_content() {
+    let fake = FakeGmail::start("me@example.com").await;
+    let with_file = |id: &str, filename: &str, size: u64| {
+        let mut message = fixture_message(id, "thread1", &["INBOX"], &[], "");
+        message.payload.as_mut().unwrap().parts = Some(vec![MessagePart {
+            mime_type: Some("application/pdf".to_string()),
+            filename: Some(filename.to_string()),
+            body: Some(MessageBody {
+                attachment_id: Some(format!("att_{}", id)),
+                size: Some(size),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }]);
+        message
+    };
+    let messages = vec![
+        with_file("m1", "report.pdf", 6),
+        with_file("m2", "report.pdf", 6),
+        with_file("m3", "report.pdf", 6),
+        with_file("m4", "notes.pdf", 9),
+        // Gone by the time it's downloaded
+        with_file("m5", "report.pdf", 6),
+    ];
+    fake.insert_attachment("m1", "att_m1", b"draft1");
+    fake.insert_attachment("m2", "att_m2", b"draft1");
+    // Same name and size, different content
+    fake.insert_attachment("m3", "att_m3", b"draft2");
+    let client = fake.client(&create_test_tokens());
+    let dir = tempfile::tempdir().unwrap();
+    let cache = Mutex::new(MessageCache::open(dir.path().to_path_buf()));
+
+    let attachments = conversation::rollup_attachments(&client, &cache, &messages).await;
+    assert_eq!(attachments.len(), 4);
+    assert_eq!(attachments[0].occurrences, 2);
+    // Download action points at the most recent copy
+    assert_eq!(attachments[0].message_id, "m2");
+    assert_eq!(attachments[0].attachment_id, "att_m2");
+    assert_eq!(attachments[1].message_id, "m3");
+    assert_ne!(attachments[0].sha256, attachments[1].sha256);
+    // A size no other file shares is listed without downloading it
+    assert_eq!(attachments[2].filename, "notes.pdf");
+    assert_eq!(attachments[2].sha256, None);
+    // A failed download is listed on its own rather than failing the thread
+    assert_eq!(attachments[3].message_id, "m5");
+    assert_eq!(attachments[3].sha256, None);
+
+    // Opening the thread again reads the downloaded files from the cache
+    let downloads = |fake: &FakeGmail| {
+        fake.requests()
+            .iter()
+            .filter(|r| r.contains("/attachments/"))
+            .count()
+    };
+    let before = downloads(&fake);
+    let again = conversation::rollup_attachments(&client, &cache, &messages).await;
+    assert_eq!(again.len(), 4);
+    assert_eq!(downloads(&fake), before + 1);
+}
+
+#[tokio::test]
+async fn test_send_and_drafts_round_trip() {
+    let fake = FakeGmail::start("me@example.com").await;
+    let client = fake.client(&create_test_tokens());
+    let email = OutgoingEmail {
+        to: "bob@example.com".to_string(),
+        subject: "Lunch".to_string(),
+        body: "Noon?".to_string(),
+        ..Default::default()
+    };
+
+    let sent_id = client.send_message(&email, None).await.unwrap();
+    let sent = fake.message(&sent_id).unwrap();
+    assert_eq!(sent.get_subject(), "Lunch");
+    assert!(fake.sent_messages()[0].contains("To: bob@example.com"));
+
+    let draft = client.create_draft(&email, None).await.unwrap();
+    let listed = client.list_drafts(None, None).await.unwrap();
+    assert_eq!(listed.drafts.unwrap()[0].id, draft.id);
+
+    client.send_draft(&draft.id).await.unwrap();
+    assert_eq!(fake.draft_count(), 0);
+    assert_eq!(fake.sent_messages().len(), 2);
+}
+
+#[tokio::test]
+async fn test_filters_create_list_delete() {
+    let fake = FakeGmail::start("me@example.com").await;
+    let client = fake.client(&create_test_tokens());
+    let criteria = FilterCriteria {
+        from: Some("news@example.com".to_string()),
+        ..Default::default()
+    };
+    let action = FilterAction {
+        add_label_ids: vec!["Label_1".to_string()],
+        remove_label_ids: vec!["INBOX".to_string()],
+        ..Default::default()
+    };
+
+    let created = client.create_filter(&criteria, &action).await.unwrap();
+    let filter_id = created.id.clone().unwrap();
+    assert_eq!(client.list_filters().await.unwrap(), vec![created]);
+
+    client.delete_filter(&filter_id).await.unwrap();
+    assert!(client.list_filters().await.unwrap().is_empty());
+    assert!(client.delete_filter(&filter_id).await.is_err());
+}
+
+#[tokio::test]
+async fn test_injected_failures_then_recovery() {
+    let fake = mailbox_with_inbox(1).await;
+    let client = fake.client(&create_test_tokens());
+
+    fake.fail_next(1, 503);
+    let error = client.list_messages(None, None, None).await.unwrap_err();
+    assert!(error.to_string().contains("503"));
+
+    let retried = client.list_messages(None, None, None).await.unwrap();
+    assert_eq!(retried.messages.unwrap().len(), 1);
+
+    // A failed batch surfaces the error instead of returning a partial page
+    fake.fail_next(1, 500);
+    let ids = vec!["msg0".to_string()];
+    assert!(client.get_messages_batch(&ids).await.is_err());
+    assert_eq!(client.get_messages_batch(&ids).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_message_deleted_elsewhere_is_not_found() {
+    let fake = mailbox_with_inbox(1).await;
+    let client = fake.client(&create_test_tokens());
+    let dir = tempfile::tempdir().unwrap();
+    let cache = Mutex::new(MessageCache::open(dir.path().to_path_buf()));
+
+    let error = message_cache::load_message(&client, &cache, "gone")
+        .await
+        .unwrap_err();
+    assert!(error.is::<MessageNotFound>());
+    match MessageError::from(error) {
+        MessageError::NotFound { message_id } => assert_eq!(message_id, "gone"),
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+
+    // Other failures stay generic
+    fake.fail_next(1, 500);
+    let error = message_cache::load_message(&client, &cache, "msg0")
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        MessageError::from(error),
+        MessageError::Failed { .. }
+    ));
+    assert!(message_cache::load_message(&client, &cache, "msg0")
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_large_message_opens_as_plain_text_without_caching() {
+    let fake = FakeGmail::start("me@example.com").await;
+    let part = |mime_type: &str, filename: &str, body: MessageBody| MessagePart {
+        mime_type: Some(mime_type.to_string()),
+        filename: Some(filename.to_string()),
+        body: Some(body),
+        ..Default::default()
+    };
+    let mut message = fixture_message("big", "big", &["INBOX"], &[("Subject", "Scans")], "");
+    message.size_estimate = Some(LARGE_MESSAGE_BYTES + 1);
+    let payload = message.payload.as_mut().unwrap();
+    payload.body = None;
+    payload.parts = Some(vec![
+        part(
+            "text/plain",
+            "",
+            MessageBody {
+                attachment_id: Some("text".to_string()),
+                ..Default::default()
+            },
+        ),
+        part(
+            "text/html",
+            "",
+            MessageBody {
+                data: Some(URL_SAFE.encode("<p>Scans attached</p>")),
+                ..Default::default()
+            },
+        ),
+        part(
+            "application/pdf",
+            "scan.pdf",
+            MessageBody {
+                attachment_id: Some("pdf".to_string()),
+                size: Some(LARGE_MESSAGE_BYTES),
+                ..Default::default()
+            },
+        ),
+    ]);
+    fake.insert_message(message);
+    fake.insert_attachment("big", "text", b"Scans attached");
+    let client = fake.client(&create_test_tokens());
+    let dir = tempfile::tempdir().unwrap();
+    let cache = Mutex::new(MessageCache::open(dir.path().to_path_buf()));
+
+    let message = message_cache::load_message_for_reading(&client, &cache, "big")
+        .await
+        .unwrap();
+    assert!(message.is_large());
+    assert_eq!(message.get_body_text(), "Scans attached");
+    assert_eq!(message.get_body_html(), None);
+    assert_eq!(message.get_attachments().len(), 1);
+    assert!(!cache.lock().unwrap().contains("big"));
+
+    let message = message_cache::load_message(&client, &cache, "big")
+        .await
+        .unwrap();
+    assert!(message.get_body_html().is_some());
+    assert!(cache.lock().unwrap().contains("big"));
+}
+
+#[tokio::test]
+async fn test_mutations_retry_only_transient_failures() {
+    let fake = mailbox_with_inbox(1).await;
+    let client = fake.client(&create_test_tokens());
+
+    fake.fail_next(1, 503);
+    let labels = Mutation::Archive
+        .apply_with_retries(&client, "msg0")
+        .await
+        .unwrap();
+    assert!(!labels.label_ids.contains(&"INBOX".to_string()));
+
+    fake.fail_next(IMMEDIATE_RETRIES as usize + 1, 503);
+    let error = Mutation::MarkRead
+        .apply_with_retries(&client, "msg0")
+        .await
+        .unwrap_err();
+    assert!(is_transient_error(error.as_ref()));
+
+    let before = fake.requests().len();
+    fake.fail_next(1, 400);
+    let error = Mutation::MarkRead
+        .apply_with_retries(&client, "msg0")
+        .await
+        .unwrap_err();
+    assert!(!is_transient_error(error.as_ref()));
+    assert_eq!(fake.requests().len(), before + 1);
+}
+
+#[tokio::test]
+async fn test_history_delta_since_last_check() {
+    let fake = mailbox_with_inbox(2).await;
+    let client = fake.client(&create_test_tokens());
+
+    // The first check only establishes where to start from
+    let baseline = client.check_for_new_emails(None, &[]).await.unwrap();
+    assert!(baseline.resynced);
+    assert!(baseline.added.is_empty());
+
+    fake.insert_message(fixture_message(
+        "msg9",
+        "thread9",
+        &["INBOX", "UNREAD"],
+        &[("Subject", "Fresh")],
+        "Just arrived",
+    ));
+    client.mark_as_read("msg0").await.unwrap();
+    client.delete_message_permanently("msg1").await.unwrap();
+
+    let delta = client
+        .check_for_new_emails(Some(&baseline.history_id), &[])
+        .await
+        .unwrap();
+    assert_eq!(delta.added, vec!["msg9"]);
+    assert_eq!(delta.deleted, vec!["msg1"]);
+    assert_eq!(delta.label_changes.len(), 1);
+    assert_eq!(delta.label_changes[0].removed_label_ids, vec!["UNREAD"]);
+
+    let quiet = client
+        .check_for_new_emails(Some(&delta.history_id), &[])
+        .await
+        .unwrap();
+    assert_eq!(
+        quiet,
+        MailboxDelta {
+            history_id: delta.history_id.clone(),
+            ..Default::default()
+        }
+    );
+
+    // An expired start id falls back to a fresh baseline
+    fake.fail_next(1, 404);
+    let resynced = client
+        .check_for_new_emails(Some(&delta.history_id), &[])
+        .await
+        .unwrap();
+    assert!(resynced.resynced);
+    assert_eq!(resynced.history_id, delta.history_id);
+}
+
+#[tokio::test]
+async fn test_history_gap_is_bridged_by_relisting_the_inbox() {
+    let fake = mailbox_with_inbox(3).await;
+    let client = fake.client(&create_test_tokens());
+    let known: Vec<MessageLabels> = ["msg2", "msg1", "msg0"]
+        .iter()
+        .map(|id| MessageLabels {
+            id: id.to_string(),
+            thread_id: String::new(),
+            label_ids: vec!["INBOX".to_string(), "UNREAD".to_string()],
+        })
+        .collect();
+    let baseline = client.check_for_new_emails(None, &known).await.unwrap();
+
+    fake.insert_message(fixture_message(
+        "msg9",
+        "thread9",
+        &["INBOX", "UNREAD"],
+        &[("Subject", "Fresh")],
+        "Just arrived",
+    ));
+    client.mark_as_read("msg1").await.unwrap();
+    client.archive_message("msg0").await.unwrap();
+
+    // The stored history id has expired
+    fake.fail_next(1, 404);
+    let delta = client
+        .check_for_new_emails(Some(&baseline.history_id), &known)
+        .await
+        .unwrap();
+    assert!(delta.recovered);
+    assert!(!delta.resynced);
+    assert_eq!(delta.added, vec!["msg9"]);
+    assert_eq!(
+        Some(delta.history_id.clone()),
+        client.get_profile().await.unwrap().history_id
+    );
+
+    let changed: Vec<(&str, &[String], &[String])> = delta
+        .label_changes
+        .iter()
+        .map(|c| {
+            (
+                c.message_id.as_str(),
+                c.added_label_ids.as_slice(),
+                c.removed_label_ids.as_slice(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        changed,
+        vec![
+            ("msg1", &[][..], &["UNREAD".to_string()][..]),
+            ("msg0", &[][..], &["INBOX".to_string()][..]),
+        ]
+    );
+
+    // Sync carries on from the recovered position
+    let quiet = client
+        .check_for_new_emails(Some(&delta.history_id), &known)
+        .await
+        .unwrap();
+    assert!(quiet.added.is_empty() && !quiet.recovered);
+}
+
+#[tokio::test]
+async fn test_important_marks_feed_the_priority_listing() {
+    let fake = mailbox_with_inbox(3).await;
+    let client = fake.client(&create_test_tokens());
+
+    client.mark_as_important("msg0").await.unwrap();
+    client.mark_as_important("msg2").await.unwrap();
+    client.mark_not_important("msg2").await.unwrap();
+
+    let page = mailbox::search(
+        &client,
+        ViewMode::Messages,
+        Some(mailbox::IMPORTANT_QUERY),
+        None,
+        20,
+    )
+    .await
+    .unwrap();
+    let emails = page.items.into_emails();
+    let ids: Vec<&str> = emails.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, vec!["msg0"]);
+    assert!(emails[0].is_important);
+}
+
+#[tokio::test]
+async fn test_phishing_report_moves_to_spam_and_drops_importance() {
+    let fake = mailbox_with_inbox(2).await;
+    let client = fake.client(&create_test_tokens());
+
+    client.mark_as_important("msg1").await.unwrap();
+    let labels = client.report_phishing("msg1").await.unwrap();
+    assert!(labels.label_ids.contains(&"SPAM".to_string()));
+    assert!(!labels.label_ids.contains(&"INBOX".to_string()));
+    assert!(!labels.label_ids.contains(&"IMPORTANT".to_string()));
+}
+
+#[tokio::test]
+async fn test_category_tabs_split_the_inbox() {
+    let fake = FakeGmail::start("me@example.com").await;
+    for (id, category) in [
+        ("friend", "CATEGORY_PERSONAL"),
+        ("sale", "CATEGORY_PROMOTIONS"),
+        ("invite", "CATEGORY_SOCIAL"),
+    ] {
+        fake.insert_message(fixture_message(
+            id,
+            id,
+            &["INBOX", category],
+            &[("Subject", id)],
+            "",
+        ));
+    }
+    let client = fake.client(&create_test_tokens());
+
+    let search = |query, view_mode| mailbox::search(&client, view_mode, Some(query), None, 20);
+
+    let promotions = search(Category::Promotions.inbox_query(), ViewMode::Messages)
+        .await
+        .unwrap()
+        .items
+        .into_emails();
+    let ids: Vec<&str> = promotions.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, vec!["sale"]);
+    assert_eq!(promotions[0].category, Some(Category::Promotions));
+
+    let primary = search(Category::Primary.inbox_query(), ViewMode::Messages)
+        .await
+        .unwrap()
+        .items
+        .into_emails();
+    assert_eq!(primary.len(), 1);
+    assert_eq!(primary[0].category, Some(Category::Primary));
+
+    // The same tab as threads when conversations are on
+    match search(Category::Primary.inbox_query(), ViewMode::Conversations)
+        .await
+        .unwrap()
+        .items
+    {
+        MailboxItems::Conversations(threads) => {
+            assert_eq!(threads.len(), 1);
+            assert_eq!(threads[0].emails[0].id, "friend");
+        }
+        other => panic!("expected conversations, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_import_reads_mbox_and_eml_files() {
+    let fake = FakeGmail::start("me@example.com").await;
+    let client = fake.client(&create_test_tokens());
+    let dir = tempfile::tempdir().unwrap();
+    let mbox = dir.path().join("archive.mbox");
+    std::fs::write(
+        &mbox,
+        "From a@example.com Mon Jan  1 00:00:00 2024\nSubject: First\n\nOne\n\n\
+         From b@example.com Tue Jan  2 00:00:00 2024\nSubject: Second\n\nTwo\n",
+    )
+    .unwrap();
+    let eml = dir.path().join("single.eml");
+    std::fs::write(&eml, "Subject: Third\r\n\r\nThree\r\n").unwrap();
+    let paths = vec![
+        mbox.to_string_lossy().to_string(),
+        eml.to_string_lossy().to_string(),
+        dir.path()
+            .join("missing.mbox")
+            .to_string_lossy()
+            .to_string(),
+    ];
+
+    let mut progress = Vec::new();
+    let summary = mail_import::import_files(
+        &client,
+        "import1",
+        &paths,
+        &["INBOX".to_string()],
+        ImportMode::Import,
+        |p| progress.push((p.imported, p.bytes_read, p.total_bytes)),
+    )
+    .await;
+
+    assert_eq!(summary.imported, 3);
+    assert_eq!(summary.failed, 1);
+    let subjects: Vec<String> = summary
+        .message_ids
+        .iter()
+        .map(|id| fake.message(id).unwrap().get_subject())
+        .collect();
+    assert_eq!(subjects, vec!["First", "Second", "Third"]);
+    assert!(fake
+        .message(&summary.message_ids[0])
+        .unwrap()
+        .label_ids
+        .unwrap()
+        .contains(&"INBOX".to_string()));
+
+    let (imported, bytes_read, total_bytes) = *progress.last().unwrap();
+    assert_eq!(imported, 3);
+    assert_eq!(bytes_read, total_bytes);
+}
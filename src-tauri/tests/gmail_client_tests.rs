@@ -34,10 +34,13 @@ fn create_test_message() -> GmailMessage {
                 }]),
                 body: Some(MessageBody {
                     data: Some(URL_SAFE.encode("Hello World Test Message")),
+                    ..Default::default()
                 }),
+                ..Default::default()
             }]),
-            body: None,
+            ..Default::default()
         }),
+        ..Default::default()
     }
 }
 
@@ -55,8 +58,7 @@ fn test_gmail_message_get_subject_missing() {
     let mut message = create_test_message();
     message.payload = Some(MessagePayload {
         headers: Some(vec![]),
-        parts: None,
-        body: None,
+        ..Default::default()
     });
     assert_eq!(message.get_subject(), "(No Subject)");
 }
@@ -158,6 +160,59 @@ fn test_gmail_response_deserialization() {
     assert_eq!(response.result_size_estimate.unwrap(), 50);
 }
 
+#[test]
+fn test_filter_serialization_omits_unset_fields() {
+    let filter = GmailFilter {
+        id: None,
+        criteria: FilterCriteria {
+            from: Some("news@example.com".to_string()),
+            ..Default::default()
+        },
+        action: FilterAction {
+            remove_label_ids: vec!["INBOX".to_string()],
+            ..Default::default()
+        },
+    };
+    assert_eq!(
+        serde_json::to_value(&filter).unwrap(),
+        json!({
+            "criteria": { "from": "news@example.com" },
+            "action": { "removeLabelIds": ["INBOX"] }
+        })
+    );
+    assert!(FilterCriteria::default().is_empty());
+    assert!(FilterAction::default().is_empty());
+    assert!(!filter.criteria.is_empty());
+}
+
+#[test]
+fn test_forwarding_settings_deserialization() {
+    let forwarding: AutoForwarding = serde_json::from_value(json!({
+        "enabled": true,
+        "emailAddress": "backup@example.com",
+        "disposition": "markRead"
+    }))
+    .unwrap();
+    assert_eq!(
+        forwarding.disposition,
+        Some(ForwardingDisposition::MarkRead)
+    );
+
+    let disabled: AutoForwarding = serde_json::from_value(json!({ "enabled": false })).unwrap();
+    assert_eq!(disabled.email_address, None);
+    assert_eq!(
+        serde_json::to_value(&disabled).unwrap(),
+        json!({ "enabled": false })
+    );
+
+    let address: ForwardingAddress = serde_json::from_value(json!({
+        "forwardingEmail": "backup@example.com",
+        "verificationStatus": "pending"
+    }))
+    .unwrap();
+    assert!(!address.is_verified());
+}
+
 #[tokio::test]
 async fn test_gmail_client_creation() {
     let tokens = create_test_tokens();
@@ -301,7 +356,9 @@ fn test_html_body_extraction() {
             }]),
             body: Some(MessageBody {
                 data: Some(URL_SAFE.encode("<p>HTML Content</p>")),
+                ..Default::default()
             }),
+            ..Default::default()
         });
 
     let html = message.get_body_html();
@@ -309,6 +366,191 @@ fn test_html_body_extraction() {
     assert_eq!(html.unwrap(), "<p>HTML Content</p>");
 }
 
+#[test]
+fn test_html_only_messages_read_as_text() {
+    let mut message = create_test_message();
+    let part = &mut message.payload.as_mut().unwrap().parts.as_mut().unwrap()[0];
+    part.headers.as_mut().unwrap()[0].value = "text/html; charset=UTF-8".to_string();
+    part.body.as_mut().unwrap().data =
+        Some(URL_SAFE.encode("<p>Hello <b>team</b>,</p><p>Fish &amp; chips</p>"));
+    assert_eq!(message.get_body_text(), "Hello team,\n\nFish & chips");
+
+    // A single-part HTML message carries its body on the payload
+    let payload = message.payload.as_mut().unwrap();
+    payload.parts = None;
+    payload.headers.as_mut().unwrap().push(MessageHeader {
+        name: "Content-Type".to_string(),
+        value: "text/html; charset=UTF-8".to_string(),
+    });
+    payload.body = Some(MessageBody {
+        data: Some(URL_SAFE.encode("<div>Only<br>HTML</div>")),
+        ..Default::default()
+    });
+    assert_eq!(message.get_body_text(), "Only\nHTML");
+
+    // Gmail's mimeType is read before the Content-Type header
+    let payload = message.payload.as_mut().unwrap();
+    payload.mime_type = Some("text/html".to_string());
+    payload
+        .headers
+        .as_mut()
+        .unwrap()
+        .retain(|h| h.name != "Content-Type");
+    assert_eq!(message.get_body_text(), "Only\nHTML");
+}
+
+#[test]
+fn test_quoted_printable_bodies_are_decoded() {
+    assert_eq!(
+        decode_quoted_printable(b"It=E2=80=99s a soft=\r\n break, =3D and =ZZ"),
+        "It\u{2019}s a soft break, = and =ZZ".as_bytes()
+    );
+
+    let mut message = create_test_message();
+    let part = &mut message.payload.as_mut().unwrap().parts.as_mut().unwrap()[0];
+    part.headers.as_mut().unwrap().push(MessageHeader {
+        name: "Content-Transfer-Encoding".to_string(),
+        value: "Quoted-Printable".to_string(),
+    });
+    part.body.as_mut().unwrap().data = Some(URL_SAFE.encode("Caf=C3=A9 at noon=\n today"));
+    assert_eq!(message.get_body_text(), "Caf\u{e9} at noon today");
+}
+
+#[test]
+fn test_encoded_word_headers_are_decoded() {
+    assert_eq!(
+        decode_encoded_words("=?UTF-8?B?5pel5pys6Kqe?= =?UTF-8?Q?caf=C3=A9_menu?="),
+        "日本語café menu"
+    );
+    // A character split across two words, and a Latin-1 word
+    assert_eq!(
+        decode_encoded_words("Caf=?utf-8?B?ww==?= =?utf-8?B?qQ==?= and =?ISO-8859-1*fr?Q?Ren=E9?="),
+        "Café and René"
+    );
+    assert_eq!(
+        decode_encoded_words("=?UTF-8?X?bad?= =?oops"),
+        "=?UTF-8?X?bad?= =?oops"
+    );
+
+    let mut message = create_test_message();
+    let headers = message.payload.as_mut().unwrap().headers.as_mut().unwrap();
+    headers[0].value = "Re: =?UTF-8?Q?Gr=C3=BC=C3=9Fe?=".to_string();
+    headers[1].value = "=?UTF-8?B?SsO8cmdlbg==?= <j@example.com>".to_string();
+    assert_eq!(message.get_subject(), "Re: Grüße");
+    assert_eq!(message.get_from(), "Jürgen <j@example.com>");
+    assert_eq!(message.get_from_address(), "j@example.com");
+}
+
+#[test]
+fn test_outgoing_subjects_are_encoded_when_needed() {
+    let mut email = OutgoingEmail {
+        to: "bob@example.com".to_string(),
+        subject: "Lunch".to_string(),
+        body: "Hi".to_string(),
+        ..Default::default()
+    };
+    assert!(email.to_rfc2822().contains("Subject: Lunch\r\n"));
+
+    // Non-ASCII text, and text that looks like an encoded word
+    email.subject = "Grüße =?UTF-8?B?SGk=?= ".repeat(4);
+    let raw = email.to_rfc2822();
+    let subject = raw
+        .split("\r\n")
+        .skip_while(|line| !line.starts_with("Subject: "))
+        .take_while(|line| line.starts_with("Subject: ") || line.starts_with(' '))
+        .collect::<Vec<_>>();
+    assert!(subject.len() > 1);
+    assert!(subject
+        .iter()
+        .all(|line| line.len() <= 78 && line.is_ascii()));
+    assert_eq!(
+        decode_encoded_words(subject.join(" ").trim_start_matches("Subject: ")),
+        email.subject.trim()
+    );
+}
+
+#[test]
+fn test_declared_charsets_are_decoded() {
+    assert_eq!(decode_charset(b"Caf\xe9", Some("ISO-8859-1")), "Caf\u{e9}");
+    assert_eq!(
+        decode_charset(b"\x93\xfa\x96\x7b", Some("Shift_JIS")),
+        "日本"
+    );
+    // Undeclared bytes that aren't UTF-8 are read as Windows-1252
+    assert_eq!(decode_charset(b"\x93Hi\x94", None), "\u{201c}Hi\u{201d}");
+    assert_eq!(
+        decode_charset("Grüße".as_bytes(), Some("x-unknown")),
+        "Grüße"
+    );
+
+    let mut message = create_test_message();
+    let part = &mut message.payload.as_mut().unwrap().parts.as_mut().unwrap()[0];
+    part.headers.as_mut().unwrap()[0].value = "text/plain; charset=\"windows-1252\"".to_string();
+    part.body.as_mut().unwrap().data = Some(URL_SAFE.encode(b"Na\xefve \x80 prices"));
+    assert_eq!(message.get_body_text(), "Na\u{ef}ve \u{20ac} prices");
+}
+
+#[test]
+fn test_nested_multipart_bodies_and_attachments() {
+    let leaf = |mime_type: &str, data: &str| MessagePart {
+        mime_type: Some(mime_type.to_string()),
+        body: Some(MessageBody {
+            data: Some(URL_SAFE.encode(data)),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let multipart = |mime_type: &str, parts: Vec<MessagePart>| MessagePart {
+        mime_type: Some(mime_type.to_string()),
+        parts: Some(parts),
+        ..Default::default()
+    };
+
+    // mixed > [notes.txt, related > [alternative > [plain, html], logo.png]]
+    let mut message = create_test_message();
+    message.payload.as_mut().unwrap().parts = Some(vec![
+        MessagePart {
+            filename: Some("notes.txt".to_string()),
+            ..leaf("text/plain", "Attached notes")
+        },
+        multipart(
+            "multipart/related",
+            vec![
+                multipart(
+                    "multipart/alternative",
+                    vec![
+                        leaf("text/plain", "Nested plain"),
+                        leaf("text/html", "<p>Nested html</p>"),
+                    ],
+                ),
+                MessagePart {
+                    filename: Some("logo.png".to_string()),
+                    mime_type: Some("image/png".to_string()),
+                    body: Some(MessageBody {
+                        attachment_id: Some("att-logo".to_string()),
+                        size: Some(512),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            ],
+        ),
+    ]);
+
+    assert_eq!(message.get_body_text(), "Nested plain");
+    assert_eq!(
+        message.get_body_html(),
+        Some("<p>Nested html</p>".to_string())
+    );
+    let attachments = message.get_attachments();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0].filename, "logo.png");
+
+    let light = message.text_only();
+    assert_eq!(light.get_body_text(), "Nested plain");
+    assert_eq!(light.get_body_html(), None);
+}
+
 #[test]
 fn test_empty_label_ids() {
     let mut message = create_test_message();
@@ -416,3 +658,319 @@ fn test_threading_with_missing_headers() {
     // Thread ID should still be available from the message struct
     assert_eq!(message.thread_id, "thread456");
 }
+
+#[test]
+fn test_message_labels_deserialization() {
+    // Shape returned by messages.modify after archiving
+    let json = json!({
+        "id": "msg1",
+        "threadId": "thread1",
+        "labelIds": ["UNREAD", "CATEGORY_UPDATES"]
+    });
+
+    let labels: MessageLabels = serde_json::from_value(json).unwrap();
+    assert_eq!(labels.id, "msg1");
+    assert!(!labels.label_ids.contains(&"INBOX".to_string()));
+
+    // Messages with every label removed omit labelIds entirely
+    let labels: MessageLabels =
+        serde_json::from_value(json!({"id": "msg2", "threadId": "thread2"})).unwrap();
+    assert!(labels.label_ids.is_empty());
+}
+
+#[test]
+fn test_labels_response_deserialization() {
+    // labels.list omits counts; labels.get includes them
+    let json = json!({
+        "labels": [
+            {"id": "INBOX", "name": "INBOX", "type": "system"},
+            {
+                "id": "Label_1",
+                "name": "Receipts",
+                "type": "user",
+                "messagesTotal": 42,
+                "messagesUnread": 3,
+                "threadsTotal": 40,
+                "threadsUnread": 3
+            }
+        ]
+    });
+
+    let response: GmailLabelsResponse = serde_json::from_value(json).unwrap();
+    let labels = response.labels.unwrap();
+    assert_eq!(labels[0].label_type.as_deref(), Some("system"));
+    assert_eq!(labels[0].messages_unread, None);
+    assert_eq!(labels[1].name, "Receipts");
+    assert_eq!(labels[1].messages_unread, Some(3));
+}
+
+#[test]
+fn test_search_query_to_query_string() {
+    let query = SearchQuery {
+        from: Some("alice@example.com".to_string()),
+        subject: Some("Q3 report".to_string()),
+        has_attachment: true,
+        after: chrono::NaiveDate::from_ymd_opt(2025, 1, 1),
+        before: chrono::NaiveDate::from_ymd_opt(2025, 2, 1),
+        label: Some("Work Projects".to_string()),
+        is_unread: Some(true),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        query.to_query_string(),
+        "from:alice@example.com subject:\"Q3 report\" has:attachment after:2025/01/01 before:2025/02/01 label:Work-Projects is:unread"
+    );
+    assert_eq!(SearchQuery::default().to_query_string(), "");
+
+    let text_only = SearchQuery {
+        text: Some("invoice".to_string()),
+        to: Some("  ".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(text_only.to_query_string(), "invoice");
+}
+
+#[test]
+fn test_search_query_deserialization() {
+    let query: SearchQuery = serde_json::from_value(
+        json!({ "from": "bob@example.com", "after": "2025-03-04", "is_unread": false }),
+    )
+    .unwrap();
+
+    assert_eq!(
+        query.to_query_string(),
+        "from:bob@example.com after:2025/03/04 is:read"
+    );
+}
+
+#[test]
+fn test_attachment_stream_decoder_across_chunks() {
+    let payload: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+    let body = format!(
+        r#"{{"size": 1000, "data": "{}"}}"#,
+        URL_SAFE.encode(&payload)
+    );
+
+    // Split at awkward points, including inside the "data" key
+    for chunk_size in [1, 7, 16, 4096] {
+        let mut decoder = AttachmentStreamDecoder::default();
+        let mut output = Vec::new();
+        for chunk in body.as_bytes().chunks(chunk_size) {
+            decoder.feed(chunk, &mut output).unwrap();
+        }
+        assert_eq!(decoder.finish(&mut output).unwrap(), 1000);
+        assert_eq!(output, payload);
+    }
+}
+
+#[test]
+fn test_attachment_stream_decoder_requires_data() {
+    let mut decoder = AttachmentStreamDecoder::default();
+    let mut output = Vec::new();
+    decoder
+        .feed(br#"{"error": "not found"}"#, &mut output)
+        .unwrap();
+    assert!(decoder.finish(&mut output).is_err());
+}
+
+#[test]
+fn test_outgoing_email_with_attachment_is_multipart_mixed() {
+    let email = OutgoingEmail {
+        to: "bob@example.com".to_string(),
+        subject: "Invoice".to_string(),
+        body: "See attached.".to_string(),
+        attachments: vec![OutgoingAttachment {
+            filename: "invoice.pdf".to_string(),
+            mime_type: "application/pdf".to_string(),
+            data: vec![7u8; 100],
+        }],
+        ..Default::default()
+    };
+
+    let raw = email.to_rfc2822();
+    assert!(raw.contains("Content-Type: multipart/mixed; boundary="));
+    assert!(raw.contains("Content-Type: text/plain; charset=utf-8\r\n\r\nSee attached.\r\n"));
+    assert!(raw.contains("Content-Disposition: attachment; filename=\"invoice.pdf\""));
+    assert!(raw.lines().all(|line| line.len() <= 998));
+    assert!(raw
+        .lines()
+        .filter(|line| line.starts_with("BwcH"))
+        .all(|line| line.len() <= 76));
+    assert_eq!(email.attachment_bytes(), 100);
+}
+
+#[test]
+fn test_attachment_size_limit() {
+    assert!(check_attachment_size(MAX_ATTACHMENT_BYTES).is_ok());
+    let error = check_attachment_size(MAX_ATTACHMENT_BYTES + 1).unwrap_err();
+    assert!(error.contains("25 MB"));
+}
+
+#[test]
+fn test_drafts_response_parsing() {
+    let drafts: GmailDraftsResponse = serde_json::from_value(json!({
+        "drafts": [
+            { "id": "r-123", "message": { "id": "msg1", "threadId": "thread1" } }
+        ],
+        "nextPageToken": "page2",
+        "resultSizeEstimate": 7
+    }))
+    .unwrap();
+
+    let draft = &drafts.drafts.unwrap()[0];
+    assert_eq!(draft.id, "r-123");
+    assert_eq!(draft.message.as_ref().unwrap().thread_id, "thread1");
+    assert_eq!(drafts.next_page_token.as_deref(), Some("page2"));
+
+    let empty: GmailDraftsResponse = serde_json::from_value(json!({})).unwrap();
+    assert!(empty.drafts.is_none());
+}
+
+#[test]
+fn test_mailbox_delta_folds_history_records() {
+    let records: Vec<HistoryRecord> = serde_json::from_value(json!([
+        { "id": "101", "messagesAdded": [
+            { "message": { "id": "m1", "threadId": "t1", "labelIds": ["INBOX", "UNREAD"] } },
+            { "message": { "id": "m2", "threadId": "t2", "labelIds": ["SENT"] } },
+            { "message": { "id": "m3", "threadId": "t3", "labelIds": ["INBOX"] } },
+            { "message": { "id": "s1", "threadId": "t4", "labelIds": ["SPAM", "UNREAD"] } }
+        ] },
+        { "id": "102", "labelsRemoved": [
+            { "message": { "id": "old", "threadId": "t9" }, "labelIds": ["UNREAD"] }
+        ] },
+        { "id": "103", "labelsAdded": [
+            { "message": { "id": "old", "threadId": "t9" }, "labelIds": ["STARRED", "UNREAD"] }
+        ] },
+        { "id": "104", "messagesDeleted": [
+            { "message": { "id": "m3", "threadId": "t3" } },
+            { "message": { "id": "gone", "threadId": "t8" } }
+        ] }
+    ]))
+    .unwrap();
+
+    let delta = MailboxDelta::from_records(&records, "104".to_string());
+
+    // Sent mail isn't new mail, and m3 came and went inside the window
+    assert_eq!(delta.added, vec!["m1"]);
+    assert_eq!(delta.spam_added, vec!["s1"]);
+    assert_eq!(delta.deleted, vec!["gone"]);
+    // UNREAD was removed then re-added, so only STARRED is a real change
+    assert_eq!(
+        delta.label_changes,
+        vec![LabelChange {
+            message_id: "old".to_string(),
+            thread_id: "t9".to_string(),
+            added_label_ids: vec!["STARRED".to_string()],
+            removed_label_ids: vec![],
+        }]
+    );
+    assert_eq!(delta.history_id, "104");
+    assert!(!delta.resynced);
+}
+
+#[test]
+fn test_mailbox_delta_reconciles_relisted_inbox() {
+    let labels = |id: &str, label_ids: &[&str]| MessageLabels {
+        id: id.to_string(),
+        thread_id: format!("t_{}", id),
+        label_ids: label_ids.iter().map(|l| l.to_string()).collect(),
+    };
+    let known = vec![
+        labels("k1", &["INBOX", "UNREAD"]),
+        labels("k2", &["INBOX"]),
+        labels("k3", &["INBOX"]),
+    ];
+    // Newest first: two arrivals, k1 read, k2 archived, an older message
+    // moved back in between, and one past the known page
+    let recent = vec![
+        labels("n2", &["INBOX", "UNREAD"]),
+        labels("n1", &["INBOX"]),
+        labels("k1", &["INBOX"]),
+        labels("back", &["INBOX"]),
+        labels("k3", &["INBOX"]),
+        labels("older", &["INBOX"]),
+    ];
+
+    let delta = MailboxDelta::reconcile(&known, &recent, 100, "900".to_string());
+    assert!(delta.recovered);
+    assert_eq!(delta.added, vec!["n1", "n2"]);
+    assert!(delta.deleted.is_empty());
+    let changes: Vec<(&str, Vec<String>, Vec<String>)> = delta
+        .label_changes
+        .iter()
+        .map(|c| {
+            (
+                c.message_id.as_str(),
+                c.added_label_ids.clone(),
+                c.removed_label_ids.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            ("k1", vec![], vec!["UNREAD".to_string()]),
+            ("back", vec!["INBOX".to_string()], vec![]),
+            ("k2", vec![], vec!["INBOX".to_string()]),
+        ]
+    );
+
+    // In a full listing, known messages older than every listed one may just
+    // have been pushed out, so nothing is said about them
+    let crowded: Vec<MessageLabels> = recent.iter().filter(|m| m.id != "k3").cloned().collect();
+    let delta = MailboxDelta::reconcile(&known, &crowded, crowded.len(), "900".to_string());
+    assert!(delta
+        .label_changes
+        .iter()
+        .all(|c| c.message_id != "k2" && c.message_id != "k3"));
+}
+
+#[test]
+fn test_large_message_text_only_view() {
+    let mut message = create_test_message();
+    assert!(!message.is_large());
+    message.size_estimate = Some(LARGE_MESSAGE_BYTES + 1);
+    assert!(message.is_large());
+
+    let parts = message.payload.as_mut().unwrap().parts.as_mut().unwrap();
+    parts.push(MessagePart {
+        headers: Some(vec![MessageHeader {
+            name: "Content-Type".to_string(),
+            value: "text/html; charset=UTF-8".to_string(),
+        }]),
+        body: Some(MessageBody {
+            data: Some(URL_SAFE.encode("<p>Hello World</p>")),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    parts.push(MessagePart {
+        filename: Some("banner.png".to_string()),
+        mime_type: Some("image/png".to_string()),
+        body: Some(MessageBody {
+            attachment_id: Some("att1".to_string()),
+            size: Some(20_000_000),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+
+    let light = message.text_only();
+    assert_eq!(light.get_body_text(), "Hello World Test Message");
+    assert_eq!(light.get_body_html(), None);
+    assert_eq!(light.get_attachments(), message.get_attachments());
+    assert_eq!(
+        message.get_body_html(),
+        Some("<p>Hello World</p>".to_string())
+    );
+}
+
+#[test]
+fn test_eml_uses_crlf_line_endings() {
+    assert_eq!(
+        to_eml(b"Subject: Hi\nFrom: a@example.com\r\n\rBody"),
+        b"Subject: Hi\r\nFrom: a@example.com\r\n\r\nBody\r\n".to_vec()
+    );
+    assert_eq!(to_eml(b"Done\r\n"), b"Done\r\n".to_vec());
+}
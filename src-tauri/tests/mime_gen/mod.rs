@@ -0,0 +1,525 @@
+//! Randomized MIME structures for the roundtrip property tests.
+//!
+//! Generates Gmail `format=full` payload trees (nested multiparts, odd
+//! charsets, missing or broken boundaries, bad base64), outgoing messages with
+//! hostile text, and parses raw RFC 2822 output back into the JSON shape
+//! Gmail would return for it. Cases are seeded; set `MIME_PROPTEST_SEED` to
+//! replay a single failing seed.
+
+use aisle3::gmail_client::{OutgoingAttachment, OutgoingEmail};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use serde_json::{json, Value};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+
+/// Cases per property
+const CASES: u64 = 300;
+
+/// Small deterministic PRNG (splitmix64), so failures replay from the seed alone
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed ^ 0x5DEE_CE66_D1CE_4E5B)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    pub fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+
+    pub fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        let len = self.below(max_len + 1);
+        (0..len).map(|_| self.next_u64() as u8).collect()
+    }
+}
+
+/// Run `property` once per seed, reporting the seed of the first failure
+pub fn check(name: &str, mut property: impl FnMut(&mut Rng)) {
+    let seeds: Vec<u64> = match std::env::var("MIME_PROPTEST_SEED") {
+        Ok(seed) => vec![seed.parse().expect("MIME_PROPTEST_SEED must be a number")],
+        Err(_) => (0..CASES).collect(),
+    };
+
+    for seed in seeds {
+        let mut rng = Rng::new(seed);
+        if let Err(panic) = catch_unwind(AssertUnwindSafe(|| property(&mut rng))) {
+            eprintln!(
+                "{} failed for seed {}; rerun with MIME_PROPTEST_SEED={}",
+                name, seed, seed
+            );
+            resume_unwind(panic);
+        }
+    }
+}
+
+/// Fragments that have broken MIME handling before: delimiter lookalikes,
+/// encoded words, QP escapes, bare CR/LF, non-ASCII
+const TEXT_PIECES: [&str; 18] = [
+    "hello",
+    "invoice",
+    "Re:",
+    "naïve café",
+    "日本語のテキスト",
+    "🎉",
+    "=?UTF-8?B?SGVsbG8=?=",
+    "=E2=80=99",
+    "--boundary_email_mixed_67890",
+    "--boundary_email_content_12345--",
+    "--",
+    "Content-Type: text/html",
+    "\r\n",
+    "\r\n\r\n",
+    "\n",
+    "\t",
+    "a;b=\"c\"",
+    "   ",
+];
+
+/// Text without '<', so the outgoing builder treats it as plain
+pub fn plain_text(rng: &mut Rng) -> String {
+    let count = rng.below(12);
+    let mut text = String::new();
+    for _ in 0..count {
+        text.push_str(rng.pick(&TEXT_PIECES));
+        if rng.chance(60) {
+            text.push(' ');
+        }
+    }
+    text
+}
+
+pub fn html_text(rng: &mut Rng) -> String {
+    let tag = rng.pick(&["p", "div", "b", "li"]);
+    format!(
+        "<{}>{}</{}><br/>{}",
+        tag,
+        plain_text(rng),
+        tag,
+        plain_text(rng)
+    )
+}
+
+const ATTACHMENT_TYPES: [&str; 5] = [
+    "application/pdf",
+    "image/png",
+    "text/csv",
+    "text/plain",
+    "application/octet-stream",
+];
+
+/// An outgoing message with hostile headers, plain or HTML body, and 0-3 attachments
+pub fn outgoing_email(rng: &mut Rng) -> OutgoingEmail {
+    let attachments = (0..rng.below(4))
+        .map(|i| OutgoingAttachment {
+            filename: format!("file{} {}.bin", i, plain_text(rng)),
+            mime_type: rng.pick(&ATTACHMENT_TYPES).to_string(),
+            data: rng.bytes(400),
+        })
+        .collect();
+
+    OutgoingEmail {
+        to: "bob@example.com".to_string(),
+        cc: rng
+            .chance(30)
+            .then(|| format!("carol@example.com{}", plain_text(rng))),
+        subject: plain_text(rng),
+        body: if rng.chance(40) {
+            html_text(rng)
+        } else {
+            plain_text(rng)
+        },
+        in_reply_to: rng.chance(20).then(|| "<abc@mail.example.com>".to_string()),
+        attachments,
+        ..Default::default()
+    }
+}
+
+fn header(name: &str, value: &str) -> Value {
+    json!({ "name": name, "value": value })
+}
+
+const CHARSETS: [&str; 6] = [
+    "utf-8",
+    "UTF-8",
+    "us-ascii",
+    "iso-8859-1",
+    "windows-1252",
+    "shift_jis",
+];
+
+/// Text in legacy charsets with its encoding worked out by hand, so the
+/// expected body never comes from a decoder
+const LATIN1_PIECES: [(&str, &[u8]); 4] = [
+    ("café", b"caf\xE9"),
+    ("Grüße", b"Gr\xFC\xDFe"),
+    ("niño", b"ni\xF1o"),
+    ("façade", b"fa\xE7ade"),
+];
+const WINDOWS_1252_PIECES: [(&str, &[u8]); 3] = [
+    ("€5", b"\x805"),
+    ("it’s", b"it\x92s"),
+    ("“quoted”", b"\x93quoted\x94"),
+];
+const SHIFT_JIS_PIECES: [(&str, &[u8]); 2] = [
+    ("日本", b"\x93\xFA\x96\x7B"),
+    ("テスト", b"\x83\x65\x83\x58\x83\x67"),
+];
+
+/// Text for a body in `charset` and its bytes in that charset
+fn charset_text(rng: &mut Rng, charset: &str) -> (String, Vec<u8>) {
+    let fixtures: Vec<(&str, &[u8])> = match charset.to_ascii_lowercase().as_str() {
+        "utf-8" => {
+            let text = plain_text(rng);
+            let bytes = text.clone().into_bytes();
+            return (text, bytes);
+        }
+        "iso-8859-1" => LATIN1_PIECES.to_vec(),
+        "windows-1252" => [&LATIN1_PIECES[..], &WINDOWS_1252_PIECES[..]].concat(),
+        "shift_jis" => SHIFT_JIS_PIECES.to_vec(),
+        _ => Vec::new(),
+    };
+
+    let ascii: Vec<&str> = TEXT_PIECES.into_iter().filter(|p| p.is_ascii()).collect();
+    let (mut text, mut bytes) = (String::new(), Vec::new());
+    for _ in 0..rng.below(12) {
+        let (piece, encoded) = if !fixtures.is_empty() && rng.chance(40) {
+            rng.pick(&fixtures)
+        } else {
+            let piece = rng.pick(&ascii);
+            (piece, piece.as_bytes())
+        };
+        text.push_str(piece);
+        bytes.extend_from_slice(encoded);
+        if rng.chance(60) {
+            text.push(' ');
+            bytes.push(b' ');
+        }
+    }
+    (text, bytes)
+}
+
+/// Quoted-printable with every byte outside printable ASCII escaped, so the
+/// encoded body has no line breaks to soften
+fn quoted_printable(bytes: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(bytes.len() * 3);
+    for &byte in bytes {
+        if byte == b' ' || (byte.is_ascii_graphic() && byte != b'=') {
+            encoded.push(byte);
+        } else {
+            encoded.extend(format!("={:02X}", byte).into_bytes());
+        }
+    }
+    encoded
+}
+
+/// Body data the way Gmail sends it, occasionally unpadded or corrupt.
+/// Returns whether the data is well-formed.
+fn body_data(rng: &mut Rng, bytes: &[u8]) -> (Value, bool) {
+    match rng.below(10) {
+        // Without padding only lengths divisible by three still decode
+        0 => (
+            json!(URL_SAFE_NO_PAD.encode(bytes)),
+            bytes.len().is_multiple_of(3),
+        ),
+        1 => (json!("!!not base64!!"), false),
+        _ => (json!(URL_SAFE.encode(bytes)), true),
+    }
+}
+
+/// A text part. The text the client should read from it, when its data is
+/// well-formed, rides along in the part's "expectedText" field, which the
+/// client ignores.
+fn text_leaf(rng: &mut Rng) -> Value {
+    let subtype = rng.pick(&["plain", "html", "Plain", "calendar"]);
+    let charset = rng.pick(&CHARSETS);
+    let (text, content) = charset_text(rng, charset);
+
+    // Undeclared bodies are read as UTF-8 or else Windows-1252, which
+    // Latin text survives but Shift_JIS doesn't
+    let mut headers = Vec::new();
+    if rng.chance(90) || charset == "shift_jis" {
+        headers.push(header(
+            "Content-Type",
+            &format!("text/{}; charset={}", subtype, charset),
+        ));
+    }
+    let mut transfer_encoding = None;
+    if rng.chance(30) {
+        let encoding = rng.pick(&["7bit", "8bit", "quoted-printable", "base64"]);
+        headers.push(header("Content-Transfer-Encoding", encoding));
+        transfer_encoding = Some(encoding);
+    }
+    // Gmail undoes base64 transfer encoding but leaves quoted-printable
+    let data = match transfer_encoding {
+        Some("quoted-printable") => quoted_printable(&content),
+        _ => content,
+    };
+
+    let mut body = json!({ "size": data.len() });
+    let mut part = json!({
+        "mimeType": format!("text/{}", subtype.to_lowercase()),
+        "filename": "",
+        "headers": headers,
+    });
+    if rng.chance(90) {
+        let (encoded, well_formed) = body_data(rng, &data);
+        body["data"] = encoded;
+        if well_formed {
+            part["expectedText"] = json!(text);
+        }
+    }
+    part["body"] = body;
+    part
+}
+
+fn attachment_leaf(rng: &mut Rng, index: usize) -> Value {
+    let mime_type = rng.pick(&ATTACHMENT_TYPES);
+    let filename = format!("attachment{}{}", index, plain_text(rng));
+    json!({
+        "mimeType": mime_type,
+        "filename": filename,
+        "headers": [
+            header("Content-Type", &format!("{}; name=\"{}\"", mime_type, filename)),
+            header("Content-Disposition", &format!("attachment; filename=\"{}\"", filename)),
+        ],
+        "body": { "attachmentId": format!("att-{}", index), "size": rng.below(5000) },
+    })
+}
+
+fn multipart(rng: &mut Rng, depth: usize, next_index: &mut usize) -> Value {
+    let subtype = rng.pick(&["mixed", "alternative", "related", "signed"]);
+    let content_type = match rng.below(10) {
+        0 => format!("multipart/{}", subtype),
+        1 => format!("multipart/{}; boundary=", subtype),
+        _ => format!("multipart/{}; boundary=\"b{}\"", subtype, depth),
+    };
+
+    let mut node = json!({
+        "mimeType": format!("multipart/{}", subtype),
+        "filename": "",
+        "headers": [header("Content-Type", &content_type)],
+        "body": { "size": 0 },
+    });
+    if rng.chance(95) {
+        let children: Vec<Value> = (0..rng.below(5))
+            .map(|_| tree_node(rng, depth - 1, next_index))
+            .collect();
+        node["parts"] = json!(children);
+    }
+    node
+}
+
+fn tree_node(rng: &mut Rng, depth: usize, next_index: &mut usize) -> Value {
+    if depth > 0 && rng.chance(30) {
+        return multipart(rng, depth, next_index);
+    }
+    if rng.chance(25) {
+        *next_index += 1;
+        return attachment_leaf(rng, *next_index);
+    }
+    text_leaf(rng)
+}
+
+/// A Gmail message JSON whose payload nests up to `max_depth` multipart levels
+pub fn message_tree(rng: &mut Rng, max_depth: usize) -> Value {
+    let mut next_index = 0;
+    let root = if rng.chance(15) {
+        text_leaf(rng)
+    } else {
+        multipart(rng, max_depth.max(1), &mut next_index)
+    };
+
+    let mut payload = root;
+    let mut headers = vec![
+        header("From", "Alice <alice@example.com>"),
+        header("Subject", &plain_text(rng)),
+    ];
+    if let Some(part_headers) = payload["headers"].as_array() {
+        headers.extend(part_headers.iter().cloned());
+    }
+    payload["headers"] = json!(headers);
+
+    json!({
+        "id": "msg1",
+        "threadId": "thread1",
+        "snippet": "snippet text",
+        "labelIds": ["INBOX"],
+        "payload": payload,
+    })
+}
+
+/// Every part in the tree, depth first, including the payload itself
+pub fn all_parts(message: &Value) -> Vec<&Value> {
+    fn walk<'a>(node: &'a Value, out: &mut Vec<&'a Value>) {
+        out.push(node);
+        for child in node["parts"].as_array().into_iter().flatten() {
+            walk(child, out);
+        }
+    }
+    let mut out = Vec::new();
+    walk(&message["payload"], &mut out);
+    out
+}
+
+/// The text the client should read from a generated part, None when its
+/// body is missing, malformed or unreadable
+pub fn expected_body(part: &Value) -> Option<String> {
+    part["expectedText"].as_str().map(str::to_string)
+}
+
+/// Raw RFC 2822 text parsed into a Gmail message JSON, plus the attachment
+/// bytes by attachment id
+pub struct ParsedRaw {
+    pub message: Value,
+    pub attachments: Vec<(String, Vec<u8>)>,
+}
+
+fn split_entity(text: &str) -> (Vec<(String, String)>, &str) {
+    let (head, body) = text.split_once("\r\n\r\n").unwrap_or((text, ""));
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in head.split("\r\n") {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    (headers, body)
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// A `name="value"` or `name=value` parameter from a structured header
+fn header_param(value: &str, name: &str) -> Option<String> {
+    let start = value.find(&format!("{}=", name))? + name.len() + 1;
+    let rest = &value[start..];
+    Some(match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or("").to_string(),
+        None => rest.split(';').next().unwrap_or("").trim().to_string(),
+    })
+}
+
+/// Body lines between `--boundary` delimiters; a missing close delimiter ends at EOF
+fn split_multipart<'a>(body: &'a str, boundary: &str) -> Vec<String> {
+    let delimiter = format!("--{}", boundary);
+    let close = format!("--{}--", boundary);
+    let mut parts = Vec::new();
+    let mut current: Option<Vec<&'a str>> = None;
+
+    for line in body.split("\r\n") {
+        if line == close {
+            break;
+        }
+        if line == delimiter {
+            if let Some(lines) = current.take() {
+                parts.push(lines.join("\r\n"));
+            }
+            current = Some(Vec::new());
+        } else if let Some(lines) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+    if let Some(lines) = current {
+        parts.push(lines.join("\r\n"));
+    }
+    parts
+}
+
+fn parse_entity(text: &str, attachments: &mut Vec<(String, Vec<u8>)>) -> Value {
+    let (headers, body) = split_entity(text);
+    let content_type = find_header(&headers, "Content-Type").unwrap_or("text/plain");
+    let mime_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+    let header_json: Vec<Value> = headers.iter().map(|(n, v)| header(n, v)).collect();
+
+    if mime_type.starts_with("multipart/") {
+        let parts: Vec<Value> = header_param(content_type, "boundary")
+            .map(|boundary| split_multipart(body, &boundary))
+            .unwrap_or_default()
+            .iter()
+            .map(|part| parse_entity(part, attachments))
+            .collect();
+        return json!({
+            "mimeType": mime_type,
+            "filename": "",
+            "headers": header_json,
+            "body": { "size": 0 },
+            "parts": parts,
+        });
+    }
+
+    let bytes = match find_header(&headers, "Content-Transfer-Encoding") {
+        Some(encoding) if encoding.eq_ignore_ascii_case("base64") => {
+            let compact: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+            STANDARD.decode(compact).expect("attachment base64")
+        }
+        _ => body.as_bytes().to_vec(),
+    };
+    let filename = find_header(&headers, "Content-Disposition")
+        .and_then(|d| header_param(d, "filename"))
+        .or_else(|| header_param(content_type, "name"))
+        .unwrap_or_default();
+
+    let body_json = if filename.is_empty() {
+        json!({ "data": URL_SAFE.encode(&bytes), "size": bytes.len() })
+    } else {
+        let id = format!("att-{}", attachments.len());
+        let size = bytes.len();
+        attachments.push((id.clone(), bytes));
+        json!({ "attachmentId": id, "size": size })
+    };
+
+    json!({
+        "mimeType": mime_type,
+        "filename": filename,
+        "headers": header_json,
+        "body": body_json,
+    })
+}
+
+/// Parse builder output the way Gmail does when the message is sent
+pub fn parse_raw(raw: &str) -> ParsedRaw {
+    let mut attachments = Vec::new();
+    let payload = parse_entity(raw, &mut attachments);
+    ParsedRaw {
+        message: json!({
+            "id": "sent1",
+            "threadId": "sent1",
+            "snippet": "",
+            "labelIds": ["SENT"],
+            "payload": payload,
+        }),
+        attachments,
+    }
+}
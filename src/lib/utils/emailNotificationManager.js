@@ -1,3 +1,4 @@
+import { invoke } from '@tauri-apps/api/core';
 import { createNotificationService } from './notificationService.js';
 import { createEmailPollingManager } from './pollingManager.js';
 
@@ -109,17 +110,30 @@ export class EmailNotificationManager {
    * @param {Array<Object>} emailDetails - Array of email objects with details
    */
   async processNewEmailsWithDetails(newEmailIds, emailDetails) {
-    if (!this.settings.enabled || !this.shouldNotify()) {
+    if (!this.settings.enabled) {
       return;
     }
 
     // Filter out emails we've already notified about
-    const emailsToNotify = newEmailIds.filter(id => !this.notifiedEmailIds.has(id));
+    let emailsToNotify = newEmailIds.filter(id => !this.notifiedEmailIds.has(id));
     
     if (emailsToNotify.length === 0) {
       return;
     }
 
+    // Notification rules can pick a sound, or let mail from particular
+    // senders through quiet hours and the cooldown
+    const styles = await this.getNotificationStyles(emailsToNotify);
+    if (!this.shouldNotify()) {
+      emailsToNotify = emailsToNotify.filter(id =>
+        styles.some(style => style.message_id === id && style.bypass_quiet_hours)
+      );
+      if (emailsToNotify.length === 0) {
+        return;
+      }
+    }
+    const sound = styles.find(style => emailsToNotify.includes(style.message_id) && style.sound)?.sound;
+
     // Add to notified set
     emailsToNotify.forEach(id => this.notifiedEmailIds.add(id));
 
@@ -131,7 +145,7 @@ export class EmailNotificationManager {
     console.log('📧 Using provided email details for notification:', filteredEmailDetails);
 
     // Send notification
-    await this.sendEmailNotification(emailsToNotify.length, filteredEmailDetails);
+    await this.sendEmailNotification(emailsToNotify.length, filteredEmailDetails, sound);
 
     // Update last notification time
     this.lastNotificationTime = Date.now();
@@ -140,6 +154,21 @@ export class EmailNotificationManager {
   }
 
 
+  /**
+   * Look up notification rule matches for new emails
+   * @param {Array<string>} emailIds - Email IDs to look up
+   * @returns {Promise<Array<any>>} Styles for the emails a rule matched
+   */
+  async getNotificationStyles(emailIds) {
+    try {
+      const styles = await invoke('get_notification_styles', { emailIds });
+      return Array.isArray(styles) ? styles : [];
+    } catch (error) {
+      console.warn('📧 Could not load notification rules:', error);
+      return [];
+    }
+  }
+
   /**
    * Add a listener for in-app notifications
    * @param {Function} listener - Callback function for in-app notifications
@@ -173,15 +202,20 @@ export class EmailNotificationManager {
    * Send email notification (OS and/or in-app)
    * @param {number} count - Number of new emails
    * @param {Array<Object>} emailDetails - Array of email objects
+   * @param {string} [sound] - Sound from a matching notification rule
    */
-  async sendEmailNotification(count, emailDetails = []) {
+  async sendEmailNotification(count, emailDetails = [], sound = undefined) {
     let osNotificationSent = false;
     let inAppNotificationSent = false;
 
     // Try OS notification first if enabled
     if (this.settings.osNotificationsEnabled && this.notificationService?.isAvailable()) {
       try {
-        await this.notificationService.notifyNewEmails(count, emailDetails);
+        if (sound) {
+          await this.notificationService.notifyNewEmails(count, emailDetails, { sound });
+        } else {
+          await this.notificationService.notifyNewEmails(count, emailDetails);
+        }
         osNotificationSent = true;
         console.log('📧 OS notification sent successfully');
       } catch (error) {
@@ -107,9 +107,11 @@ export class NotificationService {
    * Send a new email notification
    * @param {number} count - Number of new emails
    * @param {Array<Object>} emails - Array of new email objects (optional, for preview)
+   * @param {Object} [options] - Notification overrides
+   * @param {string} [options.sound] - Sound to play instead of the default
    * @returns {Promise<Object>} Result of notification send
    */
-  async notifyNewEmails(count, emails = []) {
+  async notifyNewEmails(count, emails = [], options = {}) {
     let title = '';
     let body = '';
 
@@ -139,6 +141,7 @@ export class NotificationService {
     return this.notify({
       title,
       body,
+      sound: options.sound,
       actions: [
         {
           id: 'view',